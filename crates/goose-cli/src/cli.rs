@@ -8,7 +8,7 @@ use crate::commands::configure::handle_configure;
 use crate::commands::info::handle_info;
 use crate::commands::mcp::run_server;
 use crate::commands::project::{handle_project_default, handle_projects_interactive};
-use crate::commands::recipe::{handle_deeplink, handle_list, handle_validate};
+use crate::commands::recipe::{handle_deeplink, handle_import, handle_list, handle_validate};
 // Import the new handlers from commands::schedule
 use crate::commands::schedule::{
     handle_schedule_add, handle_schedule_cron_help, handle_schedule_list, handle_schedule_remove,
@@ -245,6 +245,24 @@ enum RecipeCommand {
             help = "recipe name to get recipe file or full path to the recipe file to generate deeplink"
         )]
         recipe_name: String,
+
+        /// Sign the deeplink so people who trust your key can run it without a warning
+        #[arg(long, help = "Sign the deeplink with your recipe signing key")]
+        sign: bool,
+    },
+
+    /// Import a recipe into the local recipe directory
+    #[command(about = "Import a recipe from a URL, GitHub repo path or deeplink")]
+    Import {
+        /// Where to import the recipe from
+        #[arg(
+            help = "https URL of a recipe file, owner/repo/path[@ref] on GitHub, or a goose://recipe deeplink"
+        )]
+        source: String,
+
+        /// Replace an existing recipe with the same name
+        #[arg(long, help = "Replace an existing recipe with the same name")]
+        overwrite: bool,
     },
 
     /// List available recipes
@@ -1013,8 +1031,11 @@ pub async fn cli() -> Result<()> {
                 RecipeCommand::Validate { recipe_name } => {
                     handle_validate(&recipe_name)?;
                 }
-                RecipeCommand::Deeplink { recipe_name } => {
-                    handle_deeplink(&recipe_name)?;
+                RecipeCommand::Deeplink { recipe_name, sign } => {
+                    handle_deeplink(&recipe_name, sign)?;
+                }
+                RecipeCommand::Import { source, overwrite } => {
+                    handle_import(&source, overwrite).await?;
                 }
                 RecipeCommand::List { format, verbose } => {
                     handle_list(&format, verbose)?;
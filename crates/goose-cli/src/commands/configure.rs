@@ -10,8 +10,8 @@ use goose::agents::{extension::Envs, ExtensionConfig};
 use goose::config::extensions::name_to_key;
 use goose::config::permission::PermissionLevel;
 use goose::config::{
-    Config, ConfigError, ExperimentManager, ExtensionConfigManager, ExtensionEntry,
-    PermissionManager,
+    validate_config, Config, ConfigError, ConfigIssueSeverity, ExperimentManager,
+    ExtensionConfigManager, ExtensionEntry, PermissionManager,
 };
 use goose::message::Message;
 use goose::providers::{create, providers};
@@ -113,6 +113,7 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
                                 timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
                                 bundled: Some(true),
                                 description: None,
+                                log_level: None,
                             },
                         })?;
                     }
@@ -215,6 +216,7 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
         println!();
 
         cliclack::intro(style(" goose-configure ").on_cyan().black())?;
+        report_config_issues(config);
         let action = cliclack::select("What would you like to configure?")
             .item(
                 "providers",
@@ -246,6 +248,24 @@ pub async fn handle_configure() -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// Show problems in the existing config, found by the same validator the server uses
+fn report_config_issues(config: &Config) {
+    let issues = match validate_config(config) {
+        Ok(issues) => issues,
+        Err(e) => {
+            let _ = cliclack::log::warning(format!("Could not check your config: {}", e));
+            return;
+        }
+    };
+    for issue in issues {
+        let message = format!("{}: {}", style(&issue.path).bold(), issue);
+        let _ = match issue.severity {
+            ConfigIssueSeverity::Error => cliclack::log::error(message),
+            ConfigIssueSeverity::Warning => cliclack::log::warning(message),
+        };
+    }
+}
+
 /// Dialog for configuring the AI provider and model
 pub async fn configure_provider_dialog() -> Result<bool, Box<dyn Error>> {
     // Get global config instance
@@ -594,6 +614,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     timeout: Some(timeout),
                     bundled: Some(true),
                     description: None,
+                    log_level: None,
                 },
             })?;
 
@@ -698,9 +719,11 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     args,
                     envs: Envs::new(envs),
                     env_keys,
+                    cwd: None,
                     description,
                     timeout: Some(timeout),
                     bundled: None,
+                    log_level: None,
                 },
             })?;
 
@@ -803,6 +826,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     description,
                     timeout: Some(timeout),
                     bundled: None,
+                    log_level: None,
                 },
             })?;
 
@@ -930,6 +954,7 @@ pub fn configure_extensions_dialog() -> Result<(), Box<dyn Error>> {
                     description,
                     timeout: Some(timeout),
                     bundled: None,
+                    log_level: None,
                 },
             })?;
 
@@ -1503,26 +1528,18 @@ pub fn configure_max_turns_dialog() -> Result<(), Box<dyn Error>> {
 
 /// Handle OpenRouter authentication
 pub async fn handle_openrouter_auth() -> Result<(), Box<dyn Error>> {
-    use goose::config::{configure_openrouter, signup_openrouter::OpenRouterAuth};
+    use goose::config::{run_signup, signup_for};
     use goose::message::Message;
     use goose::providers::create;
 
-    // Use the OpenRouter authentication flow
-    let mut auth_flow = OpenRouterAuth::new()?;
-    match auth_flow.complete_flow().await {
-        Ok(api_key) => {
-            println!("\nAuthentication complete!");
-
-            // Get config instance
-            let config = Config::global();
-
-            // Use the existing configure_openrouter function to set everything up
-            println!("\nConfiguring OpenRouter...");
-            if let Err(e) = configure_openrouter(config, api_key) {
-                eprintln!("Failed to configure OpenRouter: {}", e);
-                return Err(e.into());
-            }
+    // Get config instance
+    let config = Config::global();
 
+    // Use the OpenRouter signup flow, which also saves the key it gets
+    let mut signup = signup_for("openrouter")?;
+    match run_signup(signup.as_mut(), config).await {
+        Ok(()) => {
+            println!("\nAuthentication complete!");
             println!("✓ OpenRouter configuration complete");
             println!("✓ Models configured successfully");
 
@@ -1572,6 +1589,7 @@ pub async fn handle_openrouter_auth() -> Result<(), Box<dyn Error>> {
                                         timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
                                         bundled: Some(true),
                                         description: None,
+                                        log_level: None,
                                     },
                                 }) {
                                     Ok(_) => println!("✓ Developer extension enabled"),
@@ -4,6 +4,9 @@ use console::style;
 use crate::recipes::github_recipe::RecipeSource;
 use crate::recipes::recipe::load_recipe_for_validation;
 use crate::recipes::search_recipe::list_available_recipes;
+use goose::config::RecipeSigningManager;
+use goose::recipe::import_recipe::{get_default_recipes_dir, import_recipe};
+use goose::recipe::Recipe;
 use goose::recipe_deeplink;
 
 /// Validates a recipe file
@@ -34,14 +37,15 @@ pub fn handle_validate(recipe_name: &str) -> Result<()> {
 /// # Arguments
 ///
 /// * `file_path` - Path to the recipe file
+/// * `sign` - Whether to sign the deeplink with the local signing key, creating it if needed
 ///
 /// # Returns
 ///
 /// Result indicating success or failure
-pub fn handle_deeplink(recipe_name: &str) -> Result<String> {
+pub fn handle_deeplink(recipe_name: &str, sign: bool) -> Result<String> {
     // Load the recipe file first to validate it
     match load_recipe_for_validation(recipe_name) {
-        Ok(recipe) => match recipe_deeplink::encode(&recipe) {
+        Ok(recipe) => match encode_deeplink(&recipe, sign) {
             Ok(encoded) => {
                 println!(
                     "{} Generated deeplink for: {}",
@@ -68,6 +72,57 @@ pub fn handle_deeplink(recipe_name: &str) -> Result<String> {
     }
 }
 
+fn encode_deeplink(recipe: &Recipe, sign: bool) -> Result<String> {
+    if !sign {
+        return Ok(recipe_deeplink::encode(recipe)?);
+    }
+    let key = match RecipeSigningManager::signing_key() {
+        Some(key) => key,
+        None => {
+            let key = RecipeSigningManager::generate_signing_key()?;
+            println!(
+                "{} Created recipe signing key {}",
+                style("✓").green().bold(),
+                key.id
+            );
+            key
+        }
+    };
+    Ok(recipe_deeplink::encode_signed(recipe, &key)?)
+}
+
+/// Imports a recipe from a URL, GitHub repo path or deeplink into the local recipe directory
+///
+/// # Arguments
+///
+/// * `source` - https URL, `owner/repo/path@ref` or `goose://recipe?config=...` deeplink
+/// * `overwrite` - Whether to replace an existing recipe with the same name
+///
+/// # Returns
+///
+/// Result indicating success or failure
+pub async fn handle_import(source: &str, overwrite: bool) -> Result<()> {
+    let recipes_dir = get_default_recipes_dir()?;
+    match import_recipe(source, &recipes_dir, overwrite).await {
+        Ok(imported) => {
+            for issue in &imported.issues {
+                println!("{} {}", style("!").yellow().bold(), issue);
+            }
+            println!(
+                "{} Imported {} to {}",
+                style("✓").green().bold(),
+                imported.recipe.title,
+                imported.path.display()
+            );
+            Ok(())
+        }
+        Err(err) => {
+            println!("{} {}", style("✗").red().bold(), err);
+            Err(err.into())
+        }
+    }
+}
+
 /// Lists all available recipes from local paths and GitHub repositories
 ///
 /// # Arguments
@@ -187,7 +242,7 @@ response:
         let recipe_path =
             create_test_recipe_file(&temp_dir, "test_recipe.yaml", VALID_RECIPE_CONTENT);
 
-        let result = handle_deeplink(&recipe_path);
+        let result = handle_deeplink(&recipe_path, false);
         assert!(result.is_ok());
         let url = result.unwrap();
         assert!(url.starts_with("goose://recipe?config="));
@@ -200,7 +255,7 @@ response:
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
         let recipe_path =
             create_test_recipe_file(&temp_dir, "test_recipe.yaml", INVALID_RECIPE_CONTENT);
-        let result = handle_deeplink(&recipe_path);
+        let result = handle_deeplink(&recipe_path, false);
         assert!(result.is_err());
     }
 
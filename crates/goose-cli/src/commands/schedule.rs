@@ -1,11 +1,14 @@
 use anyhow::{bail, Context, Result};
 use base64::engine::{general_purpose::STANDARD as BASE64_STANDARD, Engine};
 use goose::scheduler::{
-    get_default_scheduled_recipes_dir, get_default_scheduler_storage_path, ScheduledJob,
-    SchedulerError,
+    get_default_scheduled_recipes_dir, get_default_scheduler_storage_path, ConcurrencyPolicy,
+    ScheduledJob, SchedulerError,
 };
 use goose::scheduler_factory::SchedulerFactory;
+use goose::scheduler_gate::ExecutionConditions;
+use goose::scheduler_spec::ScheduleKind;
 use goose::temporal_scheduler::TemporalScheduler;
+use std::collections::HashMap;
 use std::path::Path;
 
 // Base64 decoding function - might be needed if recipe_source_arg can be base64
@@ -77,7 +80,11 @@ fn validate_cron_expression(cron: &str) -> Result<()> {
 
 pub async fn handle_schedule_add(
     id: String,
+    kind: ScheduleKind::Cron,
     cron: String,
+    run_at: None,
+    every: None,
+    interval_anchor: None,
     recipe_source_arg: String, // This is expected to be a file path by the Scheduler
 ) -> Result<()> {
     println!(
@@ -93,6 +100,10 @@ pub async fn handle_schedule_add(
     let job = ScheduledJob {
         id: id.clone(),
         source: recipe_source_arg.clone(), // Pass the original user-provided path
+        prompt: None,
+        working_dir: None,
+        extensions: Vec::new(),
+        params: HashMap::new(),
         cron,
         last_run: None,
         currently_running: false,
@@ -100,6 +111,17 @@ pub async fn handle_schedule_add(
         current_session_id: None,
         process_start_time: None,
         execution_mode: Some("background".to_string()), // Default to background for CLI
+        timezone: None,
+        concurrency_policy: ConcurrencyPolicy::Skip,
+        queued_run: false,
+        skipped_runs: Vec::new(),
+        retry: None,
+        on_failure: None,
+        on_success: None,
+        callback_url: None,
+        callback_secret: None,
+        run_history: Vec::new(),
+        conditions: ExecutionConditions::default(),
     };
 
     let scheduler_storage_path =
@@ -161,18 +183,28 @@ pub async fn handle_schedule_list() -> Result<()> {
         for job in jobs {
             let status = if job.currently_running {
                 "🟢 RUNNING"
+            } else if job.completed() {
+                "✅ COMPLETED"
             } else if job.paused {
                 "⏸️  PAUSED"
             } else {
                 "⏹️  IDLE"
             };
 
+            let schedule = job
+                .spec()
+                .map_or_else(|e| e.to_string(), |spec| spec.to_string());
+            let task = match &job.prompt {
+                Some(prompt) => format!("Prompt: {}", prompt),
+                // This source is now the path within scheduled_recipes_dir
+                None => format!("Recipe Source (in store): {}", job.source),
+            };
             println!(
-                "- ID: {}\n  Status: {}\n  Cron: {}\n  Recipe Source (in store): {}\n  Last Run: {}",
+                "- ID: {}\n  Status: {}\n  Schedule: {}\n  {}\n  Last Run: {}",
                 job.id,
                 status,
-                job.cron,
-                job.source, // This source is now the path within scheduled_recipes_dir
+                schedule,
+                task,
                 job.last_run
                     .map_or_else(|| "Never".to_string(), |dt| dt.to_rfc3339())
             );
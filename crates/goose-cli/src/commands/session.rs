@@ -24,6 +24,9 @@ pub fn remove_sessions(sessions: Vec<SessionInfo>) -> Result<()> {
         for session in sessions {
             fs::remove_file(session.path.clone())
                 .with_context(|| format!("Failed to remove session file '{}'", session.path))?;
+            goose::session::artifacts::remove_artifacts(&session.id).with_context(|| {
+                format!("Failed to remove artifacts of session '{}'", session.id)
+            })?;
             println!("Session `{}` removed.", session.id);
         }
     } else {
@@ -194,8 +197,12 @@ pub fn handle_session_export(identifier: Identifier, output_path: Option<PathBuf
         }
     };
 
+    // Older or damaged metadata shouldn't stop the export, it only loses the header
+    let metadata = goose::session::read_metadata(&session_file_path).ok();
+
     // Generate the markdown content using the export functionality
-    let markdown = export_session_to_markdown(messages, &session_file_path, None);
+    let markdown =
+        export_session_to_markdown(messages, &session_file_path, None, metadata.as_ref());
 
     // Output the markdown
     if let Some(output) = output_path {
@@ -209,6 +216,44 @@ pub fn handle_session_export(identifier: Identifier, output_path: Option<PathBuf
     Ok(())
 }
 
+/// Describe the provider and model settings a session ran with, and any model switches
+fn provider_header(metadata: &session::SessionMetadata) -> String {
+    let mut header = String::new();
+    if let Some(config) = &metadata.provider_config {
+        if let Some(provider) = config["provider"].as_str() {
+            header.push_str(&format!("- **Provider:** {}\n", provider));
+        }
+        if let Some(host) = config["host"].as_str() {
+            header.push_str(&format!("- **Host:** {}\n", host));
+        }
+        let model = &config["model"];
+        if let Some(model_name) = model["model_name"].as_str() {
+            header.push_str(&format!("- **Model:** {}\n", model_name));
+        }
+        for (setting, label) in [
+            ("temperature", "Temperature"),
+            ("top_p", "Top P"),
+            ("max_tokens", "Max tokens"),
+        ] {
+            if !model[setting].is_null() {
+                header.push_str(&format!("- **{}:** {}\n", label, model[setting]));
+            }
+        }
+    }
+    for change in &metadata.model_changes {
+        header.push_str(&format!(
+            "- **Switched to {}** ({}) at {}\n",
+            change.model,
+            change.mode,
+            change.changed_at.to_rfc3339()
+        ));
+    }
+    if !header.is_empty() {
+        header.push('\n');
+    }
+    header
+}
+
 /// Convert a list of messages to markdown format for session export
 ///
 /// This function handles the formatting of a complete session including headers,
@@ -217,6 +262,7 @@ fn export_session_to_markdown(
     messages: Vec<goose::message::Message>,
     session_file: &Path,
     session_name_override: Option<&str>,
+    metadata: Option<&session::SessionMetadata>,
 ) -> String {
     let mut markdown_output = String::new();
 
@@ -228,6 +274,9 @@ fn export_session_to_markdown(
     });
 
     markdown_output.push_str(&format!("# Session Export: {}\n\n", session_name));
+    if let Some(metadata) = metadata {
+        markdown_output.push_str(&provider_header(metadata));
+    }
 
     if messages.is_empty() {
         markdown_output.push_str("*(This session has no messages)*\n");
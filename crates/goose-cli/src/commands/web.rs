@@ -491,6 +491,11 @@ async fn process_message_streaming(
         execution_mode: None,
         max_turns: None,
         retry_config: None,
+        allowed_extensions: None,
+        persona: None,
+        goose_mode: None,
+        permissions_path: None,
+        recorded_tool_results: None,
     };
 
     match agent.reply(&messages, Some(session_config), None).await {
@@ -583,6 +588,7 @@ async fn process_message_streaming(
                                         goose::permission::PermissionConfirmation {
                                             principal_type: goose::permission::permission_confirmation::PrincipalType::Tool,
                                             permission: goose::permission::Permission::AllowOnce,
+                                            persist: true,
                                         }
                                     ).await;
                                 }
@@ -661,7 +667,13 @@ async fn process_message_streaming(
                     Ok(AgentEvent::ModelChange { model, mode }) => {
                         // Log model change
                         tracing::info!("Model changed to {} in {} mode", model, mode);
+                        if let Err(e) =
+                            session::record_model_change(&session_file, &model, &mode).await
+                        {
+                            error!("Failed to record model change: {}", e);
+                        }
                     }
+                    Ok(AgentEvent::TurnTiming(_)) => {}
 
                     Err(e) => {
                         error!("Error in message stream: {}", e);
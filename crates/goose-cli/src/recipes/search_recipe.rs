@@ -1,5 +1,7 @@
 use anyhow::{anyhow, Result};
 use goose::config::Config;
+use goose::recipe::import_recipe::get_default_recipes_dir;
+use goose::recipe::local_recipes::GOOSE_RECIPE_PATH_ENV_VAR;
 use goose::recipe::read_recipe_file_content::{read_recipe_file, RecipeFile};
 use goose::recipe::template_recipe::parse_recipe_content;
 use std::env;
@@ -13,8 +15,6 @@ use super::github_recipe::{
     GOOSE_RECIPE_GITHUB_REPO_CONFIG_KEY,
 };
 
-const GOOSE_RECIPE_PATH_ENV_VAR: &str = "GOOSE_RECIPE_PATH";
-
 pub fn retrieve_recipe_file(recipe_name: &str) -> Result<RecipeFile> {
     if RECIPE_FILE_EXTENSIONS
         .iter()
@@ -74,6 +74,10 @@ fn retrieve_recipe_from_local_path(recipe_name: &str) -> Result<RecipeFile> {
             .collect();
         search_dirs.extend(recipe_path_env_dirs);
     }
+    // Imported recipes come last so local copies take precedence
+    if let Ok(imported_recipes_dir) = get_default_recipes_dir() {
+        search_dirs.push(imported_recipes_dir);
+    }
     for dir in &search_dirs {
         if let Ok(result) = read_recipe_in_dir(dir, recipe_name) {
             return Ok(result);
@@ -132,6 +136,10 @@ fn discover_local_recipes() -> Result<Vec<RecipeInfo>> {
             .collect();
         search_dirs.extend(recipe_path_env_dirs);
     }
+    // Imported recipes come last so local copies take precedence
+    if let Ok(imported_recipes_dir) = get_default_recipes_dir() {
+        search_dirs.push(imported_recipes_dir);
+    }
 
     for dir in search_dirs {
         if let Ok(dir_recipes) = scan_directory_for_recipes(&dir) {
@@ -143,6 +143,7 @@ mod tests {
                     description: None,
                     timeout: None,
                     bundled: None,
+                    log_level: None,
                 },
                 ExtensionConfig::Stdio {
                     name: "slack-mcp".to_string(),
@@ -150,9 +151,11 @@ mod tests {
                     args: vec![],
                     envs: Envs::new(HashMap::new()),
                     env_keys: vec!["SLACK_TOKEN".to_string()],
+                    cwd: None,
                     timeout: None,
                     description: None,
                     bundled: None,
+                    log_level: None,
                 },
                 ExtensionConfig::Builtin {
                     name: "builtin-ext".to_string(),
@@ -160,6 +163,7 @@ mod tests {
                     description: None,
                     timeout: None,
                     bundled: None,
+                    log_level: None,
                 },
             ]),
             context: None,
@@ -237,6 +241,7 @@ mod tests {
                     description: None,
                     timeout: None,
                     bundled: None,
+                    log_level: None,
                 },
                 ExtensionConfig::Stdio {
                     name: "service-b".to_string(),
@@ -244,9 +249,11 @@ mod tests {
                     args: vec![],
                     envs: Envs::new(HashMap::new()),
                     env_keys: vec!["API_KEY".to_string()], // Same original key, different extension
+                    cwd: None,
                     timeout: None,
                     description: None,
                     bundled: None,
+                    log_level: None,
                 },
             ]),
             context: None,
@@ -294,6 +301,7 @@ mod tests {
                 description: None,
                 timeout: None,
                 bundled: None,
+                log_level: None,
             }]),
             sub_recipes: Some(vec![SubRecipe {
                 name: "child-recipe".to_string(),
@@ -49,6 +49,7 @@ impl McpClientTrait for MockClient {
                 prompts: None,
                 resources: None,
                 tools: Some(ToolsCapability { list_changed: None }),
+                logging: None,
             },
             server_info: Implementation {
                 name: "MockClient".to_string(),
@@ -105,6 +106,7 @@ impl McpClientTrait for MockClient {
                 Ok(content) => Ok(CallToolResult {
                     content,
                     is_error: None,
+                    structured_content: None,
                 }),
                 Err(e) => Err(Error::UnexpectedResponse(e.to_string())),
             }
@@ -117,7 +119,10 @@ impl McpClientTrait for MockClient {
     }
 
     async fn list_prompts(&self, _next_cursor: Option<String>) -> Result<ListPromptsResult, Error> {
-        Ok(ListPromptsResult { prompts: vec![] })
+        Ok(ListPromptsResult {
+            prompts: vec![],
+            next_cursor: None,
+        })
     }
 
     async fn get_prompt(&self, _name: &str, _arguments: Value) -> Result<GetPromptResult, Error> {
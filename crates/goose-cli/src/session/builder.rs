@@ -311,6 +311,12 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> Session {
 
     if session_config.resume {
         if let Some(session_file) = session_file.as_ref() {
+            // Refuse sessions this version can't continue without losing part of them
+            if let Err(e) = session::check_resumable(session_file) {
+                output::render_error(&format!("Cannot resume this session: {}", e));
+                process::exit(1);
+            }
+
             // Read the session metadata
             let metadata = session::read_metadata(session_file).unwrap_or_else(|e| {
                 output::render_error(&format!("Failed to read session metadata: {}", e));
@@ -347,6 +347,23 @@ pub fn message_to_markdown(message: &Message, export_all_content: bool) -> Strin
                 md.push_str("**Thinking:**\n");
                 md.push_str("> *Thinking was redacted*\n\n");
             }
+            MessageContent::ServerToolUse(tool_use) => {
+                md.push_str(&format!(
+                    "#### Tool Call: `{}` (namespace: `provider`)\n",
+                    tool_use.name
+                ));
+                md.push_str("**Arguments:**\n");
+                md.push_str(&value_to_markdown(&tool_use.input, 0, export_all_content));
+                md.push('\n');
+            }
+            MessageContent::Citations(citations) => {
+                md.push_str("**Sources:**\n");
+                for citation in &citations.citations {
+                    let title = citation.title.as_deref().unwrap_or(&citation.url);
+                    md.push_str(&format!("- [{}]({})\n", title, citation.url));
+                }
+                md.push('\n');
+            }
             _ => {
                 md.push_str(
                     "`WARNING: Message content type could not be rendered to Markdown`\n\n",
@@ -530,6 +547,8 @@ mod tests {
         let tool_response = ToolResponse {
             id: "test-id".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let result = tool_response_to_markdown(&tool_response, true);
@@ -549,6 +568,8 @@ mod tests {
         let tool_response = ToolResponse {
             id: "test-id".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let result = tool_response_to_markdown(&tool_response, true);
@@ -653,6 +674,8 @@ if __name__ == "__main__":
         let tool_response = ToolResponse {
             id: "shell-cat".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -692,6 +715,8 @@ if __name__ == "__main__":
         let tool_response = ToolResponse {
             id: "git-status".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -739,6 +764,8 @@ warning: unused variable `x`
         let tool_response = ToolResponse {
             id: "cargo-build".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -784,6 +811,8 @@ warning: unused variable `x`
         let tool_response = ToolResponse {
             id: "curl-api".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -818,6 +847,8 @@ warning: unused variable `x`
         let tool_response = ToolResponse {
             id: "editor-write".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -873,6 +904,8 @@ def process_data(data: List[Dict]) -> List[Dict]:
         let tool_response = ToolResponse {
             id: "editor-view".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -908,6 +941,8 @@ Command failed with exit code 2"#;
         let tool_response = ToolResponse {
             id: "shell-error".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -946,6 +981,8 @@ Command failed with exit code 2"#;
         let tool_response = ToolResponse {
             id: "script-exec".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -991,6 +1028,8 @@ drwx------   3 user  staff    96 Dec  6 16:20 com.apple.launchd.abc
         let tool_response = ToolResponse {
             id: "multi-cmd".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let request_result = tool_request_to_markdown(&_tool_request, true);
@@ -1032,6 +1071,8 @@ src/middleware.rs:12:async fn auth_middleware(req: Request, next: Next) -> Resul
         let tool_response = ToolResponse {
             id: "grep-search".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -1070,6 +1111,8 @@ src/middleware.rs:12:async fn auth_middleware(req: Request, next: Next) -> Resul
         let tool_response = ToolResponse {
             id: "json-test".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let response_result = tool_response_to_markdown(&tool_response, true);
@@ -1109,6 +1152,8 @@ found 0 vulnerabilities"#;
         let tool_response = ToolResponse {
             id: "npm-install".to_string(),
             tool_result: Ok(vec![Content::text(text_content.raw.text)]),
+            structured_content: None,
+            duration_ms: None,
         };
 
         let request_result = tool_request_to_markdown(&tool_request, true);
@@ -28,6 +28,7 @@ use anyhow::{Context, Result};
 use completion::GooseCompleter;
 use etcetera::{choose_app_strategy, AppStrategy};
 use goose::agents::extension::{Envs, ExtensionConfig};
+use goose::agents::extension_manager::resolve_prompt_command;
 use goose::agents::types::RetryConfig;
 use goose::agents::{Agent, SessionConfig};
 use goose::config::Config;
@@ -132,10 +133,12 @@ impl Session {
         retry_config: Option<RetryConfig>,
     ) -> Self {
         let messages = if let Some(session_file) = &session_file {
-            session::read_messages(session_file).unwrap_or_else(|e| {
-                eprintln!("Warning: Failed to load message history: {}", e);
-                Vec::new()
-            })
+            session::load_session(session_file)
+                .map(|(_, messages)| messages)
+                .unwrap_or_else(|e| {
+                    eprintln!("Warning: Failed to load message history: {}", e);
+                    Vec::new()
+                })
         } else {
             // Don't try to read messages if we're not saving sessions
             Vec::new()
@@ -207,10 +210,12 @@ impl Session {
             args: parts.iter().map(|s| s.to_string()).collect(),
             envs: Envs::new(envs),
             env_keys: Vec::new(),
+            cwd: None,
             description: Some(goose::config::DEFAULT_EXTENSION_DESCRIPTION.to_string()),
             // TODO: should set timeout
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
+            log_level: None,
         };
 
         self.agent
@@ -244,6 +249,7 @@ impl Session {
             // TODO: should set timeout
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
+            log_level: None,
         };
 
         self.agent
@@ -278,6 +284,7 @@ impl Session {
             // TODO: should set timeout
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
+            log_level: None,
         };
 
         self.agent
@@ -304,6 +311,7 @@ impl Session {
                 timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
                 bundled: None,
                 description: None,
+                log_level: None,
             };
             self.agent
                 .add_extension(config)
@@ -342,21 +350,17 @@ impl Session {
     }
 
     pub async fn get_prompt_info(&mut self, name: &str) -> Result<Option<output::PromptInfo>> {
-        let prompts = self.agent.list_extension_prompts().await;
-
-        // Find which extension has this prompt
-        for (extension, prompt_list) in prompts {
-            if let Some(prompt) = prompt_list.iter().find(|p| p.name == name) {
-                return Ok(Some(output::PromptInfo {
-                    name: prompt.name.clone(),
-                    description: prompt.description.clone(),
-                    arguments: prompt.arguments.clone(),
-                    extension: Some(extension),
-                }));
-            }
-        }
-
-        Ok(None)
+        let commands = self.agent.list_prompt_commands().await?;
+
+        // Accept both the listed name and the extension-qualified name
+        Ok(
+            resolve_prompt_command(&commands, name).map(|command| output::PromptInfo {
+                name: command.name.clone(),
+                description: command.prompt.description.clone(),
+                arguments: command.prompt.arguments.clone(),
+                extension: Some(command.extension.clone()),
+            }),
+        )
     }
 
     pub async fn get_prompt(&mut self, name: &str, arguments: Value) -> Result<Vec<PromptMessage>> {
@@ -859,6 +863,11 @@ impl Session {
                 execution_mode: None,
                 max_turns: self.max_turns,
                 retry_config: self.retry_config.clone(),
+                allowed_extensions: None,
+                persona: None,
+                goose_mode: None,
+                permissions_path: None,
+                recorded_tool_results: None,
             }
         });
         let mut stream = self
@@ -879,13 +888,20 @@ impl Session {
                                 output::hide_thinking();
 
                                 // Format the confirmation prompt
-                                let prompt = "Goose would like to call the above tool, do you allow?".to_string();
+                                let prompt = match &confirmation.matched_rule {
+                                    Some(rule) => format!(
+                                        "Goose would like to call the above tool, do you allow? (asked by permission rule {}__{})",
+                                        rule.extension, rule.tool
+                                    ),
+                                    None => "Goose would like to call the above tool, do you allow?".to_string(),
+                                };
 
                                 // Get confirmation from user
                                 let permission_result = cliclack::select(prompt)
                                     .item(Permission::AllowOnce, "Allow", "Allow the tool call once")
                                     .item(Permission::AlwaysAllow, "Always Allow", "Always allow the tool call")
                                     .item(Permission::DenyOnce, "Deny", "Deny the tool call")
+                                    .item(Permission::AlwaysDeny, "Always Deny", "Always deny the tool call")
                                     .item(Permission::Cancel, "Cancel", "Cancel the AI response and tool call")
                                     .interact();
 
@@ -928,6 +944,7 @@ impl Session {
                                     self.agent.handle_confirmation(confirmation.id.clone(), PermissionConfirmation {
                                         principal_type: PrincipalType::Tool,
                                         permission,
+                                        persist: true,
                                     },).await;
                                 }
                             } else if let Some(MessageContent::ContextLengthExceeded(_)) = message.content.first() {
@@ -1159,6 +1176,21 @@ impl Session {
                             if self.debug {
                                 eprintln!("Model changed to {} in {} mode", model, mode);
                             }
+                            if let Some(session_file) = &self.session_file {
+                                if let Err(e) =
+                                    session::record_model_change(session_file, &model, &mode).await
+                                {
+                                    eprintln!("Failed to record model change: {}", e);
+                                }
+                            }
+                        }
+                        Some(Ok(AgentEvent::TurnTiming(timing))) => {
+                            if self.debug {
+                                eprintln!(
+                                    "Turn took {:?} on the model and {:?} on tools",
+                                    timing.model_time, timing.tool_time
+                                );
+                            }
                         }
 
                         Some(Err(e)) => {
@@ -1,7 +1,9 @@
 use bat::WrappingMode;
 use console::{style, Color};
 use goose::config::Config;
-use goose::message::{Message, MessageContent, ToolRequest, ToolResponse};
+use goose::message::{
+    CitationsContent, Message, MessageContent, ServerToolUse, ToolRequest, ToolResponse,
+};
 use goose::providers::pricing::get_model_pricing;
 use goose::providers::pricing::parse_model_id;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
@@ -176,6 +178,8 @@ pub fn render_message(message: &Message, debug: bool) {
                 println!("\n{}", style("Thinking:").dim().italic());
                 print_markdown("Thinking was redacted", theme);
             }
+            MessageContent::ServerToolUse(tool_use) => render_server_tool_use(tool_use, debug),
+            MessageContent::Citations(citations) => render_citations(citations),
             _ => {
                 println!("WARNING: Message content type could not be rendered");
             }
@@ -233,7 +237,7 @@ fn render_tool_request(req: &ToolRequest, theme: Theme, debug: bool) {
     match &req.tool_call {
         Ok(call) => match call.name.as_str() {
             "developer__text_editor" => render_text_editor_request(call, debug),
-            "developer__shell" => render_shell_request(call, debug),
+            "developer__shell" | "developer__process_start" => render_shell_request(call, debug),
             "dynamic_task__create_task" => render_dynamic_task_request(call, debug),
             _ => render_default_request(call, debug),
         },
@@ -445,6 +449,28 @@ fn render_default_request(call: &ToolCall, debug: bool) {
     println!();
 }
 
+/// Tools the provider ran itself show like other tool calls, under the provider's name for them
+fn render_server_tool_use(tool_use: &ServerToolUse, debug: bool) {
+    println!();
+    println!(
+        "─── {} | {} ──────────────────────────",
+        style(&tool_use.name),
+        style("provider").magenta().dim(),
+    );
+    print_params(&tool_use.input, 0, debug);
+    println!();
+}
+
+fn render_citations(citations: &CitationsContent) {
+    println!("{}", style("Sources:").dim().italic());
+    for citation in &citations.citations {
+        match &citation.title {
+            Some(title) => println!("  {} {}", style(title).dim(), style(&citation.url).cyan()),
+            None => println!("  {}", style(&citation.url).cyan()),
+        }
+    }
+}
+
 // Helper functions
 
 fn print_tool_header(call: &ToolCall) {
@@ -1,5 +1,8 @@
 mod editor_models;
 mod lang;
+mod patch;
+mod process;
+mod search;
 mod shell;
 
 use anyhow::Result;
@@ -37,6 +40,8 @@ use rmcp::model::{
 use rmcp::object;
 
 use self::editor_models::{create_editor_model, EditorModel};
+use self::process::{OutputStream, ProcessLimits, ProcessTable};
+use self::search::SearchOptions;
 use self::shell::{expand_path, get_shell_config, is_absolute_path, normalize_line_endings};
 use indoc::indoc;
 use std::process::Stdio;
@@ -45,6 +50,19 @@ use xcap::{Monitor, Window};
 
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 
+/// Context lines a patch hunk may ignore at each end, as `patch` allows by default
+const DEFAULT_PATCH_FUZZ: usize = 2;
+
+/// Lines of a background process's output that process_logs returns by default
+const DEFAULT_LOG_LINES: usize = 50;
+
+const DEFAULT_SEARCH_RESULTS: usize = 100;
+const MAX_SEARCH_CONTEXT: usize = 10;
+/// Tool results over this size are cut by the agent, so search output stops before it. Goose
+/// sets it from its config when it starts the extension.
+const MAX_TOOL_RESULT_BYTES_ENV: &str = "GOOSE_MAX_TOOL_RESULT_BYTES";
+const DEFAULT_MAX_TOOL_RESULT_BYTES: usize = 64 * 1024;
+
 // Embeds the prompts directory to the build
 static PROMPTS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/developer/prompts");
 
@@ -98,6 +116,7 @@ pub struct DeveloperRouter {
     file_history: Arc<Mutex<HashMap<PathBuf, Vec<String>>>>,
     ignore_patterns: Arc<Gitignore>,
     editor_model: Option<EditorModel>,
+    processes: Arc<ProcessTable>,
 }
 
 impl Default for DeveloperRouter {
@@ -149,8 +168,8 @@ impl DeveloperRouter {
                 of if the command succeeded or failed.
 
                 Avoid commands that produce a large amount of output, and consider piping those outputs to files.
-                If you need to run a long lived command, background it - e.g. `uvicorn main:app &` so that
-                this tool does not run indefinitely.
+                If you need to run a long lived command, such as a dev server, start it with process_start
+                instead so that this tool does not run indefinitely.
 
                 **Important**: Each shell command runs in its own process. Things like directory changes or
                 sourcing files do not persist between tool calls. So you may need to repeat them each time by
@@ -254,6 +273,41 @@ impl DeveloperRouter {
             open_world_hint: Some(false),
         });
 
+        let search_code_tool = Tool::new(
+            "search_code".to_string(),
+            indoc! {r#"
+                Search the contents of files in the working directory for a regular expression.
+
+                Returns each matching line as `path:line:text`, with paths relative to the working
+                directory, and `path-line-text` for context lines. Files ignored by .gitignore and
+                .gooseignore, hidden files and binary files are skipped; set `include_ignored` to
+                search ignored and hidden files too (.gooseignore still applies).
+
+                Use this instead of listing directories and reading files to find where something is
+                defined or used. Narrow large searches with `globs`, e.g. ["*.rs", "!tests/**"].
+            "#}
+            .to_string(),
+            object!({
+                "type": "object",
+                "required": ["pattern"],
+                "properties": {
+                    "pattern": {"type": "string", "description": "Regular expression, in Rust regex syntax"},
+                    "path": {"type": "string", "description": "Directory to search, inside the working directory. Defaults to the working directory"},
+                    "globs": {"type": "array", "items": {"type": "string"}, "description": "Globs files must match; those starting with ! exclude files"},
+                    "case_sensitive": {"type": "boolean", "default": true},
+                    "context": {"type": "integer", "minimum": 0, "maximum": MAX_SEARCH_CONTEXT, "default": 0, "description": "Lines to show before and after each match"},
+                    "max_results": {"type": "integer", "minimum": 1, "default": DEFAULT_SEARCH_RESULTS},
+                    "include_ignored": {"type": "boolean", "default": false}
+                }
+            })
+        ).annotate(ToolAnnotations {
+            title: Some("Search code".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(true),
+            open_world_hint: Some(false),
+        });
+
         // Create text editor tool with different descriptions based on editor API configuration
         let (text_editor_desc, str_replace_command) = if let Some(ref editor) = editor_model {
             (
@@ -334,6 +388,115 @@ impl DeveloperRouter {
             }),
         );
 
+        let apply_patch_tool = Tool::new(
+            "apply_patch".to_string(),
+            indoc! {r#"
+                Apply a unified diff to one or more files.
+
+                Prefer this to rewriting whole files when changing parts of large files. Use the format
+                `diff -u` and `git diff` produce: `--- a/path` and `+++ b/path` headers for each file,
+                then `@@ -start,count +start,count @@` hunks whose lines start with ' ' (context),
+                '-' (removed) or '+' (added). Use `/dev/null` as the old path to create a file.
+                Paths are relative to the working directory and may not leave it.
+
+                A hunk may apply a few lines away from where its header says, and with up to `fuzz`
+                lines of its leading and trailing context ignored. Hunks that don't apply are
+                reported with the line they were expected at and what the file has there; the other
+                hunks still apply, so send only the failed hunks again, against the file as it is now.
+
+                Set `dry_run` to see what the patch would do without changing any files.
+            "#}
+            .to_string(),
+            object!({
+                "type": "object",
+                "required": ["patch"],
+                "properties": {
+                    "patch": {"type": "string", "description": "The unified diff to apply"},
+                    "dry_run": {"type": "boolean", "default": false, "description": "Report the result without changing any files"},
+                    "fuzz": {"type": "integer", "minimum": 0, "default": DEFAULT_PATCH_FUZZ, "description": "Context lines that may be ignored at each end of a hunk"}
+                }
+            }),
+        );
+
+        let process_start_tool = Tool::new(
+            "process_start".to_string(),
+            indoc! {r#"
+                Start a long lived command, such as a dev server or a file watcher, in the background.
+
+                Returns a handle to pass to process_logs, process_status and process_kill. The command
+                runs in the shell like the shell tool's commands, and its stdout and stderr are kept,
+                up to a limit, for process_logs. Kill processes once you no longer need them; they are
+                also killed when the session ends.
+            "#}
+            .to_string(),
+            object!({
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "command": {"type": "string"}
+                }
+            }),
+        );
+
+        let process_logs_tool = Tool::new(
+            "process_logs".to_string(),
+            indoc! {r#"
+                Read the latest output of a background process started with process_start.
+                Only the most recent output is kept, so check the logs often for chatty processes.
+            "#}
+            .to_string(),
+            object!({
+                "type": "object",
+                "required": ["handle"],
+                "properties": {
+                    "handle": {"type": "string", "description": "The handle process_start returned"},
+                    "stream": {"type": "string", "enum": ["stdout", "stderr", "both"], "default": "both"},
+                    "lines": {"type": "integer", "minimum": 1, "default": DEFAULT_LOG_LINES, "description": "Number of lines to read from the end"}
+                }
+            }),
+        )
+        .annotate(ToolAnnotations {
+            title: Some("Read background process output".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        });
+
+        let process_status_tool = Tool::new(
+            "process_status".to_string(),
+            indoc! {r#"
+                Show whether a background process is still running, or list every background process
+                of this session when no handle is given.
+            "#}
+            .to_string(),
+            object!({
+                "type": "object",
+                "properties": {
+                    "handle": {"type": "string", "description": "The handle process_start returned"}
+                }
+            }),
+        )
+        .annotate(ToolAnnotations {
+            title: Some("Check background processes".to_string()),
+            read_only_hint: Some(true),
+            destructive_hint: Some(false),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(false),
+        });
+
+        let process_kill_tool = Tool::new(
+            "process_kill".to_string(),
+            "Kill a background process started with process_start.".to_string(),
+            object!({
+                "type": "object",
+                "required": ["handle"],
+                "properties": {
+                    "handle": {"type": "string", "description": "The handle process_start returned"}
+                }
+            }),
+        );
+
         let list_windows_tool = Tool::new(
             "list_windows",
             indoc! {r#"
@@ -576,7 +739,13 @@ impl DeveloperRouter {
                 bash_tool,
                 glob_tool,
                 grep_tool,
+                search_code_tool,
                 text_editor_tool,
+                apply_patch_tool,
+                process_start_tool,
+                process_logs_tool,
+                process_status_tool,
+                process_kill_tool,
                 list_windows_tool,
                 screen_capture_tool,
                 image_processor_tool,
@@ -586,6 +755,7 @@ impl DeveloperRouter {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model,
+            processes: Arc::new(ProcessTable::new(ProcessLimits::from_env())),
         }
     }
 
@@ -652,23 +822,10 @@ impl DeveloperRouter {
         }
     }
 
-    // Shell command execution with platform-specific handling
-    async fn bash(
-        &self,
-        params: Value,
-        notifier: mpsc::Sender<JsonRpcMessage>,
-    ) -> Result<Vec<Content>, ToolError> {
-        let command =
-            params
-                .get("command")
-                .and_then(|v| v.as_str())
-                .ok_or(ToolError::InvalidParameters(
-                    "The command string is required".to_string(),
-                ))?;
-
-        // Check if command might access ignored files and return early if it does
+    // Check if command might access ignored files and return early if it does
+    fn check_command_paths(&self, command: &str) -> Result<(), ToolError> {
         let cmd_parts: Vec<&str> = command.split_whitespace().collect();
-        for arg in &cmd_parts[1..] {
+        for arg in cmd_parts.iter().skip(1) {
             // Skip command flags
             if arg.starts_with('-') {
                 continue;
@@ -686,6 +843,24 @@ impl DeveloperRouter {
                 )));
             }
         }
+        Ok(())
+    }
+
+    // Shell command execution with platform-specific handling
+    async fn bash(
+        &self,
+        params: Value,
+        notifier: mpsc::Sender<JsonRpcMessage>,
+    ) -> Result<Vec<Content>, ToolError> {
+        let command =
+            params
+                .get("command")
+                .and_then(|v| v.as_str())
+                .ok_or(ToolError::InvalidParameters(
+                    "The command string is required".to_string(),
+                ))?;
+
+        self.check_command_paths(command)?;
 
         // Get platform-specific shell configuration
         let shell_config = get_shell_config();
@@ -878,6 +1053,118 @@ impl DeveloperRouter {
         ])
     }
 
+    async fn search_code(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let pattern =
+            params
+                .get("pattern")
+                .and_then(|v| v.as_str())
+                .ok_or(ToolError::InvalidParameters(
+                    "The pattern string is required".to_string(),
+                ))?;
+        let globs = match params.get("globs") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(globs)) => globs
+                .iter()
+                .map(|glob| {
+                    glob.as_str().map(str::to_string).ok_or_else(|| {
+                        ToolError::InvalidParameters("Each glob must be a string".to_string())
+                    })
+                })
+                .collect::<Result<Vec<String>, ToolError>>()?,
+            Some(_) => {
+                return Err(ToolError::InvalidParameters(
+                    "globs must be an array of strings".to_string(),
+                ))
+            }
+        };
+        let options = SearchOptions {
+            pattern: pattern.to_string(),
+            globs,
+            case_sensitive: params
+                .get("case_sensitive")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true),
+            context: params
+                .get("context")
+                .and_then(|v| v.as_u64())
+                .map_or(0, |context| (context as usize).min(MAX_SEARCH_CONTEXT)),
+            max_results: params
+                .get("max_results")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_SEARCH_RESULTS, |max| (max as usize).max(1)),
+            include_ignored: params
+                .get("include_ignored")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            max_output_bytes: std::env::var(MAX_TOOL_RESULT_BYTES_ENV)
+                .ok()
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(DEFAULT_MAX_TOOL_RESULT_BYTES),
+        };
+
+        let cwd = std::env::current_dir().expect("should have a current working dir");
+        let root = match params.get("path").and_then(|v| v.as_str()) {
+            Some(path) => {
+                let expanded = expand_path(path);
+                if is_absolute_path(&expanded) {
+                    PathBuf::from(expanded)
+                } else {
+                    cwd.join(expanded)
+                }
+            }
+            None => cwd.clone(),
+        };
+        if !is_within(&root, &cwd) {
+            return Err(ToolError::InvalidParameters(format!(
+                "{} is outside the working directory {}",
+                root.display(),
+                cwd.display()
+            )));
+        }
+        if !root.is_dir() {
+            return Err(ToolError::InvalidParameters(format!(
+                "{} is not a directory",
+                root.display()
+            )));
+        }
+
+        let ignore_patterns = Arc::clone(&self.ignore_patterns);
+        let results = tokio::task::spawn_blocking(move || {
+            search::search(&root, &cwd, &options, |path| {
+                ignore_patterns.matched(path, false).is_ignore()
+            })
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))??;
+
+        let summary = match (results.matches, results.truncated) {
+            (0, _) => format!("No matches for `{}`", pattern),
+            (matches, false) => format!(
+                "{} in {}",
+                counted(matches, "match", "matches"),
+                counted(results.files_with_matches, "file", "files")
+            ),
+            (matches, true) => format!(
+                "Showing the first {} in {}, there are more. \
+                Narrow the search with `path` or `globs`, or a more specific pattern.",
+                counted(matches, "match", "matches"),
+                counted(results.files_with_matches, "file", "files")
+            ),
+        };
+        let result = if results.output.is_empty() {
+            summary
+        } else {
+            format!("{}\n\n{}", summary, results.output)
+        };
+
+        Ok(vec![
+            Content::text(result.clone()).with_audience(vec![Role::Assistant]),
+            Content::text(result)
+                .with_audience(vec![Role::User])
+                .with_priority(0.0),
+        ])
+    }
+
     async fn text_editor(&self, params: Value) -> Result<Vec<Content>, ToolError> {
         let command = params
             .get("command")
@@ -1405,6 +1692,229 @@ impl DeveloperRouter {
         Ok(())
     }
 
+    fn process_start(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let command =
+            params
+                .get("command")
+                .and_then(|v| v.as_str())
+                .ok_or(ToolError::InvalidParameters(
+                    "The command string is required".to_string(),
+                ))?;
+        self.check_command_paths(command)?;
+        let handle = self.processes.start(command)?;
+        Ok(vec![Content::text(format!(
+            "Started `{}` in the background as {}. Use process_logs to read its output.",
+            command, handle
+        ))])
+    }
+
+    fn process_logs(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let handle = process_handle(&params)?;
+        let stream = match params.get("stream").and_then(|v| v.as_str()) {
+            Some(name) => OutputStream::parse(name).ok_or_else(|| {
+                ToolError::InvalidParameters(format!(
+                    "Unknown stream '{}', expected stdout, stderr or both",
+                    name
+                ))
+            })?,
+            None => OutputStream::Both,
+        };
+        let lines = params
+            .get("lines")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_LOG_LINES, |lines| lines as usize);
+
+        let (status, output) = self.processes.logs(handle, stream, lines)?;
+        Ok(vec![Content::text(format!("{}\n\n{}", status, output))])
+    }
+
+    fn process_status(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let handle = params.get("handle").and_then(|v| v.as_str());
+        let statuses = self.processes.status(handle)?;
+        if statuses.is_empty() {
+            return Ok(vec![Content::text("No background processes were started")]);
+        }
+        Ok(vec![Content::text(
+            statuses
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )])
+    }
+
+    async fn process_kill(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let handle = process_handle(&params)?;
+        let status = self.processes.kill(handle).await?;
+        Ok(vec![Content::text(status.to_string())])
+    }
+
+    async fn apply_patch(&self, params: Value) -> Result<Vec<Content>, ToolError> {
+        let diff = params
+            .get("patch")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidParameters("Missing 'patch' parameter".into()))?;
+        let dry_run = params
+            .get("dry_run")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let fuzz = params
+            .get("fuzz")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_PATCH_FUZZ, |fuzz| fuzz as usize);
+
+        let patches = patch::parse(diff).map_err(|e| {
+            ToolError::InvalidParameters(format!("Could not read the patch at {}", e))
+        })?;
+
+        // Check every path before changing any file
+        let cwd = std::env::current_dir().expect("should have a current working dir");
+        let mut paths = Vec::with_capacity(patches.len());
+        for file_patch in &patches {
+            let expanded = expand_path(file_patch.path());
+            let path = if is_absolute_path(&expanded) {
+                PathBuf::from(&expanded)
+            } else {
+                cwd.join(&expanded)
+            };
+            if !is_within(&path, &cwd) {
+                return Err(ToolError::InvalidParameters(format!(
+                    "The patch changes {}, which is outside the working directory {}",
+                    path.display(),
+                    cwd.display()
+                )));
+            }
+            if self.is_ignored(&path) {
+                return Err(ToolError::ExecutionError(format!(
+                    "Access to '{}' is restricted by .gooseignore",
+                    path.display()
+                )));
+            }
+            paths.push(path);
+        }
+
+        let mut report = Vec::new();
+        let mut previews = Vec::new();
+        let mut applied_hunks = 0;
+        let mut failed_hunks = 0;
+        for (file_patch, path) in patches.iter().zip(&paths) {
+            let original = if file_patch.old_path.is_none() {
+                if path.exists() {
+                    return Err(ToolError::InvalidParameters(format!(
+                        "The patch creates {}, which already exists",
+                        path.display()
+                    )));
+                }
+                String::new()
+            } else {
+                std::fs::read_to_string(path).map_err(|e| {
+                    ToolError::ExecutionError(format!("Failed to read {}: {}", path.display(), e))
+                })?
+            };
+
+            let result = patch::apply(&original, file_patch, fuzz);
+            applied_hunks += result.applied.len();
+            failed_hunks += result.failures.len();
+
+            report.push(format!(
+                "{}: {} of {} hunks {}",
+                path.display(),
+                result.applied.len(),
+                file_patch.hunks.len(),
+                if dry_run { "would apply" } else { "applied" }
+            ));
+            for hunk in result
+                .applied
+                .iter()
+                .filter(|h| h.offset != 0 || h.fuzz > 0)
+            {
+                report.push(format!(
+                    "  Hunk {} applied at line {} (offset {} lines, fuzz {})",
+                    hunk.hunk, hunk.line, hunk.offset, hunk.fuzz
+                ));
+            }
+            for failure in &result.failures {
+                report.push(format!("  {}", failure));
+            }
+
+            let lines: Vec<&str> = result.content.lines().collect();
+            for hunk in &result.applied {
+                const SNIPPET_LINES: usize = 3;
+                let start = (hunk.line - 1).saturating_sub(SNIPPET_LINES);
+                let end = (hunk.line - 1 + hunk.new_len + SNIPPET_LINES).min(lines.len());
+                let snippet = lines[start..end]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| format!("{}: {}", start + i + 1, line))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                previews.push(formatdoc! {r#"
+                    ### {path} (hunk {hunk})
+                    ```{language}
+                    {snippet}
+                    ```
+                    "#,
+                    path=path.display(),
+                    hunk=hunk.hunk,
+                    language=lang::get_language_identifier(path),
+                    snippet=snippet
+                });
+            }
+
+            if dry_run || result.applied.is_empty() {
+                continue;
+            }
+            self.save_file_history(path)?;
+            if file_patch.new_path.is_none() && result.failures.is_empty() {
+                std::fs::remove_file(path).map_err(|e| {
+                    ToolError::ExecutionError(format!("Failed to delete {}: {}", path.display(), e))
+                })?;
+            } else {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| {
+                        ToolError::ExecutionError(format!(
+                            "Failed to create {}: {}",
+                            parent.display(),
+                            e
+                        ))
+                    })?;
+                }
+                std::fs::write(path, normalize_line_endings(&result.content)).map_err(|e| {
+                    ToolError::ExecutionError(format!("Failed to write {}: {}", path.display(), e))
+                })?;
+            }
+        }
+
+        let report = report.join("\n");
+        if applied_hunks == 0 {
+            return Err(ToolError::ExecutionError(format!(
+                "No hunks applied:\n{}",
+                report
+            )));
+        }
+        let summary = match (dry_run, failed_hunks) {
+            (true, 0) => format!("Dry run, no files were changed. The patch applies:\n{}", report),
+            (true, _) => format!(
+                "Dry run, no files were changed. {} hunks would fail:\n{}",
+                failed_hunks, report
+            ),
+            (false, 0) => format!("Applied the patch:\n{}", report),
+            (false, _) => format!(
+                "Applied the patch except for {} hunks. Send only the failed hunks again, against the files as they are now:\n{}",
+                failed_hunks, report
+            ),
+        };
+        let previews = previews.join("\n");
+
+        Ok(vec![
+            Content::text(format!("{}\n\n{}", summary, previews))
+                .with_audience(vec![Role::Assistant]),
+            Content::text(previews)
+                .with_audience(vec![Role::User])
+                .with_priority(0.2),
+        ])
+    }
+
     async fn list_windows(&self, _params: Value) -> Result<Vec<Content>, ToolError> {
         let windows = Window::all()
             .map_err(|_| ToolError::ExecutionError("Failed to list windows".into()))?;
@@ -1613,6 +2123,44 @@ impl DeveloperRouter {
     }
 }
 
+fn process_handle(params: &Value) -> Result<&str, ToolError> {
+    params
+        .get("handle")
+        .and_then(|v| v.as_str())
+        .ok_or(ToolError::InvalidParameters(
+            "The handle of a background process is required".to_string(),
+        ))
+}
+
+/// `count` followed by the singular or plural of a noun, as in "1 match" or "3 matches"
+fn counted(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+/// Whether `path` is inside `root` once symlinks are resolved. The part of the path that
+/// doesn't exist yet may not step out with `..`.
+fn is_within(path: &Path, root: &Path) -> bool {
+    let Ok(root) = root.canonicalize() else {
+        return false;
+    };
+    let mut existing = path.to_path_buf();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                missing.push(name.to_os_string());
+                existing = parent.to_path_buf();
+            }
+            _ => return false,
+        }
+    }
+    let Ok(mut resolved) = existing.canonicalize() else {
+        return false;
+    };
+    resolved.extend(missing.iter().rev());
+    resolved.starts_with(&root)
+}
+
 impl Router for DeveloperRouter {
     fn name(&self) -> String {
         "developer".to_string()
@@ -1646,7 +2194,13 @@ impl Router for DeveloperRouter {
                 "shell" => this.bash(arguments, notifier).await,
                 "glob" => this.glob(arguments).await,
                 "grep" => this.bash(arguments, notifier).await,
+                "search_code" => this.search_code(arguments).await,
                 "text_editor" => this.text_editor(arguments).await,
+                "apply_patch" => this.apply_patch(arguments).await,
+                "process_start" => this.process_start(arguments),
+                "process_logs" => this.process_logs(arguments),
+                "process_status" => this.process_status(arguments),
+                "process_kill" => this.process_kill(arguments).await,
                 "list_windows" => this.list_windows(arguments).await,
                 "screen_capture" => this.screen_capture(arguments).await,
                 "image_processor" => this.image_processor(arguments).await,
@@ -1708,6 +2262,7 @@ impl Clone for DeveloperRouter {
             file_history: Arc::clone(&self.file_history),
             ignore_patterns: Arc::clone(&self.ignore_patterns),
             editor_model: create_editor_model(), // Recreate the editor model since it's not Clone
+            processes: Arc::clone(&self.processes),
         }
     }
 }
@@ -2174,6 +2729,7 @@ mod tests {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            processes: Arc::new(ProcessTable::new(ProcessLimits::default())),
         };
 
         // Test basic file matching
@@ -2225,6 +2781,7 @@ mod tests {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            processes: Arc::new(ProcessTable::new(ProcessLimits::default())),
         };
 
         // Try to write to an ignored file
@@ -2285,6 +2842,7 @@ mod tests {
             file_history: Arc::new(Mutex::new(HashMap::new())),
             ignore_patterns: Arc::new(ignore_patterns),
             editor_model: None,
+            processes: Arc::new(ProcessTable::new(ProcessLimits::default())),
         };
 
         // Create an ignored file
@@ -2417,6 +2975,176 @@ mod tests {
         temp_dir.close().unwrap();
     }
 
+    #[cfg(not(windows))]
+    #[tokio::test]
+    #[serial]
+    async fn test_background_process_lifecycle() {
+        let router = get_router().await;
+        let started = router
+            .call_tool(
+                "process_start",
+                json!({"command": "echo ready; echo oops >&2; sleep 30"}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        let text = &started[0].as_text().unwrap().text;
+        let handle = text
+            .split_whitespace()
+            .find(|word| word.starts_with('p') && word.ends_with('.'))
+            .unwrap()
+            .trim_end_matches('.')
+            .to_string();
+
+        // The output is captured while the process keeps running
+        let mut logs = String::new();
+        for _ in 0..50 {
+            let result = router
+                .call_tool(
+                    "process_logs",
+                    json!({"handle": handle, "stream": "stdout"}),
+                    dummy_sender(),
+                )
+                .await
+                .unwrap();
+            logs = result[0].as_text().unwrap().text.clone();
+            if logs.contains("ready") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert!(logs.contains(&format!("{}: running", handle)));
+        assert!(logs.contains("ready"));
+        assert!(!logs.contains("oops"));
+
+        let killed = router
+            .call_tool("process_kill", json!({"handle": handle}), dummy_sender())
+            .await
+            .unwrap();
+        assert!(killed[0]
+            .as_text()
+            .unwrap()
+            .text
+            .starts_with(&format!("{}: killed", handle)));
+
+        let status = router
+            .call_tool("process_status", json!({"handle": handle}), dummy_sender())
+            .await
+            .unwrap();
+        assert!(status[0].as_text().unwrap().text.contains("killed"));
+
+        let error = router
+            .call_tool("process_logs", json!({"handle": "p999"}), dummy_sender())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ToolError::InvalidParameters(_)));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_search_code_in_working_dir() {
+        let router = get_router().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let working_dir = temp_dir.path().join("project");
+        fs::create_dir_all(working_dir.join("src")).unwrap();
+        fs::write(working_dir.join("src/lib.rs"), "pub fn needle() {}\n").unwrap();
+        fs::write(temp_dir.path().join("outside.rs"), "fn needle() {}\n").unwrap();
+        std::env::set_current_dir(&working_dir).unwrap();
+
+        let result = router
+            .call_tool("search_code", json!({"pattern": "needle"}), dummy_sender())
+            .await
+            .unwrap();
+        let text = &result[0].as_text().unwrap().text;
+        assert!(text.starts_with("1 match in 1 file\n"));
+        assert!(text.contains(&format!(
+            "{}:1:pub fn needle() {{}}",
+            Path::new("src").join("lib.rs").display()
+        )));
+
+        let error = router
+            .call_tool(
+                "search_code",
+                json!({"pattern": "needle", "path": ".."}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ToolError::InvalidParameters(_)));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_apply_patch_dry_run_then_apply() {
+        let router = get_router().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::env::set_current_dir(&temp_dir).unwrap();
+        let original = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n";
+        fs::write(temp_dir.path().join("Cargo.toml"), original).unwrap();
+        let patch = "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -1,3 +1,3 @@\n [package]\n name = \"demo\"\n-version = \"0.1.0\"\n+version = \"0.2.0\"\n";
+
+        let dry_run = router
+            .call_tool(
+                "apply_patch",
+                json!({"patch": patch, "dry_run": true}),
+                dummy_sender(),
+            )
+            .await
+            .unwrap();
+        let text = &dry_run[0].as_text().unwrap().text;
+        assert!(text.contains("Dry run, no files were changed"));
+        assert!(text.contains("3: version = \"0.2.0\""));
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("Cargo.toml")).unwrap(),
+            original
+        );
+
+        router
+            .call_tool("apply_patch", json!({"patch": patch}), dummy_sender())
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"demo\"\nversion = \"0.2.0\"\n"
+        );
+
+        // The same patch no longer applies, and the error says what the file has instead
+        let error = router
+            .call_tool("apply_patch", json!({"patch": patch}), dummy_sender())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains(
+            "line 3 is `version = \"0.2.0\"` but the hunk expects `version = \"0.1.0\"`"
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_apply_patch_stays_in_working_dir() {
+        let router = get_router().await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let working_dir = temp_dir.path().join("project");
+        fs::create_dir(&working_dir).unwrap();
+        fs::write(temp_dir.path().join("outside.txt"), "secret\n").unwrap();
+        std::env::set_current_dir(&working_dir).unwrap();
+
+        for path in ["../outside.txt", "sub/../../outside.txt"] {
+            let patch = format!(
+                "--- a/{path}\n+++ b/{path}\n@@ -1 +1 @@\n-secret\n+changed\n",
+                path = path
+            );
+            let error = router
+                .call_tool("apply_patch", json!({"patch": patch}), dummy_sender())
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("outside the working directory"));
+        }
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("outside.txt")).unwrap(),
+            "secret\n"
+        );
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_text_editor_descriptions() {
@@ -0,0 +1,527 @@
+//! Parsing unified diffs and applying them with fuzzy context matching, in the manner of
+//! `patch`: a hunk may apply away from the line its header names, and with up to `fuzz` lines
+//! of its leading and trailing context ignored.
+
+use std::fmt;
+
+/// The changes a diff makes to one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    /// Path before the change, `None` when the file is created
+    pub old_path: Option<String>,
+    /// Path after the change, `None` when the file is deleted
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// The path the patch applies to
+    pub fn path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// The hunk's `@@` line
+    pub header: String,
+    /// Line the hunk starts at in the original file, counted from 1, when the header says
+    pub old_start: Option<usize>,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+impl Hunk {
+    /// The lines the hunk expects to find
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    /// The lines the hunk leaves in their place
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Add(text) => Some(text.as_str()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect()
+    }
+
+    fn leading_context(&self) -> usize {
+        self.lines
+            .iter()
+            .take_while(|line| matches!(line, HunkLine::Context(_)))
+            .count()
+    }
+
+    fn trailing_context(&self) -> usize {
+        self.lines
+            .iter()
+            .rev()
+            .take_while(|line| matches!(line, HunkLine::Context(_)))
+            .count()
+    }
+}
+
+/// Why a diff couldn't be read
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Line of the diff, counted from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} of the patch: {}", self.line, self.message)
+    }
+}
+
+/// Read a unified diff, which may change several files
+pub fn parse(diff: &str) -> Result<Vec<FilePatch>, ParseError> {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ ")) {
+            patches.push(FilePatch {
+                old_path: diff_path(&line[4..]),
+                new_path: diff_path(&lines[i + 1][4..]),
+                hunks: Vec::new(),
+            });
+            i += 2;
+        } else if line.starts_with("@@") {
+            let Some(patch) = patches.last_mut() else {
+                return Err(ParseError {
+                    line: i + 1,
+                    message: "hunk before any '---' and '+++' file header".to_string(),
+                });
+            };
+            let mut hunk = Hunk {
+                header: line.to_string(),
+                old_start: old_start(line),
+                lines: Vec::new(),
+            };
+            i += 1;
+            while i < lines.len() && !starts_section(&lines, i) {
+                let line = lines[i];
+                match line.chars().next() {
+                    Some(' ') => hunk.lines.push(HunkLine::Context(line[1..].to_string())),
+                    Some('-') => hunk.lines.push(HunkLine::Remove(line[1..].to_string())),
+                    Some('+') => hunk.lines.push(HunkLine::Add(line[1..].to_string())),
+                    // "\ No newline at end of file"
+                    Some('\\') => {}
+                    // Editors and models often strip the space of an empty context line
+                    None => hunk.lines.push(HunkLine::Context(String::new())),
+                    Some(_) => {
+                        return Err(ParseError {
+                            line: i + 1,
+                            message: format!(
+                                "expected a line starting with ' ', '-' or '+' in {}, found '{}'",
+                                hunk.header, line
+                            ),
+                        })
+                    }
+                }
+                i += 1;
+            }
+            // Blank lines separating the hunk from what follows aren't part of it
+            while hunk.lines.last() == Some(&HunkLine::Context(String::new())) {
+                hunk.lines.pop();
+            }
+            patch.hunks.push(hunk);
+        } else {
+            // "diff --git", "index" and other lines between files
+            i += 1;
+        }
+    }
+
+    if patches.is_empty() {
+        return Err(ParseError {
+            line: 1,
+            message: "no '---' and '+++' file headers found".to_string(),
+        });
+    }
+    if let Some(patch) = patches.iter().find(|patch| patch.hunks.is_empty()) {
+        return Err(ParseError {
+            line: 1,
+            message: format!("no hunks for {}", patch.path()),
+        });
+    }
+    Ok(patches)
+}
+
+/// Whether line `i` starts a hunk or the next file
+fn starts_section(lines: &[&str], i: usize) -> bool {
+    let line = lines[i];
+    line.starts_with("@@")
+        || line.starts_with("diff ")
+        || (line.starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ ")))
+}
+
+/// The path in a `---` or `+++` line, without the `a/` or `b/` prefix git adds and the
+/// timestamp diff adds
+fn diff_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// The start line in `@@ -12,5 +12,6 @@`, if the header has one
+fn old_start(header: &str) -> Option<usize> {
+    let range = header
+        .split_whitespace()
+        .find(|part| part.starts_with('-'))?;
+    range[1..].split(',').next()?.parse().ok()
+}
+
+/// A hunk that applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedHunk {
+    /// Position of the hunk in the file's patch, counted from 1
+    pub hunk: usize,
+    /// Line of the patched file the hunk's changes start at, counted from 1
+    pub line: usize,
+    /// Lines away from where the header said the hunk goes
+    pub offset: isize,
+    /// Context lines ignored at each end of the hunk to make it apply
+    pub fuzz: usize,
+    /// Number of lines the hunk's changes take up in the patched file
+    pub new_len: usize,
+}
+
+/// A hunk that didn't apply, with what was found where it was expected
+#[derive(Debug, Clone, PartialEq)]
+pub struct HunkFailure {
+    /// Position of the hunk in the file's patch, counted from 1
+    pub hunk: usize,
+    pub header: String,
+    /// Line the hunk was expected at, counted from 1
+    pub expected_line: usize,
+    /// The first line that differed there: its line number, the hunk's line and the file's
+    pub mismatch: Option<(usize, String, Option<String>)>,
+    /// Where most of the hunk's lines match: the line it would start at and how many match
+    pub closest: Option<(usize, usize)>,
+    /// Number of lines the hunk expects to find
+    pub expected_len: usize,
+}
+
+impl fmt::Display for HunkFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Hunk {} ({}) failed at line {}",
+            self.hunk, self.header, self.expected_line
+        )?;
+        match &self.mismatch {
+            Some((line, expected, Some(found))) => write!(
+                f,
+                ": line {} is `{}` but the hunk expects `{}`",
+                line, found, expected
+            )?,
+            Some((line, expected, None)) => write!(
+                f,
+                ": the file ends before line {}, where the hunk expects `{}`",
+                line, expected
+            )?,
+            None => {}
+        }
+        if let Some((line, matching)) = self.closest {
+            write!(
+                f,
+                ". The closest match starts at line {}, where {} of its {} lines match",
+                line, matching, self.expected_len
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The result of applying a file's hunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Applied {
+    pub content: String,
+    pub applied: Vec<AppliedHunk>,
+    pub failures: Vec<HunkFailure>,
+}
+
+/// Apply `patch` to `original`, ignoring up to `max_fuzz` context lines at each end of a hunk.
+/// Hunks that don't apply are reported and left out; the others still apply.
+pub fn apply(original: &str, patch: &FilePatch, max_fuzz: usize) -> Applied {
+    let ends_with_newline = original.is_empty() || original.ends_with('\n');
+    let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+    let mut applied = Vec::new();
+    let mut failures = Vec::new();
+    // Lines earlier hunks added, less those they removed
+    let mut delta: isize = 0;
+    // Hunks apply in order, each after the end of the one before
+    let mut earliest = 0;
+
+    for (index, hunk) in patch.hunks.iter().enumerate() {
+        let old = hunk.old_lines();
+        let new = hunk.new_lines();
+        let expected = hunk
+            .old_start
+            .map(|start| {
+                // An empty old range names the line before the insertion
+                let start = if old.is_empty() {
+                    start
+                } else {
+                    start.saturating_sub(1)
+                };
+                (start as isize + delta).max(0) as usize
+            })
+            .unwrap_or(earliest)
+            .clamp(earliest, lines.len());
+
+        let found = (0..=max_fuzz).find_map(|fuzz| {
+            let lead = fuzz.min(hunk.leading_context());
+            let trail = fuzz.min(hunk.trailing_context());
+            if lead + trail > old.len() || (fuzz > 0 && lead + trail == old.len()) {
+                return None;
+            }
+            let wanted = &old[lead..old.len() - trail];
+            find(&lines, wanted, expected.saturating_sub(lead), earliest)
+                .map(|position| (position, fuzz, lead, trail))
+        });
+
+        match found {
+            Some((position, fuzz, lead, trail)) => {
+                let replacement: Vec<String> = new[lead..new.len() - trail]
+                    .iter()
+                    .map(|line| line.to_string())
+                    .collect();
+                let removed = old.len() - lead - trail;
+                let added = replacement.len();
+                lines.splice(position..position + removed, replacement);
+                applied.push(AppliedHunk {
+                    hunk: index + 1,
+                    line: position + 1,
+                    offset: position as isize - (expected + lead) as isize,
+                    fuzz,
+                    new_len: added,
+                });
+                delta += added as isize - removed as isize;
+                earliest = position + added;
+            }
+            None => failures.push(failure(index + 1, hunk, &old, &lines, expected)),
+        }
+    }
+
+    let mut content = lines.join("\n");
+    if ends_with_newline && !content.is_empty() {
+        content.push('\n');
+    }
+    Applied {
+        content,
+        applied,
+        failures,
+    }
+}
+
+/// Lines are compared without trailing whitespace, which models often get wrong
+fn same_line(a: &str, b: &str) -> bool {
+    a.trim_end() == b.trim_end()
+}
+
+fn matches_at(lines: &[String], wanted: &[&str], position: usize) -> bool {
+    position + wanted.len() <= lines.len()
+        && wanted
+            .iter()
+            .zip(&lines[position..])
+            .all(|(wanted, line)| same_line(wanted, line))
+}
+
+/// Where `wanted` appears at or after `earliest`, nearest to `expected` first
+fn find(lines: &[String], wanted: &[&str], expected: usize, earliest: usize) -> Option<usize> {
+    if wanted.is_empty() {
+        return Some(expected.clamp(earliest, lines.len()));
+    }
+    let last = lines.len().checked_sub(wanted.len())?;
+    let expected = expected.clamp(earliest, last.max(earliest));
+    (0..=lines.len()).find_map(|distance| {
+        let after = expected + distance;
+        let before = expected.checked_sub(distance).filter(|p| *p >= earliest);
+        [Some(after), before]
+            .into_iter()
+            .flatten()
+            .filter(|position| *position <= last)
+            .find(|position| matches_at(lines, wanted, *position))
+    })
+}
+
+fn failure(
+    hunk_number: usize,
+    hunk: &Hunk,
+    old: &[&str],
+    lines: &[String],
+    expected: usize,
+) -> HunkFailure {
+    let mismatch = old.iter().enumerate().find_map(|(i, wanted)| {
+        let found = lines.get(expected + i);
+        match found {
+            Some(found) if same_line(wanted, found) => None,
+            _ => Some((expected + i + 1, wanted.to_string(), found.cloned())),
+        }
+    });
+    let closest = (0..lines.len())
+        .map(|position| {
+            let matching = old
+                .iter()
+                .zip(lines.iter().skip(position))
+                .filter(|(wanted, line)| same_line(wanted, line))
+                .count();
+            (position, matching)
+        })
+        .filter(|(_, matching)| *matching > 0)
+        // Most matching lines, then nearest to where the hunk was expected
+        .max_by_key(|(position, matching)| {
+            (*matching, std::cmp::Reverse(position.abs_diff(expected)))
+        })
+        .map(|(position, matching)| (position + 1, matching));
+    HunkFailure {
+        hunk: hunk_number,
+        header: hunk.header.clone(),
+        expected_line: expected + 1,
+        mismatch,
+        closest,
+        expected_len: old.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str =
+        "fn main() {\n    let x = 1;\n    let y = 2;\n    println!(\"{}\", x + y);\n}\n";
+
+    fn single(diff: &str) -> FilePatch {
+        let mut patches = parse(diff).unwrap();
+        assert_eq!(patches.len(), 1);
+        patches.remove(0)
+    }
+
+    #[test]
+    fn test_clean_apply() {
+        let patch = single(
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,5 +1,5 @@\n fn main() {\n     let x = 1;\n-    let y = 2;\n+    let y = 3;\n     println!(\"{}\", x + y);\n }\n",
+        );
+        assert_eq!(patch.path(), "src/main.rs");
+
+        let result = apply(ORIGINAL, &patch, 0);
+
+        assert!(result.failures.is_empty());
+        assert_eq!(
+            result.content,
+            "fn main() {\n    let x = 1;\n    let y = 3;\n    println!(\"{}\", x + y);\n}\n"
+        );
+        assert_eq!(result.applied[0].offset, 0);
+        assert_eq!(result.applied[0].fuzz, 0);
+    }
+
+    #[test]
+    fn test_offset_apply() {
+        let original = format!("// header\n// more header\n\n{}", ORIGINAL);
+        let patch = single(
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -2,3 +2,4 @@\n     let x = 1;\n     let y = 2;\n+    let z = 3;\n     println!(\"{}\", x + y);\n",
+        );
+
+        let result = apply(&original, &patch, 0);
+
+        assert!(result.failures.is_empty());
+        assert!(result.content.contains("    let y = 2;\n    let z = 3;\n"));
+        assert_eq!(result.applied[0].line, 5);
+        assert_eq!(result.applied[0].offset, 3);
+    }
+
+    #[test]
+    fn test_fuzz_ignores_stale_context() {
+        let patch = single(
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,4 +1,4 @@\n fn main() {\n     let x = 1;\n-    let y = 2;\n+    let y = 3;\n     println!(\"{}\", x * y);\n",
+        );
+
+        assert_eq!(apply(ORIGINAL, &patch, 0).failures.len(), 1);
+        let result = apply(ORIGINAL, &patch, 1);
+        assert!(result.failures.is_empty());
+        assert_eq!(result.applied[0].fuzz, 1);
+        assert!(result
+            .content
+            .contains("let y = 3;\n    println!(\"{}\", x + y);"));
+    }
+
+    #[test]
+    fn test_conflicting_hunk_is_reported() {
+        let patch = single(
+            "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,2 +1,2 @@\n-fn main() {\n+fn run() {\n     let x = 1;\n@@ -3,2 +3,2 @@\n-    let y = 20;\n+    let y = 30;\n     println!(\"{}\", x + y);\n",
+        );
+
+        let result = apply(ORIGINAL, &patch, 2);
+
+        assert_eq!(result.applied.len(), 1);
+        assert!(result.content.starts_with("fn run() {\n"));
+        assert_eq!(result.failures.len(), 1);
+        let failure = &result.failures[0];
+        assert_eq!(failure.hunk, 2);
+        assert_eq!(failure.expected_line, 3);
+        assert_eq!(
+            failure.mismatch,
+            Some((
+                3,
+                "    let y = 20;".to_string(),
+                Some("    let y = 2;".to_string())
+            ))
+        );
+        assert_eq!(failure.closest, Some((3, 1)));
+        assert!(failure
+            .to_string()
+            .contains("line 3 is `    let y = 2;` but the hunk expects `    let y = 20;`"));
+    }
+
+    #[test]
+    fn test_new_file_and_several_files() {
+        let patches = parse(
+            "diff --git a/README.md b/README.md\nnew file mode 100644\n--- /dev/null\n+++ b/README.md\n@@ -0,0 +1,2 @@\n+# Project\n+\n+Hello\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -5 +5,2 @@\n }\n+// end\n",
+        )
+        .unwrap();
+
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].old_path, None);
+        assert_eq!(apply("", &patches[0], 0).content, "# Project\n\nHello\n");
+        assert_eq!(
+            apply(ORIGINAL, &patches[1], 0).content,
+            format!("{}// end\n", ORIGINAL)
+        );
+    }
+
+    #[test]
+    fn test_malformed_patches() {
+        assert!(parse("just some text").is_err());
+        assert!(parse("@@ -1 +1 @@\n-a\n+b\n").is_err());
+        let error = parse("--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n*b\n").unwrap_err();
+        assert_eq!(error.line, 5);
+    }
+}
@@ -0,0 +1,443 @@
+//! Background processes started with `process_start`, such as dev servers that the agent needs
+//! to keep running while it works. Their output is kept in fixed-size ring buffers so a chatty
+//! process can't use unbounded memory.
+//!
+//! Processes belong to the extension instance, and so to the session that started it. They are
+//! spawned in the extension's process group and with `kill_on_drop`, so they are killed when the
+//! extension shuts down or is killed with its process group at the end of the session.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mcp_core::handler::ToolError;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+
+use super::shell::get_shell_config;
+
+/// Most background processes that may run at once
+pub const MAX_PROCESSES_ENV: &str = "GOOSE_MAX_BACKGROUND_PROCESSES";
+/// Most bytes of output buffered across all background processes
+pub const OUTPUT_LIMIT_ENV: &str = "GOOSE_BACKGROUND_OUTPUT_LIMIT";
+
+const DEFAULT_MAX_PROCESSES: usize = 8;
+const DEFAULT_OUTPUT_LIMIT: usize = 4 * 1024 * 1024;
+
+/// How long `kill` waits for a killed process to exit
+const KILL_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessLimits {
+    pub max_processes: usize,
+    pub output_limit: usize,
+}
+
+impl Default for ProcessLimits {
+    fn default() -> Self {
+        Self {
+            max_processes: DEFAULT_MAX_PROCESSES,
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+        }
+    }
+}
+
+impl ProcessLimits {
+    /// The limits set in the environment, with defaults for those unset or invalid
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: usize| match std::env::var(name) {
+            Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                tracing::warn!("Ignoring {}={}, expected a whole number", name, value);
+                default
+            }),
+            Err(_) => default,
+        };
+        Self {
+            max_processes: read(MAX_PROCESSES_ENV, DEFAULT_MAX_PROCESSES).max(1),
+            output_limit: read(OUTPUT_LIMIT_ENV, DEFAULT_OUTPUT_LIMIT),
+        }
+    }
+
+    /// Size of each stdout and stderr buffer, so that a full table stays within the output limit
+    fn buffer_capacity(&self) -> usize {
+        self.output_limit / (self.max_processes * 2)
+    }
+}
+
+/// The last `capacity` bytes written to it
+#[derive(Debug)]
+struct RingBuffer {
+    bytes: VecDeque<u8>,
+    capacity: usize,
+    /// Bytes dropped from the front to stay within the capacity
+    dropped: usize,
+}
+
+impl RingBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            bytes: VecDeque::new(),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.bytes.extend(data);
+        if self.bytes.len() > self.capacity {
+            let excess = self.bytes.len() - self.capacity;
+            self.bytes.drain(..excess);
+            self.dropped += excess;
+        }
+    }
+
+    /// The last `lines` lines, leaving out a partial first line when earlier bytes were dropped
+    fn tail(&self, lines: usize) -> String {
+        let (front, back) = self.bytes.as_slices();
+        let text = String::from_utf8_lossy(&[front, back].concat()).into_owned();
+        let mut all: Vec<&str> = text.lines().collect();
+        if self.dropped > 0 && !all.is_empty() {
+            all.remove(0);
+        }
+        all[all.len().saturating_sub(lines)..].join("\n")
+    }
+}
+
+/// Which output of a process to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+    Both,
+}
+
+impl OutputStream {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "stdout" => Some(Self::Stdout),
+            "stderr" => Some(Self::Stderr),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    /// Exited on its own, with its exit code unless a signal ended it
+    Exited(Option<i32>),
+    Killed,
+}
+
+impl fmt::Display for ProcessState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessState::Running => write!(f, "running"),
+            ProcessState::Exited(Some(code)) => write!(f, "exited with code {}", code),
+            ProcessState::Exited(None) => write!(f, "ended by a signal"),
+            ProcessState::Killed => write!(f, "killed"),
+        }
+    }
+}
+
+/// A snapshot of a background process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessStatus {
+    pub id: String,
+    pub pid: Option<u32>,
+    pub command: String,
+    pub state: ProcessState,
+    pub runtime: Duration,
+}
+
+impl fmt::Display for ProcessStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.id, self.state)?;
+        if let Some(pid) = self.pid {
+            write!(f, ", pid {}", pid)?;
+        }
+        write!(
+            f,
+            ", for {}s, command `{}`",
+            self.runtime.as_secs(),
+            self.command
+        )
+    }
+}
+
+struct BackgroundProcess {
+    command: String,
+    pid: Option<u32>,
+    started: Instant,
+    child: Child,
+    stdout: Arc<Mutex<RingBuffer>>,
+    stderr: Arc<Mutex<RingBuffer>>,
+    /// Set once the process has ended, with when it did
+    ended: Option<(ProcessState, Instant)>,
+}
+
+impl BackgroundProcess {
+    /// Check whether the process has exited since it was last looked at
+    fn poll(&mut self) {
+        if self.ended.is_some() {
+            return;
+        }
+        if let Ok(Some(status)) = self.child.try_wait() {
+            self.ended = Some((ProcessState::Exited(status.code()), Instant::now()));
+        }
+    }
+
+    fn status(&self, id: &str) -> ProcessStatus {
+        let (state, until) = self
+            .ended
+            .unwrap_or_else(|| (ProcessState::Running, Instant::now()));
+        ProcessStatus {
+            id: id.to_string(),
+            pid: self.pid,
+            command: self.command.clone(),
+            state,
+            runtime: until.duration_since(self.started),
+        }
+    }
+}
+
+/// The background processes of one extension instance
+pub struct ProcessTable {
+    limits: ProcessLimits,
+    next_id: AtomicUsize,
+    processes: Mutex<HashMap<String, BackgroundProcess>>,
+}
+
+impl ProcessTable {
+    pub fn new(limits: ProcessLimits) -> Self {
+        Self {
+            limits,
+            next_id: AtomicUsize::new(1),
+            processes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, BackgroundProcess>> {
+        self.processes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Run `command` in the shell without waiting for it, returning its handle
+    pub fn start(&self, command: &str) -> Result<String, ToolError> {
+        let mut processes = self.lock();
+        processes.values_mut().for_each(BackgroundProcess::poll);
+        let running = processes
+            .values()
+            .filter(|process| process.ended.is_none())
+            .count();
+        if running >= self.limits.max_processes {
+            return Err(ToolError::ExecutionError(format!(
+                "{} background processes are already running, which is the most allowed ({}). \
+                Kill one you no longer need with process_kill first.",
+                running, MAX_PROCESSES_ENV
+            )));
+        }
+        // Forget the oldest ended processes so the buffers of the whole table stay in the limit
+        while processes.len() >= self.limits.max_processes {
+            let Some(oldest) = processes
+                .iter()
+                .filter(|(_, process)| process.ended.is_some())
+                .min_by_key(|(_, process)| process.started)
+                .map(|(id, _)| id.clone())
+            else {
+                break;
+            };
+            processes.remove(&oldest);
+        }
+
+        let shell_config = get_shell_config();
+        let mut child = Command::new(&shell_config.executable)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .args(&shell_config.args)
+            .arg(command)
+            .spawn()
+            .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+
+        let capacity = self.limits.buffer_capacity();
+        let stdout = Arc::new(Mutex::new(RingBuffer::new(capacity)));
+        let stderr = Arc::new(Mutex::new(RingBuffer::new(capacity)));
+        if let Some(pipe) = child.stdout.take() {
+            tokio::spawn(capture(pipe, Arc::clone(&stdout)));
+        }
+        if let Some(pipe) = child.stderr.take() {
+            tokio::spawn(capture(pipe, Arc::clone(&stderr)));
+        }
+
+        let id = format!("p{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        processes.insert(
+            id.clone(),
+            BackgroundProcess {
+                command: command.to_string(),
+                pid: child.id(),
+                started: Instant::now(),
+                child,
+                stdout,
+                stderr,
+                ended: None,
+            },
+        );
+        Ok(id)
+    }
+
+    /// The status of the process `id`, or of every process when `id` is `None`
+    pub fn status(&self, id: Option<&str>) -> Result<Vec<ProcessStatus>, ToolError> {
+        let mut processes = self.lock();
+        match id {
+            Some(id) => {
+                let process = processes.get_mut(id).ok_or_else(|| unknown_process(id))?;
+                process.poll();
+                Ok(vec![process.status(id)])
+            }
+            None => {
+                let mut statuses: Vec<ProcessStatus> = processes
+                    .iter_mut()
+                    .map(|(id, process)| {
+                        process.poll();
+                        process.status(id)
+                    })
+                    .collect();
+                statuses.sort_by_key(|status| status.runtime);
+                statuses.reverse();
+                Ok(statuses)
+            }
+        }
+    }
+
+    /// The last `lines` lines of output of the process `id`
+    pub fn logs(
+        &self,
+        id: &str,
+        stream: OutputStream,
+        lines: usize,
+    ) -> Result<(ProcessStatus, String), ToolError> {
+        let mut processes = self.lock();
+        let process = processes.get_mut(id).ok_or_else(|| unknown_process(id))?;
+        process.poll();
+        let tail = |buffer: &Arc<Mutex<RingBuffer>>| {
+            buffer
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .tail(lines)
+        };
+        let output = match stream {
+            OutputStream::Stdout => tail(&process.stdout),
+            OutputStream::Stderr => tail(&process.stderr),
+            OutputStream::Both => format!(
+                "stdout:\n{}\n\nstderr:\n{}",
+                tail(&process.stdout),
+                tail(&process.stderr)
+            ),
+        };
+        Ok((process.status(id), output))
+    }
+
+    /// Kill the process `id` and wait briefly for it to exit
+    pub async fn kill(&self, id: &str) -> Result<ProcessStatus, ToolError> {
+        {
+            let mut processes = self.lock();
+            let process = processes.get_mut(id).ok_or_else(|| unknown_process(id))?;
+            process.poll();
+            if process.ended.is_some() {
+                return Ok(process.status(id));
+            }
+            process
+                .child
+                .start_kill()
+                .map_err(|e| ToolError::ExecutionError(format!("Failed to kill {}: {}", id, e)))?;
+        }
+
+        let deadline = Instant::now() + KILL_TIMEOUT;
+        loop {
+            {
+                let mut processes = self.lock();
+                let process = processes.get_mut(id).ok_or_else(|| unknown_process(id))?;
+                if let Ok(Some(_)) = process.child.try_wait() {
+                    process.ended = Some((ProcessState::Killed, Instant::now()));
+                    return Ok(process.status(id));
+                }
+                if Instant::now() >= deadline {
+                    return Err(ToolError::ExecutionError(format!(
+                        "{} was sent a kill signal but is still running",
+                        id
+                    )));
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+fn unknown_process(id: &str) -> ToolError {
+    ToolError::InvalidParameters(format!(
+        "No background process {}, use process_status to list them",
+        id
+    ))
+}
+
+async fn capture(mut pipe: impl AsyncRead + Unpin, buffer: Arc<Mutex<RingBuffer>>) {
+    let mut chunk = [0u8; 8192];
+    while let Ok(read) = pipe.read(&mut chunk).await {
+        if read == 0 {
+            break;
+        }
+        buffer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(&chunk[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer_keeps_the_end() {
+        let mut buffer = RingBuffer::new(16);
+        buffer.push(b"first line\nsecond line\n");
+        buffer.push(b"third\n");
+
+        // "first line" and part of "second line" were dropped, and the partial line is left out
+        assert_eq!(buffer.bytes.len(), 16);
+        assert_eq!(buffer.tail(10), "third");
+
+        let mut buffer = RingBuffer::new(1024);
+        buffer.push(b"a\nb\nc\n");
+        assert_eq!(buffer.tail(2), "b\nc");
+    }
+
+    #[cfg(not(windows))]
+    #[tokio::test]
+    async fn test_limits_running_processes() {
+        let table = ProcessTable::new(ProcessLimits {
+            max_processes: 1,
+            output_limit: 1024,
+        });
+        let first = table.start("sleep 30").unwrap();
+        assert!(table.start("sleep 30").is_err());
+
+        assert_eq!(
+            table.kill(&first).await.unwrap().state,
+            ProcessState::Killed
+        );
+        let second = table.start("echo done").unwrap();
+
+        // The killed process was forgotten to make room
+        let statuses = table.status(None).unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].id, second);
+    }
+}
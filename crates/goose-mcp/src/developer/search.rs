@@ -0,0 +1,326 @@
+//! Regex search over the files of a directory for the `search_code` tool, skipping what
+//! `.gitignore` and friends ignore the way ripgrep does.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use mcp_core::handler::ToolError;
+use regex::{Regex, RegexBuilder};
+
+/// Files larger than this are skipped, they are rarely source code
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+/// Matched and context lines are cut to this many characters, for minified files
+const MAX_LINE_CHARS: usize = 300;
+
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub pattern: String,
+    /// Globs files must match, or must not match when they start with `!`
+    pub globs: Vec<String>,
+    pub case_sensitive: bool,
+    /// Lines shown before and after each match
+    pub context: usize,
+    pub max_results: usize,
+    /// Search files that `.gitignore`, `.ignore` and the like ignore, and hidden files
+    pub include_ignored: bool,
+    /// Most bytes of output, matches past it are left out
+    pub max_output_bytes: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    /// Matches grouped by file, formatted like `rg -n` output
+    pub output: String,
+    pub matches: usize,
+    pub files_with_matches: usize,
+    /// Whether the search stopped at `max_results` or `max_output_bytes`
+    pub truncated: bool,
+}
+
+/// Search the files under `root`, leaving out those `skip` says to. Paths in the output are
+/// relative to `base`.
+pub fn search(
+    root: &Path,
+    base: &Path,
+    options: &SearchOptions,
+    skip: impl Fn(&Path) -> bool,
+) -> Result<SearchResults, ToolError> {
+    let regex = RegexBuilder::new(&options.pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| ToolError::InvalidParameters(format!("Invalid pattern: {}", e)))?;
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.globs {
+        overrides
+            .add(glob)
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid glob '{}': {}", glob, e)))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| ToolError::InvalidParameters(format!("Invalid globs: {}", e)))?;
+
+    let respect_ignore = !options.include_ignored;
+    let walker = WalkBuilder::new(root)
+        .overrides(overrides)
+        .hidden(respect_ignore)
+        .ignore(respect_ignore)
+        .git_ignore(respect_ignore)
+        .git_global(respect_ignore)
+        .git_exclude(respect_ignore)
+        .parents(respect_ignore)
+        .require_git(false)
+        .follow_links(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+
+    let mut results = SearchResults::default();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Error reading search entry: {}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|t| t.is_file()) || skip(entry.path()) {
+            continue;
+        }
+        if entry.metadata().map_or(true, |m| m.len() > MAX_FILE_BYTES) {
+            continue;
+        }
+        // Binary files and files in other encodings are skipped
+        let Ok(content) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        if content.contains('\0') {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(base)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| PathBuf::from(entry.path()));
+        if !search_file(&relative, &content, &regex, options, &mut results) {
+            results.truncated = true;
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Add the matches of one file to `results`, returning false once a limit is reached
+fn search_file(
+    path: &Path,
+    content: &str,
+    regex: &Regex,
+    options: &SearchOptions,
+    results: &mut SearchResults,
+) -> bool {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut matched: Vec<usize> = Vec::new();
+    let mut complete = true;
+    for (index, line) in lines.iter().enumerate() {
+        if regex.is_match(line) {
+            if results.matches + matched.len() == options.max_results {
+                complete = false;
+                break;
+            }
+            matched.push(index);
+        }
+    }
+    if matched.is_empty() {
+        return complete;
+    }
+
+    // Overlapping or touching context is shown once, other groups are separated by `--`
+    let mut block = String::new();
+    let mut last_shown: Option<usize> = None;
+    for &index in &matched {
+        let start = index.saturating_sub(options.context);
+        let end = (index + options.context).min(lines.len() - 1);
+        let start = match last_shown {
+            Some(last) if start <= last + 1 => last + 1,
+            Some(_) if options.context > 0 => {
+                block.push_str("--\n");
+                start
+            }
+            _ => start,
+        };
+        for (offset, line) in lines[start..=end].iter().enumerate() {
+            let number = start + offset;
+            let separator = if matched.binary_search(&number).is_ok() {
+                ':'
+            } else {
+                '-'
+            };
+            let _ = writeln!(
+                block,
+                "{}{}{}{}{}",
+                path.display(),
+                separator,
+                number + 1,
+                separator,
+                shorten(line)
+            );
+        }
+        last_shown = Some(end);
+    }
+
+    if !results.output.is_empty() {
+        block.insert_str(0, "\n");
+    }
+    if results.output.len() + block.len() > options.max_output_bytes {
+        return false;
+    }
+    results.output.push_str(&block);
+    results.matches += matched.len();
+    results.files_with_matches += 1;
+    complete
+}
+
+fn shorten(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((cut, _)) => format!("{}... [line cut]", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(
+            root.join("src/main.rs"),
+            "fn main() {\n    let config = Config::load();\n    run(config);\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/nested/config.rs"),
+            "pub struct Config;\n\nimpl Config {\n    pub fn load() -> Self {\n        Config\n    }\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/notes.md"),
+            "The config is loaded at startup\n",
+        )
+        .unwrap();
+        fs::write(root.join("target/generated.rs"), "Config::load()\n").unwrap();
+        fs::write(root.join("src/blob.bin"), b"Config\0\x01\x02").unwrap();
+        dir
+    }
+
+    fn options(pattern: &str) -> SearchOptions {
+        SearchOptions {
+            pattern: pattern.to_string(),
+            globs: Vec::new(),
+            case_sensitive: true,
+            context: 0,
+            max_results: 100,
+            include_ignored: false,
+            max_output_bytes: 64 * 1024,
+        }
+    }
+
+    #[test]
+    fn test_search_respects_gitignore_and_skips_binaries() {
+        let dir = fixture();
+
+        let results = search(dir.path(), dir.path(), &options(r"Config::load"), |_| false).unwrap();
+        assert_eq!(
+            results.output,
+            "src/main.rs:2:    let config = Config::load();\n"
+        );
+
+        let results = search(
+            dir.path(),
+            dir.path(),
+            &SearchOptions {
+                include_ignored: true,
+                ..options(r"Config::load")
+            },
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(results.files_with_matches, 2);
+        assert!(results
+            .output
+            .contains("target/generated.rs:1:Config::load()"));
+    }
+
+    #[test]
+    fn test_search_globs_case_and_context() {
+        let dir = fixture();
+
+        let results = search(
+            dir.path(),
+            dir.path(),
+            &SearchOptions {
+                globs: vec!["*.rs".to_string()],
+                case_sensitive: false,
+                ..options("pub fn|config is")
+            },
+            |_| false,
+        )
+        .unwrap();
+        // notes.md doesn't match the glob
+        assert_eq!(
+            results.output,
+            "src/nested/config.rs:4:    pub fn load() -> Self {\n"
+        );
+
+        let results = search(
+            dir.path(),
+            dir.path(),
+            &SearchOptions {
+                context: 1,
+                ..options("Config")
+            },
+            |path| path.ends_with("main.rs"),
+        )
+        .unwrap();
+        assert_eq!(
+            results.output,
+            "src/nested/config.rs:1:pub struct Config;\n\
+             src/nested/config.rs-2-\n\
+             src/nested/config.rs:3:impl Config {\n\
+             src/nested/config.rs-4-    pub fn load() -> Self {\n\
+             src/nested/config.rs:5:        Config\n\
+             src/nested/config.rs-6-    }\n"
+        );
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let dir = fixture();
+
+        let results = search(
+            dir.path(),
+            dir.path(),
+            &SearchOptions {
+                max_results: 2,
+                ..options("Config")
+            },
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(results.matches, 2);
+        assert!(results.truncated);
+
+        assert!(matches!(
+            search(dir.path(), dir.path(), &options("(unclosed"), |_| false),
+            Err(ToolError::InvalidParameters(_))
+        ));
+    }
+}
@@ -1,11 +1,14 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+use crate::config_watcher;
 use crate::configuration;
+use crate::request_logging::{self, RequestLogger};
 use crate::state;
 use anyhow::Result;
 use etcetera::{choose_app_strategy, AppStrategy};
 use goose::agents::Agent;
-use goose::config::APP_STRATEGY;
+use goose::config::{Config, APP_STRATEGY};
 use goose::scheduler_factory::SchedulerFactory;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
@@ -45,15 +48,41 @@ pub async fn run() -> Result<()> {
     // NEW: Provide scheduler access to the agent
     agent_ref.set_scheduler(scheduler_instance).await;
 
+    // Pick up edits to config.yaml while running. Values are only cached once the file is
+    // watched, so without a watcher every lookup keeps reading the file.
+    let _config_watcher = match config_watcher::watch_config(app_state.clone()) {
+        Ok(watcher) => {
+            if let Err(e) = Config::global().reload() {
+                tracing::warn!("Failed to load config for watching: {}", e);
+            }
+            Some(watcher)
+        }
+        Err(e) => {
+            tracing::warn!("Failed to watch the config file for changes: {}", e);
+            None
+        }
+    };
+
+    crate::routes::events::spawn_activity_monitor(app_state.clone());
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let app = crate::routes::configure(app_state).layer(cors);
+    let app = crate::routes::configure(app_state)
+        .layer(axum::middleware::from_fn_with_state(
+            RequestLogger::from_config(),
+            request_logging::log_requests,
+        ))
+        .layer(cors);
 
     let listener = tokio::net::TcpListener::bind(settings.socket_addr()).await?;
     info!("listening on {}", listener.local_addr()?);
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use goose::config::Config;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::state::AppState;
+
+/// Editors often save in several steps, so changes this close together are reloaded once
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Reload the config file into `state` whenever it changes on disk. Watching stops when the
+/// returned watcher is dropped.
+pub fn watch_config(state: Arc<AppState>) -> notify::Result<RecommendedWatcher> {
+    let config_path = PathBuf::from(Config::global().path());
+    let file_name = config_path.file_name().map(|name| name.to_os_string());
+    let dir = config_path
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_default();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+            Ok(event)
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                ) && event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == file_name.as_deref()) =>
+            {
+                let _ = tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Config file watcher error: {}", e),
+        })?;
+    // Saving replaces the file with a renamed temporary file, which ends a watch on the file
+    // itself, so the directory is watched instead
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            match state.reload_config().await {
+                Ok(reload) if !reload.changed_keys.is_empty() => {
+                    tracing::info!("Reloaded config, changed keys: {:?}", reload.changed_keys);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!(
+                    "Config file changed but could not be loaded, keeping the previous values: {}",
+                    e
+                ),
+            }
+        }
+    });
+
+    Ok(watcher)
+}
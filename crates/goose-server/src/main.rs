@@ -1,8 +1,10 @@
 mod commands;
+mod config_watcher;
 mod configuration;
 mod error;
 mod logging;
 mod openapi;
+mod request_logging;
 mod routes;
 mod state;
 
@@ -1,16 +1,19 @@
 use goose::agents::extension::Envs;
 use goose::agents::extension::ToolInfo;
 use goose::agents::ExtensionConfig;
-use goose::config::permission::PermissionLevel;
+use goose::config::permission::{PermissionLevel, PermissionRule, ShellCommandPatterns};
 use goose::config::ExtensionEntry;
 use goose::message::{
-    ContextLengthExceeded, FrontendToolRequest, Message, MessageContent, RedactedThinkingContent,
-    SummarizationRequested, ThinkingContent, ToolConfirmationRequest, ToolRequest, ToolResponse,
+    AttachmentReference, Citation, CitationsContent, ContextLengthExceeded, FrontendToolRequest,
+    Message, MessageContent, RedactedThinkingContent, ServerToolUse, SummarizationRequested,
+    ThinkingContent, ToolConfirmationRequest, ToolRequest, ToolResponse,
 };
 use goose::permission::permission_confirmation::PrincipalType;
-use goose::providers::base::{ConfigKey, ModelInfo, ProviderMetadata};
+use goose::providers::base::{
+    ConfigKey, DeviceCodeSettings, ModelInfo, ProviderMetadata, SignupMethod,
+};
 use goose::session::info::SessionInfo;
-use goose::session::SessionMetadata;
+use goose::session::{ModelChangeRecord, SessionMetadata};
 use rmcp::model::{
     Annotations, Content, EmbeddedResource, ImageContent, RawEmbeddedResource, RawImageContent,
     RawTextContent, ResourceContents, Role, TextContent, Tool, ToolAnnotations,
@@ -356,8 +359,16 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
     paths(
         super::routes::config_management::backup_config,
         super::routes::config_management::recover_config,
+        super::routes::config_management::export_config,
+        super::routes::config_management::import_config,
         super::routes::config_management::validate_config,
         super::routes::config_management::init_config,
+        super::routes::diagnostics::health,
+        super::routes::diagnostics::diagnostics_bundle,
+        super::routes::limits::get_limits,
+        super::routes::batch::start_batch,
+        super::routes::batch::get_batch,
+        super::routes::batch::cancel_batch,
         super::routes::config_management::upsert_config,
         super::routes::config_management::remove_config,
         super::routes::config_management::read_config,
@@ -365,14 +376,56 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         super::routes::config_management::remove_extension,
         super::routes::config_management::get_extensions,
         super::routes::config_management::read_all_config,
+        super::routes::config_management::get_profiles,
+        super::routes::config_management::set_profile,
+        super::routes::config_management::reload_config,
+        super::routes::config_management::update_model_settings,
+        super::routes::config_management::set_active_model,
+        super::routes::config_management::config_events,
+        super::routes::config_management::migrate_secrets,
         super::routes::config_management::providers,
+        super::routes::config_management::provider_models,
         super::routes::config_management::upsert_permissions,
+        super::routes::config_management::get_permission_rules,
         super::routes::agent::get_tools,
+        super::routes::agent::update_tool,
         super::routes::agent::add_sub_recipes,
+        super::routes::agent::extend_prompt,
+        super::routes::agent::get_system_prompt,
+        super::routes::agent::list_agent_extensions,
+        super::routes::agent::add_agent_extension,
+        super::routes::agent::remove_agent_extension,
+        super::routes::agent::list_prompts,
+        super::routes::agent::list_personas,
+        super::routes::agent::list_extension_resources,
+        super::routes::agent::read_extension_resource,
+        super::routes::agent::subscribe_extension_resource,
+        super::routes::agent::unsubscribe_extension_resource,
+        super::routes::agent::authorize_extension,
+        super::routes::agent::get_extension_logs,
+        super::routes::agent::register_frontend_tools,
+        super::routes::agent::unregister_frontend_tools,
         super::routes::reply::confirm_permission,
+        super::routes::reply::confirm_permission_batch,
+        super::routes::reply::get_pending_confirmations,
+        super::routes::audit::get_audit_log,
+        super::routes::events::server_events,
+        super::routes::auth::list_tokens,
+        super::routes::auth::mint_token,
+        super::routes::auth::revoke_token,
         super::routes::context::manage_context,
+        super::routes::context::check_context,
         super::routes::session::list_sessions,
         super::routes::session::get_session_history,
+        super::routes::session::get_related_sessions,
+        super::routes::session::edit_session_message,
+        super::routes::session::delete_session_message,
+        super::routes::session::cleanup_sessions,
+        super::routes::session::upload_session_attachment,
+        super::routes::session::get_session_artifact,
+        super::routes::session::list_session_deliveries,
+        super::routes::session::list_provider_calls,
+        super::routes::session::replay_session,
         super::routes::schedule::create_schedule,
         super::routes::schedule::list_schedules,
         super::routes::schedule::delete_schedule,
@@ -383,25 +436,110 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         super::routes::schedule::kill_running_job,
         super::routes::schedule::inspect_running_job,
         super::routes::schedule::sessions_handler,
+        super::routes::schedule::validate_schedule,
+        super::routes::schedule::runs_handler,
+        super::routes::schedule::get_global_conditions,
+        super::routes::schedule::set_global_conditions,
         super::routes::recipe::create_recipe,
         super::routes::recipe::encode_recipe,
-        super::routes::recipe::decode_recipe
+        super::routes::recipe::decode_recipe,
+        super::routes::recipe::validate_recipe_handler,
+        super::routes::recipe::render_recipe,
+        super::routes::recipe::dry_run_recipe_handler,
+        super::routes::recipe::import_recipe_handler,
+        super::routes::recipe::list_recipes,
+        super::routes::recipe::get_recipe,
+        super::routes::recipe::save_recipe,
+        super::routes::recipe::delete_recipe
     ),
     components(schemas(
+        super::routes::diagnostics::HealthReport,
+        super::routes::diagnostics::HealthChecks,
+        super::routes::limits::LimitsReport,
+        super::routes::limits::RateUsage,
+        super::routes::limits::TokenUsage,
+        super::routes::batch::BatchRequest,
+        super::routes::batch::BatchReport,
+        super::routes::batch::BatchItemResult,
+        super::routes::batch::BatchItemStatus,
+        super::routes::batch::BatchStatus,
+        super::state::ActiveStream,
         super::routes::config_management::UpsertConfigQuery,
         super::routes::config_management::ConfigKeyQuery,
         super::routes::config_management::ConfigResponse,
+        super::routes::config_management::ReadAllConfigQuery,
+        super::routes::config_management::SetProfileRequest,
+        super::routes::config_management::UpdateModelSettingsRequest,
+        super::routes::config_management::ModelSettingsResponse,
+        super::routes::config_management::SetActiveModelRequest,
+        super::routes::config_management::ActiveModelResponse,
+        super::routes::config_management::ProfilesResponse,
+        super::routes::config_management::ConfigEvent,
+        super::routes::config_management::ConfigValidationResponse,
+        goose::config::ConfigIssue,
+        goose::config::ConfigIssueSeverity,
+        super::routes::config_management::MigrateSecretsRequest,
+        goose::config::SecretMigrationReport,
+        goose::config::SecretMigrationFailure,
+        super::routes::config_management::ImportConfigRequest,
+        goose::config::ConfigBundle,
+        goose::config::EncryptedSecrets,
+        goose::config::MergeStrategy,
+        goose::config::ConfigImportReport,
+        goose::config::ConfigReload,
         super::routes::config_management::ProvidersResponse,
         super::routes::config_management::ProviderDetails,
+        super::routes::config_management::ProviderModel,
+        goose::providers::capabilities::ModelCapabilities,
         super::routes::config_management::ExtensionResponse,
         super::routes::config_management::ExtensionQuery,
         super::routes::config_management::ToolPermission,
         super::routes::config_management::UpsertPermissionsQuery,
+        super::routes::config_management::PermissionRulesResponse,
         super::routes::reply::PermissionConfirmationRequest,
+        super::routes::reply::ToolApprovalBatchRequest,
+        super::routes::reply::ToolApprovalEntry,
+        super::routes::reply::ToolApprovalStatus,
+        super::routes::reply::ToolApprovalResult,
+        super::routes::reply::ToolApprovalBatchResponse,
+        super::routes::reply::PendingConfirmationsResponse,
+        goose::agents::PendingConfirmation,
+        goose::agents::ReplyExecutionMode,
+        super::routes::audit::AuditQuery,
+        super::routes::audit::AuditLogResponse,
+        super::routes::events::ServerEvent,
+        super::routes::auth::Scope,
+        super::routes::auth::ApiToken,
+        super::routes::auth::MintTokenRequest,
+        super::routes::auth::MintTokenResponse,
+        goose::audit::AuditEntry,
+        goose::audit::AuditEvent,
+        goose::audit::AuditDecision,
+        goose::audit::DecisionSource,
+        goose::audit::AuditStatus,
         super::routes::context::ContextManageRequest,
         super::routes::context::ContextManageResponse,
+        super::routes::context::ContextCheckRequest,
+        super::routes::context::ContextCheckResponse,
+        super::routes::context::ContextStrategy,
         super::routes::session::SessionListResponse,
         super::routes::session::SessionHistoryResponse,
+        super::routes::session::SessionCleanupQuery,
+        super::routes::session::EditMessageRequest,
+        super::routes::session::DeleteMessageQuery,
+        super::routes::session::SessionConflictResponse,
+        super::routes::session::ReplayRequest,
+        super::routes::session::ReplayResponse,
+        super::routes::session::SessionListQuery,
+        goose::session::RelatedSessions,
+        goose::session::RelatedSession,
+        goose::session::SessionOrigin,
+        goose::session::ToolUsageSummary,
+        goose::session::ToolUsage,
+        goose::session::CleanupReport,
+        goose::session::AttachmentInfo,
+        goose::session::ImageReference,
+        goose::session::RemovedSession,
         Message,
         MessageContent,
         ContentSchema,
@@ -419,11 +557,19 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         ThinkingContent,
         RedactedThinkingContent,
         FrontendToolRequest,
+        AttachmentReference,
+        ServerToolUse,
+        CitationsContent,
+        Citation,
         ResourceContentsSchema,
         ContextLengthExceeded,
+        goose::context_mgmt::breakdown::ContextBreakdown,
+        goose::context_mgmt::breakdown::MessageTokens,
         SummarizationRequested,
         RoleSchema,
         ProviderMetadata,
+        SignupMethod,
+        DeviceCodeSettings,
         ExtensionEntry,
         ExtensionConfig,
         ConfigKey,
@@ -432,19 +578,37 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         ToolAnnotationsSchema,
         ToolInfo,
         PermissionLevel,
+        PermissionRule,
+        ShellCommandPatterns,
         PrincipalType,
         ModelInfo,
         SessionInfo,
         SessionMetadata,
+        ModelChangeRecord,
         super::routes::schedule::CreateScheduleRequest,
         super::routes::schedule::UpdateScheduleRequest,
         super::routes::schedule::KillJobResponse,
         super::routes::schedule::InspectJobResponse,
         goose::scheduler::ScheduledJob,
+        goose::scheduler_spec::ScheduleKind,
+        goose::scheduler_gate::ExecutionConditions,
+        goose::scheduler::ConcurrencyPolicy,
+        goose::scheduler::SkippedRun,
+        goose::scheduler::RetryPolicy,
+        goose::scheduler::RunStatus,
+        goose::scheduler::RunAttempt,
+        goose::scheduler::RunUsage,
+        goose::scheduler::RunStats,
+        goose::scheduler_webhook::WebhookNotification,
         super::routes::schedule::RunNowResponse,
         super::routes::schedule::ListSchedulesResponse,
+        super::routes::schedule::ScheduleListEntry,
         super::routes::schedule::SessionsQuery,
         super::routes::schedule::SessionDisplayInfo,
+        super::routes::schedule::RunsQuery,
+        super::routes::schedule::ValidateCronRequest,
+        super::routes::schedule::ValidateCronResponse,
+        goose::scheduler_cron::CronParseError,
         super::routes::recipe::CreateRecipeRequest,
         super::routes::recipe::AuthorRequest,
         super::routes::recipe::CreateRecipeResponse,
@@ -452,6 +616,27 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         super::routes::recipe::EncodeRecipeResponse,
         super::routes::recipe::DecodeRecipeRequest,
         super::routes::recipe::DecodeRecipeResponse,
+        goose::recipe::risk_summary::RecipeRiskSummary,
+        goose::recipe::risk_summary::RequestedExtension,
+        super::routes::recipe::ValidateRecipeRequest,
+        super::routes::recipe::ValidateRecipeResponse,
+        goose::recipe::validate_recipe::RecipeIssue,
+        goose::recipe::validate_recipe::IssueSeverity,
+        super::routes::recipe::RenderRecipeRequest,
+        super::routes::recipe::RenderRecipeResponse,
+        super::routes::recipe::InvalidParametersResponse,
+        goose::recipe::run_recipe::RecipeDryRun,
+        goose::recipe::run_recipe::RunSettings,
+        goose::recipe::build_recipe::ParameterError,
+        super::routes::recipe::ImportRecipeRequest,
+        super::routes::recipe::ImportRecipeResponse,
+        super::routes::recipe::RecipeErrorResponse,
+        super::routes::recipe::ListRecipesResponse,
+        super::routes::recipe::LocalRecipeResponse,
+        super::routes::recipe::SaveRecipeRequest,
+        super::routes::recipe::SaveRecipeResponse,
+        goose::recipe::local_recipes::LocalRecipe,
+        goose::recipe::local_recipes::ParameterSummary,
         goose::recipe::Recipe,
         goose::recipe::Author,
         goose::recipe::Settings,
@@ -459,11 +644,36 @@ impl<'__s> ToSchema<'__s> for AnnotatedSchema {
         goose::recipe::RecipeParameterInputType,
         goose::recipe::RecipeParameterRequirement,
         goose::recipe::Response,
+        goose::recipe::response_schema::SchemaValidation,
+        goose::session_callback::DeliveryAttempt,
+        goose::providers::transcript::ProviderCallRef,
+        goose::session::ReplayRecord,
+        goose::session::TurnComparison,
+        goose::session::TurnStats,
         goose::recipe::SubRecipe,
         goose::agents::types::RetryConfig,
         goose::agents::types::SuccessCheck,
         super::routes::agent::AddSubRecipesRequest,
         super::routes::agent::AddSubRecipesResponse,
+        super::routes::agent::ExtendPromptRequest,
+        super::routes::agent::ExtendPromptResponse,
+        super::routes::agent::SystemPromptResponse,
+        super::routes::agent::AgentExtensionResponse,
+        super::routes::agent::PromptInfo,
+        goose::config::Persona,
+        super::routes::agent::PromptArgumentInfo,
+        super::routes::agent::ResourceInfo,
+        super::routes::agent::ResourceSubscriptionRequest,
+        super::routes::agent::ResourceListResponse,
+        super::routes::agent::ResourceReadResponse,
+        super::routes::agent::ExtensionLogsResponse,
+        super::routes::agent::ErrorResponse,
+        super::routes::agent::UpdateToolRequest,
+        super::routes::agent::FrontendToolDefinition,
+        super::routes::agent::RegisterFrontendToolsRequest,
+        super::routes::agent::RegisterFrontendToolsResponse,
+        goose::agents::extension::ExtensionStatus,
+        goose::agents::extension::HealthStatus,
     ))
 )]
 pub struct ApiDoc;
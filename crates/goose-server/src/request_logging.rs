@@ -0,0 +1,252 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, BodyDataStream, Bytes};
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use futures::Stream;
+use goose::config::Config;
+
+/// Fraction of requests to log, between 0.0 and 1.0. Server errors are always logged.
+pub const SAMPLE_RATE_KEY: &str = "GOOSE_SERVER_REQUEST_LOG_SAMPLE_RATE";
+
+/// Logs one structured event per request, once its response body has been fully sent or
+/// dropped. Streaming responses are therefore logged when the stream closes, with how long it
+/// stayed open and how many events it carried.
+pub struct RequestLogger {
+    sample_rate: f64,
+    seen: AtomicU64,
+}
+
+impl RequestLogger {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    pub fn from_config() -> Arc<Self> {
+        let sample_rate = Config::global()
+            .get_param::<f64>(SAMPLE_RATE_KEY)
+            .unwrap_or(1.0);
+        Arc::new(Self::new(sample_rate))
+    }
+
+    /// Whether the next request falls in the sample. Sampled requests are spread evenly rather
+    /// than drawn at random, so a rate of 0.25 logs exactly every fourth request.
+    fn sample(&self) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.sample_rate).floor() > (n * self.sample_rate).floor()
+    }
+}
+
+/// Middleware for `axum::middleware::from_fn_with_state` that logs requests through `logger`
+pub async fn log_requests(
+    State(logger): State<Arc<RequestLogger>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let sampled = logger.sample();
+    let started = Instant::now();
+    let method = request.method().clone();
+    // The templated route keeps session ids and other path parameters out of info-level logs
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "<unmatched>".to_string());
+    let path = request.uri().path().to_string();
+    let request_bytes = content_length(request.headers());
+
+    let response = next.run(request).await;
+    let status = response.status();
+    if !sampled && !status.is_server_error() {
+        return response;
+    }
+
+    let record = RequestRecord {
+        method,
+        route,
+        path,
+        status,
+        request_bytes,
+        streaming: is_event_stream(response.headers()),
+        started,
+        latency: started.elapsed(),
+    };
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(LoggedBody {
+        inner: body.into_data_stream(),
+        record: Some(record),
+        bytes: 0,
+        events: 0,
+    });
+    Response::from_parts(parts, body)
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+}
+
+struct RequestRecord {
+    method: Method,
+    route: String,
+    path: String,
+    status: StatusCode,
+    request_bytes: Option<u64>,
+    streaming: bool,
+    started: Instant,
+    /// Time until the response headers were ready
+    latency: Duration,
+}
+
+impl RequestRecord {
+    fn log(self, response_bytes: u64, events: u64, completed: bool) {
+        let latency_ms = millis(self.latency);
+        let duration_ms = millis(self.started.elapsed());
+        let events = self.streaming.then_some(events);
+
+        if self.status.is_server_error() {
+            tracing::warn!(
+                method = %self.method,
+                route = %self.route,
+                status = self.status.as_u16(),
+                latency_ms,
+                duration_ms,
+                request_bytes = self.request_bytes,
+                response_bytes,
+                events,
+                completed,
+                "request"
+            );
+        } else {
+            tracing::info!(
+                method = %self.method,
+                route = %self.route,
+                status = self.status.as_u16(),
+                latency_ms,
+                duration_ms,
+                request_bytes = self.request_bytes,
+                response_bytes,
+                events,
+                completed,
+                "request"
+            );
+        }
+        tracing::debug!(method = %self.method, path = %self.path, "request path");
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// Response body that counts what passes through it and logs the request when it ends, either
+/// because the body was fully sent or because the client went away and it was dropped
+struct LoggedBody {
+    inner: BodyDataStream,
+    record: Option<RequestRecord>,
+    bytes: u64,
+    events: u64,
+}
+
+impl LoggedBody {
+    fn finish(&mut self, completed: bool) {
+        if let Some(record) = self.record.take() {
+            record.log(self.bytes, self.events, completed);
+        }
+    }
+}
+
+impl Stream for LoggedBody {
+    type Item = Result<Bytes, axum::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.bytes += chunk.len() as u64;
+                // Server-sent events end with a blank line
+                self.events += chunk.windows(2).filter(|w| *w == b"\n\n").count() as u64;
+            }
+            Poll::Ready(Some(Err(_))) => self.finish(false),
+            Poll::Ready(None) => self.finish(true),
+            Poll::Pending => {}
+        }
+        poll
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        self.finish(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_sample_rate_spreads_requests() {
+        let logger = RequestLogger::new(0.25);
+        let sampled: Vec<bool> = (0..8).map(|_| logger.sample()).collect();
+        assert_eq!(
+            sampled,
+            vec![false, false, false, true, false, false, false, true]
+        );
+
+        let none = RequestLogger::new(0.0);
+        assert!((0..100).all(|_| !none.sample()));
+
+        let all = RequestLogger::new(1.0);
+        assert!((0..100).all(|_| all.sample()));
+    }
+
+    #[tokio::test]
+    async fn test_body_passes_through() {
+        let app = Router::new()
+            .route("/sessions/{id}", get(|| async { "data: a\n\ndata: b\n\n" }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(RequestLogger::new(1.0)),
+                log_requests,
+            ));
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/sessions/abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"data: a\n\ndata: b\n\n");
+    }
+}
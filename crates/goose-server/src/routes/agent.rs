@@ -1,40 +1,66 @@
-use super::utils::verify_secret_key;
+use super::utils::{scopes, RequireScope};
 use crate::state::AppState;
 use axum::{
-    extract::{Query, State},
-    http::{HeaderMap, StatusCode},
-    routing::{get, post},
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get, patch, post},
     Json, Router,
 };
-use goose::config::PermissionManager;
+use goose::config::{PermissionManager, Persona, PersonaManager};
 use goose::model::ModelConfig;
 use goose::providers::create;
 use goose::recipe::Response;
 use goose::{
-    agents::{extension::ToolInfo, extension_manager::get_parameter_names},
+    agents::{
+        extension::{ExtensionError, ExtensionStatus, ToolInfo},
+        extension_logs::tail_extension_log,
+        extension_manager::get_parameter_names,
+        ExtensionConfig,
+    },
     config::permission::PermissionLevel,
 };
-use goose::{config::Config, recipe::SubRecipe};
+use goose::{
+    config::{Config, ToolVisibilityManager},
+    recipe::SubRecipe,
+};
+use rmcp::model::ResourceContents;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+/// Number of trailing lines of a startup error (usually the server's stderr) to return
+const STARTUP_ERROR_TAIL_LINES: usize = 20;
+
 #[derive(Serialize)]
 struct VersionsResponse {
     available_versions: Vec<String>,
     default_version: String,
 }
 
-#[derive(Deserialize)]
-struct ExtendPromptRequest {
-    extension: String,
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ExtendPromptRequest {
+    /// Additional instruction added to the system prompt
+    #[serde(default)]
+    extension: Option<String>,
+    /// Replaces the base system prompt template; an empty string restores the default
+    #[serde(default)]
+    system_prompt_override: Option<String>,
+    /// Custom instructions appended to the end of the system prompt; an empty string clears them
+    #[serde(default)]
+    append_system_prompt: Option<String>,
 }
 
-#[derive(Serialize)]
-struct ExtendPromptResponse {
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ExtendPromptResponse {
     success: bool,
 }
 
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct SystemPromptResponse {
+    /// The rendered system prompt, including extension instructions
+    system_prompt: String,
+}
+
 #[derive(Deserialize, utoipa::ToSchema)]
 pub struct AddSubRecipesRequest {
     sub_recipes: Vec<SubRecipe>,
@@ -45,6 +71,85 @@ pub struct AddSubRecipesResponse {
     success: bool,
 }
 
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct AgentExtensionResponse {
+    /// Name of the extension
+    name: String,
+    /// Tools that became available when the extension started
+    tools: Vec<String>,
+    /// Startup error, ending with the server's stderr when it failed to launch
+    error: Option<String>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct PromptArgumentInfo {
+    name: String,
+    description: Option<String>,
+    required: bool,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct PromptInfo {
+    /// Name to pass to `POST /agent/prompts/{name}`
+    name: String,
+    /// Extension that provides the prompt
+    extension: String,
+    description: Option<String>,
+    arguments: Vec<PromptArgumentInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct ListResourcesQuery {
+    /// Cursor returned by the previous page
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReadResourceQuery {
+    uri: String,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ResourceSubscriptionRequest {
+    uri: String,
+    /// Add the new contents to the conversation when the resource changes
+    #[serde(default)]
+    inject_updates: bool,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ResourceInfo {
+    uri: String,
+    name: String,
+    description: Option<String>,
+    mime_type: Option<String>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ResourceListResponse {
+    resources: Vec<ResourceInfo>,
+    /// Pass back as `cursor` to fetch the next page
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ResourceReadResponse {
+    contents: Vec<ResourceContents>,
+}
+
+const DEFAULT_LOG_TAIL: usize = 200;
+
+#[derive(Deserialize)]
+pub struct ExtensionLogsQuery {
+    /// Number of lines to return from the end of the log
+    tail: Option<usize>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ExtensionLogsResponse {
+    lines: Vec<String>,
+}
+
 #[derive(Deserialize)]
 struct ProviderFile {
     name: String,
@@ -78,13 +183,19 @@ struct SessionConfigRequest {
     response: Option<Response>,
 }
 
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct UpdateToolRequest {
+    /// Whether the tool should be offered to the model
+    enabled: bool,
+}
+
 #[derive(Deserialize)]
 pub struct GetToolsQuery {
     extension_name: Option<String>,
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ErrorResponse {
     error: String,
 }
 
@@ -109,11 +220,9 @@ async fn get_versions() -> Json<VersionsResponse> {
 )]
 async fn add_sub_recipes(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(payload): Json<AddSubRecipesRequest>,
 ) -> Result<Json<AddSubRecipesResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let agent = state
         .get_agent()
         .await
@@ -122,21 +231,69 @@ async fn add_sub_recipes(
     Ok(Json(AddSubRecipesResponse { success: true }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/agent/prompt",
+    request_body = ExtendPromptRequest,
+    responses(
+        (status = 200, description = "System prompt updated", body = ExtendPromptResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Agent not initialized"),
+    ),
+)]
 async fn extend_prompt(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(payload): Json<ExtendPromptRequest>,
 ) -> Result<Json<ExtendPromptResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let agent = state
         .get_agent()
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
-    agent.extend_system_prompt(payload.extension.clone()).await;
+
+    if let Some(extension) = payload.extension {
+        agent.extend_system_prompt(extension).await;
+    }
+    match payload.system_prompt_override {
+        Some(template) if template.is_empty() => agent.clear_system_prompt_override().await,
+        Some(template) => agent.override_system_prompt(template).await,
+        None => {}
+    }
+    if let Some(append) = payload.append_system_prompt {
+        agent
+            .set_system_prompt_append(Some(append).filter(|a| !a.is_empty()))
+            .await;
+    }
+
     Ok(Json(ExtendPromptResponse { success: true }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/agent/prompt",
+    responses(
+        (status = 200, description = "Effective system prompt", body = SystemPromptResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Agent not initialized"),
+        (status = 500, description = "Internal server error")
+    ),
+)]
+async fn get_system_prompt(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+) -> Result<Json<SystemPromptResponse>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    let system_prompt = agent
+        .get_system_prompt()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(SystemPromptResponse { system_prompt }))
+}
+
 async fn list_providers() -> Json<Vec<ProviderList>> {
     let contents = include_str!("providers_and_keys.json");
 
@@ -175,11 +332,9 @@ async fn list_providers() -> Json<Vec<ProviderList>> {
 )]
 async fn get_tools(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Query(query): Query<GetToolsQuery>,
 ) -> Result<Json<Vec<ToolInfo>>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let config = Config::global();
     let goose_mode = config.get_param("GOOSE_MODE").unwrap_or("auto".to_string());
     let agent = state
@@ -187,6 +342,7 @@ async fn get_tools(
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
     let permission_manager = PermissionManager::default();
+    let disabled_tools = ToolVisibilityManager::disabled_tools();
 
     let mut tools: Vec<ToolInfo> = agent
         .list_tools(query.extension_name)
@@ -214,6 +370,7 @@ async fn get_tools(
                 get_parameter_names(&tool),
                 permission,
             )
+            .with_enabled(!disabled_tools.contains(tool.name.as_ref()))
         })
         .collect::<Vec<ToolInfo>>();
     tools.sort_by(|a, b| a.name.cmp(&b.name));
@@ -221,42 +378,78 @@ async fn get_tools(
     Ok(Json(tools))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/agent/tools/{name}",
+    params(
+        ("name" = String, Path, description = "Prefixed name of the tool, e.g. developer__shell")
+    ),
+    request_body = UpdateToolRequest,
+    responses(
+        (status = 200, description = "Tool updated, takes effect on the next turn"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Tool not found"),
+        (status = 412, description = "Agent not initialized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn update_tool(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateToolRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    if !agent
+        .list_tools(None)
+        .await
+        .iter()
+        .any(|tool| tool.name == name.as_str())
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    ToolVisibilityManager::set_enabled(&name, payload.enabled)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::OK)
+}
+
 #[utoipa::path(
     post,
     path = "/agent/update_provider",
     responses(
         (status = 200, description = "Update provider completed", body = String),
+        (status = 400, description = "No model in the request or the config"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 403, description = "Forbidden - Token lacks the required scope"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update_agent_provider(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     Json(payload): Json<UpdateProviderRequest>,
 ) -> Result<StatusCode, StatusCode> {
-    // Verify secret key
-    let secret_key = headers
-        .get("X-Secret-Key")
-        .and_then(|value| value.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    if secret_key != state.secret_key {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
     let agent = state
         .get_agent()
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
 
-    let config = Config::global();
-    let model = payload.model.unwrap_or_else(|| {
-        config
+    let model = match payload.model {
+        Some(model) => model,
+        None => Config::global()
             .get_param("GOOSE_MODEL")
-            .expect("Did not find a model on payload or in env to update provider with")
-    });
+            .map_err(|_| StatusCode::BAD_REQUEST)?,
+    };
     let model_config = ModelConfig::new(&model).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let new_provider = create(&payload.provider, model_config).unwrap();
+    let new_provider = create(&payload.provider, model_config).map_err(|e| {
+        tracing::error!("Failed to create provider {}: {}", payload.provider, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
     agent
         .update_provider(new_provider)
         .await
@@ -270,19 +463,15 @@ async fn update_agent_provider(
     path = "/agent/update_router_tool_selector",
     responses(
         (status = 200, description = "Tool selection strategy updated successfully", body = String),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 403, description = "Forbidden - Token lacks the required scope"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update_router_tool_selector(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
 ) -> Result<Json<String>, Json<ErrorResponse>> {
-    verify_secret_key(&headers, &state).map_err(|_| {
-        Json(ErrorResponse {
-            error: "Unauthorized - Invalid or missing API key".to_string(),
-        })
-    })?;
-
     let agent = state.get_agent().await.map_err(|e| {
         tracing::error!("Failed to get agent: {}", e);
         Json(ErrorResponse {
@@ -310,20 +499,16 @@ async fn update_router_tool_selector(
     path = "/agent/session_config",
     responses(
         (status = 200, description = "Session config updated successfully", body = String),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 403, description = "Forbidden - Token lacks the required scope"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update_session_config(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(payload): Json<SessionConfigRequest>,
 ) -> Result<Json<String>, Json<ErrorResponse>> {
-    verify_secret_key(&headers, &state).map_err(|_| {
-        Json(ErrorResponse {
-            error: "Unauthorized - Invalid or missing API key".to_string(),
-        })
-    })?;
-
     let agent = state.get_agent().await.map_err(|e| {
         tracing::error!("Failed to get agent: {}", e);
         Json(ErrorResponse {
@@ -343,12 +528,560 @@ async fn update_session_config(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/agent/extensions",
+    responses(
+        (status = 200, description = "Extensions attached to the running agent", body = Vec<ExtensionStatus>),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Agent not initialized"),
+    )
+)]
+async fn list_agent_extensions(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+) -> Result<Json<Vec<ExtensionStatus>>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    Ok(Json(agent.get_extension_statuses().await))
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/extensions",
+    request_body = ExtensionConfig,
+    responses(
+        (status = 200, description = "Extension started, or the error it failed with", body = AgentExtensionResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Agent not initialized"),
+    )
+)]
+async fn add_agent_extension(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Json(extension): Json<ExtensionConfig>,
+) -> Result<Json<AgentExtensionResponse>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+
+    let name = extension.name();
+    let tools_before: HashSet<String> = agent
+        .list_tools(None)
+        .await
+        .into_iter()
+        .map(|tool| tool.name.to_string())
+        .collect();
+
+    if let Err(e) = agent.add_extension(extension).await {
+        tracing::error!("Failed to start extension {}: {}", name, e);
+        return Ok(Json(AgentExtensionResponse {
+            name,
+            tools: vec![],
+            error: Some(startup_error_message(&e)),
+        }));
+    }
+
+    let mut tools: Vec<String> = agent
+        .list_tools(None)
+        .await
+        .into_iter()
+        .map(|tool| tool.name.to_string())
+        .filter(|tool| !tools_before.contains(tool))
+        .collect();
+    tools.sort();
+
+    Ok(Json(AgentExtensionResponse {
+        name,
+        tools,
+        error: None,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/agent/extensions/{name}",
+    params(
+        ("name" = String, Path, description = "Name of the extension to stop")
+    ),
+    responses(
+        (status = 200, description = "Extension stopped", body = String),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Agent not initialized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn remove_agent_extension(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Path(name): Path<String>,
+) -> Result<Json<String>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    agent.remove_extension(&name).await.map_err(|e| {
+        tracing::error!("Failed to stop extension {}: {}", name, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(format!("Removed extension {}", name)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agent/personas",
+    responses(
+        (status = 200, description = "Personas a reply can select, sorted by name", body = Vec<Persona>),
+        (status = 401, description = "Unauthorized - invalid secret key")
+    )
+)]
+async fn list_personas(
+    _scope: RequireScope<scopes::Chat>,
+) -> Result<Json<Vec<Persona>>, StatusCode> {
+    Ok(Json(PersonaManager::list()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agent/prompts",
+    responses(
+        (status = 200, description = "Prompts offered by the running extensions", body = Vec<PromptInfo>),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Agent not initialized"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list_prompts(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+) -> Result<Json<Vec<PromptInfo>>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    let commands = agent.list_prompt_commands().await.map_err(|e| {
+        tracing::error!("Failed to list prompts: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(
+        commands
+            .into_iter()
+            .map(|command| PromptInfo {
+                name: command.name,
+                extension: command.extension,
+                description: command.prompt.description,
+                arguments: command
+                    .prompt
+                    .arguments
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|argument| PromptArgumentInfo {
+                        name: argument.name,
+                        description: argument.description,
+                        required: argument.required.unwrap_or(false),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agent/extensions/{name}/resources",
+    params(
+        ("name" = String, Path, description = "Name of the extension"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned by the previous page")
+    ),
+    responses(
+        (status = 200, description = "One page of the extension's resources", body = ResourceListResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Extension not running", body = ErrorResponse),
+        (status = 412, description = "Agent not initialized", body = ErrorResponse),
+        (status = 501, description = "Extension does not support resources", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+async fn list_extension_resources(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+    Query(query): Query<ListResourcesQuery>,
+) -> Result<Json<ResourceListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| error_response(StatusCode::PRECONDITION_FAILED, "Agent not initialized"))?;
+    let result = agent
+        .list_extension_resources(&name, query.cursor)
+        .await
+        .map_err(resource_error_response)?;
+
+    Ok(Json(ResourceListResponse {
+        resources: result
+            .resources
+            .into_iter()
+            .map(|resource| ResourceInfo {
+                uri: resource.uri.clone(),
+                name: resource.name.clone(),
+                description: resource.description.clone(),
+                mime_type: resource.mime_type.clone(),
+            })
+            .collect(),
+        next_cursor: result.next_cursor,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agent/extensions/{name}/resources/read",
+    params(
+        ("name" = String, Path, description = "Name of the extension"),
+        ("uri" = String, Query, description = "URI of the resource to read")
+    ),
+    responses(
+        (status = 200, description = "Contents of the resource", body = ResourceReadResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Extension not running", body = ErrorResponse),
+        (status = 412, description = "Agent not initialized", body = ErrorResponse),
+        (status = 501, description = "Extension does not support resources", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+async fn read_extension_resource(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+    Query(query): Query<ReadResourceQuery>,
+) -> Result<Json<ResourceReadResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| error_response(StatusCode::PRECONDITION_FAILED, "Agent not initialized"))?;
+    let result = agent
+        .read_extension_resource(&name, &query.uri)
+        .await
+        .map_err(resource_error_response)?;
+
+    Ok(Json(ResourceReadResponse {
+        contents: result.contents,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/extensions/{name}/resources/subscribe",
+    params(
+        ("name" = String, Path, description = "Name of the extension")
+    ),
+    request_body = ResourceSubscriptionRequest,
+    responses(
+        (status = 200, description = "Subscribed to the resource", body = String),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Extension not running", body = ErrorResponse),
+        (status = 412, description = "Agent not initialized", body = ErrorResponse),
+        (status = 501, description = "Extension does not support resource subscriptions", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+async fn subscribe_extension_resource(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+    Json(request): Json<ResourceSubscriptionRequest>,
+) -> Result<Json<String>, (StatusCode, Json<ErrorResponse>)> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| error_response(StatusCode::PRECONDITION_FAILED, "Agent not initialized"))?;
+    agent
+        .subscribe_resource(&name, &request.uri, request.inject_updates)
+        .await
+        .map_err(resource_error_response)?;
+
+    Ok(Json(format!("Subscribed to {}", request.uri)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/extensions/{name}/resources/unsubscribe",
+    params(
+        ("name" = String, Path, description = "Name of the extension")
+    ),
+    request_body = ResourceSubscriptionRequest,
+    responses(
+        (status = 200, description = "Unsubscribed from the resource", body = String),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Extension not running", body = ErrorResponse),
+        (status = 412, description = "Agent not initialized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+async fn unsubscribe_extension_resource(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+    Json(request): Json<ResourceSubscriptionRequest>,
+) -> Result<Json<String>, (StatusCode, Json<ErrorResponse>)> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| error_response(StatusCode::PRECONDITION_FAILED, "Agent not initialized"))?;
+    agent
+        .unsubscribe_resource(&name, &request.uri)
+        .await
+        .map_err(resource_error_response)?;
+
+    Ok(Json(format!("Unsubscribed from {}", request.uri)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/extensions/{name}/authorize",
+    params(
+        ("name" = String, Path, description = "Name of the configured extension")
+    ),
+    responses(
+        (status = 200, description = "Extension authorized", body = String),
+        (status = 400, description = "Extension does not connect over HTTP", body = ErrorResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Extension not configured", body = ErrorResponse),
+        (status = 412, description = "Agent not initialized", body = ErrorResponse),
+        (status = 502, description = "OAuth flow failed", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+async fn authorize_extension(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Path(name): Path<String>,
+) -> Result<Json<String>, (StatusCode, Json<ErrorResponse>)> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| error_response(StatusCode::PRECONDITION_FAILED, "Agent not initialized"))?;
+    agent.authorize_extension(&name).await.map_err(|e| {
+        let status = match &e {
+            ExtensionError::NotConfigured(_) => StatusCode::NOT_FOUND,
+            ExtensionError::AuthorizationNotSupported(_) => StatusCode::BAD_REQUEST,
+            ExtensionError::Authorization(_) => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        error_response(status, e.to_string())
+    })?;
+
+    Ok(Json(format!("Authorized {}", name)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agent/extensions/{name}/logs",
+    params(
+        ("name" = String, Path, description = "Name of the extension"),
+        ("tail" = Option<usize>, Query, description = "Number of lines to return from the end of the log, 200 by default")
+    ),
+    responses(
+        (status = 200, description = "Most recent lines of the extension's log", body = ExtensionLogsResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "No log for this extension", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+async fn get_extension_logs(
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+    Query(query): Query<ExtensionLogsQuery>,
+) -> Result<Json<ExtensionLogsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let lines = tail_extension_log(&name, query.tail.unwrap_or(DEFAULT_LOG_TAIL)).map_err(|e| {
+        let status = match e.kind() {
+            std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        error_response(status, format!("Failed to read log for {}: {}", name, e))
+    })?;
+
+    Ok(Json(ExtensionLogsResponse { lines }))
+}
+
+fn error_response(
+    status: StatusCode,
+    message: impl Into<String>,
+) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+        }),
+    )
+}
+
+fn resource_error_response(error: ExtensionError) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match &error {
+        ExtensionError::NotRunning(_) => StatusCode::NOT_FOUND,
+        ExtensionError::ResourcesNotSupported(_) | ExtensionError::SubscriptionsNotSupported(_) => {
+            StatusCode::NOT_IMPLEMENTED
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error_response(status, error.to_string())
+}
+
+/// Describe why an extension failed to start, keeping only the tail of long output
+fn startup_error_message(error: &ExtensionError) -> String {
+    // The initialization variant only names the config; the client error carries the stderr
+    let message = match error {
+        ExtensionError::Initialization(_, client_error) => client_error.to_string(),
+        other => other.to_string(),
+    };
+    let lines: Vec<&str> = message.lines().collect();
+    lines[lines.len().saturating_sub(STARTUP_ERROR_TAIL_LINES)..].join("\n")
+}
+
+/// A tool the client runs itself, such as rendering a chart in the browser
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct FrontendToolDefinition {
+    /// Letters, digits, `_` and `-`; `__` is reserved for extension tools
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// JSON schema of the tool's arguments
+    #[serde(default = "empty_input_schema")]
+    #[schema(value_type = Object)]
+    input_schema: serde_json::Value,
+}
+
+fn empty_input_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct RegisterFrontendToolsRequest {
+    session_id: String,
+    /// Replaces the tools registered for the session before; an empty list removes them
+    tools: Vec<FrontendToolDefinition>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct RegisterFrontendToolsResponse {
+    /// Names of the tools now registered for the session
+    tools: Vec<String>,
+}
+
+fn is_valid_frontend_tool_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains("__")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[utoipa::path(
+    post,
+    path = "/agent/frontend_tools",
+    request_body = RegisterFrontendToolsRequest,
+    responses(
+        (status = 200, description = "Tools registered for the session", body = RegisterFrontendToolsResponse),
+        (status = 401, description = "Unauthorized - invalid secret key", body = ErrorResponse),
+        (status = 412, description = "Agent not initialized", body = ErrorResponse),
+        (status = 422, description = "A tool has an invalid or repeated name, or a schema that is not an object", body = ErrorResponse)
+    )
+)]
+// Calls to these tools are streamed to the session's replies as frontend tool requests, and the
+// reply waits for the client to send the result to `/tool_result`.
+async fn register_frontend_tools(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Json(payload): Json<RegisterFrontendToolsRequest>,
+) -> Result<Json<RegisterFrontendToolsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let mut names = HashSet::new();
+    let mut tools = Vec::with_capacity(payload.tools.len());
+    for definition in payload.tools {
+        if !is_valid_frontend_tool_name(&definition.name) {
+            return Err(error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Invalid tool name '{}'", definition.name),
+            ));
+        }
+        if !names.insert(definition.name.clone()) {
+            return Err(error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Tool '{}' is listed more than once", definition.name),
+            ));
+        }
+        let serde_json::Value::Object(input_schema) = definition.input_schema else {
+            return Err(error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!(
+                    "The input schema of tool '{}' is not an object",
+                    definition.name
+                ),
+            ));
+        };
+        tools.push(rmcp::model::Tool::new(
+            definition.name,
+            definition.description,
+            input_schema,
+        ));
+    }
+
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| error_response(StatusCode::PRECONDITION_FAILED, "Agent not initialized"))?;
+    agent
+        .register_frontend_tools(&payload.session_id, tools)
+        .await;
+    let tools = agent
+        .session_frontend_tools(&payload.session_id)
+        .await
+        .into_iter()
+        .map(|tool| tool.name.to_string())
+        .collect();
+
+    Ok(Json(RegisterFrontendToolsResponse { tools }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/agent/frontend_tools/{session_id}",
+    params(
+        ("session_id" = String, Path, description = "Session the tools were registered for")
+    ),
+    responses(
+        (status = 204, description = "Tools removed"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "No tools are registered for the session"),
+        (status = 412, description = "Agent not initialized")
+    )
+)]
+async fn unregister_frontend_tools(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    if agent.unregister_frontend_tools(&session_id).await {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/agent/versions", get(get_versions))
         .route("/agent/providers", get(list_providers))
-        .route("/agent/prompt", post(extend_prompt))
+        .route("/agent/prompt", post(extend_prompt).get(get_system_prompt))
         .route("/agent/tools", get(get_tools))
+        .route("/agent/tools/{name}", patch(update_tool))
         .route("/agent/update_provider", post(update_agent_provider))
         .route(
             "/agent/update_router_tool_selector",
@@ -356,5 +1089,38 @@ pub fn routes(state: Arc<AppState>) -> Router {
         )
         .route("/agent/session_config", post(update_session_config))
         .route("/agent/add_sub_recipes", post(add_sub_recipes))
+        .route(
+            "/agent/extensions",
+            get(list_agent_extensions).post(add_agent_extension),
+        )
+        .route("/agent/extensions/{name}", delete(remove_agent_extension))
+        .route("/agent/prompts", get(list_prompts))
+        .route("/agent/personas", get(list_personas))
+        .route(
+            "/agent/extensions/{name}/resources",
+            get(list_extension_resources),
+        )
+        .route(
+            "/agent/extensions/{name}/resources/read",
+            get(read_extension_resource),
+        )
+        .route(
+            "/agent/extensions/{name}/resources/subscribe",
+            post(subscribe_extension_resource),
+        )
+        .route(
+            "/agent/extensions/{name}/resources/unsubscribe",
+            post(unsubscribe_extension_resource),
+        )
+        .route(
+            "/agent/extensions/{name}/authorize",
+            post(authorize_extension),
+        )
+        .route("/agent/extensions/{name}/logs", get(get_extension_logs))
+        .route("/agent/frontend_tools", post(register_frontend_tools))
+        .route(
+            "/agent/frontend_tools/{session_id}",
+            delete(unregister_frontend_tools),
+        )
         .with_state(state)
 }
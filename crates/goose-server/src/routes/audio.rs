@@ -2,11 +2,11 @@
 ///
 /// This module provides endpoints for audio transcription using OpenAI's Whisper API.
 /// The OpenAI API key must be configured in the backend for this to work.
-use super::utils::verify_secret_key;
+use super::utils::{scopes, RequireScope};
 use crate::state::AppState;
 use axum::{
     extract::State,
-    http::{HeaderMap, StatusCode},
+    http::StatusCode,
     routing::{get, post},
     Json, Router,
 };
@@ -60,12 +60,9 @@ struct WhisperResponse {
 /// - 502: Bad Gateway (OpenAI API error)
 /// - 503: Service Unavailable (network error)
 async fn transcribe_handler(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(request): Json<TranscribeRequest>,
 ) -> Result<Json<TranscribeResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     // Validate input first before checking API key configuration
     // Decode the base64 audio data
     let audio_bytes = BASE64
@@ -171,12 +168,9 @@ async fn transcribe_handler(
 /// Uses ElevenLabs' speech-to-text endpoint for transcription.
 /// Requires an ElevenLabs API key with speech-to-text access.
 async fn transcribe_elevenlabs_handler(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(request): Json<TranscribeElevenLabsRequest>,
 ) -> Result<Json<TranscribeResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     // Validate input first before checking API key configuration
     // Decode the base64 audio data
     let audio_bytes = BASE64
@@ -322,11 +316,8 @@ async fn transcribe_elevenlabs_handler(
 ///
 /// Returns configuration status for dictation providers
 async fn check_dictation_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let config = goose::config::Config::global();
 
     // Check if ElevenLabs API key is configured
@@ -0,0 +1,63 @@
+use super::utils::{scopes, RequireScope};
+use std::sync::Arc;
+
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use goose::audit::{AuditEntry, AuditLog};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct AuditQuery {
+    /// Only return entries for this session
+    session_id: Option<String>,
+    /// Only return entries written at or after this time (RFC 3339)
+    since: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuditLogResponse {
+    /// Matching audit entries, oldest first
+    entries: Vec<AuditEntry>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Audit log entries retrieved successfully", body = AuditLogResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Audit"
+)]
+// Query the audit log of tool executions and permission decisions
+async fn get_audit_log(
+    _scope: RequireScope<scopes::Admin>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditLogResponse>, StatusCode> {
+    let entries = AuditLog::global()
+        .query(query.session_id.as_deref(), query.since)
+        .map_err(|e| {
+            error!("Failed to read audit log: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(AuditLogResponse { entries }))
+}
+
+// Configure routes for this module
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/audit", get(get_audit_log))
+        .with_state(state)
+}
@@ -0,0 +1,459 @@
+use super::utils::{constant_time_eq, scopes, RequireScope};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use goose::config::{Config, ConfigError};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::error;
+use utoipa::ToSchema;
+
+/// Secret holding the minted tokens. Only hashes are stored, never the tokens themselves.
+const TOKENS_KEY: &str = "GOOSE_SERVER_API_TOKENS";
+
+/// How stale a token's last-used time may get before it is written back to the secret store
+const LAST_USED_PERSIST_INTERVAL: Duration = Duration::minutes(5);
+
+/// What a token is allowed to do. `admin` grants every other scope.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+pub enum Scope {
+    #[serde(rename = "chat")]
+    Chat,
+    #[serde(rename = "sessions:read")]
+    SessionsRead,
+    #[serde(rename = "sessions:write")]
+    SessionsWrite,
+    #[serde(rename = "config:write")]
+    ConfigWrite,
+    #[serde(rename = "schedule")]
+    Schedule,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    fn grants(self, required: Scope) -> bool {
+        self == Scope::Admin || self == required
+    }
+}
+
+/// A minted API token, as listed. The token itself is only returned when it is minted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    /// Hex SHA-256 of the token
+    hash: String,
+}
+
+/// The server's API tokens. They are read from the secret store the first time a request
+/// presents something other than the server's secret key.
+#[derive(Clone)]
+pub struct TokenStore {
+    tokens: Arc<Mutex<Option<Vec<StoredToken>>>>,
+    persist: bool,
+}
+
+impl TokenStore {
+    /// Tokens kept in the config's secret store
+    pub fn new() -> Self {
+        Self {
+            tokens: Arc::default(),
+            persist: true,
+        }
+    }
+
+    /// Tokens that only live as long as the store, for tests
+    pub fn in_memory() -> Self {
+        Self {
+            tokens: Arc::new(Mutex::new(Some(Vec::new()))),
+            persist: false,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Vec<StoredToken>>> {
+        let mut guard = self
+            .tokens
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if guard.is_none() {
+            *guard = Some(
+                Config::global()
+                    .get_secret::<Vec<StoredToken>>(TOKENS_KEY)
+                    .unwrap_or_default(),
+            );
+        }
+        guard
+    }
+
+    fn save(&self, tokens: &[StoredToken]) -> Result<(), ConfigError> {
+        if !self.persist {
+            return Ok(());
+        }
+        Config::global().set_secret(TOKENS_KEY, serde_json::to_value(tokens)?)
+    }
+
+    /// Check `token` against the minted tokens. An unknown token is `UNAUTHORIZED`, a known one
+    /// without the `required` scope is `FORBIDDEN`.
+    pub fn authorize(&self, token: &str, required: Scope) -> Result<(), StatusCode> {
+        let hash = hash_token(token);
+        let mut guard = self.lock();
+        let tokens = guard.get_or_insert_with(Vec::new);
+        let stored = tokens
+            .iter_mut()
+            .find(|stored| constant_time_eq(&stored.hash, &hash))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        if !stored.token.scopes.iter().any(|s| s.grants(required)) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let now = Utc::now();
+        let stale = stored
+            .token
+            .last_used_at
+            .is_none_or(|used| now - used > LAST_USED_PERSIST_INTERVAL);
+        stored.token.last_used_at = Some(now);
+        if stale {
+            if let Err(e) = self.save(tokens) {
+                error!("Failed to record API token use: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Mint a token with `scopes`. Returns its listing and the token, which is not kept.
+    pub fn mint(
+        &self,
+        name: String,
+        scopes: Vec<Scope>,
+    ) -> Result<(ApiToken, String), ConfigError> {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let secret = format!(
+            "goose_{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+        );
+        let mut id = [0u8; 6];
+        rand::thread_rng().fill_bytes(&mut id);
+
+        let token = ApiToken {
+            id: id.iter().map(|b| format!("{:02x}", b)).collect(),
+            name,
+            scopes,
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+
+        let mut guard = self.lock();
+        let tokens = guard.get_or_insert_with(Vec::new);
+        tokens.push(StoredToken {
+            token: token.clone(),
+            hash: hash_token(&secret),
+        });
+        if let Err(e) = self.save(tokens) {
+            tokens.pop();
+            return Err(e);
+        }
+        Ok((token, secret))
+    }
+
+    /// Revoke the token with `id`, returning whether there was one
+    pub fn revoke(&self, id: &str) -> Result<bool, ConfigError> {
+        let mut guard = self.lock();
+        let tokens = guard.get_or_insert_with(Vec::new);
+        let Some(index) = tokens.iter().position(|stored| stored.token.id == id) else {
+            return Ok(false);
+        };
+        let removed = tokens.remove(index);
+        if let Err(e) = self.save(tokens) {
+            tokens.insert(index, removed);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    /// The id of the minted token `token`, if it is one
+    pub fn token_id(&self, token: &str) -> Option<String> {
+        let hash = hash_token(token);
+        self.lock()
+            .iter()
+            .flatten()
+            .find(|stored| constant_time_eq(&stored.hash, &hash))
+            .map(|stored| stored.token.id.clone())
+    }
+
+    pub fn list(&self) -> Vec<ApiToken> {
+        self.lock()
+            .iter()
+            .flatten()
+            .map(|stored| stored.token.clone())
+            .collect()
+    }
+}
+
+impl Default for TokenStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MintTokenRequest {
+    /// Label to recognise the token by
+    name: String,
+    scopes: Vec<Scope>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MintTokenResponse {
+    token: ApiToken,
+    /// The token to send as `X-Secret-Key`. It cannot be retrieved again.
+    secret: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/tokens",
+    responses(
+        (status = 200, description = "Minted API tokens", body = [ApiToken]),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 403, description = "Forbidden - Token lacks the admin scope")
+    ),
+    security(("api_key" = [])),
+    tag = "Auth"
+)]
+async fn list_tokens(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Admin>,
+) -> Result<Json<Vec<ApiToken>>, StatusCode> {
+    Ok(Json(state.tokens.list()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/tokens",
+    request_body = MintTokenRequest,
+    responses(
+        (status = 200, description = "Token minted", body = MintTokenResponse),
+        (status = 400, description = "No scopes requested"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 403, description = "Forbidden - Token lacks the admin scope"),
+        (status = 500, description = "Token could not be stored")
+    ),
+    security(("api_key" = [])),
+    tag = "Auth"
+)]
+async fn mint_token(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Admin>,
+    Json(request): Json<MintTokenRequest>,
+) -> Result<Json<MintTokenResponse>, StatusCode> {
+    if request.scopes.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let (token, secret) = state
+        .tokens
+        .mint(request.name, request.scopes)
+        .map_err(|e| {
+            error!("Failed to store API token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(MintTokenResponse { token, secret }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/tokens/{id}",
+    params(
+        ("id" = String, Path, description = "Id of the token to revoke")
+    ),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 403, description = "Forbidden - Token lacks the admin scope"),
+        (status = 404, description = "No token with this id"),
+        (status = 500, description = "Token could not be removed from storage")
+    ),
+    security(("api_key" = [])),
+    tag = "Auth"
+)]
+async fn revoke_token(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Admin>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    match state.tokens.revoke(&id) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to revoke API token: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Configure routes for this module
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/auth/tokens", get(list_tokens).post(mint_token))
+        .route("/auth/tokens/{id}", delete(revoke_token))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::utils::verify_secret_key;
+    use axum::body::Body;
+    use axum::http::{HeaderMap, Request};
+    use goose::agents::Agent;
+    use tower::ServiceExt;
+
+    fn state_with_store() -> Arc<AppState> {
+        AppState::with_tokens(
+            Arc::new(Agent::new()),
+            "legacy".to_string(),
+            TokenStore::in_memory(),
+        )
+    }
+
+    fn headers(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Secret-Key", key.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_is_unauthorized() {
+        let state = state_with_store();
+        assert_eq!(
+            verify_secret_key(&HeaderMap::new(), &state, Scope::Chat),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_token_is_unauthorized() {
+        let state = state_with_store();
+        assert_eq!(
+            verify_secret_key(&headers("goose_nope"), &state, Scope::Chat),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_legacy_secret_is_admin() {
+        let state = state_with_store();
+        assert!(verify_secret_key(&headers("legacy"), &state, Scope::Admin).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_each_scope_rejects_the_others() {
+        let state = state_with_store();
+        let scopes = [
+            Scope::Chat,
+            Scope::SessionsRead,
+            Scope::SessionsWrite,
+            Scope::ConfigWrite,
+            Scope::Schedule,
+        ];
+        for granted in scopes {
+            let (_, secret) = state
+                .tokens
+                .mint(format!("{:?}", granted), vec![granted])
+                .unwrap();
+            for required in scopes.into_iter().chain([Scope::Admin]) {
+                let result = verify_secret_key(&headers(&secret), &state, required);
+                if required == granted {
+                    assert!(result.is_ok(), "{:?} should allow {:?}", granted, required);
+                } else {
+                    assert_eq!(
+                        result,
+                        Err(StatusCode::FORBIDDEN),
+                        "{:?} should not allow {:?}",
+                        granted,
+                        required
+                    );
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admin_token_grants_everything() {
+        let state = state_with_store();
+        let (_, secret) = state
+            .tokens
+            .mint("admin".to_string(), vec![Scope::Admin])
+            .unwrap();
+        for required in [Scope::Chat, Scope::ConfigWrite, Scope::Admin] {
+            assert!(verify_secret_key(&headers(&secret), &state, required).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revoked_token_is_unauthorized() {
+        let state = state_with_store();
+        let (token, secret) = state
+            .tokens
+            .mint("chat".to_string(), vec![Scope::Chat])
+            .unwrap();
+        assert!(verify_secret_key(&headers(&secret), &state, Scope::Chat).is_ok());
+        assert!(state.tokens.list()[0].last_used_at.is_some());
+
+        assert!(state.tokens.revoke(&token.id).unwrap());
+        assert!(!state.tokens.revoke(&token.id).unwrap());
+        assert_eq!(
+            verify_secret_key(&headers(&secret), &state, Scope::Chat),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_route_rejects_token_without_scope() {
+        let state = state_with_store();
+        let (_, secret) = state
+            .tokens
+            .mint("chat".to_string(), vec![Scope::Chat])
+            .unwrap();
+        let request = |key: &str| {
+            Request::builder()
+                .uri("/auth/tokens")
+                .header("X-Secret-Key", key)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let app = routes(state);
+        let response = app.clone().oneshot(request(&secret)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.clone().oneshot(request("goose_nope")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.oneshot(request("legacy")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,870 @@
+//! Running one prompt over many inputs. `POST /ask/batch` fills a prompt template with each
+//! item and runs the agent on the results a few at a time, each in a session of its own tagged
+//! with the batch's id. A failed item doesn't stop the others.
+
+use super::events::ServerEvent;
+use super::limits::session_tokens;
+use super::reply::{allowed_working_dir_roots, validate_working_dir, SseResponse};
+use super::utils::{scopes, RequireScope};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use goose::agents::{Agent, AgentEvent, ReplyExecutionMode, SessionConfig};
+use goose::message::{push_message, Message};
+use goose::session::{self, SessionMetadata, SessionOrigin};
+use rand::RngCore;
+use rmcp::model::Role;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tracing::error;
+use utoipa::ToSchema;
+
+/// Where each item goes in the prompt template
+const ITEM_PLACEHOLDER: &str = "{{item}}";
+const MAX_BATCH_ITEMS: usize = 1000;
+const DEFAULT_CONCURRENCY: usize = 4;
+const MAX_CONCURRENCY: usize = 16;
+const DEFAULT_ITEM_TIMEOUT_SECS: u64 = 300;
+/// Finished batches kept for `GET /ask/batch/{id}`; the oldest are forgotten first
+const MAX_FINISHED_BATCHES: usize = 100;
+
+#[derive(Deserialize, ToSchema)]
+pub struct BatchRequest {
+    /// Prompt run for every item, with `{{item}}` where the item goes
+    prompt: String,
+    items: Vec<String>,
+    /// Absolute path of the working directory the items run in
+    working_dir: String,
+    /// Items run at once, 4 by default and at most 16
+    #[serde(default)]
+    concurrency: Option<usize>,
+    /// Seconds an item may run before it is stopped, 300 by default
+    #[serde(default)]
+    item_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    TimedOut,
+    /// The batch was cancelled before the item finished or started
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct BatchItemResult {
+    /// Position of the item in the request, from 0
+    pub index: usize,
+    pub item: String,
+    pub status: BatchItemStatus,
+    /// The session the item ran in, once it started
+    pub session_id: Option<String>,
+    /// Text of the agent's last message
+    pub output: Option<String>,
+    pub error: Option<String>,
+    /// Tokens spent on the item
+    pub tokens: u64,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Running,
+    /// Every item has run
+    Finished,
+    /// Stopped by `POST /ask/batch/{id}/cancel` before every item ran
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BatchReport {
+    pub id: String,
+    pub status: BatchStatus,
+    pub prompt: String,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// One result per item, in the order of the request
+    pub results: Vec<BatchItemResult>,
+}
+
+impl BatchReport {
+    fn count(&self, status: BatchItemStatus) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+}
+
+/// Events of the `POST /ask/batch` response stream
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum BatchEvent {
+    /// Sent first, with the id to cancel or look up the batch by
+    Started {
+        batch_id: String,
+        items: usize,
+    },
+    ItemFinished {
+        result: BatchItemResult,
+    },
+    /// Sent last, once no item is left running
+    Finished {
+        batch: BatchReport,
+    },
+}
+
+fn format_event(event: &BatchEvent) -> String {
+    let json = serde_json::to_string(event).unwrap_or_else(|e| {
+        format!(
+            r#"{{"type":"Error","error":"Failed to serialize event: {}"}}"#,
+            e
+        )
+    });
+    format!("data: {}\n\n", json)
+}
+
+pub struct Batch {
+    id: String,
+    report: Mutex<BatchReport>,
+    cancel: CancellationToken,
+}
+
+impl Batch {
+    fn new(id: String, prompt: String, items: Vec<String>) -> Self {
+        let results = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| BatchItemResult {
+                index,
+                item,
+                status: BatchItemStatus::Pending,
+                session_id: None,
+                output: None,
+                error: None,
+                tokens: 0,
+                duration_ms: None,
+            })
+            .collect();
+        Self {
+            id: id.clone(),
+            report: Mutex::new(BatchReport {
+                id,
+                status: BatchStatus::Running,
+                prompt,
+                created_at: Utc::now(),
+                finished_at: None,
+                results,
+            }),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    fn report(&self) -> MutexGuard<'_, BatchReport> {
+        self.report
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The batches started since the server started, with the most recent finished ones
+#[derive(Clone, Default)]
+pub struct Batches {
+    batches: Arc<Mutex<HashMap<String, Arc<Batch>>>>,
+}
+
+impl Batches {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<Batch>>> {
+        self.batches
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn insert(&self, batch: Arc<Batch>) {
+        let mut batches = self.lock();
+        let mut finished: Vec<(DateTime<Utc>, String)> = batches
+            .values()
+            .filter_map(|batch| {
+                let report = batch.report();
+                report.finished_at.map(|at| (at, batch.id.clone()))
+            })
+            .collect();
+        if finished.len() >= MAX_FINISHED_BATCHES {
+            finished.sort();
+            for (_, id) in &finished[..=finished.len() - MAX_FINISHED_BATCHES] {
+                batches.remove(id);
+            }
+        }
+        batches.insert(batch.id.clone(), batch);
+    }
+
+    fn get(&self, id: &str) -> Option<Arc<Batch>> {
+        self.lock().get(id).cloned()
+    }
+}
+
+fn unprocessable(message: impl Into<String>) -> Response {
+    (StatusCode::UNPROCESSABLE_ENTITY, message.into()).into_response()
+}
+
+fn generate_batch_id() -> String {
+    let mut suffix = [0u8; 3];
+    rand::thread_rng().fill_bytes(&mut suffix);
+    format!(
+        "batch_{}_{}",
+        session::generate_session_id(),
+        suffix
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/ask/batch",
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Stream of Started, then ItemFinished for each item as it finishes, then Finished with every result", content_type = "text/event-stream"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 412, description = "Agent not initialized"),
+        (status = 422, description = "The prompt has no {{item}} placeholder, there are no or too many items, or the working directory can't be used"),
+        (status = 429, description = "Rate limited, or the daily token budget is used up")
+    ),
+    security(("api_key" = [])),
+    tag = "Batch"
+)]
+// Run the prompt over every item. The batch carries on when the client disconnects; its
+// progress is also sent on /events.
+async fn start_batch(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Json(request): Json<BatchRequest>,
+) -> Result<SseResponse, Response> {
+    if !request.prompt.contains(ITEM_PLACEHOLDER) {
+        return Err(unprocessable(format!(
+            "The prompt needs a {} placeholder",
+            ITEM_PLACEHOLDER
+        )));
+    }
+    if request.items.is_empty() || request.items.len() > MAX_BATCH_ITEMS {
+        return Err(unprocessable(format!(
+            "A batch takes 1 to {} items",
+            MAX_BATCH_ITEMS
+        )));
+    }
+    let working_dir =
+        validate_working_dir(&request.working_dir, allowed_working_dir_roots().as_deref())
+            .map_err(unprocessable)?;
+    state
+        .rate_limiter
+        .check_token_budget()
+        .map_err(IntoResponse::into_response)?;
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED.into_response())?;
+
+    let batch = Arc::new(Batch::new(
+        generate_batch_id(),
+        request.prompt,
+        request.items,
+    ));
+    state.batches.insert(Arc::clone(&batch));
+
+    let total = batch.report().results.len();
+    // Room for every event, so a client that reads slowly never holds up the batch
+    let (tx, rx) = mpsc::channel(total + 2);
+    let _ = tx.try_send(format_event(&BatchEvent::Started {
+        batch_id: batch.id.clone(),
+        items: total,
+    }));
+    let session_config = item_session_config(&batch.id, working_dir);
+    tokio::spawn(run_batch(
+        state,
+        agent,
+        batch,
+        session_config,
+        request
+            .concurrency
+            .unwrap_or(DEFAULT_CONCURRENCY)
+            .clamp(1, MAX_CONCURRENCY),
+        Duration::from_secs(
+            request
+                .item_timeout_secs
+                .unwrap_or(DEFAULT_ITEM_TIMEOUT_SECS),
+        ),
+        tx,
+    ));
+
+    Ok(SseResponse::new(ReceiverStream::new(rx)))
+}
+
+/// The configuration of the items' sessions, which each get an id of their own
+fn item_session_config(batch_id: &str, working_dir: PathBuf) -> SessionConfig {
+    SessionConfig {
+        id: session::Identifier::Name(batch_id.to_string()),
+        working_dir,
+        schedule_id: None,
+        // Nobody is there to approve tool calls during a batch, so those that need it are declined
+        execution_mode: Some(ReplyExecutionMode::Unattended.as_str().to_string()),
+        max_turns: None,
+        retry_config: None,
+        allowed_extensions: None,
+        persona: None,
+        goose_mode: None,
+        permissions_path: None,
+        recorded_tool_results: None,
+    }
+}
+
+/// Run the items of `batch`, `concurrency` at a time, sending each result to `tx` and
+/// `/events` as it finishes
+async fn run_batch(
+    state: Arc<AppState>,
+    agent: Arc<Agent>,
+    batch: Arc<Batch>,
+    session_config: SessionConfig,
+    concurrency: usize,
+    item_timeout: Duration,
+    tx: mpsc::Sender<String>,
+) {
+    let items: Vec<(usize, String)> = batch
+        .report()
+        .results
+        .iter()
+        .map(|result| (result.index, result.item.clone()))
+        .collect();
+    let mut results = futures::stream::iter(items)
+        .map(|(index, item)| {
+            run_item(
+                &state,
+                &agent,
+                &batch,
+                index,
+                item,
+                &session_config,
+                item_timeout,
+            )
+        })
+        .buffer_unordered(concurrency);
+
+    while let Some(result) = results.next().await {
+        batch.report().results[result.index] = result.clone();
+        state.publish_event(ServerEvent::BatchItemFinished {
+            batch_id: batch.id.clone(),
+            index: result.index,
+            session_id: result.session_id.clone(),
+            status: result.status,
+        });
+        let _ = tx.try_send(format_event(&BatchEvent::ItemFinished { result }));
+    }
+
+    let report = {
+        let mut report = batch.report();
+        report.status = if batch.cancel.is_cancelled() {
+            BatchStatus::Cancelled
+        } else {
+            BatchStatus::Finished
+        };
+        report.finished_at = Some(Utc::now());
+        report.clone()
+    };
+    state.publish_event(ServerEvent::BatchFinished {
+        batch_id: batch.id.clone(),
+        status: report.status,
+        succeeded: report.count(BatchItemStatus::Succeeded),
+        failed: report.results.len() - report.count(BatchItemStatus::Succeeded),
+    });
+    let _ = tx.try_send(format_event(&BatchEvent::Finished { batch: report }));
+}
+
+/// Run the prompt for one item in a session of its own
+async fn run_item(
+    state: &AppState,
+    agent: &Agent,
+    batch: &Batch,
+    index: usize,
+    item: String,
+    session_config: &SessionConfig,
+    timeout: Duration,
+) -> BatchItemResult {
+    let session_id = format!("{}_{}", batch.id, index);
+    let mut result = BatchItemResult {
+        index,
+        item,
+        status: BatchItemStatus::Cancelled,
+        session_id: None,
+        output: None,
+        error: None,
+        tokens: 0,
+        duration_ms: None,
+    };
+    if batch.cancel.is_cancelled() {
+        return result;
+    }
+    // Every item is a new session, so they stop starting once the budget is spent
+    if state.rate_limiter.check_token_budget().is_err() {
+        result.status = BatchItemStatus::Failed;
+        result.error = Some("The daily token budget is used up".to_string());
+        return result;
+    }
+
+    let session_path = match session::get_path(session::Identifier::Name(session_id.clone())) {
+        Ok(path) => path,
+        Err(e) => {
+            result.status = BatchItemStatus::Failed;
+            result.error = Some(format!("Failed to get session path: {}", e));
+            return result;
+        }
+    };
+    let mut metadata = SessionMetadata::new(session_config.working_dir.clone());
+    metadata.description = format!("Item {} of {}", index + 1, batch.id);
+    metadata.origin = SessionOrigin::Batch;
+    metadata.batch_id = Some(batch.id.clone());
+    if let Err(e) = session::storage::save_messages_with_metadata(&session_path, &metadata, &[]) {
+        error!("Failed to create batch session {}: {:?}", session_id, e);
+        result.status = BatchItemStatus::Failed;
+        result.error = Some("Failed to create the item's session".to_string());
+        return result;
+    }
+    result.session_id = Some(session_id.clone());
+    {
+        let mut report = batch.report();
+        report.results[index].status = BatchItemStatus::Running;
+        report.results[index].session_id = Some(session_id.clone());
+    }
+
+    let session_config = SessionConfig {
+        id: session::Identifier::Name(session_id.clone()),
+        ..session_config.clone()
+    };
+    let prompt = batch
+        .report()
+        .prompt
+        .replace(ITEM_PLACEHOLDER, &result.item);
+    let mut conversation = vec![Message::user().with_text(prompt)];
+    let cancel = batch.cancel.child_token();
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(
+        timeout,
+        run_agent(agent, &session_config, &mut conversation, cancel.clone()),
+    )
+    .await;
+    // Stops the agent when the item timed out
+    cancel.cancel();
+    result.duration_ms = Some(started.elapsed().as_millis() as u64);
+
+    result.status = match outcome {
+        Ok(Ok(())) if batch.cancel.is_cancelled() => BatchItemStatus::Cancelled,
+        Ok(Ok(())) => BatchItemStatus::Succeeded,
+        Ok(Err(e)) => {
+            result.error = Some(e.to_string());
+            BatchItemStatus::Failed
+        }
+        Err(_) => {
+            result.error = Some(format!("Timed out after {}s", timeout.as_secs()));
+            BatchItemStatus::TimedOut
+        }
+    };
+    result.output = conversation
+        .iter()
+        .skip(1)
+        .rev()
+        .find(|message| message.role == Role::Assistant)
+        .map(Message::as_concat_text);
+
+    // The agent records token usage in the metadata while it replies
+    let saved = session::read_metadata(&session_path).and_then(|mut metadata| {
+        metadata.message_count = conversation.len();
+        session::storage::save_messages_with_metadata(&session_path, &metadata, &conversation)
+    });
+    if let Err(e) = saved {
+        error!("Failed to save batch session {}: {:?}", session_id, e);
+    }
+    result.tokens = session_tokens(&session_path);
+    state.rate_limiter.record_tokens(result.tokens);
+    result
+}
+
+/// Reply to the last message of `conversation`, adding the agent's messages to it
+async fn run_agent(
+    agent: &Agent,
+    session_config: &SessionConfig,
+    conversation: &mut Vec<Message>,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    let mut stream = agent
+        .reply(conversation, Some(session_config.clone()), Some(cancel))
+        .await?;
+    while let Some(event) = stream.next().await {
+        match event? {
+            AgentEvent::Message(message) => push_message(conversation, message),
+            AgentEvent::HistoryReplaced(messages) => *conversation = messages,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/ask/batch/{batch_id}",
+    params(
+        ("batch_id" = String, Path, description = "Id of the batch, from its Started event")
+    ),
+    responses(
+        (status = 200, description = "The batch and the results of its items so far", body = BatchReport),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "No such batch since the server started")
+    ),
+    security(("api_key" = [])),
+    tag = "Batch"
+)]
+async fn get_batch(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::SessionsRead>,
+    Path(batch_id): Path<String>,
+) -> Result<Json<BatchReport>, StatusCode> {
+    let batch = state.batches.get(&batch_id).ok_or(StatusCode::NOT_FOUND)?;
+    let report = batch.report().clone();
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/ask/batch/{batch_id}/cancel",
+    params(
+        ("batch_id" = String, Path, description = "Id of the batch, from its Started event")
+    ),
+    responses(
+        (status = 200, description = "Items not started yet won't run and running ones are stopped", body = BatchReport),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "No such batch since the server started")
+    ),
+    security(("api_key" = [])),
+    tag = "Batch"
+)]
+async fn cancel_batch(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Path(batch_id): Path<String>,
+) -> Result<Json<BatchReport>, StatusCode> {
+    let batch = state.batches.get(&batch_id).ok_or(StatusCode::NOT_FOUND)?;
+    batch.cancel.cancel();
+    let report = batch.report().clone();
+    Ok(Json(report))
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/ask/batch", post(start_batch))
+        .route("/ask/batch/{batch_id}", get(get_batch))
+        .route("/ask/batch/{batch_id}/cancel", post(cancel_batch))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::utils::use_test_session_dir;
+    use axum::body::Body;
+    use axum::http::Request;
+    use goose::audit::AuditLog;
+    use goose::model::ModelConfig;
+    use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+    use goose::providers::errors::ProviderError;
+    use mcp_core::tool::ToolCall;
+    use tower::ServiceExt;
+
+    /// Answers with the user's message, or fails for good when it mentions "broken"
+    #[derive(Clone)]
+    struct EchoProvider {
+        model_config: ModelConfig,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for EchoProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            let text = messages
+                .last()
+                .map(Message::as_concat_text)
+                .unwrap_or_default();
+            if text.contains("broken") {
+                return Err(ProviderError::ModelNotFound("broken".to_string()));
+            }
+            Ok((
+                Message::assistant().with_text(format!("Echo: {}", text)),
+                ProviderUsage::new("echo".to_string(), Usage::new(Some(10), Some(5), Some(15))),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+    }
+
+    /// Calls the tool named by the user's message, then reports what the tool answered
+    #[derive(Clone)]
+    struct ToolCallProvider {
+        model_config: ModelConfig,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for ToolCallProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            let last = messages.last().unwrap();
+            let message = match last.content.first() {
+                Some(goose::message::MessageContent::ToolResponse(response)) => {
+                    let answer = response
+                        .tool_result
+                        .as_ref()
+                        .ok()
+                        .and_then(|contents| contents.first())
+                        .and_then(|content| content.as_text())
+                        .map(|text| text.text.clone())
+                        .unwrap_or_default();
+                    Message::assistant().with_text(format!("Tool said: {}", answer))
+                }
+                _ => {
+                    let tool = last.as_concat_text().replace("Run ", "");
+                    Message::assistant()
+                        .with_tool_request("call_1", Ok(ToolCall::new(tool, serde_json::json!({}))))
+                }
+            };
+            Ok((
+                message,
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+    }
+
+    async fn echo_state() -> Arc<AppState> {
+        let agent = Agent::new();
+        let _ = agent
+            .update_provider(Arc::new(EchoProvider {
+                model_config: ModelConfig::new("echo").unwrap(),
+            }))
+            .await;
+        AppState::new(Arc::new(agent), "test-secret".to_string()).await
+    }
+
+    #[tokio::test]
+    async fn test_batch_runs_every_item_despite_failures() {
+        use_test_session_dir();
+        let state = echo_state().await;
+        let request = serde_json::json!({
+            "prompt": "Classify {{item}}",
+            "items": ["apple", "broken carrot", "pear"],
+            "working_dir": std::env::temp_dir(),
+            "concurrency": 2,
+        });
+        let response = routes(Arc::clone(&state))
+            .oneshot(
+                Request::builder()
+                    .uri("/ask/batch")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::from(request.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let events: Vec<serde_json::Value> = String::from_utf8(body.to_vec())
+            .unwrap()
+            .split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+
+        assert_eq!(events[0]["type"], "Started");
+        let batch_id = events[0]["batch_id"].as_str().unwrap().to_string();
+        let report = state.batches.get(&batch_id).unwrap().report().clone();
+        let metadata = session::read_metadata(
+            &session::get_path(session::Identifier::Name(format!("{}_0", batch_id))).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(metadata.batch_id.as_deref(), Some(batch_id.as_str()));
+        assert_eq!(metadata.origin, SessionOrigin::Batch);
+
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| e["type"] == "ItemFinished")
+                .count(),
+            3
+        );
+        let finished = events.last().unwrap();
+        assert_eq!(finished["type"], "Finished");
+        assert_eq!(finished["batch"]["status"], "finished");
+        assert_eq!(report.results[0].status, BatchItemStatus::Succeeded);
+        assert_eq!(
+            report.results[0].output.as_deref(),
+            Some("Echo: Classify apple")
+        );
+        assert_eq!(report.results[0].tokens, 15);
+        assert_eq!(report.results[1].status, BatchItemStatus::Failed);
+        assert_eq!(report.results[2].status, BatchItemStatus::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_batch_starts_no_more_items() {
+        let state = echo_state().await;
+        let agent = state.get_agent().await.unwrap();
+        let batch = Arc::new(Batch::new(
+            generate_batch_id(),
+            "Classify {{item}}".to_string(),
+            vec!["apple".to_string(), "pear".to_string()],
+        ));
+        state.batches.insert(Arc::clone(&batch));
+        batch.cancel.cancel();
+
+        let (tx, _rx) = mpsc::channel(4);
+        let session_config = item_session_config(&batch.id, std::env::temp_dir());
+        run_batch(
+            Arc::clone(&state),
+            agent,
+            Arc::clone(&batch),
+            session_config,
+            1,
+            Duration::from_secs(30),
+            tx,
+        )
+        .await;
+
+        let report = batch.report().clone();
+        assert_eq!(report.status, BatchStatus::Cancelled);
+        assert!(report
+            .results
+            .iter()
+            .all(|r| r.status == BatchItemStatus::Cancelled && r.session_id.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_batch_needs_an_item_placeholder() {
+        let state = echo_state().await;
+        let request = serde_json::json!({
+            "prompt": "Classify this",
+            "items": ["apple"],
+            "working_dir": std::env::temp_dir(),
+        });
+        let response = routes(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/ask/batch")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::from(request.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_batch_refuses_tools_instead_of_asking() {
+        use_test_session_dir();
+        // The refusals are kept out of the user's audit log
+        let agent = Agent::new().with_audit_log(Arc::new(AuditLog::disabled()));
+        let _ = agent
+            .update_provider(Arc::new(ToolCallProvider {
+                model_config: ModelConfig::new("mock").unwrap(),
+            }))
+            .await;
+        let state = AppState::new(Arc::new(agent), "test-secret".to_string()).await;
+        let agent = state.get_agent().await.unwrap();
+
+        let permissions_file = tempfile::NamedTempFile::new().unwrap();
+        let mut permissions = goose::config::PermissionManager::new(permissions_file.path());
+        permissions.update_user_permission(
+            "developer__shell",
+            goose::config::permission::PermissionLevel::NeverAllow,
+        );
+
+        let batch = Arc::new(Batch::new(
+            generate_batch_id(),
+            "Run {{item}}".to_string(),
+            vec![
+                "developer__shell".to_string(),
+                "developer__text_editor".to_string(),
+            ],
+        ));
+        state.batches.insert(Arc::clone(&batch));
+        let (tx, _rx) = mpsc::channel(16);
+        let session_config = SessionConfig {
+            goose_mode: Some("approve".to_string()),
+            permissions_path: Some(permissions_file.path().to_path_buf()),
+            ..item_session_config(&batch.id, std::env::temp_dir())
+        };
+        run_batch(
+            Arc::clone(&state),
+            agent,
+            Arc::clone(&batch),
+            session_config,
+            1,
+            Duration::from_secs(30),
+            tx,
+        )
+        .await;
+
+        let report = batch.report().clone();
+        // The never allowed tool is refused, and the one that needs approval is declined
+        // rather than waiting for an answer nobody will give
+        assert_eq!(report.results[0].status, BatchItemStatus::Succeeded);
+        assert!(report.results[0]
+            .output
+            .as_deref()
+            .unwrap()
+            .contains("declined to run this tool"));
+        assert_eq!(report.results[1].status, BatchItemStatus::Succeeded);
+        assert!(report.results[1]
+            .output
+            .as_deref()
+            .unwrap()
+            .contains("nobody is there to give it"));
+    }
+}
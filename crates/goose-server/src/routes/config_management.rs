@@ -1,28 +1,41 @@
-use super::utils::verify_secret_key;
-use crate::routes::utils::check_provider_configured;
+use super::events::ServerEvent;
+use super::reply::SseResponse;
+use super::utils::{scopes, RequireScope};
+use crate::routes::utils::{check_provider_configured, json_with_etag};
 use crate::state::AppState;
 use axum::{
-    extract::State,
-    routing::{delete, get, post},
+    extract::{Path, Query, State},
+    response::Response,
+    routing::{delete, get, patch, post},
     Json, Router,
 };
 use etcetera::{choose_app_strategy, AppStrategy};
-use goose::config::Config;
 use goose::config::APP_STRATEGY;
+use goose::config::{
+    backup_path, migrate_plaintext_secrets, restore_backup, write_backup, Config, ConfigBundle,
+    ConfigBundleError, ConfigError, ConfigImportReport, ConfigIssue, ConfigIssueSeverity,
+    ConfigReload, MergeStrategy, SecretMigrationReport,
+};
 use goose::config::{extensions::name_to_key, PermissionManager};
 use goose::config::{ExtensionConfigManager, ExtensionEntry};
 use goose::model::ModelConfig;
-use goose::providers::base::ProviderMetadata;
+use goose::providers::base::{Provider, ProviderMetadata};
+use goose::providers::capabilities::{self, ModelCapabilities};
 use goose::providers::pricing::{
     get_all_pricing, get_model_pricing, parse_model_id, refresh_pricing,
 };
-use goose::providers::providers as get_providers;
-use goose::{agents::ExtensionConfig, config::permission::PermissionLevel};
+use goose::providers::{create as create_provider, providers as get_providers};
+use goose::{
+    agents::ExtensionConfig,
+    config::permission::{PermissionLevel, PermissionRule, ShellCommandPatterns},
+};
 use http::{HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_yaml;
 use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
 use utoipa::ToSchema;
 
 #[derive(Serialize, ToSchema)]
@@ -50,9 +63,106 @@ pub struct ConfigKeyQuery {
     pub is_secret: bool,
 }
 
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+pub struct ReadAllConfigQuery {
+    /// Return values as stored, with `${env:VAR}` references left in place for editing
+    #[serde(default)]
+    pub raw: bool,
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct ConfigResponse {
+    /// Config values with the active profile's overrides applied
     pub config: HashMap<String, Value>,
+    /// The active profile, if any
+    pub profile: Option<String>,
+    /// The profile each overridden key comes from; other keys come from the base config
+    pub sources: HashMap<String, String>,
+}
+
+/// Sent on `/config/events` when the config file changes on disk
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum ConfigEvent {
+    /// The config file was reloaded, so settings views should refresh
+    Reloaded {
+        changed_keys: Vec<String>,
+        provider: Option<String>,
+        model: Option<String>,
+    },
+    /// The config file couldn't be parsed and the previous values are still in use
+    Invalid { error: String },
+}
+
+/// Sampling settings sent to the model. A field that is left out keeps its current value and
+/// `null` clears it, so the provider's default is used.
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateModelSettingsRequest {
+    /// Sampling temperature, at least 0.0. Providers clamp it to the range they accept.
+    #[serde(default, deserialize_with = "deserialize_setting")]
+    #[schema(value_type = Option<f64>)]
+    pub temperature: Option<Option<f64>>,
+    /// Nucleus sampling cutoff between 0.0 and 1.0
+    #[serde(default, deserialize_with = "deserialize_setting")]
+    #[schema(value_type = Option<f64>)]
+    pub top_p: Option<Option<f64>>,
+    /// Maximum number of tokens to generate
+    #[serde(default, deserialize_with = "deserialize_setting")]
+    #[schema(value_type = Option<i32>)]
+    pub max_tokens: Option<Option<i32>>,
+    /// Sequences that end generation
+    #[serde(default, deserialize_with = "deserialize_setting")]
+    #[schema(value_type = Option<Vec<String>>)]
+    pub stop_sequences: Option<Option<Vec<String>>>,
+}
+
+/// Tells a setting set to `null` apart from one that was left out
+fn deserialize_setting<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// The sampling settings in effect. Environment variables take precedence over saved values.
+#[derive(Serialize, ToSchema)]
+pub struct ModelSettingsResponse {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<i32>,
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+/// Switches the agent to another provider and model in one step
+#[derive(Deserialize, ToSchema)]
+pub struct SetActiveModelRequest {
+    pub provider: String,
+    pub model: String,
+    /// Other config keys of the provider to save along with it, such as its API key
+    #[serde(default)]
+    pub config: HashMap<String, Value>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ActiveModelResponse {
+    pub provider: String,
+    /// The settings the agent's new provider was built with
+    #[schema(value_type = Object)]
+    pub model_config: ModelConfig,
+    pub capabilities: ModelCapabilities,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetProfileRequest {
+    /// The profile to switch to, or null for the base config
+    pub profile: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProfilesResponse {
+    pub profile: Option<String>,
+    pub profiles: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -64,6 +174,13 @@ pub struct ProviderDetails {
     pub is_configured: bool,
 }
 
+/// A model a provider offers, with what it can do
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProviderModel {
+    pub name: String,
+    pub capabilities: ModelCapabilities,
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct ProvidersResponse {
     pub providers: Vec<ProviderDetails>,
@@ -77,7 +194,20 @@ pub struct ToolPermission {
 
 #[derive(Deserialize, ToSchema)]
 pub struct UpsertPermissionsQuery {
+    #[serde(default)]
     pub tool_permissions: Vec<ToolPermission>,
+    /// Replaces the ordered permission rules when present
+    #[serde(default)]
+    pub rules: Option<Vec<PermissionRule>>,
+    /// Replaces the allowed and denied shell command patterns when present
+    #[serde(default)]
+    pub shell_commands: Option<ShellCommandPatterns>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PermissionRulesResponse {
+    pub rules: Vec<PermissionRule>,
+    pub shell_commands: ShellCommandPatterns,
 }
 
 #[utoipa::path(
@@ -90,12 +220,9 @@ pub struct UpsertPermissionsQuery {
     )
 )]
 pub async fn upsert_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     Json(query): Json<UpsertConfigQuery>,
 ) -> Result<Json<Value>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let config = Config::global();
     let result = config.set(&query.key, query.value, query.is_secret);
 
@@ -116,12 +243,9 @@ pub async fn upsert_config(
     )
 )]
 pub async fn remove_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     Json(query): Json<ConfigKeyQuery>,
 ) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let config = Config::global();
 
     let result = if query.is_secret {
@@ -146,12 +270,9 @@ pub async fn remove_config(
     )
 )]
 pub async fn read_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     Json(query): Json<ConfigKeyQuery>,
 ) -> Result<Json<Value>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     if query.key == "model-limits" {
         let limits = ModelConfig::get_all_model_limits();
         return Ok(Json(
@@ -182,13 +303,26 @@ pub async fn read_config(
     )
 )]
 pub async fn get_extensions(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
 ) -> Result<Json<ExtensionResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     match ExtensionConfigManager::get_all() {
-        Ok(extensions) => Ok(Json(ExtensionResponse { extensions })),
+        Ok(extensions) => {
+            // Never echo secret values back, even if they ended up in plaintext config
+            let config = Config::global();
+            let is_secret = |name: &str, value: &str| {
+                config
+                    .get_secret::<String>(name)
+                    .is_ok_and(|secret| secret == value)
+            };
+            let extensions = extensions
+                .into_iter()
+                .map(|entry| ExtensionEntry {
+                    enabled: entry.enabled,
+                    config: entry.config.with_masked_envs(is_secret),
+                })
+                .collect();
+            Ok(Json(ExtensionResponse { extensions }))
+        }
         Err(err) => {
             if err
                 .downcast_ref::<goose::config::base::ConfigError>()
@@ -214,12 +348,9 @@ pub async fn get_extensions(
     )
 )]
 pub async fn add_extension(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     Json(extension_query): Json<ExtensionQuery>,
 ) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let extensions =
         ExtensionConfigManager::get_all().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let key = name_to_key(&extension_query.name);
@@ -251,12 +382,9 @@ pub async fn add_extension(
     )
 )]
 pub async fn remove_extension(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     axum::extract::Path(name): axum::extract::Path<String>,
 ) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let key = name_to_key(&name);
     match ExtensionConfigManager::remove(&key) {
         Ok(_) => Ok(Json(format!("Removed extension {}", name))),
@@ -267,38 +395,113 @@ pub async fn remove_extension(
 #[utoipa::path(
     get,
     path = "/config",
+    params(ReadAllConfigQuery),
     responses(
         (status = 200, description = "All configuration values retrieved successfully", body = ConfigResponse)
     )
 )]
 pub async fn read_all_config(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Query(query): Query<ReadAllConfigQuery>,
 ) -> Result<Json<ConfigResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let config = Config::global();
 
-    let values = config
-        .load_values()
+    let effective = config
+        .load_effective_values()
         .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
 
-    Ok(Json(ConfigResponse { config: values }))
+    let values = if query.raw {
+        effective.values
+    } else {
+        // A value whose variable is unset is returned as stored rather than failing the listing
+        effective
+            .values
+            .into_iter()
+            .map(|(key, value)| {
+                let value = Config::interpolate_env_vars(&key, value.clone()).unwrap_or(value);
+                (key, value)
+            })
+            .collect()
+    };
+
+    Ok(Json(ConfigResponse {
+        config: values,
+        profile: state.profile().await,
+        sources: effective.sources,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/config/profile",
+    responses(
+        (status = 200, description = "Active and available profiles retrieved successfully", body = ProfilesResponse),
+        (status = 422, description = "Config file could not be read")
+    )
+)]
+pub async fn get_profiles(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+) -> Result<Json<ProfilesResponse>, StatusCode> {
+    let profiles = Config::global()
+        .list_profiles()
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    Ok(Json(ProfilesResponse {
+        profile: state.profile().await,
+        profiles,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/config/profile",
+    request_body = SetProfileRequest,
+    responses(
+        (status = 200, description = "Profile switched successfully", body = ProfilesResponse),
+        (status = 404, description = "Profile not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn set_profile(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Json(request): Json<SetProfileRequest>,
+) -> Result<Json<ProfilesResponse>, StatusCode> {
+    let config = Config::global();
+    let profiles = config
+        .list_profiles()
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+    if let Some(profile) = &request.profile {
+        if !profiles.contains(profile) {
+            return Err(StatusCode::NOT_FOUND);
+        }
+    }
+
+    if let Err(e) = state.set_profile(request.profile).await {
+        tracing::error!("Failed to switch config profile: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(Json(ProfilesResponse {
+        profile: state.profile().await,
+        profiles,
+    }))
 }
 
 #[utoipa::path(
     get,
     path = "/config/providers",
     responses(
-        (status = 200, description = "All configuration values retrieved successfully", body = [ProviderDetails])
+        (status = 200, description = "All configuration values retrieved successfully", body = [ProviderDetails]),
+        (status = 304, description = "Unchanged since the response tagged with the request's If-None-Match")
     )
 )]
 pub async fn providers(
-    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
     headers: HeaderMap,
-) -> Result<Json<Vec<ProviderDetails>>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
+) -> Result<Response, StatusCode> {
     let providers_metadata = get_providers();
 
     let providers_response: Vec<ProviderDetails> = providers_metadata
@@ -314,7 +517,71 @@ pub async fn providers(
         })
         .collect();
 
-    Ok(Json(providers_response))
+    Ok(json_with_etag(&headers, &providers_response))
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+pub struct ProviderModelsQuery {
+    /// Fetch the list from the provider even if the cached one is still fresh
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/config/providers/{name}/models",
+    params(
+        ("name" = String, Path, description = "Name of the provider"),
+        ProviderModelsQuery
+    ),
+    responses(
+        (status = 200, description = "Models the provider offers and their capabilities. A stale list may be returned while it is fetched again.", body = [ProviderModel]),
+        (status = 304, description = "Unchanged since the response tagged with the request's If-None-Match"),
+        (status = 404, description = "Unknown provider"),
+        (status = 502, description = "The provider's models could not be fetched")
+    )
+)]
+pub async fn provider_models(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Query(query): Query<ProviderModelsQuery>,
+) -> Result<Response, StatusCode> {
+    let metadata = get_providers()
+        .into_iter()
+        .find(|metadata| metadata.name == name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let fetch = move || async move {
+        let provider = create_provider(&metadata.name, ModelConfig::new(&metadata.default_model)?)?;
+        Ok::<_, anyhow::Error>(match provider.fetch_supported_models_async().await? {
+            Some(models) => models,
+            None => metadata
+                .known_models
+                .into_iter()
+                .map(|model| model.name)
+                .collect(),
+        })
+    };
+    let models = state
+        .model_cache
+        .models(&name, query.refresh, fetch)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to fetch the models of {}: {}", name, e);
+            StatusCode::BAD_GATEWAY
+        })?;
+    let rules = capabilities::configured_rules();
+    let models: Vec<ProviderModel> = models
+        .into_iter()
+        .map(|model| ProviderModel {
+            capabilities: capabilities::resolve_with(&rules, Some(&name), &model),
+            name: model,
+        })
+        .collect();
+
+    Ok(json_with_etag(&headers, &models))
 }
 
 #[derive(Serialize, ToSchema)]
@@ -348,12 +615,9 @@ pub struct PricingQuery {
     )
 )]
 pub async fn get_pricing(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(query): Json<PricingQuery>,
 ) -> Result<Json<PricingResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let configured_only = query.configured_only.unwrap_or(true);
 
     // If refresh requested (configured_only = false), refresh the cache
@@ -447,11 +711,8 @@ pub async fn get_pricing(
     )
 )]
 pub async fn init_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
 ) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let config = Config::global();
 
     if config.exists() {
@@ -480,11 +741,15 @@ pub async fn init_config(
     )
 )]
 pub async fn upsert_permissions(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     Json(query): Json<UpsertPermissionsQuery>,
 ) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
+    if let Some(shell_commands) = &query.shell_commands {
+        if let Err(e) = shell_commands.validate() {
+            tracing::warn!("Rejecting invalid shell command pattern: {}", e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
 
     let mut permission_manager = PermissionManager::default();
 
@@ -495,9 +760,35 @@ pub async fn upsert_permissions(
         );
     }
 
+    if let Some(rules) = query.rules {
+        permission_manager.set_rules(rules);
+    }
+
+    if let Some(shell_commands) = query.shell_commands {
+        permission_manager.set_shell_command_patterns(shell_commands);
+    }
+
     Ok(Json("Permissions updated successfully".to_string()))
 }
 
+#[utoipa::path(
+    get,
+    path = "/config/permissions",
+    responses(
+        (status = 200, description = "Ordered permission rules and shell command patterns", body = PermissionRulesResponse),
+    )
+)]
+pub async fn get_permission_rules(
+    _scope: RequireScope<scopes::ConfigWrite>,
+) -> Result<Json<PermissionRulesResponse>, StatusCode> {
+    let permission_manager = PermissionManager::default();
+
+    Ok(Json(PermissionRulesResponse {
+        rules: permission_manager.get_rules().to_vec(),
+        shell_commands: permission_manager.get_shell_command_patterns().clone(),
+    }))
+}
+
 #[utoipa::path(
     post,
     path = "/config/backup",
@@ -507,32 +798,14 @@ pub async fn upsert_permissions(
     )
 )]
 pub async fn backup_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
 ) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
-    let config_dir = choose_app_strategy(APP_STRATEGY.clone())
-        .expect("goose requires a home dir")
-        .config_dir();
-
-    let config_path = config_dir.join("config.yaml");
-
-    if config_path.exists() {
-        let file_name = config_path
-            .file_name()
-            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
-
-        let mut backup_name = file_name.to_os_string();
-        backup_name.push(".bak");
-
-        let backup = config_path.with_file_name(backup_name);
-        match std::fs::copy(&config_path, &backup) {
-            Ok(_) => Ok(Json(format!("Copied {:?} to {:?}", config_path, backup))),
-            Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    match write_backup(Config::global(), &PermissionManager::default()) {
+        Ok(backup) => Ok(Json(format!("Backed up config to {:?}", backup))),
+        Err(e) => {
+            tracing::error!("Failed to back up config: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
-    } else {
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
 }
 
@@ -545,14 +818,29 @@ pub async fn backup_config(
     )
 )]
 pub async fn recover_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
 ) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let config = Config::global();
 
-    // Force a reload which will trigger recovery if needed
+    // Restore the backup written by backup_config when there is one
+    if backup_path(config).exists() {
+        return match restore_backup(config, &mut PermissionManager::default()) {
+            Ok(report) if report.applied.is_empty() => Ok(Json(
+                "Config recovery completed, the config already matches the backup.".to_string(),
+            )),
+            Ok(report) => Ok(Json(format!(
+                "Config recovery completed. Restored {} entries: {}",
+                report.applied.len(),
+                report.applied.join(", ")
+            ))),
+            Err(e) => {
+                tracing::error!("Config recovery failed: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        };
+    }
+
+    // Otherwise force a reload which will trigger recovery if needed
     match config.load_values() {
         Ok(values) => {
             let recovered_keys: Vec<String> = values.keys().cloned().collect();
@@ -573,20 +861,107 @@ pub async fn recover_config(
     }
 }
 
+/// Header carrying the passphrase secrets in a config bundle are encrypted with
+const PASSPHRASE_HEADER: &str = "X-Goose-Passphrase";
+
+fn bundle_error(e: ConfigBundleError) -> (StatusCode, String) {
+    if e.is_invalid_bundle() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else {
+        tracing::error!("Config bundle error: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/config/export",
+    params(
+        ("X-Goose-Passphrase" = Option<String>, Header, description = "Include secrets, encrypted with this passphrase")
+    ),
+    responses(
+        (status = 200, description = "Config exported", body = ConfigBundle),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn export_config(
+    _scope: RequireScope<scopes::Admin>,
+    headers: HeaderMap,
+) -> Result<Json<ConfigBundle>, (StatusCode, String)> {
+    let passphrase = headers
+        .get(PASSPHRASE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|passphrase| !passphrase.is_empty());
+
+    goose::config::export_config(Config::global(), &PermissionManager::default(), passphrase)
+        .map(Json)
+        .map_err(bundle_error)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImportConfigRequest {
+    pub bundle: ConfigBundle,
+    /// How entries that differ from the local config are resolved
+    #[serde(default)]
+    pub strategy: MergeStrategy,
+    /// Passphrase the bundle's secrets were encrypted with
+    pub passphrase: Option<String>,
+    /// Report what would change without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/config/import",
+    request_body = ImportConfigRequest,
+    responses(
+        (status = 200, description = "Config bundle imported, or previewed for a dry run", body = ConfigImportReport),
+        (status = 400, description = "Invalid bundle or passphrase"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn import_config(
+    _scope: RequireScope<scopes::Admin>,
+    Json(request): Json<ImportConfigRequest>,
+) -> Result<Json<ConfigImportReport>, (StatusCode, String)> {
+    goose::config::import_config(
+        Config::global(),
+        &mut PermissionManager::default(),
+        &request.bundle,
+        request.strategy,
+        request.passphrase.as_deref(),
+        request.dry_run,
+    )
+    .map(Json)
+    .map_err(bundle_error)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MigrateSecretsRequest {
+    /// Report what would move without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ConfigValidationResponse {
+    /// Whether the config has no errors; warnings don't make it invalid
+    pub valid: bool,
+    pub issues: Vec<ConfigIssue>,
+}
+
 #[utoipa::path(
     get,
     path = "/config/validate",
     responses(
-        (status = 200, description = "Config validation result", body = String),
+        (status = 200, description = "Config validation result", body = ConfigValidationResponse),
         (status = 422, description = "Config file is corrupted")
     )
 )]
 pub async fn validate_config(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-) -> Result<Json<String>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
+    _scope: RequireScope<scopes::ConfigWrite>,
+) -> Result<Json<ConfigValidationResponse>, StatusCode> {
     let config_dir = choose_app_strategy(APP_STRATEGY.clone())
         .expect("goose requires a home dir")
         .config_dir();
@@ -594,12 +969,26 @@ pub async fn validate_config(
     let config_path = config_dir.join("config.yaml");
 
     if !config_path.exists() {
-        return Ok(Json("Config file does not exist".to_string()));
+        return Ok(Json(ConfigValidationResponse {
+            valid: true,
+            issues: Vec::new(),
+        }));
     }
 
     match std::fs::read_to_string(&config_path) {
         Ok(content) => match serde_yaml::from_str::<serde_yaml::Value>(&content) {
-            Ok(_) => Ok(Json("Config file is valid".to_string())),
+            Ok(_) => {
+                let issues = goose::config::validate_config(Config::global()).map_err(|e| {
+                    tracing::warn!("Config validation failed: {}", e);
+                    StatusCode::UNPROCESSABLE_ENTITY
+                })?;
+                Ok(Json(ConfigValidationResponse {
+                    valid: !issues
+                        .iter()
+                        .any(|issue| issue.severity == ConfigIssueSeverity::Error),
+                    issues,
+                }))
+            }
             Err(e) => {
                 tracing::warn!("Config validation failed: {}", e);
                 Err(StatusCode::UNPROCESSABLE_ENTITY)
@@ -612,6 +1001,79 @@ pub async fn validate_config(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/config/migrate_secrets",
+    request_body = MigrateSecretsRequest,
+    responses(
+        (status = 200, description = "Plaintext provider secrets moved into the keyring", body = SecretMigrationReport),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn migrate_secrets(
+    _scope: RequireScope<scopes::Admin>,
+    Json(request): Json<MigrateSecretsRequest>,
+) -> Result<Json<SecretMigrationReport>, StatusCode> {
+    migrate_plaintext_secrets(Config::global(), request.dry_run)
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to migrate secrets: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/config/reload",
+    responses(
+        (status = 200, description = "Config file reloaded", body = ConfigReload),
+        (status = 422, description = "Config file is invalid; the previous values are kept", body = String)
+    )
+)]
+pub async fn reload_config(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+) -> Result<Json<ConfigReload>, (StatusCode, String)> {
+    state
+        .reload_config()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/config/events",
+    responses(
+        (status = 200, description = "Stream of config change events", body = ConfigEvent, content_type = "text/event-stream")
+    )
+)]
+pub async fn config_events(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+) -> Result<SseResponse, StatusCode> {
+    let mut events = state.subscribe_config_events();
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let Ok(json) = serde_json::to_string(&event) else {
+                        continue;
+                    };
+                    if tx.send(format!("data: {}\n\n", json)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    Ok(SseResponse::new(ReceiverStream::new(rx)))
+}
+
 #[utoipa::path(
     get,
     path = "/config/current-model",
@@ -620,11 +1082,8 @@ pub async fn validate_config(
     )
 )]
 pub async fn get_current_model(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
 ) -> Result<Json<Value>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let current_model = goose::providers::base::get_current_model();
 
     Ok(Json(serde_json::json!({
@@ -632,6 +1091,243 @@ pub async fn get_current_model(
     })))
 }
 
+impl UpdateModelSettingsRequest {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(Some(temperature)) = self.temperature {
+            if temperature < 0.0 {
+                return Err("temperature must be at least 0.0".to_string());
+            }
+        }
+        if let Some(Some(top_p)) = self.top_p {
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err("top_p must be between 0.0 and 1.0".to_string());
+            }
+        }
+        if let Some(Some(max_tokens)) = self.max_tokens {
+            if max_tokens <= 0 {
+                return Err("max_tokens must be a positive integer".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/config/model",
+    request_body = UpdateModelSettingsRequest,
+    responses(
+        (status = 200, description = "Model settings saved and applied to the agent's provider", body = ModelSettingsResponse),
+        (status = 422, description = "A setting is out of range", body = String),
+        (status = 500, description = "Internal server error", body = String)
+    )
+)]
+pub async fn update_model_settings(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Json(request): Json<UpdateModelSettingsRequest>,
+) -> Result<Json<ModelSettingsResponse>, (StatusCode, String)> {
+    request
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let config = Config::global();
+    let updates = [
+        (
+            "GOOSE_TEMPERATURE",
+            request.temperature.map(|v| v.map(|v| serde_json::json!(v))),
+        ),
+        (
+            "GOOSE_TOP_P",
+            request.top_p.map(|v| v.map(|v| serde_json::json!(v))),
+        ),
+        (
+            "GOOSE_MAX_TOKENS",
+            request.max_tokens.map(|v| v.map(|v| serde_json::json!(v))),
+        ),
+        (
+            "GOOSE_STOP_SEQUENCES",
+            request
+                .stop_sequences
+                .map(|v| v.map(|v| serde_json::json!(v))),
+        ),
+    ];
+    for (key, update) in updates {
+        let result = match update {
+            None => continue,
+            Some(Some(value)) => config.set_param(key, value),
+            Some(None) => config.delete(key),
+        };
+        result.map_err(|e| {
+            tracing::error!("Failed to save {}: {}", key, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        })?;
+    }
+
+    let model: String = config.get_param("GOOSE_MODEL").unwrap_or_default();
+    let model_config =
+        ModelConfig::new(&model).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    state.rebuild_provider().await.map_err(|e| {
+        tracing::error!("Failed to apply model settings: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })?;
+
+    Ok(Json(ModelSettingsResponse {
+        temperature: model_config.temperature,
+        top_p: model_config.top_p,
+        max_tokens: model_config.max_tokens,
+        stop_sequences: model_config.stop_sequences,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/config/active_model",
+    request_body = SetActiveModelRequest,
+    responses(
+        (status = 200, description = "Provider and model saved and in use by the agent", body = ActiveModelResponse),
+        (status = 422, description = "Unknown provider or config key, or the provider couldn't be built; nothing was changed", body = String),
+        (status = 502, description = "The test call to the provider failed; nothing was changed", body = String),
+        (status = 500, description = "Internal server error", body = String)
+    )
+)]
+pub async fn set_active_model(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Json(request): Json<SetActiveModelRequest>,
+) -> Result<Json<ActiveModelResponse>, (StatusCode, String)> {
+    switch_active_model(&state, Config::global(), request, create_provider)
+        .await
+        .map(Json)
+}
+
+/// Saves the provider, model and the provider's settings from `request` in one write to `config`,
+/// builds the provider with `create` and hands it to the agent once a test call to it succeeds.
+/// On failure the previous values are restored and the agent keeps its provider.
+async fn switch_active_model<F>(
+    state: &AppState,
+    config: &Config,
+    request: SetActiveModelRequest,
+    create: F,
+) -> Result<ActiveModelResponse, (StatusCode, String)>
+where
+    F: Fn(&str, ModelConfig) -> anyhow::Result<Arc<dyn Provider>>,
+{
+    let metadata = get_providers()
+        .into_iter()
+        .find(|metadata| metadata.name == request.provider)
+        .ok_or_else(|| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Unknown provider: {}", request.provider),
+            )
+        })?;
+    if request.model.trim().is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "model must not be empty".to_string(),
+        ));
+    }
+
+    let mut values = HashMap::from([
+        (
+            "GOOSE_PROVIDER".to_string(),
+            Value::String(request.provider.clone()),
+        ),
+        (
+            "GOOSE_MODEL".to_string(),
+            Value::String(request.model.clone()),
+        ),
+    ]);
+    let mut secrets = HashMap::new();
+    for (key, value) in request.config {
+        let config_key = metadata
+            .config_keys
+            .iter()
+            .find(|config_key| config_key.name == key)
+            .ok_or_else(|| {
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("{} is not a setting of {}", key, metadata.name),
+                )
+            })?;
+        if config_key.secret {
+            secrets.insert(key, value);
+        } else {
+            values.insert(key, value);
+        }
+    }
+
+    let _switching = state.model_switch.lock().await;
+    let internal_error = |e: ConfigError| {
+        tracing::error!("Failed to save the active model: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    };
+    let saved_values = config.load_values().map_err(internal_error)?;
+    let saved_secrets = if secrets.is_empty() {
+        HashMap::new()
+    } else {
+        config.load_secrets().map_err(internal_error)?
+    };
+    let rollback = || {
+        if let Err(e) = config.save_values(saved_values.clone()) {
+            tracing::error!("Failed to restore the config after a model switch: {}", e);
+        }
+        for key in secrets.keys() {
+            let result = match saved_secrets.get(key) {
+                Some(value) => config.set_secret(key, value.clone()),
+                None => config.delete_secret(key),
+            };
+            if let Err(e) = result {
+                tracing::error!("Failed to restore {} after a model switch: {}", key, e);
+            }
+        }
+    };
+
+    let saved = config.set_params(values).and_then(|_| {
+        secrets
+            .iter()
+            .try_for_each(|(key, value)| config.set_secret(key, value.clone()))
+    });
+    if let Err(e) = saved {
+        rollback();
+        return Err(internal_error(e));
+    }
+
+    let provider = match ModelConfig::new(&request.model)
+        .map_err(anyhow::Error::from)
+        .and_then(|model_config| create(&metadata.name, model_config))
+    {
+        Ok(provider) => provider,
+        Err(e) => {
+            rollback();
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string()));
+        }
+    };
+    if let Err(e) = provider.test_connection().await {
+        rollback();
+        tracing::warn!("Test call to {} failed: {}", metadata.name, e);
+        return Err((StatusCode::BAD_GATEWAY, e.to_string()));
+    }
+    if let Ok(agent) = state.get_agent().await {
+        if let Err(e) = agent.update_provider(Arc::clone(&provider)).await {
+            rollback();
+            tracing::error!("Failed to switch the agent's provider: {}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    state.publish_event(ServerEvent::ModelChanged {
+        provider: metadata.name.clone(),
+        model: request.model.clone(),
+    });
+    Ok(ActiveModelResponse {
+        capabilities: capabilities::resolve(Some(&metadata.name), &request.model),
+        model_config: provider.get_model_config(),
+        provider: metadata.name,
+    })
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/config", get(read_all_config))
@@ -642,41 +1338,65 @@ pub fn routes(state: Arc<AppState>) -> Router {
         .route("/config/extensions", post(add_extension))
         .route("/config/extensions/{name}", delete(remove_extension))
         .route("/config/providers", get(providers))
+        .route("/config/providers/{name}/models", get(provider_models))
         .route("/config/pricing", post(get_pricing))
         .route("/config/init", post(init_config))
         .route("/config/backup", post(backup_config))
         .route("/config/recover", post(recover_config))
+        .route("/config/export", get(export_config))
+        .route("/config/import", post(import_config))
         .route("/config/validate", get(validate_config))
         .route("/config/permissions", post(upsert_permissions))
+        .route("/config/permissions", get(get_permission_rules))
         .route("/config/current-model", get(get_current_model))
+        .route("/config/model", patch(update_model_settings))
+        .route("/config/active_model", post(set_active_model))
+        .route("/config/profile", get(get_profiles))
+        .route("/config/profile", post(set_profile))
+        .route("/config/reload", post(reload_config))
+        .route("/config/migrate_secrets", post(migrate_secrets))
+        .route("/config/events", get(config_events))
         .with_state(state)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use goose::message::Message;
+    use goose::providers::base::ProviderUsage;
+    use goose::providers::errors::ProviderError;
+
+    /// Fails every call, like a provider whose host can't be reached
+    struct UnreachableProvider {
+        model_config: ModelConfig,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for UnreachableProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            Err(ProviderError::RequestFailed(
+                "connection refused".to_string(),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+    }
 
     #[tokio::test]
     async fn test_read_model_limits() {
-        let test_state = AppState::new(
-            Arc::new(goose::agents::Agent::default()),
-            "test".to_string(),
-        )
-        .await;
-        let sched_storage_path = choose_app_strategy(APP_STRATEGY.clone())
-            .unwrap()
-            .data_dir()
-            .join("schedules.json");
-        let sched = goose::scheduler_factory::SchedulerFactory::create_legacy(sched_storage_path)
-            .await
-            .unwrap();
-        test_state.set_scheduler(sched).await;
-        let mut headers = HeaderMap::new();
-        headers.insert("X-Secret-Key", "test".parse().unwrap());
-
         let result = read_config(
-            State(test_state),
-            headers,
+            RequireScope::granted(),
             Json(ConfigKeyQuery {
                 key: "model-limits".to_string(),
                 is_secret: false,
@@ -695,4 +1415,94 @@ mod tests {
         assert!(gpt4_limit.is_some());
         assert_eq!(gpt4_limit.unwrap().context_limit, 128_000);
     }
+
+    #[tokio::test]
+    async fn test_providers_honor_if_none_match() {
+        let mut headers = HeaderMap::new();
+
+        let response = providers(RequireScope::granted(), headers.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[http::header::ETAG].clone();
+
+        headers.insert(http::header::IF_NONE_MATCH, etag.clone());
+        let response = providers(RequireScope::granted(), headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[http::header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn test_update_model_settings_rejects_out_of_range_values() {
+        let state = AppState::new(
+            Arc::new(goose::agents::Agent::default()),
+            "test".to_string(),
+        )
+        .await;
+        let request: UpdateModelSettingsRequest =
+            serde_json::from_value(serde_json::json!({"top_p": 1.5, "max_tokens": null})).unwrap();
+        assert_eq!(request.max_tokens, Some(None));
+        assert_eq!(request.stop_sequences, None);
+
+        let result =
+            update_model_settings(State(state), RequireScope::granted(), Json(request)).await;
+        let (status, message) = result.err().unwrap();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(message.contains("top_p"));
+    }
+
+    #[tokio::test]
+    async fn test_active_model_rolls_back_when_test_call_fails() {
+        let agent = goose::agents::Agent::default();
+        agent
+            .update_provider(Arc::new(UnreachableProvider {
+                model_config: ModelConfig::new("previous-model").unwrap(),
+            }))
+            .await
+            .unwrap();
+        let state = AppState::new(Arc::new(agent), "test".to_string()).await;
+        let mut events = state.subscribe_server_events();
+        let config_file = tempfile::NamedTempFile::new().unwrap();
+        let config = Config::new(config_file.path(), "goose-test").unwrap();
+        let before = config.load_values().unwrap();
+
+        let unreachable = |_: &str, model_config: ModelConfig| {
+            Ok::<_, anyhow::Error>(
+                Arc::new(UnreachableProvider { model_config }) as Arc<dyn Provider>
+            )
+        };
+        let request = SetActiveModelRequest {
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            config: HashMap::from([(
+                "OPENAI_HOST".to_string(),
+                Value::String("http://127.0.0.1:1".to_string()),
+            )]),
+        };
+        let (status, message) = switch_active_model(&state, &config, request, unreachable)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(message.contains("connection refused"));
+
+        // The config and the agent's provider are as they were, and nobody was told otherwise
+        assert_eq!(config.load_values().unwrap(), before);
+        let provider = state.get_agent().await.unwrap().provider().await.unwrap();
+        assert_eq!(provider.get_model_config().model_name, "previous-model");
+        assert!(events.try_recv().is_err());
+
+        // Keys the provider doesn't have are rejected before anything is written
+        let request = SetActiveModelRequest {
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            config: HashMap::from([("GOOSE_MODE".to_string(), Value::Null)]),
+        };
+        let (status, _) = switch_active_model(&state, &config, request, unreachable)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(config.load_values().unwrap(), before);
+    }
 }
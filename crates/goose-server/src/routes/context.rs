@@ -1,24 +1,50 @@
-use super::utils::verify_secret_key;
+use super::utils::{scopes, RequireScope};
 use crate::state::AppState;
-use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
-    routing::post,
-    Json, Router,
-};
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use goose::context_mgmt::auto_compact::check_compaction_needed;
+use goose::context_mgmt::breakdown::{largest_messages, MessageTokens, LARGEST_MESSAGES};
+use goose::context_mgmt::truncate::{MiddleOutTruncation, OldestFirstTruncation};
 use goose::message::Message;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use utoipa::ToSchema;
 
+/// How the conversation should be brought back within the context limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Replace the conversation with a summary written by the model
+    Summarize,
+    /// Drop the oldest messages until the conversation fits
+    TruncateOldest,
+    /// Keep the first and the most recent messages, dropping from the middle
+    TruncateMiddle,
+    /// Replace older tool outputs with a placeholder, then truncate the oldest if still needed
+    DropToolOutputs,
+}
+
 /// Request payload for context management operations
 #[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ContextManageRequest {
     /// Collection of messages to be managed
     pub messages: Vec<Message>,
-    /// Operation to perform: "truncation" or "summarize"
-    pub manage_action: String,
+    /// Legacy operation selector: "truncation" or "summarize". Ignored when `strategy` is set.
+    #[serde(default)]
+    pub manage_action: Option<String>,
+    /// Strategy to apply
+    #[serde(default)]
+    pub strategy: Option<ContextStrategy>,
+}
+
+impl ContextManageRequest {
+    fn resolved_strategy(&self) -> Option<ContextStrategy> {
+        self.strategy.or(match self.manage_action.as_deref() {
+            Some("truncation") => Some(ContextStrategy::TruncateOldest),
+            Some("summarize") => Some(ContextStrategy::Summarize),
+            _ => None,
+        })
+    }
 }
 
 /// Response from context management operations
@@ -29,6 +55,12 @@ pub struct ContextManageResponse {
     pub messages: Vec<Message>,
     /// Token counts for each processed message
     pub token_counts: Vec<usize>,
+    /// Model that performed the operation, if a model was involved
+    pub model: Option<String>,
+    /// Indices into the request messages that were dropped
+    pub removed_indices: Vec<usize>,
+    /// Indices into the request messages that were kept with shortened content
+    pub modified_indices: Vec<usize>,
 }
 
 #[utoipa::path(
@@ -37,6 +69,7 @@ pub struct ContextManageResponse {
     request_body = ContextManageRequest,
     responses(
         (status = 200, description = "Context managed successfully", body = ContextManageResponse),
+        (status = 400, description = "Bad request - Unknown strategy"),
         (status = 401, description = "Unauthorized - Invalid or missing API key"),
         (status = 412, description = "Precondition failed - Agent not available"),
         (status = 500, description = "Internal server error")
@@ -48,34 +81,132 @@ pub struct ContextManageResponse {
 )]
 async fn manage_context(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(request): Json<ContextManageRequest>,
 ) -> Result<Json<ContextManageResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let agent = state
         .get_agent()
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
 
-    let mut processed_messages: Vec<Message> = vec![];
-    let mut token_counts: Vec<usize> = vec![];
+    let strategy = request.resolved_strategy().ok_or(StatusCode::BAD_REQUEST)?;
 
-    if request.manage_action == "truncation" {
-        (processed_messages, token_counts) = agent
-            .truncate_context(&request.messages)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    } else if request.manage_action == "summarize" {
-        (processed_messages, token_counts) = agent
-            .summarize_context(&request.messages)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let outcome = match strategy {
+        ContextStrategy::Summarize => {
+            let (messages, token_counts, model) = agent
+                .summarize_context_with_model(&request.messages)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            // The summary stands in for the whole conversation
+            return Ok(Json(ContextManageResponse {
+                messages,
+                token_counts,
+                model: Some(model),
+                removed_indices: (0..request.messages.len()).collect(),
+                modified_indices: vec![],
+            }));
+        }
+        ContextStrategy::TruncateOldest => {
+            agent
+                .truncate_context_with_strategy(&request.messages, &OldestFirstTruncation)
+                .await
+        }
+        ContextStrategy::TruncateMiddle => {
+            agent
+                .truncate_context_with_strategy(&request.messages, &MiddleOutTruncation)
+                .await
+        }
+        ContextStrategy::DropToolOutputs => {
+            agent.drop_tool_outputs_context(&request.messages).await
+        }
     }
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(ContextManageResponse {
-        messages: processed_messages,
-        token_counts,
+        messages: outcome.messages,
+        token_counts: outcome.token_counts,
+        model: None,
+        removed_indices: outcome.removed,
+        modified_indices: outcome.modified,
+    }))
+}
+
+/// Request payload for checking whether a conversation needs compaction
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextCheckRequest {
+    /// Conversation to check
+    pub messages: Vec<Message>,
+    /// Session the conversation belongs to, so its attachments are counted by their contents
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Share of the context limit above which compaction is needed. Defaults to
+    /// GOOSE_AUTO_COMPACT_THRESHOLD; values outside (0, 1) disable compaction.
+    #[serde(default)]
+    pub threshold: Option<f64>,
+}
+
+/// Token usage of a conversation against the current model's context limit
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextCheckResponse {
+    pub needs_compaction: bool,
+    /// Tokens the conversation uses, counted locally for the current model
+    pub current_tokens: usize,
+    /// Usable context after room for the system prompt and tools
+    pub context_limit: usize,
+    pub usage_ratio: f64,
+    /// Tokens left before compaction is needed
+    pub remaining_tokens: usize,
+    /// The largest messages, largest first, as candidates for summarizing
+    pub largest_messages: Vec<MessageTokens>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/context/check",
+    request_body = ContextCheckRequest,
+    responses(
+        (status = 200, description = "Context usage checked", body = ContextCheckResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 412, description = "Precondition failed - Agent or provider not available")
+    ),
+    security(
+        ("api_key" = [])
+    ),
+    tag = "Context Management"
+)]
+async fn check_context(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Json(request): Json<ContextCheckRequest>,
+) -> Result<Json<ContextCheckResponse>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+
+    let check = check_compaction_needed(
+        &agent,
+        &request.messages,
+        request.session_id.as_deref(),
+        request.threshold,
+    )
+    .await
+    .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+    let model = agent
+        .provider()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?
+        .get_model_config();
+
+    Ok(Json(ContextCheckResponse {
+        needs_compaction: check.needs_compaction,
+        current_tokens: check.current_tokens,
+        context_limit: check.context_limit,
+        usage_ratio: check.usage_ratio,
+        remaining_tokens: check.remaining_tokens,
+        largest_messages: largest_messages(&request.messages, &model.model_name, LARGEST_MESSAGES),
     }))
 }
 
@@ -83,5 +214,6 @@ async fn manage_context(
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/context/manage", post(manage_context))
+        .route("/context/check", post(check_context))
         .with_state(state)
 }
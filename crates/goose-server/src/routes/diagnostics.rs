@@ -0,0 +1,409 @@
+use super::utils::{scopes, RequireScope};
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::state::{ActiveStream, AppState};
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use etcetera::{choose_app_strategy, AppStrategy};
+use goose::agents::extension::ExtensionStatus;
+use goose::agents::extension_logs::tail_extension_log;
+use goose::config::{provider_secret_keys, Config, APP_STRATEGY};
+use goose::session::info::{get_valid_sorted_sessions, SessionInfo, SortOrder};
+use goose::session::{self, Redactor};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const DEFAULT_LOG_KB: u64 = 512;
+const MAX_LOG_KB: u64 = 10 * 1024;
+const RECENT_SESSIONS: usize = 5;
+const EXTENSION_LOG_LINES: usize = 200;
+
+/// Parts of config key names that mark the value as a credential
+const SECRET_KEY_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD"];
+const MASKED: &str = "[REDACTED]";
+
+#[derive(Deserialize, IntoParams)]
+pub struct DiagnosticsBundleQuery {
+    /// How much of the end of the server log to include, in KB
+    log_kb: Option<u64>,
+    /// Include the (redacted) messages of the recent sessions
+    #[serde(default)]
+    include_transcripts: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthChecks {
+    /// The agent has a provider configured
+    pub provider: bool,
+    /// The config file can be read
+    pub config: bool,
+    pub scheduler: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthReport {
+    /// The server is running and answering requests
+    pub live: bool,
+    /// The server can take replies: the agent has a provider and the config is readable
+    pub ready: bool,
+    pub checks: HealthChecks,
+    pub active_streams: usize,
+    /// Notifications dropped from reply streams because their clients read too slowly
+    pub dropped_stream_events: u64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/diagnostics/health",
+    responses(
+        (status = 200, description = "Server is ready", body = HealthReport),
+        (status = 503, description = "Server is running but not ready", body = HealthReport)
+    )
+)]
+pub async fn health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let provider = match state.get_agent().await {
+        Ok(agent) => agent.provider().await.is_ok(),
+        Err(_) => false,
+    };
+    let config = Config::global().load_values().is_ok();
+    let scheduler = state.scheduler().await.is_ok();
+
+    let ready = provider && config;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(HealthReport {
+            live: true,
+            ready,
+            checks: HealthChecks {
+                provider,
+                config,
+                scheduler,
+            },
+            active_streams: state.active_streams().len(),
+            dropped_stream_events: state.dropped_stream_events().load(Ordering::Relaxed),
+        }),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/diagnostics/bundle",
+    params(DiagnosticsBundleQuery),
+    responses(
+        (status = 200, description = "Zip archive of logs, redacted config, extension and session state", body = Vec<u8>, content_type = "application/zip"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn diagnostics_bundle(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Admin>,
+    Query(query): Query<DiagnosticsBundleQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let extensions = match state.get_agent().await {
+        Ok(agent) => agent.get_extension_statuses().await,
+        Err(_) => Vec::new(),
+    };
+    let contents = BundleContents {
+        log_kb: query.log_kb.unwrap_or(DEFAULT_LOG_KB).min(MAX_LOG_KB),
+        include_transcripts: query.include_transcripts,
+        extensions,
+        streams: state.active_streams(),
+        profile: state.profile().await,
+    };
+
+    let bytes = tokio::task::spawn_blocking(move || contents.build())
+        .await
+        .map_err(|e| {
+            error!("Diagnostics bundle task failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map_err(|e| {
+            error!("Failed to build diagnostics bundle: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let file_name = format!(
+        "goose-diagnostics-{}.zip",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+/// What goes into a diagnostics bundle, gathered from the running server before the files are
+/// read and zipped off the async runtime
+struct BundleContents {
+    log_kb: u64,
+    include_transcripts: bool,
+    extensions: Vec<ExtensionStatus>,
+    streams: Vec<ActiveStream>,
+    profile: Option<String>,
+}
+
+impl BundleContents {
+    fn build(self) -> anyhow::Result<Vec<u8>> {
+        let redactor = Redactor::from_config();
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let mut add = |name: &str, contents: &[u8]| -> anyhow::Result<()> {
+            zip.start_file(name, options)?;
+            zip.write_all(contents)?;
+            Ok(())
+        };
+        let pretty = |value: &Value| serde_json::to_vec_pretty(value).unwrap_or_default();
+
+        add(
+            "version.json",
+            &pretty(&json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "os": std::env::consts::OS,
+                "arch": std::env::consts::ARCH,
+                "debug_build": cfg!(debug_assertions),
+                "generated_at": chrono::Utc::now(),
+            })),
+        )?;
+
+        match latest_server_log() {
+            Some(path) => match tail_bytes(&path, self.log_kb * 1024) {
+                Ok(log) => add("server.log", redactor.redact_text(&log).as_bytes())?,
+                Err(e) => add(
+                    "server.log",
+                    format!("Failed to read {:?}: {}", path, e).as_bytes(),
+                )?,
+            },
+            None => add("server.log", b"No server log found")?,
+        }
+
+        add(
+            "config.json",
+            &pretty(&redacted_config(&redactor, self.profile)),
+        )?;
+
+        add(
+            "extensions.json",
+            &pretty(&serde_json::to_value(&self.extensions)?),
+        )?;
+        for extension in &self.extensions {
+            if let Ok(lines) = tail_extension_log(&extension.name, EXTENSION_LOG_LINES) {
+                add(
+                    &format!("extension_logs/{}.log", extension.name),
+                    redactor.redact_text(&lines.join("\n")).as_bytes(),
+                )?;
+            }
+        }
+
+        add(
+            "streams.json",
+            &pretty(&serde_json::to_value(&self.streams)?),
+        )?;
+
+        let sessions: Vec<SessionInfo> = get_valid_sorted_sessions(SortOrder::Descending)
+            .unwrap_or_default()
+            .into_iter()
+            .take(RECENT_SESSIONS)
+            .collect();
+        add("sessions.json", &pretty(&serde_json::to_value(&sessions)?))?;
+        if self.include_transcripts {
+            for info in &sessions {
+                let name = format!("sessions/{}.json", info.id);
+                match session::read_messages(Path::new(&info.path)) {
+                    Ok(messages) => add(
+                        &name,
+                        &pretty(&serde_json::to_value(redactor.redact_messages(&messages))?),
+                    )?,
+                    Err(e) => add(&name, &pretty(&json!({ "error": e.to_string() })))?,
+                }
+            }
+        }
+
+        Ok(zip.finish()?.into_inner())
+    }
+}
+
+/// The config as `GET /config` returns it, with credentials masked and other values scanned
+/// for secrets
+fn redacted_config(redactor: &Redactor, profile: Option<String>) -> Value {
+    let secret_keys = provider_secret_keys();
+    match Config::global().load_effective_values() {
+        Ok(effective) => {
+            let config: serde_json::Map<String, Value> = effective
+                .values
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = mask_secrets(&key, &value, &secret_keys);
+                    (key, redactor.redact_value(&value))
+                })
+                .collect();
+            json!({
+                "config": config,
+                "profile": profile,
+                "sources": effective.sources,
+            })
+        }
+        Err(e) => json!({ "error": e.to_string() }),
+    }
+}
+
+fn is_secret_key(key: &str, secret_keys: &[String]) -> bool {
+    let upper = key.to_uppercase();
+    secret_keys
+        .iter()
+        .any(|secret| secret.eq_ignore_ascii_case(key))
+        || SECRET_KEY_MARKERS
+            .iter()
+            .any(|marker| upper.contains(marker))
+}
+
+/// Mask scalar values stored under credential-like keys, at any depth
+fn mask_secrets(key: &str, value: &Value, secret_keys: &[String]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), mask_secrets(key, value, secret_keys)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| mask_secrets(key, item, secret_keys))
+                .collect(),
+        ),
+        Value::String(_) | Value::Number(_) if is_secret_key(key, secret_keys) => {
+            Value::String(MASKED.to_string())
+        }
+        other => other.clone(),
+    }
+}
+
+/// The most recently written server log file
+fn latest_server_log() -> Option<PathBuf> {
+    let strategy = choose_app_strategy(APP_STRATEGY.clone()).ok()?;
+    let log_dir = strategy
+        .in_state_dir("logs/server")
+        .unwrap_or_else(|| strategy.in_data_dir("logs/server"));
+
+    walk_files(&log_dir)
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .max_by_key(|path| {
+            path.metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+}
+
+/// Files in `dir` and its date subdirectories
+fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            files.extend(walk_files(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// The last `max_bytes` of a file, starting at a line boundary
+fn tail_bytes(path: &Path, max_bytes: u64) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let text = String::from_utf8_lossy(&bytes);
+    Ok(match (start > 0, text.find('\n')) {
+        (true, Some(newline)) => text[newline + 1..].to_string(),
+        _ => text.into_owned(),
+    })
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/diagnostics/health", get(health))
+        .route("/diagnostics/bundle", get(diagnostics_bundle))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masks_credential_values() {
+        let secret_keys = vec!["OPENAI_API_KEY".to_string()];
+        let extensions = json!({
+            "github": {
+                "envs": { "GITHUB_PERSONAL_ACCESS_TOKEN": "ghp_value", "LOG_LEVEL": "info" },
+                "env_keys": ["GITHUB_PERSONAL_ACCESS_TOKEN"]
+            }
+        });
+
+        let masked = mask_secrets("extensions", &extensions, &secret_keys);
+        assert_eq!(
+            masked["github"]["envs"]["GITHUB_PERSONAL_ACCESS_TOKEN"],
+            MASKED
+        );
+        assert_eq!(masked["github"]["envs"]["LOG_LEVEL"], "info");
+        assert_eq!(
+            masked["github"]["env_keys"][0],
+            "GITHUB_PERSONAL_ACCESS_TOKEN"
+        );
+        assert_eq!(
+            mask_secrets("openai_api_key", &json!("sk-123"), &secret_keys),
+            MASKED
+        );
+        assert_eq!(
+            mask_secrets("GOOSE_MODEL", &json!("gpt-4o"), &secret_keys),
+            "gpt-4o"
+        );
+    }
+
+    #[test]
+    fn test_tail_starts_at_a_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.log");
+        std::fs::write(&path, "first line\nsecond line\nthird line\n").unwrap();
+
+        assert_eq!(tail_bytes(&path, 15).unwrap(), "third line\n");
+        assert_eq!(
+            tail_bytes(&path, 1024).unwrap(),
+            "first line\nsecond line\nthird line\n"
+        );
+    }
+}
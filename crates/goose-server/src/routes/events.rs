@@ -0,0 +1,274 @@
+use super::batch::{BatchItemStatus, BatchStatus};
+use super::reply::SseResponse;
+use super::utils::{scopes, RequireScope};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use goose::agents::extension::HealthStatus;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::{IntoParams, ToSchema};
+
+/// How often extensions and schedules are checked for changes to report
+const ACTIVITY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Something that happened in the server, sent on `/events` to every subscribed client
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum ServerEvent {
+    /// A reply started streaming in a session
+    SessionStarted {
+        session_id: String,
+        scheduled_job_id: Option<String>,
+    },
+    /// A reply finished streaming in a session
+    SessionFinished { session_id: String },
+    /// A scheduled job started a run
+    ScheduleFired {
+        schedule_id: String,
+        session_id: Option<String>,
+    },
+    /// An extension's health or connection changed, or it was added
+    ExtensionStatusChanged {
+        name: String,
+        status: HealthStatus,
+        connected: bool,
+        error: Option<String>,
+    },
+    /// An extension is no longer attached to the agent
+    ExtensionRemoved { name: String },
+    /// The config file was reloaded with changes
+    ConfigReloaded {
+        changed_keys: Vec<String>,
+        provider: Option<String>,
+        model: Option<String>,
+    },
+    /// The agent switched to another provider and model through `/config/active_model`
+    ModelChanged { provider: String, model: String },
+    /// A provider rejected its credentials during a reply; the user needs to sign in again
+    AuthRequired {
+        provider: String,
+        /// Config keys holding the provider's credentials
+        config_keys: Vec<String>,
+        session_id: String,
+    },
+    /// A replay of a session finished replaying one of the original's user turns
+    ReplayProgress {
+        session_id: String,
+        source_session_id: String,
+        /// Turns replayed so far
+        turn: usize,
+        total_turns: usize,
+    },
+    /// A replay of a session stopped, after its last turn or at the first error
+    ReplayFinished {
+        session_id: String,
+        source_session_id: String,
+        error: Option<String>,
+    },
+    /// An item of a batch finished, in the session it ran in
+    BatchItemFinished {
+        batch_id: String,
+        index: usize,
+        session_id: Option<String>,
+        status: BatchItemStatus,
+    },
+    /// A batch has no items left running, because all of them ran or it was cancelled
+    BatchFinished {
+        batch_id: String,
+        status: BatchStatus,
+        succeeded: usize,
+        /// Items that failed, timed out or were cancelled
+        failed: usize,
+    },
+}
+
+impl ServerEvent {
+    /// The event's `type`, as used by the `types` filter
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ServerEvent::SessionStarted { .. } => "SessionStarted",
+            ServerEvent::SessionFinished { .. } => "SessionFinished",
+            ServerEvent::ScheduleFired { .. } => "ScheduleFired",
+            ServerEvent::ExtensionStatusChanged { .. } => "ExtensionStatusChanged",
+            ServerEvent::ExtensionRemoved { .. } => "ExtensionRemoved",
+            ServerEvent::ConfigReloaded { .. } => "ConfigReloaded",
+            ServerEvent::ModelChanged { .. } => "ModelChanged",
+            ServerEvent::AuthRequired { .. } => "AuthRequired",
+            ServerEvent::ReplayProgress { .. } => "ReplayProgress",
+            ServerEvent::ReplayFinished { .. } => "ReplayFinished",
+            ServerEvent::BatchItemFinished { .. } => "BatchItemFinished",
+            ServerEvent::BatchFinished { .. } => "BatchFinished",
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// Comma-separated event types to receive, such as `SessionStarted,ConfigReloaded`. All
+    /// events are sent when unset.
+    types: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "Stream of server events", body = ServerEvent, content_type = "text/event-stream"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key")
+    ),
+    security(("api_key" = [])),
+    tag = "Events"
+)]
+// Stream events from across the server. Delivery is best effort: a client that falls too far
+// behind skips the events it missed.
+pub async fn server_events(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Query(query): Query<EventsQuery>,
+) -> Result<SseResponse, StatusCode> {
+    let types: Option<HashSet<String>> = query.types.map(|types| {
+        types
+            .split(',')
+            .map(|kind| kind.trim().to_string())
+            .filter(|kind| !kind.is_empty())
+            .collect()
+    });
+    let mut events = state.subscribe_server_events();
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if types
+                        .as_ref()
+                        .is_some_and(|types| !types.contains(event.kind()))
+                    {
+                        continue;
+                    }
+                    let Ok(json) = serde_json::to_string(&event) else {
+                        continue;
+                    };
+                    if tx.send(format!("data: {}\n\n", json)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    Ok(SseResponse::new(ReceiverStream::new(rx)))
+}
+
+/// Report extension health changes and scheduled runs starting as server events. They happen
+/// outside any request, so they are found by polling.
+pub fn spawn_activity_monitor(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut extensions: HashMap<String, (HealthStatus, bool)> = HashMap::new();
+        let mut running: HashSet<String> = HashSet::new();
+        let mut interval = tokio::time::interval(ACTIVITY_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            if let Ok(agent) = state.get_agent().await {
+                let statuses = agent.get_extension_statuses().await;
+                let mut seen = HashMap::with_capacity(statuses.len());
+                for status in statuses {
+                    let current = (status.status, status.connected);
+                    if extensions.get(&status.name) != Some(&current) {
+                        state.publish_event(ServerEvent::ExtensionStatusChanged {
+                            name: status.name.clone(),
+                            status: status.status,
+                            connected: status.connected,
+                            error: status.error,
+                        });
+                    }
+                    seen.insert(status.name, current);
+                }
+                for name in extensions.keys() {
+                    if !seen.contains_key(name) {
+                        state.publish_event(ServerEvent::ExtensionRemoved { name: name.clone() });
+                    }
+                }
+                extensions = seen;
+            }
+
+            if let Ok(scheduler) = state.scheduler().await {
+                if let Ok(jobs) = scheduler.list_scheduled_jobs().await {
+                    let now_running: HashSet<String> = jobs
+                        .iter()
+                        .filter(|job| job.currently_running)
+                        .map(|job| job.id.clone())
+                        .collect();
+                    for job in jobs.iter().filter(|job| job.currently_running) {
+                        if !running.contains(&job.id) {
+                            state.publish_event(ServerEvent::ScheduleFired {
+                                schedule_id: job.id.clone(),
+                                session_id: job.current_session_id.clone(),
+                            });
+                        }
+                    }
+                    running = now_running;
+                }
+            }
+        }
+    });
+}
+
+// Configure routes for this module
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/events", get(server_events))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use futures::StreamExt;
+    use goose::agents::Agent;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_events_are_filtered_by_type() {
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let app = routes(state.clone());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/events?types=SessionFinished")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let guard = state.track_stream("events-session", None);
+        drop(guard);
+
+        let mut body = response.into_body().into_data_stream();
+        let chunk = body.next().await.unwrap().unwrap();
+        let event = String::from_utf8(chunk.to_vec()).unwrap();
+        assert_eq!(
+            event,
+            "data: {\"type\":\"SessionFinished\",\"session_id\":\"events-session\"}\n\n"
+        );
+    }
+}
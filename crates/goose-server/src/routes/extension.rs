@@ -1,14 +1,14 @@
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::OnceLock;
 
-use super::utils::verify_secret_key;
+use super::utils::{scopes, RequireScope};
 use crate::state::AppState;
 use axum::{extract::State, routing::post, Json, Router};
 use goose::agents::{extension::Envs, ExtensionConfig};
 use http::{HeaderMap, StatusCode};
-use rmcp::model::Tool;
+use rmcp::model::{LoggingLevel, Tool};
 use serde::{Deserialize, Serialize};
 use tracing;
 
@@ -30,6 +30,9 @@ enum ExtensionConfigRequest {
         #[serde(default)]
         env_keys: Vec<String>,
         timeout: Option<u64>,
+        /// Minimum level of log messages the server should send.
+        #[serde(default)]
+        log_level: Option<LoggingLevel>,
     },
     /// Standard I/O (stdio) extension.
     #[serde(rename = "stdio")]
@@ -47,7 +50,13 @@ enum ExtensionConfigRequest {
         /// List of environment variable keys. The server will fetch their values from the keyring.
         #[serde(default)]
         env_keys: Vec<String>,
+        /// Working directory for the process.
+        #[serde(default)]
+        cwd: Option<PathBuf>,
         timeout: Option<u64>,
+        /// Minimum level of log messages the server should send.
+        #[serde(default)]
+        log_level: Option<LoggingLevel>,
     },
     /// Built-in extension that is part of the goose binary.
     #[serde(rename = "builtin")]
@@ -56,6 +65,9 @@ enum ExtensionConfigRequest {
         name: String,
         display_name: Option<String>,
         timeout: Option<u64>,
+        /// Minimum level of log messages the server should send.
+        #[serde(default)]
+        log_level: Option<LoggingLevel>,
     },
     /// Streamable HTTP extension using MCP Streamable HTTP specification.
     #[serde(rename = "streamable_http")]
@@ -74,6 +86,9 @@ enum ExtensionConfigRequest {
         #[serde(default)]
         headers: std::collections::HashMap<String, String>,
         timeout: Option<u64>,
+        /// Minimum level of log messages the server should send.
+        #[serde(default)]
+        log_level: Option<LoggingLevel>,
     },
     /// Frontend extension that provides tools to be executed by the frontend.
     #[serde(rename = "frontend")]
@@ -100,11 +115,10 @@ struct ExtensionResponse {
 /// Handler for adding a new extension configuration.
 async fn add_extension(
     State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
     headers: HeaderMap,
     raw: axum::extract::Json<serde_json::Value>,
 ) -> Result<Json<ExtensionResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     // Log the raw request for debugging
     tracing::info!(
         "Received extension request: {}",
@@ -186,6 +200,7 @@ async fn add_extension(
             envs,
             env_keys,
             timeout,
+            log_level,
         } => ExtensionConfig::Sse {
             name,
             uri,
@@ -194,6 +209,7 @@ async fn add_extension(
             description: None,
             timeout,
             bundled: None,
+            log_level,
         },
         ExtensionConfigRequest::StreamableHttp {
             name,
@@ -202,6 +218,7 @@ async fn add_extension(
             env_keys,
             headers,
             timeout,
+            log_level,
         } => ExtensionConfig::StreamableHttp {
             name,
             uri,
@@ -211,6 +228,7 @@ async fn add_extension(
             description: None,
             timeout,
             bundled: None,
+            log_level,
         },
         ExtensionConfigRequest::Stdio {
             name,
@@ -218,7 +236,9 @@ async fn add_extension(
             args,
             envs,
             env_keys,
+            cwd,
             timeout,
+            log_level,
         } => {
             // TODO: We can uncomment once bugs are fixed. Check allowlist for Stdio extensions
             // if !is_command_allowed(&cmd, &args) {
@@ -239,20 +259,24 @@ async fn add_extension(
                 description: None,
                 envs,
                 env_keys,
+                cwd,
                 timeout,
                 bundled: None,
+                log_level,
             }
         }
         ExtensionConfigRequest::Builtin {
             name,
             display_name,
             timeout,
+            log_level,
         } => ExtensionConfig::Builtin {
             name,
             display_name,
             timeout,
             bundled: None,
             description: None,
+            log_level,
         },
         ExtensionConfigRequest::Frontend {
             name,
@@ -295,11 +319,9 @@ async fn add_extension(
 /// Handler for removing an extension by name
 async fn remove_extension(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::ConfigWrite>,
     Json(name): Json<String>,
 ) -> Result<Json<ExtensionResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     // Get a reference to the agent
     let agent = state
         .get_agent()
@@ -0,0 +1,636 @@
+//! Rate limits and a daily token budget, for servers reachable beyond localhost.
+//!
+//! `GOOSE_SERVER_RATE_LIMIT` caps the requests per minute across all routes and
+//! `GOOSE_SERVER_CLIENT_RATE_LIMIT` the requests per minute one client may make to the routes
+//! that run the agent. A client is its API token when it sends a minted one, or else its remote
+//! address. `GOOSE_SERVER_DAILY_TOKEN_BUDGET` caps the LLM tokens spent per UTC day; once it is
+//! used up new sessions are turned away while those already started may carry on. The limits
+//! are read again whenever the config is reloaded.
+
+use super::utils::{constant_time_eq, scopes, RequireScope};
+use crate::state::AppState;
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::{routing::get, Extension, Json, Router};
+use chrono::{DateTime, NaiveDate, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use goose::config::{Config, APP_STRATEGY};
+use goose::session;
+use http::{header, HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Requests per minute the server takes across all routes, unlimited when unset or 0
+const RATE_LIMIT_KEY: &str = "GOOSE_SERVER_RATE_LIMIT";
+/// Requests per minute one client may make to the routes that run the agent
+const CLIENT_RATE_LIMIT_KEY: &str = "GOOSE_SERVER_CLIENT_RATE_LIMIT";
+/// LLM tokens that may be spent per UTC day before new sessions are turned away
+const DAILY_TOKEN_BUDGET_KEY: &str = "GOOSE_SERVER_DAILY_TOKEN_BUDGET";
+pub const LIMIT_KEYS: &[&str] = &[
+    RATE_LIMIT_KEY,
+    CLIENT_RATE_LIMIT_KEY,
+    DAILY_TOKEN_BUDGET_KEY,
+];
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// File in the state dir the day's token count is kept in, so a restart carries it over
+const TOKEN_USAGE_FILE: &str = "token_usage.json";
+
+/// Routes that run the agent, limited per client
+const EXPENSIVE_ROUTES: &[&str] = &[
+    "/reply",
+    "/ask/batch",
+    "/agent/prompts/{name}",
+    "/sessions/{session_id}/replay",
+];
+
+/// Health checks, left out of the limits so monitoring keeps working while the server is busy
+const EXEMPT_ROUTES: &[&str] = &["/status", "/diagnostics/health"];
+
+/// The configured limits, `None` where there is no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub requests_per_minute: Option<usize>,
+    pub client_requests_per_minute: Option<usize>,
+    pub daily_token_budget: Option<u64>,
+}
+
+impl Limits {
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        Self {
+            requests_per_minute: config
+                .get_param::<usize>(RATE_LIMIT_KEY)
+                .ok()
+                .filter(|limit| *limit > 0),
+            client_requests_per_minute: config
+                .get_param::<usize>(CLIENT_RATE_LIMIT_KEY)
+                .ok()
+                .filter(|limit| *limit > 0),
+            daily_token_budget: config
+                .get_param::<u64>(DAILY_TOKEN_BUDGET_KEY)
+                .ok()
+                .filter(|budget| *budget > 0),
+        }
+    }
+}
+
+/// Why a request was turned away
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    /// Too many requests within the last minute, server-wide or from one client
+    RateLimited {
+        per_client: bool,
+        limit: usize,
+        retry_after: Duration,
+    },
+    /// The tokens spent today reached the budget
+    BudgetExhausted {
+        budget: u64,
+        used: u64,
+        resets_at: DateTime<Utc>,
+    },
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Rejection::RateLimited {
+                per_client,
+                limit,
+                retry_after,
+            } => {
+                let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                let error = if per_client {
+                    format!(
+                        "Rate limit of {} requests per minute for this client reached",
+                        limit
+                    )
+                } else {
+                    format!("Rate limit of {} requests per minute reached", limit)
+                };
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, seconds.to_string())],
+                    Json(json!({ "error": error, "retry_after": seconds })),
+                )
+                    .into_response()
+            }
+            Rejection::BudgetExhausted {
+                budget,
+                used,
+                resets_at,
+            } => {
+                let seconds = (resets_at - Utc::now()).num_seconds().max(1);
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, seconds.to_string())],
+                    Json(json!({
+                        "error": "The daily token budget is used up, new sessions can start once it resets",
+                        "budget": budget,
+                        "used": used,
+                        "resets_at": resets_at,
+                    })),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+struct Usage {
+    /// Requests over the last minute, server-wide and by client
+    requests: VecDeque<Instant>,
+    client_requests: HashMap<String, VecDeque<Instant>>,
+    day: NaiveDate,
+    tokens_today: u64,
+}
+
+/// The token count as kept in [`TOKEN_USAGE_FILE`]
+#[derive(Serialize, Deserialize)]
+struct SavedTokenUsage {
+    day: NaiveDate,
+    tokens: u64,
+}
+
+/// Counts requests and token spend against the configured [`Limits`]
+pub struct RateLimiter {
+    limits: Mutex<Limits>,
+    usage: Mutex<Usage>,
+    /// Where the day's token count is kept, if anywhere
+    path: Option<PathBuf>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: Limits) -> Self {
+        Self::with_saved_tokens(limits, None)
+    }
+
+    /// Keep the day's token count in `path` if given, starting from the count saved there when
+    /// it is for today
+    pub fn with_saved_tokens(limits: Limits, path: Option<PathBuf>) -> Self {
+        let today = Utc::now().date_naive();
+        let tokens_today = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str::<SavedTokenUsage>(&contents).ok())
+            .filter(|saved| saved.day == today)
+            .map_or(0, |saved| saved.tokens);
+        Self {
+            limits: Mutex::new(limits),
+            usage: Mutex::new(Usage {
+                requests: VecDeque::new(),
+                client_requests: HashMap::new(),
+                day: today,
+                tokens_today,
+            }),
+            path,
+        }
+    }
+
+    /// Limits from config, with the day's token count kept in the state dir so a restart
+    /// doesn't start it afresh. Tests keep their count to themselves.
+    pub fn from_config() -> Self {
+        let path = choose_app_strategy(APP_STRATEGY.clone())
+            .ok()
+            .filter(|_| !cfg!(test))
+            .map(|strategy| {
+                strategy
+                    .in_state_dir(TOKEN_USAGE_FILE)
+                    .unwrap_or_else(|| strategy.in_data_dir(TOKEN_USAGE_FILE))
+            });
+        Self::with_saved_tokens(Limits::from_config(), path)
+    }
+
+    pub fn limits(&self) -> Limits {
+        *self
+            .limits
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_limits(&self, limits: Limits) {
+        *self
+            .limits
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = limits;
+    }
+
+    fn usage(&self) -> MutexGuard<'_, Usage> {
+        let mut usage = self
+            .usage
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let today = Utc::now().date_naive();
+        if usage.day != today {
+            usage.day = today;
+            usage.tokens_today = 0;
+        }
+        usage
+    }
+
+    /// Count a request from `client` against the limits, or say why it is turned away. Only
+    /// requests to routes that run the agent carry a client, and a rejected request isn't
+    /// counted.
+    pub fn check_request(&self, client: Option<&str>, now: Instant) -> Result<(), Rejection> {
+        let limits = self.limits();
+        let mut usage = self.usage();
+        let usage = &mut *usage;
+
+        prune(&mut usage.requests, now);
+        if let Some(limit) = limits.requests_per_minute {
+            if usage.requests.len() >= limit {
+                return Err(rate_limited(&usage.requests, false, limit, now));
+            }
+        }
+
+        let client_requests = match (client, limits.client_requests_per_minute) {
+            (Some(client), Some(limit)) => {
+                usage.client_requests.retain(|_, times| {
+                    prune(times, now);
+                    !times.is_empty()
+                });
+                let times = usage.client_requests.entry(client.to_string()).or_default();
+                if times.len() >= limit {
+                    return Err(rate_limited(times, true, limit, now));
+                }
+                Some(times)
+            }
+            _ => None,
+        };
+        if let Some(times) = client_requests {
+            times.push_back(now);
+        }
+        if limits.requests_per_minute.is_some() {
+            usage.requests.push_back(now);
+        }
+        Ok(())
+    }
+
+    /// Whether a new session may start, given the tokens spent today
+    pub fn check_token_budget(&self) -> Result<(), Rejection> {
+        let Some(budget) = self.limits().daily_token_budget else {
+            return Ok(());
+        };
+        let used = self.usage().tokens_today;
+        if used < budget {
+            return Ok(());
+        }
+        Err(Rejection::BudgetExhausted {
+            budget,
+            used,
+            resets_at: next_reset(),
+        })
+    }
+
+    /// Add tokens spent by the agent to today's count
+    pub fn record_tokens(&self, tokens: u64) {
+        let mut usage = self.usage();
+        usage.tokens_today += tokens;
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved = SavedTokenUsage {
+            day: usage.day,
+            tokens: usage.tokens_today,
+        };
+        // Written while the count is locked, so an older count never overwrites a newer one
+        let result = serde_json::to_string(&saved)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                Ok(std::fs::write(path, contents)?)
+            });
+        if let Err(e) = result {
+            tracing::warn!("Failed to save the day's token count: {}", e);
+        }
+    }
+
+    /// The limits and how much of them is used now, by `client` for the per-client limit
+    pub fn report(&self, client: Option<&str>) -> LimitsReport {
+        let limits = self.limits();
+        let now = Instant::now();
+        let mut usage = self.usage();
+        prune(&mut usage.requests, now);
+        let client_used = client
+            .and_then(|client| usage.client_requests.get_mut(client))
+            .map_or(0, |times| {
+                prune(times, now);
+                times.len()
+            });
+        LimitsReport {
+            requests: RateUsage {
+                limit_per_minute: limits.requests_per_minute,
+                used: usage.requests.len(),
+            },
+            client_requests: RateUsage {
+                limit_per_minute: limits.client_requests_per_minute,
+                used: client_used,
+            },
+            tokens: TokenUsage {
+                daily_budget: limits.daily_token_budget,
+                used_today: usage.tokens_today,
+                resets_at: next_reset(),
+            },
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(Limits::default())
+    }
+}
+
+fn prune(times: &mut VecDeque<Instant>, now: Instant) {
+    while times
+        .front()
+        .is_some_and(|oldest| now.duration_since(*oldest) >= RATE_WINDOW)
+    {
+        times.pop_front();
+    }
+}
+
+fn rate_limited(
+    times: &VecDeque<Instant>,
+    per_client: bool,
+    limit: usize,
+    now: Instant,
+) -> Rejection {
+    let retry_after = times.front().map_or(RATE_WINDOW, |oldest| {
+        RATE_WINDOW.saturating_sub(now.duration_since(*oldest))
+    });
+    Rejection::RateLimited {
+        per_client,
+        limit,
+        retry_after,
+    }
+}
+
+/// Midnight UTC, when today's token count starts over
+fn next_reset() -> DateTime<Utc> {
+    (Utc::now().date_naive() + chrono::Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .map(|midnight| midnight.and_utc())
+        .unwrap_or_else(Utc::now)
+}
+
+/// Tokens the agent has spent in the session saved at `session_path`
+pub fn session_tokens(session_path: &Path) -> u64 {
+    session::read_metadata(session_path)
+        .ok()
+        .and_then(|metadata| metadata.accumulated_total_tokens)
+        .map_or(0, |tokens| tokens.max(0) as u64)
+}
+
+/// Who a request counts against for the per-client limit: the minted token it was sent with,
+/// or else its remote address. `None` for requests served without connection info, as in tests.
+fn client_key(state: &AppState, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<String> {
+    let token_id = headers
+        .get("X-Secret-Key")
+        .and_then(|value| value.to_str().ok())
+        .filter(|key| !constant_time_eq(key, &state.secret_key))
+        .and_then(|key| state.tokens.token_id(key));
+    match (token_id, peer) {
+        (Some(id), _) => Some(format!("token:{}", id)),
+        (None, Some(peer)) => Some(format!("addr:{}", peer.ip())),
+        (None, None) => None,
+    }
+}
+
+/// Middleware rejecting requests over the rate limits with `429 Too Many Requests`. Requests are
+/// turned away before their handler runs, so a rejected reply never starts an agent task.
+pub async fn limit_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    if route
+        .as_deref()
+        .is_some_and(|route| EXEMPT_ROUTES.contains(&route))
+    {
+        return next.run(request).await;
+    }
+    let expensive = route
+        .as_deref()
+        .is_some_and(|route| EXPENSIVE_ROUTES.contains(&route));
+    let client = if expensive {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| *peer);
+        client_key(&state, request.headers(), peer)
+    } else {
+        None
+    };
+
+    if let Err(rejection) = state
+        .rate_limiter
+        .check_request(client.as_deref(), Instant::now())
+    {
+        tracing::warn!(client = ?client, "Rejected request over the rate limit");
+        return rejection.into_response();
+    }
+    next.run(request).await
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RateUsage {
+    /// Requests allowed per minute, absent when unlimited
+    pub limit_per_minute: Option<usize>,
+    /// Requests made over the last minute
+    pub used: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TokenUsage {
+    /// Tokens that may be spent per UTC day, absent when unlimited
+    pub daily_budget: Option<u64>,
+    /// Tokens spent today
+    pub used_today: u64,
+    pub resets_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LimitsReport {
+    /// Requests across all routes
+    pub requests: RateUsage,
+    /// Requests by the caller to the routes that run the agent
+    pub client_requests: RateUsage,
+    pub tokens: TokenUsage,
+}
+
+#[utoipa::path(
+    get,
+    path = "/limits",
+    responses(
+        (status = 200, description = "The configured limits and how much of them is used", body = LimitsReport),
+        (status = 401, description = "Unauthorized - Invalid or missing API key")
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_limits(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    headers: HeaderMap,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+) -> Result<Json<LimitsReport>, StatusCode> {
+    let client = client_key(
+        &state,
+        &headers,
+        connect_info.map(|Extension(ConnectInfo(peer))| peer),
+    );
+    Ok(Json(state.rate_limiter.report(client.as_deref())))
+}
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/limits", get(get_limits))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_requests_over_the_limits_are_rejected() {
+        let limiter = RateLimiter::new(Limits {
+            requests_per_minute: Some(3),
+            client_requests_per_minute: Some(1),
+            daily_token_budget: None,
+        });
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_request(Some("addr:10.0.0.1"), start), Ok(()));
+        // The client used up its own limit, which leaves the server-wide one untouched
+        assert!(matches!(
+            limiter.check_request(Some("addr:10.0.0.1"), start),
+            Err(Rejection::RateLimited {
+                per_client: true,
+                ..
+            })
+        ));
+        assert_eq!(limiter.check_request(Some("addr:10.0.0.2"), start), Ok(()));
+        assert_eq!(limiter.check_request(None, start), Ok(()));
+        let Err(Rejection::RateLimited {
+            per_client,
+            limit,
+            retry_after,
+        }) = limiter.check_request(None, start + Duration::from_secs(20))
+        else {
+            panic!("expected the server-wide limit to be reached");
+        };
+        assert!(!per_client);
+        assert_eq!(limit, 3);
+        assert_eq!(retry_after, Duration::from_secs(40));
+
+        // A minute later the requests have aged out of the window
+        assert_eq!(
+            limiter.check_request(Some("addr:10.0.0.1"), start + RATE_WINDOW),
+            Ok(())
+        );
+        assert_eq!(limiter.report(Some("addr:10.0.0.1")).requests.used, 1);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_answers_429_with_retry_after() {
+        let state = AppState::new(
+            Arc::new(goose::agents::Agent::new()),
+            "test-secret".to_string(),
+        )
+        .await;
+        state.rate_limiter.set_limits(Limits {
+            requests_per_minute: Some(1),
+            ..Limits::default()
+        });
+        let app = routes(state.clone())
+            .merge(super::super::health::routes())
+            .layer(axum::middleware::from_fn_with_state(state, limit_requests));
+        let request = |uri: &str| {
+            axum::http::Request::builder()
+                .uri(uri)
+                .header("x-secret-key", "test-secret")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let first = app.clone().oneshot(request("/limits")).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let second = app.clone().oneshot(request("/limits")).await.unwrap();
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(second.headers()[header::RETRY_AFTER], "60");
+
+        // Health checks are still answered over the limit
+        let status = app.oneshot(request("/status")).await.unwrap();
+        assert_eq!(status.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_token_budget_and_reloaded_limits() {
+        let limiter = RateLimiter::default();
+        limiter.record_tokens(5_000);
+        assert_eq!(limiter.check_token_budget(), Ok(()));
+
+        limiter.set_limits(Limits {
+            daily_token_budget: Some(5_000),
+            ..Limits::default()
+        });
+        assert!(matches!(
+            limiter.check_token_budget(),
+            Err(Rejection::BudgetExhausted {
+                budget: 5_000,
+                used: 5_000,
+                ..
+            })
+        ));
+
+        limiter.set_limits(Limits {
+            daily_token_budget: Some(10_000),
+            ..Limits::default()
+        });
+        assert_eq!(limiter.check_token_budget(), Ok(()));
+        assert_eq!(limiter.report(None).tokens.used_today, 5_000);
+    }
+
+    #[test]
+    fn test_token_count_survives_a_restart_on_the_same_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(TOKEN_USAGE_FILE);
+
+        let limiter = RateLimiter::with_saved_tokens(Limits::default(), Some(path.clone()));
+        limiter.record_tokens(1_200);
+        limiter.record_tokens(300);
+        let restarted = RateLimiter::with_saved_tokens(Limits::default(), Some(path.clone()));
+        assert_eq!(restarted.report(None).tokens.used_today, 1_500);
+
+        // A count saved on another day is not carried over
+        let yesterday = Utc::now().date_naive() - chrono::Days::new(1);
+        std::fs::write(
+            &path,
+            serde_json::to_string(&SavedTokenUsage {
+                day: yesterday,
+                tokens: 5_000,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        let next_day = RateLimiter::with_saved_tokens(Limits::default(), Some(path));
+        assert_eq!(next_day.report(None).tokens.used_today, 0);
+    }
+}
@@ -1,10 +1,16 @@
 // Export route modules
 pub mod agent;
 pub mod audio;
+pub mod audit;
+pub mod auth;
+pub mod batch;
 pub mod config_management;
 pub mod context;
+pub mod diagnostics;
+pub mod events;
 pub mod extension;
 pub mod health;
+pub mod limits;
 pub mod project;
 pub mod recipe;
 pub mod reply;
@@ -14,16 +20,23 @@ pub mod setup;
 pub mod utils;
 use std::sync::Arc;
 
+use axum::extract::DefaultBodyLimit;
 use axum::Router;
 
 // Function to configure all routes
 pub fn configure(state: Arc<crate::state::AppState>) -> Router {
     Router::new()
         .merge(health::routes())
+        .merge(limits::routes(state.clone()))
         .merge(reply::routes(state.clone()))
         .merge(agent::routes(state.clone()))
         .merge(audio::routes(state.clone()))
+        .merge(audit::routes(state.clone()))
+        .merge(auth::routes(state.clone()))
+        .merge(batch::routes(state.clone()))
         .merge(context::routes(state.clone()))
+        .merge(diagnostics::routes(state.clone()))
+        .merge(events::routes(state.clone()))
         .merge(extension::routes(state.clone()))
         .merge(config_management::routes(state.clone()))
         .merge(recipe::routes(state.clone()))
@@ -31,4 +44,13 @@ pub fn configure(state: Arc<crate::state::AppState>) -> Router {
         .merge(schedule::routes(state.clone()))
         .merge(project::routes(state.clone()))
         .merge(setup::routes(state.clone()))
+        .layer(DefaultBodyLimit::max(utils::max_body_size()))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            limits::limit_requests,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            utils::limit_auth_failures,
+        ))
 }
@@ -1,10 +1,10 @@
-use super::utils::verify_secret_key;
+use super::utils::{scopes, RequireScope};
 use std::sync::Arc;
 
 use crate::state::AppState;
 use axum::{
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::StatusCode,
     routing::{delete, get, post, put},
     Json, Router,
 };
@@ -65,11 +65,8 @@ pub struct ProjectResponse {
 )]
 // List all available projects
 async fn list_projects(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsRead>,
 ) -> Result<Json<ProjectListResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let projects =
         goose::project::list_projects().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -95,12 +92,9 @@ async fn list_projects(
 )]
 // Get a specific project details
 async fn get_project_details(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsRead>,
     Path(project_id): Path<String>,
 ) -> Result<Json<ProjectResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let project = goose::project::get_project(&project_id).map_err(|e| {
         if e.to_string().contains("not found") {
             StatusCode::NOT_FOUND
@@ -129,12 +123,9 @@ async fn get_project_details(
 )]
 // Create a new project
 async fn create_project(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsWrite>,
     Json(create_req): Json<CreateProjectRequest>,
 ) -> Result<Json<ProjectResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     // Validate input
     if create_req.name.trim().is_empty() {
         return Err(StatusCode::BAD_REQUEST);
@@ -170,13 +161,10 @@ async fn create_project(
 )]
 // Update a project
 async fn update_project(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsWrite>,
     Path(project_id): Path<String>,
     Json(update_req): Json<UpdateProjectRequest>,
 ) -> Result<Json<ProjectResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let project = goose::project::update_project(
         &project_id,
         update_req.name,
@@ -213,12 +201,9 @@ async fn update_project(
 )]
 // Delete a project
 async fn delete_project(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsWrite>,
     Path(project_id): Path<String>,
 ) -> Result<StatusCode, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     goose::project::delete_project(&project_id).map_err(|e| {
         if e.to_string().contains("not found") {
             StatusCode::NOT_FOUND
@@ -250,12 +235,9 @@ async fn delete_project(
 )]
 // Add session to project
 async fn add_session_to_project(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsWrite>,
     Path((project_id, session_id)): Path<(String, String)>,
 ) -> Result<StatusCode, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     // Add the session to project
     goose::project::add_session_to_project(&project_id, &session_id).map_err(|e| {
         if e.to_string().contains("not found") {
@@ -302,12 +284,9 @@ async fn add_session_to_project(
 )]
 // Remove session from project
 async fn remove_session_from_project(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsWrite>,
     Path((project_id, session_id)): Path<(String, String)>,
 ) -> Result<StatusCode, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     // Remove from project
     goose::project::remove_session_from_project(&project_id, &session_id).map_err(|e| {
         if e.to_string().contains("not found") {
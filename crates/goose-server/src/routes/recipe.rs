@@ -1,12 +1,34 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use goose::config::RecipeSigningManager;
 use goose::message::Message;
+use goose::recipe::build_recipe::{build_recipe_with_values, ParameterError, RecipeError};
+use goose::recipe::import_recipe::{get_default_recipes_dir, import_recipe, ImportError};
+use goose::recipe::local_recipes::{
+    configured_recipe_dirs, delete_local_recipe, get_local_recipe, list_local_recipes,
+    save_local_recipe, LocalRecipe, LocalRecipeError,
+};
+use goose::recipe::read_recipe_file_content::{read_recipe_file, RecipeFile};
+use goose::recipe::risk_summary::RecipeRiskSummary;
+use goose::recipe::run_recipe::{dry_run_recipe, RecipeDryRun};
+use goose::recipe::validate_recipe::{
+    error_summary, validate_recipe, validate_recipe_content, IssueSeverity, RecipeIssue,
+};
 use goose::recipe::Recipe;
 use goose::recipe_deeplink;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::routes::utils::{scopes, RequireScope};
 use crate::state::AppState;
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -39,6 +61,9 @@ pub struct CreateRecipeResponse {
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct EncodeRecipeRequest {
     recipe: Recipe,
+    /// Sign the deeplink with this machine's recipe signing key
+    #[serde(default)]
+    sign: bool,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -54,6 +79,134 @@ pub struct DecodeRecipeRequest {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct DecodeRecipeResponse {
     recipe: Recipe,
+    /// Whether the deeplink was signed with a trusted key. Unsigned deeplinks are never trusted.
+    trusted: bool,
+    /// Id of the key the deeplink was signed with
+    signer: Option<String>,
+    /// What the recipe could do, to show the user before it runs
+    risks: RecipeRiskSummary,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValidateRecipeRequest {
+    /// Recipe YAML or JSON; exactly one of `content` and `path` is set
+    #[serde(default)]
+    content: Option<String>,
+    /// Path of a recipe file on this machine
+    #[serde(default)]
+    path: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidateRecipeResponse {
+    /// Whether the recipe has no errors; it may still have warnings
+    valid: bool,
+    issues: Vec<RecipeIssue>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenderRecipeRequest {
+    /// The recipe to render; exactly one of `recipe` and `path` is set
+    #[serde(default)]
+    recipe: Option<Recipe>,
+    /// Path of a recipe file on this machine
+    #[serde(default)]
+    path: Option<String>,
+    /// Values for the recipe's parameters, as entered
+    #[serde(default)]
+    params: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RenderRecipeResponse {
+    recipe: Recipe,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportRecipeRequest {
+    /// An https URL of a recipe file, `owner/repo/path@ref` on GitHub, or a
+    /// `goose://recipe?config=...` deeplink
+    source: String,
+    /// Replace a recipe with the same name in the local recipe directory
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportRecipeResponse {
+    recipe: Recipe,
+    /// Where the recipe was saved
+    path: String,
+    /// Warnings found while validating the recipe
+    issues: Vec<RecipeIssue>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecipeErrorResponse {
+    message: String,
+    /// Validation errors, when the recipe itself was rejected
+    issues: Vec<RecipeIssue>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListRecipesResponse {
+    recipes: Vec<LocalRecipe>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LocalRecipeResponse {
+    recipe: LocalRecipe,
+    /// The recipe file as written, with template variables left in place
+    content: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SaveRecipeRequest {
+    /// Recipe YAML or JSON
+    content: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SaveRecipeResponse {
+    recipe: LocalRecipe,
+    /// Warnings found while validating the recipe
+    issues: Vec<RecipeIssue>,
+}
+
+pub(crate) fn local_recipe_error(e: LocalRecipeError) -> Response {
+    let status = match &e {
+        LocalRecipeError::InvalidName(_) => StatusCode::BAD_REQUEST,
+        LocalRecipeError::NotFound(_) => StatusCode::NOT_FOUND,
+        LocalRecipeError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        LocalRecipeError::NoRecipeDir | LocalRecipeError::Io(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    let message = e.to_string();
+    let issues = match e {
+        LocalRecipeError::Invalid(issues) => issues,
+        _ => Vec::new(),
+    };
+    (status, Json(RecipeErrorResponse { message, issues })).into_response()
+}
+
+/// Names each parameter whose value was rejected, so the fields can be highlighted
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InvalidParametersResponse {
+    message: String,
+    errors: Vec<ParameterError>,
+}
+
+pub(crate) fn invalid_parameters(errors: Vec<ParameterError>) -> Response {
+    let message = RecipeError::InvalidParams {
+        errors: errors.clone(),
+    }
+    .to_string();
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(InvalidParametersResponse { message, errors }),
+    )
+        .into_response()
 }
 
 #[utoipa::path(
@@ -102,6 +255,16 @@ async fn create_recipe(
                 });
             }
 
+            if let Some(error) = error_summary(&validate_recipe(&recipe, None)) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(CreateRecipeResponse {
+                        recipe: None,
+                        error: Some(format!("Created recipe is invalid: {}", error)),
+                    }),
+                ));
+            }
+
             Ok(Json(CreateRecipeResponse {
                 recipe: Some(recipe),
                 error: None,
@@ -124,14 +287,21 @@ async fn create_recipe(
     request_body = EncodeRecipeRequest,
     responses(
         (status = 200, description = "Recipe encoded successfully", body = EncodeRecipeResponse),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 412, description = "Signing was requested but no signing key is configured")
     ),
     tag = "Recipe Management"
 )]
 async fn encode_recipe(
     Json(request): Json<EncodeRecipeRequest>,
 ) -> Result<Json<EncodeRecipeResponse>, StatusCode> {
-    match recipe_deeplink::encode(&request.recipe) {
+    let encoded = if request.sign {
+        let key = RecipeSigningManager::signing_key().ok_or(StatusCode::PRECONDITION_FAILED)?;
+        recipe_deeplink::encode_signed(&request.recipe, &key)
+    } else {
+        recipe_deeplink::encode(&request.recipe)
+    };
+    match encoded {
         Ok(encoded) => Ok(Json(EncodeRecipeResponse { deeplink: encoded })),
         Err(err) => {
             tracing::error!("Failed to encode recipe: {}", err);
@@ -146,15 +316,21 @@ async fn encode_recipe(
     request_body = DecodeRecipeRequest,
     responses(
         (status = 200, description = "Recipe decoded successfully", body = DecodeRecipeResponse),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request, or the signature doesn't match the recipe")
     ),
     tag = "Recipe Management"
 )]
 async fn decode_recipe(
     Json(request): Json<DecodeRecipeRequest>,
 ) -> Result<Json<DecodeRecipeResponse>, StatusCode> {
-    match recipe_deeplink::decode(&request.deeplink) {
-        Ok(recipe) => Ok(Json(DecodeRecipeResponse { recipe })),
+    let trusted_keys = RecipeSigningManager::trusted_keys();
+    match recipe_deeplink::decode_verified(&request.deeplink, &trusted_keys) {
+        Ok(verified) => Ok(Json(DecodeRecipeResponse {
+            recipe: verified.recipe,
+            trusted: verified.trusted,
+            signer: verified.signer,
+            risks: verified.risks,
+        })),
         Err(err) => {
             tracing::error!("Failed to decode deeplink: {}", err);
             Err(StatusCode::BAD_REQUEST)
@@ -162,11 +338,284 @@ async fn decode_recipe(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/recipes/validate",
+    request_body = ValidateRecipeRequest,
+    responses(
+        (status = 200, description = "Recipe validated", body = ValidateRecipeResponse),
+        (status = 400, description = "Not exactly one of content and path is set"),
+        (status = 401, description = "Unauthorized - invalid secret key")
+    ),
+    tag = "Recipe Management"
+)]
+/// Check a recipe for errors and likely mistakes without running it
+async fn validate_recipe_handler(
+    _scope: RequireScope<scopes::Chat>,
+    Json(request): Json<ValidateRecipeRequest>,
+) -> Result<Json<ValidateRecipeResponse>, StatusCode> {
+    let issues = match (request.content, request.path) {
+        (Some(content), None) => validate_recipe_content(&content, None),
+        (None, Some(path)) => match read_recipe_file(&path) {
+            Ok(recipe_file) => {
+                validate_recipe_content(&recipe_file.content, Some(&recipe_file.parent_dir))
+            }
+            Err(e) => vec![RecipeIssue {
+                severity: IssueSeverity::Error,
+                location: String::new(),
+                message: e.to_string(),
+            }],
+        },
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    Ok(Json(ValidateRecipeResponse {
+        valid: error_summary(&issues).is_none(),
+        issues,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/recipes/render",
+    request_body = RenderRecipeRequest,
+    responses(
+        (status = 200, description = "Recipe rendered with the parameter values", body = RenderRecipeResponse),
+        (status = 400, description = "Not exactly one of recipe and path is set, or the recipe can't be rendered"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 422, description = "Missing or invalid parameter values", body = InvalidParametersResponse)
+    ),
+    tag = "Recipe Management"
+)]
+/// Check and coerce parameter values, apply defaults, and render the recipe with them before
+/// a run starts
+async fn render_recipe(
+    _scope: RequireScope<scopes::Chat>,
+    Json(request): Json<RenderRecipeRequest>,
+) -> Result<Json<RenderRecipeResponse>, Response> {
+    let recipe_file = recipe_file_from_request(request.recipe, request.path)?;
+    match build_recipe_with_values(recipe_file, &request.params) {
+        Ok(recipe) => Ok(Json(RenderRecipeResponse { recipe })),
+        Err(RecipeError::InvalidParams { errors }) => Err(invalid_parameters(errors)),
+        Err(e) => Err(recipe_bad_request(e.to_string())),
+    }
+}
+
+fn recipe_bad_request(e: String) -> Response {
+    tracing::error!("Failed to render recipe: {}", e);
+    StatusCode::BAD_REQUEST.into_response()
+}
+
+/// The recipe given inline or by path, as a recipe file; inline recipes resolve relative paths
+/// against the server's working directory
+fn recipe_file_from_request(
+    recipe: Option<Recipe>,
+    path: Option<String>,
+) -> Result<RecipeFile, Response> {
+    match (recipe, path) {
+        (Some(recipe), None) => {
+            let current_dir =
+                std::env::current_dir().map_err(|e| recipe_bad_request(e.to_string()))?;
+            Ok(RecipeFile {
+                content: serde_json::to_string(&recipe)
+                    .map_err(|e| recipe_bad_request(e.to_string()))?,
+                file_path: current_dir.join("recipe.json"),
+                parent_dir: current_dir,
+            })
+        }
+        (None, Some(path)) => {
+            read_recipe_file(&path).map_err(|e| recipe_bad_request(e.to_string()))
+        }
+        _ => Err(StatusCode::BAD_REQUEST.into_response()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/recipes/dry_run",
+    request_body = RenderRecipeRequest,
+    responses(
+        (status = 200, description = "What a run of the recipe would send and enable", body = RecipeDryRun),
+        (status = 400, description = "Not exactly one of recipe and path is set, or the recipe can't be rendered"),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 422, description = "Missing or invalid parameter values", body = InvalidParametersResponse)
+    ),
+    tag = "Recipe Management"
+)]
+/// Render a recipe with parameter values and show the system prompt, first message, tools and
+/// settings a run would use, without creating a session or calling a provider
+async fn dry_run_recipe_handler(
+    _scope: RequireScope<scopes::Chat>,
+    Json(request): Json<RenderRecipeRequest>,
+) -> Result<Json<RecipeDryRun>, Response> {
+    let recipe_file = recipe_file_from_request(request.recipe, request.path)?;
+    match dry_run_recipe(recipe_file, &request.params).await {
+        Ok(dry_run) => Ok(Json(dry_run)),
+        Err(RecipeError::InvalidParams { errors }) => Err(invalid_parameters(errors)),
+        Err(e) => Err(recipe_bad_request(e.to_string())),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/recipes/import",
+    request_body = ImportRecipeRequest,
+    responses(
+        (status = 200, description = "Recipe imported into the local recipe directory", body = ImportRecipeResponse),
+        (status = 400, description = "Unrecognized or non-https source", body = RecipeErrorResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 409, description = "A recipe with the same name already exists", body = RecipeErrorResponse),
+        (status = 413, description = "Recipe is too large", body = RecipeErrorResponse),
+        (status = 422, description = "Recipe is invalid", body = RecipeErrorResponse),
+        (status = 500, description = "Internal server error", body = RecipeErrorResponse),
+        (status = 502, description = "Recipe couldn't be fetched", body = RecipeErrorResponse)
+    ),
+    tag = "Recipe Management"
+)]
+/// Fetch a shared recipe, validate it and save it in the local recipe directory
+async fn import_recipe_handler(
+    _scope: RequireScope<scopes::Chat>,
+    Json(request): Json<ImportRecipeRequest>,
+) -> Result<Json<ImportRecipeResponse>, Response> {
+    let result = match get_default_recipes_dir() {
+        Ok(recipes_dir) => import_recipe(&request.source, &recipes_dir, request.overwrite).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(imported) => Ok(Json(ImportRecipeResponse {
+            recipe: imported.recipe,
+            path: imported.path.to_string_lossy().to_string(),
+            issues: imported.issues,
+        })),
+        Err(e) => {
+            let status = match &e {
+                ImportError::InvalidSource(_) | ImportError::InsecureUrl(_) => {
+                    StatusCode::BAD_REQUEST
+                }
+                ImportError::AlreadyExists(_) => StatusCode::CONFLICT,
+                ImportError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+                ImportError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+                ImportError::Fetch(_) => StatusCode::BAD_GATEWAY,
+                ImportError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            tracing::error!("Failed to import recipe: {}", e);
+            let message = e.to_string();
+            let issues = match e {
+                ImportError::Invalid(issues) => issues,
+                _ => Vec::new(),
+            };
+            Err((status, Json(RecipeErrorResponse { message, issues })).into_response())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/recipes",
+    responses(
+        (status = 200, description = "Recipes in the configured recipe directories", body = ListRecipesResponse),
+        (status = 401, description = "Unauthorized - invalid secret key")
+    ),
+    tag = "Recipe Management"
+)]
+/// List the recipes saved on this machine
+async fn list_recipes(
+    _scope: RequireScope<scopes::Chat>,
+) -> Result<Json<ListRecipesResponse>, StatusCode> {
+    let recipes = list_local_recipes(&configured_recipe_dirs());
+    Ok(Json(ListRecipesResponse { recipes }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/recipes/{name}",
+    params(
+        ("name" = String, Path, description = "Name of the recipe, prefixed with its folder for nested recipes")
+    ),
+    responses(
+        (status = 200, description = "Recipe found", body = LocalRecipeResponse),
+        (status = 400, description = "Invalid recipe name", body = RecipeErrorResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Recipe not found", body = RecipeErrorResponse),
+        (status = 422, description = "Recipe file can't be parsed", body = RecipeErrorResponse),
+        (status = 500, description = "Internal server error", body = RecipeErrorResponse)
+    ),
+    tag = "Recipe Management"
+)]
+async fn get_recipe(
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+) -> Result<Json<LocalRecipeResponse>, Response> {
+    let (recipe, content) =
+        get_local_recipe(&configured_recipe_dirs(), &name).map_err(local_recipe_error)?;
+    Ok(Json(LocalRecipeResponse { recipe, content }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/recipes/{name}",
+    params(
+        ("name" = String, Path, description = "Name of the recipe, prefixed with its folder for nested recipes")
+    ),
+    request_body = SaveRecipeRequest,
+    responses(
+        (status = 200, description = "Recipe saved", body = SaveRecipeResponse),
+        (status = 400, description = "Invalid recipe name", body = RecipeErrorResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 422, description = "Recipe is invalid", body = RecipeErrorResponse),
+        (status = 500, description = "Internal server error", body = RecipeErrorResponse)
+    ),
+    tag = "Recipe Management"
+)]
+/// Validate and save a recipe, replacing the file if the recipe already exists
+async fn save_recipe(
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+    Json(request): Json<SaveRecipeRequest>,
+) -> Result<Json<SaveRecipeResponse>, Response> {
+    let (recipe, issues) = save_local_recipe(&configured_recipe_dirs(), &name, &request.content)
+        .map_err(local_recipe_error)?;
+    Ok(Json(SaveRecipeResponse { recipe, issues }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/recipes/{name}",
+    params(
+        ("name" = String, Path, description = "Name of the recipe, prefixed with its folder for nested recipes")
+    ),
+    responses(
+        (status = 204, description = "Recipe deleted"),
+        (status = 400, description = "Invalid recipe name", body = RecipeErrorResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 404, description = "Recipe not found", body = RecipeErrorResponse),
+        (status = 500, description = "Internal server error", body = RecipeErrorResponse)
+    ),
+    tag = "Recipe Management"
+)]
+async fn delete_recipe(
+    _scope: RequireScope<scopes::Chat>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, Response> {
+    delete_local_recipe(&configured_recipe_dirs(), &name).map_err(local_recipe_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
+        .route("/recipes", get(list_recipes))
         .route("/recipes/create", post(create_recipe))
+        .route("/recipes/validate", post(validate_recipe_handler))
+        .route("/recipes/render", post(render_recipe))
+        .route("/recipes/dry_run", post(dry_run_recipe_handler))
+        .route("/recipes/import", post(import_recipe_handler))
         .route("/recipes/encode", post(encode_recipe))
         .route("/recipes/decode", post(decode_recipe))
+        // Nested recipes are named `folder/name`, so the name can contain a slash
+        .route(
+            "/recipes/{*name}",
+            get(get_recipe).put(save_recipe).delete(delete_recipe),
+        )
         .with_state(state)
 }
 
@@ -196,7 +645,10 @@ mod tests {
         assert_eq!(decoded.description, original_recipe.description);
         assert_eq!(decoded.instructions, original_recipe.instructions);
 
-        let encode_request = EncodeRecipeRequest { recipe: decoded };
+        let encode_request = EncodeRecipeRequest {
+            recipe: decoded,
+            sign: false,
+        };
         let encode_response = encode_recipe(Json(encode_request)).await;
 
         assert!(encode_response.is_ok());
@@ -1,37 +1,67 @@
-use super::utils::verify_secret_key;
-use crate::state::AppState;
+use super::events::ServerEvent;
+use super::limits::session_tokens;
+use super::recipe::local_recipe_error;
+use super::session::session_format_error;
+use super::utils::{max_image_body_size, scopes, RequireScope};
+use crate::state::{
+    AppState, IdempotencyClaim, IdempotentOutcome, QueueTicket, ReplayBuffer, StreamGuard,
+};
 use axum::{
-    extract::{DefaultBodyLimit, State},
+    extract::{DefaultBodyLimit, Path, State},
     http::{self, HeaderMap, StatusCode},
-    response::IntoResponse,
-    routing::post,
+    response::{IntoResponse, Response},
+    routing::{get, post},
     Json, Router,
 };
 use bytes::Bytes;
 use futures::{stream::StreamExt, Stream};
 use goose::{
-    agents::{AgentEvent, SessionConfig},
-    message::{push_message, Message},
+    agents::{
+        Agent, AgentEvent, PendingConfirmation, ReplyExecutionMode, SessionConfig, SessionTimings,
+        TimingSummary,
+    },
+    audit::AuditDecision,
+    config::{Persona, PersonaManager},
+    context_mgmt::{
+        auto_compact::{compact_messages, AutoCompactResult},
+        breakdown::ContextBreakdown,
+    },
+    message::{push_message, ContextLengthExceeded, Message, MessageContent},
     permission::permission_confirmation::PrincipalType,
+    providers::errors::ProviderError,
 };
 use goose::{
     permission::{Permission, PermissionConfirmation},
+    recipe::{
+        local_recipes::{configured_recipe_dirs, get_local_recipe},
+        response_schema::SchemaValidation,
+        template_recipe::render_recipe_for_preview,
+        Recipe, Response as RecipeResponse,
+    },
     session,
+    session_callback::{self, RecipeInfo, SessionCallback, SessionResult, SessionResultPayload},
 };
 use mcp_core::ToolResult;
-use rmcp::model::{Content, ServerNotification};
+use rmcp::model::{Content, Role, ServerNotification};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value;
 use std::{
+    collections::{HashMap, HashSet},
     convert::Infallible,
     path::PathBuf,
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{
+    self,
+    error::{SendTimeoutError, TrySendError},
 };
-use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::sync::CancellationToken;
@@ -43,15 +73,52 @@ struct ChatRequest {
     session_id: Option<String>,
     session_working_dir: String,
     scheduled_job_id: Option<String>,
+    /// Local recipe the session runs. Its tools are limited to the extensions the recipe
+    /// declares, plus goose's own.
+    #[serde(default)]
+    recipe_name: Option<String>,
+    /// Keep every loaded extension available even though a recipe is named
+    #[serde(default)]
+    allow_extra_extensions: bool,
+    /// How the agent may use tools in this reply. Each request sets its own mode, so a client
+    /// can ask for a plan and then send a follow-up in another mode to carry it out.
+    #[serde(default)]
+    execution_mode: ReplyExecutionMode,
+    /// Persona to reply as, from the `personas` registry in config. Explicit settings on the
+    /// request, such as a recipe's extensions, take precedence over the persona's.
+    #[serde(default)]
+    persona: Option<String>,
+    /// URL the reply's result is POSTed to once it finishes, http(s) only
+    #[serde(default)]
+    callback_url: Option<String>,
+    /// Key the callback body is signed with, sent as `X-Goose-Signature`
+    #[serde(default)]
+    callback_secret: Option<String>,
 }
 
+/// Seconds a reply waits for a client to make room in its stream before closing it
+const STREAM_STALL_TIMEOUT_KEY: &str = "GOOSE_SERVER_STREAM_STALL_TIMEOUT";
+const DEFAULT_STREAM_STALL_TIMEOUT: u64 = 30;
+
 pub struct SseResponse {
     rx: ReceiverStream<String>,
+    /// Sent as a final error event once the channel closes, if set by then
+    close_reason: Option<Arc<OnceLock<String>>>,
 }
 
 impl SseResponse {
-    fn new(rx: ReceiverStream<String>) -> Self {
-        Self { rx }
+    pub fn new(rx: ReceiverStream<String>) -> Self {
+        Self {
+            rx,
+            close_reason: None,
+        }
+    }
+
+    fn closing_with(rx: ReceiverStream<String>, close_reason: Arc<OnceLock<String>>) -> Self {
+        Self {
+            rx,
+            close_reason: Some(close_reason),
+        }
     }
 }
 
@@ -59,9 +126,22 @@ impl Stream for SseResponse {
     type Item = Result<Bytes, Infallible>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.rx)
-            .poll_next(cx)
-            .map(|opt| opt.map(|s| Ok(Bytes::from(s))))
+        match Pin::new(&mut self.rx).poll_next(cx) {
+            Poll::Ready(None) => {
+                let reason = self
+                    .close_reason
+                    .take()
+                    .and_then(|reason| reason.get().cloned());
+                Poll::Ready(reason.map(|error| {
+                    Ok(Bytes::from(format_event(&MessageEvent::Error {
+                        error,
+                        code: None,
+                        breakdown: None,
+                    })))
+                }))
+            }
+            poll => poll.map(|opt| opt.map(|s| Ok(Bytes::from(s)))),
+        }
     }
 }
 
@@ -85,11 +165,27 @@ enum MessageEvent {
     Message {
         message: Message,
     },
+    /// An assistant message written in plan mode, proposing steps rather than taking them
+    Plan {
+        message: Message,
+    },
     Error {
         error: String,
+        /// Identifies the kind of provider error, e.g. `model_not_found`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+        /// For `context_length_exceeded`, where the tokens of the rejected request went
+        #[serde(skip_serializing_if = "Option::is_none")]
+        breakdown: Option<ContextBreakdown>,
     },
     Finish {
         reason: String,
+        /// Model and tool time over the turns of the reply
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timing: Option<TimingSummary>,
+        /// Whether the output matched the recipe's response schema, for recipes declaring one
+        #[serde(flatten, skip_serializing_if = "Option::is_none")]
+        schema_validation: Option<SchemaValidation>,
     },
     ModelChange {
         model: String,
@@ -99,49 +195,702 @@ enum MessageEvent {
         request_id: String,
         message: ServerNotification,
     },
+    /// Progress reported by the server while a tool call runs
+    Progress {
+        tool_request_id: String,
+        progress: u32,
+        total: Option<u32>,
+        message: Option<String>,
+    },
+    ContextCompacted {
+        tokens_before: usize,
+        tokens_after: usize,
+    },
+    /// The provider rejected its credentials, so the user has to sign in with it again
+    AuthRequired {
+        provider: String,
+        /// Config keys holding the provider's credentials
+        config_keys: Vec<String>,
+    },
+    /// The agent is busy with another reply, so this one waits. Sent again as it moves up the
+    /// queue; the reply starts once it is first and the agent is free.
+    Queued {
+        /// Place in the queue, 1 being next
+        position: usize,
+    },
+    /// Sent to a reconnecting client that missed more events than are kept. It should refetch
+    /// the session's history; the events that follow are live.
+    Resync {
+        session_id: String,
+    },
 }
 
-async fn stream_event(
-    event: MessageEvent,
-    tx: &mpsc::Sender<String>,
-) -> Result<(), mpsc::error::SendError<String>> {
-    let json = serde_json::to_string(&event).unwrap_or_else(|e| {
+/// Sends a reply's events to its client without letting a slow client hold up the agent.
+/// Notifications are dropped while the channel is full. Other events wait for room, and once
+/// one has waited longer than the stall timeout the stream is closed with an error.
+///
+/// Every event is also numbered and kept in the reply's replay buffer. When the client goes away
+/// the reply carries on, so a client that reconnects within the reconnect window catches up.
+#[derive(Clone)]
+struct EventSender {
+    replay: Arc<ReplayBuffer>,
+    reconnect_window: Duration,
+    stall_timeout: Duration,
+    /// Notifications dropped from this stream
+    dropped: Arc<AtomicU64>,
+    /// Notifications dropped from all streams
+    dropped_total: Arc<AtomicU64>,
+    close_reason: Arc<OnceLock<String>>,
+    cancel: CancellationToken,
+}
+
+impl EventSender {
+    /// Whether the reply should stop: its stream was closed for stalling, or no client has been
+    /// connected for the reconnect window
+    fn is_closed(&self) -> bool {
+        self.close_reason.get().is_some() || self.replay.abandoned(self.reconnect_window)
+    }
+}
+
+fn stream_stall_timeout() -> Duration {
+    Duration::from_secs(
+        goose::config::Config::global()
+            .get_param(STREAM_STALL_TIMEOUT_KEY)
+            .unwrap_or(DEFAULT_STREAM_STALL_TIMEOUT),
+    )
+}
+
+/// The config keys holding `provider`'s credentials, for pointing the user at what to update
+fn credential_keys(provider: &str) -> Vec<String> {
+    goose::providers::providers()
+        .into_iter()
+        .find(|metadata| metadata.name == provider)
+        .map(|metadata| {
+            metadata
+                .config_keys
+                .into_iter()
+                .filter(|key| key.secret || key.required)
+                .map(|key| key.name)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn format_event(event: &MessageEvent) -> String {
+    let json = serde_json::to_string(event).unwrap_or_else(|e| {
         format!(
             r#"{{"type":"Error","error":"Failed to serialize event: {}"}}"#,
             e
         )
     });
-    tx.send(format!("data: {}\n\n", json)).await
+    format!("data: {}\n\n", json)
+}
+
+async fn stream_event(
+    event: MessageEvent,
+    tx: &EventSender,
+) -> Result<(), SendTimeoutError<String>> {
+    let droppable = matches!(
+        event,
+        MessageEvent::Notification { .. } | MessageEvent::Progress { .. }
+    );
+    let data = format_event(&event);
+    if tx.close_reason.get().is_some() {
+        return Err(SendTimeoutError::Closed(data));
+    }
+
+    let (data, client) = tx.replay.record(&data);
+    let Some(client) = client else {
+        return Ok(());
+    };
+
+    let result = if droppable {
+        match client.try_send(data) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                tx.dropped.fetch_add(1, Ordering::Relaxed);
+                tx.dropped_total.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(TrySendError::Closed(data)) => Err(SendTimeoutError::Closed(data)),
+        }
+    } else {
+        client.send_timeout(data, tx.stall_timeout).await
+    };
+
+    match result {
+        Err(SendTimeoutError::Timeout(data)) => {
+            let reason = format!(
+                "Closed the reply stream after the client stopped reading for {}s",
+                tx.stall_timeout.as_secs()
+            );
+            tracing::warn!("{}", reason);
+            let _ = tx.close_reason.set(reason);
+            tx.cancel.cancel();
+            Err(SendTimeoutError::Timeout(data))
+        }
+        // The client went away; the event stays buffered in case it reconnects
+        Err(SendTimeoutError::Closed(_)) => {
+            if tx.is_closed() {
+                tx.cancel.cancel();
+            }
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+fn is_context_length_exceeded(message: &Message) -> bool {
+    context_length_exceeded(message).is_some()
+}
+
+fn context_length_exceeded(message: &Message) -> Option<&ContextLengthExceeded> {
+    message.content.iter().find_map(|content| match content {
+        MessageContent::ContextLengthExceeded(exceeded) => Some(exceeded),
+        _ => None,
+    })
+}
+
+/// Compact the conversation after the provider rejected it for exceeding the context window
+///
+/// Any partial work from the failed turn is dropped so that the retry starts again from the
+/// most recent user prompt, which is preserved verbatim.
+async fn compact_for_retry(
+    agent: &Agent,
+    messages: &[Message],
+) -> anyhow::Result<AutoCompactResult> {
+    let prompt_index = messages
+        .iter()
+        .rposition(|m| m.role == Role::User && !m.is_tool_response())
+        .ok_or_else(|| anyhow::anyhow!("No user message to retry"))?;
+    compact_messages(agent, &messages[..=prompt_index]).await
+}
+
+/// Directories a session's working directory has to be inside, as a list or a comma-separated
+/// string. Any directory is allowed when unset.
+const ALLOWED_WORKING_DIR_ROOTS_KEY: &str = "GOOSE_ALLOWED_WORKING_DIR_ROOTS";
+
+/// The configured roots, canonical, or `None` when any directory is allowed
+pub(crate) fn allowed_working_dir_roots() -> Option<Vec<PathBuf>> {
+    let roots =
+        match goose::config::Config::global().get_param::<Value>(ALLOWED_WORKING_DIR_ROOTS_KEY) {
+            Ok(Value::Array(roots)) => roots
+                .iter()
+                .filter_map(|root| root.as_str().map(str::to_string))
+                .collect(),
+            Ok(Value::String(roots)) => roots.split(',').map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+    canonical_roots(&roots)
+}
+
+/// `roots` with symlinks resolved, leaving out those that can't be. `None` when no root is
+/// given at all; when roots are given but none can be resolved the list is empty, so that no
+/// working directory is allowed rather than every one.
+fn canonical_roots(roots: &[String]) -> Option<Vec<PathBuf>> {
+    let roots: Vec<&str> = roots
+        .iter()
+        .map(|root| root.trim())
+        .filter(|root| !root.is_empty())
+        .collect();
+    if roots.is_empty() {
+        return None;
+    }
+    let canonical: Vec<PathBuf> = roots
+        .iter()
+        .filter_map(|root| match std::fs::canonicalize(root) {
+            Ok(root) => Some(root),
+            Err(e) => {
+                tracing::warn!("Ignoring allowed working directory root {}: {}", root, e);
+                None
+            }
+        })
+        .collect();
+    if canonical.is_empty() {
+        tracing::error!(
+            "None of the roots in {} can be used, so every working directory is refused",
+            ALLOWED_WORKING_DIR_ROOTS_KEY
+        );
+    }
+    Some(canonical)
+}
+
+/// Check that `dir` is an absolute path to an existing directory inside one of `allowed_roots`,
+/// unless any directory is allowed, and return it with symlinks resolved. Roots must already be
+/// canonical.
+pub(crate) fn validate_working_dir(
+    dir: &str,
+    allowed_roots: Option<&[PathBuf]>,
+) -> Result<PathBuf, String> {
+    let path = std::path::Path::new(dir);
+    if dir.is_empty() || !path.is_absolute() {
+        return Err(format!(
+            "Working directory must be an absolute path, got '{}'",
+            dir
+        ));
+    }
+    let canonical = std::fs::canonicalize(path)
+        .map_err(|e| format!("Working directory {} is not accessible: {}", dir, e))?;
+    if !canonical.is_dir() {
+        return Err(format!("Working directory {} is not a directory", dir));
+    }
+    if allowed_roots.is_some_and(|roots| !roots.iter().any(|root| canonical.starts_with(root))) {
+        return Err(format!(
+            "Working directory {} is outside the allowed roots",
+            canonical.display()
+        ));
+    }
+    Ok(canonical)
+}
+
+/// Replace the request's working directory with its validated, canonical form, rejecting the
+/// request with `422 Unprocessable Entity` when it is not usable
+fn check_working_dir(request: &mut ChatRequest) -> Result<(), Response> {
+    let dir = validate_working_dir(
+        &request.session_working_dir,
+        allowed_working_dir_roots().as_deref(),
+    )
+    .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e).into_response())?;
+    request.session_working_dir = dir.to_string_lossy().into_owned();
+    Ok(())
+}
+
+/// The extensions a reply may use: those declared by the request's recipe, unless it names no
+/// recipe or allows extra extensions. A recipe that cannot be loaded rejects the request.
+fn recipe_allowed_extensions(request: &ChatRequest) -> Result<Option<Vec<String>>, Response> {
+    let Some(name) = request.recipe_name.as_deref() else {
+        return Ok(None);
+    };
+    if request.allow_extra_extensions {
+        return Ok(None);
+    }
+    Ok(load_recipe(name)?.extension_names())
+}
+
+/// The response the request's recipe declares, whose schema the reply's output is checked
+/// against when it finishes
+fn recipe_response(request: &ChatRequest) -> Result<Option<RecipeResponse>, Response> {
+    match request.recipe_name.as_deref() {
+        Some(name) => Ok(load_recipe(name)?.response),
+        None => Ok(None),
+    }
+}
+
+fn load_recipe(name: &str) -> Result<Recipe, Response> {
+    let (local_recipe, content) =
+        get_local_recipe(&configured_recipe_dirs(), name).map_err(local_recipe_error)?;
+    let recipe_dir = std::path::Path::new(&local_recipe.path)
+        .parent()
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    render_recipe_for_preview(&content, recipe_dir, &HashMap::new())
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response())
+}
+
+/// The persona the request names, rejecting the request with `422 Unprocessable Entity` when
+/// there is no such persona
+fn resolve_persona(request: &ChatRequest) -> Result<Option<Persona>, Response> {
+    let Some(name) = request.persona.as_deref() else {
+        return Ok(None);
+    };
+    PersonaManager::get(name).map(Some).ok_or_else(|| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Unknown persona '{}'", name),
+        )
+            .into_response()
+    })
+}
+
+/// Reject a callback URL the server won't call with `422 Unprocessable Entity`
+fn check_callback(request: &ChatRequest) -> Result<(), Response> {
+    let Some(url) = request.callback_url.as_deref() else {
+        return Ok(());
+    };
+    SessionCallback::new(url, None)
+        .map(|_| ())
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response())
+}
+
+/// Turn a reply starting a new session away with `429 Too Many Requests` once the daily token
+/// budget is used up. Replies in sessions that already exist may carry on.
+fn check_token_budget(state: &AppState, request: &ChatRequest) -> Result<(), Response> {
+    let existing = request
+        .session_id
+        .as_ref()
+        .and_then(|id| session::get_path(session::Identifier::Name(id.clone())).ok())
+        .is_some_and(|path| path.exists());
+    if existing {
+        return Ok(());
+    }
+    state
+        .rate_limiter
+        .check_token_budget()
+        .map_err(IntoResponse::into_response)
+}
+
+/// Where the reply to `request` sends its result, if it names a callback URL
+fn result_delivery(
+    request: &ChatRequest,
+    session_id: &str,
+) -> anyhow::Result<Option<ResultDelivery>> {
+    let Some(url) = request.callback_url.as_deref() else {
+        return Ok(None);
+    };
+    let callback = SessionCallback::new(url, request.callback_secret.clone())?;
+    let recipe = request.recipe_name.as_ref().map(|name| RecipeInfo {
+        source: name.clone(),
+        title: load_recipe(name).ok().map(|recipe| recipe.title),
+    });
+    Ok(Some(ResultDelivery {
+        callback,
+        session_id: session_id.to_string(),
+        recipe,
+        started: Instant::now(),
+    }))
+}
+
+/// A reply's result, sent to its callback URL in the background so the stream never waits on it
+struct ResultDelivery {
+    callback: SessionCallback,
+    session_id: String,
+    recipe: Option<RecipeInfo>,
+    started: Instant,
+}
+
+impl ResultDelivery {
+    fn send(self, result: SessionResult, error: Option<String>, messages: Vec<Message>) {
+        tokio::spawn(async move {
+            let payload = SessionResultPayload::new(
+                Some(self.session_id),
+                result,
+                error,
+                self.started.elapsed(),
+                &messages,
+                self.recipe,
+            );
+            session_callback::deliver(&self.callback, &payload).await;
+        });
+    }
+}
+
+/// Refuse to continue a session stored in a way this server can't rewrite, since saving the
+/// reply would overwrite what it doesn't understand
+fn check_session_format(request: &ChatRequest) -> Result<(), Response> {
+    let Some(session_id) = request.session_id.as_deref() else {
+        return Ok(());
+    };
+    let Ok(session_path) = session::get_path(session::Identifier::Name(session_id.to_string()))
+    else {
+        return Ok(());
+    };
+    session::check_resumable(&session_path).map_err(session_format_error)
+}
+
+/// Header a client sets to the same value when retrying a request, so it is only run once
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Claim the request's `Idempotency-Key`, if it sent one. While the request that first used
+/// the key is still replying, this is a `409 Conflict` naming its session. Once it has
+/// finished, its final event is replayed instead of replying again.
+fn claim_idempotency_key(
+    headers: &HeaderMap,
+    state: &AppState,
+    request: &mut ChatRequest,
+) -> Result<Option<IdempotencyClaim>, Response> {
+    let Some(key) = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Ok(None);
+    };
+    let session_id = request
+        .session_id
+        .get_or_insert_with(session::generate_session_id)
+        .clone();
+
+    match state.idempotency_keys.claim(key, &session_id) {
+        Ok(claim) => Ok(Some(claim)),
+        Err(IdempotentOutcome::Running { session_id }) => Err((
+            StatusCode::CONFLICT,
+            Json(json!({ "session_id": session_id })),
+        )
+            .into_response()),
+        Err(IdempotentOutcome::Finished { finish_event, .. }) => {
+            let (tx, rx) = mpsc::channel(1);
+            let _ = tx.try_send(finish_event);
+            Err(SseResponse::new(ReceiverStream::new(rx)).into_response())
+        }
+    }
 }
 
 async fn reply_handler(
     State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    headers: HeaderMap,
+    Json(mut request): Json<ChatRequest>,
+) -> Result<SseResponse, Response> {
+    check_working_dir(&mut request)?;
+    check_session_format(&request)?;
+    let allowed_extensions = recipe_allowed_extensions(&request)?;
+    let response = recipe_response(&request)?;
+    let persona = resolve_persona(&request)?;
+    check_callback(&request)?;
+    check_token_budget(&state, &request)?;
+    let ticket = join_reply_queue(&state)?;
+    let claim = claim_idempotency_key(&headers, &state, &mut request)?;
+
+    Ok(start_reply(
+        state,
+        request,
+        Vec::new(),
+        allowed_extensions,
+        response,
+        persona,
+        claim,
+        ticket,
+    ))
+}
+
+/// Take a place in the reply queue when replies run one at a time, or `503 Service
+/// Unavailable` when it is full
+fn join_reply_queue(state: &AppState) -> Result<Option<QueueTicket>, Response> {
+    state
+        .join_reply_queue()
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response())
+}
+
+/// Wait in the queue until the agent is free, telling the client its position whenever it
+/// changes. `None` when the wait times out or the reply is cancelled first.
+async fn wait_for_agent(
+    state: &AppState,
+    ticket: QueueTicket,
+    session_id: &str,
+    scheduled_job_id: Option<&str>,
+    tx: &EventSender,
+) -> Option<StreamGuard> {
+    let mut changes = state.reply_queue.changes();
+    let deadline = tokio::time::Instant::now() + state.reply_queue.timeout();
+    let mut reported = None;
+    loop {
+        match state.start_queued_stream(&ticket, session_id, scheduled_job_id) {
+            Ok(guard) => return Some(guard),
+            Err(position) if reported != Some(position) => {
+                reported = Some(position);
+                let _ = stream_event(MessageEvent::Queued { position }, tx).await;
+            }
+            Err(_) => {}
+        }
+        tokio::select! {
+            _ = changes.changed() => {}
+            _ = tokio::time::sleep_until(deadline) => return None,
+            _ = tx.cancel.cancelled() => return None,
+        }
+    }
+}
+
+/// Header a reconnecting client sets to the id of the last event it received
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+
+/// Reconnect to the latest reply in a session after the connection dropped. The events missed
+/// since `Last-Event-ID` are sent first, then the reply continues live. This works while the
+/// reply runs and for the reconnect window after it finishes.
+async fn reconnect_handler(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
     headers: HeaderMap,
-    Json(request): Json<ChatRequest>,
+    Path(session_id): Path<String>,
 ) -> Result<SseResponse, StatusCode> {
-    verify_secret_key(&headers, &state)?;
+    let replay = state
+        .replay_buffer(&session_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0);
+    let resync = format_event(&MessageEvent::Resync { session_id });
 
-    let (tx, rx) = mpsc::channel(100);
-    let stream = ReceiverStream::new(rx);
-    let cancel_token = CancellationToken::new();
+    Ok(SseResponse::new(ReceiverStream::new(
+        replay.reconnect(last_event_id, &resync),
+    )))
+}
 
-    let messages = request.messages;
-    let session_working_dir = request.session_working_dir.clone();
+#[derive(Debug, Deserialize, Serialize)]
+struct PromptReplyRequest {
+    /// Arguments passed to the prompt
+    #[serde(default)]
+    arguments: HashMap<String, String>,
+    /// Conversation so far, the rendered prompt is appended to it
+    #[serde(default)]
+    messages: Vec<Message>,
+    session_id: Option<String>,
+    session_working_dir: String,
+    scheduled_job_id: Option<String>,
+    #[serde(default)]
+    recipe_name: Option<String>,
+    #[serde(default)]
+    allow_extra_extensions: bool,
+    #[serde(default)]
+    execution_mode: ReplyExecutionMode,
+    #[serde(default)]
+    persona: Option<String>,
+    #[serde(default)]
+    callback_url: Option<String>,
+    #[serde(default)]
+    callback_secret: Option<String>,
+}
+
+/// Render an extension prompt into the conversation and reply to it
+async fn prompt_reply_handler(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(request): Json<PromptReplyRequest>,
+) -> Result<SseResponse, Response> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED.into_response())?;
+    let arguments = serde_json::to_value(&request.arguments)
+        .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY.into_response())?;
+    let prompt = agent.get_prompt(&name, arguments).await.map_err(|e| {
+        tracing::error!("Failed to render prompt {}: {}", name, e);
+        StatusCode::NOT_FOUND.into_response()
+    })?;
+    let injected = prompt.messages.into_iter().map(Message::from).collect();
+
+    let mut request = ChatRequest {
+        messages: request.messages,
+        session_id: request.session_id,
+        session_working_dir: request.session_working_dir,
+        scheduled_job_id: request.scheduled_job_id,
+        recipe_name: request.recipe_name,
+        allow_extra_extensions: request.allow_extra_extensions,
+        execution_mode: request.execution_mode,
+        persona: request.persona,
+        callback_url: request.callback_url,
+        callback_secret: request.callback_secret,
+    };
+    check_working_dir(&mut request)?;
+    check_session_format(&request)?;
+    let allowed_extensions = recipe_allowed_extensions(&request)?;
+    let response = recipe_response(&request)?;
+    let persona = resolve_persona(&request)?;
+    check_callback(&request)?;
+    check_token_budget(&state, &request)?;
+    let ticket = join_reply_queue(&state)?;
+    let claim = claim_idempotency_key(&headers, &state, &mut request)?;
+
+    Ok(start_reply(
+        state,
+        request,
+        injected,
+        allowed_extensions,
+        response,
+        persona,
+        claim,
+        ticket,
+    ))
+}
 
+/// Stream the agent's reply to `request`, first appending and streaming the `injected` messages.
+/// Tools are limited to `allowed_extensions` when set, or else to the `persona`'s extensions.
+/// The idempotency `claim`, if any, is marked finished once the reply has been streamed. With a
+/// queue `ticket` the reply first waits for the agent to be free.
+#[allow(clippy::too_many_arguments)]
+fn start_reply(
+    state: Arc<AppState>,
+    request: ChatRequest,
+    injected: Vec<Message>,
+    allowed_extensions: Option<Vec<String>>,
+    recipe_output: Option<RecipeResponse>,
+    persona: Option<Persona>,
+    claim: Option<IdempotencyClaim>,
+    ticket: Option<QueueTicket>,
+) -> SseResponse {
     let session_id = request
         .session_id
+        .clone()
         .unwrap_or_else(session::generate_session_id);
 
+    let (tx, rx) = mpsc::channel(100);
+    let stream = ReceiverStream::new(rx);
+    let replay = state.start_replay(&session_id, tx);
+    let cancel_token = CancellationToken::new();
+    let close_reason = Arc::new(OnceLock::new());
+    let tx = EventSender {
+        replay: replay.buffer(),
+        reconnect_window: state.reconnect_window(),
+        stall_timeout: stream_stall_timeout(),
+        dropped: Arc::default(),
+        dropped_total: state.dropped_stream_events(),
+        close_reason: Arc::clone(&close_reason),
+        cancel: cancel_token.clone(),
+    };
+
+    let mut messages = request.messages;
+    let session_working_dir = request.session_working_dir.clone();
+    let execution_mode = request.execution_mode;
+
     let task_cancel = cancel_token.clone();
     let task_tx = tx.clone();
+    // Checked by the handlers before the reply started
+    let mut delivery = result_delivery(&request, &session_id).ok().flatten();
 
     std::mem::drop(tokio::spawn(async move {
+        let _replay = replay;
+        let _stream_guard = match ticket {
+            None => state.track_stream(&session_id, request.scheduled_job_id.as_deref()),
+            Some(ticket) => {
+                match wait_for_agent(
+                    &state,
+                    ticket,
+                    &session_id,
+                    request.scheduled_job_id.as_deref(),
+                    &task_tx,
+                )
+                .await
+                {
+                    Some(guard) => guard,
+                    None => {
+                        if let Some(delivery) = delivery {
+                            delivery.send(
+                                SessionResult::Error,
+                                Some("Timed out waiting for the agent".to_string()),
+                                messages,
+                            );
+                        }
+                        let _ = stream_event(
+                            MessageEvent::Finish {
+                                reason: "queue_timeout".to_string(),
+                                timing: None,
+                                schema_validation: None,
+                            },
+                            &task_tx,
+                        )
+                        .await;
+                        return;
+                    }
+                }
+            }
+        };
         let agent = match state.get_agent().await {
             Ok(agent) => agent,
             Err(_) => {
+                if let Some(delivery) = delivery {
+                    delivery.send(
+                        SessionResult::Error,
+                        Some("No agent configured".to_string()),
+                        messages,
+                    );
+                }
                 let _ = stream_event(
                     MessageEvent::Error {
                         error: "No agent configured".to_string(),
+                        code: None,
+                        breakdown: None,
                     },
                     &task_tx,
                 )
@@ -150,38 +899,32 @@ async fn reply_handler(
             }
         };
 
+        // Injected messages are part of the history and count as unsaved
+        let saved_message_count = messages.len();
+        for message in injected {
+            let _ = stream_event(
+                MessageEvent::Message {
+                    message: message.clone(),
+                },
+                &task_tx,
+            )
+            .await;
+            messages.push(message);
+        }
+
         let session_config = SessionConfig {
             id: session::Identifier::Name(session_id.clone()),
             working_dir: PathBuf::from(&session_working_dir),
             schedule_id: request.scheduled_job_id.clone(),
-            execution_mode: None,
+            execution_mode: Some(execution_mode.as_str().to_string()),
             max_turns: None,
             retry_config: None,
-        };
-
-        // Messages will be auto-compacted in agent.reply() if needed
-        let messages_to_process = messages.clone();
-
-        let mut stream = match agent
-            .reply(
-                &messages_to_process,
-                Some(session_config),
-                Some(task_cancel.clone()),
-            )
-            .await
-        {
-            Ok(stream) => stream,
-            Err(e) => {
-                tracing::error!("Failed to start reply stream: {:?}", e);
-                let _ = stream_event(
-                    MessageEvent::Error {
-                        error: e.to_string(),
-                    },
-                    &task_tx,
-                )
-                .await;
-                return;
-            }
+            allowed_extensions: allowed_extensions
+                .or_else(|| persona.as_ref().and_then(|p| p.extensions.clone())),
+            persona,
+            goose_mode: None,
+            permissions_path: None,
+            recorded_tool_results: None,
         };
 
         let mut all_messages = messages.clone();
@@ -189,9 +932,18 @@ async fn reply_handler(
             Ok(path) => path,
             Err(e) => {
                 tracing::error!("Failed to get session path: {}", e);
+                if let Some(delivery) = delivery {
+                    delivery.send(
+                        SessionResult::Error,
+                        Some(format!("Failed to get session path: {}", e)),
+                        all_messages,
+                    );
+                }
                 let _ = stream_event(
                     MessageEvent::Error {
                         error: format!("Failed to get session path: {}", e),
+                        code: None,
+                        breakdown: None,
                     },
                     &task_tx,
                 )
@@ -199,90 +951,262 @@ async fn reply_handler(
                 return;
             }
         };
-        let saved_message_count = all_messages.len();
 
-        loop {
-            tokio::select! {
-                            _ = task_cancel.cancelled() => {
-                                tracing::info!("Agent task cancelled");
-                                break;
-                            }
-            response = timeout(Duration::from_millis(500), stream.next()) => {
-                                match response {
-                                    Ok(Some(Ok(AgentEvent::Message(message)))) => {
-                                        push_message(&mut all_messages, message.clone());
-                                        if let Err(e) = stream_event(MessageEvent::Message { message }, &tx).await {
-                                            tracing::error!("Error sending message through channel: {}", e);
-                                            let _ = stream_event(
-                                                MessageEvent::Error {
-                                                    error: e.to_string(),
-                                                },
-                                                &tx,
-                                            ).await;
-                                            break;
+        // The agent adds the tokens it spends to the session's metadata
+        let tokens_before = session_tokens(&session_path);
+
+        // Messages will be auto-compacted in agent.reply() if needed
+        let mut messages_to_process = messages.clone();
+        // Compaction after a context length error is only attempted once per request
+        let mut compacted = false;
+        let mut timings = SessionTimings::default();
+        // Why the reply failed, if it did
+        let mut failure = None;
+        let mut corrections_left = recipe_output
+            .as_ref()
+            .map_or(0, |response| response.max_validation_retries());
+        let mut schema_validation = None;
+
+        'reply: loop {
+            let mut stream = match agent
+                .reply(
+                    &messages_to_process,
+                    Some(session_config.clone()),
+                    Some(task_cancel.clone()),
+                )
+                .await
+            {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::error!("Failed to start reply stream: {:?}", e);
+                    if let Some(delivery) = delivery {
+                        delivery.send(SessionResult::Error, Some(e.to_string()), all_messages);
+                    }
+                    let _ = stream_event(
+                        MessageEvent::Error {
+                            error: e.to_string(),
+                            code: None,
+                            breakdown: None,
+                        },
+                        &task_tx,
+                    )
+                    .await;
+                    return;
+                }
+            };
+
+            loop {
+                tokio::select! {
+                                _ = task_cancel.cancelled() => {
+                                    tracing::info!("Agent task cancelled");
+                                    break;
+                                }
+                response = timeout(Duration::from_millis(500), stream.next()) => {
+                                    match response {
+                                        Ok(Some(Ok(AgentEvent::Message(message)))) => {
+                                            if !compacted && is_context_length_exceeded(&message) {
+                                                compacted = true;
+                                                match compact_for_retry(&agent, &all_messages).await {
+                                                    Ok(result) => {
+                                                        let _ = stream_event(
+                                                            MessageEvent::ContextCompacted {
+                                                                tokens_before: result.tokens_before.unwrap_or_default(),
+                                                                tokens_after: result.tokens_after.unwrap_or_default(),
+                                                            },
+                                                            &tx,
+                                                        ).await;
+                                                        all_messages = result.messages.clone();
+                                                        messages_to_process = result.messages;
+                                                        continue 'reply;
+                                                    }
+                                                    Err(e) => {
+                                                        tracing::error!("Failed to compact context after context length error: {}", e);
+                                                    }
+                                                }
+                                            }
+
+                                            // Compaction didn't help, so the reply ends with the breakdown
+                                            let exceeded = context_length_exceeded(&message).cloned();
+                                            push_message(&mut all_messages, message.clone());
+                                            let event = if execution_mode == ReplyExecutionMode::Plan
+                                                && message.role == Role::Assistant
+                                            {
+                                                MessageEvent::Plan { message }
+                                            } else {
+                                                MessageEvent::Message { message }
+                                            };
+                                            if let Err(e) = stream_event(event, &tx).await {
+                                                tracing::error!("Error sending message through channel: {}", e);
+                                                let _ = stream_event(
+                                                    MessageEvent::Error {
+                                                        error: e.to_string(),
+                                                        code: None,
+                                                        breakdown: None,
+                                                    },
+                                                    &tx,
+                                                ).await;
+                                                break;
+                                            }
+                                            if let Some(exceeded) = exceeded {
+                                                failure = Some(exceeded.msg.clone());
+                                                let _ = stream_event(
+                                                    MessageEvent::Error {
+                                                        error: exceeded.msg,
+                                                        code: Some("context_length_exceeded".to_string()),
+                                                        breakdown: exceeded.breakdown,
+                                                    },
+                                                    &tx,
+                                                ).await;
+                                            }
                                         }
-                                    }
-                                    Ok(Some(Ok(AgentEvent::HistoryReplaced(new_messages)))) => {
-                                        // Replace the message history with the compacted messages
-                                        all_messages = new_messages;
-                                        // Note: We don't send this as a stream event since it's an internal operation
-                                        // The client will see the compaction notification message that was sent before this event
-                                    }
-                                    Ok(Some(Ok(AgentEvent::ModelChange { model, mode }))) => {
-                                        if let Err(e) = stream_event(MessageEvent::ModelChange { model, mode }, &tx).await {
-                                            tracing::error!("Error sending model change through channel: {}", e);
-                                            let _ = stream_event(
-                                                MessageEvent::Error {
-                                                    error: e.to_string(),
+                                        Ok(Some(Ok(AgentEvent::TurnTiming(timing)))) => {
+                                            timings.record(timing);
+                                        }
+                                        Ok(Some(Ok(AgentEvent::HistoryReplaced(new_messages)))) => {
+                                            // Replace the message history with the compacted messages
+                                            all_messages = new_messages;
+                                            // Note: We don't send this as a stream event since it's an internal operation
+                                            // The client will see the compaction notification message that was sent before this event
+                                        }
+                                        Ok(Some(Ok(AgentEvent::ModelChange { model, mode }))) => {
+                                            if let Err(e) = session::record_model_change(&session_path, &model, &mode).await {
+                                                tracing::warn!("Failed to record model change: {}", e);
+                                            }
+                                            if let Err(e) = stream_event(MessageEvent::ModelChange { model, mode }, &tx).await {
+                                                tracing::error!("Error sending model change through channel: {}", e);
+                                                let _ = stream_event(
+                                                    MessageEvent::Error {
+                                                        error: e.to_string(),
+                                                        code: None,
+                                                        breakdown: None,
+                                                    },
+                                                    &tx,
+                                                ).await;
+                                            }
+                                        }
+                                        Ok(Some(Ok(AgentEvent::McpNotification((request_id, n))))) => {
+                                            let event = match n {
+                                                ServerNotification::ProgressNotification(progress) => {
+                                                    MessageEvent::Progress {
+                                                        tool_request_id: request_id,
+                                                        progress: progress.params.progress,
+                                                        total: progress.params.total,
+                                                        message: progress.params.message,
+                                                    }
+                                                }
+                                                n => MessageEvent::Notification {
+                                                    request_id,
+                                                    message: n,
                                                 },
-                                                &tx,
-                                            ).await;
+                                            };
+                                            if let Err(e) = stream_event(event, &tx).await {
+                                                tracing::error!("Error sending message through channel: {}", e);
+                                                let _ = stream_event(
+                                                    MessageEvent::Error {
+                                                        error: e.to_string(),
+                                                        code: None,
+                                                        breakdown: None,
+                                                    },
+                                                    &tx,
+                                                ).await;
+                                            }
                                         }
-                                    }
-                                    Ok(Some(Ok(AgentEvent::McpNotification((request_id, n))))) => {
-                                        if let Err(e) = stream_event(MessageEvent::Notification{
-                                            request_id: request_id.clone(),
-                                            message: n,
-                                        }, &tx).await {
-                                            tracing::error!("Error sending message through channel: {}", e);
+
+                                        Ok(Some(Err(e))) => {
+                                            tracing::error!("Error processing message: {}", e);
+                                            if let Some(ProviderError::AuthenticationFailed { provider, .. }) =
+                                                e.downcast_ref::<ProviderError>()
+                                            {
+                                                let config_keys = credential_keys(provider);
+                                                state.publish_event(ServerEvent::AuthRequired {
+                                                    provider: provider.clone(),
+                                                    config_keys: config_keys.clone(),
+                                                    session_id: session_id.clone(),
+                                                });
+                                                let _ = stream_event(
+                                                    MessageEvent::AuthRequired {
+                                                        provider: provider.clone(),
+                                                        config_keys,
+                                                    },
+                                                    &tx,
+                                                ).await;
+                                            }
+                                            failure = Some(e.to_string());
                                             let _ = stream_event(
                                                 MessageEvent::Error {
                                                     error: e.to_string(),
+                                                    code: e
+                                                    breakdown: None,
+                                                        .downcast_ref::<ProviderError>()
+                                                        .map(|e| e.code().to_string()),
                                                 },
                                                 &tx,
                                             ).await;
+                                            break;
                                         }
-                                    }
-
-                                    Ok(Some(Err(e))) => {
-                                        tracing::error!("Error processing message: {}", e);
-                                        let _ = stream_event(
-                                            MessageEvent::Error {
-                                                error: e.to_string(),
-                                            },
-                                            &tx,
-                                        ).await;
-                                        break;
-                                    }
-                                    Ok(None) => {
-                                        break;
-                                    }
-                                    Err(_) => {
-                                        if tx.is_closed() {
+                                        Ok(None) => {
                                             break;
                                         }
-                                        continue;
+                                        Err(_) => {
+                                            if tx.is_closed() {
+                                                break;
+                                            }
+                                            continue;
+                                        }
                                     }
                                 }
                             }
-                        }
+            }
+
+            if let Some(response) = recipe_output
+                .as_ref()
+                .filter(|_| failure.is_none() && !task_cancel.is_cancelled())
+            {
+                schema_validation = response.validate_output(&all_messages);
+                if let Some(validation) = schema_validation
+                    .as_ref()
+                    .filter(|validation| !validation.schema_valid && corrections_left > 0)
+                {
+                    corrections_left -= 1;
+                    let correction = response.correction_message(validation);
+                    push_message(&mut all_messages, correction.clone());
+                    let _ = stream_event(
+                        MessageEvent::Message {
+                            message: correction,
+                        },
+                        &task_tx,
+                    )
+                    .await;
+                    messages_to_process = all_messages.clone();
+                    continue 'reply;
+                }
+            }
+            break;
         }
 
-        if all_messages.len() > saved_message_count {
+        state
+            .rate_limiter
+            .record_tokens(session_tokens(&session_path).saturating_sub(tokens_before));
+
+        let result = if failure.is_some() {
+            SessionResult::Error
+        } else if task_cancel.is_cancelled() || task_tx.is_closed() {
+            SessionResult::Cancelled
+        } else {
+            SessionResult::Success
+        };
+
+        // A compacted history can be shorter than the original but still needs saving
+        if compacted || all_messages.len() > saved_message_count {
             if let Ok(provider) = agent.provider().await {
                 let provider = Arc::clone(&provider);
+                let schema_validation = schema_validation.clone();
+                // Delivered once saved, so the result carries the session's token usage
+                let delivery = delivery.take();
+                let failure = failure.clone();
+                let all_messages = all_messages.clone();
                 tokio::spawn(async move {
-                    if let Err(e) = session::persist_messages(
+                    match session::persist_messages(
                         &session_path,
                         &all_messages,
                         Some(provider),
@@ -290,21 +1214,48 @@ async fn reply_handler(
                     )
                     .await
                     {
-                        tracing::error!("Failed to store session history: {:?}", e);
+                        Ok(()) => {
+                            if let Some(validation) = schema_validation {
+                                if let Err(e) =
+                                    session::record_schema_validation(&session_path, validation)
+                                        .await
+                                {
+                                    tracing::error!("Failed to record schema validation: {:?}", e);
+                                }
+                            }
+                        }
+                        Err(e) => tracing::error!("Failed to store session history: {:?}", e),
+                    }
+                    if let Some(delivery) = delivery {
+                        delivery.send(result, failure, all_messages);
                     }
                 });
             }
         }
+        if let Some(delivery) = delivery {
+            delivery.send(result, failure, all_messages);
+        }
 
-        let _ = stream_event(
-            MessageEvent::Finish {
-                reason: "stop".to_string(),
-            },
-            &task_tx,
-        )
-        .await;
+        let finish = MessageEvent::Finish {
+            reason: "stop".to_string(),
+            timing: (!timings.is_empty()).then(|| timings.summary()),
+            schema_validation,
+        };
+        if let Some(claim) = claim {
+            claim.finish(format_event(&finish));
+        }
+        let _ = stream_event(finish, &task_tx).await;
+
+        let dropped = task_tx.dropped.load(Ordering::Relaxed);
+        if dropped > 0 {
+            tracing::info!(
+                session_id = %session_id,
+                dropped,
+                "Dropped notifications the client was too slow to read"
+            );
+        }
     }));
-    Ok(SseResponse::new(stream))
+    SseResponse::closing_with(stream, close_reason)
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
@@ -313,12 +1264,19 @@ pub struct PermissionConfirmationRequest {
     #[serde(default = "default_principal_type")]
     principal_type: PrincipalType,
     action: String,
+    /// Store `always_allow` and `always_deny` decisions so they survive restarts
+    #[serde(default = "default_persist")]
+    persist: bool,
 }
 
 fn default_principal_type() -> PrincipalType {
     PrincipalType::Tool
 }
 
+fn default_persist() -> bool {
+    true
+}
+
 #[utoipa::path(
     post,
     path = "/confirm",
@@ -331,33 +1289,212 @@ fn default_principal_type() -> PrincipalType {
 )]
 pub async fn confirm_permission(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     Json(request): Json<PermissionConfirmationRequest>,
 ) -> Result<Json<Value>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let agent = state
         .get_agent()
         .await
         .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
 
-    let permission = match request.action.as_str() {
+    let pending = agent
+        .pending_confirmations()
+        .into_iter()
+        .find(|pending| pending.request_id == request.id);
+    answer_confirmation(
+        &agent,
+        &request.id,
+        pending.as_ref(),
+        PermissionConfirmation {
+            principal_type: request.principal_type,
+            permission: permission_for_action(&request.action),
+            persist: request.persist,
+        },
+    )
+    .await;
+    Ok(Json(Value::Object(serde_json::Map::new())))
+}
+
+/// The permission a confirmation action grants. Unknown actions deny.
+fn permission_for_action(action: &str) -> Permission {
+    match action {
         "always_allow" => Permission::AlwaysAllow,
         "allow_once" => Permission::AllowOnce,
+        "always_deny" => Permission::AlwaysDeny,
         "deny" => Permission::DenyOnce,
         _ => Permission::DenyOnce,
-    };
+    }
+}
 
+/// Record the answer to a confirmation in the audit log and pass it to the agent
+async fn answer_confirmation(
+    agent: &Agent,
+    request_id: &str,
+    pending: Option<&PendingConfirmation>,
+    confirmation: PermissionConfirmation,
+) {
+    let decision = match confirmation.permission {
+        Permission::AlwaysAllow | Permission::AllowOnce => AuditDecision::Allowed,
+        _ => AuditDecision::Denied,
+    };
+    agent.audit_log().record_confirmation(
+        pending.and_then(|p| p.session_id.clone()),
+        request_id,
+        pending.map(|p| p.tool_name.as_str()),
+        decision,
+    );
     agent
-        .handle_confirmation(
-            request.id.clone(),
-            PermissionConfirmation {
-                principal_type: request.principal_type,
-                permission,
-            },
-        )
+        .handle_confirmation(request_id.to_string(), confirmation)
         .await;
-    Ok(Json(Value::Object(serde_json::Map::new())))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ToolApprovalBatchRequest {
+    /// Answers to apply, in order
+    confirmations: Vec<ToolApprovalEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ToolApprovalEntry {
+    id: String,
+    #[serde(default = "default_principal_type")]
+    principal_type: PrincipalType,
+    action: String,
+    /// Store `always_allow` and `always_deny` decisions so they survive restarts
+    #[serde(default = "default_persist")]
+    persist: bool,
+    /// Give the same answer to every other confirmation pending for the same tool in the turn
+    #[serde(default)]
+    apply_to_remaining_in_turn: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolApprovalStatus {
+    /// The answer was passed to the agent
+    Applied,
+    /// No confirmation with this id was asked for
+    UnknownId,
+    /// The confirmation was already answered, earlier in the batch or before it, or timed out
+    AlreadyResolved,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ToolApprovalResult {
+    id: String,
+    status: ToolApprovalStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ToolApprovalBatchResponse {
+    /// One result per confirmation the batch touched, in the order they were applied
+    results: Vec<ToolApprovalResult>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/reply/tool_approval_batch",
+    request_body = ToolApprovalBatchRequest,
+    responses(
+        (status = 200, description = "Answers applied, with the outcome for each id", body = ToolApprovalBatchResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Precondition failed - Agent not available")
+    )
+)]
+pub async fn confirm_permission_batch(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Json(request): Json<ToolApprovalBatchRequest>,
+) -> Result<Json<ToolApprovalBatchResponse>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+
+    let pending = agent.pending_confirmations();
+    let mut answered = HashSet::new();
+    let mut results = Vec::new();
+    for entry in request.confirmations {
+        let Some(target) = pending.iter().find(|p| p.request_id == entry.id) else {
+            let status = if agent.is_confirmation_resolved(&entry.id) {
+                ToolApprovalStatus::AlreadyResolved
+            } else {
+                ToolApprovalStatus::UnknownId
+            };
+            results.push(ToolApprovalResult {
+                id: entry.id,
+                status,
+            });
+            continue;
+        };
+
+        let mut targets = vec![target];
+        if entry.apply_to_remaining_in_turn {
+            targets.extend(pending.iter().filter(|p| {
+                p.request_id != target.request_id
+                    && p.tool_name == target.tool_name
+                    && p.session_id == target.session_id
+            }));
+        }
+        for waiting in targets {
+            if !answered.insert(waiting.request_id.clone()) {
+                // Only the id the entry names is reported again, the rest were answered by
+                // an earlier entry on purpose
+                if waiting.request_id == entry.id {
+                    results.push(ToolApprovalResult {
+                        id: entry.id.clone(),
+                        status: ToolApprovalStatus::AlreadyResolved,
+                    });
+                }
+                continue;
+            }
+            answer_confirmation(
+                &agent,
+                &waiting.request_id,
+                Some(waiting),
+                PermissionConfirmation {
+                    principal_type: entry.principal_type.clone(),
+                    permission: permission_for_action(&entry.action),
+                    persist: entry.persist,
+                },
+            )
+            .await;
+            results.push(ToolApprovalResult {
+                id: waiting.request_id.clone(),
+                status: ToolApprovalStatus::Applied,
+            });
+        }
+    }
+
+    Ok(Json(ToolApprovalBatchResponse { results }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingConfirmationsResponse {
+    confirmations: Vec<PendingConfirmation>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/reply/pending_confirmations",
+    responses(
+        (status = 200, description = "Tool confirmations waiting for an answer, oldest first", body = PendingConfirmationsResponse),
+        (status = 401, description = "Unauthorized - invalid secret key"),
+        (status = 412, description = "Precondition failed - Agent not available")
+    )
+)]
+pub async fn get_pending_confirmations(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+) -> Result<Json<PendingConfirmationsResponse>, StatusCode> {
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED)?;
+
+    Ok(Json(PendingConfirmationsResponse {
+        confirmations: agent.pending_confirmations(),
+    }))
 }
 
 #[derive(Debug, Deserialize)]
@@ -368,11 +1505,9 @@ struct ToolResultRequest {
 
 async fn submit_tool_result(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Chat>,
     raw: Json<Value>,
 ) -> Result<Json<Value>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     tracing::info!(
         "Received tool result request: {}",
         serde_json::to_string_pretty(&raw.0).unwrap()
@@ -402,9 +1537,16 @@ pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route(
             "/reply",
-            post(reply_handler).layer(DefaultBodyLimit::max(50 * 1024 * 1024)),
+            post(reply_handler).layer(DefaultBodyLimit::max(max_image_body_size())),
+        )
+        .route("/reply/stream/{session_id}", get(reconnect_handler))
+        .route("/agent/prompts/{name}", post(prompt_reply_handler))
+        .route(
+            "/reply/pending_confirmations",
+            get(get_pending_confirmations),
         )
         .route("/confirm", post(confirm_permission))
+        .route("/reply/tool_approval_batch", post(confirm_permission_batch))
         .route(
             "/tool_result",
             post(submit_tool_result).layer(DefaultBodyLimit::max(10 * 1024 * 1024)),
@@ -452,14 +1594,374 @@ mod tests {
         }
     }
 
+    /// Asks for three tool calls at once, two of them to the same tool, then answers in text
+    #[derive(Clone)]
+    struct ParallelToolProvider {
+        model_config: ModelConfig,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for ParallelToolProvider {
+        fn metadata() -> goose::providers::base::ProviderMetadata {
+            goose::providers::base::ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            let message = if messages.last().is_some_and(|m| m.is_tool_response()) {
+                Message::assistant().with_text("Done")
+            } else {
+                [
+                    ("call_1", "notes__append"),
+                    ("call_2", "notes__append"),
+                    ("call_3", "notes__delete"),
+                ]
+                .into_iter()
+                .fold(Message::assistant(), |message, (id, tool)| {
+                    message
+                        .with_tool_request(id, Ok(mcp_core::tool::ToolCall::new(tool, json!({}))))
+                })
+            };
+            Ok((
+                message,
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+    }
+
+    /// Rejects its credentials the way an expired Databricks token is rejected
+    #[derive(Clone)]
+    struct ExpiredCredentialsProvider {
+        model_config: ModelConfig,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for ExpiredCredentialsProvider {
+        fn metadata() -> goose::providers::base::ProviderMetadata {
+            goose::providers::base::ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            Err(ProviderError::AuthenticationFailed {
+                provider: "databricks".to_string(),
+                message: "Invalid access token. (status 401)".to_string(),
+            })
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+    }
+
+    /// Rejects the first request as too long for the context window, then answers in text, and
+    /// keeps the messages of every request it gets
+    #[derive(Clone)]
+    struct ContextLimitProvider {
+        model_config: ModelConfig,
+        requests: Arc<std::sync::Mutex<Vec<Vec<Message>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for ContextLimitProvider {
+        fn metadata() -> goose::providers::base::ProviderMetadata {
+            goose::providers::base::ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            let first = {
+                let mut requests = self.requests.lock().unwrap();
+                requests.push(messages.to_vec());
+                requests.len() == 1
+            };
+            if first {
+                return Err(ProviderError::ContextLengthExceeded(
+                    "prompt is too long: 210000 tokens > 200000 maximum".to_string(),
+                ));
+            }
+            Ok((
+                Message::assistant().with_text("Mock response"),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+    }
+
+    /// Takes a while to answer and logs when each completion starts and ends
+    #[derive(Clone)]
+    struct SlowProvider {
+        model_config: ModelConfig,
+        calls: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for SlowProvider {
+        fn metadata() -> goose::providers::base::ProviderMetadata {
+            goose::providers::base::ProviderMetadata::empty()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[rmcp::model::Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            self.calls.lock().unwrap().push("start");
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            self.calls.lock().unwrap().push("end");
+            Ok((
+                Message::assistant().with_text("Slow response"),
+                ProviderUsage::new("mock".to_string(), Usage::default()),
+            ))
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model_config.clone()
+        }
+    }
+
+    fn sender(capacity: usize, stall_timeout: Duration) -> (EventSender, mpsc::Receiver<String>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        let sender = EventSender {
+            replay: Arc::new(ReplayBuffer::new(16, 1, tx)),
+            reconnect_window: Duration::from_secs(60),
+            stall_timeout,
+            dropped: Arc::default(),
+            dropped_total: Arc::default(),
+            close_reason: Arc::new(OnceLock::new()),
+            cancel: CancellationToken::new(),
+        };
+        (sender, rx)
+    }
+
+    fn progress() -> MessageEvent {
+        MessageEvent::Progress {
+            tool_request_id: "tool".to_string(),
+            progress: 1,
+            total: None,
+            message: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_stream_drops_notifications() {
+        let (tx, mut rx) = sender(1, Duration::from_secs(5));
+        stream_event(progress(), &tx).await.unwrap();
+        stream_event(progress(), &tx).await.unwrap();
+
+        assert_eq!(tx.dropped.load(Ordering::Relaxed), 1);
+        assert_eq!(tx.dropped_total.load(Ordering::Relaxed), 1);
+        assert!(rx.recv().await.unwrap().contains("Progress"));
+        assert!(!tx.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_stream_closes_with_error() {
+        let (tx, rx) = sender(1, Duration::from_millis(10));
+        let message = || MessageEvent::Message {
+            message: Message::assistant().with_text("hi"),
+        };
+        stream_event(message(), &tx).await.unwrap();
+
+        let result = stream_event(message(), &tx).await;
+        assert!(matches!(result, Err(SendTimeoutError::Timeout(_))));
+        assert!(tx.is_closed());
+        assert!(tx.cancel.is_cancelled());
+        // Later events fail straight away instead of waiting again
+        assert!(matches!(
+            stream_event(message(), &tx).await,
+            Err(SendTimeoutError::Closed(_))
+        ));
+
+        let close_reason = Arc::clone(&tx.close_reason);
+        drop(tx);
+        let events: Vec<_> = SseResponse::closing_with(ReceiverStream::new(rx), close_reason)
+            .map(|event| String::from_utf8(event.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains("\"type\":\"Message\""));
+        assert!(events[1].contains("stopped reading"));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_replays_missed_events() {
+        let (tx, mut rx) = sender(16, Duration::from_secs(5));
+        let message = |text: &str| MessageEvent::Message {
+            message: Message::assistant().with_text(text),
+        };
+        for i in 1..=3 {
+            stream_event(message(&format!("event {}", i)), &tx)
+                .await
+                .unwrap();
+            assert!(rx
+                .recv()
+                .await
+                .unwrap()
+                .starts_with(&format!("id: {}\n", i)));
+        }
+
+        // The connection drops after event 3 and the reply carries on without it
+        drop(rx);
+        stream_event(message("event 4"), &tx).await.unwrap();
+        stream_event(message("event 5"), &tx).await.unwrap();
+        assert!(!tx.cancel.is_cancelled());
+
+        let mut reconnected = tx.replay.reconnect(3, "resync");
+        let fourth = reconnected.recv().await.unwrap();
+        assert!(fourth.starts_with("id: 4\n") && fourth.contains("event 4"));
+        let fifth = reconnected.recv().await.unwrap();
+        assert!(fifth.starts_with("id: 5\n") && fifth.contains("event 5"));
+
+        stream_event(message("event 6"), &tx).await.unwrap();
+        let sixth = reconnected.recv().await.unwrap();
+        assert!(sixth.starts_with("id: 6\n") && sixth.contains("event 6"));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_past_the_buffer_resyncs() {
+        let (client, rx) = mpsc::channel(16);
+        let replay = ReplayBuffer::new(2, 1, client);
+        drop(rx);
+        for i in 1..=5 {
+            replay.record(&format!("data: {}\n\n", i));
+        }
+
+        // Events 2 and 3 are no longer kept
+        let mut reconnected = replay.reconnect(1, "resync");
+        assert_eq!(reconnected.recv().await.unwrap(), "resync");
+        let (event, client) = replay.record("data: 6\n\n");
+        assert_eq!(event, "id: 6\ndata: 6\n\n");
+        client.unwrap().send(event).await.unwrap();
+        assert_eq!(reconnected.recv().await.unwrap(), "id: 6\ndata: 6\n\n");
+
+        // Once the reply has finished, a reconnect gets what it missed and the stream ends
+        replay.finish();
+        let mut late = replay.reconnect(4, "resync");
+        assert_eq!(late.recv().await.unwrap(), "id: 5\ndata: 5\n\n");
+        assert_eq!(late.recv().await.unwrap(), "id: 6\ndata: 6\n\n");
+        assert!(late.recv().await.is_none());
+    }
+
+    #[test]
+    fn test_working_dir_must_be_an_existing_absolute_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+
+        assert!(validate_working_dir("", None).is_err());
+        assert!(validate_working_dir("relative/dir", None).is_err());
+        assert!(validate_working_dir(dir.path().join("missing").to_str().unwrap(), None).is_err());
+        assert!(validate_working_dir(file.to_str().unwrap(), None).is_err());
+        assert_eq!(
+            validate_working_dir(dir.path().to_str().unwrap(), None).unwrap(),
+            dir.path().canonicalize().unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_working_dir_symlink_cannot_escape_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        let inside = root.join("project");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&inside).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+        std::os::unix::fs::symlink(&inside, dir.path().join("shortcut")).unwrap();
+        let roots = vec![root.canonicalize().unwrap()];
+        let roots = Some(roots.as_slice());
+
+        assert!(validate_working_dir(root.join("escape").to_str().unwrap(), roots).is_err());
+        assert!(validate_working_dir(outside.to_str().unwrap(), roots).is_err());
+        // A link from outside the roots to a directory inside them resolves to that directory
+        assert_eq!(
+            validate_working_dir(dir.path().join("shortcut").to_str().unwrap(), roots).unwrap(),
+            inside.canonicalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unusable_roots_allow_no_working_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing").to_string_lossy().into_owned();
+
+        assert_eq!(canonical_roots(&[]), None);
+        assert_eq!(canonical_roots(&[" ".to_string()]), None);
+        // A misconfigured sandbox refuses everything instead of allowing everything
+        let roots = canonical_roots(&[missing]).unwrap();
+        assert!(roots.is_empty());
+        assert!(
+            validate_working_dir(dir.path().to_str().unwrap(), Some(roots.as_slice())).is_err()
+        );
+    }
+
+    fn recipe_request(recipe_name: Option<&str>, allow_extra_extensions: bool) -> ChatRequest {
+        ChatRequest {
+            messages: Vec::new(),
+            session_id: None,
+            session_working_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+            scheduled_job_id: None,
+            recipe_name: recipe_name.map(str::to_string),
+            allow_extra_extensions,
+            execution_mode: ReplyExecutionMode::default(),
+            persona: None,
+            callback_url: None,
+            callback_secret: None,
+        }
+    }
+
+    #[test]
+    fn test_recipe_allowed_extensions() {
+        assert_eq!(
+            recipe_allowed_extensions(&recipe_request(None, false)).unwrap(),
+            None
+        );
+        // The override skips loading the recipe entirely
+        assert_eq!(
+            recipe_allowed_extensions(&recipe_request(Some("no-such-recipe-7f3a"), true)).unwrap(),
+            None
+        );
+        let response =
+            recipe_allowed_extensions(&recipe_request(Some("no-such-recipe-7f3a"), false))
+                .unwrap_err();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
     mod integration_tests {
         use super::*;
+        use crate::routes::limits::Limits;
+        use crate::routes::utils::use_test_session_dir;
+        use crate::state::ReplyQueue;
         use axum::{body::Body, http::Request};
+        use goose::audit::AuditLog;
         use std::sync::Arc;
         use tower::ServiceExt;
 
         #[tokio::test]
         async fn test_reply_endpoint() {
+            use_test_session_dir();
             let mock_model_config = ModelConfig::new("test-model").unwrap();
             let mock_provider = Arc::new(MockProvider {
                 model_config: mock_model_config,
@@ -479,8 +1981,136 @@ mod tests {
                     serde_json::to_string(&ChatRequest {
                         messages: vec![Message::user().with_text("test message")],
                         session_id: Some("test-session".to_string()),
+                        session_working_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+                        scheduled_job_id: None,
+                        recipe_name: None,
+                        allow_extra_extensions: false,
+                        execution_mode: ReplyExecutionMode::default(),
+                        persona: None,
+                        callback_url: None,
+                        callback_secret: None,
+                    })
+                    .unwrap(),
+                ))
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        fn idempotent_reply_request(key: &str) -> Request<Body> {
+            Request::builder()
+                .uri("/reply")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .header(IDEMPOTENCY_KEY_HEADER, key)
+                .body(Body::from(
+                    serde_json::to_string(&ChatRequest {
+                        messages: vec![Message::user().with_text("test message")],
+                        session_id: Some("retried-session".to_string()),
+                        session_working_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+                        scheduled_job_id: None,
+                        recipe_name: None,
+                        allow_extra_extensions: false,
+                        execution_mode: ReplyExecutionMode::default(),
+                        persona: None,
+                        callback_url: None,
+                        callback_secret: None,
+                    })
+                    .unwrap(),
+                ))
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn test_reply_duplicate_while_running_conflicts() {
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            let _running = state
+                .idempotency_keys
+                .claim("retry-1", "original-session")
+                .unwrap();
+            let app = routes(state);
+
+            let response = app
+                .oneshot(idempotent_reply_request("retry-1"))
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::CONFLICT);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body, json!({"session_id": "original-session"}));
+        }
+
+        #[tokio::test]
+        async fn test_reply_replays_finish_after_completion() {
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            let finish_event = format_event(&MessageEvent::Finish {
+                reason: "stop".to_string(),
+                timing: None,
+                schema_validation: None,
+            });
+            state
+                .idempotency_keys
+                .claim("retry-2", "original-session")
+                .unwrap()
+                .finish(finish_event.clone());
+            let app = routes(state);
+
+            let response = app
+                .oneshot(idempotent_reply_request("retry-2"))
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, finish_event.as_bytes());
+        }
+
+        #[tokio::test]
+        async fn test_abandoned_claim_frees_the_key() {
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            let claim = state.idempotency_keys.claim("retry-3", "first").unwrap();
+            assert_eq!(
+                state.idempotency_keys.claim("retry-3", "second").err(),
+                Some(IdempotentOutcome::Running {
+                    session_id: "first".to_string()
+                })
+            );
+
+            drop(claim);
+            assert!(state.idempotency_keys.claim("retry-3", "second").is_ok());
+        }
+
+        #[tokio::test]
+        async fn test_prompt_reply_unknown_prompt() {
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            let app = routes(state);
+
+            let request = Request::builder()
+                .uri("/agent/prompts/missing")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .body(Body::from(
+                    serde_json::to_string(&PromptReplyRequest {
+                        arguments: HashMap::new(),
+                        messages: vec![],
+                        session_id: Some("test-session".to_string()),
                         session_working_dir: "test-working-dir".to_string(),
                         scheduled_job_id: None,
+                        recipe_name: None,
+                        allow_extra_extensions: false,
+                        execution_mode: ReplyExecutionMode::default(),
+                        persona: None,
+                        callback_url: None,
+                        callback_secret: None,
                     })
                     .unwrap(),
                 ))
@@ -488,7 +2118,741 @@ mod tests {
 
             let response = app.oneshot(request).await.unwrap();
 
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        #[tokio::test]
+        async fn test_reconnect_endpoint_resumes_after_last_event_id() {
+            use_test_session_dir();
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            let (client, _rx) = mpsc::channel(16);
+            let replay = state.start_replay("reconnect-session", client);
+            for i in 1..=3 {
+                replay.buffer().record(&format!("data: {}\n\n", i));
+            }
+            drop(replay);
+            let app = routes(state);
+
+            let missing = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/reply/stream/other-session")
+                        .header("x-secret-key", "test-secret")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/reply/stream/reconnect-session")
+                        .header("x-secret-key", "test-secret")
+                        .header(LAST_EVENT_ID_HEADER, "2")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
             assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(&body[..], b"id: 3\ndata: 3\n\n");
+        }
+
+        async fn mock_agent_state() -> Arc<AppState> {
+            let agent = Agent::new();
+            let _ = agent
+                .update_provider(Arc::new(MockProvider {
+                    model_config: ModelConfig::new("test-model").unwrap(),
+                }))
+                .await;
+            AppState::new(Arc::new(agent), "test-secret".to_string()).await
+        }
+
+        fn chat_request(session_id: &str, messages: Vec<Message>) -> Request<Body> {
+            chat_request_in_mode(session_id, messages, ReplyExecutionMode::default())
+        }
+
+        fn chat_request_in_mode(
+            session_id: &str,
+            messages: Vec<Message>,
+            execution_mode: ReplyExecutionMode,
+        ) -> Request<Body> {
+            Request::builder()
+                .uri("/reply")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-secret-key", "test-secret")
+                .body(Body::from(
+                    serde_json::to_string(&ChatRequest {
+                        messages,
+                        session_id: Some(session_id.to_string()),
+                        session_working_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+                        scheduled_job_id: None,
+                        recipe_name: None,
+                        allow_extra_extensions: false,
+                        execution_mode,
+                        persona: None,
+                        callback_url: None,
+                        callback_secret: None,
+                    })
+                    .unwrap(),
+                ))
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn test_cli_session_continues_through_reply() {
+            use_test_session_dir();
+            let session_id = "test-cli-resumed-session";
+            let session_path =
+                session::get_path(session::Identifier::Name(session_id.to_string())).unwrap();
+            // Saved the way the CLI saves it, stopped partway through a tool call
+            session::persist_messages_with_schedule_id(
+                &session_path,
+                &[
+                    Message::user().with_text("List the files"),
+                    Message::assistant().with_tool_request(
+                        "call_1",
+                        Ok(mcp_core::tool::ToolCall::new(
+                            "developer__shell",
+                            json!({"command": "ls"}),
+                        )),
+                    ),
+                ],
+                None,
+                None,
+                Some(std::env::temp_dir()),
+            )
+            .await
+            .unwrap();
+
+            let state = mock_agent_state().await;
+            let history = crate::routes::session::routes(state.clone())
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/sessions/{}", session_id))
+                        .header("x-secret-key", "test-secret")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(history.status(), StatusCode::OK);
+            let history: Value = serde_json::from_slice(
+                &axum::body::to_bytes(history.into_body(), usize::MAX)
+                    .await
+                    .unwrap(),
+            )
+            .unwrap();
+            let mut messages: Vec<Message> =
+                serde_json::from_value(history["messages"].clone()).unwrap();
+            assert_eq!(messages.len(), 3);
+
+            messages.push(Message::user().with_text("Go on"));
+            let response = routes(state)
+                .oneshot(chat_request(session_id, messages))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            timeout(
+                Duration::from_secs(30),
+                axum::body::to_bytes(response.into_body(), usize::MAX),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+            let (metadata, saved) = session::load_session(&session_path).unwrap();
+            let roles: Vec<Role> = saved.iter().map(|m| m.role.clone()).collect();
+            assert_eq!(
+                roles,
+                [
+                    Role::User,
+                    Role::Assistant,
+                    Role::User,
+                    Role::User,
+                    Role::Assistant
+                ]
+            );
+            assert_eq!(saved[2].content[0].as_tool_response().unwrap().id, "call_1");
+            assert_eq!(saved[3].as_concat_text(), "Go on");
+            assert_eq!(saved[4].as_concat_text(), "Mock response");
+            assert_eq!(metadata.working_dir, std::env::temp_dir());
+            assert_eq!(metadata.message_count, 5);
+        }
+
+        #[tokio::test]
+        async fn test_serial_sessions_queue_replies() {
+            use_test_session_dir();
+            let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let agent = Agent::new();
+            let _ = agent
+                .update_provider(Arc::new(SlowProvider {
+                    model_config: ModelConfig::new("test-model").unwrap(),
+                    calls: Arc::clone(&calls),
+                }))
+                .await;
+            let mut state =
+                (*AppState::new(Arc::new(agent), "test-secret".to_string()).await).clone();
+            state.reply_queue = Arc::new(ReplyQueue::new(true, 4, Duration::from_secs(30)));
+            let app = routes(Arc::new(state));
+
+            let first = app
+                .clone()
+                .oneshot(chat_request(
+                    "test-serial-first",
+                    vec![Message::user().with_text("First")],
+                ))
+                .await
+                .unwrap();
+            let second = app
+                .oneshot(chat_request(
+                    "test-serial-second",
+                    vec![Message::user().with_text("Second")],
+                ))
+                .await
+                .unwrap();
+            assert_eq!(first.status(), StatusCode::OK);
+            assert_eq!(second.status(), StatusCode::OK);
+
+            let (first, second) = tokio::join!(
+                axum::body::to_bytes(first.into_body(), usize::MAX),
+                axum::body::to_bytes(second.into_body(), usize::MAX),
+            );
+            let first = String::from_utf8(first.unwrap().to_vec()).unwrap();
+            let second = String::from_utf8(second.unwrap().to_vec()).unwrap();
+
+            assert!(!first.contains(r#""type":"Queued""#));
+            assert!(second.contains(r#""type":"Queued","position":1"#));
+            assert!(
+                second.find(r#""type":"Queued""#).unwrap() < second.find("Slow response").unwrap()
+            );
+            assert!(first.contains(r#""reason":"stop""#));
+            assert!(second.contains(r#""reason":"stop""#));
+            // The second reply only reached the provider once the first was done with it
+            assert_eq!(*calls.lock().unwrap(), ["start", "end", "start", "end"]);
+        }
+
+        #[tokio::test]
+        async fn test_queued_reply_times_out() {
+            use_test_session_dir();
+            let mut state = (*mock_agent_state().await).clone();
+            state.reply_queue = Arc::new(ReplyQueue::new(true, 1, Duration::from_millis(100)));
+            let state = Arc::new(state);
+            let _busy = state.track_stream("test-queue-busy", None);
+
+            let response = routes(Arc::clone(&state))
+                .oneshot(chat_request(
+                    "test-queue-timeout",
+                    vec![Message::user().with_text("Anyone there?")],
+                ))
+                .await
+                .unwrap();
+            // The queue only holds one reply, and this one is in it
+            let full = routes(Arc::clone(&state))
+                .oneshot(chat_request(
+                    "test-queue-full",
+                    vec![Message::user().with_text("Me too")],
+                ))
+                .await
+                .unwrap();
+            assert_eq!(full.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let events = String::from_utf8(body.to_vec()).unwrap();
+            assert!(events.contains(r#""type":"Queued","position":1"#));
+            assert!(events.contains(r#""reason":"queue_timeout""#));
+            assert!(!events.contains("Mock response"));
+        }
+
+        #[tokio::test]
+        async fn test_spent_budget_turns_new_sessions_away() {
+            use_test_session_dir();
+            let state = mock_agent_state().await;
+            state.rate_limiter.set_limits(Limits {
+                daily_token_budget: Some(1_000),
+                ..Limits::default()
+            });
+            state.rate_limiter.record_tokens(1_000);
+
+            let response = routes(Arc::clone(&state))
+                .oneshot(chat_request(
+                    "test-budget-new-session",
+                    vec![Message::user().with_text("Hello")],
+                ))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["used"], 1_000);
+            // Turned away before a reply task was started
+            assert!(state.active_streams().is_empty());
+            assert!(state.replay_buffer("test-budget-new-session").is_none());
+        }
+
+        #[tokio::test]
+        async fn test_reply_rejects_unknown_persona() {
+            use_test_session_dir();
+            let request = ChatRequest {
+                messages: vec![Message::user().with_text("Review this")],
+                session_id: Some("test-unknown-persona-session".to_string()),
+                session_working_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+                scheduled_job_id: None,
+                recipe_name: None,
+                allow_extra_extensions: false,
+                execution_mode: ReplyExecutionMode::default(),
+                persona: Some("no-such-persona-3b9d".to_string()),
+                callback_url: None,
+                callback_secret: None,
+            };
+
+            let response = routes(mock_agent_state().await)
+                .oneshot(
+                    Request::builder()
+                        .uri("/reply")
+                        .method("POST")
+                        .header("content-type", "application/json")
+                        .header("x-secret-key", "test-secret")
+                        .body(Body::from(serde_json::to_string(&request).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, "Unknown persona 'no-such-persona-3b9d'");
+        }
+
+        #[tokio::test]
+        async fn test_reply_rejects_metadata_callback_url() {
+            use_test_session_dir();
+            let request = ChatRequest {
+                messages: vec![Message::user().with_text("Run the checks")],
+                session_id: Some("test-metadata-callback-session".to_string()),
+                session_working_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+                scheduled_job_id: None,
+                recipe_name: None,
+                allow_extra_extensions: false,
+                execution_mode: ReplyExecutionMode::default(),
+                persona: None,
+                callback_url: Some("http://169.254.169.254/latest/meta-data/".to_string()),
+                callback_secret: None,
+            };
+
+            let response = routes(mock_agent_state().await)
+                .oneshot(
+                    Request::builder()
+                        .uri("/reply")
+                        .method("POST")
+                        .header("content-type", "application/json")
+                        .header("x-secret-key", "test-secret")
+                        .body(Body::from(serde_json::to_string(&request).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        #[tokio::test]
+        async fn test_reply_refuses_newer_session_format() {
+            use_test_session_dir();
+            let session_id = "test-newer-format-session";
+            let session_path =
+                session::get_path(session::Identifier::Name(session_id.to_string())).unwrap();
+            let metadata = session::SessionMetadata {
+                format_version: session::storage::SESSION_FORMAT_VERSION + 1,
+                ..Default::default()
+            };
+            session::storage::save_messages_with_metadata(
+                &session_path,
+                &metadata,
+                &[Message::user().with_text("From the future")],
+            )
+            .unwrap();
+
+            let response = routes(mock_agent_state().await)
+                .oneshot(chat_request(
+                    session_id,
+                    vec![Message::user().with_text("Continue")],
+                ))
+                .await
+                .unwrap();
+            let unchanged = session::read_messages(&session_path).unwrap();
+
+            assert_eq!(response.status(), StatusCode::CONFLICT);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert!(body["reason"].as_str().unwrap().contains("newer goose"));
+            assert_eq!(unchanged.len(), 1);
+        }
+
+        #[tokio::test]
+        async fn test_plan_mode_streams_plan_then_follow_up_runs() {
+            use_test_session_dir();
+            let session_id = "test-plan-mode-session";
+            let session_path =
+                session::get_path(session::Identifier::Name(session_id.to_string())).unwrap();
+            let state = mock_agent_state().await;
+            let read_events = |response: Response| async move {
+                let body = timeout(
+                    Duration::from_secs(30),
+                    axum::body::to_bytes(response.into_body(), usize::MAX),
+                )
+                .await
+                .unwrap()
+                .unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            };
+
+            let mut messages = vec![Message::user().with_text("Refactor the parser")];
+            let response = routes(state.clone())
+                .oneshot(chat_request_in_mode(
+                    session_id,
+                    messages.clone(),
+                    ReplyExecutionMode::Plan,
+                ))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let events = read_events(response).await;
+            assert!(events.contains(r#""type":"Plan""#));
+            assert!(!events.contains(r#""type":"Message""#));
+            assert!(events.contains("Mock response"));
+
+            messages.push(Message::assistant().with_text("Mock response"));
+            messages.push(Message::user().with_text("Go ahead"));
+            let response = routes(state)
+                .oneshot(chat_request_in_mode(
+                    session_id,
+                    messages,
+                    ReplyExecutionMode::ApproveEachStep,
+                ))
+                .await
+                .unwrap();
+            let events = read_events(response).await;
+            assert!(events.contains(r#""type":"Message""#));
+            assert!(!events.contains(r#""type":"Plan""#));
+        }
+
+        #[tokio::test]
+        async fn test_recipe_output_is_checked_against_its_schema() {
+            use_test_session_dir();
+            let recipe_dir = tempfile::tempdir().unwrap();
+            std::fs::write(
+                recipe_dir.path().join("status-report.yaml"),
+                r#"
+version: 1.0.0
+title: Status report
+description: Reports status as JSON
+instructions: Reply with the status as JSON
+response:
+  json_schema:
+    type: object
+    properties:
+      status:
+        type: string
+    required: [status]
+  max_validation_retries: 1
+"#,
+            )
+            .unwrap();
+            std::env::set_var("GOOSE_RECIPE_PATH", recipe_dir.path());
+
+            let session_id = "test-recipe-output-schema";
+            let session_path =
+                session::get_path(session::Identifier::Name(session_id.to_string())).unwrap();
+            let mut request = ChatRequest {
+                messages: vec![Message::user().with_text("How are things?")],
+                session_id: Some(session_id.to_string()),
+                session_working_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+                scheduled_job_id: None,
+                recipe_name: Some("status-report".to_string()),
+                allow_extra_extensions: false,
+                execution_mode: ReplyExecutionMode::default(),
+                persona: None,
+                callback_url: None,
+                callback_secret: None,
+            };
+            let response = recipe_response(&request).unwrap();
+            std::env::remove_var("GOOSE_RECIPE_PATH");
+            assert_eq!(response.as_ref().unwrap().max_validation_retries(), 1);
+
+            // The mock only ever answers in prose, so the correction doesn't help
+            let state = mock_agent_state().await;
+            check_working_dir(&mut request).unwrap();
+            let sse = start_reply(state, request, Vec::new(), None, response, None, None, None);
+            let body = timeout(
+                Duration::from_secs(30),
+                axum::body::to_bytes(sse.into_response().into_body(), usize::MAX),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            let events = String::from_utf8(body.to_vec()).unwrap();
+
+            assert_eq!(
+                events
+                    .matches("Your final output does not match the required JSON schema")
+                    .count(),
+                1
+            );
+            assert!(events.contains(r#""schema_valid":false"#));
+            assert!(events.contains("The final output is not JSON"));
+
+            // The session is saved in the background after the stream ends
+            let mut schema_validation = None;
+            for _ in 0..50 {
+                schema_validation = session::read_metadata(&session_path)
+                    .ok()
+                    .and_then(|metadata| metadata.schema_validation);
+                if schema_validation.is_some() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            assert_eq!(
+                schema_validation.map(|validation| validation.schema_valid),
+                Some(false)
+            );
+        }
+
+        #[tokio::test]
+        async fn test_rejected_credentials_ask_for_reauth() {
+            use_test_session_dir();
+            let session_id = "test-rejected-credentials";
+            let session_path =
+                session::get_path(session::Identifier::Name(session_id.to_string())).unwrap();
+            let agent = Agent::new();
+            let _ = agent
+                .update_provider(Arc::new(ExpiredCredentialsProvider {
+                    model_config: ModelConfig::new("test-model").unwrap(),
+                }))
+                .await;
+            let state = AppState::new(Arc::new(agent), "test-secret".to_string()).await;
+            let mut server_events = state.subscribe_server_events();
+
+            let response = routes(state)
+                .oneshot(chat_request(
+                    session_id,
+                    vec![Message::user().with_text("Hello")],
+                ))
+                .await
+                .unwrap();
+            let body = timeout(
+                Duration::from_secs(30),
+                axum::body::to_bytes(response.into_body(), usize::MAX),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            let events = String::from_utf8(body.to_vec()).unwrap();
+
+            assert!(events.contains(r#""type":"AuthRequired""#));
+            assert!(events.contains(r#""provider":"databricks""#));
+            assert!(events.contains(r#""code":"authentication_failed""#));
+
+            let expected = ServerEvent::AuthRequired {
+                provider: "databricks".to_string(),
+                config_keys: vec![
+                    "DATABRICKS_HOST".to_string(),
+                    "DATABRICKS_TOKEN".to_string(),
+                ],
+                session_id: session_id.to_string(),
+            };
+            loop {
+                let event = timeout(Duration::from_secs(5), server_events.recv())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                if matches!(event, ServerEvent::AuthRequired { .. }) {
+                    assert_eq!(event, expected);
+                    break;
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn test_context_length_error_compacts_and_retries_once() {
+            use_test_session_dir();
+            let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let agent = Agent::new();
+            let _ = agent
+                .update_provider(Arc::new(ContextLimitProvider {
+                    model_config: ModelConfig::new("test-model").unwrap(),
+                    requests: requests.clone(),
+                }))
+                .await;
+            let state = AppState::new(Arc::new(agent), "test-secret".to_string()).await;
+
+            let prompt = "  Summarize the log,\n\tthen  list the errors  \n";
+            let response = routes(state)
+                .oneshot(chat_request(
+                    "test-context-retry",
+                    vec![
+                        Message::user().with_text("Hello"),
+                        Message::assistant().with_text("Hi, how can I help?"),
+                        Message::user().with_text(prompt),
+                    ],
+                ))
+                .await
+                .unwrap();
+            let body = timeout(
+                Duration::from_secs(30),
+                axum::body::to_bytes(response.into_body(), usize::MAX),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            let events = String::from_utf8(body.to_vec()).unwrap();
+
+            assert_eq!(events.matches(r#""type":"ContextCompacted""#).count(), 1);
+            assert!(!events.contains("context_length_exceeded"));
+            assert!(events.contains("Mock response"));
+
+            // The failed request and a single retry end with the prompt; the other requests
+            // are the summary and the session description
+            let requests = requests.lock().unwrap();
+            let prompted: Vec<&Vec<Message>> = requests
+                .iter()
+                .filter(|messages| {
+                    messages.last().is_some_and(|last| {
+                        last.role == Role::User && last.as_concat_text() == prompt
+                    })
+                })
+                .collect();
+            assert_eq!(prompted.len(), 2);
+            let retry = prompted[1];
+            assert!(!retry
+                .iter()
+                .any(|message| message.as_concat_text().contains("Hi, how can I help?")));
+        }
+
+        #[tokio::test]
+        async fn test_tool_approval_batch_reports_each_id() {
+            use_test_session_dir();
+            let session_id = "test-tool-approval-batch";
+            let session_path =
+                session::get_path(session::Identifier::Name(session_id.to_string())).unwrap();
+            // The tools it calls are kept out of the user's audit log
+            let agent = Agent::new().with_audit_log(Arc::new(AuditLog::disabled()));
+            let _ = agent
+                .update_provider(Arc::new(ParallelToolProvider {
+                    model_config: ModelConfig::new("test-model").unwrap(),
+                }))
+                .await;
+            let state = AppState::new(Arc::new(agent), "test-secret".to_string()).await;
+            let batch = |confirmations: Value| {
+                Request::builder()
+                    .uri("/reply/tool_approval_batch")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::from(
+                        json!({ "confirmations": confirmations }).to_string(),
+                    ))
+                    .unwrap()
+            };
+            let read_json = |response: Response| async move {
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<Value>(&body).unwrap()
+            };
+
+            let reply = routes(state.clone())
+                .oneshot(chat_request_in_mode(
+                    session_id,
+                    vec![Message::user().with_text("Tidy my notes")],
+                    ReplyExecutionMode::ApproveEachStep,
+                ))
+                .await
+                .unwrap();
+            let agent = state.get_agent().await.unwrap();
+            timeout(Duration::from_secs(30), async {
+                while agent.pending_confirmations().len() < 3 {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap();
+
+            let response = routes(state.clone())
+                .oneshot(batch(json!([
+                    {"id": "call_1", "action": "deny", "apply_to_remaining_in_turn": true},
+                    {"id": "call_2", "action": "allow_once"},
+                    {"id": "call_9", "action": "allow_once"},
+                    {"id": "call_3", "action": "deny"},
+                ])))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                read_json(response).await["results"],
+                json!([
+                    {"id": "call_1", "status": "applied"},
+                    {"id": "call_2", "status": "applied"},
+                    {"id": "call_2", "status": "already_resolved"},
+                    {"id": "call_9", "status": "unknown_id"},
+                    {"id": "call_3", "status": "applied"},
+                ])
+            );
+
+            // Once the reply has used the answers, they can't be given again
+            timeout(
+                Duration::from_secs(30),
+                axum::body::to_bytes(reply.into_body(), usize::MAX),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            let response = routes(state)
+                .oneshot(batch(json!([{"id": "call_1", "action": "allow_once"}])))
+                .await
+                .unwrap();
+            assert_eq!(
+                read_json(response).await["results"],
+                json!([{"id": "call_1", "status": "already_resolved"}])
+            );
+        }
+
+        #[tokio::test]
+        async fn test_pending_confirmations_empty() {
+            let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+            let app = routes(state);
+
+            let request = Request::builder()
+                .uri("/reply/pending_confirmations")
+                .method("GET")
+                .header("x-secret-key", "test-secret")
+                .body(Body::empty())
+                .unwrap();
+
+            let response = app.oneshot(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body, json!({"confirmations": []}));
         }
     }
 }
@@ -1,36 +1,145 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use axum::{
     extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 
-use chrono::NaiveDateTime;
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
 
-use crate::routes::utils::verify_secret_key;
+use crate::routes::recipe::invalid_parameters;
+use crate::routes::utils::{scopes, RequireScope};
 use crate::state::AppState;
-use goose::scheduler::ScheduledJob;
+use goose::config::Config;
+use goose::recipe::build_recipe::{build_recipe_with_values, RecipeError};
+use goose::recipe::read_recipe_file_content::read_recipe_file;
+use goose::scheduler::{
+    ConcurrencyPolicy, RetryPolicy, RunAttempt, RunStats, RunStatus, ScheduledJob, SkippedRun,
+};
+use goose::scheduler_cron::{preview_cron, resolve_timezone, CronParseError, DEFAULT_PREVIEW_RUNS};
+use goose::scheduler_gate::{ExecutionConditions, SCHEDULER_CONDITIONS_KEY};
+use goose::scheduler_spec::{ScheduleKind, ScheduleSpec};
+use goose::scheduler_trait::SchedulerTrait;
+use goose::scheduler_webhook::WebhookNotification;
+use goose::session_callback::SessionCallback;
 
 #[derive(Deserialize, Serialize, utoipa::ToSchema)]
 pub struct CreateScheduleRequest {
     id: String,
-    recipe_source: String,
-    cron: String,
+    /// Recipe file the job runs; exactly one of `recipe_source` and `prompt` is set
+    #[serde(default)]
+    recipe_source: Option<String>,
+    /// Prompt the job runs without a recipe
+    #[serde(default)]
+    prompt: Option<String>,
+    /// Directory a prompt job runs in; the server's working directory when unset
+    #[serde(default)]
+    working_dir: Option<String>,
+    /// Names of configured extensions enabled for a prompt job
+    #[serde(default)]
+    extensions: Vec<String>,
+    /// Values for the recipe's parameters; declared defaults fill in the rest
+    #[serde(default)]
+    params: HashMap<String, String>,
+    /// Cron expression the job fires on; exactly one of `cron`, `run_at` and `every` is set
+    #[serde(default)]
+    cron: Option<String>,
+    /// Run the job once at this time
+    #[serde(default)]
+    run_at: Option<DateTime<Utc>>,
+    /// Run the job repeatedly at this interval, such as `4h` or `30m`
+    #[serde(default)]
+    every: Option<String>,
     #[serde(default)]
     execution_mode: Option<String>, // "foreground" or "background"
+    /// IANA timezone to evaluate the cron expression in; UTC when unset
+    #[serde(default)]
+    timezone: Option<String>,
+    /// What happens when the job fires while its previous run is still going; `skip` when unset
+    #[serde(default)]
+    concurrency_policy: Option<ConcurrencyPolicy>,
+    /// Retry failed runs; failed runs are not retried when unset
+    #[serde(default)]
+    retry: Option<RetryPolicy>,
+    /// Webhook notified when a run fails after its last attempt
+    #[serde(default)]
+    on_failure: Option<WebhookNotification>,
+    /// Webhook notified when a run succeeds
+    #[serde(default)]
+    on_success: Option<WebhookNotification>,
+    /// URL sent the result of every finished run, http(s) only
+    #[serde(default)]
+    callback_url: Option<String>,
+    /// Key the callback body is signed with, sent as `X-Goose-Signature`
+    #[serde(default)]
+    callback_secret: Option<String>,
+    /// When runs are held back; unset conditions fall back to the global ones
+    #[serde(default)]
+    conditions: Option<ExecutionConditions>,
 }
 
 #[derive(Deserialize, Serialize, utoipa::ToSchema)]
 pub struct UpdateScheduleRequest {
+    /// Cron expression the job fires on; exactly one of `cron`, `run_at` and `every` is set
+    #[serde(default)]
+    cron: Option<String>,
+    /// Run the job once at this time
+    #[serde(default)]
+    run_at: Option<DateTime<Utc>>,
+    /// Run the job repeatedly at this interval, such as `4h` or `30m`
+    #[serde(default)]
+    every: Option<String>,
+    /// IANA timezone to evaluate the cron expression in; UTC when unset
+    #[serde(default)]
+    timezone: Option<String>,
+    /// Left unchanged when unset
+    #[serde(default)]
+    concurrency_policy: Option<ConcurrencyPolicy>,
+    /// Replaces the job's execution conditions; left unchanged when unset
+    #[serde(default)]
+    conditions: Option<ExecutionConditions>,
+}
+
+#[derive(Deserialize, Serialize, utoipa::ToSchema)]
+pub struct ValidateCronRequest {
     cron: String,
+    #[serde(default)]
+    timezone: Option<String>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateCronResponse {
+    valid: bool,
+    description: Option<String>,
+    /// The next fire times in the server's timezone
+    #[schema(value_type = Vec<String>)]
+    next_runs: Vec<DateTime<Local>>,
+    error: Option<String>,
+    /// Offset into the expression where parsing failed
+    position: Option<usize>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ScheduleListEntry {
+    #[serde(flatten)]
+    job: ScheduledJob,
+    /// Next run in the job's timezone (RFC 3339), absent for paused jobs
+    next_run: Option<String>,
+    /// The same instant in UTC
+    next_run_utc: Option<DateTime<Utc>>,
+    /// Outcome of the job's recent runs
+    stats: RunStats,
 }
 
 #[derive(Serialize, utoipa::ToSchema)]
 pub struct ListSchedulesResponse {
-    jobs: Vec<ScheduledJob>,
+    jobs: Vec<ScheduleListEntry>,
 }
 
 // Response for the kill endpoint
@@ -46,6 +155,11 @@ pub struct InspectJobResponse {
     session_id: Option<String>,
     process_start_time: Option<String>,
     running_duration_seconds: Option<i64>,
+    concurrency_policy: ConcurrencyPolicy,
+    /// A run is waiting for the current one to finish
+    queued_run: bool,
+    /// Recent runs dropped by the concurrency policy, oldest first
+    skipped_runs: Vec<SkippedRun>,
 }
 
 // Response for the run_now endpoint
@@ -61,6 +175,13 @@ pub struct SessionsQuery {
     limit: u32,
 }
 
+// Query parameters for the runs endpoint
+#[derive(Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct RunsQuery {
+    #[serde(default = "default_limit")]
+    limit: u32,
+}
+
 fn default_limit() -> u32 {
     50 // Default limit for sessions listed
 }
@@ -81,6 +202,88 @@ pub struct SessionDisplayInfo {
     accumulated_total_tokens: Option<i32>,
     accumulated_input_tokens: Option<i32>,
     accumulated_output_tokens: Option<i32>,
+    /// Which attempt of its run this session was, from the job's run history
+    attempt: Option<u32>,
+    run_status: Option<RunStatus>,
+    error: Option<String>,
+}
+
+// Reject a bad timing or timezone before the scheduler stores it
+fn check_schedule(
+    cron: Option<String>,
+    run_at: Option<DateTime<Utc>>,
+    every: Option<String>,
+    timezone: Option<&str>,
+) -> Result<ScheduleSpec, Response> {
+    let unprocessable =
+        |e: CronParseError| (StatusCode::UNPROCESSABLE_ENTITY, Json(e)).into_response();
+    let spec = ScheduleSpec::from_parts(cron, run_at, every).map_err(unprocessable)?;
+    spec.validate(Utc::now()).map_err(unprocessable)?;
+    resolve_timezone(timezone).map_err(|e| {
+        unprocessable(CronParseError {
+            message: e.to_string(),
+            position: None,
+        })
+    })?;
+    Ok(spec)
+}
+
+fn check_conditions(conditions: Option<&ExecutionConditions>) -> Result<(), Response> {
+    conditions
+        .map_or(Ok(()), ExecutionConditions::validate)
+        .map_err(|message| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(CronParseError {
+                    message,
+                    position: None,
+                }),
+            )
+                .into_response()
+        })
+}
+
+/// Refuse a concurrency policy the scheduler can't hold jobs to
+fn check_concurrency_policy(
+    scheduler: &dyn SchedulerTrait,
+    policy: Option<ConcurrencyPolicy>,
+) -> Result<(), Response> {
+    match policy {
+        Some(policy) if !scheduler.supports_concurrency_policy(policy) => {
+            Err(StatusCode::BAD_REQUEST.into_response())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_callback(url: Option<&str>) -> Result<(), Response> {
+    let Some(url) = url else {
+        return Ok(());
+    };
+    SessionCallback::new(url, None).map(|_| ()).map_err(|e| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(CronParseError {
+                message: e.to_string(),
+                position: None,
+            }),
+        )
+            .into_response()
+    })
+}
+
+fn list_entry(job: ScheduledJob) -> ScheduleListEntry {
+    let next = job.next_fire(Utc::now());
+    let next_local = resolve_timezone(job.timezone.as_deref())
+        .ok()
+        .zip(next)
+        .map(|(tz, time)| time.with_timezone(&tz).to_rfc3339());
+    ScheduleListEntry {
+        next_run: next_local,
+        next_run_utc: next,
+        stats: job.run_stats(),
+        job,
+    }
 }
 
 fn parse_session_name_to_iso(session_name: &str) -> String {
@@ -95,7 +298,8 @@ fn parse_session_name_to_iso(session_name: &str) -> String {
     request_body = CreateScheduleRequest,
     responses(
         (status = 200, description = "Scheduled job created successfully", body = ScheduledJob),
-        (status = 400, description = "Invalid cron expression or recipe file"),
+        (status = 400, description = "Invalid recipe file or prompt, not exactly one of recipe_source and prompt, or a concurrency policy the scheduler doesn't support"),
+        (status = 422, description = "Invalid cron expression, run_at time, interval, conditions or callback URL, or parameter values rejected with an InvalidParametersResponse body", body = CronParseError),
         (status = 409, description = "Job ID already exists"),
         (status = 500, description = "Internal server error")
     ),
@@ -104,30 +308,70 @@ fn parse_session_name_to_iso(session_name: &str) -> String {
 #[axum::debug_handler]
 async fn create_schedule(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Json(req): Json<CreateScheduleRequest>,
-) -> Result<Json<ScheduledJob>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
+) -> Result<Json<ScheduledJob>, Response> {
+    if req.recipe_source.is_some() == req.prompt.is_some() {
+        return Err(StatusCode::BAD_REQUEST.into_response());
+    }
+    if req.prompt.is_none() && (req.working_dir.is_some() || !req.extensions.is_empty()) {
+        return Err(StatusCode::BAD_REQUEST.into_response());
+    }
+    if req.prompt.is_some() && !req.params.is_empty() {
+        return Err(StatusCode::BAD_REQUEST.into_response());
+    }
+    // Report each rejected parameter value; other recipe problems are left to the scheduler
+    if let Some(Ok(recipe_file)) = req.recipe_source.as_ref().map(read_recipe_file) {
+        if let Err(RecipeError::InvalidParams { errors }) =
+            build_recipe_with_values(recipe_file, &req.params)
+        {
+            return Err(invalid_parameters(errors));
+        }
+    }
+    let spec = check_schedule(req.cron, req.run_at, req.every, req.timezone.as_deref())?;
+    check_conditions(req.conditions.as_ref())?;
+    check_callback(req.callback_url.as_deref())?;
     let scheduler = state
         .scheduler()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    check_concurrency_policy(scheduler.as_ref(), req.concurrency_policy)?;
 
     tracing::info!(
         "Server: Calling scheduler.add_scheduled_job() for job '{}'",
         req.id
     );
-    let job = ScheduledJob {
+    let mut job = ScheduledJob {
         id: req.id,
-        source: req.recipe_source,
-        cron: req.cron,
+        source: req.recipe_source.unwrap_or_default(),
+        prompt: req.prompt,
+        working_dir: req.working_dir,
+        extensions: req.extensions,
+        params: req.params,
+        kind: ScheduleKind::Cron,
+        cron: String::new(),
+        run_at: None,
+        every: None,
+        interval_anchor: None,
         last_run: None,
         currently_running: false,
         paused: false,
         current_session_id: None,
         process_start_time: None,
         execution_mode: req.execution_mode.or(Some("background".to_string())), // Default to background
+        timezone: req.timezone,
+        concurrency_policy: req.concurrency_policy.unwrap_or_default(),
+        queued_run: false,
+        skipped_runs: Vec::new(),
+        retry: req.retry,
+        on_failure: req.on_failure,
+        on_success: req.on_success,
+        callback_url: req.callback_url,
+        callback_secret: req.callback_secret,
+        run_history: Vec::new(),
+        conditions: req.conditions.unwrap_or_default(),
     };
+    job.set_spec(spec);
     scheduler
         .add_scheduled_job(job.clone())
         .await
@@ -137,9 +381,11 @@ async fn create_schedule(
                 goose::scheduler::SchedulerError::JobNotFound(_) => StatusCode::NOT_FOUND,
                 goose::scheduler::SchedulerError::CronParseError(_) => StatusCode::BAD_REQUEST,
                 goose::scheduler::SchedulerError::RecipeLoadError(_) => StatusCode::BAD_REQUEST,
+                goose::scheduler::SchedulerError::AgentSetupError(_) => StatusCode::BAD_REQUEST,
                 goose::scheduler::SchedulerError::JobIdExists(_) => StatusCode::CONFLICT,
                 _ => StatusCode::INTERNAL_SERVER_ERROR,
             }
+            .into_response()
         })?;
     Ok(Json(job))
 }
@@ -156,9 +402,8 @@ async fn create_schedule(
 #[axum::debug_handler]
 async fn list_schedules(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
 ) -> Result<Json<ListSchedulesResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
     let scheduler = state
         .scheduler()
         .await
@@ -169,6 +414,7 @@ async fn list_schedules(
         eprintln!("Error listing schedules: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    let jobs = jobs.into_iter().map(list_entry).collect();
     Ok(Json(ListSchedulesResponse { jobs }))
 }
 
@@ -188,10 +434,9 @@ async fn list_schedules(
 #[axum::debug_handler]
 async fn delete_schedule(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, StatusCode> {
-    verify_secret_key(&headers, &state)?;
     let scheduler = state
         .scheduler()
         .await
@@ -222,10 +467,9 @@ async fn delete_schedule(
 #[axum::debug_handler]
 async fn run_now_handler(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Path(id): Path<String>,
 ) -> Result<Json<RunNowResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
     let scheduler = state
         .scheduler()
         .await
@@ -272,16 +516,37 @@ async fn run_now_handler(
 #[axum::debug_handler]
 async fn sessions_handler(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,                    // Added this line
+    _scope: RequireScope<scopes::Schedule>,
     Path(schedule_id_param): Path<String>, // Renamed to avoid confusion with session_id
     Query(query_params): Query<SessionsQuery>,
 ) -> Result<Json<Vec<SessionDisplayInfo>>, StatusCode> {
-    verify_secret_key(&headers, &state)?; // Added this line
     let scheduler = state
         .scheduler()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // Attempts are only recorded by jobs run since run history was added
+    let run_history: Vec<RunAttempt> = scheduler
+        .list_scheduled_jobs()
+        .await
+        .map_err(|e| {
+            eprintln!(
+                "Error listing schedules for '{}': {:?}",
+                schedule_id_param, e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|job| job.id == schedule_id_param)
+        .map(|job| job.run_history)
+        .unwrap_or_default();
+    let attempt_for = |session_name: &str| {
+        run_history
+            .iter()
+            .rev()
+            .find(|attempt| attempt.session_id.as_deref() == Some(session_name))
+    };
+
     match scheduler
         .sessions(&schedule_id_param, query_params.limit as usize)
         .await
@@ -290,19 +555,25 @@ async fn sessions_handler(
             // Expecting Vec<(String, goose::session::storage::SessionMetadata)>
             let display_infos: Vec<SessionDisplayInfo> = session_tuples
                 .into_iter()
-                .map(|(session_name, metadata)| SessionDisplayInfo {
-                    id: session_name.clone(),
-                    name: metadata.description, // Use description as name
-                    created_at: parse_session_name_to_iso(&session_name),
-                    working_dir: metadata.working_dir.to_string_lossy().into_owned(),
-                    schedule_id: metadata.schedule_id, // This is the ID of the schedule itself
-                    message_count: metadata.message_count,
-                    total_tokens: metadata.total_tokens,
-                    input_tokens: metadata.input_tokens,
-                    output_tokens: metadata.output_tokens,
-                    accumulated_total_tokens: metadata.accumulated_total_tokens,
-                    accumulated_input_tokens: metadata.accumulated_input_tokens,
-                    accumulated_output_tokens: metadata.accumulated_output_tokens,
+                .map(|(session_name, metadata)| {
+                    let attempt = attempt_for(&session_name);
+                    SessionDisplayInfo {
+                        id: session_name.clone(),
+                        name: metadata.description, // Use description as name
+                        created_at: parse_session_name_to_iso(&session_name),
+                        working_dir: metadata.working_dir.to_string_lossy().into_owned(),
+                        schedule_id: metadata.schedule_id, // This is the ID of the schedule itself
+                        message_count: metadata.message_count,
+                        total_tokens: metadata.total_tokens,
+                        input_tokens: metadata.input_tokens,
+                        output_tokens: metadata.output_tokens,
+                        accumulated_total_tokens: metadata.accumulated_total_tokens,
+                        accumulated_input_tokens: metadata.accumulated_input_tokens,
+                        accumulated_output_tokens: metadata.accumulated_output_tokens,
+                        attempt: attempt.map(|attempt| attempt.attempt),
+                        run_status: attempt.map(|attempt| attempt.status),
+                        error: attempt.and_then(|attempt| attempt.error.clone()),
+                    }
                 })
                 .collect();
             Ok(Json(display_infos))
@@ -334,10 +605,9 @@ async fn sessions_handler(
 #[axum::debug_handler]
 async fn pause_schedule(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, StatusCode> {
-    verify_secret_key(&headers, &state)?;
     let scheduler = state
         .scheduler()
         .await
@@ -370,10 +640,9 @@ async fn pause_schedule(
 #[axum::debug_handler]
 async fn unpause_schedule(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, StatusCode> {
-    verify_secret_key(&headers, &state)?;
     let scheduler = state
         .scheduler()
         .await
@@ -399,7 +668,8 @@ async fn unpause_schedule(
     responses(
         (status = 200, description = "Scheduled job updated successfully", body = ScheduledJob),
         (status = 404, description = "Scheduled job not found"),
-        (status = 400, description = "Cannot update a currently running job or invalid request"),
+        (status = 400, description = "Cannot update a currently running job, a concurrency policy the scheduler doesn't support, or invalid request"),
+        (status = 422, description = "Invalid cron expression, run_at time, interval or conditions", body = CronParseError),
         (status = 500, description = "Internal server error")
     ),
     tag = "schedule"
@@ -407,18 +677,21 @@ async fn unpause_schedule(
 #[axum::debug_handler]
 async fn update_schedule(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Path(id): Path<String>,
     Json(req): Json<UpdateScheduleRequest>,
-) -> Result<Json<ScheduledJob>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
+) -> Result<Json<ScheduledJob>, Response> {
+    let spec = check_schedule(req.cron, req.run_at, req.every, req.timezone.as_deref())?;
+    check_conditions(req.conditions.as_ref())?;
     let scheduler = state
         .scheduler()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    // Checked before anything changes, so a refused policy leaves the job as it was
+    check_concurrency_policy(scheduler.as_ref(), req.concurrency_policy)?;
 
     scheduler
-        .update_schedule(&id, req.cron)
+        .update_schedule(&id, spec, req.timezone)
         .await
         .map_err(|e| {
             eprintln!("Error updating schedule '{}': {:?}", id, e);
@@ -428,17 +701,51 @@ async fn update_schedule(
                 goose::scheduler::SchedulerError::CronParseError(_) => StatusCode::BAD_REQUEST,
                 _ => StatusCode::INTERNAL_SERVER_ERROR,
             }
+            .into_response()
         })?;
+    if let Some(policy) = req.concurrency_policy {
+        scheduler
+            .set_concurrency_policy(&id, policy)
+            .await
+            .map_err(|e| {
+                eprintln!(
+                    "Error setting concurrency policy of schedule '{}': {:?}",
+                    id, e
+                );
+                match e {
+                    goose::scheduler::SchedulerError::JobNotFound(_) => StatusCode::NOT_FOUND,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                }
+                .into_response()
+            })?;
+    }
+    if let Some(conditions) = req.conditions {
+        scheduler
+            .set_execution_conditions(&id, conditions)
+            .await
+            .map_err(|e| {
+                eprintln!(
+                    "Error setting execution conditions of schedule '{}': {:?}",
+                    id, e
+                );
+                match e {
+                    goose::scheduler::SchedulerError::JobNotFound(_) => StatusCode::NOT_FOUND,
+                    goose::scheduler::SchedulerError::AnyhowError(_) => StatusCode::BAD_REQUEST,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                }
+                .into_response()
+            })?;
+    }
 
     // Return the updated schedule
     let jobs = scheduler.list_scheduled_jobs().await.map_err(|e| {
         eprintln!("Error listing schedules after update: {:?}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
     })?;
     let updated_job = jobs
         .into_iter()
         .find(|job| job.id == id)
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
 
     Ok(Json(updated_job))
 }
@@ -454,10 +761,9 @@ async fn update_schedule(
 #[axum::debug_handler]
 pub async fn kill_running_job(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Path(id): Path<String>,
 ) -> Result<Json<KillJobResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
     let scheduler = state
         .scheduler()
         .await
@@ -493,31 +799,37 @@ pub async fn kill_running_job(
 #[axum::debug_handler]
 pub async fn inspect_running_job(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::Schedule>,
     Path(id): Path<String>,
 ) -> Result<Json<InspectJobResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
     let scheduler = state
         .scheduler()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    let job = scheduler
+        .list_scheduled_jobs()
+        .await
+        .map_err(|e| {
+            eprintln!("Error listing schedules for '{}': {:?}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|job| job.id == id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
     match scheduler.get_running_job_info(&id).await {
         Ok(info) => {
-            if let Some((session_id, start_time)) = info {
-                let duration = chrono::Utc::now().signed_duration_since(start_time);
-                Ok(Json(InspectJobResponse {
-                    session_id: Some(session_id),
-                    process_start_time: Some(start_time.to_rfc3339()),
-                    running_duration_seconds: Some(duration.num_seconds()),
-                }))
-            } else {
-                Ok(Json(InspectJobResponse {
-                    session_id: None,
-                    process_start_time: None,
-                    running_duration_seconds: None,
-                }))
-            }
+            let (session_id, start_time) = info.unzip();
+            Ok(Json(InspectJobResponse {
+                session_id,
+                process_start_time: start_time.map(|time| time.to_rfc3339()),
+                running_duration_seconds: start_time
+                    .map(|time| chrono::Utc::now().signed_duration_since(time).num_seconds()),
+                concurrency_policy: job.concurrency_policy,
+                queued_run: job.queued_run,
+                skipped_runs: job.skipped_runs,
+            }))
         }
         Err(e) => {
             eprintln!("Error inspecting running job '{}': {:?}", id, e);
@@ -529,10 +841,136 @@ pub async fn inspect_running_job(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/schedule/validate",
+    request_body = ValidateCronRequest,
+    responses(
+        (status = 200, description = "Cron expression checked, nothing was created", body = ValidateCronResponse),
+        (status = 401, description = "Unauthorized - Invalid or missing API key")
+    ),
+    tag = "schedule"
+)]
+#[axum::debug_handler]
+async fn validate_schedule(
+    _scope: RequireScope<scopes::Schedule>,
+    Json(req): Json<ValidateCronRequest>,
+) -> Result<Json<ValidateCronResponse>, StatusCode> {
+    let preview = resolve_timezone(req.timezone.as_deref())
+        .map_err(|e| CronParseError {
+            message: e.to_string(),
+            position: None,
+        })
+        .and_then(|tz| preview_cron(&req.cron, &tz, DEFAULT_PREVIEW_RUNS));
+    let response = match preview {
+        Ok(preview) => ValidateCronResponse {
+            valid: true,
+            description: Some(preview.description),
+            next_runs: preview.next_runs,
+            error: None,
+            position: None,
+        },
+        Err(e) => ValidateCronResponse {
+            valid: false,
+            description: None,
+            next_runs: Vec::new(),
+            error: Some(e.message),
+            position: e.position,
+        },
+    };
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/schedule/{id}/runs",
+    params(
+        ("id" = String, Path, description = "ID of the schedule"),
+        RunsQuery
+    ),
+    responses(
+        (status = 200, description = "The schedule's runs, newest first", body = Vec<RunAttempt>),
+        (status = 404, description = "Scheduled job not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "schedule"
+)]
+#[axum::debug_handler]
+async fn runs_handler(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Schedule>,
+    Path(id): Path<String>,
+    Query(query): Query<RunsQuery>,
+) -> Result<Json<Vec<RunAttempt>>, StatusCode> {
+    let scheduler = state
+        .scheduler()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let job = scheduler
+        .list_scheduled_jobs()
+        .await
+        .map_err(|e| {
+            eprintln!("Error listing schedules for '{}': {:?}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|job| job.id == id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut runs = job.runs();
+    runs.truncate(query.limit as usize);
+    Ok(Json(runs))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scheduler/conditions",
+    responses(
+        (status = 200, description = "Execution conditions applied to all scheduled jobs", body = ExecutionConditions),
+    ),
+    tag = "schedule"
+)]
+#[axum::debug_handler]
+async fn get_global_conditions(
+    _scope: RequireScope<scopes::Schedule>,
+) -> Result<Json<ExecutionConditions>, StatusCode> {
+    Ok(Json(ExecutionConditions::global()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/scheduler/conditions",
+    request_body = ExecutionConditions,
+    responses(
+        (status = 200, description = "Execution conditions updated", body = ExecutionConditions),
+        (status = 422, description = "Invalid conditions", body = CronParseError),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "schedule"
+)]
+#[axum::debug_handler]
+async fn set_global_conditions(
+    _scope: RequireScope<scopes::Schedule>,
+    Json(conditions): Json<ExecutionConditions>,
+) -> Result<Json<ExecutionConditions>, Response> {
+    check_conditions(Some(&conditions))?;
+    let value = serde_json::to_value(&conditions)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    Config::global()
+        .set_param(SCHEDULER_CONDITIONS_KEY, value)
+        .map_err(|e| {
+            eprintln!("Error saving scheduler conditions: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+    Ok(Json(conditions))
+}
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/schedule/create", post(create_schedule))
         .route("/schedule/list", get(list_schedules))
+        .route("/schedule/validate", post(validate_schedule))
         .route("/schedule/delete/{id}", delete(delete_schedule)) // Corrected
         .route("/schedule/{id}", put(update_schedule))
         .route("/schedule/{id}/run_now", post(run_now_handler)) // Corrected
@@ -541,5 +979,10 @@ pub fn routes(state: Arc<AppState>) -> Router {
         .route("/schedule/{id}/kill", post(kill_running_job))
         .route("/schedule/{id}/inspect", get(inspect_running_job))
         .route("/schedule/{id}/sessions", get(sessions_handler)) // Corrected
+        .route("/schedule/{id}/runs", get(runs_handler))
+        .route(
+            "/scheduler/conditions",
+            get(get_global_conditions).put(set_global_conditions),
+        )
         .with_state(state)
 }
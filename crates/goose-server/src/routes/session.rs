@@ -1,23 +1,43 @@
-use super::utils::verify_secret_key;
+use super::utils::{scopes, RequireScope};
 use chrono::{DateTime, Datelike};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
+use super::events::ServerEvent;
+use super::limits::session_tokens;
 use crate::state::AppState;
 use axum::{
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
-    routing::get,
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, patch, post},
     Json, Router,
 };
-use goose::message::Message;
+use futures::StreamExt;
+use goose::agents::{Agent, AgentEvent, SessionConfig};
+use goose::config::Persona;
+use goose::message::{push_message, Message};
+use goose::providers::create_with_model;
+use goose::providers::transcript::{self, ProviderCallRef};
 use goose::session;
+use goose::session::artifacts::{artifact_content_type, get_artifact_path};
+use goose::session::attachments::AttachmentLimits;
 use goose::session::info::{get_valid_sorted_sessions, SessionInfo, SortOrder};
-use goose::session::SessionMetadata;
-use serde::Serialize;
+use goose::session::replay::user_turns;
+use goose::session::{
+    AttachmentError, AttachmentInfo, CleanupOptions, CleanupReport, MessageEditError,
+    RecordedToolResults, RelatedSessions, ReplayRecord, RetentionPolicy, SessionFormatError,
+    SessionMetadata, SessionOrigin, ToolUsageSummary, TurnComparison, TurnStats,
+};
+use goose::session_callback::{list_deliveries, DeliveryAttempt};
+use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 use utoipa::ToSchema;
 
+/// Artifact names are unique and their contents never change
+const ARTIFACT_CACHE_CONTROL: &str = "private, max-age=31536000, immutable";
+
 #[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionListResponse {
@@ -25,6 +45,14 @@ pub struct SessionListResponse {
     sessions: Vec<SessionInfo>,
 }
 
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct SessionListQuery {
+    /// Hide sessions forked from or started by another listed session
+    #[serde(default)]
+    root_only: bool,
+}
+
 #[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionHistoryResponse {
@@ -59,9 +87,21 @@ pub struct ActivityHeatmapCell {
     pub count: usize,
 }
 
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct SessionCleanupQuery {
+    /// Only report the sessions that would be removed
+    #[serde(default)]
+    dry_run: bool,
+    /// Archive removed sessions to a compressed tarball instead of deleting them
+    #[serde(default)]
+    archive: bool,
+}
+
 #[utoipa::path(
     get,
     path = "/sessions",
+    params(SessionListQuery),
     responses(
         (status = 200, description = "List of available sessions retrieved successfully", body = SessionListResponse),
         (status = 401, description = "Unauthorized - Invalid or missing API key"),
@@ -74,13 +114,14 @@ pub struct ActivityHeatmapCell {
 )]
 // List all available sessions
 async fn list_sessions(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsRead>,
+    Query(query): Query<SessionListQuery>,
 ) -> Result<Json<SessionListResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
-    let sessions = get_valid_sorted_sessions(SortOrder::Descending)
+    let mut sessions = get_valid_sorted_sessions(SortOrder::Descending)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if query.root_only {
+        sessions = session::root_sessions(sessions);
+    }
 
     Ok(Json(SessionListResponse { sessions }))
 }
@@ -92,9 +133,10 @@ async fn list_sessions(
         ("session_id" = String, Path, description = "Unique identifier for the session")
     ),
     responses(
-        (status = 200, description = "Session history retrieved successfully", body = SessionHistoryResponse),
+        (status = 200, description = "Session history, normalized to the current session format", body = SessionHistoryResponse),
         (status = 401, description = "Unauthorized - Invalid or missing API key"),
         (status = 404, description = "Session not found"),
+        (status = 409, description = "Session can't be continued by this server", body = SessionConflictResponse),
         (status = 500, description = "Internal server error")
     ),
     security(
@@ -104,26 +146,136 @@ async fn list_sessions(
 )]
 // Get a specific session's history
 async fn get_session_history(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsRead>,
     Path(session_id): Path<String>,
-) -> Result<Json<SessionHistoryResponse>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
+) -> Result<Json<SessionHistoryResponse>, Response> {
     let session_path = match session::get_path(session::Identifier::Name(session_id.clone())) {
         Ok(path) => path,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+        Err(_) => return Err(StatusCode::BAD_REQUEST.into_response()),
     };
 
-    let metadata = session::read_metadata(&session_path).map_err(|_| StatusCode::NOT_FOUND)?;
-
-    let messages = match session::read_messages(&session_path) {
-        Ok(messages) => messages,
-        Err(e) => {
-            tracing::error!("Failed to read session messages: {:?}", e);
-            return Err(StatusCode::NOT_FOUND);
+    let (mut metadata, messages) = match session::load_session(&session_path) {
+        Ok(session) => session,
+        Err(SessionFormatError::Storage(e)) => {
+            tracing::error!("Failed to read session: {:?}", e);
+            return Err(StatusCode::NOT_FOUND.into_response());
         }
+        Err(e) => return Err(session_format_error(e)),
     };
+    // Sessions saved before tool usage was counted have none in their metadata
+    metadata.tool_usage = ToolUsageSummary::from_messages(&messages);
+
+    Ok(Json(SessionHistoryResponse {
+        session_id,
+        metadata,
+        messages,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}/related",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session")
+    ),
+    responses(
+        (status = 200, description = "Ancestors of the session and the tree of sessions started from it", body = RelatedSessions),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// Get the sessions a session was forked from or started by, and the ones started from it
+async fn get_related_sessions(
+    _scope: RequireScope<scopes::SessionsRead>,
+    Path(session_id): Path<String>,
+) -> Result<Json<RelatedSessions>, StatusCode> {
+    // Oldest first, so children are listed in the order they were started
+    let sessions = get_valid_sorted_sessions(SortOrder::Ascending)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    session::related_sessions(&sessions, &session_id)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionConflictResponse {
+    /// Why the session can't be continued
+    reason: String,
+}
+
+/// Response for a session stored in a way this server can't continue or rewrite
+pub(crate) fn session_format_error(e: SessionFormatError) -> Response {
+    match e {
+        SessionFormatError::Storage(e) => {
+            error!("Failed to read session: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        e => (
+            StatusCode::CONFLICT,
+            Json(SessionConflictResponse {
+                reason: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EditMessageRequest {
+    /// New text for the message
+    text: String,
+    /// Drop every later message, to re-run the conversation from the edit
+    #[serde(default)]
+    truncate_after: bool,
+}
+
+#[derive(Deserialize, ToSchema, utoipa::IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct DeleteMessageQuery {
+    /// Drop every later message too
+    #[serde(default)]
+    truncate_after: bool,
+}
+
+/// Apply an edit to a session's history and respond with the result. Sessions with a reply
+/// streaming are left alone, since the reply would overwrite the edit when it's saved, and so
+/// are sessions in a format this server can't rewrite.
+fn edit_session_history(
+    state: &AppState,
+    session_id: String,
+    edit: impl FnOnce(&std::path::Path) -> Result<Vec<Message>, MessageEditError>,
+) -> Result<Json<SessionHistoryResponse>, StatusCode> {
+    if state.is_streaming(&session_id) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let session_path = session::get_path(session::Identifier::Name(session_id.clone()))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if !session_path.exists() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if let Err(e) = session::check_resumable(&session_path) {
+        error!("Refusing to edit session {}: {}", session_id, e);
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let messages = edit(&session_path).map_err(|e| match e {
+        MessageEditError::NoSuchMessage(_) => StatusCode::NOT_FOUND,
+        MessageEditError::NotEditable => StatusCode::UNPROCESSABLE_ENTITY,
+        MessageEditError::Storage(e) => {
+            error!("Failed to rewrite session history: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+    let metadata = session::read_metadata(&session_path).map_err(|e| {
+        error!("Failed to read session metadata: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
     Ok(Json(SessionHistoryResponse {
         session_id,
@@ -132,6 +284,69 @@ async fn get_session_history(
     }))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/sessions/{session_id}/messages/{index}",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session"),
+        ("index" = usize, Path, description = "Position of the message in the session history")
+    ),
+    request_body = EditMessageRequest,
+    responses(
+        (status = 200, description = "Message edited, with the updated history", body = SessionHistoryResponse),
+        (status = 400, description = "Invalid session id"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session or message not found"),
+        (status = 409, description = "A reply is streaming in the session, or it can't be rewritten by this server"),
+        (status = 422, description = "Message is not a user message with text"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// Replace the text of a user message, optionally dropping everything after it
+async fn edit_session_message(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::SessionsWrite>,
+    Path((session_id, index)): Path<(String, usize)>,
+    Json(request): Json<EditMessageRequest>,
+) -> Result<Json<SessionHistoryResponse>, StatusCode> {
+    edit_session_history(&state, session_id, |path| {
+        session::edit_message_text(path, index, &request.text, request.truncate_after)
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sessions/{session_id}/messages/{index}",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session"),
+        ("index" = usize, Path, description = "Position of the message in the session history"),
+        DeleteMessageQuery
+    ),
+    responses(
+        (status = 200, description = "Message deleted, with the updated history", body = SessionHistoryResponse),
+        (status = 400, description = "Invalid session id"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session or message not found"),
+        (status = 409, description = "A reply is streaming in the session, or it can't be rewritten by this server"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// Delete a message along with the other half of any tool calls in it
+async fn delete_session_message(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::SessionsWrite>,
+    Path((session_id, index)): Path<(String, usize)>,
+    Query(query): Query<DeleteMessageQuery>,
+) -> Result<Json<SessionHistoryResponse>, StatusCode> {
+    edit_session_history(&state, session_id, |path| {
+        session::delete_message(path, index, query.truncate_after)
+    })
+}
+
 #[utoipa::path(
     get,
     path = "/sessions/insights",
@@ -146,13 +361,10 @@ async fn get_session_history(
     tag = "Session Management"
 )]
 async fn get_session_insights(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsRead>,
 ) -> Result<Json<SessionInsights>, StatusCode> {
     info!("Received request for session insights");
 
-    verify_secret_key(&headers, &state)?;
-
     let sessions = get_valid_sorted_sessions(SortOrder::Descending).map_err(|e| {
         error!("Failed to get session info: {:?}", e);
         StatusCode::INTERNAL_SERVER_ERROR
@@ -263,11 +475,8 @@ async fn get_session_insights(
     tag = "Session Management"
 )]
 async fn get_activity_heatmap(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    _scope: RequireScope<scopes::SessionsRead>,
 ) -> Result<Json<Vec<ActivityHeatmapCell>>, StatusCode> {
-    verify_secret_key(&headers, &state)?;
-
     let sessions = get_valid_sorted_sessions(SortOrder::Descending)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -300,12 +509,724 @@ async fn get_activity_heatmap(
     Ok(Json(result))
 }
 
+#[utoipa::path(
+    post,
+    path = "/sessions/cleanup",
+    params(SessionCleanupQuery),
+    responses(
+        (status = 200, description = "Sessions cleaned up according to the retention policy", body = CleanupReport),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 500, description = "Internal server error"),
+        (status = 503, description = "The scheduler isn't running, so the sessions of schedules can't be told apart")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// Apply the session retention policy, exempting sessions that belong to schedules
+async fn cleanup_sessions(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::SessionsWrite>,
+    Query(query): Query<SessionCleanupQuery>,
+) -> Result<Json<CleanupReport>, StatusCode> {
+    // Without the schedules, the sessions that belong to them would be cleaned up too
+    let scheduler = state
+        .scheduler()
+        .await
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    let jobs = scheduler.list_scheduled_jobs().await.map_err(|e| {
+        error!("Failed to list scheduled jobs for session cleanup: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let options = CleanupOptions {
+        dry_run: query.dry_run,
+        archive: query.archive,
+        protected_schedule_ids: jobs.into_iter().map(|job| job.id).collect(),
+    };
+
+    let report = session::cleanup(&RetentionPolicy::from_config(), &options).map_err(|e| {
+        error!("Session cleanup failed: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{session_id}/attachments",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session")
+    ),
+    request_body(content = String, description = "Multipart form with the file in a `file` field", content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Attachment stored, with the id to reference it by in a message", body = AttachmentInfo),
+        (status = 400, description = "Invalid session id or no file in the form"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found"),
+        (status = 413, description = "File is larger than GOOSE_MAX_ATTACHMENT_BYTES"),
+        (status = 415, description = "File type is not in GOOSE_ATTACHMENT_TYPES"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// Upload a file to a session, to be sent to the model by messages that reference it
+async fn upload_session_attachment(
+    _scope: RequireScope<scopes::SessionsWrite>,
+    Path(session_id): Path<String>,
+    mut multipart: Multipart,
+) -> Result<Json<AttachmentInfo>, StatusCode> {
+    let session_path = session::get_path(session::Identifier::Name(session_id.clone()))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if !session_path.exists() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    while let Some(field) = multipart.next_field().await.map_err(|e| e.status())? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let file_name = field.file_name().map(str::to_string);
+        let content_type = field.content_type().map(str::to_string);
+        let bytes = field.bytes().await.map_err(|e| e.status())?;
+
+        let info = session::save_attachment(
+            &session_id,
+            file_name.as_deref(),
+            content_type.as_deref(),
+            &bytes,
+        )
+        .map_err(|e| match e {
+            AttachmentError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            AttachmentError::UnsupportedType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AttachmentError::InvalidId(_) | AttachmentError::NotFound(_) => StatusCode::BAD_REQUEST,
+            AttachmentError::Storage(e) => {
+                error!("Failed to store session attachment: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+        return Ok(Json(info));
+    }
+
+    Err(StatusCode::BAD_REQUEST)
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}/artifacts/{artifact_id}",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session"),
+        ("artifact_id" = String, Path, description = "Artifact file name, as given in the truncated tool output or image reference")
+    ),
+    responses(
+        (status = 200, description = "Full contents of an oversized tool result, or an image saved from one", body = String, content_type = "application/octet-stream"),
+        (status = 400, description = "Invalid session or artifact id"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Artifact not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// Get the full output of a tool result that was truncated to fit the tool result limit, or an
+// image that was moved out of one. Artifacts are never rewritten, so clients may cache them.
+async fn get_session_artifact(
+    _scope: RequireScope<scopes::SessionsRead>,
+    Path((session_id, artifact_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let path = get_artifact_path(&session_id, &artifact_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let bytes = match tokio::fs::read(&path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to read session artifact: {:?}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, artifact_content_type(&artifact_id)),
+            (header::CACHE_CONTROL, ARTIFACT_CACHE_CONTROL),
+        ],
+        bytes,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}/deliveries",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session")
+    ),
+    responses(
+        (status = 200, description = "Attempts to deliver the session's result to its callback URL, oldest first", body = Vec<DeliveryAttempt>),
+        (status = 400, description = "Invalid session id"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// List the callback deliveries made for a session's results
+async fn list_session_deliveries(
+    _scope: RequireScope<scopes::SessionsRead>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<DeliveryAttempt>>, StatusCode> {
+    session::get_path(session::Identifier::Name(session_id.clone()))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    list_deliveries(&session_id).map(Json).map_err(|e| {
+        error!(
+            "Failed to read deliveries of session {}: {:?}",
+            session_id, e
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{session_id}/provider_calls",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session")
+    ),
+    responses(
+        (status = 200, description = "Provider calls recorded for the session when GOOSE_PROVIDER_TRANSCRIPT_DIR is set, oldest first", body = Vec<ProviderCallRef>),
+        (status = 400, description = "Invalid session id"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// List the provider calls recorded for a session, each with the path of its transcript
+async fn list_provider_calls(
+    _scope: RequireScope<scopes::SessionsRead>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<ProviderCallRef>>, StatusCode> {
+    let session_path = session::get_path(session::Identifier::Name(session_id.clone()))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if !session_path.exists() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    transcript::list_calls(&session_id).map(Json).map_err(|e| {
+        error!(
+            "Failed to read provider calls of session {}: {:?}",
+            session_id, e
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReplayRequest {
+    /// Provider to replay the session with
+    provider: String,
+    /// Model to replay the session with
+    model: String,
+    /// Answer tool calls with the original session's results instead of running the tools
+    #[serde(default)]
+    skip_tools: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResponse {
+    /// The new session the replay is written to
+    session_id: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/sessions/{session_id}/replay",
+    params(
+        ("session_id" = String, Path, description = "Unique identifier for the session to replay")
+    ),
+    request_body = ReplayRequest,
+    responses(
+        (status = 202, description = "Replay started. Progress is sent on /events as ReplayProgress and ReplayFinished.", body = ReplayResponse),
+        (status = 400, description = "Invalid session id, or the provider or model can't be used"),
+        (status = 401, description = "Unauthorized - Invalid or missing API key"),
+        (status = 404, description = "Session not found"),
+        (status = 409, description = "Session can't be read by this server", body = SessionConflictResponse),
+        (status = 412, description = "Agent not initialized"),
+        (status = 422, description = "Session has no user messages to replay"),
+        (status = 429, description = "Rate limited, or the daily token budget is used up"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("api_key" = [])),
+    tag = "Session Management"
+)]
+// Replay a session's user turns against another provider and model, into a new session
+async fn replay_session(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::Chat>,
+    Path(session_id): Path<String>,
+    Json(request): Json<ReplayRequest>,
+) -> Result<(StatusCode, Json<ReplayResponse>), Response> {
+    let source_path = session::get_path(session::Identifier::Name(session_id.clone()))
+        .map_err(|_| StatusCode::BAD_REQUEST.into_response())?;
+    if !source_path.exists() {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    }
+    let (source_metadata, messages) = match session::load_session(&source_path) {
+        Ok(session) => session,
+        Err(SessionFormatError::Storage(e)) => {
+            error!("Failed to read session: {:?}", e);
+            return Err(StatusCode::NOT_FOUND.into_response());
+        }
+        Err(e) => return Err(session_format_error(e)),
+    };
+    let total_turns = user_turns(&messages).len();
+    if total_turns == 0 {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY.into_response());
+    }
+
+    let provider = create_with_model(&request.provider, &request.model).map_err(|e| {
+        error!(
+            "Failed to create {}/{} for a replay: {}",
+            request.provider, request.model, e
+        );
+        StatusCode::BAD_REQUEST.into_response()
+    })?;
+    let agent = state
+        .get_agent()
+        .await
+        .map_err(|_| StatusCode::PRECONDITION_FAILED.into_response())?;
+    state
+        .rate_limiter
+        .check_token_budget()
+        .map_err(IntoResponse::into_response)?;
+
+    let replay_id = session::generate_session_id();
+    let replay_path = session::get_path(session::Identifier::Name(replay_id.clone()))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    let mut metadata = SessionMetadata::new(source_metadata.working_dir.clone());
+    metadata.description = format!("Replay of {} with {}", session_id, request.model);
+    metadata.provider_config = Some(provider.sanitized_config());
+    metadata.parent_session_id = Some(session_id.clone());
+    metadata.origin = SessionOrigin::Replay;
+    metadata.replay = Some(ReplayRecord {
+        source_session_id: session_id.clone(),
+        provider: request.provider.clone(),
+        model: request.model.clone(),
+        skip_tools: request.skip_tools,
+        total_turns,
+        turns: Vec::new(),
+    });
+    session::storage::save_messages_with_metadata(&replay_path, &metadata, &[]).map_err(|e| {
+        error!("Failed to create the replay session: {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })?;
+
+    let session_config = SessionConfig {
+        id: session::Identifier::Name(replay_id.clone()),
+        working_dir: source_metadata.working_dir,
+        schedule_id: None,
+        // Nobody is there to approve tool calls during a replay
+        execution_mode: Some("foreground".to_string()),
+        max_turns: None,
+        retry_config: None,
+        allowed_extensions: None,
+        persona: Some(Persona {
+            name: "replay".to_string(),
+            provider: Some(request.provider),
+            model: Some(request.model),
+            ..Persona::default()
+        }),
+        goose_mode: None,
+        permissions_path: None,
+        recorded_tool_results: request
+            .skip_tools
+            .then(|| Arc::new(RecordedToolResults::from_messages(&messages))),
+    };
+    tokio::spawn(run_replay(
+        state,
+        agent,
+        session_config,
+        replay_id.clone(),
+        replay_path,
+        session_id,
+        messages,
+    ));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ReplayResponse {
+            session_id: replay_id,
+        }),
+    ))
+}
+
+/// Replay the user turns of `source_messages` one at a time, saving the replay session and
+/// reporting progress after each
+async fn run_replay(
+    state: Arc<AppState>,
+    agent: Arc<Agent>,
+    session_config: SessionConfig,
+    session_id: String,
+    replay_path: std::path::PathBuf,
+    source_session_id: String,
+    source_messages: Vec<Message>,
+) {
+    // Both sides are counted with the replay model's tokenizer
+    let model = session_config
+        .persona
+        .as_ref()
+        .and_then(|persona| persona.model.clone())
+        .unwrap_or_default();
+    let turns = user_turns(&source_messages);
+    let mut conversation: Vec<Message> = Vec::new();
+    let mut failure = None;
+
+    for (index, turn) in turns.iter().enumerate() {
+        let started = Instant::now();
+        let mut produced = vec![turn[0].clone()];
+        conversation.push(turn[0].clone());
+        if let Err(e) = replay_turn(&agent, &session_config, &mut conversation, &mut produced).await
+        {
+            failure = Some(e.to_string());
+        }
+        let comparison = TurnComparison {
+            turn: index,
+            original: TurnStats::of(turn, &model),
+            replay: TurnStats {
+                duration_ms: Some(started.elapsed().as_millis() as u64),
+                ..TurnStats::of(&produced, &model)
+            },
+        };
+
+        // The agent records token usage in the metadata while it replies
+        let saved = session::read_metadata(&replay_path).and_then(|mut metadata| {
+            metadata.message_count = conversation.len();
+            if let Some(replay) = metadata.replay.as_mut() {
+                replay.turns.push(comparison);
+            }
+            session::storage::save_messages_with_metadata(&replay_path, &metadata, &conversation)
+        });
+        if let Err(e) = saved {
+            error!("Failed to save replay session {}: {:?}", session_id, e);
+            failure.get_or_insert_with(|| "Failed to save the replay session".to_string());
+        }
+
+        state.publish_event(ServerEvent::ReplayProgress {
+            session_id: session_id.clone(),
+            source_session_id: source_session_id.clone(),
+            turn: index + 1,
+            total_turns: turns.len(),
+        });
+        if failure.is_some() {
+            break;
+        }
+    }
+
+    state
+        .rate_limiter
+        .record_tokens(session_tokens(&replay_path));
+    state.publish_event(ServerEvent::ReplayFinished {
+        session_id,
+        source_session_id,
+        error: failure,
+    });
+}
+
+/// Run one user turn, the last message of `conversation`, adding the agent's messages to both
+/// `conversation` and `produced`
+async fn replay_turn(
+    agent: &Agent,
+    session_config: &SessionConfig,
+    conversation: &mut Vec<Message>,
+    produced: &mut Vec<Message>,
+) -> anyhow::Result<()> {
+    let mut stream = agent
+        .reply(conversation, Some(session_config.clone()), None)
+        .await?;
+    while let Some(event) = stream.next().await {
+        match event? {
+            AgentEvent::Message(message) => {
+                push_message(conversation, message.clone());
+                push_message(produced, message);
+            }
+            AgentEvent::HistoryReplaced(messages) => *conversation = messages,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 // Configure routes for this module
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/sessions", get(list_sessions))
         .route("/sessions/{session_id}", get(get_session_history))
+        .route("/sessions/{session_id}/related", get(get_related_sessions))
         .route("/sessions/insights", get(get_session_insights))
         .route("/sessions/activity-heatmap", get(get_activity_heatmap))
+        .route("/sessions/cleanup", post(cleanup_sessions))
+        .route(
+            "/sessions/{session_id}/messages/{index}",
+            patch(edit_session_message).delete(delete_session_message),
+        )
+        .route(
+            "/sessions/{session_id}/attachments",
+            // Leave room for the multipart framing, the file itself is checked against the limit
+            post(upload_session_attachment).layer(DefaultBodyLimit::max(
+                AttachmentLimits::from_config()
+                    .max_bytes
+                    .saturating_add(64 * 1024),
+            )),
+        )
+        .route(
+            "/sessions/{session_id}/artifacts/{artifact_id}",
+            get(get_session_artifact),
+        )
+        .route(
+            "/sessions/{session_id}/deliveries",
+            get(list_session_deliveries),
+        )
+        .route(
+            "/sessions/{session_id}/provider_calls",
+            get(list_provider_calls),
+        )
+        .route("/sessions/{session_id}/replay", post(replay_session))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::utils::use_test_session_dir;
+    use axum::body::Body;
+    use axum::http::Request;
+    use goose::agents::Agent;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_messages_are_not_edited_while_streaming() {
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let _guard = state.track_stream("busy-session", None);
+
+        let response = routes(state)
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri("/sessions/busy-session/messages/0")
+                    .header("x-secret-key", "test-secret")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"text": "edited"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_attachment_upload_requires_a_file_and_a_session() {
+        use_test_session_dir();
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let session_id = format!("attachment-test-{}", std::process::id());
+        let path = session::get_path(session::Identifier::Name(session_id.clone())).unwrap();
+        session::storage::save_messages_with_metadata(&path, &SessionMetadata::default(), &[])
+            .unwrap();
+
+        let upload = |session_id: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/sessions/{}/attachments", session_id))
+                .header("x-secret-key", "test-secret")
+                .header("content-type", "multipart/form-data; boundary=boundary")
+                .body(Body::from(
+                    "--boundary\r\n\
+                    Content-Disposition: form-data; name=\"note\"\r\n\r\n\
+                    no file here\r\n\
+                    --boundary--\r\n",
+                ))
+                .unwrap()
+        };
+        let no_file = routes(state.clone())
+            .oneshot(upload(&session_id))
+            .await
+            .unwrap();
+        let unknown_session = routes(state)
+            .oneshot(upload("no-such-session-5c1e"))
+            .await
+            .unwrap();
+
+        assert_eq!(no_file.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(unknown_session.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_related_sessions_of_unknown_session() {
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+
+        let response = routes(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions/no-such-session-5c1e/related")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_waits_for_the_scheduler() {
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+
+        let response = routes(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions/cleanup")
+                    .method("POST")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_replay_needs_a_usable_provider() {
+        use_test_session_dir();
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let session_id = format!("replay-test-{}", std::process::id());
+        let path = session::get_path(session::Identifier::Name(session_id.clone())).unwrap();
+        session::storage::save_messages_with_metadata(
+            &path,
+            &SessionMetadata::default(),
+            &[
+                Message::user().with_text("What's in this directory?"),
+                Message::assistant().with_text("A Cargo.toml and a src directory."),
+            ],
+        )
+        .unwrap();
+
+        let replay = |session_id: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/sessions/{}/replay", session_id))
+                .header("x-secret-key", "test-secret")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"provider": "no-such-provider", "model": "some-model", "skip_tools": true}"#,
+                ))
+                .unwrap()
+        };
+        let unknown_provider = routes(state.clone())
+            .oneshot(replay(&session_id))
+            .await
+            .unwrap();
+        let unknown_session = routes(state)
+            .oneshot(replay("no-such-session-5c1e"))
+            .await
+            .unwrap();
+
+        assert_eq!(unknown_provider.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(unknown_session.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_image_artifact_is_served_with_caching_headers() {
+        use_test_session_dir();
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let session_id = format!("artifact-test-{}", std::process::id());
+        let path = get_artifact_path(&session_id, "chart.png").unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"png bytes").unwrap();
+
+        let response = routes(state)
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/sessions/{}/artifacts/chart.png", session_id))
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            ARTIFACT_CACHE_CONTROL
+        );
+    }
+
+    #[tokio::test]
+    async fn test_provider_calls_of_unknown_session() {
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let response = routes(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions/no-such-session-5c1e/provider_calls")
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_history_counts_tool_usage_of_older_sessions() {
+        use_test_session_dir();
+        let state = AppState::new(Arc::new(Agent::new()), "test-secret".to_string()).await;
+        let session_id = format!("tool-usage-test-{}", std::process::id());
+        let path = session::get_path(session::Identifier::Name(session_id.clone())).unwrap();
+
+        // Written the way sessions were before their metadata counted tool calls
+        let mut metadata = serde_json::to_value(SessionMetadata::default()).unwrap();
+        metadata.as_object_mut().unwrap().remove("tool_usage");
+        let messages = [
+            Message::assistant().with_tool_request(
+                "1",
+                Ok(mcp_core::tool::ToolCall::new(
+                    "developer__shell",
+                    serde_json::json!({"command": "ls"}),
+                )),
+            ),
+            Message::user().with_tool_response(
+                "1",
+                Err(mcp_core::ToolError::ExecutionError(
+                    "exit status 1".to_string(),
+                )),
+            ),
+        ];
+        let mut lines = vec![metadata.to_string()];
+        lines.extend(messages.iter().map(|m| serde_json::to_string(m).unwrap()));
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let response = routes(state)
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/sessions/{}", session_id))
+                    .header("x-secret-key", "test-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let history: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let usage = &history["metadata"]["tool_usage"];
+        assert_eq!(usage["calls"], 1);
+        assert_eq!(usage["failures"], 1);
+        assert_eq!(usage["tools"][0]["extension"], "developer");
+        assert_eq!(usage["tools"][0]["tool"], "shell");
+    }
+}
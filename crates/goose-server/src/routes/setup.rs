@@ -1,60 +1,248 @@
+use super::utils::{scopes, RequireScope};
 use crate::state::AppState;
 use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
-use goose::config::signup_openrouter::OpenRouterAuth;
-use goose::config::{configure_openrouter, Config};
-use serde::Serialize;
+use goose::config::{headless_auth, run_signup, signup_for, Config};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 #[derive(Serialize)]
 pub struct SetupResponse {
     pub success: bool,
     pub message: String,
+    /// Whether the signup is waiting for the user, to be finished with `/handle_signup/complete`
+    pub pending: bool,
+    /// Page the user should sign in at, for pending signups
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Code to enter on that page, for device flows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_code: Option<String>,
+}
+
+impl SetupResponse {
+    fn done(success: bool, message: String) -> Self {
+        Self {
+            success,
+            message,
+            pending: false,
+            url: None,
+            user_code: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SignupRequest {
+    /// Provider to sign up with, one whose metadata advertises a signup
+    pub provider: String,
+    /// Return the sign in URL instead of opening a browser and listening for the redirect
+    #[serde(default)]
+    pub headless: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CompleteSignupRequest {
+    pub provider: String,
+    /// The redirect URL or code the user copied, for flows that redirect back
+    #[serde(default)]
+    pub code: Option<String>,
 }
 
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
-        .route("/handle_openrouter", post(start_openrouter_setup))
+        .route("/handle_signup", post(start_signup))
+        .route("/handle_signup/complete", post(complete_signup))
         .with_state(state)
 }
 
-async fn start_openrouter_setup(
-    State(_state): State<Arc<AppState>>,
+async fn start_signup(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Json(request): Json<SignupRequest>,
 ) -> Result<Json<SetupResponse>, StatusCode> {
-    tracing::info!("Starting OpenRouter setup flow");
+    tracing::info!("Starting {} signup flow", request.provider);
 
-    let mut auth_flow = OpenRouterAuth::new().map_err(|e| {
-        tracing::error!("Failed to initialize auth flow: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let mut signup = match signup_for(&request.provider) {
+        Ok(signup) => signup,
+        Err(e) => {
+            tracing::error!("Failed to initialize signup flow: {}", e);
+            return Ok(Json(SetupResponse::done(
+                false,
+                format!("Setup failed: {}", e),
+            )));
+        }
+    };
 
-    tracing::info!("Auth flow initialized, starting complete_flow");
+    if request.headless || headless_auth() {
+        let prompt = match signup.start_headless().await {
+            Ok(prompt) => prompt,
+            Err(e) => {
+                tracing::error!("{} setup failed: {}", request.provider, e);
+                return Ok(Json(SetupResponse::done(
+                    false,
+                    format!("Setup failed: {}", e),
+                )));
+            }
+        };
+        let message = if signup.needs_pasted_code() {
+            "Sign in, then send the URL you were redirected to".to_string()
+        } else {
+            "Sign in, then confirm to finish setup".to_string()
+        };
+        state
+            .pending_signups
+            .lock()
+            .await
+            .insert(request.provider, signup);
+        return Ok(Json(SetupResponse {
+            success: true,
+            message,
+            pending: true,
+            url: Some(prompt.url),
+            user_code: prompt.user_code,
+        }));
+    }
 
-    match auth_flow.complete_flow().await {
-        Ok(api_key) => {
-            tracing::info!("Got API key, configuring OpenRouter...");
+    match run_signup(signup.as_mut(), Config::global()).await {
+        Ok(()) => {
+            tracing::info!("{} setup completed successfully", request.provider);
+            Ok(Json(SetupResponse::done(
+                true,
+                format!("{} setup completed successfully", request.provider),
+            )))
+        }
+        Err(e) => {
+            tracing::error!("{} setup failed: {}", request.provider, e);
+            Ok(Json(SetupResponse::done(
+                false,
+                format!("Setup failed: {}", e),
+            )))
+        }
+    }
+}
 
-            let config = Config::global();
+async fn complete_signup(
+    State(state): State<Arc<AppState>>,
+    _scope: RequireScope<scopes::ConfigWrite>,
+    Json(request): Json<CompleteSignupRequest>,
+) -> Result<Json<SetupResponse>, StatusCode> {
+    let mut signup = state
+        .pending_signups
+        .lock()
+        .await
+        .remove(&request.provider)
+        .ok_or(StatusCode::NOT_FOUND)?;
 
-            if let Err(e) = configure_openrouter(config, api_key) {
-                tracing::error!("Failed to configure OpenRouter: {}", e);
-                return Ok(Json(SetupResponse {
-                    success: false,
-                    message: format!("Failed to configure OpenRouter: {}", e),
-                }));
-            }
+    let api_key = if signup.needs_pasted_code() {
+        let Some(code) = request.code else {
+            // Keep it pending so the client can try again with the code
+            state
+                .pending_signups
+                .lock()
+                .await
+                .insert(request.provider, signup);
+            return Err(StatusCode::BAD_REQUEST);
+        };
+        signup.finish_with_code(&code).await
+    } else {
+        signup.finish().await
+    };
 
-            tracing::info!("OpenRouter setup completed successfully");
-            Ok(Json(SetupResponse {
-                success: true,
-                message: "OpenRouter setup completed successfully".to_string(),
-            }))
+    match api_key.and_then(|api_key| signup.configure(Config::global(), api_key)) {
+        Ok(()) => {
+            tracing::info!("{} setup completed successfully", request.provider);
+            Ok(Json(SetupResponse::done(
+                true,
+                format!("{} setup completed successfully", request.provider),
+            )))
         }
         Err(e) => {
-            tracing::error!("OpenRouter setup failed: {}", e);
-            Ok(Json(SetupResponse {
-                success: false,
-                message: format!("Setup failed: {}", e),
-            }))
+            tracing::error!("{} setup failed: {}", request.provider, e);
+            Ok(Json(SetupResponse::done(
+                false,
+                format!("Setup failed: {}", e),
+            )))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    fn post_json(uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("content-type", "application/json")
+            .header("x-secret-key", "test")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_headless_signup_waits_for_pasted_code() {
+        let state = AppState::new(Arc::new(goose::agents::Agent::new()), "test".to_string()).await;
+        let app = routes(state.clone());
+
+        // Signups write API keys into the config, so they need a key that may change it
+        let mut unauthenticated = post_json(
+            "/handle_signup",
+            serde_json::json!({"provider": "openrouter", "headless": true}),
+        );
+        unauthenticated.headers_mut().remove("x-secret-key");
+        let response = app.clone().oneshot(unauthenticated).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .clone()
+            .oneshot(post_json(
+                "/handle_signup",
+                serde_json::json!({"provider": "openrouter", "headless": true}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["pending"], true);
+        assert!(body["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://openrouter.ai/auth"));
+        assert!(state
+            .pending_signups
+            .lock()
+            .await
+            .contains_key("openrouter"));
+
+        // Without the code the signup stays pending
+        let response = app
+            .clone()
+            .oneshot(post_json(
+                "/handle_signup/complete",
+                serde_json::json!({"provider": "openrouter"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(state
+            .pending_signups
+            .lock()
+            .await
+            .contains_key("openrouter"));
+
+        let response = app
+            .oneshot(post_json(
+                "/handle_signup/complete",
+                serde_json::json!({"provider": "litellm", "code": "abc"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
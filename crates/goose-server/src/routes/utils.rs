@@ -1,10 +1,22 @@
+use super::auth::Scope;
 use crate::state::AppState;
+use axum::extract::{ConnectInfo, FromRequestParts, MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 use goose::config::Config;
 use goose::providers::base::{ConfigKey, ProviderMetadata};
-use http::{HeaderMap, StatusCode};
+use http::request::Parts;
+use http::{header, HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::error::Error;
+use std::marker::PhantomData;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum KeyLocation {
@@ -23,18 +35,233 @@ pub struct KeyInfo {
     pub value: Option<String>, // Only populated for non-secret keys that are set
 }
 
-pub fn verify_secret_key(headers: &HeaderMap, state: &AppState) -> Result<StatusCode, StatusCode> {
-    // Verify secret key
+/// Respond with `value` as JSON tagged with an `ETag` of its content, or with `304 Not Modified`
+/// when the request's `If-None-Match` already names that tag
+pub fn json_with_etag<T: Serialize>(headers: &HeaderMap, value: &T) -> Response {
+    let body = match serde_json::to_vec(value) {
+        Ok(body) => body,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let etag = format!("\"{:x}\"", Sha256::digest(&body));
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| {
+            tags.split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+        });
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    (
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::ETAG, etag),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Check that the request's `X-Secret-Key` allows `scope`. The server's own secret key grants
+/// every scope; a minted API token only grants its own. An unknown key is `UNAUTHORIZED`, a
+/// token without the scope is `FORBIDDEN`.
+pub fn verify_secret_key(
+    headers: &HeaderMap,
+    state: &AppState,
+    scope: Scope,
+) -> Result<StatusCode, StatusCode> {
     let secret_key = headers
         .get("X-Secret-Key")
         .and_then(|value| value.to_str().ok())
         .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    if secret_key != state.secret_key {
-        Err(StatusCode::UNAUTHORIZED)
-    } else {
-        Ok(StatusCode::OK)
+    if constant_time_eq(secret_key, &state.secret_key) {
+        return Ok(StatusCode::OK);
     }
+    state.tokens.authorize(secret_key, scope)?;
+    Ok(StatusCode::OK)
+}
+
+/// A scope a route declares with [`RequireScope`]
+pub trait RequiredScope: Send + Sync {
+    const SCOPE: Scope;
+}
+
+/// The scopes as types, for [`RequireScope`]
+pub mod scopes {
+    use super::{RequiredScope, Scope};
+
+    macro_rules! scope_types {
+        ($($name:ident),*) => {
+            $(
+                pub struct $name;
+
+                impl RequiredScope for $name {
+                    const SCOPE: Scope = Scope::$name;
+                }
+            )*
+        };
+    }
+
+    scope_types!(
+        Chat,
+        SessionsRead,
+        SessionsWrite,
+        ConfigWrite,
+        Schedule,
+        Admin
+    );
+}
+
+/// Routes declare the scope they need by taking this as an argument, as in
+/// `_scope: RequireScope<scopes::Chat>`. The request is rejected before the handler runs unless
+/// its `X-Secret-Key` allows the scope, with the status from [`verify_secret_key`].
+pub struct RequireScope<S: RequiredScope>(PhantomData<S>);
+
+impl<S: RequiredScope> FromRequestParts<Arc<AppState>> for RequireScope<S> {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        verify_secret_key(&parts.headers, state, S::SCOPE)?;
+        Ok(Self(PhantomData))
+    }
+}
+
+#[cfg(test)]
+impl<S: RequiredScope> RequireScope<S> {
+    /// For tests that call a handler directly, past the scope check
+    pub fn granted() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Keep the sessions that tests create in a temporary directory rather than the user's
+#[cfg(test)]
+pub fn use_test_session_dir() {
+    static SESSION_DIR: std::sync::LazyLock<tempfile::TempDir> =
+        std::sync::LazyLock::new(|| tempfile::tempdir().unwrap());
+    goose::session::set_session_dir(SESSION_DIR.path().to_path_buf());
+}
+
+/// Compare secrets without the time taken revealing how much of them matched. Only the length
+/// can leak.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+/// Largest request body accepted by default, in bytes
+const MAX_BODY_SIZE_KEY: &str = "GOOSE_SERVER_MAX_BODY_SIZE";
+const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+/// Largest request body accepted by endpoints that take images, in bytes
+const MAX_IMAGE_BODY_SIZE_KEY: &str = "GOOSE_SERVER_MAX_IMAGE_BODY_SIZE";
+const DEFAULT_MAX_IMAGE_BODY_SIZE: usize = 50 * 1024 * 1024;
+
+/// Body size limit for requests. Larger bodies are rejected with `413 Payload Too Large`.
+pub fn max_body_size() -> usize {
+    Config::global()
+        .get_param(MAX_BODY_SIZE_KEY)
+        .unwrap_or(DEFAULT_MAX_BODY_SIZE)
+}
+
+/// Body size limit for requests that can carry images, never below `max_body_size`
+pub fn max_image_body_size() -> usize {
+    Config::global()
+        .get_param(MAX_IMAGE_BODY_SIZE_KEY)
+        .unwrap_or(DEFAULT_MAX_IMAGE_BODY_SIZE)
+        .max(max_body_size())
+}
+
+/// Failed authentications allowed from one address within `AUTH_FAILURE_WINDOW`
+const MAX_AUTH_FAILURES: usize = 10;
+const AUTH_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Counts failed authentications per remote address over a sliding window
+#[derive(Default)]
+pub struct AuthFailureLimiter {
+    failures: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+}
+
+impl AuthFailureLimiter {
+    /// How long `peer` has to wait before it may try again, if it has used up its failures
+    pub fn retry_after(&self, peer: IpAddr, now: Instant) -> Option<Duration> {
+        let mut failures = self
+            .failures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let times = failures.get_mut(&peer)?;
+        prune(times, now);
+        if times.len() < MAX_AUTH_FAILURES {
+            return None;
+        }
+        times
+            .front()
+            .map(|oldest| AUTH_FAILURE_WINDOW.saturating_sub(now.duration_since(*oldest)))
+    }
+
+    pub fn record_failure(&self, peer: IpAddr, now: Instant) {
+        let mut failures = self
+            .failures
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        failures.retain(|_, times| {
+            prune(times, now);
+            !times.is_empty()
+        });
+        failures.entry(peer).or_default().push_back(now);
+    }
+}
+
+fn prune(times: &mut VecDeque<Instant>, now: Instant) {
+    while times
+        .front()
+        .is_some_and(|oldest| now.duration_since(*oldest) >= AUTH_FAILURE_WINDOW)
+    {
+        times.pop_front();
+    }
+}
+
+/// Middleware rejecting requests with `429 Too Many Requests` from addresses that keep failing
+/// authentication. Requests served without connection info, as in tests, are not limited.
+pub async fn limit_auth_failures(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(ConnectInfo(peer)) = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .copied()
+    else {
+        return next.run(request).await;
+    };
+
+    if let Some(retry_after) = state.auth_failures.retry_after(peer.ip(), Instant::now()) {
+        let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, seconds.to_string())],
+        )
+            .into_response();
+    }
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "<unmatched>".to_string());
+    let response = next.run(request).await;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        state
+            .auth_failures
+            .record_failure(peer.ip(), Instant::now());
+        tracing::warn!(peer = %peer, route = %route, "Rejected request with a missing or invalid secret key");
+    }
+    response
 }
 
 /// Inspects a configuration key to determine if it's set, its location, and value (for non-secret keys)
@@ -176,3 +403,52 @@ pub fn check_provider_configured(metadata: &ProviderMetadata) -> bool {
         is_set_in_env || is_set_in_config
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("secret", "secret"));
+        assert!(!constant_time_eq("secret", "secreT"));
+        assert!(!constant_time_eq("secret", "secret-longer"));
+        assert!(!constant_time_eq("", "secret"));
+    }
+
+    #[test]
+    fn test_limiter_blocks_after_max_failures_within_window() {
+        let limiter = AuthFailureLimiter::default();
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        for i in 0..MAX_AUTH_FAILURES {
+            assert_eq!(limiter.retry_after(peer, start), None);
+            limiter.record_failure(peer, start + Duration::from_secs(i as u64));
+        }
+
+        let now = start + Duration::from_secs(15);
+        assert_eq!(
+            limiter.retry_after(peer, now),
+            Some(Duration::from_secs(45))
+        );
+        assert_eq!(limiter.retry_after(other, now), None);
+
+        // Once the oldest failure leaves the window there is room for another attempt
+        let later = start + AUTH_FAILURE_WINDOW;
+        assert_eq!(limiter.retry_after(peer, later), None);
+        limiter.record_failure(peer, later);
+        assert!(limiter.retry_after(peer, later).is_some());
+    }
+
+    #[test]
+    fn test_limiter_forgets_idle_peers() {
+        let limiter = AuthFailureLimiter::default();
+        let start = Instant::now();
+        limiter.record_failure("10.0.0.1".parse().unwrap(), start);
+        limiter.record_failure("10.0.0.2".parse().unwrap(), start + AUTH_FAILURE_WINDOW);
+
+        assert_eq!(limiter.failures.lock().unwrap().len(), 1);
+    }
+}
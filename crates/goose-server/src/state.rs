@@ -1,23 +1,673 @@
+use crate::routes::auth::TokenStore;
+use crate::routes::batch::Batches;
+use crate::routes::config_management::ConfigEvent;
+use crate::routes::events::ServerEvent;
+use crate::routes::limits::{Limits, RateLimiter, LIMIT_KEYS};
+use crate::routes::utils::AuthFailureLimiter;
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
 use goose::agents::Agent;
+use goose::config::{Config, ConfigError, ConfigReload, ProviderSignup, APP_STRATEGY};
+use goose::model::ModelConfig;
+use goose::providers::create;
 use goose::scheduler_trait::SchedulerTrait;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, watch, Mutex};
+use utoipa::ToSchema;
 
 pub type AgentRef = Arc<Agent>;
 
+/// A reply currently streaming to a client
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ActiveStream {
+    pub session_id: String,
+    pub started_at: DateTime<Utc>,
+}
+
+type ActiveStreams = Arc<std::sync::Mutex<HashMap<u64, ActiveStream>>>;
+
+/// Keeps a stream listed as active until dropped, when its session is reported finished
+pub struct StreamGuard {
+    id: u64,
+    streams: ActiveStreams,
+    events: broadcast::Sender<ServerEvent>,
+    queue: Arc<ReplyQueue>,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        let stream = self
+            .streams
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(&self.id);
+        if let Some(stream) = stream {
+            let _ = self.events.send(ServerEvent::SessionFinished {
+                session_id: stream.session_id,
+            });
+        }
+        self.queue.changed.send_replace(());
+    }
+}
+
+/// Run replies one at a time, queueing those that arrive while the agent is busy
+const SERIAL_SESSIONS_KEY: &str = "GOOSE_SERIAL_SESSIONS";
+/// Replies that may wait at once; more are turned away
+const SERIAL_QUEUE_SIZE_KEY: &str = "GOOSE_SERIAL_QUEUE_SIZE";
+const DEFAULT_SERIAL_QUEUE_SIZE: usize = 16;
+/// Seconds a reply waits for the agent before giving up
+const SERIAL_QUEUE_TIMEOUT_KEY: &str = "GOOSE_SERIAL_QUEUE_TIMEOUT";
+const DEFAULT_SERIAL_QUEUE_TIMEOUT: u64 = 600;
+
+/// Replies waiting for the agent in serial mode, first in first out
+pub struct ReplyQueue {
+    enabled: bool,
+    capacity: usize,
+    timeout: Duration,
+    waiting: std::sync::Mutex<VecDeque<u64>>,
+    next_ticket: AtomicU64,
+    /// Signalled whenever the queue moves or a stream finishes
+    changed: watch::Sender<()>,
+}
+
+impl ReplyQueue {
+    pub fn new(enabled: bool, capacity: usize, timeout: Duration) -> Self {
+        Self {
+            enabled,
+            capacity,
+            timeout,
+            waiting: std::sync::Mutex::default(),
+            next_ticket: AtomicU64::new(0),
+            changed: watch::channel(()).0,
+        }
+    }
+
+    fn from_config() -> Self {
+        let config = Config::global();
+        Self::new(
+            config.get_param(SERIAL_SESSIONS_KEY).unwrap_or(false),
+            config
+                .get_param(SERIAL_QUEUE_SIZE_KEY)
+                .unwrap_or(DEFAULT_SERIAL_QUEUE_SIZE),
+            Duration::from_secs(
+                config
+                    .get_param(SERIAL_QUEUE_TIMEOUT_KEY)
+                    .unwrap_or(DEFAULT_SERIAL_QUEUE_TIMEOUT),
+            ),
+        )
+    }
+
+    /// How long a reply waits for the agent before giving up
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Notified whenever a waiting reply's place may have changed
+    pub fn changes(&self) -> watch::Receiver<()> {
+        self.changed.subscribe()
+    }
+
+    fn waiting(&self) -> std::sync::MutexGuard<'_, VecDeque<u64>> {
+        self.waiting
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A reply's place in the [`ReplyQueue`], given up when dropped
+pub struct QueueTicket {
+    id: u64,
+    queue: Arc<ReplyQueue>,
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        let mut waiting = self.queue.waiting();
+        if let Some(index) = waiting.iter().position(|id| *id == self.id) {
+            waiting.remove(index);
+            drop(waiting);
+            self.queue.changed.send_replace(());
+        }
+    }
+}
+
+/// Server events kept for subscribers that fall behind; older ones are skipped
+const SERVER_EVENT_BUFFER: usize = 256;
+
+/// Seconds a finished request's idempotency key is remembered
+const IDEMPOTENCY_TTL_KEY: &str = "GOOSE_SERVER_IDEMPOTENCY_TTL";
+const DEFAULT_IDEMPOTENCY_TTL: u64 = 600;
+/// Finished keys remembered at most; the oldest are forgotten first
+const MAX_IDEMPOTENCY_KEYS: usize = 1024;
+
+/// What became of the request that first used an idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdempotentOutcome {
+    /// Its reply is still streaming
+    Running { session_id: String },
+    /// Its reply finished with `finish_event`, the last event it streamed
+    Finished {
+        session_id: String,
+        finish_event: String,
+    },
+}
+
+struct IdempotencyEntry {
+    outcome: IdempotentOutcome,
+    recorded_at: Instant,
+}
+
+type IdempotencyEntries = Arc<std::sync::Mutex<HashMap<String, IdempotencyEntry>>>;
+
+/// Recently used `Idempotency-Key`s, so a retried request is not run twice
+#[derive(Clone)]
+pub struct IdempotencyKeys {
+    entries: IdempotencyEntries,
+    ttl: Duration,
+}
+
+impl IdempotencyKeys {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::default(),
+            ttl,
+        }
+    }
+
+    /// Claim `key` for a request replying in `session_id`, or return what became of the
+    /// request that already claimed it
+    pub fn claim(
+        &self,
+        key: &str,
+        session_id: &str,
+    ) -> Result<IdempotencyClaim, IdempotentOutcome> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        entries.retain(|_, entry| {
+            matches!(entry.outcome, IdempotentOutcome::Running { .. })
+                || now.duration_since(entry.recorded_at) < self.ttl
+        });
+        if let Some(entry) = entries.get(key) {
+            return Err(entry.outcome.clone());
+        }
+
+        while entries.len() >= MAX_IDEMPOTENCY_KEYS {
+            let oldest = entries
+                .iter()
+                .filter(|(_, entry)| matches!(entry.outcome, IdempotentOutcome::Finished { .. }))
+                .min_by_key(|(_, entry)| entry.recorded_at)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => entries.remove(&oldest),
+                None => break,
+            };
+        }
+        entries.insert(
+            key.to_string(),
+            IdempotencyEntry {
+                outcome: IdempotentOutcome::Running {
+                    session_id: session_id.to_string(),
+                },
+                recorded_at: now,
+            },
+        );
+
+        Ok(IdempotencyClaim {
+            key: Some(key.to_string()),
+            session_id: session_id.to_string(),
+            entries: Arc::clone(&self.entries),
+        })
+    }
+}
+
+/// A claimed idempotency key. Dropping it before the reply finishes frees the key again, so a
+/// request that failed early can be retried.
+pub struct IdempotencyClaim {
+    key: Option<String>,
+    session_id: String,
+    entries: IdempotencyEntries,
+}
+
+impl IdempotencyClaim {
+    /// Remember that the reply finished with `finish_event`, to replay to retries
+    pub fn finish(mut self, finish_event: String) {
+        if let Some(key) = self.key.take() {
+            self.entries
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .insert(
+                    key,
+                    IdempotencyEntry {
+                        outcome: IdempotentOutcome::Finished {
+                            session_id: std::mem::take(&mut self.session_id),
+                            finish_event,
+                        },
+                        recorded_at: Instant::now(),
+                    },
+                );
+        }
+    }
+}
+
+impl Drop for IdempotencyClaim {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.entries
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .remove(&key);
+        }
+    }
+}
+
+/// Reply events kept per session for clients that reconnect
+const REPLAY_BUFFER_SIZE_KEY: &str = "GOOSE_SERVER_REPLAY_BUFFER_SIZE";
+const DEFAULT_REPLAY_BUFFER_SIZE: usize = 512;
+/// Seconds a reply keeps running without a client, and its events are kept after it finishes
+const RECONNECT_WINDOW_KEY: &str = "GOOSE_SERVER_RECONNECT_WINDOW";
+const DEFAULT_RECONNECT_WINDOW: u64 = 60;
+/// Room for live events in a reconnected client's stream, on top of the replayed ones
+const RECONNECT_CHANNEL_CAPACITY: usize = 100;
+
+struct ReplayLog {
+    /// Recent events with their ids, oldest first
+    events: VecDeque<(u64, String)>,
+    next_id: u64,
+    /// The connected client, until the reply finishes
+    client: Option<mpsc::Sender<String>>,
+    /// When the client was first found gone; cleared when one reconnects
+    disconnected_since: Option<Instant>,
+}
+
+/// The events of one reply, numbered from 1 and kept so that a client whose connection drops
+/// can reconnect and pick up where it left off
+pub struct ReplayBuffer {
+    log: std::sync::Mutex<ReplayLog>,
+    capacity: usize,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize, first_id: u64, client: mpsc::Sender<String>) -> Self {
+        Self {
+            log: std::sync::Mutex::new(ReplayLog {
+                events: VecDeque::new(),
+                next_id: first_id,
+                client: Some(client),
+                disconnected_since: None,
+            }),
+            capacity,
+        }
+    }
+
+    fn log(&self) -> std::sync::MutexGuard<'_, ReplayLog> {
+        self.log
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Number the formatted event `data` and keep it. Returns it with its `id:` field, and the
+    /// client to send it to if one is connected.
+    pub fn record(&self, data: &str) -> (String, Option<mpsc::Sender<String>>) {
+        let mut log = self.log();
+        let id = log.next_id;
+        log.next_id += 1;
+        let event = format!("id: {}\n{}", id, data);
+        if self.capacity > 0 {
+            if log.events.len() == self.capacity {
+                log.events.pop_front();
+            }
+            log.events.push_back((id, event.clone()));
+        }
+        (event, log.client.clone())
+    }
+
+    /// Connect a client that last received the event `last_event_id`. Its stream starts with the
+    /// events it missed, or with `resync` when some of them are no longer kept, and continues
+    /// with live events until the reply finishes.
+    pub fn reconnect(&self, last_event_id: u64, resync: &str) -> mpsc::Receiver<String> {
+        let mut log = self.log();
+        let first_kept = log.events.front().map_or(log.next_id, |(id, _)| *id);
+        let missed: Vec<String> = if last_event_id.saturating_add(1) < first_kept {
+            vec![resync.to_string()]
+        } else {
+            log.events
+                .iter()
+                .filter(|(id, _)| *id > last_event_id)
+                .map(|(_, event)| event.clone())
+                .collect()
+        };
+
+        let (tx, rx) = mpsc::channel(missed.len() + RECONNECT_CHANNEL_CAPACITY);
+        for event in missed {
+            let _ = tx.try_send(event);
+        }
+        if log.client.is_some() {
+            log.client = Some(tx);
+            log.disconnected_since = None;
+        }
+        rx
+    }
+
+    /// Whether no client has been connected for at least `window`
+    pub fn abandoned(&self, window: Duration) -> bool {
+        let mut log = self.log();
+        if log
+            .client
+            .as_ref()
+            .is_some_and(|client| !client.is_closed())
+        {
+            log.disconnected_since = None;
+            return false;
+        }
+        let since = *log.disconnected_since.get_or_insert_with(Instant::now);
+        since.elapsed() >= window
+    }
+
+    /// Mark the reply finished, ending the connected client's stream after the events it has
+    pub fn finish(&self) {
+        self.log().client = None;
+    }
+
+    fn next_id(&self) -> u64 {
+        self.log().next_id
+    }
+}
+
+type ReplayBuffers = Arc<std::sync::Mutex<HashMap<String, Arc<ReplayBuffer>>>>;
+
+/// Keeps a reply's events open to reconnecting clients until dropped. Its events are then
+/// forgotten once the reconnect window has passed, unless a newer reply replaced them.
+pub struct ReplayGuard {
+    buffer: Arc<ReplayBuffer>,
+    session_id: String,
+    buffers: ReplayBuffers,
+    window: Duration,
+}
+
+impl ReplayGuard {
+    pub fn buffer(&self) -> Arc<ReplayBuffer> {
+        Arc::clone(&self.buffer)
+    }
+}
+
+impl Drop for ReplayGuard {
+    fn drop(&mut self) {
+        self.buffer.finish();
+        let buffers = Arc::clone(&self.buffers);
+        let session_id = std::mem::take(&mut self.session_id);
+        let buffer = Arc::clone(&self.buffer);
+        let window = self.window;
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let mut buffers = buffers
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if buffers
+                .get(&session_id)
+                .is_some_and(|current| Arc::ptr_eq(current, &buffer))
+            {
+                buffers.remove(&session_id);
+            }
+        });
+    }
+}
+
+/// Seconds a provider's fetched model list is served before it is fetched again
+const MODEL_CACHE_TTL_KEY: &str = "GOOSE_MODEL_CACHE_TTL_SECS";
+const DEFAULT_MODEL_CACHE_TTL: u64 = 300;
+/// File in the state dir the last fetched model lists are kept in, for offline startups
+const MODEL_CACHE_FILE: &str = "model_cache.json";
+
+struct CachedModels {
+    models: Vec<String>,
+    /// When the list was fetched, or `None` when it was loaded from disk and is always stale
+    fetched_at: Option<Instant>,
+}
+
+/// The model lists fetched from providers. A stale list is still served while it is fetched
+/// again in the background, and concurrent requests for a provider share one fetch.
+#[derive(Clone)]
+pub struct ModelCache {
+    entries: Arc<std::sync::Mutex<HashMap<String, CachedModels>>>,
+    /// Held while a provider's models are being fetched
+    fetches: Arc<std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+    ttl: Duration,
+    path: Option<PathBuf>,
+}
+
+impl ModelCache {
+    /// Serve fetched lists for `ttl`, keeping them in `path` if given. The lists already saved
+    /// there are served, stale, until fetched again.
+    pub fn new(ttl: Duration, path: Option<PathBuf>) -> Self {
+        let saved: HashMap<String, Vec<String>> = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        let entries = saved
+            .into_iter()
+            .map(|(provider, models)| {
+                let cached = CachedModels {
+                    models,
+                    fetched_at: None,
+                };
+                (provider, cached)
+            })
+            .collect();
+        Self {
+            entries: Arc::new(std::sync::Mutex::new(entries)),
+            fetches: Arc::default(),
+            ttl,
+            path,
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, CachedModels>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The cached models of `provider`, and whether they are still fresh
+    fn lookup(&self, provider: &str) -> Option<(Vec<String>, bool)> {
+        self.entries().get(provider).map(|cached| {
+            let fresh = cached
+                .fetched_at
+                .is_some_and(|fetched_at| fetched_at.elapsed() < self.ttl);
+            (cached.models.clone(), fresh)
+        })
+    }
+
+    /// The models of `provider`, fetched with `fetch` when there is no cached list or when
+    /// `refresh` is set. A stale list is returned right away and fetched again in the background.
+    pub async fn models<F, Fut>(
+        &self,
+        provider: &str,
+        refresh: bool,
+        fetch: F,
+    ) -> Result<Vec<String>, anyhow::Error>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Vec<String>, anyhow::Error>> + Send + 'static,
+    {
+        if !refresh {
+            match self.lookup(provider) {
+                Some((models, true)) => return Ok(models),
+                Some((models, false)) => {
+                    let cache = self.clone();
+                    let provider = provider.to_string();
+                    tokio::spawn(async move {
+                        if let Err(e) = cache.fetch(&provider, false, fetch).await {
+                            tracing::warn!("Failed to refresh the models of {}: {}", provider, e);
+                        }
+                    });
+                    return Ok(models);
+                }
+                None => {}
+            }
+        }
+        self.fetch(provider, refresh, fetch).await
+    }
+
+    async fn fetch<F, Fut>(
+        &self,
+        provider: &str,
+        refresh: bool,
+        fetch: F,
+    ) -> Result<Vec<String>, anyhow::Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<String>, anyhow::Error>>,
+    {
+        let waited_from = Instant::now();
+        let lock = Arc::clone(
+            self.fetches
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .entry(provider.to_string())
+                .or_default(),
+        );
+        let _fetching = lock.lock().await;
+
+        // A request that held the lock first may have fetched the list already
+        if let Some(cached) = self.entries().get(provider) {
+            let fetched_since = cached
+                .fetched_at
+                .is_some_and(|fetched_at| fetched_at >= waited_from);
+            let fresh = cached
+                .fetched_at
+                .is_some_and(|fetched_at| fetched_at.elapsed() < self.ttl);
+            if fetched_since || (fresh && !refresh) {
+                return Ok(cached.models.clone());
+            }
+        }
+
+        let models = fetch().await?;
+        self.entries().insert(
+            provider.to_string(),
+            CachedModels {
+                models: models.clone(),
+                fetched_at: Some(Instant::now()),
+            },
+        );
+        self.save();
+        Ok(models)
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved: HashMap<String, Vec<String>> = self
+            .entries()
+            .iter()
+            .map(|(provider, cached)| (provider.clone(), cached.models.clone()))
+            .collect();
+        let result = serde_json::to_string(&saved)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                Ok(std::fs::write(path, contents)?)
+            });
+        if let Err(e) = result {
+            tracing::warn!("Failed to save the model cache: {}", e);
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
     agent: Option<AgentRef>,
+    /// Accepted as an admin token alongside the minted ones
     pub secret_key: String,
+    pub tokens: TokenStore,
+    pub auth_failures: Arc<AuthFailureLimiter>,
     pub scheduler: Arc<Mutex<Option<Arc<dyn SchedulerTrait>>>>,
+    /// The config profile in use, kept in step with the global config
+    profile: Arc<Mutex<Option<String>>>,
+    config_events: broadcast::Sender<ConfigEvent>,
+    active_streams: ActiveStreams,
+    next_stream_id: Arc<AtomicU64>,
+    /// Notifications dropped because a client read its reply stream too slowly
+    dropped_stream_events: Arc<AtomicU64>,
+    pub idempotency_keys: IdempotencyKeys,
+    server_events: broadcast::Sender<ServerEvent>,
+    /// The latest reply's events for each session
+    replay_buffers: ReplayBuffers,
+    replay_buffer_size: usize,
+    reconnect_window: Duration,
+    pub model_cache: ModelCache,
+    /// Headless signups waiting for the user to paste their code back, by provider
+    pub pending_signups: Arc<Mutex<HashMap<String, Box<dyn ProviderSignup>>>>,
+    pub reply_queue: Arc<ReplyQueue>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub batches: Batches,
+    /// Held while `/config/active_model` switches the provider, so switches don't interleave
+    pub model_switch: Arc<Mutex<()>>,
 }
 
 impl AppState {
     pub async fn new(agent: AgentRef, secret_key: String) -> Arc<AppState> {
+        Self::with_tokens(agent, secret_key, TokenStore::new())
+    }
+
+    pub fn with_tokens(agent: AgentRef, secret_key: String, tokens: TokenStore) -> Arc<AppState> {
         Arc::new(Self {
             agent: Some(agent.clone()),
             secret_key,
+            tokens,
+            auth_failures: Arc::default(),
             scheduler: Arc::new(Mutex::new(None)),
+            profile: Arc::new(Mutex::new(Config::global().profile())),
+            config_events: broadcast::channel(16).0,
+            active_streams: Arc::default(),
+            next_stream_id: Arc::default(),
+            dropped_stream_events: Arc::default(),
+            server_events: broadcast::channel(SERVER_EVENT_BUFFER).0,
+            idempotency_keys: IdempotencyKeys::new(Duration::from_secs(
+                Config::global()
+                    .get_param(IDEMPOTENCY_TTL_KEY)
+                    .unwrap_or(DEFAULT_IDEMPOTENCY_TTL),
+            )),
+            replay_buffers: Arc::default(),
+            replay_buffer_size: Config::global()
+                .get_param(REPLAY_BUFFER_SIZE_KEY)
+                .unwrap_or(DEFAULT_REPLAY_BUFFER_SIZE),
+            reconnect_window: Duration::from_secs(
+                Config::global()
+                    .get_param(RECONNECT_WINDOW_KEY)
+                    .unwrap_or(DEFAULT_RECONNECT_WINDOW),
+            ),
+            model_cache: ModelCache::new(
+                Duration::from_secs(
+                    Config::global()
+                        .get_param(MODEL_CACHE_TTL_KEY)
+                        .unwrap_or(DEFAULT_MODEL_CACHE_TTL),
+                ),
+                choose_app_strategy(APP_STRATEGY.clone())
+                    .ok()
+                    .map(|strategy| {
+                        strategy
+                            .in_state_dir(MODEL_CACHE_FILE)
+                            .unwrap_or_else(|| strategy.in_data_dir(MODEL_CACHE_FILE))
+                    }),
+            ),
+            pending_signups: Arc::default(),
+            reply_queue: Arc::new(ReplyQueue::from_config()),
+            rate_limiter: Arc::new(RateLimiter::from_config()),
+            batches: Batches::default(),
+            model_switch: Arc::default(),
         })
     }
 
@@ -39,4 +689,305 @@ impl AppState {
             .clone()
             .ok_or_else(|| anyhow::anyhow!("Scheduler not initialized"))
     }
+
+    pub async fn profile(&self) -> Option<String> {
+        self.profile.lock().await.clone()
+    }
+
+    /// Switch the config profile, or back to the base config with `None`. The agent's provider
+    /// is rebuilt from the new profile's settings so the switch takes effect without a restart.
+    pub async fn set_profile(&self, profile: Option<String>) -> Result<(), anyhow::Error> {
+        let mut guard = self.profile.lock().await;
+        let config = Config::global();
+        config.set_profile(profile.as_deref())?;
+        *guard = profile;
+        self.rebuild_provider().await
+    }
+
+    /// Replace the agent's provider with one built from the current config, if it has one
+    pub async fn rebuild_provider(&self) -> Result<(), anyhow::Error> {
+        let Some(agent) = &self.agent else {
+            return Ok(());
+        };
+        if agent.provider().await.is_err() {
+            return Ok(());
+        }
+        let config = Config::global();
+        let provider_name: String = config.get_param("GOOSE_PROVIDER")?;
+        let model: String = config.get_param("GOOSE_MODEL")?;
+        let provider = create(&provider_name, ModelConfig::new(&model)?)?;
+        agent.update_provider(provider).await
+    }
+
+    /// List a reply stream for `session_id` as active while the returned guard lives, and
+    /// report the session started. `scheduled_job_id` is the job running it, if any.
+    pub fn track_stream(&self, session_id: &str, scheduled_job_id: Option<&str>) -> StreamGuard {
+        let id = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
+        self.active_streams
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(
+                id,
+                ActiveStream {
+                    session_id: session_id.to_string(),
+                    started_at: Utc::now(),
+                },
+            );
+        self.publish_event(ServerEvent::SessionStarted {
+            session_id: session_id.to_string(),
+            scheduled_job_id: scheduled_job_id.map(str::to_string),
+        });
+        StreamGuard {
+            id,
+            streams: Arc::clone(&self.active_streams),
+            events: self.server_events.clone(),
+            queue: Arc::clone(&self.reply_queue),
+        }
+    }
+
+    /// Take a place at the back of the reply queue. `Ok(None)` when replies aren't queued, and
+    /// an error when the queue is full.
+    pub fn join_reply_queue(&self) -> Result<Option<QueueTicket>, anyhow::Error> {
+        let queue = &self.reply_queue;
+        if !queue.enabled {
+            return Ok(None);
+        }
+        let mut waiting = queue.waiting();
+        if waiting.len() >= queue.capacity {
+            return Err(anyhow::anyhow!(
+                "{} replies are already waiting for the agent",
+                waiting.len()
+            ));
+        }
+        let id = queue.next_ticket.fetch_add(1, Ordering::Relaxed);
+        waiting.push_back(id);
+        Ok(Some(QueueTicket {
+            id,
+            queue: Arc::clone(queue),
+        }))
+    }
+
+    /// Start the queued reply if it is first in line and no reply is streaming. Otherwise
+    /// returns its position in the queue, counting from 1.
+    pub fn start_queued_stream(
+        &self,
+        ticket: &QueueTicket,
+        session_id: &str,
+        scheduled_job_id: Option<&str>,
+    ) -> Result<StreamGuard, usize> {
+        let mut waiting = self.reply_queue.waiting();
+        let position = waiting
+            .iter()
+            .position(|id| *id == ticket.id)
+            .map_or(waiting.len(), |index| index + 1);
+        let busy = !self
+            .active_streams
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .is_empty();
+        if position != 1 || busy {
+            return Err(position);
+        }
+        // Listed as active before the queue is released, so the next in line sees it busy
+        waiting.pop_front();
+        let guard = self.track_stream(session_id, scheduled_job_id);
+        drop(waiting);
+        self.reply_queue.changed.send_replace(());
+        Ok(guard)
+    }
+
+    /// Whether a reply is streaming in `session_id`
+    pub fn is_streaming(&self, session_id: &str) -> bool {
+        self.active_streams
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .any(|stream| stream.session_id == session_id)
+    }
+
+    /// Reply streams in progress, oldest first
+    pub fn active_streams(&self) -> Vec<ActiveStream> {
+        let mut streams: Vec<ActiveStream> = self
+            .active_streams
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        streams.sort_by_key(|stream| stream.started_at);
+        streams
+    }
+
+    /// Start keeping the events of a new reply in `session_id`, sent live to `client`. Their
+    /// ids continue from the session's previous reply. The reply is finished when the returned
+    /// guard is dropped.
+    pub fn start_replay(&self, session_id: &str, client: mpsc::Sender<String>) -> ReplayGuard {
+        let mut buffers = self
+            .replay_buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let first_id = buffers.get(session_id).map_or(1, |buffer| buffer.next_id());
+        let buffer = Arc::new(ReplayBuffer::new(self.replay_buffer_size, first_id, client));
+        buffers.insert(session_id.to_string(), Arc::clone(&buffer));
+        ReplayGuard {
+            buffer,
+            session_id: session_id.to_string(),
+            buffers: Arc::clone(&self.replay_buffers),
+            window: self.reconnect_window,
+        }
+    }
+
+    /// The events of the latest reply in `session_id`, while it runs and for the reconnect
+    /// window after it finishes
+    pub fn replay_buffer(&self, session_id: &str) -> Option<Arc<ReplayBuffer>> {
+        self.replay_buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(session_id)
+            .cloned()
+    }
+
+    /// How long a reply keeps running while no client is connected to it
+    pub fn reconnect_window(&self) -> Duration {
+        self.reconnect_window
+    }
+
+    /// Counter of notifications dropped from reply streams since the server started
+    pub fn dropped_stream_events(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.dropped_stream_events)
+    }
+
+    pub fn subscribe_config_events(&self) -> broadcast::Receiver<ConfigEvent> {
+        self.config_events.subscribe()
+    }
+
+    /// Tell `/events` subscribers about `event`. It is lost if nobody is subscribed.
+    pub fn publish_event(&self, event: ServerEvent) {
+        let _ = self.server_events.send(event);
+    }
+
+    pub fn subscribe_server_events(&self) -> broadcast::Receiver<ServerEvent> {
+        self.server_events.subscribe()
+    }
+
+    /// Reload the config file, rebuild the provider if its settings changed and tell
+    /// subscribers. An invalid file leaves the previous values in place.
+    pub async fn reload_config(&self) -> Result<ConfigReload, ConfigError> {
+        let reload = match Config::global().reload() {
+            Ok(reload) => reload,
+            Err(e) => {
+                let _ = self.config_events.send(ConfigEvent::Invalid {
+                    error: e.to_string(),
+                });
+                return Err(e);
+            }
+        };
+
+        if reload.provider_changed {
+            if let Err(e) = self.rebuild_provider().await {
+                tracing::warn!(
+                    "Failed to rebuild the provider after a config reload: {}",
+                    e
+                );
+            }
+        }
+        if reload
+            .changed_keys
+            .iter()
+            .any(|key| LIMIT_KEYS.contains(&key.as_str()))
+        {
+            self.rate_limiter.set_limits(Limits::from_config());
+        }
+        if !reload.changed_keys.is_empty() {
+            let _ = self.config_events.send(ConfigEvent::Reloaded {
+                changed_keys: reload.changed_keys.clone(),
+                provider: reload.provider.clone(),
+                model: reload.model.clone(),
+            });
+            self.publish_event(ServerEvent::ConfigReloaded {
+                changed_keys: reload.changed_keys.clone(),
+                provider: reload.provider.clone(),
+                model: reload.model.clone(),
+            });
+        }
+        Ok(reload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::BoxFuture;
+    use std::sync::atomic::AtomicUsize;
+
+    /// A fetch returning `models` that counts its calls in `calls`
+    fn counting_fetch(
+        calls: &Arc<AtomicUsize>,
+        models: &[&str],
+    ) -> impl FnOnce() -> BoxFuture<'static, Result<Vec<String>, anyhow::Error>> + Send + 'static
+    {
+        let calls = Arc::clone(calls);
+        let models: Vec<String> = models.iter().map(|m| m.to_string()).collect();
+        move || {
+            Box::pin(async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok(models)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_share_one_fetch() {
+        let cache = ModelCache::new(Duration::from_secs(300), None);
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let requests =
+            (0..10).map(|_| cache.models("openai", false, counting_fetch(&calls, &["gpt-4o"])));
+        for models in futures::future::join_all(requests).await {
+            assert_eq!(models.unwrap(), vec!["gpt-4o"]);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Fresh lists are served from the cache until a refresh is asked for
+        cache
+            .models("openai", false, counting_fetch(&calls, &["gpt-4o"]))
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let refreshed = cache
+            .models("openai", true, counting_fetch(&calls, &["gpt-4.1"]))
+            .await
+            .unwrap();
+        assert_eq!(refreshed, vec!["gpt-4.1"]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_saved_lists_are_served_stale_and_revalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MODEL_CACHE_FILE);
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let cache = ModelCache::new(Duration::from_secs(300), Some(path.clone()));
+        cache
+            .models("anthropic", false, counting_fetch(&calls, &["claude-old"]))
+            .await
+            .unwrap();
+
+        // A restarted server serves the saved list without waiting on the provider
+        let restarted = ModelCache::new(Duration::from_secs(300), Some(path));
+        let requests = (0..5)
+            .map(|_| restarted.models("anthropic", false, counting_fetch(&calls, &["claude-new"])));
+        for models in futures::future::join_all(requests).await {
+            assert_eq!(models.unwrap(), vec!["claude-old"]);
+        }
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            restarted.lookup("anthropic"),
+            Some((vec!["claude-new".to_string()], true))
+        );
+    }
 }
@@ -1,5 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use goose::message::Message;
 use goose::token_counter::TokenCounter;
+use goose::tokenizer;
 
 fn benchmark_tokenization(c: &mut Criterion) {
     let lengths = [1_000, 5_000, 10_000, 50_000, 100_000, 124_000, 200_000];
@@ -61,10 +63,37 @@ fn benchmark_cache_performance(c: &mut Criterion) {
     });
 }
 
+fn benchmark_message_counting(c: &mut Criterion) {
+    let code = r#"
+```rust
+fn main() {
+    let values: Vec<u32> = (0..100).map(|i| i * 2).collect();
+    println!("{:?}", values.iter().sum::<u32>());
+}
+```
+"#;
+    let messages: Vec<Message> = (0..200)
+        .map(|i| {
+            if i % 2 == 0 {
+                Message::user().with_text(format!("Why does this print the wrong total?{}", code))
+            } else {
+                Message::assistant().with_text(format!("The range is off by one.{}", code))
+            }
+        })
+        .collect();
+
+    for model in ["gpt-4o", "gpt-4", "claude-sonnet-4"] {
+        c.bench_function(&format!("count_messages_{}", model), |b| {
+            b.iter(|| tokenizer::count_messages(black_box(&messages), model))
+        });
+    }
+}
+
 criterion_group!(
     benches,
     benchmark_tokenization,
     benchmark_async_tokenization,
-    benchmark_cache_performance
+    benchmark_cache_performance,
+    benchmark_message_counting
 );
 criterion_main!(benches);
@@ -2,14 +2,19 @@ use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 use futures::stream::BoxStream;
 use futures::{stream, FutureExt, Stream, StreamExt, TryStreamExt};
 use uuid::Uuid;
 
-use crate::agents::extension::{ExtensionConfig, ExtensionError, ExtensionResult, ToolInfo};
-use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
+use crate::agents::extension::{
+    ExtensionConfig, ExtensionError, ExtensionPrompt, ExtensionResult, ExtensionStatus, ToolInfo,
+};
+use crate::agents::extension_manager::{
+    get_parameter_names, normalize, resolve_prompt_command, ExtensionManager,
+};
 use crate::agents::final_output_tool::{FINAL_OUTPUT_CONTINUATION_MESSAGE, FINAL_OUTPUT_TOOL_NAME};
 use crate::agents::platform_tools::{
     PLATFORM_LIST_RESOURCES_TOOL_NAME, PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME,
@@ -30,30 +35,55 @@ use crate::agents::subagent_execution_tool::subagent_execute_task_tool::{
 use crate::agents::subagent_execution_tool::tasks_manager::TasksManager;
 use crate::agents::tool_route_manager::ToolRouteManager;
 use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
+use crate::agents::turn_timing::TurnTiming;
 use crate::agents::types::SessionConfig;
-use crate::agents::types::{FrontendTool, ToolResultReceiver};
-use crate::config::{Config, ExtensionConfigManager, PermissionManager};
+use crate::agents::types::{
+    FrontendTool, PendingConfirmations, ReplyExecutionMode, ResolvedConfirmations,
+    ToolResultReceiver,
+};
+use crate::audit::{AuditLog, AuditStatus, DecisionSource};
+use crate::config::{
+    Config, ExtensionConfigManager, ExtensionCredentialStore, PermissionManager, Persona,
+    ToolVisibilityManager,
+};
 use crate::context_mgmt::auto_compact;
-use crate::message::{push_message, Message, ToolRequest};
-use crate::permission::permission_judge::{check_tool_permissions, PermissionCheckResult};
+use crate::context_mgmt::breakdown::ContextBreakdown;
+use crate::message::{push_message, Message, MessageContent, ToolRequest};
+use crate::permission::permission_judge::{
+    check_tool_permissions, PermissionCheckResult, APPROVE_EACH_STEP_MODE,
+};
+use crate::permission::shell_command::denied_command_message;
 use crate::permission::PermissionConfirmation;
+use crate::providers;
 use crate::providers::base::Provider;
+use crate::providers::capabilities;
 use crate::providers::errors::ProviderError;
+use crate::providers::transcript::{self, ProviderTranscript};
 use crate::recipe::{Author, Recipe, Response, Settings, SubRecipe};
 use crate::scheduler_trait::SchedulerTrait;
+use crate::session::{
+    replay, resolve_attachments, resolve_image_references, ImageArtifacts, ToolResultLimiter,
+};
 use crate::tool_monitor::{ToolCall, ToolMonitor};
 use crate::utils::is_token_cancelled;
+use mcp_client::oauth::CredentialStore;
+use mcp_core::protocol::{ListResourcesResult, ReadResourceResult};
 use mcp_core::{ToolError, ToolResult};
 use regex::Regex;
-use rmcp::model::{Content, GetPromptResult, Prompt, ServerNotification, Tool};
+use rmcp::model::{Content, GetPromptResult, Prompt, ResourceContents, ServerNotification, Tool};
 use serde_json::Value;
-use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 
 use super::final_output_tool::FinalOutputTool;
 use super::platform_tools;
-use super::tool_execution::{ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE};
+use super::reply_parts::{excluded_extensions_notification, is_tool_allowed};
+use super::tool_execution::{
+    frontend_tool_timeout_from_config, ConfirmationTimeout, ToolCallResult,
+    CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE,
+};
 use crate::agents::subagent_task_config::TaskConfig;
 use crate::conversation_fixer::{debug_conversation_fix, ConversationFixer};
 
@@ -86,24 +116,43 @@ pub struct Agent {
     pub(super) tasks_manager: TasksManager,
     pub(super) final_output_tool: Arc<Mutex<Option<FinalOutputTool>>>,
     pub(super) frontend_tools: Mutex<HashMap<String, FrontendTool>>,
+    /// Frontend tools a client registered for one session, keyed by session id and tool name
+    pub(super) session_frontend_tools: Mutex<HashMap<String, HashMap<String, FrontendTool>>>,
+    /// How long to wait for the client to return a frontend tool's result; `None` waits forever
+    pub(super) frontend_tool_timeout: Option<Duration>,
     pub(super) frontend_instructions: Mutex<Option<String>>,
     pub(super) prompt_manager: Mutex<PromptManager>,
     pub(super) confirmation_tx: mpsc::Sender<(String, PermissionConfirmation)>,
     pub(super) confirmation_rx: Mutex<mpsc::Receiver<(String, PermissionConfirmation)>>,
+    pub(super) confirmation_timeout: ConfirmationTimeout,
+    pub(super) pending_confirmations: PendingConfirmations,
+    pub(super) resolved_confirmations: ResolvedConfirmations,
+    pub(super) audit_log: Arc<AuditLog>,
     pub(super) tool_result_tx: mpsc::Sender<(String, ToolResult<Vec<Content>>)>,
     pub(super) tool_result_rx: ToolResultReceiver,
     pub(super) tool_monitor: Arc<Mutex<Option<ToolMonitor>>>,
     pub(super) tool_route_manager: ToolRouteManager,
     pub(super) scheduler_service: Mutex<Option<Arc<dyn SchedulerTrait>>>,
     pub(super) retry_manager: RetryManager,
+    /// Messages describing subscribed resources that changed since the last reply
+    pub(super) resource_updates: Arc<Mutex<Vec<Message>>>,
+    /// Tasks watching subscribed resources for updates, by extension and URI
+    pub(super) resource_watchers: Mutex<HashMap<(String, String), JoinHandle<()>>>,
 }
 
 #[derive(Clone, Debug)]
 pub enum AgentEvent {
     Message(Message),
+    /// A notification tagged with the tool request it arrived during, or with the extension
+    /// name for log messages forwarded outside of a tool call
     McpNotification((String, ServerNotification)),
-    ModelChange { model: String, mode: String },
+    ModelChange {
+        model: String,
+        mode: String,
+    },
     HistoryReplaced(Vec<Message>),
+    /// Sent at the end of each turn with how long it waited on the model and ran tools
+    TurnTiming(TurnTiming),
 }
 
 impl Default for Agent {
@@ -114,6 +163,8 @@ impl Default for Agent {
 
 pub enum ToolStreamItem<T> {
     Message(ServerNotification),
+    /// Structured content of the result that follows
+    StructuredContent(Value),
     Result(T),
 }
 
@@ -124,6 +175,20 @@ pub type ToolStream = Pin<Box<dyn Stream<Item = ToolStreamItem<ToolResult<Vec<Co
 // this lets us capture all notifications emitted during the tool call for
 // simpler consumption
 pub fn tool_stream<S, F>(rx: S, done: F) -> ToolStream
+where
+    S: Stream<Item = ServerNotification> + Send + Unpin + 'static,
+    F: Future<Output = ToolResult<Vec<Content>>> + Send + 'static,
+{
+    structured_tool_stream(rx, done, None)
+}
+
+// structured_tool_stream is tool_stream for calls that may also return structured content,
+// which is yielded just before the result once the call has completed
+pub fn structured_tool_stream<S, F>(
+    rx: S,
+    done: F,
+    structured: Option<oneshot::Receiver<Value>>,
+) -> ToolStream
 where
     S: Stream<Item = ServerNotification> + Send + Unpin + 'static,
     F: Future<Output = ToolResult<Vec<Content>>> + Send + 'static,
@@ -131,6 +196,7 @@ where
     Box::pin(async_stream::stream! {
         tokio::pin!(done);
         let mut rx = rx;
+        let mut structured = structured;
 
         loop {
             tokio::select! {
@@ -138,6 +204,11 @@ where
                     yield ToolStreamItem::Message(msg);
                 }
                 r = &mut done => {
+                    if let Some(structured) = structured.take() {
+                        if let Ok(value) = structured.await {
+                            yield ToolStreamItem::StructuredContent(value);
+                        }
+                    }
                     yield ToolStreamItem::Result(r);
                     break;
                 }
@@ -146,6 +217,79 @@ where
     })
 }
 
+/// Describe the new contents of an updated resource as a user message
+fn resource_update_message(uri: &str, contents: &[ResourceContents]) -> Message {
+    let text = contents
+        .iter()
+        .map(|content| match content {
+            ResourceContents::TextResourceContents { text, .. } => text.clone(),
+            ResourceContents::BlobResourceContents { mime_type, .. } => format!(
+                "[binary content{}]",
+                mime_type
+                    .as_ref()
+                    .map(|m| format!(": {}", m))
+                    .unwrap_or_default()
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Message::user().with_text(format!("The resource {} was updated:\n{}", uri, text))
+}
+
+fn has_images(message: &Message) -> bool {
+    message
+        .content
+        .iter()
+        .any(|content| matches!(content, MessageContent::Image(_)))
+}
+
+/// `message` with its images replaced by a note, for models that can't read them
+fn without_images(message: &Message) -> Message {
+    let mut message = message.clone();
+    for content in message.content.iter_mut() {
+        if let MessageContent::Image(image) = content {
+            *content = MessageContent::text(format!(
+                "[{} image left out: the model can't read images]",
+                image.mime_type
+            ));
+        }
+    }
+    message
+}
+
+/// The provider for a persona that picks its own provider or model, or `None` when it keeps the
+/// configured one. A persona that names only a provider gets that provider's default model.
+fn create_persona_provider(persona: &Persona) -> Result<Option<Arc<dyn Provider>>> {
+    if persona.provider.is_none() && persona.model.is_none() {
+        return Ok(None);
+    }
+    let config = Config::global();
+    let provider_name = match &persona.provider {
+        Some(provider) => provider.clone(),
+        None => config.get_param::<String>("GOOSE_PROVIDER")?,
+    };
+    let model_name = match (&persona.model, &persona.provider) {
+        (Some(model), _) => model.clone(),
+        (None, Some(_)) => providers::providers()
+            .into_iter()
+            .find(|metadata| metadata.name == provider_name)
+            .map(|metadata| metadata.default_model)
+            .ok_or_else(|| anyhow!("Unknown provider {}", provider_name))?,
+        (None, None) => config.get_param::<String>("GOOSE_MODEL")?,
+    };
+    providers::create_with_model(&provider_name, &model_name)
+        .map(Some)
+        .map_err(|e| {
+            anyhow!(
+                "Persona {} could not use {}/{}: {}",
+                persona.name,
+                provider_name,
+                model_name,
+                e
+            )
+        })
+}
+
 impl Agent {
     pub fn new() -> Self {
         // Create channels with buffer size 32 (adjust if needed)
@@ -162,19 +306,38 @@ impl Agent {
             tasks_manager: TasksManager::new(),
             final_output_tool: Arc::new(Mutex::new(None)),
             frontend_tools: Mutex::new(HashMap::new()),
+            session_frontend_tools: Mutex::new(HashMap::new()),
+            frontend_tool_timeout: frontend_tool_timeout_from_config(),
             frontend_instructions: Mutex::new(None),
             prompt_manager: Mutex::new(PromptManager::new()),
             confirmation_tx: confirm_tx,
             confirmation_rx: Mutex::new(confirm_rx),
+            confirmation_timeout: ConfirmationTimeout::from_config(),
+            pending_confirmations: PendingConfirmations::default(),
+            resolved_confirmations: ResolvedConfirmations::default(),
+            audit_log: AuditLog::global(),
             tool_result_tx: tool_tx,
             tool_result_rx: Arc::new(Mutex::new(tool_rx)),
             tool_monitor,
             tool_route_manager: ToolRouteManager::new(),
             scheduler_service: Mutex::new(None),
             retry_manager,
+            resource_updates: Arc::new(Mutex::new(Vec::new())),
+            resource_watchers: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Record tool calls in `audit_log` instead of the process-wide one
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// The audit log this agent records its tool calls in
+    pub fn audit_log(&self) -> Arc<AuditLog> {
+        self.audit_log.clone()
+    }
+
     pub async fn configure_tool_monitor(&self, max_repetitions: Option<u32>) {
         let mut tool_monitor = self.tool_monitor.lock().await;
         *tool_monitor = Some(ToolMonitor::new(max_repetitions));
@@ -230,7 +393,8 @@ impl Agent {
         let initial_messages = messages.clone();
         let config = Config::global();
 
-        let (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+        let (tools, toolshim_tools, system_prompt) =
+            self.prepare_tools_and_prompt(session.as_ref()).await?;
         let goose_mode = Self::determine_goose_mode(session.as_ref(), config);
 
         Ok(ReplyContext {
@@ -248,12 +412,13 @@ impl Agent {
         &self,
         response: &Message,
         tools: &[rmcp::model::Tool],
+        session_id: Option<&str>,
     ) -> ToolCategorizeResult {
         let (readonly_tools, regular_tools) = Self::categorize_tools_by_annotation(tools);
 
         // Categorize tool requests
         let (frontend_requests, remaining_requests, filtered_response) =
-            self.categorize_tool_requests(response).await;
+            self.categorize_tool_requests(response, session_id).await;
 
         ToolCategorizeResult {
             frontend_requests,
@@ -269,12 +434,31 @@ impl Agent {
         permission_check_result: &PermissionCheckResult,
         message_tool_response: Arc<Mutex<Message>>,
         cancel_token: Option<tokio_util::sync::CancellationToken>,
+        session_id: Option<String>,
     ) -> Result<Vec<(String, ToolStream)>> {
         let mut tool_futures: Vec<(String, ToolStream)> = Vec::new();
+        let decided_by = |request: &ToolRequest| {
+            if permission_check_result
+                .matched_rules
+                .contains_key(&request.id)
+            {
+                DecisionSource::Rule
+            } else {
+                DecisionSource::Policy
+            }
+        };
 
         // Handle pre-approved and read-only tools
         for request in &permission_check_result.approved {
             if let Ok(tool_call) = request.tool_call.clone() {
+                self.audit_log.begin(
+                    session_id.clone(),
+                    &request.id,
+                    &tool_call.name,
+                    &tool_call.arguments,
+                    decided_by(request),
+                    None,
+                );
                 let (req_id, tool_result) = self
                     .dispatch_tool_call(tool_call, request.id.clone(), cancel_token.clone())
                     .await;
@@ -282,12 +466,7 @@ impl Agent {
                 tool_futures.push((
                     req_id,
                     match tool_result {
-                        Ok(result) => tool_stream(
-                            result
-                                .notification_stream
-                                .unwrap_or_else(|| Box::new(stream::empty())),
-                            result.result,
-                        ),
+                        Ok(result) => result.into_stream(),
                         Err(e) => {
                             tool_stream(Box::new(stream::empty()), futures::future::ready(Err(e)))
                         }
@@ -298,11 +477,29 @@ impl Agent {
 
         // Handle denied tools
         for request in &permission_check_result.denied {
+            let denied_pattern = permission_check_result.denied_commands.get(&request.id);
+            if let Ok(tool_call) = &request.tool_call {
+                self.audit_log.record_denied(
+                    session_id.clone(),
+                    &request.id,
+                    &tool_call.name,
+                    &tool_call.arguments,
+                    if denied_pattern.is_some() {
+                        DecisionSource::Rule
+                    } else {
+                        decided_by(request)
+                    },
+                    denied_pattern.map(String::as_str),
+                );
+            }
+            let result = match denied_pattern {
+                Some(pattern) => Err(ToolError::ExecutionError(denied_command_message(pattern))),
+                None => Ok(vec![rmcp::model::Content::text(DECLINED_RESPONSE)]),
+            };
             let mut response = message_tool_response.lock().await;
-            *response = response.clone().with_tool_response(
-                request.id.clone(),
-                Ok(vec![rmcp::model::Content::text(DECLINED_RESPONSE)]),
-            );
+            *response = response
+                .clone()
+                .with_tool_response(request.id.clone(), result);
         }
 
         Ok(tool_futures)
@@ -326,6 +523,17 @@ impl Agent {
         }
     }
 
+    /// The provider to reply with: the persona's when it picks a model, otherwise the agent's
+    async fn reply_provider(
+        &self,
+        persona_provider: &Option<Arc<dyn Provider>>,
+    ) -> Result<Arc<dyn Provider>, anyhow::Error> {
+        match persona_provider {
+            Some(provider) => Ok(Arc::clone(provider)),
+            None => self.provider().await,
+        }
+    }
+
     /// Check if a tool is a frontend tool
     pub async fn is_frontend_tool(&self, name: &str) -> bool {
         self.frontend_tools.lock().await.contains_key(name)
@@ -336,6 +544,64 @@ impl Agent {
         self.frontend_tools.lock().await.get(name).cloned()
     }
 
+    /// Check if a tool is a frontend tool, either for every session or registered for
+    /// `session_id`
+    pub(crate) async fn is_session_frontend_tool(
+        &self,
+        session_id: Option<&str>,
+        name: &str,
+    ) -> bool {
+        if self.is_frontend_tool(name).await {
+            return true;
+        }
+        let Some(session_id) = session_id else {
+            return false;
+        };
+        self.session_frontend_tools
+            .lock()
+            .await
+            .get(session_id)
+            .is_some_and(|tools| tools.contains_key(name))
+    }
+
+    /// Offer `tools` to the model in `session_id` only, to be run by the client. They replace
+    /// the tools previously registered for the session.
+    pub async fn register_frontend_tools(&self, session_id: &str, tools: Vec<Tool>) {
+        let tools: HashMap<String, FrontendTool> = tools
+            .into_iter()
+            .map(|tool| {
+                let name = tool.name.to_string();
+                (name.clone(), FrontendTool { name, tool })
+            })
+            .collect();
+        let mut sessions = self.session_frontend_tools.lock().await;
+        if tools.is_empty() {
+            sessions.remove(session_id);
+        } else {
+            sessions.insert(session_id.to_string(), tools);
+        }
+    }
+
+    /// Forget the frontend tools registered for `session_id`, returning whether there were any
+    pub async fn unregister_frontend_tools(&self, session_id: &str) -> bool {
+        self.session_frontend_tools
+            .lock()
+            .await
+            .remove(session_id)
+            .is_some()
+    }
+
+    /// The frontend tools registered for `session_id`, sorted by name
+    pub async fn session_frontend_tools(&self, session_id: &str) -> Vec<Tool> {
+        let sessions = self.session_frontend_tools.lock().await;
+        let mut tools: Vec<Tool> = sessions
+            .get(session_id)
+            .map(|tools| tools.values().map(|tool| tool.tool.clone()).collect())
+            .unwrap_or_default();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+
     pub async fn add_final_output_tool(&self, response: Response) {
         let mut final_output_tool = self.final_output_tool.lock().await;
         let created_final_output_tool = FinalOutputTool::new(response);
@@ -371,6 +637,17 @@ impl Agent {
             }
         }
 
+        // Disabled tools are not offered to the model, but it may still try to call them
+        if !ToolVisibilityManager::is_enabled(&tool_call.name) {
+            return (
+                request_id,
+                Err(ToolError::ExecutionError(format!(
+                    "Tool '{}' has been disabled by the user",
+                    tool_call.name
+                ))),
+            );
+        }
+
         if tool_call.name == PLATFORM_MANAGE_SCHEDULE_TOOL_NAME {
             let result = self
                 .handle_schedule_management(tool_call.arguments, request_id.clone())
@@ -468,25 +745,16 @@ impl Agent {
             }
         } else {
             // Clone the result to ensure no references to extension_manager are returned
+            // The tool request id doubles as the progress token, so progress maps back to it
             let result = extension_manager
-                .dispatch_tool_call(tool_call.clone())
+                .dispatch_tool_call_with_progress(tool_call.clone(), Some(request_id.clone()))
                 .await;
             result.unwrap_or_else(|e| {
                 ToolCallResult::from(Err(ToolError::ExecutionError(e.to_string())))
             })
         };
 
-        (
-            request_id,
-            Ok(ToolCallResult {
-                notification_stream: result.notification_stream,
-                result: Box::new(
-                    result
-                        .result
-                        .map(super::large_response_handler::process_tool_response),
-                ),
-            }),
-        )
+        (request_id, Ok(result))
     }
 
     pub(super) async fn manage_extensions(
@@ -711,6 +979,18 @@ impl Agent {
         extension_manager.remove_extension(name).await?;
         drop(extension_manager);
 
+        let sanitized_name = normalize(name.to_string());
+        self.resource_watchers
+            .lock()
+            .await
+            .retain(|(extension, _), watcher| {
+                let keep = *extension != sanitized_name;
+                if !keep {
+                    watcher.abort();
+                }
+                keep
+            });
+
         // If vector tool selection is enabled, remove tools from the index
         let selector = self.tool_route_manager.get_router_tool_selector().await;
         if ToolRouterIndexManager::is_tool_router_enabled(&selector) {
@@ -737,6 +1017,142 @@ impl Agent {
             .expect("Failed to list extensions")
     }
 
+    /// Connection state and tool count of each running extension
+    pub async fn get_extension_statuses(&self) -> Vec<ExtensionStatus> {
+        let extension_manager = self.extension_manager.read().await;
+        extension_manager.get_extension_statuses().await
+    }
+
+    /// List one page of the resources offered by a running extension
+    pub async fn list_extension_resources(
+        &self,
+        name: &str,
+        cursor: Option<String>,
+    ) -> ExtensionResult<ListResourcesResult> {
+        let extension_manager = self.extension_manager.read().await;
+        extension_manager
+            .list_extension_resources(name, cursor)
+            .await
+    }
+
+    /// Read a resource from a running extension
+    pub async fn read_extension_resource(
+        &self,
+        name: &str,
+        uri: &str,
+    ) -> ExtensionResult<ReadResourceResult> {
+        let client = self.extension_manager.read().await.resource_client(name)?;
+        Ok(client.read_resource(uri).await?)
+    }
+
+    /// Subscribe to a resource of a running extension
+    ///
+    /// Each update re-reads the resource; with `inject_updates` the new contents are added to the
+    /// conversation as a user message at the start of the next reply.
+    pub async fn subscribe_resource(
+        &self,
+        name: &str,
+        uri: &str,
+        inject_updates: bool,
+    ) -> ExtensionResult<()> {
+        let mut notifications = self
+            .extension_manager
+            .write()
+            .await
+            .subscribe_resource(name, uri)
+            .await?;
+
+        let key = (normalize(name.to_string()), uri.to_string());
+        let extension_manager = Arc::clone(&self.extension_manager);
+        let resource_updates = Arc::clone(&self.resource_updates);
+        let name = name.to_string();
+        let uri = uri.to_string();
+        let watcher = tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                let ServerNotification::ResourceUpdatedNotification(notification) = notification
+                else {
+                    continue;
+                };
+                if notification.params.uri != uri {
+                    continue;
+                }
+
+                let client = {
+                    let extension_manager = extension_manager.read().await;
+                    if !extension_manager.is_subscribed(&name, &uri) {
+                        break;
+                    }
+                    extension_manager.resource_client(&name)
+                };
+                // The extension manager isn't held while the extension answers
+                let result = match client {
+                    Ok(client) => client
+                        .read_resource(&uri)
+                        .await
+                        .map_err(ExtensionError::from),
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(result) if inject_updates => {
+                        resource_updates
+                            .lock()
+                            .await
+                            .push(resource_update_message(&uri, &result.contents));
+                    }
+                    Ok(_) => debug!("Resource {} from {} was updated", uri, name),
+                    Err(e) => {
+                        tracing::warn!("Failed to re-read updated resource {}: {}", uri, e)
+                    }
+                }
+            }
+        });
+        // Subscribing again replaces the watcher, so each update is read once
+        if let Some(previous) = self.resource_watchers.lock().await.insert(key, watcher) {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    pub async fn unsubscribe_resource(&self, name: &str, uri: &str) -> ExtensionResult<()> {
+        let key = (normalize(name.to_string()), uri.to_string());
+        if let Some(watcher) = self.resource_watchers.lock().await.remove(&key) {
+            watcher.abort();
+        }
+        let mut extension_manager = self.extension_manager.write().await;
+        extension_manager.unsubscribe_resource(name, uri).await
+    }
+
+    /// Run the OAuth flow for a configured remote extension
+    ///
+    /// The credentials are stored per extension; a running extension is reconnected so that it
+    /// picks up the new token.
+    pub async fn authorize_extension(&self, name: &str) -> ExtensionResult<()> {
+        let config = ExtensionConfigManager::get_config_by_name(name)
+            .ok()
+            .flatten()
+            .ok_or_else(|| ExtensionError::NotConfigured(name.to_string()))?;
+        let uri = match &config {
+            ExtensionConfig::Sse { uri, .. } | ExtensionConfig::StreamableHttp { uri, .. } => {
+                uri.clone()
+            }
+            _ => return Err(ExtensionError::AuthorizationNotSupported(name.to_string())),
+        };
+
+        let credentials = mcp_client::oauth::authorize_server(&uri)
+            .await
+            .map_err(|e| ExtensionError::Authorization(e.to_string()))?;
+        ExtensionCredentialStore::new(&config.name()).save(&credentials);
+
+        let running = self.extension_manager.read().await.is_running(name);
+        if running {
+            self.remove_extension(name)
+                .await
+                .map_err(|e| ExtensionError::SetupError(e.to_string()))?;
+            self.add_extension(config).await?;
+        }
+        Ok(())
+    }
+
     /// Handle a confirmation response for a tool request
     pub async fn handle_confirmation(
         &self,
@@ -752,8 +1168,10 @@ impl Agent {
     async fn handle_auto_compaction(
         &self,
         messages: &[Message],
+        session_id: Option<&str>,
     ) -> Result<Option<(Vec<Message>, String)>> {
-        let compact_result = auto_compact::check_and_compact_messages(self, messages, None).await?;
+        let compact_result =
+            auto_compact::check_and_compact_messages(self, messages, session_id, None).await?;
 
         if compact_result.compacted {
             let compacted_messages = compact_result.messages;
@@ -778,15 +1196,43 @@ impl Agent {
         Ok(None)
     }
 
-    #[instrument(skip(self, unfixed_messages, session), fields(user_message))]
+    #[instrument(skip(self, unfixed_messages, session), fields(user_message, persona))]
     pub async fn reply(
         &self,
         unfixed_messages: &[Message],
         session: Option<SessionConfig>,
         cancel_token: Option<CancellationToken>,
     ) -> Result<BoxStream<'_, Result<AgentEvent>>> {
+        // Extensions that stopped answering pings are restarted before they are used again
+        self.extension_manager
+            .write()
+            .await
+            .restart_degraded_extensions()
+            .await;
+
+        // Servers see the session's working directory as their root
+        if let Some(session) = &session {
+            let mut extension_manager = self.extension_manager.write().await;
+            extension_manager
+                .set_working_dir(session.working_dir.clone())
+                .await;
+        }
+
+        // Updates to subscribed resources join the conversation ahead of the reply
+        let resource_updates = std::mem::take(&mut *self.resource_updates.lock().await);
+        let with_updates: Vec<Message>;
+        let unfixed_messages = if resource_updates.is_empty() {
+            unfixed_messages
+        } else {
+            with_updates = [unfixed_messages, resource_updates.as_slice()].concat();
+            &with_updates
+        };
+
         // Handle auto-compaction before processing
-        let (messages, compaction_msg) = match self.handle_auto_compaction(unfixed_messages).await?
+        let session_id = session.as_ref().and_then(|s| s.id.session_id());
+        let (messages, compaction_msg) = match self
+            .handle_auto_compaction(unfixed_messages, session_id.as_deref())
+            .await?
         {
             Some((compacted_messages, msg)) => (compacted_messages, Some(msg)),
             None => {
@@ -800,6 +1246,9 @@ impl Agent {
         // If we compacted, yield the compaction message and history replacement event
         if let Some(compaction_msg) = compaction_msg {
             return Ok(Box::pin(async_stream::try_stream! {
+                for update in resource_updates {
+                    yield AgentEvent::Message(update);
+                }
                 yield AgentEvent::Message(Message::assistant().with_text(compaction_msg));
                 yield AgentEvent::HistoryReplaced(messages.clone());
 
@@ -812,7 +1261,18 @@ impl Agent {
         }
 
         // No compaction needed, proceed with normal processing
-        self.reply_internal(&messages, session, cancel_token).await
+        let reply_stream = self
+            .reply_internal(&messages, session, cancel_token)
+            .await?;
+        if resource_updates.is_empty() {
+            return Ok(reply_stream);
+        }
+        let update_events = stream::iter(
+            resource_updates
+                .into_iter()
+                .map(|message| Ok(AgentEvent::Message(message))),
+        );
+        Ok(Box::pin(update_events.chain(reply_stream)))
     }
 
     /// Main reply method that handles the actual agent processing
@@ -843,8 +1303,43 @@ impl Agent {
             debug!("user_message" = &content);
         }
 
+        let persona = session.as_ref().and_then(|s| s.persona.as_ref());
+        if let Some(persona) = persona {
+            reply_span.record("persona", persona.name.as_str());
+        }
+        let persona_provider = match persona {
+            Some(persona) => create_persona_provider(persona)?,
+            None => None,
+        };
+        let provider_name = persona
+            .and_then(|persona| persona.provider.clone())
+            .or_else(|| config.get_param::<String>("GOOSE_PROVIDER").ok());
+        let persona_rules = persona
+            .map(|persona| persona.permission_rules.clone())
+            .unwrap_or_default();
+        let tool_result_limiter = ToolResultLimiter::for_session(session.as_ref().map(|s| &s.id));
+        let image_artifacts = ImageArtifacts::for_session(session.as_ref().map(|s| &s.id));
+        let session_id = session.as_ref().and_then(|s| s.id.session_id());
+        let unattended = session.as_ref().and_then(|s| s.execution_mode.as_deref())
+            == Some(ReplyExecutionMode::Unattended.as_str());
+        let allowed_extensions = session.as_ref().and_then(|s| s.allowed_extensions.clone());
+        let recorded_tool_results = session
+            .as_ref()
+            .and_then(|s| s.recorded_tool_results.clone());
+        let permissions_path = session.as_ref().and_then(|s| s.permissions_path.clone());
+        let excluded_extensions = match &allowed_extensions {
+            Some(allowed) => self.excluded_extensions(allowed).await,
+            None => Vec::new(),
+        };
+
         Ok(Box::pin(async_stream::try_stream! {
             let _ = reply_span.enter();
+            if !excluded_extensions.is_empty() {
+                yield AgentEvent::McpNotification((
+                    "goose".to_string(),
+                    excluded_extensions_notification(&excluded_extensions),
+                ));
+            }
             let mut turns_taken = 0u32;
             let max_turns = session
                 .as_ref()
@@ -858,6 +1353,12 @@ impl Agent {
                     break;
                 }
 
+                // Warnings and errors logged by extensions since the last turn
+                let log_alerts = self.extension_manager.read().await.take_log_alerts();
+                for (extension_name, notification) in log_alerts {
+                    yield AgentEvent::McpNotification((extension_name, notification));
+                }
+
                 if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
                     if final_output_tool.final_output.is_some() {
                         let final_event = AgentEvent::Message(
@@ -876,19 +1377,58 @@ impl Agent {
                     break;
                 }
 
-                let mut stream = Self::stream_response_from_provider(
-                    self.provider().await?,
-                    &system_prompt,
-                    &messages,
-                    &tools,
-                    &toolshim_tools,
-                ).await?;
+                let provider = self.reply_provider(&persona_provider).await?;
+                let model_name = provider.get_active_model_name();
+                let capabilities = capabilities::resolve(provider_name.as_deref(), &model_name);
+                let mut provider_messages = resolve_image_references(
+                    session_id.as_deref(),
+                    &resolve_attachments(session_id.as_deref(), &messages),
+                );
+                if !capabilities.supports_vision {
+                    if provider_messages.last().is_some_and(has_images) {
+                        yield AgentEvent::Message(Message::assistant().with_text(format!(
+                            "{} can't read images. Send the message without them or switch to a model that supports images.",
+                            model_name
+                        )));
+                        break;
+                    }
+                    provider_messages = provider_messages.iter().map(without_images).collect();
+                }
+
+                let request_start = Instant::now();
+                let mut time_to_first_token = None;
+                let mut tool_time = Duration::ZERO;
+                // Kept until the end of the turn so the transcript has the whole response
+                let transcript = session_id.as_deref().and_then(|id| {
+                    ProviderTranscript::begin(
+                        id,
+                        replay::user_turns(&messages).len().saturating_sub(1),
+                        provider_name.as_deref(),
+                        &model_name,
+                    )
+                });
+                let stream = transcript::scope(
+                    transcript.clone(),
+                    Self::stream_response_from_provider(
+                        provider,
+                        &capabilities,
+                        &system_prompt,
+                        &provider_messages,
+                        &tools,
+                        &toolshim_tools,
+                    ),
+                ).await;
+                if let (Err(e), Some(transcript)) = (&stream, &transcript) {
+                    transcript.record_error(&e.to_string());
+                }
+                let mut stream = transcript::scope_stream(transcript.clone(), stream?);
 
                 let mut added_message = false;
                 let mut messages_to_add = Vec::new();
                 let mut tools_updated = false;
 
                 while let Some(next) = stream.next().await {
+                    time_to_first_token.get_or_insert_with(|| request_start.elapsed());
                     if is_token_cancelled(&cancel_token) {
                         break;
                     }
@@ -896,7 +1436,7 @@ impl Agent {
                     match next {
                         Ok((response, usage)) => {
                             // Emit model change event if provider is lead-worker
-                            let provider = self.provider().await?;
+                            let provider = self.reply_provider(&persona_provider).await?;
                             if let Some(lead_worker) = provider.as_lead_worker() {
                                 if let Some(ref usage) = usage {
                                     let active_model = usage.model.clone();
@@ -916,6 +1456,10 @@ impl Agent {
                                 }
                             }
 
+                            if usage.as_ref().is_some_and(|usage| usage.truncated) {
+                                warn!("The reply was cut off at the output token limit");
+                            }
+
                             // Record usage for the session
                             if let Some(ref session_config) = &session {
                                 if let Some(ref usage) = usage {
@@ -931,7 +1475,17 @@ impl Agent {
                                     filtered_response,
                                     readonly_tools,
                                     regular_tools,
-                                } = self.categorize_tools(&response, &tools).await;
+                                } = self.categorize_tools(&response, &tools, session_id.as_deref()).await;
+                                // The model may still name a tool it was not offered
+                                let (remaining_requests, blocked_requests): (Vec<_>, Vec<_>) =
+                                    remaining_requests.into_iter().partition(|request| {
+                                        match (&request.tool_call, &allowed_extensions) {
+                                            (Ok(tool_call), Some(allowed)) => {
+                                                is_tool_allowed(&tool_call.name, allowed)
+                                            }
+                                            _ => true,
+                                        }
+                                    });
                                 let requests_to_record: Vec<ToolRequest> = frontend_requests.iter().chain(remaining_requests.iter()).cloned().collect();
                                 self.tool_route_manager
                                     .record_tool_requests(&requests_to_record)
@@ -940,10 +1494,11 @@ impl Agent {
                                 yield AgentEvent::Message(filtered_response.clone());
                                 tokio::task::yield_now().await;
 
-                                let num_tool_requests = frontend_requests.len() + remaining_requests.len();
+                                let num_tool_requests = frontend_requests.len() + remaining_requests.len() + blocked_requests.len();
                                 if num_tool_requests == 0 {
                                     continue;
                                 }
+                                let tool_start = Instant::now();
 
                                 let message_tool_response = Arc::new(Mutex::new(Message::user().with_id(
                                     format!("msg_{}", Uuid::new_v4())
@@ -958,8 +1513,33 @@ impl Agent {
                                     yield AgentEvent::Message(msg);
                                 }
 
+                                for request in &blocked_requests {
+                                    if let Ok(tool_call) = &request.tool_call {
+                                        let mut response = message_tool_response.lock().await;
+                                        *response = response.clone().with_tool_response(
+                                            request.id.clone(),
+                                            Err(ToolError::NotFound(format!(
+                                                "Tool '{}' is not available in this session",
+                                                tool_call.name
+                                            ))),
+                                        );
+                                    }
+                                }
+
                                 let mode = goose_mode.clone();
-                                if mode.as_str() == "chat" {
+                                if let Some(recorded) = &recorded_tool_results {
+                                    // A replay answers with the original session's results
+                                    // instead of running the tools again
+                                    for request in remaining_requests {
+                                        if let Ok(tool_call) = &request.tool_call {
+                                            let mut response = message_tool_response.lock().await;
+                                            *response = response.clone().with_tool_response(
+                                                request.id.clone(),
+                                                recorded.take(tool_call),
+                                            );
+                                        }
+                                    }
+                                } else if mode.as_str() == "chat" {
                                     // Skip all tool calls in chat mode
                                     for request in remaining_requests {
                                         let mut response = message_tool_response.lock().await;
@@ -969,7 +1549,11 @@ impl Agent {
                                         );
                                     }
                                 } else {
-                                    let mut permission_manager = PermissionManager::default();
+                                    let mut permission_manager = permissions_path
+                                        .as_ref()
+                                        .map(PermissionManager::new)
+                                        .unwrap_or_default();
+                                    permission_manager.set_session_rules(persona_rules.clone());
                                     let (permission_check_result, enable_extension_request_ids) =
                                         check_tool_permissions(
                                             &remaining_requests,
@@ -977,13 +1561,14 @@ impl Agent {
                                             readonly_tools.clone(),
                                             regular_tools.clone(),
                                             &mut permission_manager,
-                                            self.provider().await?,
+                                            self.reply_provider(&persona_provider).await?,
                                         ).await;
 
                                     let mut tool_futures = self.handle_approved_and_denied_tools(
                                         &permission_check_result,
                                         message_tool_response.clone(),
-                                        cancel_token.clone()
+                                        cancel_token.clone(),
+                                        session_id.clone(),
                                     ).await?;
 
                                     let tool_futures_arc = Arc::new(Mutex::new(tool_futures));
@@ -991,14 +1576,18 @@ impl Agent {
                                     // Process tools requiring approval
                                     let mut tool_approval_stream = self.handle_approval_tool_requests(
                                         &permission_check_result.needs_approval,
+                                        &permission_check_result.matched_rules,
                                         tool_futures_arc.clone(),
                                         &mut permission_manager,
                                         message_tool_response.clone(),
                                         cancel_token.clone(),
+                                        session_id.clone(),
+                                        unattended,
+                                        mode == APPROVE_EACH_STEP_MODE,
                                     );
 
-                                    while let Some(msg) = tool_approval_stream.try_next().await? {
-                                        yield AgentEvent::Message(msg);
+                                    while let Some(event) = tool_approval_stream.try_next().await? {
+                                        yield event;
                                     }
 
                                     tool_futures = {
@@ -1006,6 +1595,10 @@ impl Agent {
                                         futures_lock.drain(..).collect::<Vec<_>>()
                                     };
 
+                                    let running_request_ids: Vec<String> = tool_futures
+                                        .iter()
+                                        .map(|(request_id, _)| request_id.clone())
+                                        .collect();
                                     let with_id = tool_futures
                                         .into_iter()
                                         .map(|(request_id, stream)| {
@@ -1015,6 +1608,9 @@ impl Agent {
 
                                     let mut combined = stream::select_all(with_id);
                                     let mut all_install_successful = true;
+                                    let mut structured_contents = HashMap::new();
+                                    // The calls run concurrently once polled, so each is timed from here
+                                    let dispatched_at = Instant::now();
 
                                     while let Some((request_id, item)) = combined.next().await {
                                         if is_token_cancelled(&cancel_token) {
@@ -1027,9 +1623,34 @@ impl Agent {
                                                 {
                                                     all_install_successful = false;
                                                 }
+                                                self.audit_log.finish(
+                                                    &request_id,
+                                                    if output.is_ok() { AuditStatus::Success } else { AuditStatus::Error },
+                                                );
+                                                let structured_content = structured_contents.remove(&request_id);
+                                                let (output, structured_content) = match output {
+                                                    Ok(contents) => {
+                                                        // Large images are saved as artifacts before the size limit applies
+                                                        let contents = match &image_artifacts {
+                                                            Some(artifacts) => artifacts.persist(contents),
+                                                            None => contents,
+                                                        };
+                                                        let (contents, structured_content) = tool_result_limiter
+                                                            .limit(contents, structured_content);
+                                                        (Ok(contents), structured_content)
+                                                    }
+                                                    Err(e) => (Err(e), structured_content),
+                                                };
                                                 let mut response = message_tool_response.lock().await;
-                                                *response =
-                                                    response.clone().with_tool_response(request_id, output);
+                                                *response = response.clone().with_timed_tool_response(
+                                                    request_id,
+                                                    output,
+                                                    structured_content,
+                                                    dispatched_at.elapsed(),
+                                                );
+                                            }
+                                            ToolStreamItem::StructuredContent(value) => {
+                                                structured_contents.insert(request_id, value);
                                             }
                                             ToolStreamItem::Message(msg) => {
                                                 yield AgentEvent::McpNotification((
@@ -1039,12 +1660,18 @@ impl Agent {
                                         }
                                     }
 
+                                    // Calls that did not finish were cancelled
+                                    for request_id in &running_request_ids {
+                                        self.audit_log.finish(request_id, AuditStatus::Cancelled);
+                                    }
+
                                     if all_install_successful {
                                         tools_updated = true;
                                     }
                                 }
 
                                 let final_message_tool_resp = message_tool_response.lock().await.clone();
+                                tool_time += tool_start.elapsed();
                                 yield AgentEvent::Message(final_message_tool_resp.clone());
 
                                 added_message = true;
@@ -1052,12 +1679,22 @@ impl Agent {
                                 push_message(&mut messages_to_add, final_message_tool_resp);
                             }
                         }
-                        Err(ProviderError::ContextLengthExceeded(_)) => {
-                            yield AgentEvent::Message(Message::assistant().with_context_length_exceeded(
+                        Err(ProviderError::ContextLengthExceeded(provider_error)) => {
+                            let model = self.reply_provider(&persona_provider).await?.get_model_config();
+                            let breakdown = ContextBreakdown::new(&model, &system_prompt, &tools, &messages, &provider_error);
+                            yield AgentEvent::Message(Message::assistant().with_content(
+                                MessageContent::context_length_exceeded_with_breakdown(
                                     "The context length of the model has been exceeded. Please start a new session and try again.",
-                                ));
+                                    breakdown,
+                                ),
+                            ));
                             break;
                         }
+                        Err(e @ (ProviderError::ModelNotFound(_) | ProviderError::AuthenticationFailed { .. })) => {
+                            error!("Error: {}", e);
+                            // Retrying won't help, so this ends the reply as an error the client can act on
+                            Err::<(), _>(anyhow::Error::new(e))?;
+                        }
                         Err(e) => {
                             error!("Error: {}", e);
                             yield AgentEvent::Message(Message::assistant().with_text(
@@ -1067,8 +1704,13 @@ impl Agent {
                         }
                     }
                 }
+                yield AgentEvent::TurnTiming(TurnTiming {
+                    time_to_first_token,
+                    model_time: request_start.elapsed().saturating_sub(tool_time),
+                    tool_time,
+                });
                 if tools_updated {
-                    (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt().await?;
+                    (tools, toolshim_tools, system_prompt) = self.prepare_tools_and_prompt(session.as_ref()).await?;
                 }
                 if !added_message {
                     if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
@@ -1115,12 +1757,26 @@ impl Agent {
         match mode {
             Some("foreground") => "auto".to_string(),
             Some("background") => "chat".to_string(),
-            _ => config
-                .get_param("GOOSE_MODE")
-                .unwrap_or_else(|_| "auto".to_string()),
+            // A plan reply has no tools, and any call the model makes up anyway is skipped
+            Some("plan") => "chat".to_string(),
+            Some(APPROVE_EACH_STEP_MODE) => APPROVE_EACH_STEP_MODE.to_string(),
+            _ => session
+                .and_then(|s| s.goose_mode.clone())
+                .unwrap_or_else(|| {
+                    config
+                        .get_param("GOOSE_MODE")
+                        .unwrap_or_else(|_| "auto".to_string())
+                }),
         }
     }
 
+    /// The turn limit for sessions that don't set their own
+    pub fn default_max_turns() -> u32 {
+        Config::global()
+            .get_param("GOOSE_MAX_TURNS")
+            .unwrap_or(DEFAULT_MAX_TURNS)
+    }
+
     /// Extend the system prompt with one line of additional instruction
     pub async fn extend_system_prompt(&self, instruction: String) {
         let mut prompt_manager = self.prompt_manager.lock().await;
@@ -1158,6 +1814,24 @@ impl Agent {
         prompt_manager.set_system_prompt_override(template);
     }
 
+    /// Remove a system prompt override, restoring the default template
+    pub async fn clear_system_prompt_override(&self) {
+        let mut prompt_manager = self.prompt_manager.lock().await;
+        prompt_manager.clear_system_prompt_override();
+    }
+
+    /// Set (or clear) custom instructions appended to the end of the system prompt
+    pub async fn set_system_prompt_append(&self, instructions: Option<String>) {
+        let mut prompt_manager = self.prompt_manager.lock().await;
+        prompt_manager.set_system_prompt_append(instructions);
+    }
+
+    /// The system prompt the next reply will use, including extension instructions
+    pub async fn get_system_prompt(&self) -> Result<String> {
+        let (_, _, system_prompt) = self.prepare_tools_and_prompt(None).await?;
+        Ok(system_prompt)
+    }
+
     pub async fn list_extension_prompts(&self) -> HashMap<String, Vec<Prompt>> {
         let extension_manager = self.extension_manager.read().await;
         extension_manager
@@ -1166,26 +1840,39 @@ impl Agent {
             .expect("Failed to list prompts")
     }
 
+    /// Prompts from all extensions, named as they can be passed to [`Agent::get_prompt`]
+    pub async fn list_prompt_commands(&self) -> Result<Vec<ExtensionPrompt>> {
+        let extension_manager = self.extension_manager.read().await;
+        extension_manager
+            .list_prompt_commands()
+            .await
+            .map_err(|e| anyhow!("Failed to list prompts: {}", e))
+    }
+
+    /// Render a prompt by name; prompts offered by several extensions need an `extension/` prefix
     pub async fn get_prompt(&self, name: &str, arguments: Value) -> Result<GetPromptResult> {
         let extension_manager = self.extension_manager.read().await;
 
-        // First find which extension has this prompt
-        let prompts = extension_manager
-            .list_prompts()
+        let commands = extension_manager
+            .list_prompt_commands()
             .await
             .map_err(|e| anyhow!("Failed to list prompts: {}", e))?;
 
-        if let Some(extension) = prompts
-            .iter()
-            .find(|(_, prompt_list)| prompt_list.iter().any(|p| p.name == name))
-            .map(|(extension, _)| extension)
-        {
+        if let Some(command) = resolve_prompt_command(&commands, name) {
             return extension_manager
-                .get_prompt(extension, name, arguments)
+                .get_prompt(&command.extension, &command.prompt.name, arguments)
                 .await
                 .map_err(|e| anyhow!("Failed to get prompt: {}", e));
         }
 
+        if commands.iter().any(|c| c.prompt.name == name) {
+            return Err(anyhow!(
+                "Prompt '{}' is offered by several extensions, use 'extension/{}'",
+                name,
+                name
+            ));
+        }
+
         Err(anyhow!("Prompt '{}' not found", name))
     }
 
@@ -1376,6 +2063,7 @@ mod tests {
                     "result": {"type": "string"}
                 }
             })),
+            max_validation_retries: None,
         };
 
         agent.add_final_output_tool(response).await;
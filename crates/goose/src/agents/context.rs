@@ -1,10 +1,14 @@
 use anyhow::Ok;
 
 use crate::message::Message;
-use crate::token_counter::create_async_token_counter;
+use crate::token_counter::{create_async_token_counter, AsyncTokenCounter};
 
 use crate::context_mgmt::summarize::summarize_messages_async;
-use crate::context_mgmt::truncate::{truncate_messages, OldestFirstTruncation};
+use crate::context_mgmt::summarizer::summarizer_provider;
+use crate::context_mgmt::truncate::{
+    drop_tool_outputs, truncate_messages_with_outcome, OldestFirstTruncation, TruncationOutcome,
+    TruncationStrategy,
+};
 use crate::context_mgmt::{estimate_target_context_limit, get_messages_token_counts_async};
 
 use super::super::agents::Agent;
@@ -15,6 +19,18 @@ impl Agent {
         &self,
         messages: &[Message], // last message is a user msg that led to assistant message with_context_length_exceeded
     ) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
+        let outcome = self
+            .truncate_context_with_strategy(messages, &OldestFirstTruncation)
+            .await?;
+        Ok((outcome.messages, outcome.token_counts))
+    }
+
+    /// Truncate the conversation with the given strategy, reporting which messages were removed.
+    pub async fn truncate_context_with_strategy(
+        &self,
+        messages: &[Message],
+        strategy: &(dyn TruncationStrategy + Sync),
+    ) -> Result<TruncationOutcome, anyhow::Error> {
         let provider = self.provider().await?;
         let token_counter = create_async_token_counter()
             .await
@@ -22,29 +38,51 @@ impl Agent {
         let target_context_limit = estimate_target_context_limit(provider);
         let token_counts = get_messages_token_counts_async(&token_counter, messages);
 
-        let (mut new_messages, mut new_token_counts) = truncate_messages(
+        let mut outcome = truncate_messages_with_outcome(
             messages,
             &token_counts,
             target_context_limit,
-            &OldestFirstTruncation,
+            strategy,
         )?;
 
-        // Only add an assistant message if we have room for it and it won't cause another overflow
-        let assistant_message = Message::assistant().with_text("I had run into a context length exceeded error so I truncated some of the oldest messages in our conversation.");
-        let assistant_tokens =
-            token_counter.count_chat_tokens("", &[assistant_message.clone()], &[]);
-
-        let current_total: usize = new_token_counts.iter().sum();
-        if current_total + assistant_tokens <= target_context_limit {
-            new_messages.push(assistant_message);
-            new_token_counts.push(assistant_tokens);
-        } else {
-            // If we can't fit the assistant message, at least log what happened
-            tracing::warn!("Cannot add truncation notice message due to context limits. Current: {}, Assistant: {}, Limit: {}", 
-                          current_total, assistant_tokens, target_context_limit);
-        }
+        push_context_notice(
+            &mut outcome,
+            &token_counter,
+            target_context_limit,
+            strategy.notice(),
+        );
+        Ok(outcome)
+    }
 
-        Ok((new_messages, new_token_counts))
+    /// Replace the output of older tool calls with a placeholder until the conversation fits,
+    /// falling back to truncating the oldest messages if that is not enough.
+    pub async fn drop_tool_outputs_context(
+        &self,
+        messages: &[Message],
+    ) -> Result<TruncationOutcome, anyhow::Error> {
+        let provider = self.provider().await?;
+        let token_counter = create_async_token_counter()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+        let target_context_limit = estimate_target_context_limit(provider);
+        let token_counts = get_messages_token_counts_async(&token_counter, messages);
+
+        let mut outcome = drop_tool_outputs(
+            messages,
+            &token_counts,
+            target_context_limit,
+            &|message: &Message| {
+                token_counter.count_chat_tokens("", std::slice::from_ref(message), &[])
+            },
+        )?;
+
+        push_context_notice(
+            &mut outcome,
+            &token_counter,
+            target_context_limit,
+            "I had run into a context length exceeded error so I cleared the output of older tool calls in our conversation.",
+        );
+        Ok(outcome)
     }
 
     /// Public API to summarize the conversation so that its token count is within the allowed context limit.
@@ -52,15 +90,58 @@ impl Agent {
         &self,
         messages: &[Message], // last message is a user msg that led to assistant message with_context_length_exceeded
     ) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
+        let (new_messages, new_token_counts, _) =
+            self.summarize_context_with_model(messages).await?;
+        Ok((new_messages, new_token_counts))
+    }
+
+    /// Summarize the conversation, also returning the name of the model that wrote the summary.
+    ///
+    /// Uses the configured summarizer model when there is one, falling back to the primary provider.
+    pub async fn summarize_context_with_model(
+        &self,
+        messages: &[Message],
+    ) -> Result<(Vec<Message>, Vec<usize>, String), anyhow::Error> {
         let provider = self.provider().await?;
         let token_counter = create_async_token_counter()
             .await
             .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
+        // The summary has to fit the primary model, so its limit applies either way
         let target_context_limit = estimate_target_context_limit(provider.clone());
 
-        let (mut new_messages, mut new_token_counts) =
-            summarize_messages_async(provider, messages, &token_counter, target_context_limit)
-                .await?;
+        let mut summarized = None;
+        if let Some(summarizer) = summarizer_provider() {
+            let result = summarize_messages_async(
+                summarizer.clone(),
+                messages,
+                &token_counter,
+                target_context_limit,
+            )
+            .await;
+            if let Err(e) = &result {
+                tracing::warn!(
+                    "Summarizer model failed, falling back to the primary model: {}",
+                    e
+                );
+            }
+            summarized = result
+                .ok()
+                .map(|result| (result, summarizer.get_model_config().model_name));
+        }
+
+        let ((mut new_messages, mut new_token_counts), model_name) = match summarized {
+            Some(result) => result,
+            None => (
+                summarize_messages_async(
+                    provider.clone(),
+                    messages,
+                    &token_counter,
+                    target_context_limit,
+                )
+                .await?,
+                provider.get_model_config().model_name,
+            ),
+        };
 
         // If the summarized messages only contains one message, it means no tool request and response message in the summarized messages,
         // Add an assistant message to the summarized messages to ensure the assistant's response is included in the context.
@@ -82,6 +163,28 @@ impl Agent {
             }
         }
 
-        Ok((new_messages, new_token_counts))
+        Ok((new_messages, new_token_counts, model_name))
+    }
+}
+
+/// Append an assistant message explaining what happened, if it fits within the limit
+fn push_context_notice(
+    outcome: &mut TruncationOutcome,
+    token_counter: &AsyncTokenCounter,
+    target_context_limit: usize,
+    notice: &str,
+) {
+    // Only add an assistant message if we have room for it and it won't cause another overflow
+    let assistant_message = Message::assistant().with_text(notice);
+    let assistant_tokens = token_counter.count_chat_tokens("", &[assistant_message.clone()], &[]);
+
+    let current_total: usize = outcome.token_counts.iter().sum();
+    if current_total + assistant_tokens <= target_context_limit {
+        outcome.messages.push(assistant_message);
+        outcome.token_counts.push(assistant_tokens);
+    } else {
+        // If we can't fit the assistant message, at least log what happened
+        tracing::warn!("Cannot add truncation notice message due to context limits. Current: {}, Assistant: {}, Limit: {}",
+                      current_total, assistant_tokens, target_context_limit);
     }
 }
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
+use chrono::{DateTime, Utc};
 use mcp_client::client::Error as ClientError;
-use rmcp::model::Tool;
+use rmcp::model::{LoggingLevel, Prompt, Tool};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::warn;
@@ -30,10 +32,25 @@ pub enum ExtensionError {
     TaskJoinError(#[from] tokio::task::JoinError),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("Extension `{0}` is not running")]
+    NotRunning(String),
+    #[error("Extension `{0}` does not support resources")]
+    ResourcesNotSupported(String),
+    #[error("Extension `{0}` does not support resource subscriptions")]
+    SubscriptionsNotSupported(String),
+    #[error("Extension `{0}` is not configured")]
+    NotConfigured(String),
+    #[error("Extension `{0}` does not connect over HTTP and cannot be authorized")]
+    AuthorizationNotSupported(String),
+    #[error("Authorization failed: {0}")]
+    Authorization(String),
 }
 
 pub type ExtensionResult<T> = Result<T, ExtensionError>;
 
+const SECRET_REFERENCE_PREFIX: &str = "${secret:";
+const SECRET_MASK: &str = "********";
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default, ToSchema)]
 pub struct Envs {
     /// A map of environment variables to set, e.g. API_KEY -> some_secret, HOST -> host
@@ -113,6 +130,58 @@ impl Envs {
         Ok(())
     }
 
+    /// Returns the secret name when `value` is a `${secret:KEY}` reference
+    pub fn secret_reference(value: &str) -> Option<&str> {
+        value
+            .strip_prefix(SECRET_REFERENCE_PREFIX)?
+            .strip_suffix('}')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+    }
+
+    /// Returns the env vars with `${secret:KEY}` references replaced by the value from `lookup`
+    pub fn resolve_secrets<F>(&self, lookup: F) -> Result<HashMap<String, String>, ExtensionError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.map
+            .iter()
+            .map(|(name, value)| {
+                let resolved = match Self::secret_reference(value) {
+                    Some(key) => lookup(key).ok_or_else(|| {
+                        ExtensionError::SetupError(format!(
+                            "Secret '{}' referenced by env var '{}' was not found",
+                            key, name
+                        ))
+                    })?,
+                    None => value.clone(),
+                };
+                Ok((name.clone(), resolved))
+            })
+            .collect()
+    }
+
+    /// Returns a copy that is safe to show: secret references are kept as they are, while
+    /// plaintext values for which `is_secret(name, value)` holds are masked
+    pub fn masked<F>(&self, is_secret: F) -> Self
+    where
+        F: Fn(&str, &str) -> bool,
+    {
+        let map = self
+            .map
+            .iter()
+            .map(|(name, value)| {
+                let shown = if Self::secret_reference(value).is_none() && is_secret(name, value) {
+                    SECRET_MASK.to_string()
+                } else {
+                    value.clone()
+                };
+                (name.clone(), shown)
+            })
+            .collect();
+        Self { map }
+    }
+
     fn is_disallowed(key: &str) -> bool {
         Self::DISALLOWED_KEYS
             .iter()
@@ -141,6 +210,10 @@ pub enum ExtensionConfig {
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Minimum level of log messages the server should send, see `logging/setLevel`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<String>)]
+        log_level: Option<LoggingLevel>,
     },
     /// Standard I/O client with command and arguments
     #[serde(rename = "stdio")]
@@ -149,15 +222,24 @@ pub enum ExtensionConfig {
         name: String,
         cmd: String,
         args: Vec<String>,
+        /// Values may reference a secret as `${secret:KEY}`, resolved when the process starts
         #[serde(default)]
         envs: Envs,
         #[serde(default)]
         env_keys: Vec<String>,
+        /// Working directory for the process, defaults to the current directory
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<String>)]
+        cwd: Option<PathBuf>,
         timeout: Option<u64>,
         description: Option<String>,
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Minimum level of log messages the server should send, see `logging/setLevel`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<String>)]
+        log_level: Option<LoggingLevel>,
     },
     /// Built-in extension that is part of the goose binary
     #[serde(rename = "builtin")]
@@ -170,6 +252,10 @@ pub enum ExtensionConfig {
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Minimum level of log messages the server should send, see `logging/setLevel`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<String>)]
+        log_level: Option<LoggingLevel>,
     },
     /// Streamable HTTP client with a URI endpoint using MCP Streamable HTTP specification
     #[serde(rename = "streamable_http")]
@@ -190,6 +276,10 @@ pub enum ExtensionConfig {
         /// Whether this extension is bundled with Goose
         #[serde(default)]
         bundled: Option<bool>,
+        /// Minimum level of log messages the server should send, see `logging/setLevel`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<String>)]
+        log_level: Option<LoggingLevel>,
     },
     /// Frontend-provided tools that will be called through the frontend
     #[serde(rename = "frontend")]
@@ -218,6 +308,10 @@ pub enum ExtensionConfig {
         /// Python package dependencies required by this extension
         #[serde(default)]
         dependencies: Option<Vec<String>>,
+        /// Minimum level of log messages the server should send, see `logging/setLevel`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<String>)]
+        log_level: Option<LoggingLevel>,
     },
 }
 
@@ -229,6 +323,7 @@ impl Default for ExtensionConfig {
             description: None,
             timeout: Some(config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: Some(true),
+            log_level: None,
         }
     }
 }
@@ -243,6 +338,7 @@ impl ExtensionConfig {
             description: Some(description.into()),
             timeout: Some(timeout.into()),
             bundled: None,
+            log_level: None,
         }
     }
 
@@ -261,6 +357,7 @@ impl ExtensionConfig {
             description: Some(description.into()),
             timeout: Some(timeout.into()),
             bundled: None,
+            log_level: None,
         }
     }
 
@@ -276,9 +373,11 @@ impl ExtensionConfig {
             args: vec![],
             envs: Envs::default(),
             env_keys: Vec::new(),
+            cwd: None,
             description: Some(description.into()),
             timeout: Some(timeout.into()),
             bundled: None,
+            log_level: None,
         }
     }
 
@@ -294,6 +393,7 @@ impl ExtensionConfig {
             description: Some(description.into()),
             timeout: Some(timeout.into()),
             dependencies: None,
+            log_level: None,
         }
     }
 
@@ -308,19 +408,96 @@ impl ExtensionConfig {
                 cmd,
                 envs,
                 env_keys,
+                cwd,
                 timeout,
                 description,
                 bundled,
+                log_level,
                 ..
             } => Self::Stdio {
                 name,
                 cmd,
                 envs,
                 env_keys,
+                cwd,
                 args: args.into_iter().map(Into::into).collect(),
                 description,
                 timeout,
                 bundled,
+                log_level,
+            },
+            other => other,
+        }
+    }
+
+    /// Mask plaintext env values that hold secrets, see [`Envs::masked`]
+    pub fn with_masked_envs<F>(self, is_secret: F) -> Self
+    where
+        F: Fn(&str, &str) -> bool,
+    {
+        match self {
+            Self::Sse {
+                name,
+                uri,
+                envs,
+                env_keys,
+                description,
+                timeout,
+                bundled,
+                log_level,
+            } => Self::Sse {
+                name,
+                uri,
+                envs: envs.masked(is_secret),
+                env_keys,
+                description,
+                timeout,
+                bundled,
+                log_level,
+            },
+            Self::StreamableHttp {
+                name,
+                uri,
+                envs,
+                env_keys,
+                headers,
+                description,
+                timeout,
+                bundled,
+                log_level,
+            } => Self::StreamableHttp {
+                name,
+                uri,
+                envs: envs.masked(is_secret),
+                env_keys,
+                headers,
+                description,
+                timeout,
+                bundled,
+                log_level,
+            },
+            Self::Stdio {
+                name,
+                cmd,
+                args,
+                envs,
+                env_keys,
+                cwd,
+                timeout,
+                description,
+                bundled,
+                log_level,
+            } => Self::Stdio {
+                name,
+                cmd,
+                args,
+                envs: envs.masked(is_secret),
+                env_keys,
+                cwd,
+                timeout,
+                description,
+                bundled,
+                log_level,
             },
             other => other,
         }
@@ -343,6 +520,18 @@ impl ExtensionConfig {
         }
         .to_string()
     }
+
+    /// The level passed to `logging/setLevel` when the extension starts, if any
+    pub fn log_level(&self) -> Option<LoggingLevel> {
+        match self {
+            Self::Sse { log_level, .. }
+            | Self::StreamableHttp { log_level, .. }
+            | Self::Stdio { log_level, .. }
+            | Self::Builtin { log_level, .. }
+            | Self::InlinePython { log_level, .. } => log_level.clone(),
+            Self::Frontend { .. } => None,
+        }
+    }
 }
 
 impl std::fmt::Display for ExtensionConfig {
@@ -386,6 +575,44 @@ impl ExtensionInfo {
     }
 }
 
+/// Health of an extension as seen by periodic pings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// No ping has completed yet
+    Unknown,
+    Healthy,
+    /// Several consecutive pings went unanswered; the extension will be restarted
+    Degraded,
+}
+
+/// Runtime state of an extension attached to the agent
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct ExtensionStatus {
+    pub name: String,
+    /// Whether the MCP server answered a tool listing
+    pub connected: bool,
+    pub tool_count: usize,
+    /// Error returned by the server when it could not be reached
+    pub error: Option<String>,
+    /// Median round trip of recent pings
+    pub latency_ms_p50: Option<u64>,
+    /// When the server last answered a ping
+    pub last_ping_at: Option<DateTime<Utc>>,
+    pub status: HealthStatus,
+    /// How often the extension was restarted after it stopped answering pings
+    pub restarts: u32,
+}
+
+/// A prompt offered by an extension, under the name it is invoked with
+#[derive(Clone, Debug)]
+pub struct ExtensionPrompt {
+    /// The prompt name, prefixed with `extension/` when several extensions share it
+    pub name: String,
+    pub extension: String,
+    pub prompt: Prompt,
+}
+
 /// Information about the tool used for building prompts
 #[derive(Clone, Debug, Serialize, ToSchema)]
 pub struct ToolInfo {
@@ -393,6 +620,8 @@ pub struct ToolInfo {
     pub description: String,
     pub parameters: Vec<String>,
     pub permission: Option<PermissionLevel>,
+    /// Whether the tool is shown to the model, see [`crate::config::ToolVisibilityManager`]
+    pub enabled: bool,
 }
 
 impl ToolInfo {
@@ -407,6 +636,66 @@ impl ToolInfo {
             description: description.to_string(),
             parameters,
             permission,
+            enabled: true,
         }
     }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envs(pairs: &[(&str, &str)]) -> Envs {
+        Envs::new(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_secret_reference() {
+        assert_eq!(
+            Envs::secret_reference("${secret:GH_TOKEN}"),
+            Some("GH_TOKEN")
+        );
+        assert_eq!(Envs::secret_reference("${secret:}"), None);
+        assert_eq!(Envs::secret_reference("plain"), None);
+        assert_eq!(Envs::secret_reference("prefix ${secret:GH_TOKEN}"), None);
+    }
+
+    #[test]
+    fn test_resolve_secrets() {
+        let envs = envs(&[("TOKEN", "${secret:GH_TOKEN}"), ("HOST", "example.com")]);
+
+        let resolved = envs
+            .resolve_secrets(|key| (key == "GH_TOKEN").then(|| "s3cret".to_string()))
+            .unwrap();
+        assert_eq!(resolved["TOKEN"], "s3cret");
+        assert_eq!(resolved["HOST"], "example.com");
+
+        let missing = envs.resolve_secrets(|_| None);
+        assert!(matches!(missing, Err(ExtensionError::SetupError(_))));
+    }
+
+    #[test]
+    fn test_masked_keeps_references() {
+        let envs = envs(&[
+            ("TOKEN", "${secret:GH_TOKEN}"),
+            ("API_KEY", "s3cret"),
+            ("HOST", "example.com"),
+        ]);
+
+        let masked = envs.masked(|name, value| name == "API_KEY" && value == "s3cret");
+        let masked = masked.get_env();
+        assert_eq!(masked["TOKEN"], "${secret:GH_TOKEN}");
+        assert_eq!(masked["API_KEY"], SECRET_MASK);
+        assert_eq!(masked["HOST"], "example.com");
+    }
 }
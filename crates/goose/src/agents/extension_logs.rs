@@ -0,0 +1,229 @@
+//! Per-extension log files for MCP `notifications/message` logging.
+//!
+//! Each extension writes to `logs/extensions/{name}.log` under the goose state directory
+//! (`~/.local/state/goose` on macOS and Linux). When a file grows past `MAX_LOG_BYTES` it is
+//! rotated to `{name}.log.1`, shifting older files up to `MAX_ROTATED_FILES`.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use etcetera::{choose_app_strategy, AppStrategy};
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam, ServerNotification};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use super::extension_manager::normalize;
+use crate::config::APP_STRATEGY;
+
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_ROTATED_FILES: usize = 3;
+
+/// Log notifications at warning or above, kept to be shown to the user, per extension
+pub(crate) type LogAlerts = Arc<Mutex<Vec<(String, ServerNotification)>>>;
+
+/// Directory holding the extension log files
+pub fn extension_log_dir() -> PathBuf {
+    // choose_app_strategy().state_dir()
+    // - macOS/Linux: ~/.local/state/goose/logs/extensions
+    // - Windows has no convention for state_dir, use data_dir instead
+    let strategy = choose_app_strategy(APP_STRATEGY.clone()).expect("goose requires a home dir");
+    strategy
+        .in_state_dir("logs/extensions")
+        .unwrap_or_else(|| strategy.in_data_dir("logs/extensions"))
+}
+
+/// Path of the current log file for the extension
+pub fn extension_log_path(name: &str) -> PathBuf {
+    extension_log_dir().join(format!("{}.log", normalize(name.to_string())))
+}
+
+/// The last `lines` lines of the extension's log file
+pub fn tail_extension_log(name: &str, lines: usize) -> io::Result<Vec<String>> {
+    tail_file(&extension_log_path(name), lines)
+}
+
+fn tail_file(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut tail = VecDeque::with_capacity(lines);
+    for line in reader.lines() {
+        if tail.len() == lines {
+            tail.pop_front();
+        }
+        if lines > 0 {
+            tail.push_back(line?);
+        }
+    }
+    Ok(tail.into())
+}
+
+pub(crate) fn is_warning_or_above(level: &LoggingLevel) -> bool {
+    matches!(
+        level,
+        LoggingLevel::Warning
+            | LoggingLevel::Error
+            | LoggingLevel::Critical
+            | LoggingLevel::Alert
+            | LoggingLevel::Emergency
+    )
+}
+
+fn level_name(level: &LoggingLevel) -> String {
+    serde_json::to_value(level)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_uppercase))
+        .unwrap_or_default()
+}
+
+fn format_log_line(params: &LoggingMessageNotificationParam) -> String {
+    let data = match &params.data {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let logger = params
+        .logger
+        .as_ref()
+        .map(|logger| format!(" [{}]", logger))
+        .unwrap_or_default();
+    format!(
+        "{} {}{} {}",
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        level_name(&params.level),
+        logger,
+        data
+    )
+}
+
+/// Appends lines to a log file, rotating it once it grows past `max_bytes`
+struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingLog {
+    fn new(path: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            path,
+            max_bytes,
+            file: None,
+            size: 0,
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.file.is_some() && self.size + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+
+        let file = self.file.as_mut().expect("log file was just opened");
+        writeln!(file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Write the extension's log notifications to its log file until the client goes away
+///
+/// Notifications at warning or above are also added to `alerts`.
+pub(crate) fn spawn_log_writer(
+    name: String,
+    mut notifications: mpsc::Receiver<ServerNotification>,
+    alerts: LogAlerts,
+) {
+    let path = extension_log_path(&name);
+    tokio::spawn(async move {
+        let mut log = RotatingLog::new(path, MAX_LOG_BYTES);
+        while let Some(notification) = notifications.recv().await {
+            let ServerNotification::LoggingMessageNotification(message) = &notification else {
+                continue;
+            };
+            if let Err(e) = log.write_line(&format_log_line(&message.params)) {
+                tracing::warn!("Failed to write log for extension {}: {}", name, e);
+            }
+            if is_warning_or_above(&message.params.level) {
+                alerts.lock().unwrap().push((name.clone(), notification));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_format_log_line() {
+        let params: LoggingMessageNotificationParam = serde_json::from_value(json!({
+            "level": "warning",
+            "logger": "db",
+            "data": "connection lost"
+        }))
+        .unwrap();
+
+        assert!(format_log_line(&params).ends_with(" WARNING [db] connection lost"));
+    }
+
+    #[test]
+    fn test_rotation_and_tail() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ext.log");
+        let mut log = RotatingLog::new(path.clone(), 20);
+
+        for i in 0..12 {
+            log.write_line(&format!("line {:02}", i)).unwrap();
+        }
+
+        // Each line is 8 bytes plus a newline, so every file holds two lines
+        assert_eq!(tail_file(&path, 200).unwrap(), vec!["line 10", "line 11"]);
+        assert_eq!(
+            tail_file(&dir.path().join("ext.log.1"), 1).unwrap(),
+            vec!["line 09"]
+        );
+        assert!(dir.path().join("ext.log.3").exists());
+        assert!(!dir.path().join("ext.log.4").exists());
+    }
+
+    #[test]
+    fn test_warning_or_above() {
+        assert!(is_warning_or_above(&LoggingLevel::Warning));
+        assert!(is_warning_or_above(&LoggingLevel::Emergency));
+        assert!(!is_warning_or_above(&LoggingLevel::Notice));
+        assert!(!is_warning_or_above(&LoggingLevel::Debug));
+    }
+}
@@ -3,25 +3,38 @@ use chrono::{DateTime, TimeZone, Utc};
 use futures::stream::{FuturesUnordered, StreamExt};
 use futures::{future, FutureExt};
 use rmcp::model::GetPromptResult;
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
 use tempfile::tempdir;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, oneshot};
 use tokio::task;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, warn};
 
-use super::extension::{ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, ToolInfo};
+use super::extension::{
+    ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionPrompt, ExtensionResult,
+    ExtensionStatus, HealthStatus, ToolInfo,
+};
+use super::extension_logs::{is_warning_or_above, spawn_log_writer, LogAlerts};
 use super::tool_execution::ToolCallResult;
 use crate::agents::extension::Envs;
-use crate::config::{Config, ExtensionConfigManager};
+use crate::config::{Config, ExtensionConfigManager, ExtensionCredentialStore};
 use crate::prompt_template;
-use mcp_client::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
+use crate::session::artifacts::{max_tool_result_bytes, MAX_TOOL_RESULT_BYTES_KEY};
+use mcp_client::client::{
+    ClientCapabilities, ClientInfo, Error as ClientError, McpClient, McpClientTrait, Root,
+    RootsCapability,
+};
 use mcp_client::transport::{SseTransport, StdioTransport, StreamableHttpTransport, Transport};
+use mcp_core::protocol::{ListResourcesResult, ReadResourceResult};
 use mcp_core::{ToolCall, ToolError};
-use rmcp::model::{Content, Prompt, Resource, ResourceContents, Tool};
+use rmcp::model::{
+    Content, JsonObject, NumberOrString, Prompt, Resource, ResourceContents, ServerNotification,
+    Tool,
+};
 use serde_json::Value;
 
 // By default, we set it to Jan 1, 2020 if the resource does not have a timestamp
@@ -29,14 +42,104 @@ use serde_json::Value;
 static DEFAULT_TIMESTAMP: LazyLock<DateTime<Utc>> =
     LazyLock::new(|| Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
 
-type McpClientBox = Arc<Mutex<Box<dyn McpClientTrait>>>;
+/// Config key for the request timeout of extensions that do not set their own
+const EXTENSION_TIMEOUT_KEY: &str = "GOOSE_EXTENSION_TIMEOUT";
+
+/// Config key for additional directories offered to servers as roots
+const EXTRA_ROOTS_KEY: &str = "GOOSE_EXTRA_ROOTS";
+
+/// Config key for the seconds between pings to each extension; 0 disables pinging
+const PING_INTERVAL_KEY: &str = "GOOSE_EXTENSION_PING_INTERVAL";
+const DEFAULT_PING_INTERVAL_SECS: u64 = 30;
+
+/// Config key for the consecutive missed pings after which an extension is degraded
+const MAX_MISSED_PINGS_KEY: &str = "GOOSE_EXTENSION_MAX_MISSED_PINGS";
+const DEFAULT_MAX_MISSED_PINGS: u32 = 3;
+
+/// Config keys of the developer extension's limits on background processes
+const PROCESS_LIMIT_KEYS: [&str; 2] = [
+    "GOOSE_MAX_BACKGROUND_PROCESSES",
+    "GOOSE_BACKGROUND_OUTPUT_LIMIT",
+];
+
+/// Number of recent ping latencies the median is taken over
+const LATENCY_WINDOW: usize = 20;
+
+/// Check tool arguments against the tool's input schema before calling it; servers with
+/// schemas their tools don't actually hold to can turn this off
+const STRICT_TOOL_ARGUMENTS_KEY: &str = "GOOSE_STRICT_TOOL_ARGUMENTS";
+
+type McpClientBox = Arc<dyn McpClientTrait>;
+
+/// Rolling ping results of one extension
+#[derive(Debug, Default)]
+struct ExtensionHealth {
+    latencies: VecDeque<Duration>,
+    last_ping_at: Option<DateTime<Utc>>,
+    missed_pings: u32,
+}
+
+impl ExtensionHealth {
+    fn record_success(&mut self, latency: Duration) {
+        if self.latencies.len() == LATENCY_WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+        self.last_ping_at = Some(Utc::now());
+        self.missed_pings = 0;
+    }
+
+    fn record_miss(&mut self) {
+        self.missed_pings += 1;
+    }
+
+    fn latency_ms_p50(&self) -> Option<u64> {
+        let mut latencies: Vec<Duration> = self.latencies.iter().copied().collect();
+        latencies.sort();
+        latencies
+            .get(latencies.len().saturating_sub(1) / 2)
+            .map(|latency| latency.as_millis() as u64)
+    }
+
+    fn status(&self) -> HealthStatus {
+        if self.missed_pings >= max_missed_pings() {
+            HealthStatus::Degraded
+        } else if self.last_ping_at.is_some() {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unknown
+        }
+    }
+}
+
+fn max_missed_pings() -> u32 {
+    Config::global()
+        .get_param(MAX_MISSED_PINGS_KEY)
+        .unwrap_or(DEFAULT_MAX_MISSED_PINGS)
+}
 
 /// Manages Goose extensions / MCP clients and their interactions
 pub struct ExtensionManager {
     clients: HashMap<String, McpClientBox>,
     instructions: HashMap<String, String>,
     resource_capable_extensions: HashSet<String>,
+    /// Resource URIs subscribed to, per extension
+    resource_subscriptions: HashMap<String, HashSet<String>>,
     temp_dirs: HashMap<String, tempfile::TempDir>,
+    /// Working directory of the current session, offered to servers as a root
+    working_dir: Option<PathBuf>,
+    /// Configs the extensions were started from, used to restart degraded ones
+    configs: HashMap<String, ExtensionConfig>,
+    /// Ping results per extension, updated by a background task for each client
+    health: Arc<Mutex<HashMap<String, ExtensionHealth>>>,
+    /// How often each extension was restarted after missing pings
+    restart_counts: HashMap<String, u32>,
+    /// Declared input schemas, per prefixed tool name, recorded when tools are listed
+    input_schemas: Mutex<HashMap<String, Arc<JsonObject>>>,
+    /// Declared output schemas, per prefixed tool name, recorded when tools are listed
+    output_schemas: Mutex<HashMap<String, Arc<JsonObject>>>,
+    /// Warning and error log messages from extensions, waiting to be shown to the user
+    log_alerts: LogAlerts,
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -74,7 +177,7 @@ impl ResourceItem {
 
 /// Sanitizes a string by replacing invalid characters with underscores.
 /// Valid characters match [a-zA-Z0-9_-]
-fn normalize(input: String) -> String {
+pub(crate) fn normalize(input: String) -> String {
     let mut result = String::with_capacity(input.len());
     for c in input.chars() {
         result.push(match c {
@@ -86,6 +189,28 @@ fn normalize(input: String) -> String {
     result.to_lowercase()
 }
 
+/// Validation errors for tool arguments or structured output against the tool's declared
+/// schema, each starting with the path of the offending value
+fn schema_errors(schema: &JsonObject, value: &Value) -> Vec<String> {
+    match jsonschema::validator_for(&Value::Object(schema.clone())) {
+        Ok(validator) => validator
+            .iter_errors(value)
+            .map(|error| {
+                let path = error.instance_path.to_string();
+                let path = if path.is_empty() { "/" } else { &path };
+                format!("{}: {}", path, error)
+            })
+            .collect(),
+        Err(e) => vec![format!("invalid schema: {}", e)],
+    }
+}
+
+fn strict_tool_arguments() -> bool {
+    Config::global()
+        .get_param(STRICT_TOOL_ARGUMENTS_KEY)
+        .unwrap_or(true)
+}
+
 pub fn get_parameter_names(tool: &Tool) -> Vec<String> {
     tool.input_schema
         .get("properties")
@@ -107,7 +232,47 @@ impl ExtensionManager {
             clients: HashMap::new(),
             instructions: HashMap::new(),
             resource_capable_extensions: HashSet::new(),
+            resource_subscriptions: HashMap::new(),
             temp_dirs: HashMap::new(),
+            working_dir: None,
+            configs: HashMap::new(),
+            health: Arc::new(Mutex::new(HashMap::new())),
+            restart_counts: HashMap::new(),
+            input_schemas: Mutex::new(HashMap::new()),
+            output_schemas: Mutex::new(HashMap::new()),
+            log_alerts: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Roots offered to servers: the session working directory plus `GOOSE_EXTRA_ROOTS`
+    fn roots(&self) -> Vec<Root> {
+        let working_dir = self
+            .working_dir
+            .clone()
+            .or_else(|| std::env::current_dir().ok());
+        let extra_roots: Vec<String> = Config::global()
+            .get_param(EXTRA_ROOTS_KEY)
+            .unwrap_or_default();
+
+        working_dir
+            .into_iter()
+            .chain(extra_roots.into_iter().map(PathBuf::from))
+            .map(|path| root_for_path(&path))
+            .collect()
+    }
+
+    /// Set the session working directory, notifying running servers when the roots change
+    pub async fn set_working_dir(&mut self, working_dir: PathBuf) {
+        if self.working_dir.as_ref() == Some(&working_dir) {
+            return;
+        }
+        self.working_dir = Some(working_dir);
+
+        let roots = self.roots();
+        for (name, client) in &self.clients {
+            if let Err(e) = client.set_roots(roots.clone()).await {
+                warn!("Failed to update roots for {}: {}", name, e);
+            }
         }
     }
 
@@ -127,8 +292,10 @@ impl ExtensionManager {
             env_keys: &[String],
             ext_name: &str,
         ) -> Result<HashMap<String, String>, ExtensionError> {
-            let mut all_envs = envs.get_env();
             let config_instance = Config::global();
+            // `${secret:KEY}` references are resolved here so the values never hit the config file
+            let mut all_envs =
+                envs.resolve_secrets(|key| config_instance.get_secret::<String>(key).ok())?;
 
             for key in env_keys {
                 // If the Envs payload already contains the key, prefer that value
@@ -187,17 +354,10 @@ impl ExtensionManager {
                 ..
             } => {
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
-                let transport = SseTransport::new(uri, all_envs);
+                let transport = SseTransport::new(uri, all_envs)
+                    .with_credential_store(Arc::new(ExtensionCredentialStore::new(&config.name())));
                 let handle = transport.start().await?;
-                Box::new(
-                    McpClient::connect(
-                        handle,
-                        Duration::from_secs(
-                            timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                        ),
-                    )
-                    .await?,
-                )
+                Box::new(McpClient::connect(handle, extension_timeout(*timeout)).await?)
             }
             ExtensionConfig::StreamableHttp {
                 uri,
@@ -209,38 +369,27 @@ impl ExtensionManager {
             } => {
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
                 let transport =
-                    StreamableHttpTransport::with_headers(uri, all_envs, headers.clone());
+                    StreamableHttpTransport::with_headers(uri, all_envs, headers.clone())
+                        .with_credential_store(Arc::new(ExtensionCredentialStore::new(
+                            &config.name(),
+                        )));
                 let handle = transport.start().await?;
-                Box::new(
-                    McpClient::connect(
-                        handle,
-                        Duration::from_secs(
-                            timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                        ),
-                    )
-                    .await?,
-                )
+                Box::new(McpClient::connect(handle, extension_timeout(*timeout)).await?)
             }
             ExtensionConfig::Stdio {
                 cmd,
                 args,
                 envs,
                 env_keys,
+                cwd,
                 timeout,
                 ..
             } => {
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
-                let transport = StdioTransport::new(cmd, args.to_vec(), all_envs);
+                let transport =
+                    StdioTransport::new(cmd, args.to_vec(), all_envs).with_cwd(cwd.clone());
                 let handle = transport.start().await?;
-                Box::new(
-                    McpClient::connect(
-                        handle,
-                        Duration::from_secs(
-                            timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                        ),
-                    )
-                    .await?,
-                )
+                Box::new(McpClient::connect(handle, extension_timeout(*timeout)).await?)
             }
             ExtensionConfig::Builtin {
                 name,
@@ -248,27 +397,18 @@ impl ExtensionManager {
                 description: _,
                 timeout,
                 bundled: _,
+                log_level: _,
             } => {
                 let cmd = std::env::current_exe()
                     .expect("should find the current executable")
                     .to_str()
                     .expect("should resolve executable to string path")
                     .to_string();
-                let transport = StdioTransport::new(
-                    &cmd,
-                    vec!["mcp".to_string(), name.clone()],
-                    HashMap::new(),
-                );
+                let envs = builtin_envs(Config::global());
+                let transport =
+                    StdioTransport::new(&cmd, vec!["mcp".to_string(), name.clone()], envs);
                 let handle = transport.start().await?;
-                Box::new(
-                    McpClient::connect(
-                        handle,
-                        Duration::from_secs(
-                            timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                        ),
-                    )
-                    .await?,
-                )
+                Box::new(McpClient::connect(handle, extension_timeout(*timeout)).await?)
             }
             ExtensionConfig::InlinePython {
                 name,
@@ -299,15 +439,8 @@ impl ExtensionManager {
 
                 let transport = StdioTransport::new("uvx", args, HashMap::new());
                 let handle = transport.start().await?;
-                let client = Box::new(
-                    McpClient::connect(
-                        handle,
-                        Duration::from_secs(
-                            timeout.unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT),
-                        ),
-                    )
-                    .await?,
-                );
+                let client =
+                    Box::new(McpClient::connect(handle, extension_timeout(*timeout)).await?);
 
                 self.temp_dirs.insert(sanitized_name.clone(), temp_dir);
 
@@ -316,12 +449,15 @@ impl ExtensionManager {
             _ => unreachable!(),
         };
 
-        // Initialize the client with default capabilities
+        client.set_roots(self.roots()).await?;
+
         let info = ClientInfo {
             name: "goose".to_string(),
             version: env!("CARGO_PKG_VERSION").to_string(),
         };
-        let capabilities = ClientCapabilities::default();
+        let capabilities = ClientCapabilities {
+            roots: Some(RootsCapability { list_changed: true }),
+        };
 
         let init_result = client
             .initialize(info, capabilities)
@@ -338,14 +474,114 @@ impl ExtensionManager {
                 .insert(sanitized_name.clone());
         }
 
-        self.add_client(sanitized_name, client);
+        if let Some(level) = config.log_level() {
+            if let Err(e) = client.set_logging_level(level).await {
+                warn!(
+                    "Failed to set log level for extension {}: {}",
+                    sanitized_name, e
+                );
+            }
+        }
+        spawn_log_writer(
+            sanitized_name.clone(),
+            client.subscribe().await,
+            Arc::clone(&self.log_alerts),
+        );
+
+        self.add_client(sanitized_name.clone(), client);
+        self.configs.insert(sanitized_name.clone(), config);
+        self.start_health_checks(sanitized_name);
         Ok(())
     }
 
+    /// Take the warning and error log messages received since the last call, with the name of
+    /// the extension that sent each
+    pub fn take_log_alerts(&self) -> Vec<(String, ServerNotification)> {
+        std::mem::take(&mut *self.log_alerts.lock().unwrap())
+    }
+
     pub fn add_client(&mut self, client_name: String, client: Box<dyn McpClientTrait>) {
         let sanitized_name = normalize(client_name);
-        self.clients
-            .insert(sanitized_name, Arc::new(Mutex::new(client)));
+        self.clients.insert(sanitized_name, Arc::from(client));
+    }
+
+    /// Ping the extension every `GOOSE_EXTENSION_PING_INTERVAL` seconds until it is removed
+    fn start_health_checks(&self, name: String) {
+        let interval = Config::global()
+            .get_param(PING_INTERVAL_KEY)
+            .unwrap_or(DEFAULT_PING_INTERVAL_SECS);
+        let Some(client) = self.clients.get(&name) else {
+            return;
+        };
+        if interval == 0 {
+            return;
+        }
+
+        let client: Weak<dyn McpClientTrait> = Arc::downgrade(client);
+        let health = Arc::clone(&self.health);
+        health
+            .lock()
+            .unwrap()
+            .insert(name.clone(), ExtensionHealth::default());
+
+        let interval = Duration::from_secs(interval);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                // The client is gone once the extension is removed or restarted
+                let Some(client) = client.upgrade() else {
+                    break;
+                };
+                let started = Instant::now();
+                // A ping that takes longer than the interval counts as missed
+                let result = tokio::time::timeout(interval, client.ping()).await;
+                drop(client);
+
+                let mut health = health.lock().unwrap();
+                let Some(entry) = health.get_mut(&name) else {
+                    break;
+                };
+                match result {
+                    Ok(Ok(())) => entry.record_success(started.elapsed()),
+                    Ok(Err(e)) => {
+                        warn!("Ping to extension {} failed: {}", name, e);
+                        entry.record_miss();
+                    }
+                    Err(_) => {
+                        warn!("Ping to extension {} timed out", name);
+                        entry.record_miss();
+                    }
+                }
+            }
+        });
+    }
+
+    /// Restart extensions that missed too many pings, returning their names
+    pub async fn restart_degraded_extensions(&mut self) -> Vec<String> {
+        let degraded: Vec<String> = self
+            .health
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, health)| health.status() == HealthStatus::Degraded)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let mut restarted = Vec::new();
+        for name in degraded {
+            let Some(config) = self.configs.get(&name).cloned() else {
+                continue;
+            };
+            warn!("Extension {} stopped answering pings, restarting it", name);
+            let _ = self.remove_extension(&name).await;
+            *self.restart_counts.entry(name.clone()).or_default() += 1;
+            match self.add_extension(config).await {
+                Ok(()) => restarted.push(name),
+                Err(e) => error!("Failed to restart extension {}: {}", name, e),
+            }
+        }
+        restarted
     }
 
     /// Get extensions info
@@ -360,6 +596,41 @@ impl ExtensionManager {
             .collect()
     }
 
+    /// Connection state and tool count of each attached extension
+    pub async fn get_extension_statuses(&self) -> Vec<ExtensionStatus> {
+        let mut names: Vec<String> = self.clients.keys().cloned().collect();
+        names.sort();
+
+        let mut statuses = Vec::with_capacity(names.len());
+        for name in names {
+            let (latency_ms_p50, last_ping_at, status) =
+                match self.health.lock().unwrap().get(&name) {
+                    Some(health) => (
+                        health.latency_ms_p50(),
+                        health.last_ping_at,
+                        health.status(),
+                    ),
+                    None => (None, None, HealthStatus::Unknown),
+                };
+            let (connected, tool_count, error) =
+                match self.get_prefixed_tools(Some(name.clone())).await {
+                    Ok(tools) => (true, tools.len(), None),
+                    Err(e) => (false, 0, Some(e.to_string())),
+                };
+            statuses.push(ExtensionStatus {
+                restarts: self.restart_counts.get(&name).copied().unwrap_or_default(),
+                name,
+                connected,
+                tool_count,
+                error,
+                latency_ms_p50,
+                last_ping_at,
+                status,
+            });
+        }
+        statuses
+    }
+
     /// Get aggregated usage statistics
     pub async fn remove_extension(&mut self, name: &str) -> ExtensionResult<()> {
         let sanitized_name = normalize(name.to_string());
@@ -367,7 +638,19 @@ impl ExtensionManager {
         self.clients.remove(&sanitized_name);
         self.instructions.remove(&sanitized_name);
         self.resource_capable_extensions.remove(&sanitized_name);
+        self.resource_subscriptions.remove(&sanitized_name);
         self.temp_dirs.remove(&sanitized_name);
+        self.configs.remove(&sanitized_name);
+        self.health.lock().unwrap().remove(&sanitized_name);
+        let prefix = format!("{}__", sanitized_name);
+        self.input_schemas
+            .lock()
+            .unwrap()
+            .retain(|tool_name, _| !tool_name.starts_with(&prefix));
+        self.output_schemas
+            .lock()
+            .unwrap()
+            .retain(|tool_name, _| !tool_name.starts_with(&prefix));
         Ok(())
     }
 
@@ -427,30 +710,19 @@ impl ExtensionManager {
 
             task::spawn(async move {
                 let mut tools = Vec::new();
-                let client_guard = client.lock().await;
-                let mut client_tools = client_guard.list_tools(None).await?;
-
-                loop {
-                    for client_tool in client_tools.tools {
-                        let mut tool = Tool::new(
-                            format!("{}__{}", name, client_tool.name),
-                            client_tool.description.unwrap_or_default(),
-                            client_tool.input_schema,
-                        );
-
-                        if tool.annotations.is_some() {
-                            tool = tool.annotate(client_tool.annotations.unwrap())
-                        }
-
-                        tools.push(tool);
-                    }
+                for client_tool in client.list_all_tools().await? {
+                    let mut tool = Tool::new(
+                        format!("{}__{}", name, client_tool.name),
+                        client_tool.description.unwrap_or_default(),
+                        client_tool.input_schema,
+                    );
 
-                    // Exit loop when there are no more pages
-                    if client_tools.next_cursor.is_none() {
-                        break;
+                    if tool.annotations.is_some() {
+                        tool = tool.annotate(client_tool.annotations.unwrap())
                     }
+                    tool.output_schema = client_tool.output_schema;
 
-                    client_tools = client_guard.list_tools(client_tools.next_cursor).await?;
+                    tools.push(tool);
                 }
 
                 Ok::<Vec<Tool>, ExtensionError>(tools)
@@ -470,25 +742,130 @@ impl ExtensionManager {
             }
         }
 
+        {
+            let mut input_schemas = self.input_schemas.lock().unwrap();
+            let mut output_schemas = self.output_schemas.lock().unwrap();
+            for tool in &tools {
+                input_schemas.insert(tool.name.to_string(), tool.input_schema.clone());
+                if let Some(schema) = &tool.output_schema {
+                    output_schemas.insert(tool.name.to_string(), schema.clone());
+                }
+            }
+        }
+
         Ok(tools)
     }
 
+    pub fn is_running(&self, name: &str) -> bool {
+        self.clients.contains_key(&normalize(name.to_string()))
+    }
+
+    fn get_running_client(&self, name: &str) -> ExtensionResult<(String, McpClientBox)> {
+        let sanitized_name = normalize(name.to_string());
+        let client = self
+            .clients
+            .get(&sanitized_name)
+            .cloned()
+            .ok_or_else(|| ExtensionError::NotRunning(name.to_string()))?;
+        Ok((sanitized_name, client))
+    }
+
+    /// List one page of the resources offered by an extension, passing the cursor through
+    pub async fn list_extension_resources(
+        &self,
+        name: &str,
+        cursor: Option<String>,
+    ) -> ExtensionResult<ListResourcesResult> {
+        let (sanitized_name, client) = self.get_running_client(name)?;
+        if !self.resource_capable_extensions.contains(&sanitized_name) {
+            return Err(ExtensionError::ResourcesNotSupported(name.to_string()));
+        }
+
+        Ok(client.list_resources(cursor).await?)
+    }
+
+    /// Read a resource from an extension
+    pub async fn read_extension_resource(
+        &self,
+        name: &str,
+        uri: &str,
+    ) -> ExtensionResult<ReadResourceResult> {
+        Ok(self.resource_client(name)?.read_resource(uri).await?)
+    }
+
+    /// The client of a running extension that offers resources, to read them with once the
+    /// extension manager is no longer locked
+    pub fn resource_client(&self, name: &str) -> ExtensionResult<McpClientBox> {
+        let (sanitized_name, client) = self.get_running_client(name)?;
+        if !self.resource_capable_extensions.contains(&sanitized_name) {
+            return Err(ExtensionError::ResourcesNotSupported(name.to_string()));
+        }
+        Ok(client)
+    }
+
+    /// Subscribe to updates of a resource, returning the extension's notification stream
+    ///
+    /// The stream carries all notifications from the server; updates for the resource arrive
+    /// as `ResourceUpdatedNotification`s.
+    pub async fn subscribe_resource(
+        &mut self,
+        name: &str,
+        uri: &str,
+    ) -> ExtensionResult<mpsc::Receiver<ServerNotification>> {
+        let (sanitized_name, client) = self.get_running_client(name)?;
+        if !self.resource_capable_extensions.contains(&sanitized_name) {
+            return Err(ExtensionError::ResourcesNotSupported(name.to_string()));
+        }
+
+        client.subscribe_resource(uri).await.map_err(|e| match e {
+            ClientError::UnsupportedCapability(_) => {
+                ExtensionError::SubscriptionsNotSupported(name.to_string())
+            }
+            e => ExtensionError::Client(e),
+        })?;
+        let notifications = client.subscribe().await;
+
+        self.resource_subscriptions
+            .entry(sanitized_name)
+            .or_default()
+            .insert(uri.to_string());
+        Ok(notifications)
+    }
+
+    pub async fn unsubscribe_resource(&mut self, name: &str, uri: &str) -> ExtensionResult<()> {
+        let (sanitized_name, client) = self.get_running_client(name)?;
+        let subscribed = self
+            .resource_subscriptions
+            .get_mut(&sanitized_name)
+            .is_some_and(|uris| uris.remove(uri));
+        if !subscribed {
+            return Ok(());
+        }
+
+        Ok(client.unsubscribe_resource(uri).await?)
+    }
+
+    pub fn is_subscribed(&self, name: &str, uri: &str) -> bool {
+        self.resource_subscriptions
+            .get(&normalize(name.to_string()))
+            .is_some_and(|uris| uris.contains(uri))
+    }
+
     /// Get client resources and their contents
     pub async fn get_resources(&self) -> ExtensionResult<Vec<ResourceItem>> {
         let mut result: Vec<ResourceItem> = Vec::new();
 
         for (name, client) in &self.clients {
-            let client_guard = client.lock().await;
-            let resources = client_guard.list_resources(None).await?;
+            let resources = client.list_all_resources().await?;
 
-            for resource in resources.resources {
+            for resource in resources {
                 // Skip reading the resource if it's not marked active
                 // This avoids blowing up the context with inactive resources
                 if !resource_is_active(&resource) {
                     continue;
                 }
 
-                if let Ok(contents) = client_guard.read_resource(&resource.uri).await {
+                if let Ok(contents) = client.read_resource(&resource.uri).await {
                     for content in contents.contents {
                         let (uri, content_str) = match content {
                             ResourceContents::TextResourceContents { uri, text, .. } => (uri, text),
@@ -591,8 +968,7 @@ impl ExtensionManager {
             .get(extension_name)
             .ok_or(ToolError::InvalidParameters(error_msg))?;
 
-        let client_guard = client.lock().await;
-        let read_result = client_guard.read_resource(uri).await.map_err(|_| {
+        let read_result = client.read_resource(uri).await.map_err(|_| {
             ToolError::ExecutionError(format!("Could not read resource with uri: {}", uri))
         })?;
 
@@ -616,9 +992,8 @@ impl ExtensionManager {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
-            .list_resources(None)
+        client
+            .list_all_resources()
             .await
             .map_err(|e| {
                 ToolError::ExecutionError(format!(
@@ -626,9 +1001,8 @@ impl ExtensionManager {
                     extension_name, e
                 ))
             })
-            .map(|lr| {
-                let resource_list = lr
-                    .resources
+            .map(|resources| {
+                let resource_list = resources
                     .into_iter()
                     .map(|r| format!("{} - {}, uri: ({})", extension_name, r.name, r.uri))
                     .collect::<Vec<String>>()
@@ -689,6 +1063,34 @@ impl ExtensionManager {
     }
 
     pub async fn dispatch_tool_call(&self, tool_call: ToolCall) -> Result<ToolCallResult> {
+        self.dispatch_tool_call_with_progress(tool_call, None).await
+    }
+
+    /// Where the call's arguments break the tool's input schema, if they do and arguments are
+    /// checked
+    fn argument_errors(&self, tool_call: &ToolCall) -> Option<Vec<String>> {
+        if !strict_tool_arguments() {
+            return None;
+        }
+        let schema = self
+            .input_schemas
+            .lock()
+            .unwrap()
+            .get(&tool_call.name)
+            .cloned()?;
+        let errors = schema_errors(&schema, &tool_call.arguments);
+        (!errors.is_empty()).then_some(errors)
+    }
+
+    /// Dispatch a tool call, asking the server to report progress under `progress_token`
+    ///
+    /// Progress notifications carrying another token belong to other calls to the same server
+    /// and are left out of the returned notification stream.
+    pub async fn dispatch_tool_call_with_progress(
+        &self,
+        tool_call: ToolCall,
+        progress_token: Option<String>,
+    ) -> Result<ToolCallResult> {
         // Dispatch tool call based on the prefix naming convention
         let (client_name, client) = self
             .get_client_for_tool(&tool_call.name)
@@ -702,22 +1104,82 @@ impl ExtensionManager {
             .ok_or_else(|| ToolError::NotFound(tool_call.name.clone()))?
             .to_string();
 
+        // Answer malformed arguments here, with the reasons, rather than with whatever the
+        // server makes of them
+        if let Some(errors) = self.argument_errors(&tool_call) {
+            return Ok(ToolCallResult::from(Err(ToolError::InvalidParameters(
+                format!(
+                    "The arguments for {} do not match its input schema:\n{}\nCall it again with arguments that do.",
+                    tool_call.name,
+                    errors
+                        .iter()
+                        .map(|error| format!("- {}", error))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            ))));
+        }
+
         let arguments = tool_call.arguments.clone();
         let client = client.clone();
-        let notifications_receiver = client.lock().await.subscribe().await;
+        let notifications_receiver = client.subscribe().await;
+        let output_schema = self
+            .output_schemas
+            .lock()
+            .unwrap()
+            .get(&tool_call.name)
+            .cloned();
+        let (structured_tx, structured_rx) = oneshot::channel();
 
+        let token = progress_token.clone();
         let fut = async move {
-            let client_guard = client.lock().await;
-            client_guard
-                .call_tool(&tool_name, arguments)
-                .await
-                .map(|call| call.content)
+            let result = match &token {
+                Some(token) => {
+                    client
+                        .call_tool_with_progress(&tool_name, arguments, token)
+                        .await
+                }
+                None => client.call_tool(&tool_name, arguments).await,
+            };
+            result
+                .map(|call| {
+                    if let Some(structured) = call.structured_content {
+                        if let Some(schema) = &output_schema {
+                            let errors = schema_errors(schema, &structured);
+                            if !errors.is_empty() {
+                                warn!(
+                                    "Structured content from {} does not match its output schema: {}",
+                                    tool_call.name,
+                                    errors.join("; ")
+                                );
+                            }
+                        }
+                        let _ = structured_tx.send(structured);
+                    }
+                    call.content
+                })
                 .map_err(|e| ToolError::ExecutionError(e.to_string()))
         };
 
+        let notifications =
+            ReceiverStream::new(notifications_receiver).filter(move |notification| {
+                future::ready(match (notification, &progress_token) {
+                    (ServerNotification::ProgressNotification(progress), Some(token)) => matches!(
+                        &progress.params.progress_token.0,
+                        NumberOrString::String(t) if t.as_ref() == token.as_str()
+                    ),
+                    // Forwarded through the log alerts instead, see `take_log_alerts`
+                    (ServerNotification::LoggingMessageNotification(message), _) => {
+                        !is_warning_or_above(&message.params.level)
+                    }
+                    _ => true,
+                })
+            });
+
         Ok(ToolCallResult {
             result: Box::new(fut.boxed()),
-            notification_stream: Some(Box::new(ReceiverStream::new(notifications_receiver))),
+            notification_stream: Some(Box::new(notifications)),
+            structured_content: Some(structured_rx),
         })
     }
 
@@ -729,17 +1191,12 @@ impl ExtensionManager {
             ToolError::InvalidParameters(format!("Extension {} is not valid", extension_name))
         })?;
 
-        let client_guard = client.lock().await;
-        client_guard
-            .list_prompts(None)
-            .await
-            .map_err(|e| {
-                ToolError::ExecutionError(format!(
-                    "Unable to list prompts for {}, {:?}",
-                    extension_name, e
-                ))
-            })
-            .map(|lp| lp.prompts)
+        client.list_all_prompts().await.map_err(|e| {
+            ToolError::ExecutionError(format!(
+                "Unable to list prompts for {}, {:?}",
+                extension_name, e
+            ))
+        })
     }
 
     pub async fn list_prompts(&self) -> Result<HashMap<String, Vec<Prompt>>, ToolError> {
@@ -784,6 +1241,11 @@ impl ExtensionManager {
         Ok(all_prompts)
     }
 
+    /// Prompts from every extension, sorted by the name they are invoked with
+    pub async fn list_prompt_commands(&self) -> Result<Vec<ExtensionPrompt>, ToolError> {
+        Ok(prompt_commands(self.list_prompts().await?))
+    }
+
     pub async fn get_prompt(
         &self,
         extension_name: &str,
@@ -795,8 +1257,7 @@ impl ExtensionManager {
             .get(extension_name)
             .ok_or_else(|| anyhow::anyhow!("Extension {} not found", extension_name))?;
 
-        let client_guard = client.lock().await;
-        client_guard
+        client
             .get_prompt(name, arguments)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to get prompt: {}", e))
@@ -880,15 +1341,96 @@ fn resource_is_active(resource: &Resource) -> bool {
     resource.priority().is_some_and(|p| (p - 1.0).abs() < 1e-6)
 }
 
+/// Timeout for each request to an extension, in order of preference: the extension's own
+/// setting, `GOOSE_EXTENSION_TIMEOUT`, then the built-in default
+fn extension_timeout(timeout: Option<u64>) -> Duration {
+    Duration::from_secs(timeout.unwrap_or_else(|| {
+        Config::global()
+            .get_param(EXTENSION_TIMEOUT_KEY)
+            .unwrap_or(crate::config::DEFAULT_EXTENSION_TIMEOUT)
+    }))
+}
+
+/// Builtin extensions can't read the config, so the limits they keep to are passed to them in
+/// their environment
+fn builtin_envs(config: &Config) -> HashMap<String, String> {
+    let mut envs = HashMap::from([(
+        MAX_TOOL_RESULT_BYTES_KEY.to_string(),
+        max_tool_result_bytes().to_string(),
+    )]);
+    for key in PROCESS_LIMIT_KEYS {
+        if let Ok(limit) = config.get_param::<usize>(key) {
+            envs.insert(key.to_string(), limit.to_string());
+        }
+    }
+    envs
+}
+
+fn root_for_path(path: &Path) -> Root {
+    let uri = url::Url::from_file_path(path)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| format!("file://{}", path.display()));
+    Root {
+        uri,
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+    }
+}
+
+/// Name each prompt, prefixing it with its extension when the name is not unique
+fn prompt_commands(prompts: HashMap<String, Vec<Prompt>>) -> Vec<ExtensionPrompt> {
+    let mut name_counts: HashMap<String, usize> = HashMap::new();
+    for prompt in prompts.values().flatten() {
+        *name_counts.entry(prompt.name.clone()).or_default() += 1;
+    }
+
+    let mut commands: Vec<ExtensionPrompt> = prompts
+        .into_iter()
+        .flat_map(|(extension, prompt_list)| {
+            let name_counts = &name_counts;
+            prompt_list.into_iter().map(move |prompt| {
+                let name = if name_counts[&prompt.name] > 1 {
+                    format!("{}/{}", extension, prompt.name)
+                } else {
+                    prompt.name.clone()
+                };
+                ExtensionPrompt {
+                    name,
+                    extension: extension.clone(),
+                    prompt,
+                }
+            })
+        })
+        .collect();
+    commands.sort_by(|a, b| a.name.cmp(&b.name));
+    commands
+}
+
+/// Find a prompt by its command name, or by `extension/prompt` even when the name is unique
+pub fn resolve_prompt_command<'a>(
+    commands: &'a [ExtensionPrompt],
+    name: &str,
+) -> Option<&'a ExtensionPrompt> {
+    commands.iter().find(|c| c.name == name).or_else(|| {
+        let (extension, prompt) = name.split_once('/')?;
+        commands
+            .iter()
+            .find(|c| c.extension == extension && c.prompt.name == prompt)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::agent::ToolStreamItem;
     use mcp_client::client::Error;
     use mcp_client::client::McpClientTrait;
     use mcp_core::protocol::{
         CallToolResult, InitializeResult, ListPromptsResult, ListResourcesResult, ListToolsResult,
         ReadResourceResult,
     };
+    use mcp_core::ToolResult;
     use rmcp::model::{GetPromptResult, ServerNotification};
     use serde_json::json;
     use tokio::sync::mpsc;
@@ -925,6 +1467,7 @@ mod tests {
                 "tool" | "test__tool" => Ok(CallToolResult {
                     content: vec![],
                     is_error: None,
+                    structured_content: None,
                 }),
                 _ => Err(Error::NotInitialized),
             }
@@ -957,23 +1500,21 @@ mod tests {
         // Add some mock clients
         extension_manager.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("__client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("__client".to_string()), Arc::new(MockClient {}));
 
         extension_manager.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // Test basic case
         assert!(extension_manager
@@ -1005,18 +1546,17 @@ mod tests {
         // Add some mock clients
         extension_manager.clients.insert(
             normalize("test_client".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
         extension_manager.clients.insert(
             normalize("__cli__ent__".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
+            Arc::new(MockClient {}),
         );
 
-        extension_manager.clients.insert(
-            normalize("client 🚀".to_string()),
-            Arc::new(Mutex::new(Box::new(MockClient {}))),
-        );
+        extension_manager
+            .clients
+            .insert(normalize("client 🚀".to_string()), Arc::new(MockClient {}));
 
         // verify a normal tool call
         let tool_call = ToolCall {
@@ -1095,4 +1635,525 @@ mod tests {
             panic!("Expected ToolError::NotFound");
         }
     }
+
+    /// Mock server with two pages of resources
+    struct ResourceMockClient {}
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for ResourceMockClient {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            let resource = |uri: &str, name: &str| {
+                Resource::new(rmcp::model::RawResource::new(uri, name), None)
+            };
+            Ok(match next_cursor.as_deref() {
+                None => ListResourcesResult {
+                    resources: vec![resource("file:///a.txt", "a")],
+                    next_cursor: Some("page-2".to_string()),
+                },
+                _ => ListResourcesResult {
+                    resources: vec![resource("file:///b.txt", "b")],
+                    next_cursor: None,
+                },
+            })
+        }
+
+        async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, Error> {
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: uri.to_string(),
+                    mime_type: Some("text/plain".to_string()),
+                    text: format!("contents of {}", uri),
+                }],
+            })
+        }
+
+        async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            mpsc::channel(1).1
+        }
+    }
+
+    /// Emits progress for two different tool calls on every subscription
+    struct ProgressMockClient {}
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for ProgressMockClient {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            Ok(CallToolResult {
+                content: vec![],
+                is_error: None,
+                structured_content: None,
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            let (tx, rx) = mpsc::channel(2);
+            for token in ["toolu_1", "toolu_2"] {
+                let notification = serde_json::from_value(json!({
+                    "method": "notifications/progress",
+                    "params": { "progressToken": token, "progress": 1, "total": 2 }
+                }))
+                .unwrap();
+                tx.try_send(notification).unwrap();
+            }
+            rx
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_filters_progress_by_token() {
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.add_client("slow".to_string(), Box::new(ProgressMockClient {}));
+
+        let tool_call = ToolCall {
+            name: "slow__tool".to_string(),
+            arguments: json!({}),
+        };
+        let result = extension_manager
+            .dispatch_tool_call_with_progress(tool_call, Some("toolu_1".to_string()))
+            .await
+            .unwrap();
+        let notifications: Vec<ServerNotification> =
+            result.notification_stream.unwrap().collect().await;
+
+        assert_eq!(notifications.len(), 1);
+        let ServerNotification::ProgressNotification(progress) = &notifications[0] else {
+            panic!("expected a progress notification");
+        };
+        assert_eq!(
+            progress.params.progress_token.0,
+            NumberOrString::String("toolu_1".into())
+        );
+    }
+
+    /// Declares an output schema on its tool and returns both text and structured content
+    struct StructuredMockClient {
+        structured: Value,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for StructuredMockClient {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_tools(&self, _next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            let tool = serde_json::from_value(json!({
+                "name": "tool",
+                "inputSchema": { "type": "object" },
+                "outputSchema": {
+                    "type": "object",
+                    "properties": { "temperature": { "type": "number" } },
+                    "required": ["temperature"]
+                }
+            }))
+            .unwrap();
+            Ok(ListToolsResult {
+                tools: vec![tool],
+                next_cursor: None,
+            })
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            Ok(CallToolResult {
+                content: vec![Content::text(self.structured.to_string())],
+                is_error: None,
+                structured_content: Some(self.structured.clone()),
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            mpsc::channel(1).1
+        }
+    }
+
+    async fn dispatch_structured(
+        structured: Value,
+    ) -> Vec<ToolStreamItem<ToolResult<Vec<Content>>>> {
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.add_client(
+            "weather".to_string(),
+            Box::new(StructuredMockClient { structured }),
+        );
+
+        let tools = extension_manager.get_prefixed_tools(None).await.unwrap();
+        assert!(tools[0].output_schema.is_some());
+
+        let tool_call = ToolCall {
+            name: "weather__tool".to_string(),
+            arguments: json!({}),
+        };
+        let result = extension_manager
+            .dispatch_tool_call(tool_call)
+            .await
+            .unwrap();
+        result.into_stream().collect().await
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_returns_structured_content() {
+        let items = dispatch_structured(json!({"temperature": 21})).await;
+
+        assert_eq!(items.len(), 2);
+        let ToolStreamItem::StructuredContent(structured) = &items[0] else {
+            panic!("expected structured content before the result");
+        };
+        assert_eq!(structured, &json!({"temperature": 21}));
+        let ToolStreamItem::Result(Ok(content)) = &items[1] else {
+            panic!("expected a successful result");
+        };
+        assert_eq!(content[0].as_text().unwrap().text, r#"{"temperature":21}"#);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_tool_call_keeps_mismatched_structured_content() {
+        // A mismatch against the output schema is only logged, the content is still passed on
+        let items = dispatch_structured(json!({"temperature": "warm"})).await;
+
+        assert!(matches!(
+            &items[0],
+            ToolStreamItem::StructuredContent(structured) if structured["temperature"] == "warm"
+        ));
+        assert!(matches!(&items[1], ToolStreamItem::Result(Ok(_))));
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "temperature": { "type": "number" } },
+            "required": ["temperature"]
+        });
+        let schema = schema.as_object().unwrap();
+
+        assert!(schema_errors(schema, &json!({"temperature": 21})).is_empty());
+        assert_eq!(schema_errors(schema, &json!({})).len(), 1);
+        assert_eq!(
+            schema_errors(schema, &json!({"temperature": "warm"})).len(),
+            1
+        );
+    }
+
+    async fn dispatch_with_arguments(arguments: Value) -> ToolResult<Vec<Content>> {
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager
+            .clients
+            .insert(normalize("editor".to_string()), Arc::new(MockClient {}));
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "line": { "type": "integer", "minimum": 1 },
+                "mode": { "enum": ["insert", "replace"] }
+            },
+            "required": ["path", "line"]
+        });
+        extension_manager.input_schemas.lock().unwrap().insert(
+            "editor__tool".to_string(),
+            Arc::new(schema.as_object().unwrap().clone()),
+        );
+
+        let tool_call = ToolCall {
+            name: "editor__tool".to_string(),
+            arguments,
+        };
+        extension_manager
+            .dispatch_tool_call(tool_call)
+            .await
+            .unwrap()
+            .result
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_malformed_arguments_are_rejected_with_reasons() {
+        let rejection = |result: ToolResult<Vec<Content>>| match result {
+            Err(ToolError::InvalidParameters(message)) => message,
+            other => panic!("expected invalid parameters, got {:?}", other),
+        };
+
+        let message = rejection(dispatch_with_arguments(json!({"path": "src/main.rs"})).await);
+        assert!(message.contains("editor__tool"));
+        assert!(message.contains(r#"- /: "line" is a required property"#));
+
+        let message =
+            rejection(dispatch_with_arguments(json!({"path": "src/main.rs", "line": "12"})).await);
+        assert!(message.contains("- /line: "));
+
+        let message = rejection(
+            dispatch_with_arguments(json!({"path": 7, "line": 0, "mode": "append"})).await,
+        );
+        assert_eq!(message.matches("\n- ").count(), 3);
+        assert!(message.contains("- /path: "));
+        assert!(message.contains("- /line: "));
+        assert!(message.contains("- /mode: "));
+
+        let message = rejection(dispatch_with_arguments(json!("src/main.rs")).await);
+        assert!(message.contains("- /: "));
+    }
+
+    #[tokio::test]
+    async fn test_matching_arguments_are_dispatched() {
+        let result =
+            dispatch_with_arguments(json!({"path": "src/main.rs", "line": 12, "mode": "insert"}))
+                .await;
+        assert!(result.is_ok());
+    }
+
+    fn resource_extension_manager() -> ExtensionManager {
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.add_client("files".to_string(), Box::new(ResourceMockClient {}));
+        extension_manager
+            .resource_capable_extensions
+            .insert("files".to_string());
+        extension_manager.add_client("plain".to_string(), Box::new(MockClient {}));
+        extension_manager
+    }
+
+    #[tokio::test]
+    async fn test_list_extension_resources_passes_cursor() {
+        let extension_manager = resource_extension_manager();
+
+        let first = extension_manager
+            .list_extension_resources("files", None)
+            .await
+            .unwrap();
+        assert_eq!(first.resources[0].uri, "file:///a.txt");
+        assert_eq!(first.next_cursor.as_deref(), Some("page-2"));
+
+        let second = extension_manager
+            .list_extension_resources("files", first.next_cursor)
+            .await
+            .unwrap();
+        assert_eq!(second.resources[0].uri, "file:///b.txt");
+        assert!(second.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_extension_resource() {
+        let extension_manager = resource_extension_manager();
+
+        let result = extension_manager
+            .read_extension_resource("files", "file:///a.txt")
+            .await
+            .unwrap();
+        assert!(matches!(
+            &result.contents[0],
+            ResourceContents::TextResourceContents { text, .. } if text == "contents of file:///a.txt"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resources_unsupported_or_missing() {
+        let extension_manager = resource_extension_manager();
+
+        let result = extension_manager
+            .list_extension_resources("plain", None)
+            .await;
+        assert!(matches!(
+            result,
+            Err(ExtensionError::ResourcesNotSupported(_))
+        ));
+
+        let result = extension_manager
+            .read_extension_resource("missing", "file:///a.txt")
+            .await;
+        assert!(matches!(result, Err(ExtensionError::NotRunning(_))));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_resource_requires_capability() {
+        let mut extension_manager = resource_extension_manager();
+
+        // The mock server offers resources but does not advertise subscriptions
+        let result = extension_manager
+            .subscribe_resource("files", "file:///a.txt")
+            .await;
+        assert!(matches!(
+            result,
+            Err(ExtensionError::SubscriptionsNotSupported(_))
+        ));
+        assert!(!extension_manager.is_subscribed("files", "file:///a.txt"));
+
+        let result = extension_manager
+            .subscribe_resource("plain", "file:///a.txt")
+            .await;
+        assert!(matches!(
+            result,
+            Err(ExtensionError::ResourcesNotSupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_builtin_envs_carry_the_configured_process_limits() {
+        let config_file = tempfile::NamedTempFile::new().unwrap();
+        let config = Config::new(config_file.path(), "goose-test").unwrap();
+        config
+            .set_param("GOOSE_MAX_BACKGROUND_PROCESSES", json!(3))
+            .unwrap();
+
+        let envs = builtin_envs(&config);
+        assert_eq!(envs["GOOSE_MAX_BACKGROUND_PROCESSES"], "3");
+        assert!(!envs.contains_key("GOOSE_BACKGROUND_OUTPUT_LIMIT"));
+        assert!(envs.contains_key(MAX_TOOL_RESULT_BYTES_KEY));
+    }
+
+    #[test]
+    fn test_prompt_commands_disambiguate_collisions() {
+        let prompt = |name: &str| Prompt::new(name, None::<&str>, None);
+        let prompts = HashMap::from([
+            ("git".to_string(), vec![prompt("review"), prompt("commit")]),
+            ("github".to_string(), vec![prompt("review")]),
+        ]);
+
+        let commands = prompt_commands(prompts);
+        let names: Vec<&str> = commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["commit", "git/review", "github/review"]);
+
+        let review = resolve_prompt_command(&commands, "github/review").unwrap();
+        assert_eq!(review.extension, "github");
+        assert_eq!(review.prompt.name, "review");
+
+        let commit = resolve_prompt_command(&commands, "git/commit").unwrap();
+        assert_eq!(commit.name, "commit");
+        assert!(resolve_prompt_command(&commands, "review").is_none());
+    }
+
+    #[test]
+    fn test_extension_health_tracks_latency_and_misses() {
+        let mut health = ExtensionHealth::default();
+        assert_eq!(health.status(), HealthStatus::Unknown);
+        assert_eq!(health.latency_ms_p50(), None);
+
+        for ms in [30, 10, 20] {
+            health.record_success(Duration::from_millis(ms));
+        }
+        assert_eq!(health.status(), HealthStatus::Healthy);
+        assert_eq!(health.latency_ms_p50(), Some(20));
+        assert!(health.last_ping_at.is_some());
+
+        for _ in 0..DEFAULT_MAX_MISSED_PINGS {
+            health.record_miss();
+        }
+        assert_eq!(health.status(), HealthStatus::Degraded);
+
+        health.record_success(Duration::from_millis(5));
+        assert_eq!(health.status(), HealthStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_extension_status_without_pings() {
+        let mut extension_manager = ExtensionManager::new();
+        extension_manager.add_client("test_client".to_string(), Box::new(MockClient {}));
+
+        let statuses = extension_manager.get_extension_statuses().await;
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].status, HealthStatus::Unknown);
+        assert_eq!(statuses[0].latency_ms_p50, None);
+        assert!(extension_manager
+            .restart_degraded_extensions()
+            .await
+            .is_empty());
+    }
 }
@@ -173,7 +173,10 @@ mod tests {
     #[test]
     #[should_panic(expected = "Cannot create FinalOutputTool: json_schema is required")]
     fn test_new_with_missing_schema() {
-        let response = Response { json_schema: None };
+        let response = Response {
+            json_schema: None,
+            max_validation_retries: None,
+        };
         FinalOutputTool::new(response);
     }
 
@@ -182,6 +185,7 @@ mod tests {
     fn test_new_with_empty_schema() {
         let response = Response {
             json_schema: Some(json!({})),
+            max_validation_retries: None,
         };
         FinalOutputTool::new(response);
     }
@@ -198,6 +202,7 @@ mod tests {
                     }
                 }
             })),
+            max_validation_retries: None,
         };
         FinalOutputTool::new(response);
     }
@@ -217,6 +222,7 @@ mod tests {
                 },
                 "required": ["message", "count"]
             })),
+            max_validation_retries: None,
         };
 
         let mut tool = FinalOutputTool::new(response);
@@ -239,6 +245,7 @@ mod tests {
     async fn test_execute_tool_call_complex_valid_json() {
         let response = Response {
             json_schema: Some(create_complex_test_schema()),
+            max_validation_retries: None,
         };
 
         let mut tool = FinalOutputTool::new(response);
@@ -1,9 +1,9 @@
 mod agent;
 mod context;
 pub mod extension;
+pub mod extension_logs;
 pub mod extension_manager;
 pub mod final_output_tool;
-mod large_response_handler;
 pub mod platform_tools;
 pub mod prompt_manager;
 mod recipe_tools;
@@ -21,6 +21,7 @@ mod tool_execution;
 mod tool_route_manager;
 mod tool_router_index_manager;
 pub(crate) mod tool_vectordb;
+pub mod turn_timing;
 pub mod types;
 
 pub use agent::{Agent, AgentEvent};
@@ -29,4 +30,7 @@ pub use extension_manager::ExtensionManager;
 pub use prompt_manager::PromptManager;
 pub use subagent::{SubAgent, SubAgentProgress, SubAgentStatus};
 pub use subagent_task_config::TaskConfig;
-pub use types::{FrontendTool, RetryConfig, SessionConfig, SuccessCheck};
+pub use turn_timing::{SessionTimings, TimingSummary, TurnTiming};
+pub use types::{
+    FrontendTool, PendingConfirmation, ReplyExecutionMode, RetryConfig, SessionConfig, SuccessCheck,
+};
@@ -11,6 +11,7 @@ use crate::{config::Config, prompt_template};
 pub struct PromptManager {
     system_prompt_override: Option<String>,
     system_prompt_extras: Vec<String>,
+    system_prompt_append: Option<String>,
     current_date_timestamp: String,
 }
 
@@ -25,14 +26,19 @@ impl PromptManager {
         PromptManager {
             system_prompt_override: None,
             system_prompt_extras: Vec::new(),
+            system_prompt_append: None,
             // Use the fixed current date time so that prompt cache can be used.
             current_date_timestamp: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         }
     }
 
-    /// Add an additional instruction to the system prompt
+    /// Add an additional instruction to the system prompt. An instruction that is already there
+    /// isn't added again, since clients resending their prompt when they resume a session would
+    /// otherwise repeat it.
     pub fn add_system_prompt_extra(&mut self, instruction: String) {
-        self.system_prompt_extras.push(instruction);
+        if !self.system_prompt_extras.contains(&instruction) {
+            self.system_prompt_extras.push(instruction);
+        }
     }
 
     /// Override the system prompt with custom text
@@ -40,6 +46,16 @@ impl PromptManager {
         self.system_prompt_override = Some(template);
     }
 
+    /// Remove the system prompt override, going back to the default template
+    pub fn clear_system_prompt_override(&mut self) {
+        self.system_prompt_override = None;
+    }
+
+    /// Set (or clear) custom instructions that always go at the very end of the system prompt
+    pub fn set_system_prompt_append(&mut self, instructions: Option<String>) {
+        self.system_prompt_append = instructions;
+    }
+
     /// Normalize a model name (replace - and / with _, lower case)
     fn normalize_model_name(name: &str) -> String {
         name.replace(['-', '/', '.'], "_").to_lowercase()
@@ -149,6 +165,10 @@ impl PromptManager {
                 .push("Right now you are *NOT* in the chat only mode and have access to tool use and system.".to_string());
         }
 
+        if let Some(append) = &self.system_prompt_append {
+            system_prompt_extras.push(append.clone());
+        }
+
         if system_prompt_extras.is_empty() {
             base_prompt
         } else {
@@ -207,6 +227,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_override_and_append() {
+        let mut prompt_manager = PromptManager::new();
+        prompt_manager.set_system_prompt_override("You are a custom agent.".to_string());
+        prompt_manager.set_system_prompt_append(Some("Always answer in French.".to_string()));
+
+        let prompt = prompt_manager.build_system_prompt(vec![], None, Value::Null, None, None);
+        assert!(prompt.starts_with("You are a custom agent."));
+        assert!(prompt.ends_with("Always answer in French."));
+
+        prompt_manager.clear_system_prompt_override();
+        prompt_manager.set_system_prompt_append(None);
+        let prompt = prompt_manager.build_system_prompt(vec![], None, Value::Null, None, None);
+        assert!(!prompt.starts_with("You are a custom agent."));
+        assert!(!prompt.contains("Always answer in French."));
+    }
+
+    #[test]
+    fn test_repeated_extras_are_added_once() {
+        let mut prompt_manager = PromptManager::new();
+        prompt_manager.add_system_prompt_extra("Use the desktop app's tools.".to_string());
+        prompt_manager.add_system_prompt_extra("Use the desktop app's tools.".to_string());
+
+        let prompt = prompt_manager.build_system_prompt(vec![], None, Value::Null, None, None);
+        assert_eq!(prompt.matches("Use the desktop app's tools.").count(), 1);
+    }
+
     #[test]
     fn test_model_prompt_map_none() {
         // should return system.md for unrecognized/unsupported model names
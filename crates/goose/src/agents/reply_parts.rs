@@ -6,17 +6,63 @@ use async_stream::try_stream;
 use futures::stream::StreamExt;
 
 use crate::agents::router_tool_selector::RouterToolSelectionStrategy;
+use crate::config::ToolVisibilityManager;
 use crate::message::{Message, MessageContent, ToolRequest};
 use crate::providers::base::{stream_from_single_message, MessageStream, Provider, ProviderUsage};
+use crate::providers::capabilities::ModelCapabilities;
 use crate::providers::errors::ProviderError;
 use crate::providers::toolshim::{
     augment_message_with_tool_calls, convert_tool_messages_to_text,
     modify_system_prompt_for_tool_json, OllamaInterpreter,
 };
 use crate::session;
-use rmcp::model::Tool;
+use rmcp::model::{
+    LoggingLevel, LoggingMessageNotification, LoggingMessageNotificationMethod,
+    LoggingMessageNotificationParam, ServerNotification, Tool,
+};
+use serde_json::Value;
 
 use super::super::agents::Agent;
+use super::extension_manager::normalize;
+use super::types::{ReplyExecutionMode, SessionConfig};
+
+/// Prefixes of the tools goose provides itself, which stay available when a session is limited
+/// to some extensions
+const AGENT_TOOL_PREFIXES: &[&str] = &[
+    "platform",
+    "router",
+    "recipe",
+    "subrecipe",
+    "dynamic_task",
+    "subagent",
+];
+
+/// Added to the system prompt when a reply runs in plan mode
+const PLAN_MODE_INSTRUCTIONS: &str =
+    "You are in plan mode and cannot use any tools in this reply. \
+Write a numbered plan of the steps you would take to complete the user's request, naming the \
+tools you would call and anything that could go wrong, then stop. The user will review the plan \
+and ask you to carry it out in a later message.";
+
+/// Whether a session limited to `allowed_extensions` may use the tool named `tool_name`
+pub(crate) fn is_tool_allowed(tool_name: &str, allowed_extensions: &[String]) -> bool {
+    let Some((prefix, _)) = tool_name.split_once("__") else {
+        return true;
+    };
+    AGENT_TOOL_PREFIXES.contains(&prefix)
+        || allowed_extensions.iter().any(|name| {
+            tool_name
+                .strip_prefix(normalize(name.clone()).as_str())
+                .is_some_and(|rest| rest.starts_with("__"))
+        })
+}
+
+/// Drop the tools named in `disabled_tools` from `tools`
+pub(crate) fn remove_disabled_tools(tools: &mut Vec<Tool>, disabled_tools: &HashSet<String>) {
+    if !disabled_tools.is_empty() {
+        tools.retain(|tool| !disabled_tools.contains(tool.name.as_ref()));
+    }
+}
 
 async fn toolshim_postprocess(
     response: Message,
@@ -31,9 +77,46 @@ async fn toolshim_postprocess(
         .map_err(|e| ProviderError::ExecutionError(format!("Failed to augment message: {}", e)))
 }
 
+/// Tell the user which loaded extensions a session cannot use
+pub(crate) fn excluded_extensions_notification(excluded: &[String]) -> ServerNotification {
+    ServerNotification::LoggingMessageNotification(LoggingMessageNotification {
+        method: LoggingMessageNotificationMethod,
+        params: LoggingMessageNotificationParam {
+            level: LoggingLevel::Info,
+            logger: Some("goose".to_string()),
+            data: Value::String(format!(
+                "Tools from these extensions are not available in this session: {}",
+                excluded.join(", ")
+            )),
+        },
+        extensions: Default::default(),
+    })
+}
+
 impl Agent {
-    /// Prepares tools and system prompt for a provider request
-    pub async fn prepare_tools_and_prompt(&self) -> anyhow::Result<(Vec<Tool>, Vec<Tool>, String)> {
+    /// Loaded extensions that are not in `allowed_extensions`, sorted by name
+    pub(crate) async fn excluded_extensions(&self, allowed_extensions: &[String]) -> Vec<String> {
+        let allowed: HashSet<String> = allowed_extensions
+            .iter()
+            .map(|name| normalize(name.clone()))
+            .collect();
+        let mut excluded: Vec<String> = self
+            .extension_manager
+            .read()
+            .await
+            .list_extensions()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|name| !allowed.contains(name))
+            .collect();
+        excluded.sort();
+        excluded
+    }
+
+    /// The tools offered to the model on the next request: those picked by the tool selection
+    /// strategy, minus the ones the user disabled, plus the frontend tools
+    pub async fn list_tools_for_request(&self) -> Vec<Tool> {
         // Get tool selection strategy from config
         let tool_selection_strategy = self
             .tool_route_manager
@@ -52,29 +135,55 @@ impl Agent {
             }
             _ => self.list_tools(None).await,
         };
+        // Hide tools the user disabled; read on every turn so changes apply without a restart
+        remove_disabled_tools(&mut tools, &ToolVisibilityManager::disabled_tools());
+
         // Add frontend tools
         let frontend_tools = self.frontend_tools.lock().await;
         for frontend_tool in frontend_tools.values() {
             tools.push(frontend_tool.tool.clone());
         }
+        tools
+    }
 
-        // Prepare system prompt
-        let extension_manager = self.extension_manager.read().await;
-        let extensions_info = extension_manager.get_extensions_info().await;
+    /// Prepares tools and system prompt for a provider request. For a `session` limited to some
+    /// extensions, tools from the others are left out; frontend tools are always kept, including
+    /// those the client registered for the session.
+    pub async fn prepare_tools_and_prompt(
+        &self,
+        session: Option<&SessionConfig>,
+    ) -> anyhow::Result<(Vec<Tool>, Vec<Tool>, String)> {
+        let mut tools = self.list_tools_for_request().await;
+        if let Some(allowed_extensions) = session.and_then(|s| s.allowed_extensions.as_deref()) {
+            let frontend_tools = self.frontend_tools.lock().await;
+            tools.retain(|tool| {
+                frontend_tools.contains_key(tool.name.as_ref())
+                    || is_tool_allowed(&tool.name, allowed_extensions)
+            });
+        }
+        if let Some(session_id) = session.and_then(|s| s.id.session_id()) {
+            tools.extend(self.session_frontend_tools(&session_id).await);
+        }
 
         // Get model name from provider
         let provider = self.provider().await?;
         let model_config = provider.get_model_config();
         let model_name = &model_config.model_name;
 
-        let prompt_manager = self.prompt_manager.lock().await;
-        let mut system_prompt = prompt_manager.build_system_prompt(
-            extensions_info,
-            self.frontend_instructions.lock().await.clone(),
-            extension_manager.suggest_disable_extensions_prompt().await,
-            Some(model_name),
-            tool_selection_strategy,
-        );
+        let mut system_prompt = self.build_system_prompt(Some(model_name)).await;
+        if let Some(instructions) = session
+            .and_then(|s| s.persona.as_ref())
+            .and_then(|persona| persona.instructions.as_deref())
+        {
+            system_prompt = format!("{}\n\n{}", system_prompt, instructions);
+        }
+
+        // Plan mode offers no tools, so the model can only describe what it would do
+        let execution_mode = session.and_then(|s| s.execution_mode.as_deref());
+        if execution_mode == Some(ReplyExecutionMode::Plan.as_str()) {
+            system_prompt = format!("{}\n\n{}", system_prompt, PLAN_MODE_INSTRUCTIONS);
+            return Ok((vec![], vec![], system_prompt));
+        }
 
         // Handle toolshim if enabled
         let mut toolshim_tools = vec![];
@@ -90,6 +199,26 @@ impl Agent {
         Ok((tools, toolshim_tools, system_prompt))
     }
 
+    /// Build the system prompt for `model_name` from the loaded extensions and the prompt
+    /// settings. This needs no provider, so it also serves previews of a run.
+    pub async fn build_system_prompt(&self, model_name: Option<&str>) -> String {
+        let tool_selection_strategy = self
+            .tool_route_manager
+            .get_router_tool_selection_strategy()
+            .await;
+        let extension_manager = self.extension_manager.read().await;
+        let extensions_info = extension_manager.get_extensions_info().await;
+
+        let prompt_manager = self.prompt_manager.lock().await;
+        prompt_manager.build_system_prompt(
+            extensions_info,
+            self.frontend_instructions.lock().await.clone(),
+            extension_manager.suggest_disable_extensions_prompt().await,
+            model_name,
+            tool_selection_strategy,
+        )
+    }
+
     /// Categorize tools based on their annotations
     /// Returns:
     /// - read_only_tools: Tools with read-only annotations
@@ -145,15 +274,29 @@ impl Agent {
     }
 
     /// Stream a response from the LLM provider.
-    /// Handles toolshim transformations if needed
+    /// Handles toolshim transformations if needed, and leaves out tools and streaming for
+    /// models without `capabilities` for them
     pub(crate) async fn stream_response_from_provider(
         provider: Arc<dyn Provider>,
+        capabilities: &ModelCapabilities,
         system_prompt: &str,
         messages: &[Message],
         tools: &[Tool],
         toolshim_tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
         let config = provider.get_model_config();
+        let tools = if capabilities.supports_tools {
+            tools
+        } else {
+            if !tools.is_empty() {
+                tracing::warn!(
+                    "{} doesn't support tools, sending the request without {} tools",
+                    config.model_name,
+                    tools.len()
+                );
+            }
+            &[]
+        };
 
         // Convert tool messages to text if toolshim is enabled
         let messages_for_provider = if config.toolshim {
@@ -168,7 +311,7 @@ impl Agent {
         let toolshim_tools = toolshim_tools.to_owned();
         let provider = provider.clone();
 
-        let mut stream = if provider.supports_streaming() {
+        let mut stream = if provider.supports_streaming() && capabilities.supports_streaming {
             provider
                 .stream(system_prompt.as_str(), &messages_for_provider, &tools)
                 .await?
@@ -180,7 +323,8 @@ impl Agent {
         };
 
         Ok(Box::pin(try_stream! {
-            while let Some(Ok((mut message, usage))) = stream.next().await {
+            while let Some(item) = stream.next().await {
+                let (mut message, usage) = item?;
                 // Store the model information in the global store
                 if let Some(usage) = usage.as_ref() {
                     crate::providers::base::set_current_model(&usage.model);
@@ -201,9 +345,12 @@ impl Agent {
     /// - frontend_requests: Tool requests that should be handled by the frontend
     /// - other_requests: All other tool requests (including requests to enable extensions)
     /// - filtered_message: The original message with frontend tool requests removed
+    ///
+    /// Frontend tools registered for another session than `session_id` are not frontend tools here.
     pub(crate) async fn categorize_tool_requests(
         &self,
         response: &Message,
+        session_id: Option<&str>,
     ) -> (Vec<ToolRequest>, Vec<ToolRequest>, Message) {
         // First collect all tool requests
         let tool_requests: Vec<ToolRequest> = response
@@ -226,7 +373,9 @@ impl Agent {
             let should_include = match content {
                 MessageContent::ToolRequest(req) => {
                     if let Ok(tool_call) = &req.tool_call {
-                        !self.is_frontend_tool(&tool_call.name).await
+                        !self
+                            .is_session_frontend_tool(session_id, &tool_call.name)
+                            .await
                     } else {
                         true
                     }
@@ -252,7 +401,10 @@ impl Agent {
 
         for request in tool_requests {
             if let Ok(tool_call) = &request.tool_call {
-                if self.is_frontend_tool(&tool_call.name).await {
+                if self
+                    .is_session_frontend_tool(session_id, &tool_call.name)
+                    .await
+                {
                     frontend_requests.push(request);
                 } else {
                     other_requests.push(request);
@@ -277,9 +429,16 @@ impl Agent {
                 return Err(anyhow::anyhow!("Failed to get session file path: {}", e));
             }
         };
+        let is_new_session = !session_file_path.exists();
         let mut metadata = session::storage::read_metadata(&session_file_path)?;
 
         metadata.schedule_id = session_config.schedule_id.clone();
+        if is_new_session && metadata.schedule_id.is_some() {
+            metadata.origin = session::SessionOrigin::Schedule;
+        }
+        if let Some(persona) = &session_config.persona {
+            metadata.persona = Some(persona.name.clone());
+        }
 
         metadata.total_tokens = usage.usage.total_tokens;
         metadata.input_tokens = usage.usage.input_tokens;
@@ -301,9 +460,122 @@ impl Agent {
             metadata.accumulated_output_tokens,
             usage.usage.output_tokens,
         );
+        metadata.accumulated_web_search_requests = accumulate(
+            metadata.accumulated_web_search_requests,
+            usage.usage.web_search_requests,
+        );
 
         session::storage::update_metadata(&session_file_path, &metadata).await?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::Recipe;
+    use mcp_core::ToolCall;
+
+    #[test]
+    fn test_single_extension_recipe_limits_tools() {
+        let recipe = Recipe::from_content(
+            r#"
+version: 1.0.0
+title: Shell only
+description: Runs with the developer extension alone
+instructions: Use the shell
+extensions:
+  - type: builtin
+    name: Developer
+"#,
+        )
+        .unwrap();
+        let allowed = recipe.extension_names().unwrap();
+        assert_eq!(allowed, vec!["Developer".to_string()]);
+
+        assert!(is_tool_allowed("developer__shell", &allowed));
+        assert!(!is_tool_allowed("computercontroller__web_scrape", &allowed));
+        assert!(!is_tool_allowed("developerx__shell", &allowed));
+        assert!(is_tool_allowed("platform__manage_extensions", &allowed));
+        assert!(is_tool_allowed("recipe__final_output", &allowed));
+        assert!(is_tool_allowed("frontend_tool", &allowed));
+    }
+
+    #[test]
+    fn test_disabled_tools_are_removed() {
+        let tool = |name: &str| {
+            Tool::new(
+                name.to_string(),
+                format!("The {} tool", name),
+                serde_json::Map::new(),
+            )
+        };
+        let mut tools = vec![
+            tool("developer__shell"),
+            tool("developer__text_editor"),
+            tool("computercontroller__web_scrape"),
+        ];
+
+        remove_disabled_tools(&mut tools, &HashSet::new());
+        assert_eq!(tools.len(), 3);
+
+        let disabled = HashSet::from([
+            "developer__shell".to_string(),
+            "memory__remember".to_string(),
+        ]);
+        remove_disabled_tools(&mut tools, &disabled);
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(
+            names,
+            vec!["developer__text_editor", "computercontroller__web_scrape"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registered_frontend_tools_are_scoped_to_their_session() {
+        let agent = Agent::new();
+        agent
+            .register_frontend_tools(
+                "session-a",
+                vec![Tool::new(
+                    "render_chart".to_string(),
+                    "Render a chart in the browser".to_string(),
+                    serde_json::Map::new(),
+                )],
+            )
+            .await;
+        let response = Message::assistant().with_tool_request(
+            "call_1",
+            Ok(ToolCall::new("render_chart", serde_json::json!({}))),
+        );
+
+        let (frontend, other, filtered) = agent
+            .categorize_tool_requests(&response, Some("session-a"))
+            .await;
+        assert_eq!(frontend.len(), 1);
+        assert!(other.is_empty());
+        assert!(filtered.content.is_empty());
+
+        let (frontend, other, _) = agent
+            .categorize_tool_requests(&response, Some("session-b"))
+            .await;
+        assert!(frontend.is_empty());
+        assert_eq!(other.len(), 1);
+
+        assert!(agent.unregister_frontend_tools("session-a").await);
+        assert!(agent.session_frontend_tools("session-a").await.is_empty());
+        assert!(!agent.unregister_frontend_tools("session-a").await);
+    }
+
+    #[test]
+    fn test_recipe_without_extensions_sets_no_limit() {
+        let recipe = Recipe::builder()
+            .title("No extensions")
+            .description("Uses whatever is loaded")
+            .instructions("Do the task")
+            .build()
+            .unwrap();
+        assert_eq!(recipe.extension_names(), None);
+    }
+}
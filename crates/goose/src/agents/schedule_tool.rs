@@ -3,6 +3,7 @@
 //! This module contains all the handlers for the schedule management platform tool,
 //! including job creation, execution, monitoring, and session management.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use chrono::Utc;
@@ -144,13 +145,32 @@ impl Agent {
         let job = crate::scheduler::ScheduledJob {
             id: job_id.clone(),
             source: recipe_path.to_string(),
+            prompt: None,
+            working_dir: None,
+            extensions: Vec::new(),
+            params: HashMap::new(),
+            kind: crate::scheduler_spec::ScheduleKind::Cron,
             cron: cron_expression.to_string(),
+            run_at: None,
+            every: None,
+            interval_anchor: None,
             last_run: None,
             currently_running: false,
             paused: false,
             current_session_id: None,
             process_start_time: None,
             execution_mode: Some(execution_mode.to_string()),
+            timezone: None,
+            concurrency_policy: crate::scheduler::ConcurrencyPolicy::Skip,
+            queued_run: false,
+            skipped_runs: Vec::new(),
+            retry: None,
+            on_failure: None,
+            on_success: None,
+            callback_url: None,
+            callback_secret: None,
+            run_history: Vec::new(),
+            conditions: crate::scheduler_gate::ExecutionConditions::default(),
         };
 
         match scheduler.add_scheduled_job(job).await {
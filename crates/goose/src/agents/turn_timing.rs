@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Where the time of one assistant turn went: waiting on the model or running tools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TurnTiming {
+    /// From sending the provider request to the first streamed chunk
+    pub time_to_first_token: Option<Duration>,
+    /// Time spent on the provider request, not counting tool calls made while it streamed
+    pub model_time: Duration,
+    /// Time spent running the turn's tool calls, including waiting for approval
+    pub tool_time: Duration,
+}
+
+/// Median and 95th percentile of a latency, in milliseconds
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+pub struct LatencySummary {
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+/// Model and tool time over the turns of a reply
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct TimingSummary {
+    pub turns: usize,
+    pub total_model_ms: u64,
+    pub total_tool_ms: u64,
+    /// Absent when no turn received a streamed chunk
+    pub time_to_first_token: Option<LatencySummary>,
+    pub model_time: LatencySummary,
+    pub tool_time: LatencySummary,
+}
+
+/// Collects the timing of each turn as a reply streams
+#[derive(Debug, Clone, Default)]
+pub struct SessionTimings {
+    turns: Vec<TurnTiming>,
+}
+
+impl SessionTimings {
+    pub fn record(&mut self, turn: TurnTiming) {
+        self.turns.push(turn);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    pub fn summary(&self) -> TimingSummary {
+        let model_ms: Vec<u64> = self.turns.iter().map(|t| millis(t.model_time)).collect();
+        let tool_ms: Vec<u64> = self.turns.iter().map(|t| millis(t.tool_time)).collect();
+        let first_token_ms: Vec<u64> = self
+            .turns
+            .iter()
+            .filter_map(|t| t.time_to_first_token.map(millis))
+            .collect();
+
+        TimingSummary {
+            turns: self.turns.len(),
+            total_model_ms: model_ms.iter().sum(),
+            total_tool_ms: tool_ms.iter().sum(),
+            time_to_first_token: (!first_token_ms.is_empty())
+                .then(|| LatencySummary::of(first_token_ms)),
+            model_time: LatencySummary::of(model_ms),
+            tool_time: LatencySummary::of(tool_ms),
+        }
+    }
+}
+
+impl LatencySummary {
+    fn of(mut values: Vec<u64>) -> Self {
+        values.sort_unstable();
+        Self {
+            p50_ms: percentile(&values, 50),
+            p95_ms: percentile(&values, 95),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * percent).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(first_token_ms: Option<u64>, model_ms: u64, tool_ms: u64) -> TurnTiming {
+        TurnTiming {
+            time_to_first_token: first_token_ms.map(Duration::from_millis),
+            model_time: Duration::from_millis(model_ms),
+            tool_time: Duration::from_millis(tool_ms),
+        }
+    }
+
+    #[test]
+    fn test_summary_percentiles() {
+        let mut timings = SessionTimings::default();
+        for i in 1..=20 {
+            timings.record(turn(Some(i * 10), i * 100, if i % 2 == 0 { 50 } else { 0 }));
+        }
+
+        let summary = timings.summary();
+        assert_eq!(summary.turns, 20);
+        assert_eq!(summary.total_model_ms, 21_000);
+        assert_eq!(summary.total_tool_ms, 500);
+        assert_eq!(
+            summary.time_to_first_token,
+            Some(LatencySummary {
+                p50_ms: 100,
+                p95_ms: 190
+            })
+        );
+        assert_eq!(
+            summary.model_time,
+            LatencySummary {
+                p50_ms: 1000,
+                p95_ms: 1900
+            }
+        );
+        assert_eq!(summary.tool_time.p95_ms, 50);
+    }
+
+    #[test]
+    fn test_summary_without_streamed_chunks() {
+        let mut timings = SessionTimings::default();
+        assert!(timings.is_empty());
+        timings.record(turn(None, 300, 0));
+
+        let summary = timings.summary();
+        assert_eq!(summary.time_to_first_token, None);
+        assert_eq!(summary.model_time.p50_ms, 300);
+        assert_eq!(summary.model_time.p95_ms, 300);
+    }
+}
@@ -1,7 +1,10 @@
+use crate::config::Persona;
 use crate::session;
+use chrono::{DateTime, Utc};
 use mcp_core::ToolResult;
 use rmcp::model::{Content, Tool};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
@@ -16,6 +19,32 @@ pub const DEFAULT_RETRY_TIMEOUT_SECONDS: u64 = 300;
 /// Default timeout for on_failure operations (10 minutes - longer for on_failure tasks)
 pub const DEFAULT_ON_FAILURE_TIMEOUT_SECONDS: u64 = 600;
 
+/// Default time to wait for the user to answer a tool confirmation (5 minutes)
+pub const DEFAULT_CONFIRMATION_TIMEOUT_SECONDS: u64 = 300;
+
+/// Default time to wait for a client to return the result of a frontend tool (2 minutes)
+pub const DEFAULT_FRONTEND_TOOL_TIMEOUT_SECONDS: u64 = 120;
+
+/// Tool confirmations waiting for the user's answer, keyed by tool request id
+pub type PendingConfirmations = std::sync::Mutex<HashMap<String, PendingConfirmation>>;
+
+/// Request ids of the confirmations that stopped waiting most recently, oldest first
+pub type ResolvedConfirmations = std::sync::Mutex<VecDeque<String>>;
+
+/// Number of resolved confirmations remembered
+pub const RESOLVED_CONFIRMATIONS_KEPT: usize = 256;
+
+/// A tool confirmation request that has been shown to the user but not answered yet
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PendingConfirmation {
+    /// ID of the tool request awaiting confirmation
+    pub request_id: String,
+    /// Session the request belongs to, if the reply was started for one
+    pub session_id: Option<String>,
+    pub tool_name: String,
+    pub requested_at: DateTime<Utc>,
+}
+
 /// Configuration for retry logic in recipe execution
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RetryConfig {
@@ -78,6 +107,35 @@ pub struct FrontendTool {
     pub tool: Tool,
 }
 
+/// How the agent may use tools in a reply, chosen by each request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyExecutionMode {
+    /// Tools run as `GOOSE_MODE` and the stored permissions allow
+    #[default]
+    Auto,
+    /// The agent writes a plan for the request and stops without running any tools
+    Plan,
+    /// Every tool call waits for the user's confirmation, even tools that are always allowed.
+    /// Tools that are never allowed are still denied.
+    ApproveEachStep,
+    /// Tools run as in `Auto`, but nobody is there to confirm them, so calls that would wait
+    /// for the user's confirmation are declined
+    Unattended,
+}
+
+impl ReplyExecutionMode {
+    /// The value of [`SessionConfig::execution_mode`] that selects this mode
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplyExecutionMode::Auto => "auto",
+            ReplyExecutionMode::Plan => "plan",
+            ReplyExecutionMode::ApproveEachStep => "approve_each_step",
+            ReplyExecutionMode::Unattended => "unattended",
+        }
+    }
+}
+
 /// Session configuration for an agent
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
@@ -87,11 +145,30 @@ pub struct SessionConfig {
     pub working_dir: PathBuf,
     /// ID of the schedule that triggered this session, if any
     pub schedule_id: Option<String>,
-    /// Execution mode for scheduled jobs: "foreground" or "background"
+    /// Execution mode: "foreground" or "background" for scheduled jobs, or one of the
+    /// [`ReplyExecutionMode`] values for interactive replies
     pub execution_mode: Option<String>,
     /// Maximum number of turns (iterations) allowed without user input
     pub max_turns: Option<u32>,
     /// Retry configuration for automated validation and recovery
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry_config: Option<RetryConfig>,
+    /// Extensions whose tools the session may use, such as those declared by the recipe it
+    /// runs. Platform tools are always available. All loaded extensions are allowed when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_extensions: Option<Vec<String>>,
+    /// Persona the session replies as. Its instructions, model and permission rules apply to
+    /// the reply; its extensions are resolved into `allowed_extensions` by the caller, so an
+    /// explicit restriction such as a recipe's takes precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<Persona>,
+    /// Mode for tool calls, such as "approve", used instead of the configured GOOSE_MODE
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goose_mode: Option<String>,
+    /// Permissions file to check tool calls against instead of the user's permission.yaml
+    #[serde(skip)]
+    pub permissions_path: Option<PathBuf>,
+    /// Results to answer tool calls with instead of running the tools, when replaying a session
+    #[serde(skip)]
+    pub recorded_tool_results: Option<Arc<session::RecordedToolResults>>,
 }
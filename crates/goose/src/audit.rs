@@ -0,0 +1,648 @@
+//! Append-only audit log of tool executions and permission decisions.
+//!
+//! Every tool call the agent handles is written as one JSON line to `audit/audit.jsonl` under
+//! the goose state directory (`~/.local/state/goose` on macOS and Linux), along with every
+//! confirmation answered through the server. Each entry carries the hash of the previous entry
+//! and its own hash, so editing or removing a line breaks the chain; see [`verify_chain`]. Every
+//! append locks the file and links to the entry last written there, so several goose processes
+//! can share one log.
+//!
+//! Arguments are recorded as a SHA-256 digest unless `GOOSE_AUDIT_LOG_FULL_ARGUMENTS` is set.
+//! The log can be disabled with `GOOSE_AUDIT_LOG_ENABLED=false`.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use etcetera::{choose_app_strategy, AppStrategy};
+use fs2::FileExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::config::{Config, APP_STRATEGY};
+
+const AUDIT_LOG_ENABLED_KEY: &str = "GOOSE_AUDIT_LOG_ENABLED";
+const AUDIT_LOG_FULL_ARGUMENTS_KEY: &str = "GOOSE_AUDIT_LOG_FULL_ARGUMENTS";
+
+/// `prev_hash` of the first entry in the log
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Bytes read at a time when looking for the last entry from the end of the file
+const TAIL_CHUNK: u64 = 8 * 1024;
+
+static GLOBAL_AUDIT_LOG: Lazy<Arc<AuditLog>> = Lazy::new(|| Arc::new(AuditLog::from_config()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A tool call, written once it has finished or was denied
+    ToolCall,
+    /// A user's answer to a tool confirmation
+    Confirmation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDecision {
+    Allowed,
+    Denied,
+}
+
+/// Who made the permission decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionSource {
+    /// A configured permission rule
+    Rule,
+    /// The user, answering a confirmation
+    User,
+    /// The goose mode, read-only detection or a stored always allow/deny
+    Policy,
+    /// Nobody answered the confirmation in time
+    Timeout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditStatus {
+    Success,
+    Error,
+    Denied,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub event: AuditEvent,
+    pub session_id: Option<String>,
+    pub request_id: String,
+    pub extension: Option<String>,
+    pub tool_name: Option<String>,
+    /// SHA-256 of the JSON arguments
+    pub arguments_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Value>,
+    pub decision: AuditDecision,
+    pub decided_by: DecisionSource,
+    /// The shell command pattern that decided the call, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_pattern: Option<String>,
+    pub status: Option<AuditStatus>,
+    pub duration_ms: Option<u64>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// SHA-256 over the entry serialized with an empty `hash`
+    pub fn compute_hash(&self) -> String {
+        let unhashed = AuditEntry {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let bytes = serde_json::to_vec(&unhashed).expect("audit entries serialize to JSON");
+        format!("{:x}", Sha256::digest(bytes))
+    }
+}
+
+/// Result of checking the hash chain of an audit log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainVerification {
+    Valid {
+        entries: usize,
+    },
+    /// The first line, counting from 1, that does not match the chain
+    Broken {
+        line: usize,
+        reason: String,
+    },
+}
+
+/// A tool call that was allowed and is running, written once it finishes
+struct InFlightCall {
+    entry: AuditEntry,
+    started: Instant,
+}
+
+/// Writes audit entries to a jsonl file, linking each to the previous one
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    full_arguments: bool,
+    /// Held while appending, alongside the file lock that keeps other processes out
+    writing: Mutex<()>,
+    in_flight: Mutex<HashMap<String, InFlightCall>>,
+}
+
+/// Directory holding the audit log
+pub fn audit_log_dir() -> PathBuf {
+    // choose_app_strategy().state_dir()
+    // - macOS/Linux: ~/.local/state/goose/audit
+    // - Windows has no convention for state_dir, use data_dir instead
+    let strategy = choose_app_strategy(APP_STRATEGY.clone()).expect("goose requires a home dir");
+    strategy
+        .in_state_dir("audit")
+        .unwrap_or_else(|| strategy.in_data_dir("audit"))
+}
+
+pub fn audit_log_path() -> PathBuf {
+    audit_log_dir().join("audit.jsonl")
+}
+
+/// The extension a prefixed tool name such as `developer__shell` belongs to
+fn extension_of(tool_name: &str) -> Option<String> {
+    tool_name
+        .split_once("__")
+        .map(|(extension, _)| extension.to_string())
+}
+
+fn digest_arguments(arguments: &Value) -> String {
+    format!("{:x}", Sha256::digest(arguments.to_string()))
+}
+
+fn read_entries(path: &Path) -> io::Result<Vec<AuditEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => tracing::warn!("Skipping unreadable audit entry: {}", e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Hash of the last entry in `file`, read back from its end, or [`GENESIS_HASH`] when it has none
+fn last_hash(file: &mut File, path: &Path) -> io::Result<String> {
+    let mut start = file.seek(SeekFrom::End(0))?;
+    let mut tail = Vec::new();
+    // Read back a chunk at a time until the tail holds the whole last line
+    while start > 0 {
+        let chunk = TAIL_CHUNK.min(start);
+        start -= chunk;
+        let mut bytes = vec![0; chunk as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut bytes)?;
+        bytes.append(&mut tail);
+        tail = bytes;
+        let content_end = tail
+            .iter()
+            .rposition(|byte| !byte.is_ascii_whitespace())
+            .map_or(0, |end| end + 1);
+        if tail[..content_end].contains(&b'\n') {
+            break;
+        }
+    }
+
+    let tail = String::from_utf8_lossy(&tail);
+    let Some(line) = tail.lines().rev().find(|line| !line.trim().is_empty()) else {
+        return Ok(GENESIS_HASH.to_string());
+    };
+    match serde_json::from_str::<AuditEntry>(line) {
+        Ok(entry) => Ok(entry.hash),
+        // The last line is unreadable, so link to the last entry that can be read
+        Err(_) => Ok(read_entries(path)?
+            .last()
+            .map(|entry| entry.hash.clone())
+            .unwrap_or_else(|| GENESIS_HASH.to_string())),
+    }
+}
+
+/// Check that every entry's hash matches its content and links to the entry before it
+pub fn verify_chain(path: &Path) -> io::Result<ChainVerification> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(ChainVerification::Valid { entries: 0 })
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut entries = 0;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let broken = |reason: String| ChainVerification::Broken {
+            line: index + 1,
+            reason,
+        };
+        let entry: AuditEntry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(e) => return Ok(broken(format!("unreadable entry: {}", e))),
+        };
+        if entry.prev_hash != prev_hash {
+            return Ok(broken("does not link to the previous entry".to_string()));
+        }
+        if entry.compute_hash() != entry.hash {
+            return Ok(broken("hash does not match the entry".to_string()));
+        }
+        prev_hash = entry.hash;
+        entries += 1;
+    }
+    Ok(ChainVerification::Valid { entries })
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, full_arguments: bool) -> Self {
+        Self {
+            path: Some(path),
+            full_arguments,
+            writing: Mutex::new(()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A log that records nothing
+    pub fn disabled() -> Self {
+        Self {
+            path: None,
+            full_arguments: false,
+            writing: Mutex::new(()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        if !config
+            .get_param::<bool>(AUDIT_LOG_ENABLED_KEY)
+            .unwrap_or(true)
+        {
+            return Self::disabled();
+        }
+        let full_arguments = config
+            .get_param::<bool>(AUDIT_LOG_FULL_ARGUMENTS_KEY)
+            .unwrap_or(false);
+        Self::new(audit_log_path(), full_arguments)
+    }
+
+    /// The process-wide audit log, shared so that every writer extends the same chain
+    pub fn global() -> Arc<AuditLog> {
+        GLOBAL_AUDIT_LOG.clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new_entry(
+        &self,
+        event: AuditEvent,
+        session_id: Option<String>,
+        request_id: &str,
+        tool_name: Option<&str>,
+        arguments: Option<&Value>,
+        decision: AuditDecision,
+        decided_by: DecisionSource,
+        status: Option<AuditStatus>,
+    ) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now(),
+            event,
+            session_id,
+            request_id: request_id.to_string(),
+            extension: tool_name.and_then(extension_of),
+            tool_name: tool_name.map(str::to_string),
+            arguments_digest: arguments.map(digest_arguments),
+            arguments: arguments.filter(|_| self.full_arguments).cloned(),
+            decision,
+            decided_by,
+            matched_pattern: None,
+            status,
+            duration_ms: None,
+            prev_hash: String::new(),
+            hash: String::new(),
+        }
+    }
+
+    /// Record that a tool call was allowed; the entry is written when [`AuditLog::finish`] is called
+    pub fn begin(
+        &self,
+        session_id: Option<String>,
+        request_id: &str,
+        tool_name: &str,
+        arguments: &Value,
+        decided_by: DecisionSource,
+        matched_pattern: Option<&str>,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut entry = self.new_entry(
+            AuditEvent::ToolCall,
+            session_id,
+            request_id,
+            Some(tool_name),
+            Some(arguments),
+            AuditDecision::Allowed,
+            decided_by,
+            None,
+        );
+        entry.matched_pattern = matched_pattern.map(str::to_string);
+        self.in_flight.lock().unwrap().insert(
+            request_id.to_string(),
+            InFlightCall {
+                entry,
+                started: Instant::now(),
+            },
+        );
+    }
+
+    /// Write the entry for a call started with [`AuditLog::begin`], if it has not been written yet
+    pub fn finish(&self, request_id: &str, status: AuditStatus) {
+        let Some(call) = self.in_flight.lock().unwrap().remove(request_id) else {
+            return;
+        };
+        let mut entry = call.entry;
+        entry.status = Some(status);
+        entry.duration_ms = Some(call.started.elapsed().as_millis() as u64);
+        self.append(entry);
+    }
+
+    /// Write the entry for a tool call that was denied and never ran
+    pub fn record_denied(
+        &self,
+        session_id: Option<String>,
+        request_id: &str,
+        tool_name: &str,
+        arguments: &Value,
+        decided_by: DecisionSource,
+        matched_pattern: Option<&str>,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut entry = self.new_entry(
+            AuditEvent::ToolCall,
+            session_id,
+            request_id,
+            Some(tool_name),
+            Some(arguments),
+            AuditDecision::Denied,
+            decided_by,
+            Some(AuditStatus::Denied),
+        );
+        entry.matched_pattern = matched_pattern.map(str::to_string);
+        self.append(entry);
+    }
+
+    /// Write the user's answer to a confirmation, with the tool name when it is known
+    pub fn record_confirmation(
+        &self,
+        session_id: Option<String>,
+        request_id: &str,
+        tool_name: Option<&str>,
+        decision: AuditDecision,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let entry = self.new_entry(
+            AuditEvent::Confirmation,
+            session_id,
+            request_id,
+            tool_name,
+            None,
+            decision,
+            DecisionSource::User,
+            None,
+        );
+        self.append(entry);
+    }
+
+    fn append(&self, entry: AuditEntry) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = self.try_append(path, entry) {
+            tracing::error!("Failed to write audit log entry: {}", e);
+        }
+    }
+
+    fn try_append(&self, path: &Path, mut entry: AuditEntry) -> io::Result<()> {
+        let _writing = self.writing.lock().unwrap();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+
+        // Another process may have appended since this one last did, so the previous hash is
+        // read under the lock, right before writing
+        file.lock_exclusive()?;
+        let result = last_hash(&mut file, path).and_then(|prev_hash| {
+            entry.prev_hash = prev_hash;
+            entry.hash = entry.compute_hash();
+            let line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+            writeln!(file, "{}", line)?;
+            file.flush()
+        });
+        FileExt::unlock(&file)?;
+        result
+    }
+
+    /// Entries for `session_id`, if given, written at or after `since`, oldest first
+    pub fn query(
+        &self,
+        session_id: Option<&str>,
+        since: Option<DateTime<Utc>>,
+    ) -> io::Result<Vec<AuditEntry>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        Ok(read_entries(path)?
+            .into_iter()
+            .filter(|entry| session_id.is_none() || entry.session_id.as_deref() == session_id)
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .collect())
+    }
+
+    /// Verify the hash chain of this log's file
+    pub fn verify(&self) -> io::Result<ChainVerification> {
+        match &self.path {
+            Some(path) => verify_chain(path),
+            None => Ok(ChainVerification::Valid { entries: 0 }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn write_calls(log: &AuditLog) {
+        let args = json!({"command": "ls"});
+        log.begin(
+            Some("s1".to_string()),
+            "call_1",
+            "developer__shell",
+            &args,
+            DecisionSource::Rule,
+            None,
+        );
+        log.finish("call_1", AuditStatus::Success);
+        log.record_denied(
+            Some("s2".to_string()),
+            "call_2",
+            "developer__shell",
+            &args,
+            DecisionSource::User,
+            Some("^rm "),
+        );
+        log.record_confirmation(
+            Some("s1".to_string()),
+            "call_3",
+            Some("developer__text_editor"),
+            AuditDecision::Allowed,
+        );
+    }
+
+    #[test]
+    fn test_entries_are_chained() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        write_calls(&AuditLog::new(path.clone(), false));
+        // A new handle continues the chain from the file
+        write_calls(&AuditLog::new(path.clone(), false));
+
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0].prev_hash, GENESIS_HASH);
+        for pair in entries.windows(2) {
+            assert_eq!(pair[1].prev_hash, pair[0].hash);
+        }
+
+        let call = &entries[0];
+        assert_eq!(call.extension.as_deref(), Some("developer"));
+        assert_eq!(call.decided_by, DecisionSource::Rule);
+        assert_eq!(call.status, Some(AuditStatus::Success));
+        assert!(call.duration_ms.is_some());
+        assert_eq!(
+            call.arguments_digest.as_deref(),
+            Some(digest_arguments(&json!({"command": "ls"})).as_str())
+        );
+        assert!(call.arguments.is_none());
+        assert_eq!(entries[1].matched_pattern.as_deref(), Some("^rm "));
+
+        assert_eq!(
+            verify_chain(&path).unwrap(),
+            ChainVerification::Valid { entries: 6 }
+        );
+    }
+
+    #[test]
+    fn test_handles_sharing_a_file_keep_one_chain() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        // As two goose processes would, each with its own handle on the file
+        let first = AuditLog::new(path.clone(), false);
+        let second = AuditLog::new(path.clone(), false);
+        write_calls(&first);
+        write_calls(&second);
+        write_calls(&first);
+
+        assert_eq!(
+            verify_chain(&path).unwrap(),
+            ChainVerification::Valid { entries: 9 }
+        );
+    }
+
+    #[test]
+    fn test_last_hash_is_read_from_a_long_tail() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        // Entries longer than a chunk are still read back whole
+        let log = AuditLog::new(path.clone(), true);
+        let args = json!({"command": "x".repeat(3 * TAIL_CHUNK as usize)});
+        log.record_denied(
+            None,
+            "call_1",
+            "developer__shell",
+            &args,
+            DecisionSource::User,
+            None,
+        );
+        log.record_denied(
+            None,
+            "call_2",
+            "developer__shell",
+            &args,
+            DecisionSource::User,
+            None,
+        );
+
+        let entries = read_entries(&path).unwrap();
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(last_hash(&mut file, &path).unwrap(), entries[1].hash);
+        assert_eq!(
+            verify_chain(&path).unwrap(),
+            ChainVerification::Valid { entries: 2 }
+        );
+    }
+
+    #[test]
+    fn test_tampering_breaks_the_chain() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        write_calls(&AuditLog::new(path.clone(), false));
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replacen("\"denied\"", "\"allowed\"", 1)).unwrap();
+        assert!(matches!(
+            verify_chain(&path).unwrap(),
+            ChainVerification::Broken { line: 2, .. }
+        ));
+
+        // Dropping a line is caught by the next entry's link
+        let lines: Vec<&str> = content.lines().collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert!(matches!(
+            verify_chain(&path).unwrap(),
+            ChainVerification::Broken { line: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_query_and_full_arguments() {
+        let dir = tempdir().unwrap();
+        let log = AuditLog::new(dir.path().join("audit.jsonl"), true);
+        write_calls(&log);
+
+        let entries = log.query(Some("s1"), None).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].arguments, Some(json!({"command": "ls"})));
+        assert_eq!(entries[1].event, AuditEvent::Confirmation);
+
+        let later = entries[1].timestamp + chrono::Duration::seconds(1);
+        assert!(log.query(None, Some(later)).unwrap().is_empty());
+        assert_eq!(log.query(None, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_disabled_log_writes_nothing() {
+        let log = AuditLog::disabled();
+        write_calls(&log);
+        assert!(log.query(None, None).unwrap().is_empty());
+    }
+}
@@ -2,14 +2,17 @@ use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
 use fs2::FileExt;
 use keyring::Entry;
 use once_cell::sync::{Lazy, OnceCell};
-use serde::Deserialize;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use thiserror::Error;
+use utoipa::ToSchema;
 
 pub static APP_STRATEGY: Lazy<AppStrategyArgs> = Lazy::new(|| AppStrategyArgs {
     top_level_domain: "Block".to_string(),
@@ -20,6 +23,17 @@ pub static APP_STRATEGY: Lazy<AppStrategyArgs> = Lazy::new(|| AppStrategyArgs {
 const KEYRING_SERVICE: &str = "goose";
 const KEYRING_USERNAME: &str = "secrets";
 
+/// The config file section holding named profiles, each a map of values that override the
+/// base values while the profile is active
+pub const PROFILES_KEY: &str = "profiles";
+pub const PROFILE_ENV_VAR: &str = "GOOSE_PROFILE";
+
+/// `${env:VAR_NAME}` or `${env:VAR_NAME:-default}` inside a string value
+static ENV_REFERENCE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\$\{env:([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}")
+        .expect("env reference pattern is valid")
+});
+
 #[cfg(test)]
 const TEST_KEYRING_SERVICE: &str = "goose-test";
 
@@ -37,6 +51,8 @@ pub enum ConfigError {
     KeyringError(String),
     #[error("Failed to lock config file: {0}")]
     LockError(String),
+    #[error("Config value '{key}' references environment variable {var}, which is not set and has no default")]
+    UnsetEnvVar { key: String, var: String },
 }
 
 impl From<serde_json::Error> for ConfigError {
@@ -69,14 +85,30 @@ impl From<keyring::Error> for ConfigError {
 ///
 /// Configuration values are loaded with the following precedence:
 /// 1. Environment variables (exact key match)
-/// 2. Configuration file (~/.config/goose/config.yaml by default)
+/// 2. The active profile's section of the configuration file, if a profile is active
+/// 3. Configuration file (~/.config/goose/config.yaml by default)
 ///
 /// Secrets are loaded with the following precedence:
 /// 1. Environment variables (exact key match)
-/// 2. System keyring (which can be disabled with GOOSE_DISABLE_KEYRING)
-/// 3. If the keyring is disabled, secrets are stored in a secrets file
+/// 2. The active profile's secrets, if a profile is active
+/// 3. System keyring (which can be disabled with GOOSE_DISABLE_KEYRING)
+/// 4. If the keyring is disabled, secrets are stored in a secrets file
 ///    (~/.config/goose/secrets.yaml by default)
 ///
+/// # Profiles
+/// Named profiles live under the `profiles` key of the configuration file:
+///
+/// ```yaml
+/// GOOSE_PROVIDER: openai
+/// profiles:
+///   work:
+///     GOOSE_PROVIDER: databricks
+/// ```
+///
+/// A profile is selected with the GOOSE_PROFILE environment variable at startup or with
+/// [`Config::set_profile`] at runtime. While a profile is active, writes go to its section and
+/// to its own secret storage, so switching back leaves the base values untouched.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -106,6 +138,9 @@ impl From<keyring::Error> for ConfigError {
 pub struct Config {
     config_path: PathBuf,
     secrets: SecretStorage,
+    profile: RwLock<Option<String>>,
+    /// Values from the last successful [`Config::reload`], served instead of reading the file
+    cache: RwLock<Option<HashMap<String, Value>>>,
 }
 
 enum SecretStorage {
@@ -137,13 +172,72 @@ impl Default for Config {
                 service: KEYRING_SERVICE.to_string(),
             },
         };
+        let profile = env::var(PROFILE_ENV_VAR)
+            .ok()
+            .filter(|profile| !profile.is_empty())
+            .filter(|profile| {
+                let valid = is_valid_profile_name(profile);
+                if !valid {
+                    tracing::warn!("Ignoring invalid {} '{}'", PROFILE_ENV_VAR, profile);
+                }
+                valid
+            });
+
         Config {
             config_path,
             secrets,
+            profile: RwLock::new(profile),
+            cache: RwLock::new(None),
         }
     }
 }
 
+/// Profile names end up in secret storage names, so they are kept to letters, digits, `-` and `_`
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// What [`Config::migrate_secrets`] moved out of the config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct SecretMigrationReport {
+    pub dry_run: bool,
+    /// Paths of the values moved into the keyring, or that would be for a dry run
+    pub moved: Vec<String>,
+    /// Values left in the config file
+    pub failed: Vec<SecretMigrationFailure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SecretMigrationFailure {
+    /// Path of the value, such as `OPENAI_API_KEY` or `profiles.work.OPENAI_API_KEY`
+    pub path: String,
+    pub error: String,
+}
+
+/// What a [`Config::reload`] changed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct ConfigReload {
+    /// Keys that were added, changed or removed, sorted
+    pub changed_keys: Vec<String>,
+    /// The effective provider after the reload
+    pub provider: Option<String>,
+    /// The effective model after the reload
+    pub model: Option<String>,
+    /// Whether the effective provider or model changed
+    pub provider_changed: bool,
+}
+
+/// Config values with the active profile applied
+#[derive(Debug, Clone, Default)]
+pub struct EffectiveValues {
+    pub values: HashMap<String, Value>,
+    /// The profile each overridden key comes from; other keys come from the base config
+    pub sources: HashMap<String, String>,
+}
+
 impl Config {
     /// Get the global configuration instance.
     ///
@@ -163,6 +257,8 @@ impl Config {
             secrets: SecretStorage::Keyring {
                 service: service.to_string(),
             },
+            profile: RwLock::new(None),
+            cache: RwLock::new(None),
         })
     }
 
@@ -179,6 +275,8 @@ impl Config {
             secrets: SecretStorage::File {
                 path: secrets_path.as_ref().to_path_buf(),
             },
+            profile: RwLock::new(None),
+            cache: RwLock::new(None),
         })
     }
 
@@ -197,8 +295,150 @@ impl Config {
         self.config_path.to_string_lossy().to_string()
     }
 
+    /// The active profile, if any
+    pub fn profile(&self) -> Option<String> {
+        self.profile
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Switch to the profile `name`, or back to the base values with `None`. Values are read
+    /// from the file on every lookup, so the switch applies to everything read afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns a ConfigError if the profile isn't defined in the config file
+    pub fn set_profile(&self, name: Option<&str>) -> Result<(), ConfigError> {
+        if let Some(name) = name {
+            if !self.list_profiles()?.iter().any(|profile| profile == name) {
+                return Err(ConfigError::NotFound(format!("profile '{}'", name)));
+            }
+        }
+        *self
+            .profile
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = name.map(str::to_string);
+        Ok(())
+    }
+
+    /// Names of the profiles defined in the config file, sorted
+    pub fn list_profiles(&self) -> Result<Vec<String>, ConfigError> {
+        let values = self.load_values()?;
+        let mut profiles: Vec<String> = match values.get(PROFILES_KEY) {
+            Some(Value::Object(profiles)) => profiles
+                .keys()
+                .filter(|name| is_valid_profile_name(name))
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        };
+        profiles.sort();
+        Ok(profiles)
+    }
+
+    fn profile_value<'a>(
+        values: &'a HashMap<String, Value>,
+        profile: &str,
+        key: &str,
+    ) -> Option<&'a Value> {
+        values.get(PROFILES_KEY)?.get(profile)?.get(key)
+    }
+
+    /// The section of `values` that writes go to while `profile` is active
+    fn profile_section<'a>(
+        values: &'a mut HashMap<String, Value>,
+        profile: &str,
+    ) -> &'a mut serde_json::Map<String, Value> {
+        let profiles = values
+            .entry(PROFILES_KEY.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+        if !profiles.is_object() {
+            *profiles = Value::Object(Default::default());
+        }
+        let section = profiles
+            .as_object_mut()
+            .expect("profiles is an object")
+            .entry(profile.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+        if !section.is_object() {
+            *section = Value::Object(Default::default());
+        }
+        section.as_object_mut().expect("profile is an object")
+    }
+
+    /// The values lookups see with the active profile applied, and which profile each overridden
+    /// key comes from. Environment variables aren't included.
+    pub fn load_effective_values(&self) -> Result<EffectiveValues, ConfigError> {
+        let mut values = self.load_values()?;
+        let mut sources = HashMap::new();
+        if let Some(profile) = self.profile() {
+            let overrides = values
+                .get(PROFILES_KEY)
+                .and_then(|profiles| profiles.get(&profile))
+                .and_then(Value::as_object)
+                .cloned()
+                .unwrap_or_default();
+            for (key, value) in overrides {
+                sources.insert(key.clone(), profile.clone());
+                values.insert(key, value);
+            }
+        }
+        Ok(EffectiveValues { values, sources })
+    }
+
+    /// Read the config file again and serve its values until the next reload or write.
+    ///
+    /// Until the first reload every lookup reads the file. Afterwards lookups use the reloaded
+    /// values, so an editor's half-written file is never seen: if the file doesn't parse, the
+    /// previous values are kept and the parse error is returned. Unlike [`Config::load_values`],
+    /// this never restores a backup over the file.
+    pub fn reload(&self) -> Result<ConfigReload, ConfigError> {
+        let content = match std::fs::read_to_string(&self.config_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let new_values = self.parse_yaml_content(&content)?;
+        let old_values = self.load_values().unwrap_or_default();
+        let old_provider = self.get_param::<String>("GOOSE_PROVIDER").ok();
+        let old_model = self.get_param::<String>("GOOSE_MODEL").ok();
+
+        let mut changed_keys: Vec<String> = old_values
+            .keys()
+            .chain(new_values.keys())
+            .filter(|key| old_values.get(*key) != new_values.get(*key))
+            .cloned()
+            .collect();
+        changed_keys.sort();
+        changed_keys.dedup();
+
+        *self
+            .cache
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(new_values);
+
+        let provider = self.get_param::<String>("GOOSE_PROVIDER").ok();
+        let model = self.get_param::<String>("GOOSE_MODEL").ok();
+        Ok(ConfigReload {
+            changed_keys,
+            provider_changed: provider != old_provider || model != old_model,
+            provider,
+            model,
+        })
+    }
+
     // Load current values from the config file
     pub fn load_values(&self) -> Result<HashMap<String, Value>, ConfigError> {
+        if let Some(values) = self
+            .cache
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+        {
+            return Ok(values.clone());
+        }
+
         if self.config_path.exists() {
             self.load_values_with_recovery()
         } else {
@@ -344,7 +584,7 @@ impl Config {
     fn get_backup_paths(&self) -> Vec<PathBuf> {
         let mut paths = Vec::new();
 
-        // Primary backup (created by backup_config endpoint)
+        // Primary backup (written before each save)
         if let Some(file_name) = self.config_path.file_name() {
             let mut backup_name = file_name.to_os_string();
             backup_name.push(".bak");
@@ -406,6 +646,14 @@ impl Config {
         // Atomically replace the original file
         std::fs::rename(&temp_path, &self.config_path)?;
 
+        let mut cache = self
+            .cache
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if cache.is_some() {
+            *cache = Some(values);
+        }
+
         Ok(())
     }
 
@@ -476,11 +724,137 @@ impl Config {
         Ok(())
     }
 
-    // Load current secrets from the keyring
+    /// Load the secrets lookups see: the base secrets with the active profile's secrets on top
     pub fn load_secrets(&self) -> Result<HashMap<String, Value>, ConfigError> {
+        let mut values = self.load_secret_store(None)?;
+        if let Some(profile) = self.profile() {
+            values.extend(self.load_secret_store(Some(&profile))?);
+        }
+        Ok(values)
+    }
+
+    /// Each profile keeps its secrets in their own keyring entry or file
+    fn keyring_username(profile: Option<&str>) -> String {
+        match profile {
+            Some(profile) => format!("{}:{}", KEYRING_USERNAME, profile),
+            None => KEYRING_USERNAME.to_string(),
+        }
+    }
+
+    fn secrets_file_path(path: &Path, profile: Option<&str>) -> PathBuf {
+        match profile {
+            Some(profile) => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let name = match path.extension() {
+                    Some(extension) => {
+                        format!("{}.{}.{}", stem, profile, extension.to_string_lossy())
+                    }
+                    None => format!("{}.{}", stem, profile),
+                };
+                path.with_file_name(name)
+            }
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Move plaintext values of `secret_keys` out of the config file, including profile
+    /// sections, into the system keyring. A value is only removed from the file once the
+    /// keyring holds it, and a value that differs from one already in the keyring is left
+    /// alone. With `dry_run` the keyring is read to check it works but nothing is changed.
+    pub fn migrate_secrets(
+        &self,
+        secret_keys: &[String],
+        dry_run: bool,
+    ) -> Result<SecretMigrationReport, ConfigError> {
+        let mut values = self.load_values()?;
+        let mut report = SecretMigrationReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        let mut candidates: Vec<(Option<String>, String, Value)> = secret_keys
+            .iter()
+            .filter_map(|key| Some((None, key.clone(), values.get(key)?.clone())))
+            .collect();
+        if let Some(Value::Object(profiles)) = values.get(PROFILES_KEY) {
+            for (profile, section) in profiles {
+                for key in secret_keys {
+                    if let Some(value) = section.get(key) {
+                        candidates.push((Some(profile.clone()), key.clone(), value.clone()));
+                    }
+                }
+            }
+        }
+        let path = |profile: &Option<String>, key: &str| match profile {
+            Some(profile) => format!("{}.{}.{}", PROFILES_KEY, profile, key),
+            None => key.to_string(),
+        };
+        candidates.sort_by_key(|(profile, key, _)| path(profile, key));
+
+        if let SecretStorage::File { .. } = self.secrets {
+            report.failed = candidates
+                .iter()
+                .map(|(profile, key, _)| SecretMigrationFailure {
+                    path: path(profile, key),
+                    error: "The system keyring is disabled".to_string(),
+                })
+                .collect();
+            return Ok(report);
+        }
+
+        let mut moved = Vec::new();
+        for (profile, key, value) in candidates {
+            let result = self
+                .load_secret_store(profile.as_deref())
+                .map_err(|e| e.to_string())
+                .and_then(|mut store| match store.get(&key) {
+                    Some(existing) if *existing != value => {
+                        Err("A different value is already in the keyring".to_string())
+                    }
+                    Some(_) => Ok(()),
+                    None if dry_run => Ok(()),
+                    None => {
+                        store.insert(key.clone(), value);
+                        self.save_secret_store(profile.as_deref(), &store)
+                            .map_err(|e| e.to_string())
+                    }
+                });
+            match result {
+                Ok(()) => {
+                    report.moved.push(path(&profile, &key));
+                    moved.push((profile, key));
+                }
+                Err(error) => report.failed.push(SecretMigrationFailure {
+                    path: path(&profile, &key),
+                    error,
+                }),
+            }
+        }
+
+        if !dry_run && !moved.is_empty() {
+            for (profile, key) in moved {
+                match profile {
+                    Some(profile) => {
+                        Self::profile_section(&mut values, &profile).remove(&key);
+                    }
+                    None => {
+                        values.remove(&key);
+                    }
+                }
+            }
+            self.save_values(values)?;
+        }
+        Ok(report)
+    }
+
+    // Load the secrets of one profile, or the base secrets, from the keyring
+    fn load_secret_store(
+        &self,
+        profile: Option<&str>,
+    ) -> Result<HashMap<String, Value>, ConfigError> {
         match &self.secrets {
             SecretStorage::Keyring { service } => {
-                let entry = Entry::new(service, KEYRING_USERNAME)?;
+                let entry = Entry::new(service, &Self::keyring_username(profile))?;
 
                 match entry.get_password() {
                     Ok(content) => {
@@ -492,6 +866,7 @@ impl Config {
                 }
             }
             SecretStorage::File { path } => {
+                let path = Self::secrets_file_path(path, profile);
                 if path.exists() {
                     let file_content = std::fs::read_to_string(path)?;
                     let yaml_value: serde_yaml::Value = serde_yaml::from_str(&file_content)?;
@@ -507,6 +882,25 @@ impl Config {
         }
     }
 
+    fn save_secret_store(
+        &self,
+        profile: Option<&str>,
+        values: &HashMap<String, Value>,
+    ) -> Result<(), ConfigError> {
+        match &self.secrets {
+            SecretStorage::Keyring { service } => {
+                let json_value = serde_json::to_string(values)?;
+                let entry = Entry::new(service, &Self::keyring_username(profile))?;
+                entry.set_password(&json_value)?;
+            }
+            SecretStorage::File { path } => {
+                let yaml_value = serde_yaml::to_string(values)?;
+                std::fs::write(Self::secrets_file_path(path, profile), yaml_value)?;
+            }
+        };
+        Ok(())
+    }
+
     /// Parse an environment variable value into a JSON Value.
     ///
     /// This function tries to intelligently parse environment variable values:
@@ -540,6 +934,50 @@ impl Config {
         Ok(Value::String(val.to_string()))
     }
 
+    /// Replace `${env:VAR_NAME}` references in the strings of `value` with the variable's value,
+    /// or with the default given as `${env:VAR_NAME:-default}` when it isn't set. Values are
+    /// stored with their references, so this only happens when they are read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::UnsetEnvVar` naming `key` if a referenced variable isn't set and has
+    /// no default
+    pub fn interpolate_env_vars(key: &str, value: Value) -> Result<Value, ConfigError> {
+        match value {
+            Value::String(text) => {
+                let mut unset = None;
+                let interpolated = ENV_REFERENCE.replace_all(&text, |captures: &Captures| {
+                    match (env::var(&captures[1]), captures.get(2)) {
+                        (Ok(value), _) => value,
+                        (Err(_), Some(default)) => default.as_str().to_string(),
+                        (Err(_), None) => {
+                            unset.get_or_insert_with(|| captures[1].to_string());
+                            String::new()
+                        }
+                    }
+                });
+                match unset {
+                    Some(var) => Err(ConfigError::UnsetEnvVar {
+                        key: key.to_string(),
+                        var,
+                    }),
+                    None => Ok(Value::String(interpolated.into_owned())),
+                }
+            }
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| Self::interpolate_env_vars(key, item))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            Value::Object(map) => map
+                .into_iter()
+                .map(|(name, item)| Ok((name, Self::interpolate_env_vars(key, item)?)))
+                .collect::<Result<serde_json::Map<_, _>, ConfigError>>()
+                .map(Value::Object),
+            other => Ok(other),
+        }
+    }
+
     // check all possible places for a parameter
     pub fn get(&self, key: &str, is_secret: bool) -> Result<Value, ConfigError> {
         if is_secret {
@@ -562,7 +1000,11 @@ impl Config {
     ///
     /// This will attempt to get the value from:
     /// 1. Environment variable with the exact key name
-    /// 2. Configuration file
+    /// 2. The active profile's section of the configuration file
+    /// 3. Configuration file
+    ///
+    /// `${env:VAR_NAME}` references in values from the config file are replaced
+    /// with the variable's value, see [`Config::interpolate_env_vars`].
     ///
     /// The value will be deserialized into the requested type. This works with
     /// both simple types (String, i32, etc.) and complex types that implement
@@ -572,6 +1014,7 @@ impl Config {
     ///
     /// Returns a ConfigError if:
     /// - The key doesn't exist in either environment or config file
+    /// - The value references an unset environment variable without a default
     /// - The value cannot be deserialized into the requested type
     /// - There is an error reading the config file
     pub fn get_param<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T, ConfigError> {
@@ -582,20 +1025,35 @@ impl Config {
             return Ok(serde_json::from_value(value)?);
         }
 
-        // Load current values from file
-        let values = self.load_values()?;
+        // Then check the active profile and our stored values
+        let value = self.stored_value(key)?;
+        let value = Self::interpolate_env_vars(key, value)?;
+        Ok(serde_json::from_value(value)?)
+    }
 
-        // Then check our stored values
-        values
-            .get(key)
+    /// Get a configuration value as stored in the config file, without environment
+    /// variable overrides or `${env:VAR_NAME}` interpolation.
+    ///
+    /// Use this to read a value that is modified and written back with [`Config::set_param`],
+    /// so references are kept rather than replaced by what they resolve to.
+    pub fn get_param_raw<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T, ConfigError> {
+        Ok(serde_json::from_value(self.stored_value(key)?)?)
+    }
+
+    fn stored_value(&self, key: &str) -> Result<Value, ConfigError> {
+        let values = self.load_values()?;
+        self.profile()
+            .and_then(|profile| Self::profile_value(&values, &profile, key))
+            .or_else(|| values.get(key))
+            .cloned()
             .ok_or_else(|| ConfigError::NotFound(key.to_string()))
-            .and_then(|v| Ok(serde_json::from_value(v.clone())?))
     }
 
     /// Set a configuration value in the config file (non-secret).
     ///
     /// This will immediately write the value to the config file. The value
-    /// can be any type that can be serialized to JSON/YAML.
+    /// can be any type that can be serialized to JSON/YAML. While a profile is
+    /// active, the value is written to that profile's section.
     ///
     /// Note that this does not affect environment variables - those can only
     /// be set through the system environment.
@@ -610,16 +1068,42 @@ impl Config {
         let mut values = self.load_values()?;
 
         // Modify values
-        values.insert(key.to_string(), value);
+        match self.profile() {
+            Some(profile) => {
+                Self::profile_section(&mut values, &profile).insert(key.to_string(), value);
+            }
+            None => {
+                values.insert(key.to_string(), value);
+            }
+        }
 
         // Save all values using the atomic write approach
         self.save_values(values)
     }
 
+    /// Set several configuration values in the config file with a single write, so readers
+    /// see either all of them or none. Values go to the active profile's section like
+    /// [`Config::set_param`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a ConfigError if:
+    /// - There is an error reading or writing the config file
+    /// - There is an error serializing the values
+    pub fn set_params(&self, updates: HashMap<String, Value>) -> Result<(), ConfigError> {
+        let mut values = self.load_values()?;
+        match self.profile() {
+            Some(profile) => Self::profile_section(&mut values, &profile).extend(updates),
+            None => values.extend(updates),
+        }
+
+        self.save_values(values)
+    }
+
     /// Delete a configuration value in the config file.
     ///
-    /// This will immediately write the value to the config file. The value
-    /// can be any type that can be serialized to JSON/YAML.
+    /// This will immediately write the value to the config file. While a profile
+    /// is active, only that profile's override is removed.
     ///
     /// Note that this does not affect environment variables - those can only
     /// be set through the system environment.
@@ -631,7 +1115,14 @@ impl Config {
     /// - There is an error serializing the value
     pub fn delete(&self, key: &str) -> Result<(), ConfigError> {
         let mut values = self.load_values()?;
-        values.remove(key);
+        match self.profile() {
+            Some(profile) => {
+                Self::profile_section(&mut values, &profile).remove(key);
+            }
+            None => {
+                values.remove(key);
+            }
+        }
 
         self.save_values(values)
     }
@@ -640,7 +1131,11 @@ impl Config {
     ///
     /// This will attempt to get the value from:
     /// 1. Environment variable with the exact key name
-    /// 2. System keyring
+    /// 2. The active profile's secrets
+    /// 3. System keyring
+    ///
+    /// `${env:VAR_NAME}` references in stored secrets are replaced with the
+    /// variable's value, see [`Config::interpolate_env_vars`].
     ///
     /// The value will be deserialized into the requested type. This works with
     /// both simple types (String, i32, etc.) and complex types that implement
@@ -650,6 +1145,7 @@ impl Config {
     ///
     /// Returns a ConfigError if:
     /// - The key doesn't exist in either environment or keyring
+    /// - The value references an unset environment variable without a default
     /// - The value cannot be deserialized into the requested type
     /// - There is an error accessing the keyring
     pub fn get_secret<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<T, ConfigError> {
@@ -665,14 +1161,16 @@ impl Config {
         values
             .get(key)
             .ok_or_else(|| ConfigError::NotFound(key.to_string()))
-            .and_then(|v| Ok(serde_json::from_value(v.clone())?))
+            .and_then(|v| Self::interpolate_env_vars(key, v.clone()))
+            .and_then(|v| Ok(serde_json::from_value(v)?))
     }
 
     /// Set a secret value in the system keyring.
     ///
     /// This will store the value in a single JSON object in the system keyring,
     /// alongside any other secrets. The value can be any type that can be
-    /// serialized to JSON.
+    /// serialized to JSON. While a profile is active, the value is stored with
+    /// that profile's secrets.
     ///
     /// Note that this does not affect environment variables - those can only
     /// be set through the system environment.
@@ -683,27 +1181,17 @@ impl Config {
     /// - There is an error accessing the keyring
     /// - There is an error serializing the value
     pub fn set_secret(&self, key: &str, value: Value) -> Result<(), ConfigError> {
-        let mut values = self.load_secrets()?;
+        let profile = self.profile();
+        let mut values = self.load_secret_store(profile.as_deref())?;
         values.insert(key.to_string(), value);
-
-        match &self.secrets {
-            SecretStorage::Keyring { service } => {
-                let json_value = serde_json::to_string(&values)?;
-                let entry = Entry::new(service, KEYRING_USERNAME)?;
-                entry.set_password(&json_value)?;
-            }
-            SecretStorage::File { path } => {
-                let yaml_value = serde_yaml::to_string(&values)?;
-                std::fs::write(path, yaml_value)?;
-            }
-        };
-        Ok(())
+        self.save_secret_store(profile.as_deref(), &values)
     }
 
     /// Delete a secret from the system keyring.
     ///
     /// This will remove the specified key from the JSON object in the system keyring.
-    /// Other secrets will remain unchanged.
+    /// Other secrets will remain unchanged. While a profile is active, only that
+    /// profile's secret is removed.
     ///
     /// # Errors
     ///
@@ -711,21 +1199,10 @@ impl Config {
     /// - There is an error accessing the keyring
     /// - There is an error serializing the remaining values
     pub fn delete_secret(&self, key: &str) -> Result<(), ConfigError> {
-        let mut values = self.load_secrets()?;
+        let profile = self.profile();
+        let mut values = self.load_secret_store(profile.as_deref())?;
         values.remove(key);
-
-        match &self.secrets {
-            SecretStorage::Keyring { service } => {
-                let json_value = serde_json::to_string(&values)?;
-                let entry = Entry::new(service, KEYRING_USERNAME)?;
-                entry.set_password(&json_value)?;
-            }
-            SecretStorage::File { path } => {
-                let yaml_value = serde_yaml::to_string(&values)?;
-                std::fs::write(path, yaml_value)?;
-            }
-        };
-        Ok(())
+        self.save_secret_store(profile.as_deref(), &values)
     }
 }
 
@@ -1463,4 +1940,246 @@ mod tests {
 
         Ok(())
     }
+
+    fn profile_config(dir: &tempfile::TempDir) -> Result<Config, ConfigError> {
+        std::fs::write(
+            dir.path().join("config.yaml"),
+            "GOOSE_PROVIDER: openai\nGOOSE_MODEL: gpt-4o\nprofiles:\n  work:\n    GOOSE_PROVIDER: databricks\n",
+        )?;
+        Config::new_with_file_secrets(
+            dir.path().join("config.yaml"),
+            dir.path().join("secrets.yaml"),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn test_profile_precedence() -> Result<(), ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = profile_config(&dir)?;
+        assert_eq!(config.list_profiles()?, vec!["work"]);
+
+        config.set_profile(Some("work"))?;
+        let provider: String = config.get_param("GOOSE_PROVIDER")?;
+        assert_eq!(provider, "databricks");
+        // Keys the profile doesn't override fall back to the base values
+        let model: String = config.get_param("GOOSE_MODEL")?;
+        assert_eq!(model, "gpt-4o");
+
+        let effective = config.load_effective_values()?;
+        assert_eq!(effective.values["GOOSE_PROVIDER"], "databricks");
+        assert_eq!(effective.sources["GOOSE_PROVIDER"], "work");
+        assert!(!effective.sources.contains_key("GOOSE_MODEL"));
+
+        // Environment variables still win over the profile
+        std::env::set_var("GOOSE_PROVIDER", "anthropic");
+        let provider: String = config.get_param("GOOSE_PROVIDER")?;
+        std::env::remove_var("GOOSE_PROVIDER");
+        assert_eq!(provider, "anthropic");
+
+        assert!(matches!(
+            config.set_profile(Some("missing")),
+            Err(ConfigError::NotFound(_))
+        ));
+        assert_eq!(config.profile().as_deref(), Some("work"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_writes_and_switching() -> Result<(), ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = profile_config(&dir)?;
+
+        config.set_secret("api_key", Value::String("personal".to_string()))?;
+        config.set_profile(Some("work"))?;
+        let api_key: String = config.get_secret("api_key")?;
+        assert_eq!(api_key, "personal");
+
+        config.set_param("GOOSE_MODEL", Value::String("claude".to_string()))?;
+        config.set_secret("api_key", Value::String("work".to_string()))?;
+        let model: String = config.get_param("GOOSE_MODEL")?;
+        let api_key: String = config.get_secret("api_key")?;
+        assert_eq!(model, "claude");
+        assert_eq!(api_key, "work");
+        assert!(dir.path().join("secrets.work.yaml").exists());
+
+        // Switching back needs no restart and the base values are untouched
+        config.set_profile(None)?;
+        let model: String = config.get_param("GOOSE_MODEL")?;
+        let api_key: String = config.get_secret("api_key")?;
+        assert_eq!(model, "gpt-4o");
+        assert_eq!(api_key, "personal");
+
+        // Deleting within a profile only removes the override
+        config.set_profile(Some("work"))?;
+        config.delete("GOOSE_PROVIDER")?;
+        config.delete_secret("api_key")?;
+        let provider: String = config.get_param("GOOSE_PROVIDER")?;
+        let api_key: String = config.get_secret("api_key")?;
+        assert_eq!(provider, "openai");
+        assert_eq!(api_key, "personal");
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_env_var_interpolation() -> Result<(), ConfigError> {
+        let config_file = NamedTempFile::new().unwrap();
+        let secrets_file = NamedTempFile::new().unwrap();
+        let config = Config::new_with_file_secrets(config_file.path(), secrets_file.path())?;
+
+        std::env::set_var("GOOSE_TEST_INTERP_HOST", "db.internal");
+        std::env::remove_var("GOOSE_TEST_INTERP_PORT");
+        config.set_param(
+            "database_url",
+            Value::String(
+                "postgres://${env:GOOSE_TEST_INTERP_HOST}:${env:GOOSE_TEST_INTERP_PORT:-5432}/app"
+                    .to_string(),
+            ),
+        )?;
+        config.set_param(
+            "hosts",
+            serde_json::json!({"primary": "${env:GOOSE_TEST_INTERP_HOST}", "replicas": 2}),
+        )?;
+        config.set_secret(
+            "api_key",
+            Value::String("${env:GOOSE_TEST_INTERP_KEY:-}".to_string()),
+        )?;
+
+        let url: String = config.get_param("database_url")?;
+        assert_eq!(url, "postgres://db.internal:5432/app");
+        let hosts: HashMap<String, Value> = config.get_param("hosts")?;
+        assert_eq!(hosts["primary"], "db.internal");
+        assert_eq!(hosts["replicas"], 2);
+        let api_key: String = config.get_secret("api_key")?;
+        assert_eq!(api_key, "");
+
+        // Values are resolved at read time, not when they are stored
+        std::env::set_var("GOOSE_TEST_INTERP_HOST", "db.example.com");
+        let url: String = config.get_param("database_url")?;
+        assert_eq!(url, "postgres://db.example.com:5432/app");
+
+        // Writing a value back keeps the reference
+        let raw: Value = config.get_param_raw("database_url")?;
+        config.set_param("database_url", raw)?;
+        assert_eq!(
+            config.load_values()?["database_url"],
+            "postgres://${env:GOOSE_TEST_INTERP_HOST}:${env:GOOSE_TEST_INTERP_PORT:-5432}/app"
+        );
+        std::env::remove_var("GOOSE_TEST_INTERP_HOST");
+
+        let result: Result<String, ConfigError> = config.get_param("database_url");
+        match result {
+            Err(ConfigError::UnsetEnvVar { key, var }) => {
+                assert_eq!(key, "database_url");
+                assert_eq!(var, "GOOSE_TEST_INTERP_HOST");
+            }
+            other => panic!("expected an unset variable error, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_reload_keeps_values_when_file_is_invalid() -> Result<(), ConfigError> {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config = Config::new(temp_file.path(), TEST_KEYRING_SERVICE)?;
+        std::fs::write(
+            temp_file.path(),
+            "GOOSE_PROVIDER: openai\nGOOSE_MODEL: gpt-4o\nother: 1\n",
+        )?;
+
+        let reload = config.reload()?;
+        assert_eq!(
+            reload.changed_keys,
+            vec!["GOOSE_MODEL", "GOOSE_PROVIDER", "other"]
+        );
+        assert!(reload.provider_changed);
+
+        // An edit shows up only after the next reload
+        std::fs::write(
+            temp_file.path(),
+            "GOOSE_PROVIDER: anthropic\nGOOSE_MODEL: gpt-4o\nother: 1\n",
+        )?;
+        let provider: String = config.get_param("GOOSE_PROVIDER")?;
+        assert_eq!(provider, "openai");
+        let reload = config.reload()?;
+        assert_eq!(reload.changed_keys, vec!["GOOSE_PROVIDER"]);
+        assert_eq!(reload.provider.as_deref(), Some("anthropic"));
+        assert!(reload.provider_changed);
+
+        // A half-written file is rejected and left alone
+        std::fs::write(temp_file.path(), "GOOSE_PROVIDER: [unclosed")?;
+        assert!(matches!(
+            config.reload(),
+            Err(ConfigError::DeserializeError(_))
+        ));
+        let provider: String = config.get_param("GOOSE_PROVIDER")?;
+        assert_eq!(provider, "anthropic");
+        assert_eq!(
+            std::fs::read_to_string(temp_file.path())?,
+            "GOOSE_PROVIDER: [unclosed"
+        );
+
+        // Writes update the reloaded values
+        config.set_param("other", Value::from(2))?;
+        let other: i64 = config.get_param("other")?;
+        assert_eq!(other, 2);
+        let reload = config.reload()?;
+        assert!(reload.changed_keys.is_empty());
+        assert!(!reload.provider_changed);
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_migrate_secrets_to_keyring() -> Result<(), ConfigError> {
+        cleanup_keyring()?;
+        let temp_file = NamedTempFile::new().unwrap();
+        let config = Config::new(temp_file.path(), TEST_KEYRING_SERVICE)?;
+        std::fs::write(
+            temp_file.path(),
+            "GOOSE_PROVIDER: openai\nOPENAI_API_KEY: sk-plain\nOTHER_API_KEY: other\n",
+        )?;
+        config.set_secret("OTHER_API_KEY", Value::String("different".to_string()))?;
+        let keys = vec!["OPENAI_API_KEY".to_string(), "OTHER_API_KEY".to_string()];
+
+        let report = config.migrate_secrets(&keys, true)?;
+        assert_eq!(report.moved, vec!["OPENAI_API_KEY"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, "OTHER_API_KEY");
+        assert!(config.load_values()?.contains_key("OPENAI_API_KEY"));
+        assert!(config.get_secret::<String>("OPENAI_API_KEY").is_err());
+
+        let report = config.migrate_secrets(&keys, false)?;
+        assert_eq!(report.moved, vec!["OPENAI_API_KEY"]);
+        let values = config.load_values()?;
+        assert!(!values.contains_key("OPENAI_API_KEY"));
+        assert_eq!(values["OTHER_API_KEY"], "other");
+        let api_key: String = config.get_secret("OPENAI_API_KEY")?;
+        assert_eq!(api_key, "sk-plain");
+
+        cleanup_keyring()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_secrets_without_keyring_keeps_values() -> Result<(), ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = profile_config(&dir)?;
+        let mut values = config.load_values()?;
+        values.insert("OPENAI_API_KEY".to_string(), Value::from("sk-plain"));
+        config.save_values(values)?;
+
+        let report = config.migrate_secrets(&["OPENAI_API_KEY".to_string()], false)?;
+        assert!(report.moved.is_empty());
+        assert_eq!(report.failed[0].path, "OPENAI_API_KEY");
+        assert_eq!(config.load_values()?["OPENAI_API_KEY"], "sk-plain");
+
+        Ok(())
+    }
 }
@@ -0,0 +1,659 @@
+//! Export and import of the whole goose configuration as one JSON bundle, for moving it to
+//! another machine and for config backups.
+//!
+//! A bundle holds the config file values, the extension entries and the contents of
+//! permission.yaml. Secrets are only included when a passphrase is given, in which case they
+//! are encrypted with AES-256-GCM under a key derived from the passphrase with Argon2id.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::base::{Config, ConfigError};
+use super::extensions::ExtensionEntry;
+use super::permission::{PermissionFile, PermissionManager};
+
+pub const BUNDLE_VERSION: u32 = 1;
+
+const EXTENSIONS_KEY: &str = "extensions";
+const KDF_NAME: &str = "argon2id";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Error, Debug)]
+pub enum ConfigBundleError {
+    #[error("Config bundle version {0} is not supported, expected version {BUNDLE_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("Config bundle contains encrypted secrets, a passphrase is required to import it")]
+    PassphraseRequired,
+    #[error("Secrets in the config bundle could not be decrypted with the given passphrase")]
+    DecryptionFailed,
+    #[error("Failed to encrypt secrets for the config bundle")]
+    EncryptionFailed,
+    #[error("Config bundle is malformed: {0}")]
+    Malformed(String),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("Failed to access config backup: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl ConfigBundleError {
+    /// Whether the error is a problem with the bundle or passphrase rather than with local
+    /// config storage
+    pub fn is_invalid_bundle(&self) -> bool {
+        !matches!(
+            self,
+            ConfigBundleError::Config(_)
+                | ConfigBundleError::Io(_)
+                | ConfigBundleError::EncryptionFailed
+        )
+    }
+}
+
+/// Secrets encrypted with a key derived from a passphrase
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct EncryptedSecrets {
+    /// Key derivation function, always `argon2id`
+    pub kdf: String,
+    /// Base64 encoded KDF salt
+    pub salt: String,
+    /// Base64 encoded AES-GCM nonce
+    pub nonce: String,
+    /// Base64 encoded AES-256-GCM encryption of the secrets as a JSON object
+    pub ciphertext: String,
+}
+
+/// Everything needed to set goose up the same way on another machine
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ConfigBundle {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    /// Config file values other than extensions, as stored (`${env:VAR}` references are kept)
+    #[schema(value_type = Object)]
+    pub config: HashMap<String, Value>,
+    /// Extension entries by key
+    pub extensions: HashMap<String, ExtensionEntry>,
+    /// The contents of permission.yaml
+    #[schema(value_type = Object)]
+    pub permissions: PermissionFile,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<EncryptedSecrets>,
+}
+
+/// How entries that exist both locally and in an imported bundle are resolved
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Keep the local value
+    #[default]
+    KeepExisting,
+    /// Replace the local value with the one from the bundle
+    Overwrite,
+}
+
+/// What importing a bundle changed, or would change for a dry run. Entries are named by
+/// section and key, like `config.GOOSE_MODEL`, `extensions.developer`, `secrets.OPENAI_API_KEY`
+/// or `permissions.user.developer__shell`.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct ConfigImportReport {
+    pub dry_run: bool,
+    /// Entries written from the bundle
+    pub applied: Vec<String>,
+    /// Entries that differ between the local config and the bundle
+    pub conflicts: Vec<String>,
+    /// Conflicting entries left as they were because of the merge strategy
+    pub skipped: Vec<String>,
+}
+
+impl ConfigImportReport {
+    /// Record an entry of the bundle; returns whether it should be written
+    fn merge<T: PartialEq>(
+        &mut self,
+        path: String,
+        existing: Option<&T>,
+        imported: &T,
+        strategy: MergeStrategy,
+    ) -> bool {
+        match existing {
+            Some(existing) if existing == imported => false,
+            Some(_) => {
+                self.conflicts.push(path.clone());
+                if strategy == MergeStrategy::Overwrite {
+                    self.applied.push(path);
+                    true
+                } else {
+                    self.skipped.push(path);
+                    false
+                }
+            }
+            None => {
+                self.applied.push(path);
+                true
+            }
+        }
+    }
+}
+
+/// Bundle the current config. Secrets are included, encrypted, only when `passphrase` is given.
+pub fn export_config(
+    config: &Config,
+    permissions: &PermissionManager,
+    passphrase: Option<&str>,
+) -> Result<ConfigBundle, ConfigBundleError> {
+    let mut values = config.load_values()?;
+    let extensions = match values.remove(EXTENSIONS_KEY) {
+        Some(Value::Object(entries)) => entries
+            .into_iter()
+            .filter_map(
+                |(key, entry)| match serde_json::from_value::<ExtensionEntry>(entry) {
+                    Ok(entry) => Some((key, entry)),
+                    Err(e) => {
+                        tracing::warn!("Leaving invalid extension '{}' out of export: {}", key, e);
+                        None
+                    }
+                },
+            )
+            .collect(),
+        _ => HashMap::new(),
+    };
+
+    let secrets = match passphrase {
+        Some(passphrase) => Some(encrypt_secrets(&config.load_secrets()?, passphrase)?),
+        None => None,
+    };
+
+    Ok(ConfigBundle {
+        version: BUNDLE_VERSION,
+        created_at: Utc::now(),
+        config: values,
+        extensions,
+        permissions: permissions.to_file(),
+        secrets,
+    })
+}
+
+/// Merge `bundle` into the current config. With `dry_run` nothing is written and the report
+/// previews the changes.
+pub fn import_config(
+    config: &Config,
+    permissions: &mut PermissionManager,
+    bundle: &ConfigBundle,
+    strategy: MergeStrategy,
+    passphrase: Option<&str>,
+    dry_run: bool,
+) -> Result<ConfigImportReport, ConfigBundleError> {
+    if bundle.version != BUNDLE_VERSION {
+        return Err(ConfigBundleError::UnsupportedVersion(bundle.version));
+    }
+    let secrets = match (&bundle.secrets, passphrase) {
+        (Some(secrets), Some(passphrase)) => decrypt_secrets(secrets, passphrase)?,
+        (Some(_), None) => return Err(ConfigBundleError::PassphraseRequired),
+        (None, _) => HashMap::new(),
+    };
+
+    let mut report = ConfigImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let mut values = config.load_values()?;
+    let mut values_changed = false;
+    for (key, value) in sorted(&bundle.config) {
+        if key == EXTENSIONS_KEY {
+            continue;
+        }
+        if report.merge(format!("config.{}", key), values.get(key), value, strategy) {
+            values.insert(key.clone(), value.clone());
+            values_changed = true;
+        }
+    }
+
+    let mut extensions = match values.get(EXTENSIONS_KEY) {
+        Some(Value::Object(entries)) => entries.clone(),
+        _ => serde_json::Map::new(),
+    };
+    let mut extensions_changed = false;
+    for (key, entry) in sorted(&bundle.extensions) {
+        let entry = serde_json::to_value(entry)?;
+        if report.merge(
+            format!("extensions.{}", key),
+            extensions.get(key),
+            &entry,
+            strategy,
+        ) {
+            extensions.insert(key.clone(), entry);
+            extensions_changed = true;
+        }
+    }
+    if extensions_changed {
+        values.insert(EXTENSIONS_KEY.to_string(), Value::Object(extensions));
+        values_changed = true;
+    }
+
+    let existing_secrets = if secrets.is_empty() {
+        HashMap::new()
+    } else {
+        config.load_secrets()?
+    };
+    let mut secret_updates = Vec::new();
+    for (key, value) in sorted(&secrets) {
+        if report.merge(
+            format!("secrets.{}", key),
+            existing_secrets.get(key),
+            value,
+            strategy,
+        ) {
+            secret_updates.push((key, value));
+        }
+    }
+
+    let mut permission_file = permissions.to_file();
+    let permissions_changed = merge_permissions(
+        &mut permission_file,
+        &bundle.permissions,
+        strategy,
+        &mut report,
+    );
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    if values_changed {
+        config.save_values(values)?;
+    }
+    for (key, value) in secret_updates {
+        config.set_secret(key, value.clone())?;
+    }
+    if permissions_changed {
+        permissions.replace(permission_file);
+    }
+    Ok(report)
+}
+
+fn merge_permissions(
+    local: &mut PermissionFile,
+    imported: &PermissionFile,
+    strategy: MergeStrategy,
+    report: &mut ConfigImportReport,
+) -> bool {
+    let mut changed = false;
+    for (category, permission_config) in sorted(&imported.permission_map) {
+        let mut entries = permission_config.entries();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (tool, level) in entries {
+            let local_config = local.permission_map.entry(category.clone()).or_default();
+            if report.merge(
+                format!("permissions.{}.{}", category, tool),
+                local_config.level(&tool).as_ref(),
+                &level,
+                strategy,
+            ) {
+                local_config.set_level(&tool, level);
+                changed = true;
+            }
+        }
+    }
+
+    // Rules are ordered and shell patterns are read as a set, so each is merged as a whole
+    if !imported.rules.is_empty()
+        && report.merge(
+            "permissions.rules".to_string(),
+            (!local.rules.is_empty()).then_some(&local.rules),
+            &imported.rules,
+            strategy,
+        )
+    {
+        local.rules = imported.rules.clone();
+        changed = true;
+    }
+    if !imported.shell_commands.is_empty()
+        && report.merge(
+            "permissions.shell_commands".to_string(),
+            (!local.shell_commands.is_empty()).then_some(&local.shell_commands),
+            &imported.shell_commands,
+            strategy,
+        )
+    {
+        local.shell_commands = imported.shell_commands.clone();
+        changed = true;
+    }
+    changed
+}
+
+fn sorted<T>(map: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, ConfigBundleError> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| {
+            ConfigBundleError::Malformed(format!("invalid key derivation input: {}", e))
+        })?;
+    Ok(key)
+}
+
+fn encrypt_secrets(
+    secrets: &HashMap<String, Value>,
+    passphrase: &str,
+) -> Result<EncryptedSecrets, ConfigBundleError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, serde_json::to_vec(secrets)?.as_slice())
+        .map_err(|_| ConfigBundleError::EncryptionFailed)?;
+
+    Ok(EncryptedSecrets {
+        kdf: KDF_NAME.to_string(),
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt_secrets(
+    secrets: &EncryptedSecrets,
+    passphrase: &str,
+) -> Result<HashMap<String, Value>, ConfigBundleError> {
+    if secrets.kdf != KDF_NAME {
+        return Err(ConfigBundleError::Malformed(format!(
+            "unsupported key derivation function '{}'",
+            secrets.kdf
+        )));
+    }
+    let decode = |field: &str, encoded: &str| {
+        BASE64
+            .decode(encoded)
+            .map_err(|e| ConfigBundleError::Malformed(format!("invalid {}: {}", field, e)))
+    };
+    let salt = decode("salt", &secrets.salt)?;
+    let nonce = decode("nonce", &secrets.nonce)?;
+    let ciphertext = decode("ciphertext", &secrets.ciphertext)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(ConfigBundleError::Malformed(format!(
+            "expected a {} byte nonce, found {} bytes",
+            NONCE_LEN,
+            nonce.len()
+        )));
+    }
+
+    let plaintext = Aes256Gcm::new(&derive_key(passphrase, &salt)?)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| ConfigBundleError::DecryptionFailed)?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| ConfigBundleError::Malformed(format!("invalid secrets: {}", e)))
+}
+
+/// Where config backups are written: `config.backup.json` next to the config file
+pub fn backup_path(config: &Config) -> PathBuf {
+    Path::new(&config.path()).with_extension("backup.json")
+}
+
+/// Write a bundle of the current config, without secrets, to the backup path
+pub fn write_backup(
+    config: &Config,
+    permissions: &PermissionManager,
+) -> Result<PathBuf, ConfigBundleError> {
+    let bundle = export_config(config, permissions, None)?;
+    let path = backup_path(config);
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, serde_json::to_vec_pretty(&bundle)?)?;
+    std::fs::rename(&temp_path, &path)?;
+    Ok(path)
+}
+
+/// Restore the config from the backup bundle, replacing values that have changed since
+pub fn restore_backup(
+    config: &Config,
+    permissions: &mut PermissionManager,
+) -> Result<ConfigImportReport, ConfigBundleError> {
+    let contents = std::fs::read(backup_path(config))?;
+    let bundle: ConfigBundle = serde_json::from_slice(&contents)
+        .map_err(|e| ConfigBundleError::Malformed(e.to_string()))?;
+    import_config(
+        config,
+        permissions,
+        &bundle,
+        MergeStrategy::Overwrite,
+        None,
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::permission::{PermissionLevel, PermissionRule};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn setup(dir: &TempDir, name: &str) -> (Config, PermissionManager) {
+        let config = Config::new_with_file_secrets(
+            dir.path().join(format!("{}.yaml", name)),
+            dir.path().join(format!("{}-secrets.yaml", name)),
+        )
+        .unwrap();
+        let permissions =
+            PermissionManager::new(dir.path().join(format!("{}-permission.yaml", name)));
+        (config, permissions)
+    }
+
+    fn populate(config: &Config, permissions: &mut PermissionManager) {
+        config.set_param("GOOSE_PROVIDER", json!("openai")).unwrap();
+        config
+            .set_param(
+                "GOOSE_MODEL",
+                json!("${env:GOOSE_TEST_BUNDLE_MODEL:-gpt-4o}"),
+            )
+            .unwrap();
+        config
+            .set_param(
+                EXTENSIONS_KEY,
+                json!({
+                    "developer": {
+                        "enabled": true,
+                        "type": "builtin",
+                        "name": "developer",
+                        "timeout": 300
+                    }
+                }),
+            )
+            .unwrap();
+        config
+            .set_secret("OPENAI_API_KEY", json!("sk-source"))
+            .unwrap();
+        permissions.update_user_permission("developer__shell", PermissionLevel::AlwaysAllow);
+        permissions.add_rule(
+            PermissionRule::new("github", "*", PermissionLevel::AskBefore),
+            None,
+        );
+    }
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let (source, mut source_permissions) = setup(&dir, "source");
+        populate(&source, &mut source_permissions);
+
+        let bundle = export_config(&source, &source_permissions, Some("correct horse")).unwrap();
+        assert_eq!(
+            bundle.config.get("GOOSE_MODEL"),
+            Some(&json!("${env:GOOSE_TEST_BUNDLE_MODEL:-gpt-4o}"))
+        );
+        assert!(bundle.extensions.contains_key("developer"));
+        let serialized = serde_json::to_string(&bundle).unwrap();
+        assert!(!serialized.contains("sk-source"));
+
+        let (target, mut target_permissions) = setup(&dir, "target");
+        let bundle: ConfigBundle = serde_json::from_str(&serialized).unwrap();
+        let report = import_config(
+            &target,
+            &mut target_permissions,
+            &bundle,
+            MergeStrategy::KeepExisting,
+            Some("correct horse"),
+            false,
+        )
+        .unwrap();
+        assert!(report.conflicts.is_empty());
+        assert!(report
+            .applied
+            .contains(&"permissions.user.developer__shell".to_string()));
+
+        assert_eq!(
+            target.get_param::<String>("GOOSE_PROVIDER").unwrap(),
+            "openai"
+        );
+        assert_eq!(
+            target.get_secret::<String>("OPENAI_API_KEY").unwrap(),
+            "sk-source"
+        );
+        assert!(
+            target.get_param::<Value>(EXTENSIONS_KEY).unwrap()["developer"]["enabled"]
+                .as_bool()
+                .unwrap()
+        );
+        assert_eq!(target_permissions.to_file(), source_permissions.to_file());
+    }
+
+    #[test]
+    fn test_import_conflicts_follow_strategy() {
+        let dir = TempDir::new().unwrap();
+        let (source, mut source_permissions) = setup(&dir, "source");
+        populate(&source, &mut source_permissions);
+        let bundle = export_config(&source, &source_permissions, None).unwrap();
+
+        let (target, mut target_permissions) = setup(&dir, "target");
+        target
+            .set_param("GOOSE_PROVIDER", json!("anthropic"))
+            .unwrap();
+
+        let preview = import_config(
+            &target,
+            &mut target_permissions,
+            &bundle,
+            MergeStrategy::Overwrite,
+            None,
+            true,
+        )
+        .unwrap();
+        assert_eq!(preview.conflicts, vec!["config.GOOSE_PROVIDER"]);
+        assert!(preview
+            .applied
+            .contains(&"config.GOOSE_PROVIDER".to_string()));
+        assert_eq!(
+            target.get_param::<String>("GOOSE_PROVIDER").unwrap(),
+            "anthropic"
+        );
+        assert!(target.get_param::<String>("GOOSE_MODEL").is_err());
+
+        let kept = import_config(
+            &target,
+            &mut target_permissions,
+            &bundle,
+            MergeStrategy::KeepExisting,
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(kept.skipped, vec!["config.GOOSE_PROVIDER"]);
+        assert_eq!(
+            target.get_param::<String>("GOOSE_PROVIDER").unwrap(),
+            "anthropic"
+        );
+        assert_eq!(target.get_param::<String>("GOOSE_MODEL").unwrap(), "gpt-4o");
+
+        let overwritten = import_config(
+            &target,
+            &mut target_permissions,
+            &bundle,
+            MergeStrategy::Overwrite,
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(overwritten.applied, vec!["config.GOOSE_PROVIDER"]);
+        assert_eq!(
+            target.get_param::<String>("GOOSE_PROVIDER").unwrap(),
+            "openai"
+        );
+    }
+
+    #[test]
+    fn test_encrypted_secrets_need_the_passphrase() {
+        let dir = TempDir::new().unwrap();
+        let (source, mut source_permissions) = setup(&dir, "source");
+        populate(&source, &mut source_permissions);
+        let bundle = export_config(&source, &source_permissions, Some("correct horse")).unwrap();
+
+        let (target, mut target_permissions) = setup(&dir, "target");
+        for (passphrase, expected) in [
+            (None, "passphrase is required"),
+            (Some("battery staple"), "could not be decrypted"),
+        ] {
+            let err = import_config(
+                &target,
+                &mut target_permissions,
+                &bundle,
+                MergeStrategy::Overwrite,
+                passphrase,
+                false,
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+            assert!(err.is_invalid_bundle());
+        }
+        assert!(target.get_param::<String>("GOOSE_PROVIDER").is_err());
+        assert!(target_permissions.to_file().permission_map.is_empty());
+
+        let mut future = bundle.clone();
+        future.version = BUNDLE_VERSION + 1;
+        assert!(matches!(
+            import_config(
+                &target,
+                &mut target_permissions,
+                &future,
+                MergeStrategy::Overwrite,
+                Some("correct horse"),
+                false,
+            ),
+            Err(ConfigBundleError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let dir = TempDir::new().unwrap();
+        let (config, mut permissions) = setup(&dir, "config");
+        populate(&config, &mut permissions);
+
+        let path = write_backup(&config, &permissions).unwrap();
+        assert_eq!(path, dir.path().join("config.backup.json"));
+
+        config.set_param("GOOSE_PROVIDER", json!("ollama")).unwrap();
+        let report = restore_backup(&config, &mut permissions).unwrap();
+        assert_eq!(report.applied, vec!["config.GOOSE_PROVIDER"]);
+        assert_eq!(
+            config.get_param::<String>("GOOSE_PROVIDER").unwrap(),
+            "openai"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use super::base::Config;
 use crate::agents::ExtensionConfig;
 use anyhow::Result;
+use mcp_client::oauth::{CredentialStore, OAuthCredentials};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use utoipa::ToSchema;
@@ -24,6 +25,45 @@ pub fn name_to_key(name: &str) -> String {
         .to_lowercase()
 }
 
+/// OAuth credentials of a remote extension, kept as a secret so they land in the keyring
+pub struct ExtensionCredentialStore {
+    key: String,
+}
+
+impl ExtensionCredentialStore {
+    pub fn new(name: &str) -> Self {
+        Self {
+            key: format!("mcp_oauth_{}", name_to_key(name)),
+        }
+    }
+
+    /// Forget the stored credentials, if any
+    pub fn clear(&self) {
+        if let Err(e) = Config::global().delete_secret(&self.key) {
+            tracing::debug!("No OAuth credentials removed for {}: {}", self.key, e);
+        }
+    }
+}
+
+impl CredentialStore for ExtensionCredentialStore {
+    fn load(&self) -> Option<OAuthCredentials> {
+        Config::global().get_secret(&self.key).ok()
+    }
+
+    fn save(&self, credentials: &OAuthCredentials) {
+        let result = serde_json::to_value(credentials)
+            .map_err(|e| e.to_string())
+            .and_then(|value| {
+                Config::global()
+                    .set_secret(&self.key, value)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            tracing::warn!("Failed to store OAuth credentials for {}: {}", self.key, e);
+        }
+    }
+}
+
 /// Extension configuration management
 pub struct ExtensionConfigManager;
 
@@ -47,6 +87,7 @@ impl ExtensionConfigManager {
                             timeout: Some(DEFAULT_EXTENSION_TIMEOUT),
                             bundled: Some(true),
                             description: Some(DEFAULT_EXTENSION_DESCRIPTION.to_string()),
+                            log_level: None,
                         },
                     },
                 )]);
@@ -86,7 +127,7 @@ impl ExtensionConfigManager {
         let config = Config::global();
 
         let mut extensions: HashMap<String, ExtensionEntry> = config
-            .get_param("extensions")
+            .get_param_raw("extensions")
             .unwrap_or_else(|_| HashMap::new());
 
         let key = entry.config.key();
@@ -101,11 +142,12 @@ impl ExtensionConfigManager {
         let config = Config::global();
 
         let mut extensions: HashMap<String, ExtensionEntry> = config
-            .get_param("extensions")
+            .get_param_raw("extensions")
             .unwrap_or_else(|_| HashMap::new());
 
         extensions.remove(key);
         config.set_param("extensions", serde_json::to_value(extensions)?)?;
+        ExtensionCredentialStore::new(key).clear();
         Ok(())
     }
 
@@ -114,7 +156,7 @@ impl ExtensionConfigManager {
         let config = Config::global();
 
         let mut extensions: HashMap<String, ExtensionEntry> = config
-            .get_param("extensions")
+            .get_param_raw("extensions")
             .unwrap_or_else(|_| HashMap::new());
 
         if let Some(entry) = extensions.get_mut(key) {
@@ -1,15 +1,37 @@
 pub mod base;
+mod bundle;
 mod experiments;
 pub mod extensions;
 pub mod permission;
-pub mod signup_openrouter;
+pub mod personas;
+mod recipe_signing;
+mod secret_migration;
+pub mod signup;
+mod tool_visibility;
+mod validation;
 
 pub use crate::agents::ExtensionConfig;
-pub use base::{Config, ConfigError, APP_STRATEGY};
+pub use base::{
+    Config, ConfigError, ConfigReload, EffectiveValues, SecretMigrationFailure,
+    SecretMigrationReport, APP_STRATEGY, PROFILE_ENV_VAR,
+};
+pub use bundle::{
+    backup_path, export_config, import_config, restore_backup, write_backup, ConfigBundle,
+    ConfigBundleError, ConfigImportReport, EncryptedSecrets, MergeStrategy,
+};
 pub use experiments::ExperimentManager;
-pub use extensions::{ExtensionConfigManager, ExtensionEntry};
+pub use extensions::{ExtensionConfigManager, ExtensionCredentialStore, ExtensionEntry};
 pub use permission::PermissionManager;
-pub use signup_openrouter::configure_openrouter;
+pub use personas::{Persona, PersonaManager};
+pub use recipe_signing::{RecipeSigningKey, RecipeSigningManager};
+pub use secret_migration::{migrate_plaintext_secrets, provider_secret_keys};
+pub use signup::openrouter::configure_openrouter;
+pub use signup::{
+    headless_auth, parse_pasted_code, run_signup, run_signup_headless, signup_for, ProviderSignup,
+    SignupPrompt,
+};
+pub use tool_visibility::ToolVisibilityManager;
+pub use validation::{validate_config, ConfigIssue, ConfigIssueSeverity};
 
 pub use extensions::DEFAULT_DISPLAY_NAME;
 pub use extensions::DEFAULT_EXTENSION;
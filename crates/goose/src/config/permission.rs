@@ -1,5 +1,7 @@
 use super::APP_STRATEGY;
 use etcetera::{choose_app_strategy, AppStrategy};
+use glob::Pattern;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -16,18 +18,152 @@ pub enum PermissionLevel {
 }
 
 /// Struct representing the configuration of permissions, categorized by level.
-#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq, Eq)]
 pub struct PermissionConfig {
     pub always_allow: Vec<String>, // List of tools that are always allowed
     pub ask_before: Vec<String>,   // List of tools that require user consent
     pub never_allow: Vec<String>,  // List of tools that are never allowed
 }
 
+impl PermissionConfig {
+    /// The level the tool is listed under, if any
+    pub fn level(&self, principal_name: &str) -> Option<PermissionLevel> {
+        let listed = |names: &Vec<String>| names.iter().any(|name| name == principal_name);
+        if listed(&self.always_allow) {
+            Some(PermissionLevel::AlwaysAllow)
+        } else if listed(&self.ask_before) {
+            Some(PermissionLevel::AskBefore)
+        } else if listed(&self.never_allow) {
+            Some(PermissionLevel::NeverAllow)
+        } else {
+            None
+        }
+    }
+
+    /// List the tool under `level` only
+    pub fn set_level(&mut self, principal_name: &str, level: PermissionLevel) {
+        self.always_allow.retain(|p| p != principal_name);
+        self.ask_before.retain(|p| p != principal_name);
+        self.never_allow.retain(|p| p != principal_name);
+
+        let names = match level {
+            PermissionLevel::AlwaysAllow => &mut self.always_allow,
+            PermissionLevel::AskBefore => &mut self.ask_before,
+            PermissionLevel::NeverAllow => &mut self.never_allow,
+        };
+        names.push(principal_name.to_string());
+    }
+
+    /// Every listed tool with its level
+    pub fn entries(&self) -> Vec<(String, PermissionLevel)> {
+        let listed = |names: &Vec<String>, level: PermissionLevel| {
+            names
+                .iter()
+                .map(move |name| (name.clone(), level.clone()))
+                .collect::<Vec<_>>()
+        };
+        let mut entries = listed(&self.always_allow, PermissionLevel::AlwaysAllow);
+        entries.extend(listed(&self.ask_before, PermissionLevel::AskBefore));
+        entries.extend(listed(&self.never_allow, PermissionLevel::NeverAllow));
+        entries
+    }
+}
+
+fn match_all() -> String {
+    "*".to_string()
+}
+
+/// A pattern-based permission rule, applied to tools without a per-tool user permission.
+///
+/// Tool names are split at the first `__` into the extension and the tool part, so
+/// `developer__read_file` has extension `developer` and tool `read_file`. Both globs support
+/// `*`, `?` and `[...]`, and default to `*`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct PermissionRule {
+    /// Glob matched against the extension part of the tool name
+    #[serde(default = "match_all")]
+    pub extension: String,
+    /// Glob matched against the tool part of the tool name
+    #[serde(default = "match_all")]
+    pub tool: String,
+    /// Permission applied to tools matching both globs
+    pub level: PermissionLevel,
+}
+
+impl PermissionRule {
+    pub fn new(extension: &str, tool: &str, level: PermissionLevel) -> Self {
+        Self {
+            extension: extension.to_string(),
+            tool: tool.to_string(),
+            level,
+        }
+    }
+
+    /// Whether the rule applies to the (prefixed) tool name
+    pub fn matches(&self, tool_name: &str) -> bool {
+        let (extension, tool) = tool_name.split_once("__").unwrap_or(("", tool_name));
+        glob_matches(&self.extension, extension) && glob_matches(&self.tool, tool)
+    }
+}
+
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    match Pattern::new(pattern) {
+        Ok(pattern) => pattern.matches(value),
+        Err(e) => {
+            tracing::warn!(
+                "Ignoring invalid permission rule pattern '{}': {}",
+                pattern,
+                e
+            );
+            false
+        }
+    }
+}
+
+/// Regexes matched against the commands of shell tool calls that would otherwise need approval
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct ShellCommandPatterns {
+    /// Commands whose every segment matches one of these run without asking
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Commands with any segment matching one of these are refused
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl ShellCommandPatterns {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Check that every pattern is a valid regex
+    pub fn validate(&self) -> Result<(), regex::Error> {
+        for pattern in self.allow.iter().chain(&self.deny) {
+            Regex::new(pattern)?;
+        }
+        Ok(())
+    }
+}
+
+/// Layout of permission.yaml: the permission categories plus the ordered rules
+#[derive(Debug, Deserialize, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct PermissionFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<PermissionRule>,
+    #[serde(default, skip_serializing_if = "ShellCommandPatterns::is_empty")]
+    pub shell_commands: ShellCommandPatterns,
+    #[serde(flatten)]
+    pub permission_map: HashMap<String, PermissionConfig>,
+}
+
 /// PermissionManager manages permission configurations for various tools.
 #[derive(Debug)]
 pub struct PermissionManager {
     config_path: PathBuf, // Path to the permission configuration file
     permission_map: HashMap<String, PermissionConfig>, // Mapping of permission names to configurations
+    rules: Vec<PermissionRule>,                        // Ordered rules, the first match wins
+    shell_commands: ShellCommandPatterns,              // Allowed and denied shell command patterns
+    session_rules: Vec<PermissionRule>, // Rules checked ahead of `rules`, never saved
 }
 
 // Constants representing specific permission categories
@@ -44,22 +180,7 @@ impl Default for PermissionManager {
 
         // Ensure the configuration directory exists
         std::fs::create_dir_all(&config_dir).expect("Failed to create config directory");
-        let config_path = config_dir.join("permission.yaml");
-
-        // Load the existing configuration file or create an empty map if the file doesn't exist
-        let permission_map = if config_path.exists() {
-            // Load the configuration file
-            let file_contents =
-                fs::read_to_string(&config_path).expect("Failed to read permission.yaml");
-            serde_yaml::from_str(&file_contents).unwrap_or_else(|_| HashMap::new())
-        } else {
-            HashMap::new() // No config file, create an empty map
-        };
-
-        PermissionManager {
-            config_path,
-            permission_map,
-        }
+        PermissionManager::new(config_dir.join("permission.yaml"))
     }
 }
 
@@ -68,19 +189,22 @@ impl PermissionManager {
     pub fn new<P: AsRef<Path>>(config_path: P) -> Self {
         let config_path = config_path.as_ref().to_path_buf();
 
-        // Load the existing configuration file or create an empty map if the file doesn't exist
-        let permission_map = if config_path.exists() {
+        // Load the existing configuration file or start empty if the file doesn't exist
+        let file = if config_path.exists() {
             // Load the configuration file
             let file_contents =
                 fs::read_to_string(&config_path).expect("Failed to read permission.yaml");
-            serde_yaml::from_str(&file_contents).unwrap_or_else(|_| HashMap::new())
+            serde_yaml::from_str(&file_contents).unwrap_or_default()
         } else {
-            HashMap::new() // No config file, create an empty map
+            PermissionFile::default() // No config file, start empty
         };
 
         PermissionManager {
             config_path,
-            permission_map,
+            permission_map: file.permission_map,
+            rules: file.rules,
+            shell_commands: file.shell_commands,
+            session_rules: Vec::new(),
         }
     }
 
@@ -101,27 +225,9 @@ impl PermissionManager {
 
     /// Helper function to retrieve the permission level for a specific permission category and tool.
     fn get_permission(&self, name: &str, principal_name: &str) -> Option<PermissionLevel> {
-        // Check if the permission category exists in the map
-        if let Some(permission_config) = self.permission_map.get(name) {
-            // Check the permission levels for the given tool
-            if permission_config
-                .always_allow
-                .contains(&principal_name.to_string())
-            {
-                return Some(PermissionLevel::AlwaysAllow);
-            } else if permission_config
-                .ask_before
-                .contains(&principal_name.to_string())
-            {
-                return Some(PermissionLevel::AskBefore);
-            } else if permission_config
-                .never_allow
-                .contains(&principal_name.to_string())
-            {
-                return Some(PermissionLevel::NeverAllow);
-            }
-        }
-        None // Return None if no matching permission level is found
+        self.permission_map
+            .get(name)
+            .and_then(|permission_config| permission_config.level(principal_name))
     }
 
     /// Updates the user permission level for a specific tool.
@@ -141,34 +247,13 @@ impl PermissionManager {
     /// Helper function to update a permission level for a specific tool in a given permission category.
     fn update_permission(&mut self, name: &str, principal_name: &str, level: PermissionLevel) {
         // Get or create a new PermissionConfig for the specified category
-        let permission_config = self.permission_map.entry(name.to_string()).or_default();
+        self.permission_map
+            .entry(name.to_string())
+            .or_default()
+            .set_level(principal_name, level);
 
-        // Remove the principal from all existing lists to avoid duplicates
-        permission_config
-            .always_allow
-            .retain(|p| p != principal_name);
-        permission_config.ask_before.retain(|p| p != principal_name);
-        permission_config
-            .never_allow
-            .retain(|p| p != principal_name);
-
-        // Add the principal to the appropriate list
-        match level {
-            PermissionLevel::AlwaysAllow => permission_config
-                .always_allow
-                .push(principal_name.to_string()),
-            PermissionLevel::AskBefore => permission_config
-                .ask_before
-                .push(principal_name.to_string()),
-            PermissionLevel::NeverAllow => permission_config
-                .never_allow
-                .push(principal_name.to_string()),
-        }
-
-        // Serialize the updated permission map and write it back to the config file
-        let yaml_content = serde_yaml::to_string(&self.permission_map)
-            .expect("Failed to serialize permission config");
-        fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
+        // Write the updated permission map back to the config file
+        self.save();
     }
 
     /// Removes all entries where the principal name starts with the given extension name.
@@ -185,8 +270,84 @@ impl PermissionManager {
                 .retain(|p| !p.starts_with(extension_name));
         }
 
-        let yaml_content = serde_yaml::to_string(&self.permission_map)
-            .expect("Failed to serialize permission config");
+        self.save();
+    }
+
+    /// The ordered permission rules
+    pub fn get_rules(&self) -> &[PermissionRule] {
+        &self.rules
+    }
+
+    /// Replace the ordered permission rules
+    pub fn set_rules(&mut self, rules: Vec<PermissionRule>) {
+        self.rules = rules;
+        self.save();
+    }
+
+    /// Insert a rule at `index`, or append it when `index` is `None` or past the end
+    pub fn add_rule(&mut self, rule: PermissionRule, index: Option<usize>) {
+        let index = index.unwrap_or(self.rules.len()).min(self.rules.len());
+        self.rules.insert(index, rule);
+        self.save();
+    }
+
+    /// Remove the rule at `index`, returning it if it existed
+    pub fn remove_rule(&mut self, index: usize) -> Option<PermissionRule> {
+        if index >= self.rules.len() {
+            return None;
+        }
+        let rule = self.rules.remove(index);
+        self.save();
+        Some(rule)
+    }
+
+    /// Check `rules` ahead of the ordered permission rules, such as those of a persona. They
+    /// last as long as this manager and are not written to permission.yaml.
+    pub fn set_session_rules(&mut self, rules: Vec<PermissionRule>) {
+        self.session_rules = rules;
+    }
+
+    /// The first rule matching the tool, if any, starting with the session rules
+    pub fn get_matching_rule(&self, tool_name: &str) -> Option<&PermissionRule> {
+        self.session_rules
+            .iter()
+            .chain(self.rules.iter())
+            .find(|rule| rule.matches(tool_name))
+    }
+
+    /// The allowed and denied shell command patterns
+    pub fn get_shell_command_patterns(&self) -> &ShellCommandPatterns {
+        &self.shell_commands
+    }
+
+    /// Replace the allowed and denied shell command patterns
+    pub fn set_shell_command_patterns(&mut self, patterns: ShellCommandPatterns) {
+        self.shell_commands = patterns;
+        self.save();
+    }
+
+    /// Everything stored in permission.yaml
+    pub fn to_file(&self) -> PermissionFile {
+        PermissionFile {
+            rules: self.rules.clone(),
+            shell_commands: self.shell_commands.clone(),
+            permission_map: self.permission_map.clone(),
+        }
+    }
+
+    /// Replace everything stored in permission.yaml
+    pub fn replace(&mut self, file: PermissionFile) {
+        self.rules = file.rules;
+        self.shell_commands = file.shell_commands;
+        self.permission_map = file.permission_map;
+        self.save();
+    }
+
+    /// Serialize the permission map, rules and shell command patterns and write them back to
+    /// the config file
+    fn save(&self) {
+        let yaml_content =
+            serde_yaml::to_string(&self.to_file()).expect("Failed to serialize permission config");
         fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
     }
 }
@@ -305,4 +466,128 @@ mod tests {
             .always_allow
             .contains(&"nonprefix__tool2".to_string()));
     }
+
+    #[test]
+    fn test_rule_matching() {
+        let rule = PermissionRule::new("*", "*delete*", PermissionLevel::NeverAllow);
+        assert!(rule.matches("developer__delete_file"));
+        assert!(rule.matches("github__delete_branch"));
+        assert!(!rule.matches("developer__read_file"));
+
+        let rule = PermissionRule::new("github", "*", PermissionLevel::AskBefore);
+        assert!(rule.matches("github__create_issue"));
+        assert!(!rule.matches("githubx__create_issue"));
+        assert!(!rule.matches("create_issue"));
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let mut manager = create_test_permission_manager();
+        manager.set_rules(vec![
+            PermissionRule::new("*", "*delete*", PermissionLevel::NeverAllow),
+            PermissionRule::new("github", "*", PermissionLevel::AskBefore),
+            PermissionRule::new("*", "*", PermissionLevel::AlwaysAllow),
+        ]);
+
+        let level = |tool: &str| manager.get_matching_rule(tool).map(|r| r.level.clone());
+        assert_eq!(
+            level("github__delete_branch"),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(
+            level("github__create_issue"),
+            Some(PermissionLevel::AskBefore)
+        );
+        assert_eq!(
+            level("developer__read_file"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+    }
+
+    #[test]
+    fn test_rule_crud_persists_alongside_permissions() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut manager = PermissionManager::new(temp_file.path());
+        manager.update_user_permission("developer__shell", PermissionLevel::AskBefore);
+        manager.add_rule(
+            PermissionRule::new("github", "*", PermissionLevel::AskBefore),
+            None,
+        );
+        manager.add_rule(
+            PermissionRule::new("*", "*delete*", PermissionLevel::NeverAllow),
+            Some(0),
+        );
+
+        let reloaded = PermissionManager::new(temp_file.path());
+        assert_eq!(reloaded.get_rules(), manager.get_rules());
+        assert_eq!(reloaded.get_rules()[0].tool, "*delete*");
+        assert_eq!(
+            reloaded.get_user_permission("developer__shell"),
+            Some(PermissionLevel::AskBefore)
+        );
+
+        manager.remove_rule(0);
+        assert_eq!(manager.get_rules().len(), 1);
+        assert_eq!(manager.remove_rule(5), None);
+    }
+
+    #[test]
+    fn test_session_rules_come_first_and_are_not_saved() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut manager = PermissionManager::new(temp_file.path());
+        manager.add_rule(
+            PermissionRule::new("github", "*", PermissionLevel::AlwaysAllow),
+            None,
+        );
+        manager.set_session_rules(vec![PermissionRule::new(
+            "github",
+            "*delete*",
+            PermissionLevel::NeverAllow,
+        )]);
+
+        let level = |tool: &str| manager.get_matching_rule(tool).map(|r| r.level.clone());
+        assert_eq!(
+            level("github__delete_branch"),
+            Some(PermissionLevel::NeverAllow)
+        );
+        assert_eq!(
+            level("github__create_issue"),
+            Some(PermissionLevel::AlwaysAllow)
+        );
+
+        manager.update_user_permission("developer__shell", PermissionLevel::AskBefore);
+        let reloaded = PermissionManager::new(temp_file.path());
+        assert_eq!(reloaded.get_rules().len(), 1);
+        assert!(reloaded
+            .get_matching_rule("github__delete_branch")
+            .is_some_and(|rule| rule.level == PermissionLevel::AlwaysAllow));
+    }
+
+    #[test]
+    fn test_rules_default_to_match_all() {
+        let rule: PermissionRule = serde_yaml::from_str("level: ask_before").unwrap();
+        assert_eq!(rule.extension, "*");
+        assert_eq!(rule.tool, "*");
+    }
+
+    #[test]
+    fn test_shell_command_patterns_persist() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut manager = PermissionManager::new(temp_file.path());
+        let patterns = ShellCommandPatterns {
+            allow: vec![r"^git (status|diff|log)\b".to_string()],
+            deny: vec![r"curl .* \| *sh".to_string()],
+        };
+        manager.set_shell_command_patterns(patterns.clone());
+
+        let reloaded = PermissionManager::new(temp_file.path());
+        assert_eq!(reloaded.get_shell_command_patterns(), &patterns);
+
+        let invalid = ShellCommandPatterns {
+            allow: vec!["(".to_string()],
+            deny: vec![],
+        };
+        assert!(invalid.validate().is_err());
+        assert!(patterns.validate().is_ok());
+    }
 }
@@ -0,0 +1,100 @@
+use super::base::{Config, ConfigError};
+use super::permission::PermissionRule;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+const PERSONAS_KEY: &str = "personas";
+
+/// A named setup of the agent that a request can select, such as "reviewer" or "sre"
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Persona {
+    /// Name the persona is selected by, its key in the registry
+    #[serde(default)]
+    pub name: String,
+    /// Instructions appended to the system prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Provider to reply with instead of the configured one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model to reply with instead of the configured one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Extensions whose tools the persona may use. Platform tools are always available, and
+    /// all loaded extensions are allowed when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<String>>,
+    /// Rules checked ahead of the configured permission rules, the first match wins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permission_rules: Vec<PermissionRule>,
+}
+
+/// Registry of personas, stored in config as a map of name to persona
+pub struct PersonaManager;
+
+impl PersonaManager {
+    fn get_all() -> HashMap<String, Persona> {
+        match Config::global().get_param::<HashMap<String, Persona>>(PERSONAS_KEY) {
+            Ok(personas) => personas,
+            Err(ConfigError::NotFound(_)) => HashMap::new(),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid personas config: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    /// Every persona, sorted by name
+    pub fn list() -> Vec<Persona> {
+        let mut personas: Vec<Persona> = Self::get_all()
+            .into_iter()
+            .map(|(name, persona)| Persona { name, ..persona })
+            .collect();
+        personas.sort_by(|a, b| a.name.cmp(&b.name));
+        personas
+    }
+
+    /// The persona registered under `name`
+    pub fn get(name: &str) -> Option<Persona> {
+        Self::get_all().remove(name).map(|persona| Persona {
+            name: name.to_string(),
+            ..persona
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::permission::PermissionLevel;
+
+    #[test]
+    fn test_persona_from_config() {
+        let personas: HashMap<String, Persona> = serde_yaml::from_str(
+            r#"
+reviewer:
+  instructions: Review the change and point out bugs
+  model: gpt-4o
+  extensions: [developer]
+  permission_rules:
+    - tool: "*write*"
+      level: never_allow
+"#,
+        )
+        .unwrap();
+
+        let reviewer = &personas["reviewer"];
+        assert_eq!(reviewer.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(reviewer.provider, None);
+        assert_eq!(reviewer.extensions, Some(vec!["developer".to_string()]));
+        assert_eq!(
+            reviewer.permission_rules,
+            vec![PermissionRule::new(
+                "*",
+                "*write*",
+                PermissionLevel::NeverAllow
+            )]
+        );
+    }
+}
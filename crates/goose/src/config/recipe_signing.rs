@@ -0,0 +1,146 @@
+use super::base::Config;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const RECIPE_SIGNING_KEY: &str = "GOOSE_RECIPE_SIGNING_KEY";
+const RECIPE_TRUSTED_KEYS: &str = "GOOSE_RECIPE_TRUSTED_KEYS";
+const MIN_SECRET_LEN: usize = 32;
+
+/// A shared secret that signs recipe deeplinks with HMAC-SHA256. Anyone holding the secret can
+/// both sign and verify, so it is only shared with people whose links should be trusted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipeSigningKey {
+    /// Short fingerprint of the secret, sent along with signatures
+    pub id: String,
+    /// The secret, base64 encoded
+    pub secret: String,
+}
+
+impl RecipeSigningKey {
+    /// Create a key with a new random secret
+    pub fn generate() -> Self {
+        let mut secret = [0u8; MIN_SECRET_LEN];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self {
+            id: key_id(&secret),
+            secret: STANDARD.encode(secret),
+        }
+    }
+
+    /// A key from a base64 encoded secret, such as one shared by another user
+    pub fn from_secret(secret: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(secret.trim())
+            .map_err(|_| anyhow!("Signing key secret is not valid base64"))?;
+        if bytes.len() < MIN_SECRET_LEN {
+            return Err(anyhow!(
+                "Signing key secret must be at least {} bytes",
+                MIN_SECRET_LEN
+            ));
+        }
+        Ok(Self {
+            id: key_id(&bytes),
+            secret: STANDARD.encode(&bytes),
+        })
+    }
+
+    pub fn secret_bytes(&self) -> Vec<u8> {
+        STANDARD.decode(&self.secret).unwrap_or_default()
+    }
+}
+
+fn key_id(secret: &[u8]) -> String {
+    Sha256::digest(secret)
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The key this machine signs recipe deeplinks with, and the keys of other people whose
+/// deeplinks are trusted. Both are kept in secret storage.
+pub struct RecipeSigningManager;
+
+impl RecipeSigningManager {
+    /// The local signing key, if one has been created
+    pub fn signing_key() -> Option<RecipeSigningKey> {
+        Config::global().get_secret(RECIPE_SIGNING_KEY).ok()
+    }
+
+    /// Create a new local signing key, replacing the current one. Links signed with the old
+    /// key are no longer trusted on this machine.
+    pub fn generate_signing_key() -> Result<RecipeSigningKey> {
+        let key = RecipeSigningKey::generate();
+        Config::global().set_secret(RECIPE_SIGNING_KEY, serde_json::to_value(&key)?)?;
+        Ok(key)
+    }
+
+    pub fn remove_signing_key() -> Result<()> {
+        Config::global().delete_secret(RECIPE_SIGNING_KEY)?;
+        Ok(())
+    }
+
+    fn added_keys() -> HashMap<String, String> {
+        Config::global()
+            .get_secret(RECIPE_TRUSTED_KEYS)
+            .unwrap_or_default()
+    }
+
+    /// The keys deeplinks are verified against, by id: the keys added with `trust_key` and the
+    /// local signing key
+    pub fn trusted_keys() -> HashMap<String, RecipeSigningKey> {
+        let mut keys: HashMap<String, RecipeSigningKey> = Self::added_keys()
+            .into_values()
+            .filter_map(|secret| RecipeSigningKey::from_secret(&secret).ok())
+            .map(|key| (key.id.clone(), key))
+            .collect();
+        if let Some(key) = Self::signing_key() {
+            keys.insert(key.id.clone(), key);
+        }
+        keys
+    }
+
+    /// Trust deeplinks signed with the key whose secret is `secret`
+    pub fn trust_key(secret: &str) -> Result<RecipeSigningKey> {
+        let key = RecipeSigningKey::from_secret(secret)?;
+        let mut keys = Self::added_keys();
+        keys.insert(key.id.clone(), key.secret.clone());
+        Config::global().set_secret(RECIPE_TRUSTED_KEYS, serde_json::to_value(keys)?)?;
+        Ok(key)
+    }
+
+    /// Stop trusting the key with `id`; returns whether it was trusted
+    pub fn untrust_key(id: &str) -> Result<bool> {
+        let mut keys = Self::added_keys();
+        let removed = keys.remove(id).is_some();
+        if removed {
+            Config::global().set_secret(RECIPE_TRUSTED_KEYS, serde_json::to_value(keys)?)?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_round_trips_through_secret() {
+        let key = RecipeSigningKey::generate();
+        assert_eq!(key.id.len(), 16);
+        assert_eq!(key.secret_bytes().len(), MIN_SECRET_LEN);
+
+        let shared = RecipeSigningKey::from_secret(&key.secret).unwrap();
+        assert_eq!(shared, key);
+    }
+
+    #[test]
+    fn test_rejects_weak_or_malformed_secrets() {
+        assert!(RecipeSigningKey::from_secret("not base64!").is_err());
+        assert!(RecipeSigningKey::from_secret(&STANDARD.encode(b"short")).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use super::base::{Config, ConfigError, SecretMigrationReport};
+use crate::providers::providers;
+
+/// Names of the config keys any provider marks as secret, sorted
+pub fn provider_secret_keys() -> Vec<String> {
+    let mut keys: Vec<String> = providers()
+        .into_iter()
+        .flat_map(|provider| provider.config_keys)
+        .filter(|config_key| config_key.secret)
+        .map(|config_key| config_key.name)
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+/// Move provider API keys and tokens saved in the config file before keyring support into
+/// the system keyring, see [`Config::migrate_secrets`]
+pub fn migrate_plaintext_secrets(
+    config: &Config,
+    dry_run: bool,
+) -> Result<SecretMigrationReport, ConfigError> {
+    config.migrate_secrets(&provider_secret_keys(), dry_run)
+}
@@ -0,0 +1,278 @@
+//! Device authorization grant (RFC 8628) against a gateway that publishes its endpoints as OAuth
+//! authorization server metadata (RFC 8414) under its host. The access token it issues is saved
+//! as the provider's API key.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ProviderSignup, SignupPrompt};
+use crate::config::Config;
+use crate::providers::base::DeviceCodeSettings;
+
+const METADATA_PATH: &str = ".well-known/oauth-authorization-server";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Poll interval when the server doesn't give one, per RFC 8628
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+
+#[derive(Debug, Deserialize)]
+struct ServerMetadata {
+    device_authorization_endpoint: Option<String>,
+    token_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceAuthorization {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+struct PendingAuthorization {
+    device_code: String,
+    token_endpoint: String,
+    interval: Duration,
+}
+
+/// Signs in to an OAuth gateway by having the user enter a code on its sign in page
+pub struct DeviceCodeSignup {
+    provider: String,
+    default_model: String,
+    host: String,
+    settings: DeviceCodeSettings,
+    client: Client,
+    pending: Option<PendingAuthorization>,
+}
+
+impl DeviceCodeSignup {
+    pub fn new(
+        provider: &str,
+        default_model: &str,
+        host: &str,
+        settings: DeviceCodeSettings,
+    ) -> Self {
+        Self {
+            provider: provider.to_string(),
+            default_model: default_model.to_string(),
+            host: host.trim_end_matches('/').to_string(),
+            settings,
+            client: Client::new(),
+            pending: None,
+        }
+    }
+}
+
+#[async_trait]
+impl ProviderSignup for DeviceCodeSignup {
+    fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    async fn start(&mut self) -> Result<SignupPrompt> {
+        let metadata: ServerMetadata = self
+            .client
+            .get(format!("{}/{}", self.host, METADATA_PATH))
+            .send()
+            .await
+            .context("failed to fetch the gateway's OAuth metadata")?
+            .error_for_status()
+            .context("the gateway doesn't publish OAuth metadata")?
+            .json()
+            .await
+            .context("failed to parse the gateway's OAuth metadata")?;
+        let device_endpoint = metadata
+            .device_authorization_endpoint
+            .ok_or_else(|| anyhow!("{} doesn't offer device code sign in", self.host))?;
+
+        let mut form = vec![("client_id", self.settings.client_id.as_str())];
+        if let Some(scope) = &self.settings.scope {
+            form.push(("scope", scope.as_str()));
+        }
+        let authorization: DeviceAuthorization = self
+            .client
+            .post(device_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .context("failed to request a device code")?
+            .error_for_status()
+            .context("the gateway refused to issue a device code")?
+            .json()
+            .await
+            .context("failed to parse the device code response")?;
+
+        self.pending = Some(PendingAuthorization {
+            device_code: authorization.device_code,
+            token_endpoint: metadata.token_endpoint,
+            interval: Duration::from_secs(
+                authorization.interval.unwrap_or(DEFAULT_POLL_INTERVAL_SECS),
+            ),
+        });
+        Ok(SignupPrompt {
+            url: authorization
+                .verification_uri_complete
+                .unwrap_or(authorization.verification_uri),
+            user_code: Some(authorization.user_code),
+        })
+    }
+
+    async fn finish(&mut self) -> Result<String> {
+        let pending = self
+            .pending
+            .as_mut()
+            .ok_or_else(|| anyhow!("Device code sign in was not started"))?;
+
+        loop {
+            let response: TokenResponse = self
+                .client
+                .post(&pending.token_endpoint)
+                .form(&[
+                    ("grant_type", DEVICE_CODE_GRANT),
+                    ("device_code", pending.device_code.as_str()),
+                    ("client_id", self.settings.client_id.as_str()),
+                ])
+                .send()
+                .await
+                .context("failed to poll for the access token")?
+                .json()
+                .await
+                .context("failed to parse the token response")?;
+
+            if let Some(access_token) = response.access_token {
+                self.pending = None;
+                return Ok(access_token);
+            }
+            match response.error.as_deref() {
+                Some("authorization_pending") => {}
+                Some("slow_down") => pending.interval += Duration::from_secs(5),
+                error => {
+                    return Err(anyhow!(
+                        "Sign in failed: {}",
+                        response
+                            .error_description
+                            .or(error.map(String::from))
+                            .unwrap_or_else(|| "no access token was issued".to_string())
+                    ))
+                }
+            }
+            tokio::time::sleep(pending.interval).await;
+        }
+    }
+
+    fn configure(&self, config: &Config, api_key: String) -> Result<()> {
+        config.set_secret(&self.settings.api_key_key, Value::String(api_key))?;
+        config.set_param("GOOSE_PROVIDER", Value::String(self.provider.clone()))?;
+        config.set_param("GOOSE_MODEL", Value::String(self.default_model.clone()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_device_code_signup() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/oauth-authorization-server"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": server.uri(),
+                "device_authorization_endpoint": format!("{}/oauth/device", server.uri()),
+                "token_endpoint": format!("{}/oauth/token", server.uri()),
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/oauth/device"))
+            .and(body_string_contains("client_id=goose"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "device_code": "device-123",
+                "user_code": "WDJB-MJHT",
+                "verification_uri": format!("{}/device", server.uri()),
+                "expires_in": 900,
+                "interval": 0,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/oauth/token"))
+            .and(body_string_contains("device_code=device-123"))
+            .respond_with(
+                ResponseTemplate::new(400).set_body_json(json!({"error": "authorization_pending"})),
+            )
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/oauth/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "sk-gateway",
+                "token_type": "Bearer",
+            })))
+            .mount(&server)
+            .await;
+
+        let settings = DeviceCodeSettings {
+            host_key: "LITELLM_HOST".to_string(),
+            api_key_key: "LITELLM_API_KEY".to_string(),
+            client_id: "goose".to_string(),
+            scope: None,
+        };
+        let mut signup = DeviceCodeSignup::new("litellm", "gpt-4o", &server.uri(), settings);
+
+        let prompt = signup.start().await?;
+        assert_eq!(prompt.url, format!("{}/device", server.uri()));
+        assert_eq!(prompt.user_code.as_deref(), Some("WDJB-MJHT"));
+        assert_eq!(signup.finish().await?, "sk-gateway");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_denied_sign_in_is_an_error() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/oauth/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": "access_denied",
+                "error_description": "The user denied the request",
+            })))
+            .mount(&server)
+            .await;
+
+        let settings = DeviceCodeSettings {
+            host_key: "LITELLM_HOST".to_string(),
+            api_key_key: "LITELLM_API_KEY".to_string(),
+            client_id: "goose".to_string(),
+            scope: None,
+        };
+        let mut signup = DeviceCodeSignup::new("litellm", "gpt-4o", &server.uri(), settings);
+        signup.pending = Some(PendingAuthorization {
+            device_code: "device-123".to_string(),
+            token_endpoint: format!("{}/oauth/token", server.uri()),
+            interval: Duration::ZERO,
+        });
+
+        let error = signup.finish().await.unwrap_err();
+        assert!(error.to_string().contains("The user denied the request"));
+        Ok(())
+    }
+}
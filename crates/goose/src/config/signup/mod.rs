@@ -0,0 +1,227 @@
+//! One-click setup for providers that can hand out an API key after the user signs in with
+//! them. A provider advertises how in its metadata's `signup`, and [`signup_for`] builds the
+//! matching [`ProviderSignup`].
+
+pub mod device_code;
+pub mod openrouter;
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::time::timeout;
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::providers::base::SignupMethod;
+use device_code::DeviceCodeSignup;
+use openrouter::PkceAuthFlow;
+
+/// Limit on a whole signup, so an abandoned browser tab can't keep it waiting
+const SIGNUP_TIMEOUT: Duration = Duration::from_secs(300);
+/// Set to sign in without a browser or callback server on this machine, e.g. over SSH
+pub const HEADLESS_AUTH_KEY: &str = "GOOSE_HEADLESS_AUTH";
+
+/// Whether sign ins should ask for the code to be pasted back instead of catching a redirect
+pub fn headless_auth() -> bool {
+    Config::global()
+        .get_param(HEADLESS_AUTH_KEY)
+        .unwrap_or(false)
+}
+
+/// Pull the authorization code out of what the user pasted: either the whole URL they were
+/// redirected to, or just the code. The redirect's `state`, if any, is returned alongside.
+pub fn parse_pasted_code(pasted: &str) -> Result<(String, Option<String>)> {
+    let pasted = pasted.trim();
+    if pasted.is_empty() {
+        return Err(anyhow!("No authorization code was entered"));
+    }
+
+    let Ok(url) = Url::parse(pasted) else {
+        return Ok((pasted.to_string(), None));
+    };
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if let Some(error) = param("error") {
+        return Err(anyhow!("Authorization failed: {}", error));
+    }
+    let code = param("code")
+        .ok_or_else(|| anyhow!("The pasted URL doesn't contain an authorization code"))?;
+    Ok((code, param("state")))
+}
+
+/// What the user is asked to do to sign in
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SignupPrompt {
+    /// Page to sign in at
+    pub url: String,
+    /// Code to enter on that page, for device flows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_code: Option<String>,
+}
+
+/// A flow that signs the user in with a provider and configures the API key it yields
+#[async_trait]
+pub trait ProviderSignup: Send {
+    /// Name of the provider the signup configures
+    fn provider(&self) -> &str;
+
+    /// Begin signing in, returning where the user should go
+    async fn start(&mut self) -> Result<SignupPrompt>;
+
+    /// Wait for the user to finish signing in, then exchange the result for an API key
+    async fn finish(&mut self) -> Result<String>;
+
+    /// Begin signing in without listening for a redirect on this machine. Flows that need
+    /// the code pasted back say so through [`ProviderSignup::needs_pasted_code`].
+    async fn start_headless(&mut self) -> Result<SignupPrompt> {
+        self.start().await
+    }
+
+    /// Whether a headless signup finishes with [`ProviderSignup::finish_with_code`]
+    fn needs_pasted_code(&self) -> bool {
+        false
+    }
+
+    /// Exchange the code, or redirect URL, the user pasted back for an API key
+    async fn finish_with_code(&mut self, _pasted: &str) -> Result<String> {
+        self.finish().await
+    }
+
+    /// Save `api_key` and make the provider the configured one
+    fn configure(&self, config: &Config, api_key: String) -> Result<()>;
+}
+
+/// The signup flow `provider` advertises in its metadata
+pub fn signup_for(provider: &str) -> Result<Box<dyn ProviderSignup>> {
+    let metadata = crate::providers::providers()
+        .into_iter()
+        .find(|metadata| metadata.name == provider)
+        .ok_or_else(|| anyhow!("Unknown provider '{}'", provider))?;
+
+    match metadata.signup {
+        Some(SignupMethod::Pkce) if provider == "openrouter" => Ok(Box::new(PkceAuthFlow::new()?)),
+        Some(SignupMethod::DeviceCode(settings)) => {
+            let host = Config::global()
+                .get_param::<String>(&settings.host_key)
+                .ok()
+                .or_else(|| {
+                    metadata
+                        .config_keys
+                        .iter()
+                        .find(|key| key.name == settings.host_key)
+                        .and_then(|key| key.default.clone())
+                })
+                .ok_or_else(|| anyhow!("{} needs to be set first", settings.host_key))?;
+            Ok(Box::new(DeviceCodeSignup::new(
+                provider,
+                &metadata.default_model,
+                &host,
+                settings,
+            )))
+        }
+        _ => Err(anyhow!(
+            "{} doesn't support signing up from goose",
+            provider
+        )),
+    }
+}
+
+/// Run `signup` to the end: open the sign in page, wait for the API key and configure it
+pub async fn run_signup(signup: &mut dyn ProviderSignup, config: &Config) -> Result<()> {
+    if headless_auth() {
+        return run_signup_headless(signup, config).await;
+    }
+
+    let flow = async {
+        let prompt = signup.start().await?;
+
+        println!("Opening browser for authentication...");
+        if let Some(code) = &prompt.user_code {
+            println!("Enter the code {} when asked", code);
+        }
+        if let Err(e) = webbrowser::open(&prompt.url) {
+            eprintln!("Failed to open browser automatically: {}", e);
+            println!("Please open this URL manually: {}", prompt.url);
+        }
+
+        println!("Waiting for authentication...");
+        let api_key = signup.finish().await?;
+        signup.configure(config, api_key)
+    };
+
+    timeout(SIGNUP_TIMEOUT, flow)
+        .await
+        .map_err(|_| anyhow!("Authentication timeout - please try again"))?
+}
+
+/// Run `signup` without a browser or callback server: print the sign in page and, for flows
+/// that redirect back, read the redirect URL or code the user pastes in
+pub async fn run_signup_headless(signup: &mut dyn ProviderSignup, config: &Config) -> Result<()> {
+    let flow = async {
+        let prompt = signup.start_headless().await?;
+
+        println!("Open this URL in a browser on any machine to sign in:");
+        println!("{}", prompt.url);
+        if let Some(code) = &prompt.user_code {
+            println!("Enter the code {} when asked", code);
+        }
+
+        let api_key = if signup.needs_pasted_code() {
+            println!("Then paste the URL you were redirected to, or the code from it:");
+            let pasted = tokio::task::spawn_blocking(|| {
+                let mut line = String::new();
+                std::io::stdin().read_line(&mut line).map(|_| line)
+            })
+            .await??;
+            signup.finish_with_code(&pasted).await?
+        } else {
+            println!("Waiting for authentication...");
+            signup.finish().await?
+        };
+        signup.configure(config, api_key)
+    };
+
+    timeout(SIGNUP_TIMEOUT, flow)
+        .await
+        .map_err(|_| anyhow!("Authentication timeout - please try again"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signup_follows_provider_metadata() {
+        assert_eq!(signup_for("openrouter").unwrap().provider(), "openrouter");
+        assert_eq!(signup_for("litellm").unwrap().provider(), "litellm");
+
+        let error = signup_for("openai").err().unwrap();
+        assert!(error.to_string().contains("doesn't support signing up"));
+        assert!(signup_for("no-such-provider").is_err());
+    }
+
+    #[test]
+    fn test_parse_pasted_code() {
+        assert_eq!(
+            parse_pasted_code("  abc123\n").unwrap(),
+            ("abc123".to_string(), None)
+        );
+        assert_eq!(
+            parse_pasted_code("http://localhost:3000/?code=abc%20123&state=xyz").unwrap(),
+            ("abc 123".to_string(), Some("xyz".to_string()))
+        );
+
+        let error = parse_pasted_code("http://localhost:3000/?error=access_denied")
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("access_denied"));
+        assert!(parse_pasted_code("http://localhost:3000/").is_err());
+        assert!(parse_pasted_code("   ").is_err());
+    }
+}
@@ -3,13 +3,16 @@ pub mod server;
 #[cfg(test)]
 mod tests;
 
+use super::{parse_pasted_code, ProviderSignup, SignupPrompt};
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::{distributions::Alphanumeric, Rng};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::time::Duration;
+use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tokio::time::timeout;
 
@@ -18,13 +21,18 @@ const OPENROUTER_DEFAULT_MODEL: &str = "qwen/qwen3-coder";
 
 const OPENROUTER_AUTH_URL: &str = "https://openrouter.ai/auth";
 const OPENROUTER_TOKEN_URL: &str = "https://openrouter.ai/api/v1/auth/keys";
-const CALLBACK_URL: &str = "http://localhost:3000";
+/// Port the local callback server tries first
+const CALLBACK_PORT_KEY: &str = "GOOSE_OAUTH_CALLBACK_PORT";
+const DEFAULT_CALLBACK_PORT: u16 = 3000;
 const AUTH_TIMEOUT: Duration = Duration::from_secs(180); // 3 minutes
 
 #[derive(Debug)]
 pub struct PkceAuthFlow {
     code_verifier: String,
     code_challenge: String,
+    /// Port of the callback server, the configured one until the server is bound
+    port: u16,
+    listener: Option<TcpListener>,
     server_shutdown_tx: Option<oneshot::Sender<()>>,
 }
 
@@ -54,24 +62,51 @@ impl PkceAuthFlow {
 
         let code_challenge = URL_SAFE_NO_PAD.encode(hash);
 
+        let port = Config::global()
+            .get_param(CALLBACK_PORT_KEY)
+            .unwrap_or(DEFAULT_CALLBACK_PORT);
+
         Ok(Self {
             code_verifier,
             code_challenge,
+            port,
+            listener: None,
             server_shutdown_tx: None,
         })
     }
 
+    /// Port OpenRouter redirects back to
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
     pub fn get_auth_url(&self) -> String {
         format!(
             "{}?callback_url={}&code_challenge={}&code_challenge_method=S256",
             OPENROUTER_AUTH_URL,
-            urlencoding::encode(CALLBACK_URL),
+            urlencoding::encode(&format!("http://localhost:{}", self.port)),
             urlencoding::encode(&self.code_challenge)
         )
     }
 
+    /// Bind the local callback server, on the configured port or on another one if that is
+    /// taken. The auth URL then points at the port actually bound.
+    pub async fn bind_callback(&mut self) -> Result<()> {
+        let listener = server::bind_callback_listener(self.port).await?;
+        self.port = listener.local_addr()?.port();
+        self.listener = Some(listener);
+        Ok(())
+    }
+
     /// Start local server and wait for callback
     pub async fn start_server(&mut self) -> Result<String> {
+        if self.listener.is_none() {
+            self.bind_callback().await?;
+        }
+        let listener = self
+            .listener
+            .take()
+            .ok_or_else(|| anyhow!("Callback server is not bound"))?;
         let (code_tx, code_rx) = oneshot::channel::<String>();
         let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
 
@@ -80,7 +115,7 @@ impl PkceAuthFlow {
 
         // Start the server in a background task
         tokio::spawn(async move {
-            if let Err(e) = server::run_callback_server(code_tx, shutdown_rx).await {
+            if let Err(e) = server::run_callback_server(listener, code_tx, shutdown_rx).await {
                 eprintln!("Server error: {}", e);
             }
         });
@@ -129,33 +164,53 @@ impl PkceAuthFlow {
         let token_response: TokenResponse = response.json().await?;
         Ok(token_response.key)
     }
+}
 
-    /// Complete flow: open browser, wait for callback, exchange code
-    pub async fn complete_flow(&mut self) -> Result<String> {
-        let auth_url = self.get_auth_url();
-
-        println!("Opening browser for authentication...");
-        eprintln!("Auth URL: {}", auth_url);
-
-        if let Err(e) = webbrowser::open(&auth_url) {
-            eprintln!("Failed to open browser automatically: {}", e);
-            println!("Please open this URL manually: {}", auth_url);
-        }
-
-        println!("Waiting for authentication callback...");
-        let code = self.start_server().await?;
+#[async_trait]
+impl ProviderSignup for PkceAuthFlow {
+    fn provider(&self) -> &str {
+        "openrouter"
+    }
 
-        println!("Authorization code received. Exchanging for API key...");
-        eprintln!("Received code: {}", code);
+    async fn start(&mut self) -> Result<SignupPrompt> {
+        self.bind_callback().await?;
+        Ok(SignupPrompt {
+            url: self.get_auth_url(),
+            user_code: None,
+        })
+    }
 
-        let api_key = self.exchange_code(code).await?;
+    async fn finish(&mut self) -> Result<String> {
+        let code = self.start_server().await;
 
         // Shutdown the server if it's still running
         if let Some(tx) = self.server_shutdown_tx.take() {
             let _ = tx.send(());
         }
 
-        Ok(api_key)
+        println!("Authorization code received. Exchanging for API key...");
+        self.exchange_code(code?).await
+    }
+
+    async fn start_headless(&mut self) -> Result<SignupPrompt> {
+        // Nothing listens on the callback port: the user copies the redirect back instead
+        Ok(SignupPrompt {
+            url: self.get_auth_url(),
+            user_code: None,
+        })
+    }
+
+    fn needs_pasted_code(&self) -> bool {
+        true
+    }
+
+    async fn finish_with_code(&mut self, pasted: &str) -> Result<String> {
+        let (code, _) = parse_pasted_code(pasted)?;
+        self.exchange_code(code).await
+    }
+
+    fn configure(&self, config: &Config, api_key: String) -> Result<()> {
+        configure_openrouter(config, api_key)
     }
 }
 
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use axum::{
     extract::Query,
     http::StatusCode,
@@ -10,10 +10,11 @@ use include_dir::{include_dir, Dir};
 use minijinja::{context, Environment};
 use serde::Deserialize;
 use std::net::SocketAddr;
+use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 
 static TEMPLATES_DIR: Dir =
-    include_dir!("$CARGO_MANIFEST_DIR/src/config/signup_openrouter/templates");
+    include_dir!("$CARGO_MANIFEST_DIR/src/config/signup/openrouter/templates");
 
 #[derive(Debug, Deserialize)]
 struct CallbackQuery {
@@ -21,14 +22,37 @@ struct CallbackQuery {
     error: Option<String>,
 }
 
-/// Run the callback server on localhost:3000
+/// Bind the callback server to `port` on localhost, or to a port the OS picks when that one
+/// can't be used, e.g. because a dev server already listens on it
+pub async fn bind_callback_listener(port: u16) -> Result<TcpListener> {
+    match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port))).await {
+        Ok(listener) => Ok(listener),
+        Err(e) => {
+            tracing::warn!(
+                "Can't listen for the OpenRouter callback on port {} ({}), using another port",
+                port,
+                e
+            );
+            TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+                .await
+                .map_err(|e| {
+                    anyhow!(
+                        "Could not start the local server OpenRouter redirects to after signing in ({}). \
+                         Set GOOSE_OAUTH_CALLBACK_PORT to a free port and try again.",
+                        e
+                    )
+                })
+        }
+    }
+}
+
+/// Run the callback server on `listener`
 pub async fn run_callback_server(
+    listener: TcpListener,
     code_tx: oneshot::Sender<String>,
     shutdown_rx: oneshot::Receiver<()>,
 ) -> Result<()> {
     let app = Router::new().route("/", get(handle_callback));
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    let listener = tokio::net::TcpListener::bind(addr).await?;
     let state = std::sync::Arc::new(tokio::sync::Mutex::new(Some(code_tx)));
 
     axum::serve(listener, app.with_state(state.clone()).into_make_service())
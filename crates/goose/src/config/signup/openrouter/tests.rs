@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use crate::config::signup_openrouter::PkceAuthFlow;
+    use crate::config::signup::openrouter::{server::bind_callback_listener, PkceAuthFlow};
     use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
     use sha2::{Digest, Sha256};
 
@@ -19,7 +19,10 @@ mod tests {
         // Verify auth URL is properly formatted
         let auth_url = flow.get_auth_url();
         assert!(auth_url.starts_with("https://openrouter.ai/auth"));
-        assert!(auth_url.contains("callback_url=http%3A%2F%2Flocalhost%3A3000"));
+        assert!(auth_url.contains(&format!(
+            "callback_url=http%3A%2F%2Flocalhost%3A{}",
+            flow.port
+        )));
         assert!(auth_url.contains(&format!("code_challenge={}", flow.code_challenge)));
         assert!(auth_url.contains("code_challenge_method=S256"));
     }
@@ -65,4 +68,23 @@ mod tests {
         assert!(flow.code_verifier.len() >= 43);
         assert!(flow.code_verifier.len() <= 128);
     }
+
+    #[tokio::test]
+    async fn test_callback_falls_back_to_a_free_port() {
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+
+        let listener = bind_callback_listener(taken_port).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert_ne!(port, taken_port);
+
+        // The auth URL sends OpenRouter back to the port actually bound
+        let mut flow = PkceAuthFlow::new().expect("Failed to create PKCE flow");
+        flow.port = taken_port;
+        flow.bind_callback().await.unwrap();
+        assert_ne!(flow.port, taken_port);
+        assert!(flow
+            .get_auth_url()
+            .contains(&format!("localhost%3A{}", flow.port)));
+    }
 }
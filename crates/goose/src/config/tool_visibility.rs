@@ -0,0 +1,48 @@
+use super::base::Config;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+const TOOL_VISIBILITY_KEY: &str = "tool_visibility";
+
+/// Per-tool enablement, so individual tools can be hidden without removing their extension.
+///
+/// Stored in config as a map of (prefixed) tool name to enabled flag. Tools that are not in the
+/// map are enabled.
+pub struct ToolVisibilityManager;
+
+impl ToolVisibilityManager {
+    fn get_all() -> HashMap<String, bool> {
+        Config::global()
+            .get_param(TOOL_VISIBILITY_KEY)
+            .unwrap_or_default()
+    }
+
+    /// Names of all tools that have been disabled
+    pub fn disabled_tools() -> HashSet<String> {
+        Self::get_all()
+            .into_iter()
+            .filter(|(_, enabled)| !enabled)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Check if a tool is enabled
+    pub fn is_enabled(name: &str) -> bool {
+        Self::get_all().get(name).copied().unwrap_or(true)
+    }
+
+    /// Enable or disable a tool
+    pub fn set_enabled(name: &str, enabled: bool) -> Result<()> {
+        let config = Config::global();
+        let mut visibility = Self::get_all();
+        if enabled {
+            // Enabled is the default, so there is no need to keep the entry around
+            visibility.remove(name);
+        } else {
+            visibility.insert(name.to_string(), false);
+        }
+
+        config.set_param(TOOL_VISIBILITY_KEY, serde_json::to_value(visibility)?)?;
+        Ok(())
+    }
+}
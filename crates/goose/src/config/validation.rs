@@ -0,0 +1,648 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::base::{Config, ConfigError, PROFILES_KEY};
+use super::extensions::ExtensionEntry;
+use crate::providers::base::ProviderMetadata;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigIssueSeverity {
+    /// The value will fail when it is used
+    Error,
+    /// The value is probably a mistake but nothing will fail because of it
+    Warning,
+}
+
+/// A problem with one value in the config file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ConfigIssue {
+    /// Dotted path to the value, such as `extensions.github.type`
+    pub path: String,
+    pub severity: ConfigIssueSeverity,
+    pub message: String,
+    /// The closest valid value or key, when the problem looks like a typo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl ConfigIssue {
+    fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            severity: ConfigIssueSeverity::Error,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: ConfigIssueSeverity::Warning,
+            ..Self::error(path, message)
+        }
+    }
+
+    fn with_suggestion(mut self, suggestion: Option<&str>) -> Self {
+        self.suggestion = suggestion.map(str::to_string);
+        self
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean '{}'?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of value a known key holds
+#[derive(Debug, Clone, Copy)]
+enum ValueType {
+    String,
+    Bool,
+    Integer,
+    Number,
+    List,
+    Map,
+    OneOf(&'static [&'static str]),
+    /// The name of a provider from [`crate::providers::providers`]
+    Provider,
+    /// Structured values checked elsewhere or free-form
+    Any,
+}
+
+const KNOWN_KEYS: &[(&str, ValueType)] = &[
+    ("GOOSE_PROVIDER", ValueType::Provider),
+    ("GOOSE_MODEL", ValueType::String),
+    ("GOOSE_TEMPERATURE", ValueType::Number),
+    ("GOOSE_TOP_P", ValueType::Number),
+    ("GOOSE_MAX_TOKENS", ValueType::Integer),
+    ("GOOSE_STOP_SEQUENCES", ValueType::List),
+    ("GOOSE_NATIVE_TOOLS", ValueType::List),
+    ("CLAUDE_THINKING_ENABLED", ValueType::Bool),
+    ("CLAUDE_THINKING_BUDGET", ValueType::Integer),
+    ("GOOSE_CONTEXT_LIMIT", ValueType::Integer),
+    ("GOOSE_TOOLSHIM", ValueType::Bool),
+    ("GOOSE_TOOLSHIM_OLLAMA_MODEL", ValueType::String),
+    ("GOOSE_MODEL_CAPABILITIES", ValueType::List),
+    (
+        "GOOSE_MODE",
+        ValueType::OneOf(&["auto", "approve", "smart_approve", "chat"]),
+    ),
+    ("GOOSE_MAX_TURNS", ValueType::Integer),
+    ("GOOSE_SUBAGENT_MAX_TURNS", ValueType::Integer),
+    ("GOOSE_LEAD_PROVIDER", ValueType::Provider),
+    ("GOOSE_LEAD_MODEL", ValueType::String),
+    ("GOOSE_LEAD_TURNS", ValueType::Integer),
+    ("GOOSE_LEAD_FAILURE_THRESHOLD", ValueType::Integer),
+    ("GOOSE_LEAD_FALLBACK_TURNS", ValueType::Integer),
+    ("GOOSE_LEAD_CONTEXT_LIMIT", ValueType::Integer),
+    ("GOOSE_PLANNER_PROVIDER", ValueType::Provider),
+    ("GOOSE_PLANNER_MODEL", ValueType::String),
+    ("GOOSE_PLANNER_CONTEXT_LIMIT", ValueType::Integer),
+    ("GOOSE_WORKER_CONTEXT_LIMIT", ValueType::Integer),
+    ("GOOSE_SUMMARIZER_PROVIDER", ValueType::Provider),
+    ("GOOSE_SUMMARIZER_MODEL", ValueType::String),
+    ("GOOSE_EMBEDDING_MODEL_PROVIDER", ValueType::Provider),
+    ("GOOSE_EMBEDDING_MODEL", ValueType::String),
+    (
+        "GOOSE_CONTEXT_STRATEGY",
+        ValueType::OneOf(&["clear", "truncate", "summarize", "prompt"]),
+    ),
+    ("GOOSE_AUTO_COMPACT_THRESHOLD", ValueType::Number),
+    (
+        "GOOSE_ROUTER_TOOL_SELECTION_STRATEGY",
+        ValueType::OneOf(&["default", "vector", "llm"]),
+    ),
+    ("GOOSE_VECTOR_DB_PATH", ValueType::String),
+    ("GOOSE_SYSTEM_PROMPT_FILE_PATH", ValueType::String),
+    ("GOOSE_EXTENSION_TIMEOUT", ValueType::Integer),
+    ("GOOSE_EXTENSION_PING_INTERVAL", ValueType::Integer),
+    ("GOOSE_EXTENSION_MAX_MISSED_PINGS", ValueType::Integer),
+    ("GOOSE_EXTRA_ROOTS", ValueType::List),
+    ("GOOSE_STRICT_TOOL_ARGUMENTS", ValueType::Bool),
+    ("GOOSE_CONFIRMATION_TIMEOUT_SECS", ValueType::Integer),
+    (
+        "GOOSE_CONFIRMATION_TIMEOUT_ACTION",
+        ValueType::OneOf(&["allow_once", "deny"]),
+    ),
+    ("GOOSE_ALLOWLIST", ValueType::String),
+    ("GOOSE_MAX_TOOL_RESULT_BYTES", ValueType::Integer),
+    ("GOOSE_MAX_BACKGROUND_PROCESSES", ValueType::Integer),
+    ("GOOSE_BACKGROUND_OUTPUT_LIMIT", ValueType::Integer),
+    ("GOOSE_IMAGE_ARTIFACT_BYTES", ValueType::Integer),
+    ("GOOSE_MAX_ATTACHMENT_BYTES", ValueType::Integer),
+    ("GOOSE_ATTACHMENT_TYPES", ValueType::List),
+    ("GOOSE_AUDIT_LOG_ENABLED", ValueType::Bool),
+    ("GOOSE_AUDIT_LOG_FULL_ARGUMENTS", ValueType::Bool),
+    ("GOOSE_REDACTION_ENABLED", ValueType::Bool),
+    ("GOOSE_REDACTION_PATTERNS", ValueType::List),
+    ("GOOSE_SESSION_ENCRYPTION", ValueType::Bool),
+    ("GOOSE_SESSION_RETENTION_DAYS", ValueType::Integer),
+    ("GOOSE_SESSION_MAX_COUNT", ValueType::Integer),
+    ("GOOSE_PROVIDER_TRANSCRIPT_DIR", ValueType::String),
+    ("GOOSE_PROVIDER_TRANSCRIPT_MAX_BYTES", ValueType::Integer),
+    ("GOOSE_MODEL_CACHE_TTL_SECS", ValueType::Integer),
+    ("GOOSE_OAUTH_CALLBACK_PORT", ValueType::Integer),
+    ("GOOSE_HEADLESS_AUTH", ValueType::Bool),
+    ("GOOSE_CALLBACK_ALLOW_LINK_LOCAL", ValueType::Bool),
+    ("GOOSE_SERIAL_SESSIONS", ValueType::Bool),
+    ("GOOSE_SERIAL_QUEUE_SIZE", ValueType::Integer),
+    ("GOOSE_SERIAL_QUEUE_TIMEOUT", ValueType::Integer),
+    ("GOOSE_SERVER_RATE_LIMIT", ValueType::Integer),
+    ("GOOSE_SERVER_CLIENT_RATE_LIMIT", ValueType::Integer),
+    ("GOOSE_SERVER_DAILY_TOKEN_BUDGET", ValueType::Integer),
+    (
+        "GOOSE_SCHEDULER_TYPE",
+        ValueType::OneOf(&["legacy", "temporal"]),
+    ),
+    ("GOOSE_SCHEDULER_CONDITIONS", ValueType::Any),
+    ("GOOSE_SCHEDULER_RUN_HISTORY_LIMIT", ValueType::Integer),
+    ("GOOSE_RECIPE_GITHUB_REPO", ValueType::String),
+    ("GOOSE_RECIPE_PATH", ValueType::String),
+    ("GOOSE_RECIPE_RETRY_TIMEOUT_SECONDS", ValueType::Integer),
+    (
+        "GOOSE_RECIPE_ON_FAILURE_TIMEOUT_SECONDS",
+        ValueType::Integer,
+    ),
+    (
+        "GOOSE_CLI_THEME",
+        ValueType::OneOf(&["light", "dark", "ansi"]),
+    ),
+    ("GOOSE_CLI_MIN_PRIORITY", ValueType::Number),
+    ("GOOSE_CLI_SHOW_COST", ValueType::Bool),
+    ("GOOSE_CLI_SHOW_THINKING", ValueType::Bool),
+    (
+        "GOOSE_CLI_TOOL_PARAMS_TRUNCATION_MAX_LENGTH",
+        ValueType::Integer,
+    ),
+    ("extensions", ValueType::Any),
+    ("experiments", ValueType::Map),
+    ("tool_visibility", ValueType::Map),
+    ("personas", ValueType::Map),
+    ("model-limits", ValueType::List),
+    (PROFILES_KEY, ValueType::Map),
+];
+
+/// Keys that only work when another key is also set
+const REQUIRES: &[(&str, &str)] = &[
+    ("GOOSE_PROVIDER", "GOOSE_MODEL"),
+    ("GOOSE_LEAD_PROVIDER", "GOOSE_LEAD_MODEL"),
+    ("GOOSE_PLANNER_PROVIDER", "GOOSE_PLANNER_MODEL"),
+    ("GOOSE_SUMMARIZER_PROVIDER", "GOOSE_SUMMARIZER_MODEL"),
+    ("GOOSE_EMBEDDING_MODEL_PROVIDER", "GOOSE_EMBEDDING_MODEL"),
+];
+
+const EXTENSION_TYPES: &[&str] = &[
+    "builtin",
+    "stdio",
+    "sse",
+    "streamable_http",
+    "frontend",
+    "inline_python",
+];
+
+/// Check the config file against the known keys, the provider list and the extension schema,
+/// and check that the selected provider has everything it needs. Values set through
+/// environment variables and secrets count as set.
+pub fn validate_config(config: &Config) -> Result<Vec<ConfigIssue>, ConfigError> {
+    let values = config.load_values()?;
+    let mut effective = config.load_effective_values()?.values;
+    // The provider can also be chosen through the environment
+    if let Ok(provider) = config.get_param::<String>("GOOSE_PROVIDER") {
+        effective.insert("GOOSE_PROVIDER".to_string(), Value::String(provider));
+    }
+    let is_set = |key: &str| {
+        std::env::var(key.to_uppercase()).is_ok()
+            || effective.contains_key(key)
+            || config.get_secret::<Value>(key).is_ok()
+    };
+    Ok(validate_values(
+        &values,
+        &effective,
+        &crate::providers::providers(),
+        is_set,
+    ))
+}
+
+/// Validate the raw config file `values`. `effective` are the values with the active profile
+/// applied, which decide the provider whose keys are required, and `is_set` reports whether a
+/// key has a value from any source.
+fn validate_values(
+    values: &HashMap<String, Value>,
+    effective: &HashMap<String, Value>,
+    providers: &[ProviderMetadata],
+    is_set: impl Fn(&str) -> bool,
+) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+
+    let mut keys: Vec<&String> = values.keys().collect();
+    keys.sort();
+    for key in keys {
+        check_value("", key, &values[key], providers, &mut issues);
+    }
+
+    if let Some(Value::Object(profiles)) = values.get(PROFILES_KEY) {
+        for (profile, section) in sorted(profiles) {
+            let prefix = format!("{}.{}.", PROFILES_KEY, profile);
+            match section {
+                Value::Object(section) => {
+                    for (key, value) in sorted(section) {
+                        check_value(&prefix, key, value, providers, &mut issues);
+                    }
+                }
+                _ => issues.push(ConfigIssue::error(
+                    format!("{}.{}", PROFILES_KEY, profile),
+                    format!("Profile '{}' must be a map of config keys", profile),
+                )),
+            }
+        }
+    }
+
+    for (key, required) in REQUIRES {
+        if is_set(key) && !is_set(required) {
+            issues.push(ConfigIssue::error(
+                *required,
+                format!("{} is set but {} is not", key, required),
+            ));
+        }
+    }
+
+    let provider = effective
+        .get("GOOSE_PROVIDER")
+        .and_then(Value::as_str)
+        .and_then(|provider| providers.iter().find(|metadata| metadata.name == provider));
+    if let Some(metadata) = provider {
+        for config_key in &metadata.config_keys {
+            if config_key.required && config_key.default.is_none() && !is_set(&config_key.name) {
+                let mut issue = ConfigIssue::error(
+                    config_key.name.as_str(),
+                    format!(
+                        "{} requires {}, which is not set",
+                        metadata.display_name, config_key.name
+                    ),
+                );
+                if config_key.secret {
+                    issue
+                        .message
+                        .push_str(" in secret storage or the environment");
+                }
+                issues.push(issue);
+            }
+        }
+    }
+
+    issues
+}
+
+fn check_value(
+    prefix: &str,
+    key: &str,
+    value: &Value,
+    providers: &[ProviderMetadata],
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let path = format!("{}{}", prefix, key);
+
+    if let Err(e) = Config::interpolate_env_vars(key, value.clone()) {
+        issues.push(ConfigIssue::error(path.as_str(), e.to_string()));
+    }
+
+    let Some(value_type) = KNOWN_KEYS
+        .iter()
+        .find(|(known, _)| *known == key)
+        .map(|(_, value_type)| *value_type)
+    else {
+        if !is_provider_key(key, providers) {
+            let known_keys = KNOWN_KEYS.iter().map(|(known, _)| *known);
+            issues.push(
+                ConfigIssue::warning(path.as_str(), format!("{} is not a known config key", key))
+                    .with_suggestion(closest_match(key, known_keys)),
+            );
+        }
+        return;
+    };
+
+    if key == "extensions" {
+        check_extensions(&path, value, issues);
+        return;
+    }
+    if key == PROFILES_KEY && !prefix.is_empty() {
+        issues.push(ConfigIssue::error(path, "Profiles can't be nested"));
+        return;
+    }
+
+    let type_error = |expected: &str| {
+        ConfigIssue::error(
+            path.as_str(),
+            format!("{} must be {}, found {}", key, expected, describe(value)),
+        )
+    };
+    match value_type {
+        ValueType::String if !value.is_string() => issues.push(type_error("a string")),
+        ValueType::Bool if !value.is_boolean() => issues.push(type_error("true or false")),
+        ValueType::Integer if !(value.is_u64() || value.is_i64()) => {
+            issues.push(type_error("a whole number"))
+        }
+        ValueType::Number if !value.is_number() => issues.push(type_error("a number")),
+        ValueType::List if !value.is_array() => issues.push(type_error("a list")),
+        ValueType::Map if !value.is_object() => issues.push(type_error("a map")),
+        ValueType::OneOf(allowed) => match value.as_str() {
+            Some(text) if allowed.contains(&text) || text.contains("${env:") => {}
+            Some(text) => issues.push(
+                ConfigIssue::error(
+                    path.as_str(),
+                    format!(
+                        "{} is '{}', which is not one of {}",
+                        key,
+                        text,
+                        allowed.join(", ")
+                    ),
+                )
+                .with_suggestion(closest_match(text, allowed.iter().copied())),
+            ),
+            None => issues.push(type_error("a string")),
+        },
+        ValueType::Provider => match value.as_str() {
+            // References are resolved at read time, so only the resolved name could be checked
+            Some(text)
+                if providers.iter().any(|provider| provider.name == text)
+                    || text.contains("${env:") => {}
+            Some(text) => issues.push(
+                ConfigIssue::error(
+                    path.as_str(),
+                    format!("{} is '{}', which is not a known provider", key, text),
+                )
+                .with_suggestion(closest_match(
+                    text,
+                    providers.iter().map(|provider| provider.name.as_str()),
+                )),
+            ),
+            None => issues.push(type_error("a provider name")),
+        },
+        _ => {}
+    }
+}
+
+fn check_extensions(path: &str, value: &Value, issues: &mut Vec<ConfigIssue>) {
+    let Value::Object(entries) = value else {
+        issues.push(ConfigIssue::error(
+            path,
+            format!("extensions must be a map, found {}", describe(value)),
+        ));
+        return;
+    };
+
+    for (name, entry) in sorted(entries) {
+        let entry_path = format!("{}.{}", path, name);
+        match entry.get("type") {
+            None => {
+                issues.push(ConfigIssue::error(
+                    format!("{}.type", entry_path),
+                    format!(
+                        "Extension '{}' has no type; expected one of {}",
+                        name,
+                        EXTENSION_TYPES.join(", ")
+                    ),
+                ));
+                continue;
+            }
+            Some(Value::String(kind)) if !EXTENSION_TYPES.contains(&kind.as_str()) => {
+                issues.push(
+                    ConfigIssue::error(
+                        format!("{}.type", entry_path),
+                        format!(
+                            "Extension '{}' has type '{}', which is not one of {}",
+                            name,
+                            kind,
+                            EXTENSION_TYPES.join(", ")
+                        ),
+                    )
+                    .with_suggestion(closest_match(kind, EXTENSION_TYPES.iter().copied())),
+                );
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Err(e) = serde_json::from_value::<ExtensionEntry>(entry.clone()) {
+            issues.push(ConfigIssue::error(
+                entry_path,
+                format!("Extension '{}' is invalid: {}", name, e),
+            ));
+        }
+    }
+}
+
+/// Provider settings aren't all listed in the metadata, so keys sharing a prefix with a
+/// provider's config keys, like `DATABRICKS_MAX_RETRIES`, are accepted as provider settings
+fn is_provider_key(key: &str, providers: &[ProviderMetadata]) -> bool {
+    providers
+        .iter()
+        .flat_map(|provider| &provider.config_keys)
+        .any(|config_key| {
+            config_key.name == key
+                || config_key
+                    .name
+                    .split_once('_')
+                    .is_some_and(|(prefix, _)| key.starts_with(&format!("{}_", prefix)))
+        })
+}
+
+/// Entries in key order, so issues come out in the same order as the keys in the file
+fn sorted(map: &serde_json::Map<String, Value>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "nothing".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("'{}'", s),
+        Value::Array(_) => "a list".to_string(),
+        Value::Object(_) => "a map".to_string(),
+    }
+}
+
+/// The candidate closest to `input`, if it is close enough to be a likely typo
+fn closest_match<'a>(
+    input: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let input = input.to_lowercase();
+    let max_distance = (input.chars().count() / 3).clamp(2, 3);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&input, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::base::ConfigKey;
+    use serde_json::json;
+
+    fn providers() -> Vec<ProviderMetadata> {
+        vec![
+            ProviderMetadata::new(
+                "anthropic",
+                "Anthropic",
+                "",
+                "claude",
+                vec![],
+                "",
+                vec![ConfigKey::new("ANTHROPIC_API_KEY", true, true, None)],
+            ),
+            ProviderMetadata::new(
+                "databricks",
+                "Databricks",
+                "",
+                "model",
+                vec![],
+                "",
+                vec![
+                    ConfigKey::new("DATABRICKS_HOST", true, false, None),
+                    ConfigKey::new("DATABRICKS_TOKEN", false, true, None),
+                ],
+            ),
+        ]
+    }
+
+    fn validate(values: serde_json::Value, secrets: &[&str]) -> Vec<ConfigIssue> {
+        let values: HashMap<String, Value> = serde_json::from_value(values).unwrap();
+        validate_values(&values, &values, &providers(), |key| {
+            values.contains_key(key) || secrets.contains(&key)
+        })
+    }
+
+    #[test]
+    fn test_valid_config_has_no_issues() {
+        let issues = validate(
+            json!({
+                "GOOSE_PROVIDER": "anthropic",
+                "GOOSE_MODEL": "claude",
+                "GOOSE_MODE": "smart_approve",
+                "GOOSE_MAX_TURNS": 100,
+                "extensions": {
+                    "developer": {"enabled": true, "type": "builtin", "name": "developer"}
+                }
+            }),
+            &["ANTHROPIC_API_KEY"],
+        );
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn test_suggests_provider_and_enum_values() {
+        let issues = validate(
+            json!({
+                "GOOSE_PROVIDER": "anthropics",
+                "GOOSE_MODEL": "claude",
+                "GOOSE_MODE": "smart-approve",
+            }),
+            &[],
+        );
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].path, "GOOSE_MODE");
+        assert_eq!(issues[0].suggestion.as_deref(), Some("smart_approve"));
+        assert_eq!(
+            issues[1].to_string(),
+            "GOOSE_PROVIDER is 'anthropics', which is not a known provider, did you mean 'anthropic'?"
+        );
+    }
+
+    #[test]
+    fn test_type_errors_and_unknown_keys() {
+        let issues = validate(
+            json!({
+                "GOOSE_MAX_TURNS": "lots",
+                "GOOSE_MODELS": "claude",
+                "DATABRICKS_MAX_RETRIES": 3,
+                "profiles": {"work": {"GOOSE_TEMPERATURE": "hot"}}
+            }),
+            &[],
+        );
+        let paths: Vec<(&str, ConfigIssueSeverity)> = issues
+            .iter()
+            .map(|issue| (issue.path.as_str(), issue.severity))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("GOOSE_MAX_TURNS", ConfigIssueSeverity::Error),
+                ("GOOSE_MODELS", ConfigIssueSeverity::Warning),
+                (
+                    "profiles.work.GOOSE_TEMPERATURE",
+                    ConfigIssueSeverity::Error
+                ),
+            ]
+        );
+        assert_eq!(issues[1].suggestion.as_deref(), Some("GOOSE_MODEL"));
+    }
+
+    #[test]
+    fn test_required_keys() {
+        let issues = validate(json!({"GOOSE_PROVIDER": "databricks"}), &[]);
+        let paths: Vec<&str> = issues.iter().map(|issue| issue.path.as_str()).collect();
+        assert_eq!(paths, vec!["GOOSE_MODEL", "DATABRICKS_HOST"]);
+
+        let issues = validate(
+            json!({"GOOSE_PROVIDER": "anthropic", "GOOSE_MODEL": "claude"}),
+            &[],
+        );
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("ANTHROPIC_API_KEY"));
+    }
+
+    #[test]
+    fn test_extension_entries() {
+        let issues = validate(
+            json!({
+                "extensions": {
+                    "github": {"enabled": true, "type": "stdoi", "name": "github", "cmd": "gh"},
+                    "fetch": {"enabled": true, "type": "stdio", "name": "fetch"},
+                    "notype": {"enabled": true, "name": "notype"},
+                }
+            }),
+            &[],
+        );
+        let paths: Vec<&str> = issues.iter().map(|issue| issue.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "extensions.fetch",
+                "extensions.github.type",
+                "extensions.notype.type"
+            ]
+        );
+        assert_eq!(issues[1].suggestion.as_deref(), Some("stdio"));
+    }
+}
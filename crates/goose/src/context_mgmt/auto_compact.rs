@@ -3,6 +3,7 @@ use crate::{
     config::Config,
     context_mgmt::{estimate_target_context_limit, get_messages_token_counts_async},
     message::Message,
+    session::resolve_attachments,
     token_counter::create_async_token_counter,
 };
 use anyhow::Result;
@@ -46,6 +47,7 @@ pub struct CompactionCheckResult {
 /// # Arguments
 /// * `agent` - The agent to use for context management
 /// * `messages` - The current message history
+/// * `session_id` - The session the messages belong to, to count its attachments by their contents
 /// * `threshold_override` - Optional threshold override (defaults to GOOSE_AUTO_COMPACT_THRESHOLD config)
 ///
 /// # Returns
@@ -53,6 +55,7 @@ pub struct CompactionCheckResult {
 pub async fn check_compaction_needed(
     agent: &Agent,
     messages: &[Message],
+    session_id: Option<&str>,
     threshold_override: Option<f64>,
 ) -> Result<CompactionCheckResult> {
     // Get threshold from config or use override
@@ -63,15 +66,12 @@ pub async fn check_compaction_needed(
             .unwrap_or(0.3) // Default to 30%
     });
 
-    // Get provider and token counter
+    // Count with the provider's model so its own tokenizer is used where one is known. The
+    // target context limit already sets aside room for the system prompt and tools.
     let provider = agent.provider().await?;
-    let token_counter = create_async_token_counter()
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to create token counter: {}", e))?;
-
-    // Calculate current token usage
-    let token_counts = get_messages_token_counts_async(&token_counter, messages);
-    let current_tokens: usize = token_counts.iter().sum();
+    let current_tokens = provider
+        .get_model_config()
+        .count_tokens(&resolve_attachments(session_id, messages), &[]);
     let context_limit = estimate_target_context_limit(provider);
 
     // Calculate usage ratio
@@ -164,6 +164,7 @@ pub async fn perform_compaction(
 /// # Arguments
 /// * `agent` - The agent to use for context management
 /// * `messages` - The current message history
+/// * `session_id` - The session the messages belong to, to count its attachments by their contents
 /// * `threshold_override` - Optional threshold override (defaults to GOOSE_AUTO_COMPACT_THRESHOLD config)
 ///
 /// # Returns
@@ -171,10 +172,12 @@ pub async fn perform_compaction(
 pub async fn check_and_compact_messages(
     agent: &Agent,
     messages: &[Message],
+    session_id: Option<&str>,
     threshold_override: Option<f64>,
 ) -> Result<AutoCompactResult> {
     // First check if compaction is needed
-    let check_result = check_compaction_needed(agent, messages, threshold_override).await?;
+    let check_result =
+        check_compaction_needed(agent, messages, session_id, threshold_override).await?;
 
     // If no compaction is needed, return early
     if !check_result.needs_compaction {
@@ -196,6 +199,22 @@ pub async fn check_and_compact_messages(
         check_result.usage_ratio * 100.0
     );
 
+    compact_messages(agent, messages).await
+}
+
+/// Compact messages unconditionally
+///
+/// Used when compaction is already known to be necessary, e.g. after the provider rejected the
+/// conversation for exceeding the context window. If the most recent message is a user message,
+/// it is preserved by removing it before compaction and adding it back afterwards.
+///
+/// # Arguments
+/// * `agent` - The agent to use for context management
+/// * `messages` - The current message history
+///
+/// # Returns
+/// * `AutoCompactResult` containing the compacted messages and token counts
+pub async fn compact_messages(agent: &Agent, messages: &[Message]) -> Result<AutoCompactResult> {
     // Check if the most recent message is a user message
     let (messages_to_compact, preserved_user_message) = if let Some(last_message) = messages.last()
     {
@@ -300,7 +319,7 @@ mod tests {
         // Create small messages that won't trigger compaction
         let messages = vec![create_test_message("Hello"), create_test_message("World")];
 
-        let result = check_compaction_needed(&agent, &messages, Some(0.3))
+        let result = check_compaction_needed(&agent, &messages, None, Some(0.3))
             .await
             .unwrap();
 
@@ -326,14 +345,14 @@ mod tests {
         let messages = vec![create_test_message("Hello")];
 
         // Test with threshold 0 (disabled)
-        let result = check_compaction_needed(&agent, &messages, Some(0.0))
+        let result = check_compaction_needed(&agent, &messages, None, Some(0.0))
             .await
             .unwrap();
 
         assert!(!result.needs_compaction);
 
         // Test with threshold 1.0 (disabled)
-        let result = check_compaction_needed(&agent, &messages, Some(1.0))
+        let result = check_compaction_needed(&agent, &messages, None, Some(1.0))
             .await
             .unwrap();
 
@@ -382,7 +401,7 @@ mod tests {
         let messages = vec![create_test_message("Hello"), create_test_message("World")];
 
         // Test with threshold 0 (disabled)
-        let result = check_and_compact_messages(&agent, &messages, Some(0.0))
+        let result = check_and_compact_messages(&agent, &messages, None, Some(0.0))
             .await
             .unwrap();
 
@@ -392,7 +411,7 @@ mod tests {
         assert!(result.tokens_after.is_none());
 
         // Test with threshold 1.0 (disabled)
-        let result = check_and_compact_messages(&agent, &messages, Some(1.0))
+        let result = check_and_compact_messages(&agent, &messages, None, Some(1.0))
             .await
             .unwrap();
 
@@ -413,7 +432,7 @@ mod tests {
         // Create small messages that won't trigger compaction
         let messages = vec![create_test_message("Hello"), create_test_message("World")];
 
-        let result = check_and_compact_messages(&agent, &messages, Some(0.3))
+        let result = check_and_compact_messages(&agent, &messages, None, Some(0.3))
             .await
             .unwrap();
 
@@ -448,7 +467,7 @@ mod tests {
             )));
         }
 
-        let result = check_and_compact_messages(&agent, &messages, Some(0.3))
+        let result = check_and_compact_messages(&agent, &messages, None, Some(0.3))
             .await
             .unwrap();
 
@@ -501,16 +520,14 @@ mod tests {
             .unwrap();
 
         // Should use config value when no override provided
-        let result = check_and_compact_messages(&agent, &messages, None)
+        let result = check_and_compact_messages(&agent, &messages, None, None)
             .await
             .unwrap();
 
         // Debug info if not compacted
         if !result.compacted {
             let provider = agent.provider().await.unwrap();
-            let token_counter = create_async_token_counter().await.unwrap();
-            let token_counts = get_messages_token_counts_async(&token_counter, &messages);
-            let total_tokens: usize = token_counts.iter().sum();
+            let total_tokens = provider.get_model_config().count_tokens(&messages, &[]);
             let context_limit = estimate_target_context_limit(provider);
             let usage_ratio = total_tokens as f64 / context_limit as f64;
 
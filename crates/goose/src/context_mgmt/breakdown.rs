@@ -0,0 +1,189 @@
+//! Where the tokens of a request that overflowed the context window went, so users can see
+//! whether the system prompt, the tool definitions or a few huge messages are to blame.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::message::Message;
+use crate::model::ModelConfig;
+use crate::tokenizer;
+
+/// Largest messages listed in a breakdown
+pub const LARGEST_MESSAGES: usize = 5;
+
+/// "prompt is too long: 208310 tokens > 200000 maximum" (Anthropic)
+static TOKENS_OVER_MAXIMUM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(\d+) tokens > (\d+) maximum").expect("token count pattern is valid")
+});
+/// "maximum context length is 128000 tokens. However, your messages resulted in 130021 tokens"
+/// (OpenAI and compatible servers)
+static MAXIMUM_CONTEXT_LENGTH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"maximum context length is (\d+) tokens").expect("limit pattern is valid")
+});
+static RESULTED_IN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"resulted in (\d+) tokens").expect("token count pattern is valid"));
+/// "The input token count (1200000) exceeds the maximum number of tokens allowed (1048576)"
+/// (Google)
+static INPUT_TOKEN_COUNT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"input token count \((\d+)\) exceeds the maximum number of tokens allowed \((\d+)\)",
+    )
+    .expect("token count pattern is valid")
+});
+
+/// Tokens taken up by one message of the conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MessageTokens {
+    /// Position of the message in the conversation
+    pub index: usize,
+    pub tokens: usize,
+}
+
+/// How the tokens of a request add up against the model's context limit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ContextBreakdown {
+    /// Tokens of the whole request, counted locally
+    pub estimated_tokens: usize,
+    /// The model's context limit
+    pub context_limit: usize,
+    pub system_prompt_tokens: usize,
+    pub tool_tokens: usize,
+    pub message_tokens: usize,
+    /// Tokens the provider counted, when its error said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_tokens: Option<usize>,
+    /// Context limit the provider enforced, when its error said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_limit: Option<usize>,
+    /// The largest messages, largest first
+    pub largest_messages: Vec<MessageTokens>,
+}
+
+impl ContextBreakdown {
+    /// Count a request to `model` with the given prompt, tools and messages. `provider_error`
+    /// is the provider's error message, read for the counts it reports.
+    pub fn new(
+        model: &ModelConfig,
+        system_prompt: &str,
+        tools: &[Tool],
+        messages: &[Message],
+        provider_error: &str,
+    ) -> Self {
+        let system_prompt_tokens = tokenizer::count_text(system_prompt, &model.model_name);
+        let tool_tokens = tokenizer::count_tools(tools, &model.model_name);
+        let message_tokens = tokenizer::count_messages(messages, &model.model_name);
+        let (reported_tokens, reported_limit) = reported_token_counts(provider_error);
+        Self {
+            estimated_tokens: system_prompt_tokens + tool_tokens + message_tokens,
+            context_limit: model.context_limit(),
+            system_prompt_tokens,
+            tool_tokens,
+            message_tokens,
+            reported_tokens,
+            reported_limit,
+            largest_messages: largest_messages(messages, &model.model_name, LARGEST_MESSAGES),
+        }
+    }
+}
+
+/// The `count` largest of `messages` for `model`, largest first
+pub fn largest_messages(messages: &[Message], model: &str, count: usize) -> Vec<MessageTokens> {
+    let mut sizes: Vec<MessageTokens> = messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| MessageTokens {
+            index,
+            tokens: tokenizer::count_message(message, model),
+        })
+        .collect();
+    // Ties keep conversation order
+    sizes.sort_by(|a, b| b.tokens.cmp(&a.tokens).then(a.index.cmp(&b.index)));
+    sizes.truncate(count);
+    sizes
+}
+
+/// The request size and limit a provider's context length error states, as far as it states
+/// them
+pub fn reported_token_counts(error: &str) -> (Option<usize>, Option<usize>) {
+    if let Some(captures) = TOKENS_OVER_MAXIMUM
+        .captures(error)
+        .or_else(|| INPUT_TOKEN_COUNT.captures(error))
+    {
+        return (number(&captures, 1), number(&captures, 2));
+    }
+    (
+        RESULTED_IN
+            .captures(error)
+            .and_then(|captures| number(&captures, 1)),
+        MAXIMUM_CONTEXT_LENGTH
+            .captures(error)
+            .and_then(|captures| number(&captures, 1)),
+    )
+}
+
+fn number(captures: &regex::Captures, group: usize) -> Option<usize> {
+    captures.get(group)?.as_str().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reported_token_counts() {
+        assert_eq!(
+            reported_token_counts("prompt is too long: 208310 tokens > 200000 maximum"),
+            (Some(208310), Some(200000))
+        );
+        assert_eq!(
+            reported_token_counts(
+                "This model's maximum context length is 128000 tokens. However, your messages \
+                 resulted in 130021 tokens. Please reduce the length of the messages."
+            ),
+            (Some(130021), Some(128000))
+        );
+        assert_eq!(
+            reported_token_counts(
+                "The input token count (1200000) exceeds the maximum number of tokens allowed \
+                 (1048576)."
+            ),
+            (Some(1200000), Some(1048576))
+        );
+        assert_eq!(reported_token_counts("Input is too long"), (None, None));
+    }
+
+    #[test]
+    fn test_breakdown_lists_the_largest_messages() {
+        let model = ModelConfig::new("claude-sonnet-4")
+            .unwrap()
+            .with_context_limit(Some(1_000));
+        let mut messages: Vec<Message> = (0..8)
+            .map(|i| Message::user().with_text(format!("message {}", i)))
+            .collect();
+        messages[2] = Message::user().with_text("log line ".repeat(400));
+        messages[6] = Message::assistant().with_text("output ".repeat(100));
+
+        let breakdown = ContextBreakdown::new(
+            &model,
+            "You are a helpful assistant.",
+            &[],
+            &messages,
+            "prompt is too long: 1203 tokens > 1000 maximum",
+        );
+
+        assert_eq!(breakdown.context_limit, 1_000);
+        assert_eq!(breakdown.reported_tokens, Some(1203));
+        assert_eq!(breakdown.tool_tokens, 0);
+        assert_eq!(
+            breakdown.estimated_tokens,
+            breakdown.system_prompt_tokens + breakdown.message_tokens
+        );
+        assert_eq!(breakdown.largest_messages.len(), LARGEST_MESSAGES);
+        assert_eq!(breakdown.largest_messages[0].index, 2);
+        assert_eq!(breakdown.largest_messages[1].index, 6);
+        assert!(breakdown.largest_messages[0].tokens > breakdown.largest_messages[1].tokens);
+    }
+}
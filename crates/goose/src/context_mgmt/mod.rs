@@ -1,6 +1,8 @@
 pub mod auto_compact;
+pub mod breakdown;
 mod common;
 pub mod summarize;
+pub mod summarizer;
 pub mod truncate;
 
 pub use common::*;
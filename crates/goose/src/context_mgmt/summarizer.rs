@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::providers;
+use crate::providers::base::Provider;
+
+const SUMMARIZER_PROVIDER_KEY: &str = "GOOSE_SUMMARIZER_PROVIDER";
+const SUMMARIZER_MODEL_KEY: &str = "GOOSE_SUMMARIZER_MODEL";
+
+/// Build the secondary provider used for summarization and session naming
+///
+/// Configured with `GOOSE_SUMMARIZER_MODEL` and optionally `GOOSE_SUMMARIZER_PROVIDER`, which
+/// defaults to `GOOSE_PROVIDER`. Returns `None` when no summarizer model is configured or the
+/// provider can't be constructed, in which case callers should use the primary provider.
+pub fn summarizer_provider() -> Option<Arc<dyn Provider>> {
+    let config = Config::global();
+    let model_name = config.get_param::<String>(SUMMARIZER_MODEL_KEY).ok()?;
+    let provider_name = config
+        .get_param::<String>(SUMMARIZER_PROVIDER_KEY)
+        .or_else(|_| config.get_param::<String>("GOOSE_PROVIDER"))
+        .ok()?;
+
+    match providers::create_with_model(&provider_name, &model_name) {
+        Ok(provider) => Some(provider),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to create summarizer provider {}/{}, using the primary provider: {}",
+                provider_name,
+                model_name,
+                e
+            );
+            None
+        }
+    }
+}
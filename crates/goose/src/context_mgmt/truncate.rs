@@ -1,7 +1,7 @@
 use crate::message::{Message, MessageContent};
 use crate::utils::safe_truncate;
 use anyhow::{anyhow, Result};
-use rmcp::model::{RawContent, ResourceContents, Role};
+use rmcp::model::{Content, RawContent, ResourceContents, Role};
 use std::collections::HashSet;
 use std::ops::DerefMut;
 use tracing::{debug, warn};
@@ -9,16 +9,77 @@ use tracing::{debug, warn};
 /// Maximum size for truncated content in characters
 const MAX_TRUNCATED_CONTENT_SIZE: usize = 5000;
 
+/// Text that replaces a tool response body when tool outputs are dropped
+const DROPPED_TOOL_OUTPUT_PLACEHOLDER: &str = "[tool output removed to save context]";
+
+/// Result of truncating a conversation, including what happened to each input message
+#[derive(Debug, Clone, Default)]
+pub struct TruncationOutcome {
+    pub messages: Vec<Message>,
+    pub token_counts: Vec<usize>,
+    /// Indices into the input of messages that were dropped
+    pub removed: Vec<usize>,
+    /// Indices into the input of messages that were kept with shortened content
+    pub modified: Vec<usize>,
+}
+
+/// Messages being truncated, each remembering the input index it came from
+struct TrackedMessages {
+    messages: Vec<Message>,
+    token_counts: Vec<usize>,
+    origins: Vec<usize>,
+    modified: HashSet<usize>,
+}
+
+impl TrackedMessages {
+    fn new(messages: &[Message], token_counts: &[usize]) -> Self {
+        Self {
+            messages: messages.to_owned(),
+            token_counts: token_counts.to_owned(),
+            origins: (0..messages.len()).collect(),
+            modified: HashSet::new(),
+        }
+    }
+
+    /// Removes the message at `index`, returning its token count
+    fn remove(&mut self, index: usize) -> usize {
+        self.messages.remove(index);
+        self.origins.remove(index);
+        self.token_counts.remove(index)
+    }
+
+    fn into_outcome(self, input_len: usize) -> TruncationOutcome {
+        let kept: HashSet<usize> = self.origins.iter().copied().collect();
+        let removed = (0..input_len).filter(|i| !kept.contains(i)).collect();
+        let mut modified: Vec<usize> = self
+            .modified
+            .into_iter()
+            .filter(|i| kept.contains(i))
+            .collect();
+        modified.sort_unstable();
+
+        TruncationOutcome {
+            messages: self.messages,
+            token_counts: self.token_counts,
+            removed,
+            modified,
+        }
+    }
+}
+
 /// Handles messages that are individually larger than the context limit
 /// by truncating their content rather than removing them entirely
 fn handle_oversized_messages(
-    messages: &[Message],
-    token_counts: &[usize],
+    tracked: TrackedMessages,
     context_limit: usize,
     strategy: &dyn TruncationStrategy,
-) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
-    let mut truncated_messages = Vec::new();
-    let mut truncated_token_counts = Vec::new();
+) -> Result<TrackedMessages, anyhow::Error> {
+    let mut truncated = TrackedMessages {
+        messages: Vec::new(),
+        token_counts: Vec::new(),
+        origins: Vec::new(),
+        modified: tracked.modified,
+    };
     let mut any_truncated = false;
 
     // Create a basic token counter for re-estimating truncated content
@@ -28,7 +89,13 @@ fn handle_oversized_messages(
         (text.len() / 4).max(1)
     };
 
-    for (i, (message, &original_tokens)) in messages.iter().zip(token_counts.iter()).enumerate() {
+    for (i, ((message, &original_tokens), &origin)) in tracked
+        .messages
+        .iter()
+        .zip(tracked.token_counts.iter())
+        .zip(tracked.origins.iter())
+        .enumerate()
+    {
         if original_tokens > context_limit {
             warn!(
                 "Message {} has {} tokens, exceeding context limit of {}",
@@ -47,27 +114,25 @@ fn handle_oversized_messages(
                 continue;
             }
 
-            truncated_messages.push(truncated_message);
-            truncated_token_counts.push(estimated_new_tokens);
+            truncated.messages.push(truncated_message);
+            truncated.token_counts.push(estimated_new_tokens);
+            truncated.origins.push(origin);
+            truncated.modified.insert(origin);
             any_truncated = true;
         } else {
-            truncated_messages.push(message.clone());
-            truncated_token_counts.push(original_tokens);
+            truncated.messages.push(message.clone());
+            truncated.token_counts.push(original_tokens);
+            truncated.origins.push(origin);
         }
     }
 
     if any_truncated {
         debug!("Truncated large message content, now attempting normal truncation");
         // After content truncation, try normal truncation if still needed
-        return truncate_messages(
-            &truncated_messages,
-            &truncated_token_counts,
-            context_limit,
-            strategy,
-        );
+        return truncate_tracked(truncated, context_limit, strategy);
     }
 
-    Ok((truncated_messages, truncated_token_counts))
+    Ok(truncated)
 }
 
 /// Truncates the content within a message while preserving its structure
@@ -169,46 +234,67 @@ fn estimate_message_tokens(message: &Message, estimate_fn: &dyn Fn(&str) -> usiz
 }
 
 /// Truncates the messages to fit within the model's context window.
-/// Mutates the input messages and token counts in place.
 /// Returns an error if it's impossible to truncate the messages within the context limit.
 /// - messages: The vector of messages in the conversation.
 /// - token_counts: A parallel vector containing the token count for each message.
 /// - context_limit: The maximum allowed context length in tokens.
-/// - strategy: The truncation strategy to use. Only option is OldestFirstTruncation.
+/// - strategy: The truncation strategy to use, e.g. OldestFirstTruncation or MiddleOutTruncation.
 pub fn truncate_messages(
     messages: &[Message],
     token_counts: &[usize],
     context_limit: usize,
     strategy: &dyn TruncationStrategy,
 ) -> Result<(Vec<Message>, Vec<usize>), anyhow::Error> {
-    let mut messages = messages.to_owned();
-    let mut token_counts = token_counts.to_owned();
+    let outcome = truncate_messages_with_outcome(messages, token_counts, context_limit, strategy)?;
+    Ok((outcome.messages, outcome.token_counts))
+}
 
+/// Same as [`truncate_messages`], but also reports which input messages were removed or modified.
+pub fn truncate_messages_with_outcome(
+    messages: &[Message],
+    token_counts: &[usize],
+    context_limit: usize,
+    strategy: &dyn TruncationStrategy,
+) -> Result<TruncationOutcome, anyhow::Error> {
     if messages.len() != token_counts.len() {
         return Err(anyhow!(
             "The vector for messages and token_counts must have same length"
         ));
     }
 
+    let tracked = truncate_tracked(
+        TrackedMessages::new(messages, token_counts),
+        context_limit,
+        strategy,
+    )?;
+    Ok(tracked.into_outcome(messages.len()))
+}
+
+fn truncate_tracked(
+    mut tracked: TrackedMessages,
+    context_limit: usize,
+    strategy: &dyn TruncationStrategy,
+) -> Result<TrackedMessages, anyhow::Error> {
     // Step 1: Calculate total tokens
-    let mut total_tokens: usize = token_counts.iter().sum();
+    let mut total_tokens: usize = tracked.token_counts.iter().sum();
     debug!("Total tokens before truncation: {}", total_tokens);
 
     // Check if any individual message is larger than the context limit
     // First, check for any message that's too large
-    let max_message_tokens = token_counts.iter().max().copied().unwrap_or(0);
+    let max_message_tokens = tracked.token_counts.iter().max().copied().unwrap_or(0);
     if max_message_tokens > context_limit {
         // Try to handle large messages by truncating their content
         debug!(
             "Found oversized message with {} tokens, attempting content truncation",
             max_message_tokens
         );
-        return handle_oversized_messages(&messages, &token_counts, context_limit, strategy);
+        return handle_oversized_messages(tracked, context_limit, strategy);
     }
 
-    let min_user_msg_tokens = messages
+    let min_user_msg_tokens = tracked
+        .messages
         .iter()
-        .zip(token_counts.iter())
+        .zip(tracked.token_counts.iter())
         .filter(|(msg, _)| msg.role == Role::User && msg.has_only_text_content())
         .map(|(_, &tokens)| tokens)
         .min();
@@ -221,17 +307,20 @@ pub fn truncate_messages(
     }
 
     if total_tokens <= context_limit {
-        return Ok((messages, token_counts)); // No truncation needed
+        return Ok(tracked); // No truncation needed
     }
 
     // Step 2: Determine indices to remove based on strategy
-    let indices_to_remove =
-        strategy.determine_indices_to_remove(&messages, &token_counts, context_limit)?;
+    let indices_to_remove = strategy.determine_indices_to_remove(
+        &tracked.messages,
+        &tracked.token_counts,
+        context_limit,
+    )?;
 
     // Circuit breaker: if we can't remove enough messages, fail gracefully
     let tokens_to_remove: usize = indices_to_remove
         .iter()
-        .map(|&i| token_counts.get(i).copied().unwrap_or(0))
+        .map(|&i| tracked.token_counts.get(i).copied().unwrap_or(0))
         .sum();
 
     if total_tokens - tokens_to_remove > context_limit && !indices_to_remove.is_empty() {
@@ -241,7 +330,7 @@ pub fn truncate_messages(
             tokens_to_remove
         );
         // Try more aggressive truncation or content truncation
-        return handle_oversized_messages(&messages, &token_counts, context_limit, strategy);
+        return handle_oversized_messages(tracked, context_limit, strategy);
     }
 
     if indices_to_remove.is_empty() && total_tokens > context_limit {
@@ -256,32 +345,25 @@ pub fn truncate_messages(
     indices_to_remove.sort_unstable_by(|a, b| b.cmp(a));
 
     for &index in &indices_to_remove {
-        if index < messages.len() {
-            let _ = messages.remove(index);
-            let removed_tokens = token_counts.remove(index);
-            total_tokens -= removed_tokens;
+        if index < tracked.messages.len() {
+            total_tokens -= tracked.remove(index);
         }
     }
 
     // Step 4: Ensure the last message is a user message with TextContent only
-    while let Some(last_msg) = messages.last() {
+    while let Some(last_msg) = tracked.messages.last() {
         if last_msg.role != Role::User || !last_msg.has_only_text_content() {
-            let _ = messages.pop().ok_or(anyhow!("Failed to pop message"))?;
-            let removed_tokens = token_counts
-                .pop()
-                .ok_or(anyhow!("Failed to pop token count"))?;
-            total_tokens -= removed_tokens;
+            let last_index = tracked.messages.len() - 1;
+            total_tokens -= tracked.remove(last_index);
         } else {
             break;
         }
     }
 
     // Step 5: Check first msg is a User message with TextContent only
-    while let Some(first_msg) = messages.first() {
+    while let Some(first_msg) = tracked.messages.first() {
         if first_msg.role != Role::User || !first_msg.has_only_text_content() {
-            let _ = messages.remove(0);
-            let removed_tokens = token_counts.remove(0);
-            total_tokens -= removed_tokens;
+            total_tokens -= tracked.remove(0);
         } else {
             break;
         }
@@ -290,7 +372,7 @@ pub fn truncate_messages(
     debug!("Total tokens after truncation: {}", total_tokens);
 
     // Ensure we have at least one message remaining and it's within context limit
-    if messages.is_empty() {
+    if tracked.messages.is_empty() {
         return Err(anyhow!(
             "Unable to preserve any messages within context limit"
         ));
@@ -303,7 +385,71 @@ pub fn truncate_messages(
     }
 
     debug!("Truncation complete. Total tokens: {}", total_tokens);
-    Ok((messages, token_counts))
+    Ok(tracked)
+}
+
+/// Replaces the bodies of older tool responses with a short placeholder until the conversation
+/// fits within the context limit. Tool outputs are usually the bulk of a conversation, and
+/// keeping the responses (rather than removing them) keeps every ToolRequest paired.
+///
+/// The most recent message is never touched. If clearing tool outputs is not enough, the
+/// remaining overflow is handled by [`OldestFirstTruncation`].
+/// - count_tokens: Counts the tokens of a single message, used to re-count modified messages.
+pub fn drop_tool_outputs(
+    messages: &[Message],
+    token_counts: &[usize],
+    context_limit: usize,
+    count_tokens: &dyn Fn(&Message) -> usize,
+) -> Result<TruncationOutcome, anyhow::Error> {
+    if messages.len() != token_counts.len() {
+        return Err(anyhow!(
+            "The vector for messages and token_counts must have same length"
+        ));
+    }
+
+    let mut tracked = TrackedMessages::new(messages, token_counts);
+    let mut total_tokens: usize = tracked.token_counts.iter().sum();
+    let last_index = messages.len().saturating_sub(1);
+
+    for i in 0..last_index {
+        if total_tokens <= context_limit {
+            break;
+        }
+
+        let mut changed = false;
+        for content in &mut tracked.messages[i].content {
+            if let MessageContent::ToolResponse(tool_response) = content {
+                if let Ok(result) = &mut tool_response.tool_result {
+                    let already_dropped = result.len() == 1
+                        && result[0]
+                            .as_text()
+                            .is_some_and(|t| t.text == DROPPED_TOOL_OUTPUT_PLACEHOLDER);
+                    if !already_dropped {
+                        *result = vec![Content::text(DROPPED_TOOL_OUTPUT_PLACEHOLDER)];
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if changed {
+            let new_tokens = count_tokens(&tracked.messages[i]);
+            total_tokens = total_tokens - tracked.token_counts[i] + new_tokens;
+            tracked.token_counts[i] = new_tokens;
+            tracked.modified.insert(i);
+            debug!(
+                "DropToolOutputs: Cleared tool output at index {}. Total tokens: {}",
+                i, total_tokens
+            );
+        }
+    }
+
+    if total_tokens > context_limit {
+        debug!("Dropping tool outputs was not enough, falling back to oldest first truncation");
+        tracked = truncate_tracked(tracked, context_limit, &OldestFirstTruncation)?;
+    }
+
+    Ok(tracked.into_outcome(messages.len()))
 }
 
 /// Trait representing a truncation strategy
@@ -321,6 +467,11 @@ pub trait TruncationStrategy {
         token_counts: &[usize],
         context_limit: usize,
     ) -> Result<HashSet<usize>>;
+
+    /// What the assistant tells the user about the messages this strategy removed
+    fn notice(&self) -> &'static str {
+        "I had run into a context length exceeded error so I truncated some of the oldest messages in our conversation."
+    }
 }
 
 /// Strategy to truncate messages by removing the oldest first
@@ -375,6 +526,71 @@ impl TruncationStrategy for OldestFirstTruncation {
     }
 }
 
+/// Strategy to truncate messages from the middle of the conversation outwards, keeping the
+/// first message (usually the original task) and the most recent messages
+pub struct MiddleOutTruncation;
+
+impl TruncationStrategy for MiddleOutTruncation {
+    fn notice(&self) -> &'static str {
+        "I had run into a context length exceeded error so I removed some messages from the middle of our conversation, keeping the earliest and latest ones."
+    }
+
+    fn determine_indices_to_remove(
+        &self,
+        messages: &[Message],
+        token_counts: &[usize],
+        context_limit: usize,
+    ) -> Result<HashSet<usize>> {
+        let mut indices_to_remove = HashSet::new();
+        let mut total_tokens: usize = token_counts.iter().sum();
+
+        if messages.len() < 3 {
+            return Ok(indices_to_remove);
+        }
+
+        // Everything except the head and the tail, closest to the middle first
+        let middle = messages.len() / 2;
+        let mut candidates: Vec<usize> = (1..messages.len() - 1).collect();
+        candidates.sort_by_key(|&i| (i.abs_diff(middle), i));
+
+        for i in candidates {
+            if total_tokens <= context_limit {
+                break;
+            }
+            if indices_to_remove.insert(i) {
+                total_tokens -= token_counts[i];
+                debug!(
+                    "MiddleOut: Removing message at index {}. Tokens removed: {}",
+                    i, token_counts[i]
+                );
+            }
+        }
+
+        add_paired_tool_messages(messages, &mut indices_to_remove);
+        Ok(indices_to_remove)
+    }
+}
+
+/// Marks the other half of every ToolRequest/ToolResponse pair touched by `indices_to_remove`,
+/// so that a request is never kept without its response or the other way around
+fn add_paired_tool_messages(messages: &[Message], indices_to_remove: &mut HashSet<usize>) {
+    let tool_ids: HashSet<String> = indices_to_remove
+        .iter()
+        .flat_map(|&i| messages[i].get_tool_ids())
+        .map(String::from)
+        .collect();
+
+    for (i, message) in messages.iter().enumerate() {
+        if message
+            .get_tool_ids()
+            .iter()
+            .any(|id| tool_ids.contains(*id))
+        {
+            indices_to_remove.insert(i);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -717,4 +933,74 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_middle_out_keeps_head_tail_and_tool_pairs() -> Result<()> {
+        let messages = vec![
+            user_text(0, 10).0,
+            assistant_text(1, 10).0,
+            user_text(2, 10).0,
+            assistant_tool_request("tool1", ToolCall::new("read_file", json!({})), 50).0,
+            user_tool_response("tool1", vec![Content::text("contents")], 50).0,
+            assistant_text(5, 10).0,
+            user_text(6, 10).0,
+        ];
+        let token_counts = vec![10, 10, 10, 50, 50, 10, 10];
+
+        let outcome =
+            truncate_messages_with_outcome(&messages, &token_counts, 100, &MiddleOutTruncation)?;
+
+        // Removing the tool request from the middle also removes its response
+        assert_eq!(outcome.removed, vec![3, 4]);
+        assert!(outcome.modified.is_empty());
+        assert_eq!(outcome.messages.len(), 5);
+        assert_eq!(outcome.messages[0], messages[0]);
+        assert_eq!(outcome.messages.last(), messages.last());
+        assert_eq!(outcome.token_counts.iter().sum::<usize>(), 50);
+        assert!(MiddleOutTruncation.notice().contains("middle"));
+        assert!(!MiddleOutTruncation.notice().contains("oldest"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncation_outcome_reports_removed_indices() -> Result<()> {
+        let (messages, token_counts) = create_messages_with_counts(5, 10, true);
+
+        let outcome =
+            truncate_messages_with_outcome(&messages, &token_counts, 50, &OldestFirstTruncation)?;
+
+        assert_eq!(outcome.removed, vec![0, 1, 2, 3]);
+        assert_eq!(outcome.messages, messages[4..].to_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_tool_outputs_replaces_old_responses() -> Result<()> {
+        let messages = vec![
+            user_text(0, 10).0,
+            assistant_tool_request("tool1", ToolCall::new("read_file", json!({})), 10).0,
+            user_tool_response("tool1", vec![Content::text("A".repeat(1000))], 200).0,
+            assistant_text(3, 10).0,
+            user_text(4, 10).0,
+        ];
+        let token_counts = vec![10, 10, 200, 10, 10];
+
+        let outcome = drop_tool_outputs(&messages, &token_counts, 100, &|_| 5)?;
+
+        assert!(outcome.removed.is_empty());
+        assert_eq!(outcome.modified, vec![2]);
+        assert_eq!(outcome.token_counts, vec![10, 10, 5, 10, 10]);
+        let MessageContent::ToolResponse(response) = &outcome.messages[2].content[0] else {
+            panic!("expected tool response");
+        };
+        let result = response.tool_result.as_ref().unwrap();
+        assert_eq!(
+            result[0].as_text().unwrap().text,
+            DROPPED_TOOL_OUTPUT_PLACEHOLDER
+        );
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 pub mod agents;
+pub mod audit;
 pub mod config;
 pub mod context_mgmt;
 mod conversation_fixer;
@@ -11,11 +12,17 @@ pub mod providers;
 pub mod recipe;
 pub mod recipe_deeplink;
 pub mod scheduler;
+pub mod scheduler_cron;
 pub mod scheduler_factory;
+pub mod scheduler_gate;
+pub mod scheduler_spec;
 pub mod scheduler_trait;
+pub mod scheduler_webhook;
 pub mod session;
+pub mod session_callback;
 pub mod temporal_scheduler;
 pub mod token_counter;
+pub mod tokenizer;
 pub mod tool_monitor;
 pub mod tracing;
 pub mod utils;
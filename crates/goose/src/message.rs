@@ -5,6 +5,8 @@
 ///
 /// The content of the messages uses MCP types to avoid additional conversions
 /// when interacting with MCP servers.
+use crate::config::permission::PermissionRule;
+use crate::context_mgmt::breakdown::ContextBreakdown;
 use chrono::Utc;
 use mcp_core::handler::ToolResult;
 use mcp_core::tool::ToolCall;
@@ -18,6 +20,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashSet;
 use std::fmt;
+use std::time::Duration;
 use utoipa::ToSchema;
 
 mod tool_result_serde;
@@ -56,6 +59,23 @@ pub struct ToolResponse {
     #[serde(with = "tool_result_serde")]
     #[schema(value_type = Object)]
     pub tool_result: ToolResult<Vec<Content>>,
+    /// Structured result returned by the tool alongside the text rendering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub structured_content: Option<Value>,
+    /// How long the tool took to run, for calls the agent timed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+impl ToolResponse {
+    /// The structured content as compact JSON, which providers send in place of the text blocks
+    pub fn structured_text(&self) -> Option<String> {
+        self.structured_content
+            .as_ref()
+            .filter(|_| self.tool_result.is_ok())
+            .map(|value| value.to_string())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -66,6 +86,9 @@ pub struct ToolConfirmationRequest {
     pub tool_name: String,
     pub arguments: Value,
     pub prompt: Option<String>,
+    /// Permission rule that asked for confirmation, if the request was not a mode default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_rule: Option<PermissionRule>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
@@ -91,6 +114,9 @@ pub struct FrontendToolRequest {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct ContextLengthExceeded {
     pub msg: String,
+    /// Where the tokens of the request that overflowed went
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<ContextBreakdown>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
@@ -98,6 +124,46 @@ pub struct SummarizationRequested {
     pub msg: String,
 }
 
+/// A file uploaded to the session. It is replaced with the file's contents just before the
+/// conversation is sent to the provider, so the history stays small.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AttachmentReference {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// A tool the provider ran itself, such as web search, rather than one goose called. The
+/// provider's result block is kept as it came so it can be sent back on later turns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ServerToolUse {
+    pub id: String,
+    /// Name the provider gives the tool, such as `web_search`
+    pub name: String,
+    #[schema(value_type = Object)]
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Object)]
+    pub result: Option<Value>,
+}
+
+/// Sources the text before it in the message was drawn from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CitationsContent {
+    pub citations: Vec<Citation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Citation {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The passage of the source the text relies on, when the provider gives it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cited_text: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 /// Content passed inside a message, which can be both simple content and tool content
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -112,6 +178,9 @@ pub enum MessageContent {
     RedactedThinking(RedactedThinkingContent),
     ContextLengthExceeded(ContextLengthExceeded),
     SummarizationRequested(SummarizationRequested),
+    Attachment(AttachmentReference),
+    ServerToolUse(ServerToolUse),
+    Citations(CitationsContent),
 }
 
 impl fmt::Display for MessageContent {
@@ -145,6 +214,11 @@ impl fmt::Display for MessageContent {
             MessageContent::SummarizationRequested(r) => {
                 write!(f, "[SummarizationRequested: {}]", r.msg)
             }
+            MessageContent::Attachment(a) => {
+                write!(f, "[Attachment: {}]", a.name.as_deref().unwrap_or(&a.id))
+            }
+            MessageContent::ServerToolUse(u) => write!(f, "[ServerToolUse: {}]", u.name),
+            MessageContent::Citations(c) => write!(f, "[Citations: {}]", c.citations.len()),
         }
     }
 }
@@ -172,9 +246,19 @@ impl MessageContent {
     }
 
     pub fn tool_response<S: Into<String>>(id: S, tool_result: ToolResult<Vec<Content>>) -> Self {
+        Self::structured_tool_response(id, tool_result, None)
+    }
+
+    pub fn structured_tool_response<S: Into<String>>(
+        id: S,
+        tool_result: ToolResult<Vec<Content>>,
+        structured_content: Option<Value>,
+    ) -> Self {
         MessageContent::ToolResponse(ToolResponse {
             id: id.into(),
             tool_result,
+            structured_content,
+            duration_ms: None,
         })
     }
 
@@ -183,12 +267,14 @@ impl MessageContent {
         tool_name: String,
         arguments: Value,
         prompt: Option<String>,
+        matched_rule: Option<PermissionRule>,
     ) -> Self {
         MessageContent::ToolConfirmationRequest(ToolConfirmationRequest {
             id: id.into(),
             tool_name,
             arguments,
             prompt,
+            matched_rule,
         })
     }
 
@@ -211,13 +297,51 @@ impl MessageContent {
     }
 
     pub fn context_length_exceeded<S: Into<String>>(msg: S) -> Self {
-        MessageContent::ContextLengthExceeded(ContextLengthExceeded { msg: msg.into() })
+        MessageContent::ContextLengthExceeded(ContextLengthExceeded {
+            msg: msg.into(),
+            breakdown: None,
+        })
+    }
+
+    pub fn context_length_exceeded_with_breakdown<S: Into<String>>(
+        msg: S,
+        breakdown: ContextBreakdown,
+    ) -> Self {
+        MessageContent::ContextLengthExceeded(ContextLengthExceeded {
+            msg: msg.into(),
+            breakdown: Some(breakdown),
+        })
     }
 
     pub fn summarization_requested<S: Into<String>>(msg: S) -> Self {
         MessageContent::SummarizationRequested(SummarizationRequested { msg: msg.into() })
     }
 
+    pub fn attachment<S: Into<String>>(id: S, name: Option<String>) -> Self {
+        MessageContent::Attachment(AttachmentReference {
+            id: id.into(),
+            name,
+        })
+    }
+
+    pub fn server_tool_use<S1: Into<String>, S2: Into<String>>(
+        id: S1,
+        name: S2,
+        input: Value,
+        result: Option<Value>,
+    ) -> Self {
+        MessageContent::ServerToolUse(ServerToolUse {
+            id: id.into(),
+            name: name.into(),
+            input,
+            result,
+        })
+    }
+
+    pub fn citations(citations: Vec<Citation>) -> Self {
+        MessageContent::Citations(CitationsContent { citations })
+    }
+
     // Add this new method to check for summarization requested content
     pub fn as_summarization_requested(&self) -> Option<&SummarizationRequested> {
         if let MessageContent::SummarizationRequested(ref summarization_requested) = self {
@@ -449,6 +573,11 @@ impl Message {
         self.with_content(MessageContent::image(data, mime_type))
     }
 
+    /// Add a reference to a file uploaded to the session
+    pub fn with_attachment<S: Into<String>>(self, id: S, name: Option<String>) -> Self {
+        self.with_content(MessageContent::attachment(id, name))
+    }
+
     /// Add a tool request to the message
     pub fn with_tool_request<S: Into<String>>(
         self,
@@ -467,6 +596,36 @@ impl Message {
         self.with_content(MessageContent::tool_response(id, result))
     }
 
+    /// Add a tool response carrying structured content to the message
+    pub fn with_structured_tool_response<S: Into<String>>(
+        self,
+        id: S,
+        result: ToolResult<Vec<Content>>,
+        structured_content: Option<Value>,
+    ) -> Self {
+        self.with_content(MessageContent::structured_tool_response(
+            id,
+            result,
+            structured_content,
+        ))
+    }
+
+    /// Add a tool response with how long the tool took to run
+    pub fn with_timed_tool_response<S: Into<String>>(
+        self,
+        id: S,
+        result: ToolResult<Vec<Content>>,
+        structured_content: Option<Value>,
+        elapsed: Duration,
+    ) -> Self {
+        self.with_content(MessageContent::ToolResponse(ToolResponse {
+            id: id.into(),
+            tool_result: result,
+            structured_content,
+            duration_ms: Some(elapsed.as_millis() as u64),
+        }))
+    }
+
     /// Add a tool confirmation request to the message
     pub fn with_tool_confirmation_request<S: Into<String>>(
         self,
@@ -474,9 +633,14 @@ impl Message {
         tool_name: String,
         arguments: Value,
         prompt: Option<String>,
+        matched_rule: Option<PermissionRule>,
     ) -> Self {
         self.with_content(MessageContent::tool_confirmation_request(
-            id, tool_name, arguments, prompt,
+            id,
+            tool_name,
+            arguments,
+            prompt,
+            matched_rule,
         ))
     }
 
@@ -703,6 +867,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_structured_tool_response_round_trip() {
+        let message = Message::user().with_structured_tool_response(
+            "tool123",
+            Ok(vec![Content::text("It is 21 degrees")]),
+            Some(json!({"temperature": 21})),
+        );
+
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            value["content"][0]["structuredContent"],
+            json!({"temperature": 21})
+        );
+
+        let parsed: Message = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, message);
+        let MessageContent::ToolResponse(response) = &parsed.content[0] else {
+            panic!("Expected ToolResponse content");
+        };
+        assert_eq!(
+            response.structured_text().as_deref(),
+            Some(r#"{"temperature":21}"#)
+        );
+
+        // Responses without structured content leave the field out entirely
+        let plain = Message::user().with_tool_response("tool456", Ok(vec![]));
+        let value = serde_json::to_value(&plain).unwrap();
+        assert!(value["content"][0].get("structuredContent").is_none());
+    }
+
     #[test]
     fn test_from_prompt_message_text() {
         let prompt_content = PromptMessageContent::Text {
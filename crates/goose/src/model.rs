@@ -1,8 +1,16 @@
+use crate::config::Config;
+use crate::message::Message;
+use crate::providers::formats::anthropic::supports_extended_thinking;
+use crate::tokenizer;
 use once_cell::sync::Lazy;
+use rmcp::model::Tool;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
 
 const DEFAULT_CONTEXT_LIMIT: usize = 128_000;
+const DEFAULT_THINKING_BUDGET: i32 = 16_000;
+const MIN_THINKING_BUDGET: i32 = 1024;
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -69,8 +77,61 @@ pub struct ModelConfig {
     pub context_limit: Option<usize>,
     pub temperature: Option<f32>,
     pub max_tokens: Option<i32>,
+    /// Nucleus sampling cutoff between 0.0 and 1.0
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Sequences that end generation when the model produces them
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
+    /// Extended thinking, for models that support it
+    #[serde(default)]
+    pub thinking: ThinkingConfig,
     pub toolshim: bool,
     pub toolshim_model: Option<String>,
+    /// Tools the provider runs itself, sent along with the extensions' tools
+    #[serde(default)]
+    pub native_tools: Vec<NativeTool>,
+}
+
+/// A tool the provider runs on its side rather than through an extension. Providers that
+/// don't offer a tool leave it out of their requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NativeTool {
+    WebSearch,
+    CodeExecution,
+}
+
+impl NativeTool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NativeTool::WebSearch => "web_search",
+            NativeTool::CodeExecution => "code_execution",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [NativeTool::WebSearch, NativeTool::CodeExecution]
+            .into_iter()
+            .find(|tool| tool.name() == name)
+    }
+}
+
+/// Whether the model should think before answering, and how many tokens it may spend doing so
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThinkingConfig {
+    pub enabled: bool,
+    /// Tokens the model may spend thinking, on top of `max_tokens`. At least 1024.
+    pub budget_tokens: i32,
+}
+
+impl Default for ThinkingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            budget_tokens: DEFAULT_THINKING_BUDGET,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,16 +151,25 @@ impl ModelConfig {
     ) -> Result<Self, ConfigError> {
         let context_limit = Self::parse_context_limit(&model_name, context_env_var)?;
         let temperature = Self::parse_temperature()?;
+        let top_p = Self::parse_top_p()?;
+        let max_tokens = Self::parse_max_tokens()?;
+        let stop_sequences = Self::parse_stop_sequences()?;
+        let thinking = Self::parse_thinking(&model_name)?;
         let toolshim = Self::parse_toolshim()?;
         let toolshim_model = Self::parse_toolshim_model()?;
+        let native_tools = Self::parse_native_tools()?;
 
         Ok(Self {
             model_name,
             context_limit,
             temperature,
-            max_tokens: None,
+            max_tokens,
+            top_p,
+            stop_sequences,
+            thinking,
             toolshim,
             toolshim_model,
+            native_tools,
         })
     }
 
@@ -137,8 +207,21 @@ impl ModelConfig {
         Ok(limit)
     }
 
+    /// Read a model setting from the environment or, failing that, the config file, so
+    /// values saved at runtime take effect when the provider is next built
+    fn configured_value(key: &str) -> Option<String> {
+        if let Ok(val) = std::env::var(key) {
+            return Some(val);
+        }
+        match Config::global().get_param::<Value>(key) {
+            Ok(Value::Null) | Err(_) => None,
+            Ok(Value::String(val)) => Some(val),
+            Ok(val) => Some(val.to_string()),
+        }
+    }
+
     fn parse_temperature() -> Result<Option<f32>, ConfigError> {
-        if let Ok(val) = std::env::var("GOOSE_TEMPERATURE") {
+        if let Some(val) = Self::configured_value("GOOSE_TEMPERATURE") {
             let temp = val.parse::<f32>().map_err(|_| {
                 ConfigError::InvalidValue(
                     "GOOSE_TEMPERATURE".to_string(),
@@ -158,6 +241,108 @@ impl ModelConfig {
         }
     }
 
+    fn parse_top_p() -> Result<Option<f32>, ConfigError> {
+        if let Some(val) = Self::configured_value("GOOSE_TOP_P") {
+            let top_p = val.parse::<f32>().map_err(|_| {
+                ConfigError::InvalidValue(
+                    "GOOSE_TOP_P".to_string(),
+                    val.clone(),
+                    "must be a valid number".to_string(),
+                )
+            })?;
+            if !(0.0..=1.0).contains(&top_p) {
+                return Err(ConfigError::InvalidRange("GOOSE_TOP_P".to_string(), val));
+            }
+            Ok(Some(top_p))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_max_tokens() -> Result<Option<i32>, ConfigError> {
+        if let Some(val) = Self::configured_value("GOOSE_MAX_TOKENS") {
+            match val.parse::<i32>() {
+                Ok(tokens) if tokens > 0 => Ok(Some(tokens)),
+                _ => Err(ConfigError::InvalidValue(
+                    "GOOSE_MAX_TOKENS".to_string(),
+                    val,
+                    "must be a positive integer".to_string(),
+                )),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Stop sequences are given as a JSON list of strings, or in the environment as a
+    /// comma-separated list
+    fn parse_stop_sequences() -> Result<Option<Vec<String>>, ConfigError> {
+        let Some(val) = Self::configured_value("GOOSE_STOP_SEQUENCES") else {
+            return Ok(None);
+        };
+        let sequences: Vec<String> = if val.trim_start().starts_with('[') {
+            serde_json::from_str(&val).map_err(|_| {
+                ConfigError::InvalidValue(
+                    "GOOSE_STOP_SEQUENCES".to_string(),
+                    val.clone(),
+                    "must be a list of strings".to_string(),
+                )
+            })?
+        } else {
+            val.split(',').map(|s| s.trim().to_string()).collect()
+        };
+        let sequences: Vec<String> = sequences.into_iter().filter(|s| !s.is_empty()).collect();
+        Ok((!sequences.is_empty()).then_some(sequences))
+    }
+
+    /// Thinking is turned on by setting `CLAUDE_THINKING_ENABLED` to anything but a false value.
+    /// The budget is only checked when `model_name` will think with it; otherwise a bad one is
+    /// replaced by the default.
+    fn parse_thinking(model_name: &str) -> Result<ThinkingConfig, ConfigError> {
+        let enabled = Self::configured_value("CLAUDE_THINKING_ENABLED").is_some_and(|val| {
+            !matches!(
+                val.trim().to_lowercase().as_str(),
+                "0" | "false" | "no" | "off"
+            )
+        });
+        let budget_tokens = match Self::configured_value("CLAUDE_THINKING_BUDGET") {
+            Some(val) => match Self::parse_thinking_budget(&val) {
+                Ok(budget) => budget,
+                Err(e) if enabled && supports_extended_thinking(model_name) => return Err(e),
+                Err(e) => {
+                    tracing::warn!(
+                        "Ignoring CLAUDE_THINKING_BUDGET, which {} doesn't use: {}",
+                        model_name,
+                        e
+                    );
+                    DEFAULT_THINKING_BUDGET
+                }
+            },
+            None => DEFAULT_THINKING_BUDGET,
+        };
+        Ok(ThinkingConfig {
+            enabled,
+            budget_tokens,
+        })
+    }
+
+    fn parse_thinking_budget(val: &str) -> Result<i32, ConfigError> {
+        let budget = val.parse::<i32>().map_err(|_| {
+            ConfigError::InvalidValue(
+                "CLAUDE_THINKING_BUDGET".to_string(),
+                val.to_string(),
+                "must be a positive integer".to_string(),
+            )
+        })?;
+        if budget < MIN_THINKING_BUDGET {
+            return Err(ConfigError::InvalidRange(
+                "CLAUDE_THINKING_BUDGET".to_string(),
+                "must be at least 1024".to_string(),
+            ));
+        }
+        Ok(budget)
+    }
+
     fn parse_toolshim() -> Result<bool, ConfigError> {
         if let Ok(val) = std::env::var("GOOSE_TOOLSHIM") {
             match val.to_lowercase().as_str() {
@@ -186,6 +371,39 @@ impl ModelConfig {
         }
     }
 
+    /// Native tools are given as a JSON list of names, or in the environment as a
+    /// comma-separated list, such as `web_search,code_execution`
+    fn parse_native_tools() -> Result<Vec<NativeTool>, ConfigError> {
+        let Some(val) = Self::configured_value("GOOSE_NATIVE_TOOLS") else {
+            return Ok(Vec::new());
+        };
+        let names: Vec<String> = if val.trim_start().starts_with('[') {
+            serde_json::from_str(&val).map_err(|_| {
+                ConfigError::InvalidValue(
+                    "GOOSE_NATIVE_TOOLS".to_string(),
+                    val.clone(),
+                    "must be a list of tool names".to_string(),
+                )
+            })?
+        } else {
+            val.split(',').map(|s| s.trim().to_string()).collect()
+        };
+        let mut tools = Vec::new();
+        for name in names.iter().filter(|name| !name.is_empty()) {
+            let tool = NativeTool::parse(name).ok_or_else(|| {
+                ConfigError::InvalidValue(
+                    "GOOSE_NATIVE_TOOLS".to_string(),
+                    val.clone(),
+                    "must only name web_search or code_execution".to_string(),
+                )
+            })?;
+            if !tools.contains(&tool) {
+                tools.push(tool);
+            }
+        }
+        Ok(tools)
+    }
+
     fn get_model_specific_limit(model_name: &str) -> Option<usize> {
         MODEL_SPECIFIC_LIMITS
             .iter()
@@ -220,6 +438,21 @@ impl ModelConfig {
         self
     }
 
+    pub fn with_top_p(mut self, top_p: Option<f32>) -> Self {
+        self.top_p = top_p;
+        self
+    }
+
+    pub fn with_stop_sequences(mut self, sequences: Option<Vec<String>>) -> Self {
+        self.stop_sequences = sequences;
+        self
+    }
+
+    pub fn with_thinking(mut self, thinking: ThinkingConfig) -> Self {
+        self.thinking = thinking;
+        self
+    }
+
     pub fn with_toolshim(mut self, toolshim: bool) -> Self {
         self.toolshim = toolshim;
         self
@@ -230,10 +463,26 @@ impl ModelConfig {
         self
     }
 
+    pub fn with_native_tools(mut self, tools: Vec<NativeTool>) -> Self {
+        self.native_tools = tools;
+        self
+    }
+
+    /// Whether the provider should run `tool` for this model
+    pub fn native_tool_enabled(&self, tool: NativeTool) -> bool {
+        self.native_tools.contains(&tool)
+    }
+
     pub fn context_limit(&self) -> usize {
         self.context_limit.unwrap_or(DEFAULT_CONTEXT_LIMIT)
     }
 
+    /// Count the tokens a request with `messages` and `tools` would use with this model
+    pub fn count_tokens(&self, messages: &[Message], tools: &[Tool]) -> usize {
+        tokenizer::count_messages(messages, &self.model_name)
+            + tokenizer::count_tools(tools, &self.model_name)
+    }
+
     pub fn new_or_fail(model_name: &str) -> ModelConfig {
         ModelConfig::new(model_name)
             .unwrap_or_else(|_| panic!("Failed to create model config for {}", model_name))
@@ -301,6 +550,107 @@ mod tests {
         });
     }
 
+    #[test]
+    #[serial]
+    fn test_sampling_settings() {
+        with_var("GOOSE_TOP_P", Some("0.9"), || {
+            with_var("GOOSE_MAX_TOKENS", Some("2048"), || {
+                with_var("GOOSE_STOP_SEQUENCES", Some("END, STOP,"), || {
+                    let config = ModelConfig::new("test-model").unwrap();
+                    assert_eq!(config.top_p, Some(0.9));
+                    assert_eq!(config.max_tokens, Some(2048));
+                    assert_eq!(
+                        config.stop_sequences,
+                        Some(vec!["END".to_string(), "STOP".to_string()])
+                    );
+                });
+            });
+        });
+
+        with_var("GOOSE_STOP_SEQUENCES", Some(r#"["\n\nHuman:"]"#), || {
+            let config = ModelConfig::new("test-model").unwrap();
+            assert_eq!(config.stop_sequences, Some(vec!["\n\nHuman:".to_string()]));
+        });
+
+        with_var("GOOSE_TOP_P", Some("1.5"), || {
+            assert!(matches!(
+                ModelConfig::new("test-model").unwrap_err(),
+                ConfigError::InvalidRange(_, _)
+            ));
+        });
+
+        with_var("GOOSE_MAX_TOKENS", Some("0"), || {
+            assert!(ModelConfig::new("test-model").is_err());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_thinking_settings() {
+        let config = ModelConfig::new("test-model").unwrap();
+        assert_eq!(config.thinking, ThinkingConfig::default());
+
+        with_var("CLAUDE_THINKING_ENABLED", Some("1"), || {
+            with_var("CLAUDE_THINKING_BUDGET", Some("4096"), || {
+                let config = ModelConfig::new("test-model").unwrap();
+                assert!(config.thinking.enabled);
+                assert_eq!(config.thinking.budget_tokens, 4096);
+            });
+        });
+
+        with_var("CLAUDE_THINKING_ENABLED", Some("false"), || {
+            let config = ModelConfig::new("test-model").unwrap();
+            assert!(!config.thinking.enabled);
+        });
+
+        // A bad budget only fails models that think with it
+        with_var("CLAUDE_THINKING_BUDGET", Some("512"), || {
+            let config = ModelConfig::new("claude-sonnet-4").unwrap();
+            assert_eq!(config.thinking.budget_tokens, DEFAULT_THINKING_BUDGET);
+
+            with_var("CLAUDE_THINKING_ENABLED", Some("1"), || {
+                let config = ModelConfig::new("gpt-4o").unwrap();
+                assert_eq!(config.thinking.budget_tokens, DEFAULT_THINKING_BUDGET);
+                assert!(matches!(
+                    ModelConfig::new("claude-sonnet-4").unwrap_err(),
+                    ConfigError::InvalidRange(_, _)
+                ));
+            });
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_native_tools_settings() {
+        let config = ModelConfig::new("test-model").unwrap();
+        assert!(config.native_tools.is_empty());
+
+        with_var(
+            "GOOSE_NATIVE_TOOLS",
+            Some("web_search, code_execution,web_search"),
+            || {
+                let config = ModelConfig::new("test-model").unwrap();
+                assert_eq!(
+                    config.native_tools,
+                    vec![NativeTool::WebSearch, NativeTool::CodeExecution]
+                );
+                assert!(config.native_tool_enabled(NativeTool::WebSearch));
+            },
+        );
+
+        with_var("GOOSE_NATIVE_TOOLS", Some(r#"["web_search"]"#), || {
+            let config = ModelConfig::new("test-model").unwrap();
+            assert_eq!(config.native_tools, vec![NativeTool::WebSearch]);
+        });
+
+        with_var("GOOSE_NATIVE_TOOLS", Some("web_search,browser"), || {
+            assert!(matches!(
+                ModelConfig::new("test-model").unwrap_err(),
+                ConfigError::InvalidValue(_, _, _)
+            ));
+        });
+    }
+
     #[test]
     #[serial]
     fn test_invalid_toolshim() {
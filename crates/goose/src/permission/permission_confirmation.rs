@@ -7,6 +7,7 @@ pub enum Permission {
     AllowOnce,
     Cancel,
     DenyOnce,
+    AlwaysDeny,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
@@ -19,4 +20,11 @@ pub enum PrincipalType {
 pub struct PermissionConfirmation {
     pub principal_type: PrincipalType,
     pub permission: Permission,
+    /// Store an always allow/deny decision in the permission config so it survives restarts
+    #[serde(default = "default_persist")]
+    pub persist: bool,
+}
+
+fn default_persist() -> bool {
+    true
 }
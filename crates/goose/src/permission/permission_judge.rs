@@ -1,7 +1,8 @@
 use crate::agents::platform_tools::PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME;
-use crate::config::permission::PermissionLevel;
+use crate::config::permission::{PermissionLevel, PermissionRule};
 use crate::config::PermissionManager;
 use crate::message::{Message, MessageContent, ToolRequest};
+use crate::permission::shell_command::{check_shell_command, shell_command, ShellCommandDecision};
 use crate::providers::base::Provider;
 use chrono::Utc;
 use indoc::indoc;
@@ -9,9 +10,12 @@ use rmcp::model::{Tool, ToolAnnotations};
 use rmcp::object;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+/// Mode in which every tool call waits for the user, whatever permissions are stored for it
+pub const APPROVE_EACH_STEP_MODE: &str = "approve_each_step";
+
 /// Creates the tool definition for checking read-only permissions.
 fn create_read_only_tool() -> Tool {
     Tool::new(
@@ -154,6 +158,10 @@ pub struct PermissionCheckResult {
     pub approved: Vec<ToolRequest>,
     pub needs_approval: Vec<ToolRequest>,
     pub denied: Vec<ToolRequest>,
+    /// Permission rule that decided each request, keyed by tool request id
+    pub matched_rules: HashMap<String, PermissionRule>,
+    /// Deny pattern that refused each denied shell command, keyed by tool request id
+    pub denied_commands: HashMap<String, String>,
 }
 
 pub async fn check_tool_permissions(
@@ -167,14 +175,31 @@ pub async fn check_tool_permissions(
     let mut approved = vec![];
     let mut needs_approval = vec![];
     let mut denied = vec![];
+    let mut matched_rules = HashMap::new();
+    let mut denied_commands = HashMap::new();
     let mut llm_detect_candidates = vec![];
     let mut extension_request_ids = vec![];
+    let approve_each_step = mode == APPROVE_EACH_STEP_MODE;
 
     for request in candidate_requests {
         if let Ok(tool_call) = request.tool_call.clone() {
             if mode == "chat" {
                 continue;
-            } else if mode == "auto" {
+            }
+
+            // Shell commands matching a deny pattern are refused whatever the mode or the
+            // permissions stored for the tool
+            let command_decision =
+                shell_command(&tool_call.name, &tool_call.arguments).and_then(|command| {
+                    check_shell_command(permission_manager.get_shell_command_patterns(), command)
+                });
+            if let Some(ShellCommandDecision::Deny(pattern)) = command_decision {
+                denied.push(request.clone());
+                denied_commands.insert(request.id.clone(), pattern);
+                continue;
+            }
+
+            if mode == "auto" {
                 approved.push(request.clone());
             } else {
                 if tool_call.name == PLATFORM_MANAGE_EXTENSIONS_TOOL_NAME {
@@ -184,16 +209,35 @@ pub async fn check_tool_permissions(
                 // 1. Check user-defined permission
                 if let Some(level) = permission_manager.get_user_permission(&tool_call.name) {
                     match level {
-                        PermissionLevel::AlwaysAllow => approved.push(request.clone()),
-                        PermissionLevel::AskBefore => needs_approval.push(request.clone()),
+                        PermissionLevel::AlwaysAllow if !approve_each_step => {
+                            approved.push(request.clone())
+                        }
+                        PermissionLevel::AlwaysAllow | PermissionLevel::AskBefore => {
+                            needs_approval.push(request.clone())
+                        }
                         PermissionLevel::NeverAllow => denied.push(request.clone()),
                     }
                     continue;
                 }
 
-                // 2. Fallback based on mode
+                // 2. Check the ordered permission rules, the first match wins
+                if let Some(rule) = permission_manager.get_matching_rule(&tool_call.name) {
+                    match rule.level {
+                        PermissionLevel::AlwaysAllow if !approve_each_step => {
+                            approved.push(request.clone())
+                        }
+                        PermissionLevel::AlwaysAllow | PermissionLevel::AskBefore => {
+                            needs_approval.push(request.clone())
+                        }
+                        PermissionLevel::NeverAllow => denied.push(request.clone()),
+                    }
+                    matched_rules.insert(request.id.clone(), rule.clone());
+                    continue;
+                }
+
+                // 3. Fallback based on mode
                 match mode {
-                    "approve" => {
+                    "approve" | APPROVE_EACH_STEP_MODE => {
                         needs_approval.push(request.clone());
                     }
                     "smart_approve" => {
@@ -224,7 +268,7 @@ pub async fn check_tool_permissions(
         }
     }
 
-    // 3. LLM detect
+    // 4. LLM detect
     if !llm_detect_candidates.is_empty() && mode == "smart_approve" {
         let detected_readonly_tools =
             detect_read_only_tools(provider, llm_detect_candidates.iter().collect()).await;
@@ -252,6 +296,8 @@ pub async fn check_tool_permissions(
             approved,
             needs_approval,
             denied,
+            matched_rules,
+            denied_commands,
         },
         extension_request_ids,
     )
@@ -260,6 +306,7 @@ pub async fn check_tool_permissions(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::permission::ShellCommandPatterns;
     use crate::message::{Message, MessageContent, ToolRequest};
     use crate::model::ModelConfig;
     use crate::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
@@ -514,4 +561,151 @@ mod tests {
         assert_eq!(result.needs_approval.len(), 0); // data_fetcher should need approval
         assert_eq!(result.denied.len(), 0); // No tool should be denied in this test
     }
+
+    #[tokio::test]
+    async fn test_denied_commands_are_refused_in_every_mode() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut permission_manager = PermissionManager::new(temp_file.path());
+        permission_manager.update_user_permission("developer__shell", PermissionLevel::AlwaysAllow);
+        permission_manager.set_shell_command_patterns(ShellCommandPatterns {
+            allow: vec![],
+            deny: vec![r"rm -rf /(\s|$)".to_string()],
+        });
+
+        let request = |id: &str, name: &str, command: &str| ToolRequest {
+            id: id.to_string(),
+            tool_call: ToolResult::Ok(ToolCall {
+                name: name.to_string(),
+                arguments: json!({ "command": command }),
+            }),
+        };
+        let candidate_requests = vec![
+            request("shell", "developer__shell", "FOO=bar rm -rf /"),
+            request(
+                "process",
+                "developer__process_start",
+                "cargo test && rm -rf /",
+            ),
+            request("allowed", "developer__shell", "ls"),
+        ];
+
+        for mode in ["auto", "approve", "smart_approve"] {
+            let (result, _) = check_tool_permissions(
+                &candidate_requests,
+                mode,
+                HashSet::new(),
+                HashSet::new(),
+                &mut permission_manager,
+                create_mock_provider(),
+            )
+            .await;
+
+            let denied: Vec<&str> = result.denied.iter().map(|r| r.id.as_str()).collect();
+            assert_eq!(denied, vec!["shell", "process"], "mode {}", mode);
+            assert_eq!(result.denied_commands["shell"], r"rm -rf /(\s|$)");
+            assert!(!result.denied_commands.contains_key("allowed"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_tool_permissions_rule_precedence() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut permission_manager = PermissionManager::new(temp_file.path());
+        let provider = create_mock_provider();
+
+        permission_manager.set_rules(vec![
+            PermissionRule::new("*", "*delete*", PermissionLevel::NeverAllow),
+            PermissionRule::new("github", "*", PermissionLevel::AskBefore),
+            PermissionRule::new("developer", "read_file", PermissionLevel::AlwaysAllow),
+        ]);
+        // A per-tool user permission takes precedence over any rule
+        permission_manager
+            .update_user_permission("github__delete_branch", PermissionLevel::AlwaysAllow);
+
+        let request = |id: &str, name: &str| ToolRequest {
+            id: id.to_string(),
+            tool_call: ToolResult::Ok(ToolCall {
+                name: name.to_string(),
+                arguments: json!({}),
+            }),
+        };
+        let candidate_requests = vec![
+            request("user", "github__delete_branch"),
+            request("deny", "developer__delete_file"),
+            request("ask", "github__create_issue"),
+            request("allow", "developer__read_file"),
+            request("fallback", "developer__shell"),
+        ];
+
+        let (result, _) = check_tool_permissions(
+            &candidate_requests,
+            "smart_approve",
+            HashSet::new(),
+            HashSet::new(),
+            &mut permission_manager,
+            provider,
+        )
+        .await;
+
+        let ids = |requests: &[ToolRequest]| -> Vec<String> {
+            requests.iter().map(|r| r.id.clone()).collect()
+        };
+        assert_eq!(ids(&result.approved), vec!["user", "allow"]);
+        assert_eq!(ids(&result.needs_approval), vec!["ask", "fallback"]);
+        assert_eq!(ids(&result.denied), vec!["deny"]);
+
+        assert!(!result.matched_rules.contains_key("user"));
+        assert!(!result.matched_rules.contains_key("fallback"));
+        assert_eq!(result.matched_rules["deny"].tool, "*delete*");
+        assert_eq!(result.matched_rules["ask"].extension, "github");
+    }
+
+    #[tokio::test]
+    async fn test_approve_each_step_asks_for_allowed_tools() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut permission_manager = PermissionManager::new(temp_file.path());
+        let provider = create_mock_provider();
+
+        permission_manager.set_rules(vec![
+            PermissionRule::new("*", "*delete*", PermissionLevel::NeverAllow),
+            PermissionRule::new("developer", "read_file", PermissionLevel::AlwaysAllow),
+        ]);
+        permission_manager.update_user_permission("developer__shell", PermissionLevel::AlwaysAllow);
+
+        let request = |id: &str, name: &str| ToolRequest {
+            id: id.to_string(),
+            tool_call: ToolResult::Ok(ToolCall {
+                name: name.to_string(),
+                arguments: json!({}),
+            }),
+        };
+        let candidate_requests = vec![
+            request("user", "developer__shell"),
+            request("rule", "developer__read_file"),
+            request("deny", "developer__delete_file"),
+            request("fallback", "github__create_issue"),
+        ];
+
+        let (result, _) = check_tool_permissions(
+            &candidate_requests,
+            APPROVE_EACH_STEP_MODE,
+            vec!["github__create_issue".to_string()]
+                .into_iter()
+                .collect(),
+            HashSet::new(),
+            &mut permission_manager,
+            provider,
+        )
+        .await;
+
+        let ids = |requests: &[ToolRequest]| -> Vec<String> {
+            requests.iter().map(|r| r.id.clone()).collect()
+        };
+        assert!(result.approved.is_empty());
+        assert_eq!(
+            ids(&result.needs_approval),
+            vec!["user", "rule", "fallback"]
+        );
+        assert_eq!(ids(&result.denied), vec!["deny"]);
+    }
 }
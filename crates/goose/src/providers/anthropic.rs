@@ -8,18 +8,22 @@ use serde_json::Value;
 use std::io;
 use std::time::Duration;
 use tokio::pin;
+use tokio::time::sleep;
 
 use tokio_util::io::StreamReader;
 
-use super::base::{ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    sanitized_provider_config, ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata,
+    ProviderUsage,
+};
 use super::errors::ProviderError;
 use super::formats::anthropic::{
     create_request, get_usage, response_to_message, response_to_streaming_message,
 };
-use super::utils::{emit_debug_trace, get_model};
+use super::utils::{emit_debug_trace, get_model, map_http_error_to_provider_error};
 use crate::impl_provider_default;
 use crate::message::Message;
-use crate::model::ModelConfig;
+use crate::model::{ModelConfig, NativeTool};
 use rmcp::model::Tool;
 
 pub const ANTHROPIC_DEFAULT_MODEL: &str = "claude-3-5-sonnet-latest";
@@ -37,6 +41,28 @@ pub const ANTHROPIC_KNOWN_MODELS: &[&str] = &[
 
 pub const ANTHROPIC_DOC_URL: &str = "https://docs.anthropic.com/en/docs/about-claude/models";
 pub const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+/// Times a stream that fails with an overloaded or rate limit error before any output is retried
+const STREAM_MAX_RETRIES: usize = 3;
+/// Delay before the first stream retry, doubled for each one after
+const STREAM_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The betas a request with `model` needs, which share one comma-separated header
+fn beta_header(model: &ModelConfig) -> Option<String> {
+    let mut betas = Vec::new();
+    if model.model_name.starts_with("claude-3-7-sonnet-") {
+        if model.thinking.enabled {
+            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
+            betas.push("output-128k-2025-02-19");
+        }
+        // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
+        betas.push("token-efficient-tools-2025-02-19");
+    }
+    if model.native_tool_enabled(NativeTool::CodeExecution) {
+        // https://docs.anthropic.com/en/docs/agents-and-tools/tool-use/code-execution-tool
+        betas.push("code-execution-2025-05-22");
+    }
+    (!betas.is_empty()).then(|| betas.join(","))
+}
 
 #[derive(serde::Serialize)]
 pub struct AnthropicProvider {
@@ -89,25 +115,42 @@ impl AnthropicProvider {
 
         // https://docs.anthropic.com/en/api/errors
         match status {
-            StatusCode::OK => payload.ok_or_else( || ProviderError::RequestFailed("Response body is not valid JSON".to_string()) ),
-            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-                Err(ProviderError::Authentication(format!("Authentication failed. Please ensure your API keys are valid and have the required permissions. \
-                    Status: {}. Response: {:?}", status, payload)))
-            }
+            StatusCode::OK => payload.ok_or_else(|| {
+                ProviderError::RequestFailed("Response body is not valid JSON".to_string())
+            }),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(
+                map_http_error_to_provider_error("anthropic", status, payload),
+            ),
             StatusCode::BAD_REQUEST => {
                 let mut error_msg = "Unknown error".to_string();
                 if let Some(payload) = &payload {
                     if let Some(error) = payload.get("error") {
-                    tracing::debug!("Bad Request Error: {error:?}");
-                    error_msg = error.get("message").and_then(|m| m.as_str()).unwrap_or("Unknown error").to_string();
-                    if error_msg.to_lowercase().contains("too long") || error_msg.to_lowercase().contains("too many") {
-                        return Err(ProviderError::ContextLengthExceeded(error_msg.to_string()));
+                        tracing::debug!("Bad Request Error: {error:?}");
+                        error_msg = error
+                            .get("message")
+                            .and_then(|m| m.as_str())
+                            .unwrap_or("Unknown error")
+                            .to_string();
+                        if error_msg.to_lowercase().contains("too long")
+                            || error_msg.to_lowercase().contains("too many")
+                        {
+                            return Err(ProviderError::ContextLengthExceeded(
+                                error_msg.to_string(),
+                            ));
+                        }
                     }
-                }}
+                }
                 tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                    "{}",
+                    format!(
+                        "Provider request failed with status: {}. Payload: {:?}",
+                        status, payload
+                    )
                 );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}. Message: {}", status, error_msg)))
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}. Message: {}",
+                    status, error_msg
+                )))
             }
             StatusCode::TOO_MANY_REQUESTS => {
                 Err(ProviderError::RateLimitExceeded(format!("{:?}", payload)))
@@ -117,14 +160,48 @@ impl AnthropicProvider {
             }
             _ => {
                 tracing::debug!(
-                    "{}", format!("Provider request failed with status: {}. Payload: {:?}", status, payload)
+                    "{}",
+                    format!(
+                        "Provider request failed with status: {}. Payload: {:?}",
+                        status, payload
+                    )
                 );
-                Err(ProviderError::RequestFailed(format!("Request failed with status: {}", status)))
+                Err(ProviderError::RequestFailed(format!(
+                    "Request failed with status: {}",
+                    status
+                )))
             }
         }
     }
 }
 
+/// Send a streaming request, failing on an error status
+async fn post_stream(
+    client: &Client,
+    url: &url::Url,
+    headers: &HeaderMap,
+    payload: &Value,
+) -> Result<reqwest::Response, ProviderError> {
+    let response = client
+        .post(url.clone())
+        .headers(headers.clone())
+        .json(payload)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        let payload = serde_json::from_str(&error_text).unwrap_or(Value::String(error_text));
+        return Err(map_http_error_to_provider_error(
+            "anthropic",
+            status,
+            Some(payload),
+        ));
+    }
+    Ok(response)
+}
+
 #[async_trait]
 impl Provider for AnthropicProvider {
     fn metadata() -> ProviderMetadata {
@@ -163,6 +240,10 @@ impl Provider for AnthropicProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
@@ -179,18 +260,8 @@ impl Provider for AnthropicProvider {
         headers.insert("x-api-key", self.api_key.parse().unwrap());
         headers.insert("anthropic-version", ANTHROPIC_API_VERSION.parse().unwrap());
 
-        let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
-            headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
-        }
-
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
-            // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
-            headers.insert(
-                "anthropic-beta",
-                "token-efficient-tools-2025-02-19".parse().unwrap(),
-            );
+        if let Some(betas) = beta_header(&self.model) {
+            headers.insert("anthropic-beta", betas.parse().unwrap());
         }
 
         // Make request
@@ -262,18 +333,8 @@ impl Provider for AnthropicProvider {
         headers.insert("x-api-key", self.api_key.parse().unwrap());
         headers.insert("anthropic-version", ANTHROPIC_API_VERSION.parse().unwrap());
 
-        let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-            // https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking#extended-output-capabilities-beta
-            headers.insert("anthropic-beta", "output-128k-2025-02-19".parse().unwrap());
-        }
-
-        if self.model.model_name.starts_with("claude-3-7-sonnet-") {
-            // https://docs.anthropic.com/en/docs/build-with-claude/tool-use/token-efficient-tool-use
-            headers.insert(
-                "anthropic-beta",
-                "token-efficient-tools-2025-02-19".parse().unwrap(),
-            );
+        if let Some(betas) = beta_header(&self.model) {
+            headers.insert("anthropic-beta", betas.parse().unwrap());
         }
 
         let base_url = url::Url::parse(&self.host)
@@ -282,38 +343,54 @@ impl Provider for AnthropicProvider {
             ProviderError::RequestFailed(format!("Failed to construct endpoint URL: {e}"))
         })?;
 
-        let response = self
-            .client
-            .post(url)
-            .headers(headers)
-            .json(&payload)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(ProviderError::RequestFailed(format!(
-                "Streaming request failed with status: {}. Error: {}",
-                status, error_text
-            )));
-        }
-
-        // Map reqwest error to io::Error
-        let stream = response.bytes_stream().map_err(io::Error::other);
+        let response = post_stream(&self.client, &url, &headers, &payload).await?;
 
+        let client = self.client.clone();
         let model_config = self.model.clone();
         // Wrap in a line decoder and yield lines inside the stream
         Ok(Box::pin(try_stream! {
-            let stream_reader = StreamReader::new(stream);
-            let framed = tokio_util::codec::FramedRead::new(stream_reader, tokio_util::codec::LinesCodec::new()).map_err(anyhow::Error::from);
-
-            let message_stream = response_to_streaming_message(framed);
-            pin!(message_stream);
-            while let Some(message) = futures::StreamExt::next(&mut message_stream).await {
-                let (message, usage) = message.map_err(|e| ProviderError::RequestFailed(format!("Stream decode error: {}", e)))?;
-                super::utils::emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
-                yield (message, usage);
+            let mut response = Some(response);
+            let mut attempts = 0;
+            'request: loop {
+                let response = match response.take() {
+                    Some(response) => response,
+                    None => post_stream(&client, &url, &headers, &payload).await?,
+                };
+                // Map reqwest error to io::Error
+                let stream = response.bytes_stream().map_err(io::Error::other);
+                let stream_reader = StreamReader::new(stream);
+                let framed = tokio_util::codec::FramedRead::new(stream_reader, tokio_util::codec::LinesCodec::new()).map_err(anyhow::Error::from);
+
+                let message_stream = response_to_streaming_message(framed);
+                pin!(message_stream);
+                let mut yielded = false;
+                while let Some(message) = futures::StreamExt::next(&mut message_stream).await {
+                    let (message, usage) = match message {
+                        Ok(item) => item,
+                        Err(e) => {
+                            let error = e.downcast::<ProviderError>().unwrap_or_else(|e| {
+                                ProviderError::RequestFailed(format!("Stream decode error: {}", e))
+                            });
+                            // Nothing has reached the caller yet, so the request can start over
+                            let retryable = matches!(
+                                error,
+                                ProviderError::ServerError(_) | ProviderError::RateLimitExceeded(_)
+                            );
+                            if retryable && !yielded && attempts < STREAM_MAX_RETRIES {
+                                attempts += 1;
+                                let delay = STREAM_RETRY_DELAY * 2u32.pow(attempts as u32 - 1);
+                                tracing::warn!("{}: retrying stream in {:?} ({}/{})", error, delay, attempts, STREAM_MAX_RETRIES);
+                                sleep(delay).await;
+                                continue 'request;
+                            }
+                            Err(error)?
+                        }
+                    };
+                    yielded = true;
+                    super::utils::emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
+                    yield (message, usage);
+                }
+                break;
             }
         }))
     }
@@ -322,3 +399,121 @@ impl Provider for AnthropicProvider {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn sse(events: &[Value]) -> String {
+        events
+            .iter()
+            .map(|event| {
+                format!(
+                    "event: {}\ndata: {}\n\n",
+                    event["type"].as_str().unwrap(),
+                    event
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_stream_is_retried() -> Result<()> {
+        let server = MockServer::start().await;
+        let start = json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-20250514", "usage": {"input_tokens": 10, "output_tokens": 1}}});
+
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(sse(&[
+                start.clone(),
+                json!({"type": "ping"}),
+                json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+            ])))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(sse(&[
+                start,
+                json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+                json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello"}}),
+                json!({"type": "content_block_stop", "index": 0}),
+                json!({"type": "message_stop"}),
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = AnthropicProvider {
+            client: Client::new(),
+            host: server.uri(),
+            api_key: "test-key".to_string(),
+            model: ModelConfig::new_or_fail("claude-sonnet-4-20250514"),
+        };
+        let items: Vec<_> = provider
+            .stream("system", &[Message::user().with_text("Hi")], &[])
+            .await?
+            .collect()
+            .await;
+        let items = items.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+        let text: String = items
+            .iter()
+            .filter_map(|(message, _)| message.as_ref())
+            .map(|message| message.as_concat_text())
+            .collect();
+        assert_eq!(text, "Hello");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejected_key_is_authentication_failure() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "type": "error",
+                "error": {"type": "authentication_error", "message": "invalid x-api-key"}
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = AnthropicProvider {
+            client: Client::new(),
+            host: server.uri(),
+            api_key: "expired-key".to_string(),
+            model: ModelConfig::new_or_fail("claude-sonnet-4-20250514"),
+        };
+        let messages = [Message::user().with_text("Hi")];
+
+        let error = provider
+            .complete("system", &messages, &[])
+            .await
+            .unwrap_err();
+        let ProviderError::AuthenticationFailed {
+            provider: name,
+            message,
+        } = error
+        else {
+            panic!("expected an authentication failure, got {:?}", error);
+        };
+        assert_eq!(name, "anthropic");
+        assert!(message.contains("invalid x-api-key"));
+
+        let error = provider
+            .stream("system", &messages, &[])
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), "authentication_failed");
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use super::errors::ProviderError;
 use crate::message::Message;
 use crate::model::ModelConfig;
+use crate::tokenizer;
 use crate::utils::safe_truncate;
 use rmcp::model::Tool;
 use utoipa::ToSchema;
@@ -95,6 +96,32 @@ pub struct ProviderMetadata {
     pub model_doc_link: String,
     /// Required configuration keys
     pub config_keys: Vec<ConfigKey>,
+    /// How the user can sign up and get an API key from goose, if they can
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signup: Option<SignupMethod>,
+}
+
+/// A way for the user to sign in with a provider and have goose configure the key it hands out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SignupMethod {
+    /// Sign in in the browser, which redirects back to a local server (OAuth with PKCE)
+    Pkce,
+    /// Enter a code goose shows on the provider's sign in page (RFC 8628)
+    DeviceCode(DeviceCodeSettings),
+}
+
+/// A gateway offering the device authorization grant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeviceCodeSettings {
+    /// Config key of the gateway's URL. Its OAuth server metadata (RFC 8414) lists the device
+    /// authorization and token endpoints.
+    pub host_key: String,
+    /// Config key the access token is saved to, as the API key
+    pub api_key_key: String,
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
 }
 
 impl ProviderMetadata {
@@ -125,6 +152,7 @@ impl ProviderMetadata {
                 .collect(),
             model_doc_link: model_doc_link.to_string(),
             config_keys,
+            signup: None,
         }
     }
 
@@ -146,6 +174,7 @@ impl ProviderMetadata {
             known_models: models,
             model_doc_link: model_doc_link.to_string(),
             config_keys,
+            signup: None,
         }
     }
 
@@ -158,8 +187,15 @@ impl ProviderMetadata {
             known_models: vec![],
             model_doc_link: "".to_string(),
             config_keys: vec![],
+            signup: None,
         }
     }
+
+    /// Advertise that the user can sign up with `signup`
+    pub fn with_signup(mut self, signup: SignupMethod) -> Self {
+        self.signup = Some(signup);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -185,11 +221,32 @@ impl ConfigKey {
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    /// Whether the tokens were counted locally because the provider didn't report them
+    #[serde(default)]
+    pub estimated: bool,
+    /// Whether the reply was cut off at the output token limit
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 impl ProviderUsage {
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        Self {
+            model,
+            usage,
+            estimated: false,
+            truncated: false,
+        }
+    }
+
+    /// Usage counted with the local tokenizer rather than reported by the provider
+    pub fn estimated(model: String, usage: Usage) -> Self {
+        Self {
+            model,
+            usage,
+            estimated: true,
+            truncated: false,
+        }
     }
 }
 
@@ -198,6 +255,9 @@ pub struct Usage {
     pub input_tokens: Option<i32>,
     pub output_tokens: Option<i32>,
     pub total_tokens: Option<i32>,
+    /// Web searches the provider ran itself, which are billed per search on top of the tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_search_requests: Option<i32>,
 }
 
 fn sum_optionals<T>(a: Option<T>, b: Option<T>) -> Option<T>
@@ -220,6 +280,7 @@ impl Add for Usage {
             input_tokens: sum_optionals(self.input_tokens, other.input_tokens),
             output_tokens: sum_optionals(self.output_tokens, other.output_tokens),
             total_tokens: sum_optionals(self.total_tokens, other.total_tokens),
+            web_search_requests: sum_optionals(self.web_search_requests, other.web_search_requests),
         }
     }
 }
@@ -240,8 +301,14 @@ impl Usage {
             input_tokens,
             output_tokens,
             total_tokens,
+            web_search_requests: None,
         }
     }
+
+    pub fn with_web_search_requests(mut self, requests: Option<i32>) -> Self {
+        self.web_search_requests = requests;
+        self
+    }
 }
 
 use async_trait::async_trait;
@@ -255,6 +322,30 @@ pub trait LeadWorkerProviderTrait {
     fn get_active_model(&self) -> String;
 }
 
+/// Parts of field names that mark provider settings holding credentials
+const SECRET_FIELD_MARKERS: &[&str] = &["key", "token", "secret", "password", "auth", "header"];
+
+/// Serialize `provider` with any credentials it holds removed, naming the provider and
+/// including its model settings
+pub fn sanitized_provider_config<P: Provider + Serialize>(provider: &P) -> serde_json::Value {
+    let mut config = match serde_json::to_value(provider) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    config.retain(|field, _| {
+        let field = field.to_lowercase();
+        !SECRET_FIELD_MARKERS
+            .iter()
+            .any(|marker| field.contains(marker))
+    });
+    config.insert("provider".to_string(), P::metadata().name.into());
+    config.insert(
+        "model".to_string(),
+        serde_json::to_value(provider.get_model_config()).unwrap_or_default(),
+    );
+    serde_json::Value::Object(config)
+}
+
 /// Base trait for AI providers (OpenAI, Anthropic, etc)
 #[async_trait]
 pub trait Provider: Send + Sync {
@@ -291,6 +382,13 @@ pub trait Provider: Send + Sync {
         Ok(None)
     }
 
+    /// Check that the provider can be reached with its credentials and model, using a one
+    /// word completion. Providers with a cheaper way to check override this.
+    async fn test_connection(&self) -> Result<(), ProviderError> {
+        let message = Message::user().with_text("Reply with OK");
+        self.complete("", &[message], &[]).await.map(|_| ())
+    }
+
     /// Check if this provider supports embeddings
     fn supports_embeddings(&self) -> bool {
         false
@@ -340,6 +438,13 @@ pub trait Provider: Send + Sync {
         }
     }
 
+    /// A snapshot of this provider's configuration with credentials removed, recorded with
+    /// sessions so it's clear what produced them. Providers that serialize their settings
+    /// override this with [`sanitized_provider_config`].
+    fn sanitized_config(&self) -> serde_json::Value {
+        serde_json::json!({ "model": self.get_model_config() })
+    }
+
     /// Generate a session name/description based on the conversation history
     /// This method can be overridden by providers to implement custom session naming strategies.
     /// The default implementation creates a prompt asking for a concise description in 4 words or less.
@@ -395,12 +500,149 @@ pub fn stream_from_single_message(message: Message, usage: ProviderUsage) -> Mes
     Box::pin(stream)
 }
 
+/// Pass `stream` through, and if it ends without the provider reporting any usage, yield usage
+/// estimated with the local tokenizer from the request and the streamed reply
+pub fn estimate_missing_usage(
+    mut stream: MessageStream,
+    model: String,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> MessageStream {
+    let system = system.to_string();
+    let messages = messages.to_vec();
+    let tools = tools.to_vec();
+    Box::pin(async_stream::try_stream! {
+        use futures::StreamExt;
+
+        let mut reply: Vec<Message> = Vec::new();
+        let mut reported = false;
+        while let Some(item) = stream.next().await {
+            let (message, usage) = item?;
+            reported |= usage.is_some();
+            if let Some(message) = &message {
+                crate::message::push_message(&mut reply, message.clone());
+            }
+            yield (message, usage);
+        }
+
+        if !reported {
+            let input = (tokenizer::count_text(&system, &model)
+                + tokenizer::count_messages(&messages, &model)
+                + tokenizer::count_tools(&tools, &model)) as i32;
+            let output = tokenizer::count_messages(&reply, &model) as i32;
+            let usage = Usage::new(Some(input), Some(output), Some(input + output));
+            yield (None, Some(ProviderUsage::estimated(model, usage)));
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures::StreamExt;
     use std::collections::HashMap;
 
     use serde_json::json;
+    #[tokio::test]
+    async fn test_estimate_missing_usage() {
+        let reply = |text: &str| {
+            let mut message = Message::assistant().with_text(text);
+            message.id = Some("reply".to_string());
+            Ok((Some(message), None))
+        };
+        let messages = vec![Message::user().with_text("Say hello")];
+
+        let stream: MessageStream =
+            Box::pin(futures::stream::iter(vec![reply("Hello"), reply(" there")]));
+        let items: Vec<_> =
+            estimate_missing_usage(stream, "gpt-4o".to_string(), "system", &messages, &[])
+                .collect()
+                .await;
+        assert_eq!(items.len(), 3);
+        let (message, usage) = items.last().unwrap().as_ref().unwrap();
+        assert!(message.is_none());
+        let usage = usage.as_ref().unwrap();
+        assert!(usage.estimated);
+        assert_eq!(usage.model, "gpt-4o");
+        assert!(usage.usage.input_tokens.unwrap() > 0);
+        assert!(usage.usage.output_tokens.unwrap() > 0);
+
+        // Reported usage is passed through and nothing is estimated
+        let reported =
+            ProviderUsage::new("gpt-4o".to_string(), Usage::new(Some(5), Some(2), Some(7)));
+        let stream: MessageStream = Box::pin(futures::stream::iter(vec![
+            reply("Hello"),
+            Ok((None, Some(reported))),
+        ]));
+        let items: Vec<_> =
+            estimate_missing_usage(stream, "gpt-4o".to_string(), "system", &messages, &[])
+                .collect()
+                .await;
+        assert_eq!(items.len(), 2);
+        let usage = items[1].as_ref().unwrap().1.as_ref().unwrap();
+        assert!(!usage.estimated);
+        assert_eq!(usage.usage.total_tokens, Some(7));
+    }
+
+    #[derive(Serialize)]
+    struct HostedProvider {
+        host: String,
+        api_key: String,
+        custom_headers: HashMap<String, String>,
+        model: ModelConfig,
+    }
+
+    #[async_trait]
+    impl Provider for HostedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::new("hosted", "Hosted", "", "model", vec![], "", vec![])
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            self.model.clone()
+        }
+
+        async fn complete(
+            &self,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_sanitized_provider_config_drops_credentials() {
+        let provider = HostedProvider {
+            host: "https://models.example.com".to_string(),
+            api_key: "sk-secret".to_string(),
+            custom_headers: HashMap::from([("Authorization".to_string(), "secret".to_string())]),
+            model: ModelConfig {
+                model_name: "model".to_string(),
+                context_limit: None,
+                temperature: Some(0.4),
+                max_tokens: None,
+                top_p: None,
+                stop_sequences: None,
+                thinking: Default::default(),
+                toolshim: false,
+                toolshim_model: None,
+                native_tools: Vec::new(),
+            },
+        };
+
+        let config = sanitized_provider_config(&provider);
+        assert_eq!(config["provider"], "hosted");
+        assert_eq!(config["host"], "https://models.example.com");
+        assert_eq!(config["model"]["model_name"], "model");
+        assert!(config["model"]["temperature"].is_number());
+        assert!(config.get("api_key").is_none());
+        assert!(config.get("custom_headers").is_none());
+        assert!(!config.to_string().contains("secret"));
+    }
+
     #[test]
     fn test_usage_creation() {
         let usage = Usage::new(Some(10), Some(20), Some(30));
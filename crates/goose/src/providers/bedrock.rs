@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    sanitized_provider_config, ConfigKey, Provider, ProviderMetadata, ProviderUsage,
+};
 use super::errors::ProviderError;
 use crate::impl_provider_default;
 use crate::message::Message;
@@ -52,7 +54,11 @@ impl BedrockProvider {
             }
         };
 
-        set_aws_env_vars(config.load_values());
+        set_aws_env_vars(
+            config
+                .load_effective_values()
+                .map(|effective| effective.values),
+        );
         set_aws_env_vars(config.load_secrets());
 
         let sdk_config = futures::executor::block_on(aws_config::load_from_env());
@@ -90,6 +96,10 @@ impl Provider for BedrockProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
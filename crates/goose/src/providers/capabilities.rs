@@ -0,0 +1,240 @@
+//! What a model can do, so features it doesn't support are left out of requests instead of
+//! failing them. Capabilities are looked up by provider and model name patterns: built-in rules
+//! for known models, then the rules in `GOOSE_MODEL_CAPABILITIES`.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::Config;
+
+const CAPABILITIES_KEY: &str = "GOOSE_MODEL_CAPABILITIES";
+
+/// The features a model supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ModelCapabilities {
+    pub supports_tools: bool,
+    pub supports_vision: bool,
+    pub supports_streaming: bool,
+    pub supports_structured_output: bool,
+    /// Most tokens the model writes in one response, when known
+    pub max_output_tokens: Option<usize>,
+}
+
+impl Default for ModelCapabilities {
+    fn default() -> Self {
+        Self {
+            supports_tools: true,
+            supports_vision: true,
+            supports_streaming: true,
+            supports_structured_output: true,
+            max_output_tokens: None,
+        }
+    }
+}
+
+/// Capabilities of the models whose name contains `pattern`, for one provider or all of them.
+/// Fields left unset keep what earlier rules said.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityRule {
+    /// Provider the rule is for, or every provider when unset
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Part of the model name, matched case-insensitively. Empty matches every model.
+    pub pattern: String,
+    #[serde(default)]
+    pub supports_tools: Option<bool>,
+    #[serde(default)]
+    pub supports_vision: Option<bool>,
+    #[serde(default)]
+    pub supports_streaming: Option<bool>,
+    #[serde(default)]
+    pub supports_structured_output: Option<bool>,
+    #[serde(default)]
+    pub max_output_tokens: Option<usize>,
+}
+
+impl CapabilityRule {
+    fn new(provider: Option<&str>, pattern: &str) -> Self {
+        Self {
+            provider: provider.map(str::to_string),
+            pattern: pattern.to_string(),
+            ..Self::default()
+        }
+    }
+
+    fn matches(&self, provider: Option<&str>, model: &str) -> bool {
+        let provider_matches = match (&self.provider, provider) {
+            (None, _) => true,
+            (Some(rule_provider), Some(provider)) => rule_provider.eq_ignore_ascii_case(provider),
+            (Some(_), None) => false,
+        };
+        provider_matches && model.contains(&self.pattern.to_lowercase())
+    }
+
+    fn apply(&self, capabilities: &mut ModelCapabilities) {
+        if let Some(supports_tools) = self.supports_tools {
+            capabilities.supports_tools = supports_tools;
+        }
+        if let Some(supports_vision) = self.supports_vision {
+            capabilities.supports_vision = supports_vision;
+        }
+        if let Some(supports_streaming) = self.supports_streaming {
+            capabilities.supports_streaming = supports_streaming;
+        }
+        if let Some(supports_structured_output) = self.supports_structured_output {
+            capabilities.supports_structured_output = supports_structured_output;
+        }
+        if let Some(max_output_tokens) = self.max_output_tokens {
+            capabilities.max_output_tokens = Some(max_output_tokens);
+        }
+    }
+}
+
+/// Rules for known models, general ones first so more specific ones can override them
+static KNOWN_MODEL_RULES: Lazy<Vec<CapabilityRule>> = Lazy::new(|| {
+    let no_vision = |provider, pattern| CapabilityRule {
+        supports_vision: Some(false),
+        ..CapabilityRule::new(provider, pattern)
+    };
+    let vision = |provider, pattern| CapabilityRule {
+        supports_vision: Some(true),
+        ..CapabilityRule::new(provider, pattern)
+    };
+    let no_tools = |provider, pattern| CapabilityRule {
+        supports_tools: Some(false),
+        supports_structured_output: Some(false),
+        ..CapabilityRule::new(provider, pattern)
+    };
+    let max_output = |pattern, tokens| CapabilityRule {
+        max_output_tokens: Some(tokens),
+        ..CapabilityRule::new(None, pattern)
+    };
+    vec![
+        // openai
+        no_vision(None, "gpt-3.5"),
+        max_output("gpt-4o", 16_384),
+        max_output("gpt-4.1", 32_768),
+        max_output("o3", 100_000),
+        max_output("o4-mini", 100_000),
+        CapabilityRule {
+            supports_streaming: Some(false),
+            ..no_tools(None, "o1-mini")
+        },
+        CapabilityRule {
+            supports_streaming: Some(false),
+            ..no_tools(None, "o1-preview")
+        },
+        // anthropic
+        max_output("claude-3-haiku", 4_096),
+        max_output("claude-3-opus", 4_096),
+        max_output("claude-3-5", 8_192),
+        max_output("claude-3-7", 64_000),
+        max_output("claude-sonnet-4", 64_000),
+        max_output("claude-opus-4", 32_000),
+        // google
+        max_output("gemini-2", 65_536),
+        // groq and ollama serve mostly text-only models
+        no_vision(Some("groq"), ""),
+        vision(Some("groq"), "llama-4"),
+        vision(Some("groq"), "vision"),
+        no_vision(Some("ollama"), ""),
+        vision(Some("ollama"), "llava"),
+        vision(Some("ollama"), "vision"),
+        vision(Some("ollama"), "gemma3"),
+        vision(Some("ollama"), "qwen2.5vl"),
+        // models without tool calling
+        no_tools(None, "deepseek-r1"),
+        no_tools(None, "gemma"),
+        vision(None, "gemma3"),
+        no_tools(None, "llama-2"),
+        no_tools(None, "llama2"),
+        no_tools(None, "codellama"),
+        // embedding endpoints, such as those served by databricks
+        CapabilityRule {
+            supports_vision: Some(false),
+            supports_streaming: Some(false),
+            ..no_tools(None, "embed")
+        },
+    ]
+});
+
+/// The rules in the config, or none when they're missing or malformed
+pub fn configured_rules() -> Vec<CapabilityRule> {
+    match Config::global().get_param::<Vec<CapabilityRule>>(CAPABILITIES_KEY) {
+        Ok(rules) => rules,
+        Err(crate::config::ConfigError::NotFound(_)) => Vec::new(),
+        Err(e) => {
+            tracing::warn!("Ignoring {}: {}", CAPABILITIES_KEY, e);
+            Vec::new()
+        }
+    }
+}
+
+/// What `model` can do when served by `provider`, with the configured rules applied on top of
+/// the known ones
+pub fn resolve(provider: Option<&str>, model: &str) -> ModelCapabilities {
+    resolve_with(&configured_rules(), provider, model)
+}
+
+/// What `model` can do when served by `provider`, with `rules` applied on top of the known ones
+pub fn resolve_with(
+    rules: &[CapabilityRule],
+    provider: Option<&str>,
+    model: &str,
+) -> ModelCapabilities {
+    let model = model.to_lowercase();
+    let mut capabilities = ModelCapabilities::default();
+    for rule in KNOWN_MODEL_RULES.iter().chain(rules) {
+        if rule.matches(provider, &model) {
+            rule.apply(&mut capabilities);
+        }
+    }
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_models() {
+        let claude = resolve_with(&[], Some("anthropic"), "claude-sonnet-4-20250514");
+        assert_eq!(
+            claude,
+            ModelCapabilities {
+                max_output_tokens: Some(64_000),
+                ..ModelCapabilities::default()
+            }
+        );
+
+        let groq_llama = resolve_with(&[], Some("groq"), "llama-3.1-8b-instant");
+        assert!(groq_llama.supports_tools);
+        assert!(!groq_llama.supports_vision);
+        assert!(resolve_with(&[], Some("groq"), "meta-llama/llama-4-scout").supports_vision);
+        // The same model elsewhere isn't assumed to be text-only
+        assert!(resolve_with(&[], Some("openrouter"), "llama-3.1-8b").supports_vision);
+
+        let embedding = resolve_with(&[], Some("databricks"), "databricks-bge-large-embedding");
+        assert!(!embedding.supports_tools);
+        assert!(!embedding.supports_vision);
+        assert!(!embedding.supports_streaming);
+    }
+
+    #[test]
+    fn test_configured_rules_override_known_ones() {
+        let rules: Vec<CapabilityRule> = serde_json::from_value(serde_json::json!([
+            {"provider": "groq", "pattern": "llama-3.1", "supports_vision": true},
+            {"pattern": "my-finetune", "supports_tools": false, "max_output_tokens": 2048}
+        ]))
+        .unwrap();
+
+        assert!(resolve_with(&rules, Some("groq"), "llama-3.1-8b-instant").supports_vision);
+        assert!(!resolve_with(&rules, Some("groq"), "llama-3.3-70b").supports_vision);
+
+        let finetune = resolve_with(&rules, None, "My-Finetune-v2");
+        assert!(!finetune.supports_tools);
+        assert!(finetune.supports_streaming);
+        assert_eq!(finetune.max_output_tokens, Some(2048));
+    }
+}
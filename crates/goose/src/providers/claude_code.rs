@@ -7,7 +7,9 @@ use std::process::Stdio;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    sanitized_provider_config, ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
 use super::errors::ProviderError;
 use super::utils::emit_debug_trace;
 use crate::config::Config;
@@ -473,6 +475,10 @@ impl Provider for ClaudeCodeProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
@@ -10,12 +10,15 @@ use std::time::Duration;
 use tokio::pin;
 use tokio_util::io::StreamReader;
 
-use super::base::{ConfigKey, MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    estimate_missing_usage, sanitized_provider_config, ConfigKey, MessageStream, Provider,
+    ProviderMetadata, ProviderUsage, Usage,
+};
 use super::embedding::EmbeddingCapable;
 use super::errors::ProviderError;
 use super::formats::databricks::{create_request, response_to_message};
 use super::oauth;
-use super::utils::{get_model, ImageFormat};
+use super::utils::{get_model, map_http_error_to_provider_error, ImageFormat};
 use crate::config::ConfigError;
 use crate::impl_provider_default;
 use crate::message::Message;
@@ -317,7 +320,12 @@ impl DatabricksProvider {
                 StatusCode::TOO_MANY_REQUESTS
                 | StatusCode::INTERNAL_SERVER_ERROR
                 | StatusCode::SERVICE_UNAVAILABLE => {
-                    if attempts < self.retry_config.max_retries {
+                    let bytes = response.bytes().await?;
+                    let error = map_databricks_error(endpoint_name(path), &bytes);
+                    // An exhausted pay-per-token quota won't recover by the next attempt
+                    let quota_exhausted =
+                        databricks_error_code(&bytes).as_deref() == Some("QUOTA_EXCEEDED");
+                    if attempts < self.retry_config.max_retries && !quota_exhausted {
                         attempts += 1;
                         tracing::warn!(
                             "{}: retrying ({}/{})",
@@ -333,17 +341,30 @@ impl DatabricksProvider {
                         continue;
                     }
 
-                    Err(match status {
-                        StatusCode::TOO_MANY_REQUESTS => {
+                    Err(match error {
+                        Some(error) => error,
+                        None if status == StatusCode::TOO_MANY_REQUESTS => {
                             ProviderError::RateLimitExceeded("Rate limit exceeded".to_string())
                         }
-                        _ => ProviderError::ServerError("Server error".to_string()),
+                        None => ProviderError::ServerError("Server error".to_string()),
                     })
                 }
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    // An expired token or OAuth refresh token; retrying won't help
+                    let bytes = response.bytes().await?;
+                    return Err(map_http_error_to_provider_error(
+                        "databricks",
+                        status,
+                        serde_json::from_slice(&bytes).ok(),
+                    ));
+                }
                 StatusCode::BAD_REQUEST => {
                     // Databricks provides a generic 'error' but also includes 'external_model_message' which is provider specific
                     // We try to extract the error message from the payload and check for phrases that indicate context length exceeded
                     let bytes = response.bytes().await?;
+                    if let Some(error) = map_databricks_error(endpoint_name(path), &bytes) {
+                        return Err(error);
+                    }
                     let payload_str = String::from_utf8_lossy(&bytes).to_lowercase();
                     let check_phrases = [
                         "too long",
@@ -391,14 +412,18 @@ impl DatabricksProvider {
                     )));
                 }
                 _ => {
+                    let bytes = response.bytes().await?;
                     tracing::debug!(
                         "{}",
                         format!(
                             "Provider request failed with status: {}. Payload: {:?}",
                             status,
-                            response.text().await.ok().unwrap_or_default()
+                            String::from_utf8_lossy(&bytes)
                         )
                     );
+                    if let Some(error) = map_databricks_error(endpoint_name(path), &bytes) {
+                        return Err(error);
+                    }
                     return Err(ProviderError::RequestFailed(format!(
                         "Request failed with status: {}",
                         status
@@ -409,6 +434,40 @@ impl DatabricksProvider {
     }
 }
 
+/// The serving endpoint a `serving-endpoints/{name}/invocations` path posts to
+fn endpoint_name(path: &str) -> &str {
+    path.strip_prefix("serving-endpoints/")
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or(path)
+}
+
+/// The `error_code` Databricks puts in the body of a failed request
+fn databricks_error_code(body: &[u8]) -> Option<String> {
+    let body: Value = serde_json::from_slice(body).ok()?;
+    body.get("error_code")?.as_str().map(String::from)
+}
+
+/// Map a Databricks error body to a more specific error than its status alone gives, when its
+/// `error_code` is one goose knows
+fn map_databricks_error(endpoint: &str, body: &[u8]) -> Option<ProviderError> {
+    let code = databricks_error_code(body)?;
+    let message = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|body| body.get("message")?.as_str().map(String::from))
+        .unwrap_or_else(|| code.clone());
+    match code.as_str() {
+        "RESOURCE_DOES_NOT_EXIST" => Some(ProviderError::ModelNotFound(format!(
+            "Databricks serving endpoint '{}' does not exist: {}. \
+             Run fetch_supported_models (or `goose configure`) to list the endpoints available in this workspace.",
+            endpoint, message
+        ))),
+        "QUOTA_EXCEEDED" | "REQUEST_LIMIT_EXCEEDED" => Some(ProviderError::RateLimitExceeded(
+            format!("{}: {}", code, message),
+        )),
+        _ => None,
+    }
+}
+
 #[async_trait]
 impl Provider for DatabricksProvider {
     fn metadata() -> ProviderMetadata {
@@ -430,6 +489,10 @@ impl Provider for DatabricksProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
@@ -491,7 +554,7 @@ impl Provider for DatabricksProvider {
 
         let model_config = self.model.clone();
         // Wrap in a line decoder and yield lines inside the stream
-        Ok(Box::pin(try_stream! {
+        let reply: MessageStream = Box::pin(try_stream! {
             let stream_reader = StreamReader::new(stream);
             let framed = FramedRead::new(stream_reader, LinesCodec::new()).map_err(anyhow::Error::from);
 
@@ -502,7 +565,14 @@ impl Provider for DatabricksProvider {
                 super::utils::emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
                 yield (message, usage);
             }
-        }))
+        });
+        Ok(estimate_missing_usage(
+            reply,
+            self.model.model_name.clone(),
+            system,
+            messages,
+            tools,
+        ))
     }
 
     fn supports_streaming(&self) -> bool {
@@ -635,3 +705,83 @@ impl EmbeddingCapable for DatabricksProvider {
         Ok(embeddings)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_endpoint_is_model_not_found() {
+        let body = br#"{"error_code": "RESOURCE_DOES_NOT_EXIST", "message": "The given endpoint does not exist, please retry after checking the specified model and version deployment exists."}"#;
+        let error = map_databricks_error(
+            endpoint_name("serving-endpoints/databricks-claude-9/invocations"),
+            body,
+        )
+        .unwrap();
+
+        assert!(matches!(error, ProviderError::ModelNotFound(_)));
+        assert_eq!(error.code(), "model_not_found");
+        let message = error.to_string();
+        assert!(message.contains("'databricks-claude-9'"));
+        assert!(message.contains("fetch_supported_models"));
+    }
+
+    #[test]
+    fn test_quota_errors_are_rate_limits() {
+        for code in ["QUOTA_EXCEEDED", "REQUEST_LIMIT_EXCEEDED"] {
+            let body = format!(
+                r#"{{"error_code": "{}", "message": "Pay-per-token limit reached"}}"#,
+                code
+            );
+            let error = map_databricks_error("endpoint", body.as_bytes()).unwrap();
+            assert!(matches!(error, ProviderError::RateLimitExceeded(_)));
+            assert!(error.to_string().contains("Pay-per-token limit reached"));
+        }
+    }
+
+    #[test]
+    fn test_other_bodies_are_not_mapped() {
+        for body in [
+            &br#"{"error_code": "BAD_REQUEST", "message": "Invalid input"}"#[..],
+            br#"{"message": "Input is too long"}"#,
+            b"<html>Bad gateway</html>",
+        ] {
+            assert!(map_databricks_error("endpoint", body).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_authentication_failure() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(
+                "/serving-endpoints/databricks-claude-sonnet-4/invocations",
+            ))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error_code": "401",
+                "message": "Credential was not sent or was of an unsupported type for this API."
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = DatabricksProvider::from_params(
+            server.uri(),
+            "expired-token".to_string(),
+            ModelConfig::new_or_fail("databricks-claude-sonnet-4"),
+        )
+        .unwrap();
+        let error = provider.post(&json!({"messages": []})).await.unwrap_err();
+
+        let ProviderError::AuthenticationFailed { provider, message } = error else {
+            panic!("expected an authentication failure, got {:?}", error);
+        };
+        assert_eq!(provider, "databricks");
+        assert!(message.contains("Credential was not sent"));
+    }
+}
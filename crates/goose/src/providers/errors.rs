@@ -26,6 +26,31 @@ pub enum ProviderError {
 
     #[error("Unsupported operation: {0}")]
     NotImplemented(String),
+
+    #[error("Model not found: {0}")]
+    ModelNotFound(String),
+
+    /// The provider rejected its credentials, e.g. an expired key or OAuth token
+    #[error("Authentication with {provider} failed: {message}")]
+    AuthenticationFailed { provider: String, message: String },
+}
+
+impl ProviderError {
+    /// Stable identifier for the kind of error, for clients that react to it
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProviderError::Authentication(_) => "authentication",
+            ProviderError::ContextLengthExceeded(_) => "context_length_exceeded",
+            ProviderError::RateLimitExceeded(_) => "rate_limit_exceeded",
+            ProviderError::ServerError(_) => "server_error",
+            ProviderError::RequestFailed(_) => "request_failed",
+            ProviderError::ExecutionError(_) => "execution_error",
+            ProviderError::UsageError(_) => "usage_error",
+            ProviderError::NotImplemented(_) => "not_implemented",
+            ProviderError::ModelNotFound(_) => "model_not_found",
+            ProviderError::AuthenticationFailed { .. } => "authentication_failed",
+        }
+    }
 }
 
 impl From<anyhow::Error> for ProviderError {
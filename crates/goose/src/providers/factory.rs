@@ -146,6 +146,15 @@ fn create_lead_worker_from_env(
     )))
 }
 
+/// Create a provider for an explicit (provider, model) pair
+///
+/// Unlike [`create`], this never wraps the result in a lead/worker provider, which makes it
+/// suitable for secondary providers such as a dedicated summarization model.
+pub fn create_with_model(provider_name: &str, model_name: &str) -> Result<Arc<dyn Provider>> {
+    let model = ModelConfig::new(model_name)?;
+    create_provider(provider_name, model)
+}
+
 fn create_provider(name: &str, model: ModelConfig) -> Result<Arc<dyn Provider>> {
     // We use Arc instead of Box to be able to clone for multiple async tasks
     match name {
@@ -1,5 +1,5 @@
-use crate::message::{Message, MessageContent};
-use crate::model::ModelConfig;
+use crate::message::{Citation, Message, MessageContent};
+use crate::model::{ModelConfig, NativeTool};
 use crate::providers::base::Usage;
 use crate::providers::errors::ProviderError;
 use anyhow::{anyhow, Result};
@@ -27,6 +27,8 @@ const TOOL_USE_ID_FIELD: &str = "tool_use_id";
 const IS_ERROR_FIELD: &str = "is_error";
 const SIGNATURE_FIELD: &str = "signature";
 const DATA_FIELD: &str = "data";
+const SERVER_TOOL_USE_TYPE: &str = "server_tool_use";
+const CITATIONS_FIELD: &str = "citations";
 
 /// Convert internal Message format to Anthropic's API message specification
 pub fn format_messages(messages: &[Message]) -> Vec<Value> {
@@ -66,11 +68,13 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 }
                 MessageContent::ToolResponse(tool_response) => match &tool_response.tool_result {
                     Ok(result) => {
-                        let text = result
-                            .iter()
-                            .filter_map(|c| c.as_text().map(|t| t.text.clone()))
-                            .collect::<Vec<_>>()
-                            .join("\n");
+                        let text = tool_response.structured_text().unwrap_or_else(|| {
+                            result
+                                .iter()
+                                .filter_map(|c| c.as_text().map(|t| t.text.clone()))
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        });
 
                         content.push(json!({
                             TYPE_FIELD: TOOL_RESULT_TYPE,
@@ -96,7 +100,15 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                MessageContent::Attachment(_) => {
+                    // Attachments are resolved into their contents before reaching the provider
+                }
                 MessageContent::Thinking(thinking) => {
+                    // The API rejects thinking blocks without the signature it issued with them,
+                    // such as those from other providers
+                    if thinking.signature.is_empty() {
+                        continue;
+                    }
                     content.push(json!({
                         TYPE_FIELD: THINKING_TYPE,
                         THINKING_TYPE: thinking.thinking,
@@ -109,6 +121,23 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                         DATA_FIELD: redacted.data
                     }));
                 }
+                MessageContent::ServerToolUse(tool_use) => {
+                    // The API rejects a server tool use without its result, such as one from
+                    // another provider, and the result block is sent back as it came
+                    let Some(result) = &tool_use.result else {
+                        continue;
+                    };
+                    content.push(json!({
+                        TYPE_FIELD: SERVER_TOOL_USE_TYPE,
+                        ID_FIELD: tool_use.id,
+                        NAME_FIELD: tool_use.name,
+                        INPUT_FIELD: tool_use.input
+                    }));
+                    content.push(result.clone());
+                }
+                MessageContent::Citations(_) => {
+                    // Citations only point into earlier results, the text they follow is enough
+                }
                 MessageContent::Image(_) => continue, // Anthropic doesn't support image content yet
                 MessageContent::FrontendToolRequest(tool_request) => {
                     if let Ok(tool_call) = &tool_request.tool_call {
@@ -197,6 +226,66 @@ pub fn format_tools(tools: &[Tool]) -> Vec<Value> {
     tool_specs
 }
 
+/// Request blocks for the tools the model runs on Anthropic's side. A tool is left out when an
+/// extension tool already has its name, as the API rejects tools with the same name.
+pub fn format_native_tools(model_config: &ModelConfig, tool_specs: &[Value]) -> Vec<Value> {
+    model_config
+        .native_tools
+        .iter()
+        .filter(|tool| {
+            let taken = tool_specs
+                .iter()
+                .any(|spec| spec.get(NAME_FIELD).and_then(|n| n.as_str()) == Some(tool.name()));
+            if taken {
+                tracing::warn!(
+                    "Not sending the native {} tool, an extension tool has the same name",
+                    tool.name()
+                );
+            }
+            !taken
+        })
+        .map(|tool| match tool {
+            NativeTool::WebSearch => json!({
+                TYPE_FIELD: "web_search_20250305",
+                NAME_FIELD: tool.name(),
+            }),
+            NativeTool::CodeExecution => json!({
+                TYPE_FIELD: "code_execution_20250522",
+                NAME_FIELD: tool.name(),
+            }),
+        })
+        .collect()
+}
+
+/// Web page citations on a text block. Other kinds, such as places in a document, are left out.
+fn parse_citations(citations: Option<&Value>) -> Vec<Citation> {
+    citations
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(parse_citation)
+        .collect()
+}
+
+fn parse_citation(citation: &Value) -> Option<Citation> {
+    let field = |name: &str| {
+        citation
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    Some(Citation {
+        url: field("url")?,
+        title: field("title"),
+        cited_text: field("cited_text"),
+    })
+}
+
+/// Whether the block holds the result of a server tool, such as `web_search_tool_result`
+fn is_server_tool_result(block_type: &str) -> bool {
+    block_type != TOOL_RESULT_TYPE && block_type.ends_with("_tool_result")
+}
+
 /// Convert system message to Anthropic's API system specification
 pub fn format_system(system: &str) -> Value {
     json!([{
@@ -221,6 +310,41 @@ pub fn response_to_message(response: &Value) -> Result<Message> {
                 if let Some(text) = block.get(TEXT_TYPE).and_then(|t| t.as_str()) {
                     message = message.with_text(text.to_string());
                 }
+                let citations = parse_citations(block.get(CITATIONS_FIELD));
+                if !citations.is_empty() {
+                    message = message.with_content(MessageContent::citations(citations));
+                }
+            }
+            Some(SERVER_TOOL_USE_TYPE) => {
+                let id = block
+                    .get(ID_FIELD)
+                    .and_then(|i| i.as_str())
+                    .ok_or_else(|| anyhow!("Missing server_tool_use id"))?;
+                let name = block
+                    .get(NAME_FIELD)
+                    .and_then(|n| n.as_str())
+                    .ok_or_else(|| anyhow!("Missing server_tool_use name"))?;
+                let input = block.get(INPUT_FIELD).cloned().unwrap_or(json!({}));
+                message =
+                    message.with_content(MessageContent::server_tool_use(id, name, input, None));
+            }
+            Some(block_type) if is_server_tool_result(block_type) => {
+                let tool_use_id = block.get(TOOL_USE_ID_FIELD).and_then(|i| i.as_str());
+                let tool_use = message
+                    .content
+                    .iter_mut()
+                    .find_map(|content| match content {
+                        MessageContent::ServerToolUse(tool_use)
+                            if Some(tool_use.id.as_str()) == tool_use_id =>
+                        {
+                            Some(tool_use)
+                        }
+                        _ => None,
+                    });
+                match tool_use {
+                    Some(tool_use) => tool_use.result = Some(block.clone()),
+                    None => tracing::debug!("Dropping {} without its server_tool_use", block_type),
+                }
             }
             Some(TOOL_USE_TYPE) => {
                 let id = block
@@ -302,7 +426,8 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
             Some(total_input_i32),
             Some(output_tokens_i32),
             Some(total_tokens_i32),
-        ))
+        )
+        .with_web_search_requests(web_search_requests(usage)))
     } else if data.as_object().is_some() {
         // Check if the data itself is the usage object (for message_delta events that might have usage at top level)
         let input_tokens = data
@@ -345,7 +470,8 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
                 Some(total_input_i32),
                 Some(output_tokens_i32),
                 Some(total_tokens_i32),
-            ))
+            )
+            .with_web_search_requests(web_search_requests(data)))
         } else {
             tracing::debug!("🔍 Anthropic no token data found in object");
             Ok(Usage::new(None, None, None))
@@ -360,6 +486,27 @@ pub fn get_usage(data: &Value) -> Result<Usage> {
     }
 }
 
+/// Web searches the usage reports, which are billed apart from the tokens
+fn web_search_requests(usage: &Value) -> Option<i32> {
+    usage
+        .get("server_tool_use")
+        .and_then(|s| s.get("web_search_requests"))
+        .and_then(|v| v.as_u64())
+        .map(|v| v.min(i32::MAX as u64) as i32)
+}
+
+/// Whether the model accepts the `thinking` request field: Claude 3.7 Sonnet and Claude 4 models
+pub fn supports_extended_thinking(model_name: &str) -> bool {
+    [
+        "claude-3-7-sonnet",
+        "claude-sonnet-4",
+        "claude-opus-4",
+        "claude-4",
+    ]
+    .iter()
+    .any(|family| model_name.contains(family))
+}
+
 /// Create a complete request payload for Anthropic's API
 pub fn create_request(
     model_config: &ModelConfig,
@@ -368,7 +515,10 @@ pub fn create_request(
     tools: &[Tool],
 ) -> Result<Value> {
     let anthropic_messages = format_messages(messages);
-    let tool_specs = format_tools(tools);
+    let mut tool_specs = format_tools(tools);
+    // Native tools go first so the cache marker on the last extension tool covers them too
+    let native_specs = format_native_tools(model_config, &tool_specs);
+    tool_specs.splice(0..0, native_specs);
     let system_spec = format_system(system);
 
     // Check if we have any messages to send
@@ -401,26 +551,38 @@ pub fn create_request(
             .insert("tools".to_string(), json!(tool_specs));
     }
 
-    // Add temperature if specified and not using extended thinking model
-    if let Some(temp) = model_config.temperature {
-        // Claude 3.7 models with thinking enabled don't support temperature
-        if !model_config.model_name.starts_with("claude-3-7-sonnet-") {
+    let thinking_enabled =
+        model_config.thinking.enabled && supports_extended_thinking(&model_config.model_name);
+
+    // Add temperature or top_p if specified and not using extended thinking. Anthropic accepts
+    // temperatures up to 1.0 and newer models reject both settings together, so top_p is only
+    // sent without a temperature
+    if !thinking_enabled && !model_config.model_name.starts_with("claude-3-7-sonnet-") {
+        if let Some(temp) = model_config.temperature {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("temperature".to_string(), json!(temp.clamp(0.0, 1.0)));
+        } else if let Some(top_p) = model_config.top_p {
             payload
                 .as_object_mut()
                 .unwrap()
-                .insert("temperature".to_string(), json!(temp));
+                .insert("top_p".to_string(), json!(top_p.clamp(0.0, 1.0)));
         }
     }
 
-    // Add thinking parameters for claude-3-7-sonnet model
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if model_config.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-        // Minimum budget_tokens is 1024
-        let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
-            .unwrap_or_else(|_| "16000".to_string())
-            .parse()
-            .unwrap_or(16000);
+    if let Some(stop) = model_config.stop_sequences.as_ref() {
+        if !stop.is_empty() {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("stop_sequences".to_string(), json!(stop));
+        }
+    }
 
+    // The thinking budget counts towards max_tokens, so it is added on top of the output limit
+    if thinking_enabled {
+        let budget_tokens = model_config.thinking.budget_tokens;
         payload
             .as_object_mut()
             .unwrap()
@@ -438,6 +600,27 @@ pub fn create_request(
     Ok(payload)
 }
 
+/// The error carried by an `error` event in a stream, such as
+/// `{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}`.
+/// Overloaded and rate limited requests map to errors worth retrying.
+fn streaming_error(data: &Value) -> ProviderError {
+    let error = data.get("error");
+    let kind = error
+        .and_then(|e| e.get("type"))
+        .and_then(|t| t.as_str())
+        .unwrap_or("unknown_error");
+    let message = error
+        .and_then(|e| e.get("message"))
+        .and_then(|m| m.as_str())
+        .unwrap_or(kind)
+        .to_string();
+    match kind {
+        "overloaded_error" | "api_error" => ProviderError::ServerError(message),
+        "rate_limit_error" => ProviderError::RateLimitExceeded(message),
+        _ => ProviderError::RequestFailed(format!("{}: {}", kind, message)),
+    }
+}
+
 /// Process streaming response from Anthropic's API
 pub fn response_to_streaming_message<S>(
     mut stream: S,
@@ -466,8 +649,18 @@ where
         let mut accumulated_text = String::new();
         let mut accumulated_tool_calls: std::collections::HashMap<String, (String, String)> = std::collections::HashMap::new();
         let mut current_tool_id: Option<String> = None;
+        // Thinking text and signature of the thinking block being streamed
+        let mut current_thinking: Option<(String, String)> = None;
+        let mut current_redacted_thinking: Option<String> = None;
+        // Id, name and input of the server tool use being streamed, and those waiting for their
+        // result, which comes in a block of its own
+        let mut current_server_tool: Option<(String, String, String)> = None;
+        let mut pending_server_tools: Vec<(String, String, Value)> = Vec::new();
+        // Citations of the text block being streamed
+        let mut current_citations: Vec<Citation> = Vec::new();
         let mut final_usage: Option<crate::providers::base::ProviderUsage> = None;
         let mut message_id: Option<String> = None;
+        let mut truncated = false;
 
         while let Some(line_result) = stream.next().await {
             let line = line_result?;
@@ -520,13 +713,35 @@ where
                 "content_block_start" => {
                     // A new content block started
                     if let Some(content_block) = event.data.get("content_block") {
-                        if content_block.get("type") == Some(&json!("tool_use")) {
+                        if content_block.get("type") == Some(&json!(THINKING_TYPE)) {
+                            let field = |name: &str| content_block.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                            current_thinking = Some((field(THINKING_TYPE), field(SIGNATURE_FIELD)));
+                        } else if content_block.get("type") == Some(&json!(REDACTED_THINKING_TYPE)) {
+                            let data = content_block.get(DATA_FIELD).and_then(|v| v.as_str()).unwrap_or_default();
+                            current_redacted_thinking = Some(data.to_string());
+                        } else if content_block.get("type") == Some(&json!("tool_use")) {
                             if let Some(id) = content_block.get("id").and_then(|v| v.as_str()) {
                                 current_tool_id = Some(id.to_string());
                                 if let Some(name) = content_block.get("name").and_then(|v| v.as_str()) {
                                     accumulated_tool_calls.insert(id.to_string(), (name.to_string(), String::new()));
                                 }
                             }
+                        } else if content_block.get("type") == Some(&json!(SERVER_TOOL_USE_TYPE)) {
+                            let field = |name: &str| content_block.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                            current_server_tool = Some((field(ID_FIELD), field(NAME_FIELD), String::new()));
+                        } else if content_block.get("type").and_then(|t| t.as_str()).is_some_and(is_server_tool_result) {
+                            // Results arrive whole in the block's start event
+                            let tool_use_id = content_block.get(TOOL_USE_ID_FIELD).and_then(|v| v.as_str());
+                            if let Some(position) = pending_server_tools.iter().position(|(id, _, _)| Some(id.as_str()) == tool_use_id) {
+                                let (id, name, input) = pending_server_tools.remove(position);
+                                let mut message = Message::new(
+                                    Role::Assistant,
+                                    chrono::Utc::now().timestamp(),
+                                    vec![MessageContent::server_tool_use(id, name, input, Some(content_block.clone()))],
+                                );
+                                message.id = message_id.clone();
+                                yield (Some(message), None);
+                            }
                         }
                     }
                     continue;
@@ -547,9 +762,25 @@ where
                                 message.id = message_id.clone();
                                 yield (Some(message), None);
                             }
+                        } else if delta.get("type") == Some(&json!("thinking_delta")) {
+                            if let (Some((thinking, _)), Some(text)) = (current_thinking.as_mut(), delta.get(THINKING_TYPE).and_then(|v| v.as_str())) {
+                                thinking.push_str(text);
+                            }
+                        } else if delta.get("type") == Some(&json!("signature_delta")) {
+                            if let (Some((_, signature)), Some(text)) = (current_thinking.as_mut(), delta.get(SIGNATURE_FIELD).and_then(|v| v.as_str())) {
+                                signature.push_str(text);
+                            }
+                        } else if delta.get("type") == Some(&json!("citations_delta")) {
+                            if let Some(citation) = delta.get("citation").and_then(parse_citation) {
+                                current_citations.push(citation);
+                            }
                         } else if delta.get("type") == Some(&json!("input_json_delta")) {
                             // Tool input delta
-                            if let Some(tool_id) = &current_tool_id {
+                            if let Some((_, _, input)) = current_server_tool.as_mut() {
+                                if let Some(partial_json) = delta.get("partial_json").and_then(|v| v.as_str()) {
+                                    input.push_str(partial_json);
+                                }
+                            } else if let Some(tool_id) = &current_tool_id {
                                 if let Some(partial_json) = delta.get("partial_json").and_then(|v| v.as_str()) {
                                     if let Some((_name, args)) = accumulated_tool_calls.get_mut(tool_id) {
                                         args.push_str(partial_json);
@@ -561,8 +792,25 @@ where
                     continue;
                 }
                 "content_block_stop" => {
-                    // Content block finished
-                    if let Some(tool_id) = current_tool_id.take() {
+                    // Content block finished. Thinking is yielded whole, as its signature only
+                    // arrives at the end and must be sent back with it on later turns.
+                    if let Some((thinking, signature)) = current_thinking.take() {
+                        let mut message = Message::new(
+                            Role::Assistant,
+                            chrono::Utc::now().timestamp(),
+                            vec![MessageContent::thinking(thinking, signature)],
+                        );
+                        message.id = message_id.clone();
+                        yield (Some(message), None);
+                    } else if let Some(data) = current_redacted_thinking.take() {
+                        let mut message = Message::new(
+                            Role::Assistant,
+                            chrono::Utc::now().timestamp(),
+                            vec![MessageContent::redacted_thinking(data)],
+                        );
+                        message.id = message_id.clone();
+                        yield (Some(message), None);
+                    } else if let Some(tool_id) = current_tool_id.take() {
                         // Tool call finished, yield complete tool call
                         if let Some((name, args)) = accumulated_tool_calls.remove(&tool_id) {
                             let parsed_args = if args.is_empty() {
@@ -596,11 +844,26 @@ where
                             message.id = message_id.clone();
                             yield (Some(message), None);
                         }
+                    } else if let Some((id, name, input)) = current_server_tool.take() {
+                        // Held back until its result arrives, as the two are sent back together
+                        let input = serde_json::from_str(&input).unwrap_or_else(|_| json!({}));
+                        pending_server_tools.push((id, name, input));
+                    } else if !current_citations.is_empty() {
+                        let mut message = Message::new(
+                            Role::Assistant,
+                            chrono::Utc::now().timestamp(),
+                            vec![MessageContent::citations(std::mem::take(&mut current_citations))],
+                        );
+                        message.id = message_id.clone();
+                        yield (Some(message), None);
                     }
                     continue;
                 }
                 "message_delta" => {
                     // Message metadata delta (like stop_reason) and cumulative usage
+                    if event.data.get("delta").and_then(|d| d.get("stop_reason")) == Some(&json!("max_tokens")) {
+                        truncated = true;
+                    }
                     tracing::debug!("🔍 Anthropic message_delta event data: {}", serde_json::to_string_pretty(&event.data).unwrap_or_else(|_| format!("{:?}", event.data)));
                     if let Some(usage_data) = event.data.get("usage") {
                         tracing::debug!("🔍 Anthropic message_delta usage data (cumulative): {}", serde_json::to_string_pretty(usage_data).unwrap_or_else(|_| format!("{:?}", usage_data)));
@@ -611,8 +874,15 @@ where
                         // IMPORTANT: message_delta usage should be MERGED with existing usage, not replace it
                         // message_start has input tokens, message_delta has output tokens
                         if let Some(existing_usage) = &final_usage {
-                            let merged_input = existing_usage.usage.input_tokens.or(delta_usage.input_tokens);
+                            // Server tool results add to the input while the message streams, so
+                            // input tokens in the delta, when there are any, are the final count
+                            let merged_input = if usage_data.get("input_tokens").and_then(|v| v.as_u64()).is_some() {
+                                delta_usage.input_tokens.or(existing_usage.usage.input_tokens)
+                            } else {
+                                existing_usage.usage.input_tokens.or(delta_usage.input_tokens)
+                            };
                             let merged_output = delta_usage.output_tokens.or(existing_usage.usage.output_tokens);
+                            let merged_searches = delta_usage.web_search_requests.or(existing_usage.usage.web_search_requests);
                             let merged_total = match (merged_input, merged_output) {
                                 (Some(input), Some(output)) => Some(input + output),
                                 (Some(input), None) => Some(input),
@@ -620,7 +890,8 @@ where
                                 (None, None) => None,
                             };
 
-                            let merged_usage = crate::providers::base::Usage::new(merged_input, merged_output, merged_total);
+                            let merged_usage = crate::providers::base::Usage::new(merged_input, merged_output, merged_total)
+                                .with_web_search_requests(merged_searches);
                             final_usage = Some(crate::providers::base::ProviderUsage::new(existing_usage.model.clone(), merged_usage));
                             tracing::debug!("🔍 Anthropic MERGED usage: input_tokens={:?}, output_tokens={:?}, total_tokens={:?}",
                                     merged_input, merged_output, merged_total);
@@ -656,6 +927,11 @@ where
                     }
                     break;
                 }
+                "ping" => continue,
+                "error" => {
+                    Err::<(), ProviderError>(streaming_error(&event.data))?;
+                    continue;
+                }
                 _ => {
                     // Unknown event type, log and continue
                     tracing::debug!("Unknown streaming event type: {}", event.event_type);
@@ -664,8 +940,20 @@ where
             }
         }
 
+        // Server tool uses the stream ended before the results of, such as on a paused turn
+        for (id, name, input) in pending_server_tools {
+            let mut message = Message::new(
+                Role::Assistant,
+                chrono::Utc::now().timestamp(),
+                vec![MessageContent::server_tool_use(id, name, input, None)],
+            );
+            message.id = message_id.clone();
+            yield (Some(message), None);
+        }
+
         // Yield final usage information if available
-        if let Some(usage) = final_usage {
+        if let Some(mut usage) = final_usage {
+            usage.truncated = truncated;
             yield (None, Some(usage));
         } else {
             tracing::debug!("🔍 Anthropic no final usage to yield");
@@ -676,6 +964,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::ThinkingConfig;
     use rmcp::object;
     use serde_json::json;
 
@@ -911,41 +1200,527 @@ mod tests {
 
     #[test]
     fn test_create_request_with_thinking() -> Result<()> {
-        let original_value = std::env::var("CLAUDE_THINKING_ENABLED").ok();
-        std::env::set_var("CLAUDE_THINKING_ENABLED", "true");
+        let thinking = ThinkingConfig {
+            enabled: true,
+            budget_tokens: 4096,
+        };
+        let model_config = ModelConfig {
+            model_name: "claude-3-7-sonnet-20250219".to_string(),
+            context_limit: None,
+            temperature: Some(0.5),
+            max_tokens: Some(8192),
+            top_p: None,
+            stop_sequences: None,
+            thinking,
+            toolshim: false,
+            toolshim_model: None,
+            native_tools: Vec::new(),
+        };
+        let system = "You are a helpful assistant.";
+        let messages = vec![Message::user().with_text("Hello")];
+        let tools = vec![];
+
+        let payload = create_request(&model_config, system, &messages, &tools)?;
+
+        // Verify basic structure
+        assert_eq!(payload["model"], "claude-3-7-sonnet-20250219");
+        assert_eq!(payload["messages"][0]["role"], "user");
+        assert_eq!(payload["messages"][0]["content"][0]["text"], "Hello");
+
+        // Verify thinking parameters, with the budget added to the output limit
+        assert_eq!(
+            payload["thinking"],
+            json!({"type": "enabled", "budget_tokens": 4096})
+        );
+        assert_eq!(payload["max_tokens"], json!(8192 + 4096));
 
-        let result = (|| {
-            let model_config = ModelConfig::new_or_fail("claude-3-7-sonnet-20250219");
-            let system = "You are a helpful assistant.";
-            let messages = vec![Message::user().with_text("Hello")];
-            let tools = vec![];
+        // Temperature should not be present for 3.7 models with thinking
+        assert!(payload.get("temperature").is_none());
 
-            let payload = create_request(&model_config, system, &messages, &tools)?;
+        // Claude 4 models think too, and take a temperature again once thinking is off
+        let model_config = ModelConfig {
+            model_name: "claude-opus-4-20250514".to_string(),
+            ..model_config
+        };
+        let payload = create_request(&model_config, system, &messages, &tools)?;
+        assert_eq!(payload["thinking"]["budget_tokens"], json!(4096));
+        assert!(payload.get("temperature").is_none());
+
+        let model_config = model_config.with_thinking(ThinkingConfig::default());
+        let payload = create_request(&model_config, system, &messages, &tools)?;
+        assert!(payload.get("thinking").is_none());
+        assert_eq!(payload["max_tokens"], json!(8192));
+        assert_eq!(payload["temperature"], json!(0.5));
+
+        // Older models don't support thinking, so it is left out even when enabled
+        let model_config = ModelConfig {
+            model_name: "claude-3-5-sonnet-latest".to_string(),
+            ..model_config.with_thinking(thinking)
+        };
+        let payload = create_request(&model_config, system, &messages, &tools)?;
+        assert!(payload.get("thinking").is_none());
+        assert_eq!(payload["max_tokens"], json!(8192));
 
-            // Verify basic structure
-            assert_eq!(payload["model"], "claude-3-7-sonnet-20250219");
-            assert_eq!(payload["messages"][0]["role"], "user");
-            assert_eq!(payload["messages"][0]["content"][0]["text"], "Hello");
+        Ok(())
+    }
 
-            // Verify thinking parameters
-            assert!(payload.get("thinking").is_some());
-            assert_eq!(payload["thinking"]["type"], "enabled");
-            assert!(payload["thinking"]["budget_tokens"].as_i64().unwrap() >= 1024);
+    #[test]
+    fn test_thinking_round_trips_with_signature() {
+        let messages = vec![
+            Message::user().with_text("What is 2 + 2?"),
+            Message::assistant()
+                .with_thinking("Adding the numbers", "c2lnbmF0dXJl")
+                .with_redacted_thinking("ZW5jcnlwdGVk")
+                .with_text("4"),
+            Message::assistant()
+                .with_thinking("From another provider", "")
+                .with_text("Still 4"),
+        ];
 
-            // Temperature should not be present for 3.7 models with thinking
-            assert!(payload.get("temperature").is_none());
+        let spec = format_messages(&messages);
 
-            Ok(())
-        })();
+        assert_eq!(
+            spec[1]["content"][0],
+            json!({"type": "thinking", "thinking": "Adding the numbers", "signature": "c2lnbmF0dXJl"})
+        );
+        assert_eq!(
+            spec[1]["content"][1],
+            json!({"type": "redacted_thinking", "data": "ZW5jcnlwdGVk"})
+        );
+        // Unsigned thinking is dropped, as the API would reject it
+        assert_eq!(spec[2]["content"].as_array().unwrap().len(), 1);
+        assert_eq!(spec[2]["content"][0]["type"], "text");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_thinking_blocks() -> Result<()> {
+        use futures::StreamExt;
+
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-20250514", "usage": {"input_tokens": 10, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": "", "signature": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "Let me "}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "add them."}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "signature_delta", "signature": "c2lnbmF0dXJl"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "redacted_thinking", "data": "ZW5jcnlwdGVk"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "content_block_start", "index": 2, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "text_delta", "text": "4"}}),
+            json!({"type": "content_block_stop", "index": 2}),
+            json!({"type": "message_stop"}),
+        ];
+        let lines: Vec<Result<String>> = events
+            .iter()
+            .map(|event| Ok(format!("data: {}", event)))
+            .collect();
+
+        let messages: Vec<Message> = response_to_streaming_message(futures::stream::iter(lines))
+            .filter_map(|item| async move { item.unwrap().0 })
+            .collect()
+            .await;
+
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| m.id.as_deref() == Some("msg_1")));
+        let MessageContent::Thinking(thinking) = &messages[0].content[0] else {
+            panic!("Expected Thinking content");
+        };
+        assert_eq!(thinking.thinking, "Let me add them.");
+        assert_eq!(thinking.signature, "c2lnbmF0dXJl");
+        let MessageContent::RedactedThinking(redacted) = &messages[1].content[0] else {
+            panic!("Expected RedactedThinking content");
+        };
+        assert_eq!(redacted.data, "ZW5jcnlwdGVk");
+        assert_eq!(messages[2].as_concat_text(), "4");
+
+        Ok(())
+    }
+
+    /// SSE lines for `events`, framed the way the API sends them
+    fn stream_lines(events: &[Value]) -> Vec<Result<String>> {
+        events
+            .iter()
+            .flat_map(|event| {
+                [
+                    format!("event: {}", event["type"].as_str().unwrap()),
+                    format!("data: {}", event),
+                    String::new(),
+                ]
+            })
+            .map(Ok)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_streaming_ignores_ping_events() -> Result<()> {
+        use futures::StreamExt;
+
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-20250514", "usage": {"input_tokens": 10, "output_tokens": 1}}}),
+            json!({"type": "ping"}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}),
+            json!({"type": "ping"}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 2}}),
+            json!({"type": "message_stop"}),
+        ];
 
-        // Restore the original env var state
-        match original_value {
-            Some(val) => std::env::set_var("CLAUDE_THINKING_ENABLED", val),
-            None => std::env::remove_var("CLAUDE_THINKING_ENABLED"),
+        let items: Vec<_> =
+            response_to_streaming_message(futures::stream::iter(stream_lines(&events)))
+                .collect()
+                .await;
+        let items: Vec<_> = items.into_iter().collect::<Result<_>>()?;
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].0.as_ref().unwrap().as_concat_text(), "Hi");
+        let usage = items[1].1.as_ref().unwrap();
+        assert_eq!(usage.usage.output_tokens, Some(2));
+        assert!(!usage.truncated);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_streaming_error_events_are_provider_errors() {
+        use futures::StreamExt;
+
+        for (kind, retryable) in [
+            ("overloaded_error", true),
+            ("rate_limit_error", true),
+            ("invalid_request_error", false),
+        ] {
+            let events = [
+                json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-20250514", "usage": {"input_tokens": 10, "output_tokens": 1}}}),
+                json!({"type": "ping"}),
+                json!({"type": "error", "error": {"type": kind, "message": "Try again later"}}),
+            ];
+
+            let items: Vec<_> =
+                response_to_streaming_message(futures::stream::iter(stream_lines(&events)))
+                    .collect()
+                    .await;
+
+            assert_eq!(items.len(), 1);
+            let error = items
+                .into_iter()
+                .next()
+                .unwrap()
+                .unwrap_err()
+                .downcast::<ProviderError>()
+                .unwrap();
+            assert_eq!(
+                matches!(
+                    error,
+                    ProviderError::ServerError(_) | ProviderError::RateLimitExceeded(_)
+                ),
+                retryable,
+                "{kind}"
+            );
+            assert!(error.to_string().contains("Try again later"));
         }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_max_tokens_marks_usage_truncated() -> Result<()> {
+        use futures::StreamExt;
+
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-20250514", "usage": {"input_tokens": 10, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "The list goes"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "max_tokens"}, "usage": {"output_tokens": 4096}}),
+            json!({"type": "message_stop"}),
+        ];
+
+        let usage = response_to_streaming_message(futures::stream::iter(stream_lines(&events)))
+            .filter_map(|item| async move { item.unwrap().1 })
+            .next()
+            .await
+            .unwrap();
+
+        assert!(usage.truncated);
+        assert_eq!(usage.usage.output_tokens, Some(4096));
+        Ok(())
+    }
+
+    /// A reply that searched the web, in the shape the API returns it
+    fn web_search_response() -> Value {
+        json!({
+            "id": "msg_789",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {
+                    "type": "text",
+                    "text": "I'll look up the latest release."
+                },
+                {
+                    "type": "server_tool_use",
+                    "id": "srvtoolu_01",
+                    "name": "web_search",
+                    "input": {"query": "rust latest release"}
+                },
+                {
+                    "type": "web_search_tool_result",
+                    "tool_use_id": "srvtoolu_01",
+                    "content": [
+                        {
+                            "type": "web_search_result",
+                            "url": "https://blog.rust-lang.org/releases/latest",
+                            "title": "Announcing Rust",
+                            "encrypted_content": "RXhhbXBsZSBjb250ZW50",
+                            "page_age": "2 days ago"
+                        }
+                    ]
+                },
+                {
+                    "type": "text",
+                    "text": "The latest stable release came out this week.",
+                    "citations": [
+                        {
+                            "type": "web_search_result_location",
+                            "url": "https://blog.rust-lang.org/releases/latest",
+                            "title": "Announcing Rust",
+                            "encrypted_index": "RW5jcnlwdGVk",
+                            "cited_text": "The Rust team is happy to announce a new version"
+                        }
+                    ]
+                }
+            ],
+            "model": "claude-sonnet-4-20250514",
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 2100,
+                "output_tokens": 80,
+                "cache_creation_input_tokens": 0,
+                "cache_read_input_tokens": 0,
+                "server_tool_use": {"web_search_requests": 1}
+            }
+        })
+    }
+
+    #[test]
+    fn test_parse_web_search_response() -> Result<()> {
+        let response = web_search_response();
+        let message = response_to_message(&response)?;
+        let usage = get_usage(&response)?;
+
+        assert_eq!(message.content.len(), 4);
+        let MessageContent::ServerToolUse(tool_use) = &message.content[1] else {
+            panic!("Expected ServerToolUse content at index 1");
+        };
+        assert_eq!(tool_use.id, "srvtoolu_01");
+        assert_eq!(tool_use.name, "web_search");
+        assert_eq!(tool_use.input, json!({"query": "rust latest release"}));
+        assert_eq!(
+            tool_use.result.as_ref(),
+            Some(&response["content"][2]),
+            "the result block is kept as it came"
+        );
+        assert_eq!(
+            message.content[2].as_text(),
+            Some("The latest stable release came out this week.")
+        );
+        let MessageContent::Citations(citations) = &message.content[3] else {
+            panic!("Expected Citations content at index 3");
+        };
+        assert_eq!(
+            citations.citations,
+            vec![Citation {
+                url: "https://blog.rust-lang.org/releases/latest".to_string(),
+                title: Some("Announcing Rust".to_string()),
+                cited_text: Some("The Rust team is happy to announce a new version".to_string()),
+            }]
+        );
 
-        // Return the test result
-        result
+        assert_eq!(usage.input_tokens, Some(2100));
+        assert_eq!(usage.total_tokens, Some(2180));
+        assert_eq!(usage.web_search_requests, Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_web_search_round_trips() -> Result<()> {
+        let response = web_search_response();
+        let messages = vec![
+            Message::user().with_text("What's the latest Rust release?"),
+            response_to_message(&response)?,
+            Message::user().with_text("Thanks"),
+        ];
+
+        let spec = format_messages(&messages);
+        let content = spec[1]["content"].as_array().unwrap();
+        let types: Vec<&str> = content
+            .iter()
+            .map(|c| c["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec!["text", "server_tool_use", "web_search_tool_result", "text"]
+        );
+        assert_eq!(content[1]["input"], json!({"query": "rust latest release"}));
+        assert_eq!(content[2], response["content"][2]);
+
+        // Without its result, as from another provider, the tool use is left out
+        let messages = vec![Message::assistant().with_text("Searched").with_content(
+            MessageContent::server_tool_use("search_1", "web_search", json!({}), None),
+        )];
+        let spec = format_messages(&messages);
+        assert_eq!(spec[0]["content"].as_array().unwrap().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_with_native_tools() -> Result<()> {
+        let messages = vec![Message::user().with_text("Hello")];
+        let tool = Tool::new(
+            "developer__shell",
+            "Run a command",
+            object!({"type": "object", "properties": {}}),
+        );
+        let model_config = ModelConfig {
+            model_name: "claude-sonnet-4-20250514".to_string(),
+            context_limit: None,
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop_sequences: None,
+            thinking: ThinkingConfig::default(),
+            toolshim: false,
+            toolshim_model: None,
+            native_tools: vec![NativeTool::WebSearch, NativeTool::CodeExecution],
+        };
+
+        let payload = create_request(&model_config, "system", &messages, &[tool])?;
+        let tools = payload["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 3);
+        assert_eq!(
+            tools[0],
+            json!({"type": "web_search_20250305", "name": "web_search"})
+        );
+        assert_eq!(
+            tools[1],
+            json!({"type": "code_execution_20250522", "name": "code_execution"})
+        );
+        assert_eq!(tools[2]["name"], "developer__shell");
+        assert!(tools[2].get(CACHE_CONTROL_FIELD).is_some());
+
+        // An extension tool with the same name keeps it, the native one is left out
+        let clashing = Tool::new(
+            "web_search",
+            "Search with an extension",
+            object!({"type": "object", "properties": {}}),
+        );
+        let payload = create_request(&model_config, "system", &messages, &[clashing])?;
+        let names: Vec<&str> = payload["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["code_execution", "web_search"]);
+        assert!(payload["tools"][1].get("input_schema").is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_streaming_web_search() -> Result<()> {
+        use futures::StreamExt;
+
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_2", "model": "claude-sonnet-4-20250514", "usage": {"input_tokens": 400, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "server_tool_use", "id": "srvtoolu_02", "name": "web_search", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "input_json_delta", "partial_json": "{\"query\": "}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "input_json_delta", "partial_json": "\"weather\"}"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_02", "content": [{"type": "web_search_result", "url": "https://weather.example.com", "title": "Weather", "encrypted_content": "ZW5j"}]}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "content_block_start", "index": 2, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "citations_delta", "citation": {"type": "web_search_result_location", "url": "https://weather.example.com", "title": "Weather", "encrypted_index": "aWR4", "cited_text": "Sunny"}}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "text_delta", "text": "It's sunny."}}),
+            json!({"type": "content_block_stop", "index": 2}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"input_tokens": 1800, "output_tokens": 40, "server_tool_use": {"web_search_requests": 1}}}),
+            json!({"type": "message_stop"}),
+        ];
+
+        let items: Vec<_> =
+            response_to_streaming_message(futures::stream::iter(stream_lines(&events)))
+                .map(|item| item.unwrap())
+                .collect()
+                .await;
+        let messages: Vec<&Message> = items.iter().filter_map(|(m, _)| m.as_ref()).collect();
+
+        assert_eq!(messages.len(), 3);
+        let MessageContent::ServerToolUse(tool_use) = &messages[0].content[0] else {
+            panic!("Expected ServerToolUse content");
+        };
+        assert_eq!(tool_use.input, json!({"query": "weather"}));
+        assert_eq!(
+            tool_use.result.as_ref().map(|r| r["type"].clone()),
+            Some(json!("web_search_tool_result"))
+        );
+        assert_eq!(messages[1].as_concat_text(), "It's sunny.");
+        let MessageContent::Citations(citations) = &messages[2].content[0] else {
+            panic!("Expected Citations content");
+        };
+        assert_eq!(citations.citations[0].cited_text.as_deref(), Some("Sunny"));
+
+        // The search results grow the input, so the final count comes from the delta
+        let usage = items.last().unwrap().1.as_ref().unwrap();
+        assert_eq!(usage.usage.input_tokens, Some(1800));
+        assert_eq!(usage.usage.output_tokens, Some(40));
+        assert_eq!(usage.usage.web_search_requests, Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_sampling_settings() -> Result<()> {
+        let messages = vec![Message::user().with_text("Hello")];
+        let model_config = ModelConfig {
+            model_name: "claude-sonnet-4-20250514".to_string(),
+            context_limit: None,
+            temperature: Some(1.5),
+            max_tokens: Some(512),
+            top_p: Some(0.9),
+            stop_sequences: Some(vec!["END".to_string()]),
+            thinking: Default::default(),
+            toolshim: false,
+            toolshim_model: None,
+            native_tools: Vec::new(),
+        };
+        let payload = create_request(&model_config, "", &messages, &[])?;
+        assert_eq!(payload["temperature"], json!(1.0));
+        assert!(payload.get("top_p").is_none());
+        assert_eq!(payload["max_tokens"], json!(512));
+        assert_eq!(payload["stop_sequences"], json!(["END"]));
+
+        let model_config = ModelConfig {
+            temperature: None,
+            ..model_config
+        };
+        let payload = create_request(&model_config, "", &messages, &[])?;
+        assert!(payload.get("temperature").is_none());
+        assert_eq!(payload["top_p"], json!(0.9f32));
+
+        // Only the default token limit is sent when the settings are unset
+        let model_config = ModelConfig {
+            max_tokens: None,
+            top_p: None,
+            stop_sequences: None,
+            ..model_config
+        };
+        let payload = create_request(&model_config, "", &messages, &[])?;
+        let mut keys: Vec<&String> = payload.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["max_tokens", "messages", "model"]);
+        assert_eq!(payload["max_tokens"], json!(8192));
+
+        Ok(())
     }
 
     #[test]
@@ -44,12 +44,20 @@ pub fn to_bedrock_message_content(content: &MessageContent) -> Result<bedrock::C
             // Redacted thinking blocks are not supported in Bedrock - skip
             bedrock::ContentBlock::Text("".to_string())
         }
+        MessageContent::ServerToolUse(_) | MessageContent::Citations(_) => {
+            // Tools other providers ran and their citations are not supported in Bedrock - skip
+            bedrock::ContentBlock::Text("".to_string())
+        }
         MessageContent::ContextLengthExceeded(_) => {
             bail!("ContextLengthExceeded should not get passed to the provider")
         }
         MessageContent::SummarizationRequested(_) => {
             bail!("SummarizationRequested should not get passed to the provider")
         }
+        MessageContent::Attachment(_) => {
+            // Attachments are resolved into their contents before reaching the provider - skip
+            bedrock::ContentBlock::Text("".to_string())
+        }
         MessageContent::ToolRequest(tool_req) => {
             let tool_use_id = tool_req.id.to_string();
             let tool_use = if let Ok(call) = tool_req.tool_call.as_ref() {
@@ -327,6 +335,7 @@ pub fn from_bedrock_usage(usage: &bedrock::TokenUsage) -> Usage {
         input_tokens: Some(usage.input_tokens),
         output_tokens: Some(usage.output_tokens),
         total_tokens: Some(usage.total_tokens),
+        web_search_requests: None,
     }
 }
 
@@ -115,6 +115,14 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                 MessageContent::SummarizationRequested(_) => {
                     continue;
                 }
+                MessageContent::Attachment(_) => {
+                    // Attachments are resolved into their contents before reaching the provider
+                    continue;
+                }
+                MessageContent::ServerToolUse(_) | MessageContent::Citations(_) => {
+                    // Tools other providers ran and their citations can't be sent here
+                    continue;
+                }
                 MessageContent::ToolResponse(response) => {
                     match &response.tool_result {
                         Ok(contents) => {
@@ -159,11 +167,13 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                                     }
                                 }
                             }
-                            let tool_response_content: Value = json!(tool_content
-                                .iter()
-                                .filter_map(|content| content.as_text().map(|t| t.text.clone()))
-                                .collect::<Vec<String>>()
-                                .join(" "));
+                            // Structured content is already clean JSON, so prefer it over the text blocks
+                            let tool_response_content: Value =
+                                json!(response.structured_text().unwrap_or_else(|| tool_content
+                                    .iter()
+                                    .filter_map(|content| content.as_text().map(|t| t.text.clone()))
+                                    .collect::<Vec<String>>()
+                                    .join(" ")));
 
                             // Add tool response as a separate message
                             result.push(json!({
@@ -532,13 +542,8 @@ pub fn create_request(
     }
 
     // Add thinking parameters for Claude 3.7 Sonnet model when requested
-    let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if is_claude_sonnet && is_thinking_enabled {
-        // Minimum budget_tokens is 1024
-        let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
-            .unwrap_or_else(|_| "16000".to_string())
-            .parse()
-            .unwrap_or(16000);
+    if is_claude_sonnet && model_config.thinking.enabled {
+        let budget_tokens = model_config.thinking.budget_tokens;
 
         // For Claude models with thinking enabled, we need to add max_tokens + budget_tokens
         // Default to 8192 (Claude max output) + budget if not specified
@@ -562,13 +567,19 @@ pub fn create_request(
             .unwrap()
             .insert("temperature".to_string(), json!(2));
     } else {
-        // o1, o3 models currently don't support temperature
+        // o1, o3 models currently don't support temperature or top_p
         if !is_o1 && !is_o3 {
             if let Some(temp) = model_config.temperature {
                 payload
                     .as_object_mut()
                     .unwrap()
-                    .insert("temperature".to_string(), json!(temp));
+                    .insert("temperature".to_string(), json!(temp.clamp(0.0, 2.0)));
+            }
+            if let Some(top_p) = model_config.top_p {
+                payload
+                    .as_object_mut()
+                    .unwrap()
+                    .insert("top_p".to_string(), json!(top_p.clamp(0.0, 1.0)));
             }
         }
 
@@ -586,6 +597,17 @@ pub fn create_request(
         }
     }
 
+    if !is_o1 && !is_o3 {
+        if let Some(stop) = model_config.stop_sequences.as_ref() {
+            if !stop.is_empty() {
+                payload
+                    .as_object_mut()
+                    .unwrap()
+                    .insert("stop".to_string(), json!(stop));
+            }
+        }
+    }
+
     Ok(payload)
 }
 
@@ -992,8 +1014,12 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop_sequences: None,
+            thinking: Default::default(),
             toolshim: false,
             toolshim_model: None,
+            native_tools: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1023,8 +1049,12 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop_sequences: None,
+            thinking: Default::default(),
             toolshim: false,
             toolshim_model: None,
+            native_tools: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1055,8 +1085,12 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop_sequences: None,
+            thinking: Default::default(),
             toolshim: false,
             toolshim_model: None,
+            native_tools: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1079,6 +1113,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_create_request_sampling_settings() -> anyhow::Result<()> {
+        let model_config = ModelConfig {
+            model_name: "databricks-meta-llama-3-3-70b-instruct".to_string(),
+            context_limit: None,
+            temperature: Some(3.0),
+            max_tokens: Some(512),
+            top_p: Some(0.9),
+            stop_sequences: Some(vec!["END".to_string()]),
+            thinking: Default::default(),
+            toolshim: false,
+            toolshim_model: None,
+            native_tools: Vec::new(),
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["temperature"], json!(2.0));
+        assert_eq!(request["top_p"], json!(0.9f32));
+        assert_eq!(request["max_tokens"], json!(512));
+        assert_eq!(request["stop"], json!(["END"]));
+
+        let model_config = ModelConfig {
+            model_name: "goose-o3-mini".to_string(),
+            ..model_config
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        let obj = request.as_object().unwrap();
+        assert!(!obj.contains_key("temperature"));
+        assert!(!obj.contains_key("top_p"));
+        assert!(!obj.contains_key("stop"));
+        assert_eq!(request["max_completion_tokens"], json!(512));
+
+        let model_config = ModelConfig {
+            model_name: "databricks-meta-llama-3-3-70b-instruct".to_string(),
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop_sequences: None,
+            ..model_config
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        let mut keys: Vec<&String> = request.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["messages", "model"]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_response_to_message_claude_thinking() -> anyhow::Result<()> {
         let response = json!({
@@ -360,6 +360,7 @@ mod tests {
                 tool_call.name.clone(),
                 tool_call.arguments.clone(),
                 Some("Goose would like to call the above tool. Allow? (y/n):".to_string()),
+                None,
             )],
         )
     }
@@ -1,5 +1,5 @@
-use crate::message::{Message, MessageContent};
-use crate::model::ModelConfig;
+use crate::message::{Citation, Message, MessageContent};
+use crate::model::{ModelConfig, NativeTool};
 use crate::providers::base::{ProviderUsage, Usage};
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file, safely_parse_json,
@@ -33,6 +33,9 @@ struct Delta {
     content: Option<String>,
     role: Option<String>,
     tool_calls: Option<Vec<DeltaToolCall>>,
+    /// Pages the search models cite, usually on the last chunk of content
+    #[serde(default)]
+    annotations: Option<Vec<Value>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -48,9 +51,26 @@ struct StreamingChunk {
     created: Option<i64>,
     id: Option<String>,
     usage: Option<Value>,
+    /// Groq reports usage here rather than in `usage`
+    x_groq: Option<Value>,
     model: String,
 }
 
+impl StreamingChunk {
+    fn provider_usage(&self) -> Option<ProviderUsage> {
+        let usage = self
+            .usage
+            .as_ref()
+            .filter(|usage| !usage.is_null())
+            .or_else(|| self.x_groq.as_ref().and_then(|x_groq| x_groq.get("usage")))
+            .map(get_usage)?;
+        if usage.input_tokens.is_none() && usage.output_tokens.is_none() {
+            return None;
+        }
+        Some(ProviderUsage::new(self.model.clone(), usage))
+    }
+}
+
 /// Convert internal Message format to OpenAI's API message specification
 ///   some openai compatible endpoints use the anthropic image spec at the content level
 ///   even though the message structure is otherwise following openai, the enum switches this
@@ -98,6 +118,14 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                 MessageContent::SummarizationRequested(_) => {
                     continue;
                 }
+                MessageContent::Attachment(_) => {
+                    // Attachments are resolved into their contents before reaching the provider
+                    continue;
+                }
+                MessageContent::ServerToolUse(_) | MessageContent::Citations(_) => {
+                    // The text of the reply already holds what the provider's own tools found
+                    continue;
+                }
                 MessageContent::ToolRequest(request) => match &request.tool_call {
                     Ok(tool_call) => {
                         let sanitized_name = sanitize_function_name(&tool_call.name);
@@ -168,14 +196,16 @@ pub fn format_messages(messages: &[Message], image_format: &ImageFormat) -> Vec<
                                     }
                                 }
                             }
-                            let tool_response_content: Value = json!(tool_content
-                                .iter()
-                                .map(|content| match content.deref() {
-                                    RawContent::Text(text) => text.text.clone(),
-                                    _ => String::new(),
-                                })
-                                .collect::<Vec<String>>()
-                                .join(" "));
+                            // Structured content is already clean JSON, so prefer it over the text blocks
+                            let tool_response_content: Value =
+                                json!(response.structured_text().unwrap_or_else(|| tool_content
+                                    .iter()
+                                    .map(|content| match content.deref() {
+                                        RawContent::Text(text) => text.text.clone(),
+                                        _ => String::new(),
+                                    })
+                                    .collect::<Vec<String>>()
+                                    .join(" ")));
 
                             // First add the tool response with all content
                             output.push(json!({
@@ -276,6 +306,16 @@ pub fn response_to_message(response: &Value) -> anyhow::Result<Message> {
         }
     }
 
+    let citations = parse_annotations(
+        original["annotations"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default(),
+    );
+    if !citations.is_empty() {
+        content.push(MessageContent::citations(citations));
+    }
+
     if let Some(tool_calls) = original.get("tool_calls") {
         if let Some(tool_calls_array) = tool_calls.as_array() {
             for tool_call in tool_calls_array {
@@ -332,6 +372,23 @@ pub fn response_to_message(response: &Value) -> anyhow::Result<Message> {
     ))
 }
 
+/// The `url_citation` annotations search models add to their replies, such as
+/// `{"type": "url_citation", "url_citation": {"url": "...", "title": "..."}}`
+fn parse_annotations(annotations: &[Value]) -> Vec<Citation> {
+    annotations
+        .iter()
+        .filter(|annotation| annotation["type"] == "url_citation")
+        .filter_map(|annotation| {
+            let citation = &annotation["url_citation"];
+            Some(Citation {
+                url: citation["url"].as_str()?.to_string(),
+                title: citation["title"].as_str().map(str::to_string),
+                cited_text: None,
+            })
+        })
+        .collect()
+}
+
 pub fn get_usage(usage: &Value) -> Usage {
     let input_tokens = usage
         .get("prompt_tokens")
@@ -405,7 +462,39 @@ fn strip_data_prefix(line: &str) -> Option<&str> {
     line.strip_prefix("data: ").map(|s| s.trim())
 }
 
+/// Parse a chat completions stream. Usage usually arrives in a chunk of its own after the
+/// last content, so messages are yielded one behind and the usage is attached to the last one.
 pub fn response_to_streaming_message<S>(
+    stream: S,
+) -> impl Stream<Item = anyhow::Result<(Option<Message>, Option<ProviderUsage>)>> + 'static
+where
+    S: Stream<Item = anyhow::Result<String>> + Unpin + Send + 'static,
+{
+    try_stream! {
+        use futures::StreamExt;
+
+        let chunks = streaming_chunks_to_messages(stream);
+        tokio::pin!(chunks);
+        let mut pending: Option<Message> = None;
+        let mut final_usage: Option<ProviderUsage> = None;
+        while let Some(item) = chunks.next().await {
+            let (message, usage) = item?;
+            if usage.is_some() {
+                final_usage = usage;
+            }
+            if let Some(message) = message {
+                if let Some(previous) = pending.replace(message) {
+                    yield (Some(previous), None);
+                }
+            }
+        }
+        if pending.is_some() || final_usage.is_some() {
+            yield (pending, final_usage);
+        }
+    }
+}
+
+fn streaming_chunks_to_messages<S>(
     mut stream: S,
 ) -> impl Stream<Item = anyhow::Result<(Option<Message>, Option<ProviderUsage>)>> + 'static
 where
@@ -428,14 +517,7 @@ where
             let chunk: StreamingChunk = serde_json::from_str(line
                 .ok_or_else(|| anyhow!("unexpected stream format"))?)
                 .map_err(|e| anyhow!("Failed to parse streaming chunk: {}: {:?}", e, &line))?;
-            let model = chunk.model.clone();
-
-            let usage = chunk.usage.as_ref().map(|u| {
-                ProviderUsage {
-                    usage: get_usage(u),
-                    model,
-                }
-            });
+            let mut usage = chunk.provider_usage();
 
             if chunk.choices.is_empty() {
                 yield (None, usage)
@@ -458,6 +540,14 @@ where
                         if let Some(line) = strip_data_prefix(&response_str) {
                             let tool_chunk: StreamingChunk = serde_json::from_str(line)
                                 .map_err(|e| anyhow!("Failed to parse streaming chunk: {}: {:?}", e, &line))?;
+                            if let Some(tool_usage) = tool_chunk.provider_usage() {
+                                usage = Some(tool_usage);
+                            }
+                            // A chunk without choices carries only usage, after the last content
+                            if tool_chunk.choices.is_empty() {
+                                done = true;
+                                continue;
+                            }
 
                             if let Some(delta_tool_calls) = &tool_chunk.choices[0].delta.tool_calls {
                                 for delta_call in delta_tool_calls {
@@ -520,20 +610,27 @@ where
                     }),
                     usage,
                 )
-            } else if let Some(text) = &chunk.choices[0].delta.content {
+            } else if chunk.choices[0].delta.content.is_some() || chunk.choices[0].delta.annotations.is_some() {
+                let delta = &chunk.choices[0].delta;
+                let mut content = Vec::new();
+                if let Some(text) = &delta.content {
+                    content.push(MessageContent::text(text));
+                }
+                let citations = parse_annotations(delta.annotations.as_deref().unwrap_or_default());
+                if !citations.is_empty() {
+                    content.push(MessageContent::citations(citations));
+                }
                 yield (
                     Some(Message {
                         id: chunk.id,
                         role: Role::Assistant,
                         created: chrono::Utc::now().timestamp(),
-                        content: vec![MessageContent::text(text)],
+                        content,
                     }),
-                    if chunk.choices[0].finish_reason.is_some() {
-                        usage
-                    } else {
-                        None
-                    },
+                    usage,
                 )
+            } else if usage.is_some() {
+                yield (None, usage)
             }
         }
     }
@@ -610,13 +707,46 @@ pub fn create_request(
             .unwrap()
             .insert("tools".to_string(), json!(tools_spec));
     }
-    // o1, o3 models currently don't support temperature
+
+    // Chat completions only search the web with the search models, which take it as an option
+    // rather than a tool, so it can't collide with the extensions' tools
+    if model_config.native_tool_enabled(NativeTool::WebSearch) {
+        if model_name.contains("search") {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("web_search_options".to_string(), json!({}));
+        } else {
+            tracing::debug!(
+                "{} can't search the web, leaving web_search out",
+                model_name
+            );
+        }
+    }
+
+    // o1, o3 models currently don't support temperature, top_p or stop sequences
     if !is_ox_model {
         if let Some(temp) = model_config.temperature {
             payload
                 .as_object_mut()
                 .unwrap()
-                .insert("temperature".to_string(), json!(temp));
+                .insert("temperature".to_string(), json!(temp.clamp(0.0, 2.0)));
+        }
+        if let Some(top_p) = model_config.top_p {
+            payload
+                .as_object_mut()
+                .unwrap()
+                .insert("top_p".to_string(), json!(top_p.clamp(0.0, 1.0)));
+        }
+        // OpenAI accepts at most 4 stop sequences
+        if let Some(stop) = model_config.stop_sequences.as_ref() {
+            if !stop.is_empty() {
+                let stop: Vec<&String> = stop.iter().take(4).collect();
+                payload
+                    .as_object_mut()
+                    .unwrap()
+                    .insert("stop".to_string(), json!(stop));
+            }
         }
     }
 
@@ -750,6 +880,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_messages_structured_tool_response() -> anyhow::Result<()> {
+        let message = Message::user().with_structured_tool_response(
+            "tool1",
+            Ok(vec![Content::text("It is 21 degrees")]),
+            Some(json!({"temperature": 21})),
+        );
+        let spec = format_messages(&[message], &ImageFormat::OpenAi);
+
+        assert_eq!(spec.len(), 1);
+        assert_eq!(spec[0]["role"], "tool");
+        assert_eq!(spec[0]["content"], r#"{"temperature":21}"#);
+        Ok(())
+    }
+
     #[test]
     fn test_format_tools() -> anyhow::Result<()> {
         let tool = Tool::new(
@@ -1040,8 +1185,12 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop_sequences: None,
+            thinking: Default::default(),
             toolshim: false,
             toolshim_model: None,
+            native_tools: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1071,8 +1220,12 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop_sequences: None,
+            thinking: Default::default(),
             toolshim: false,
             toolshim_model: None,
+            native_tools: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1103,8 +1256,12 @@ mod tests {
             context_limit: Some(4096),
             temperature: None,
             max_tokens: Some(1024),
+            top_p: None,
+            stop_sequences: None,
+            thinking: Default::default(),
             toolshim: false,
             toolshim_model: None,
+            native_tools: Vec::new(),
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1127,6 +1284,141 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_create_request_web_search() -> anyhow::Result<()> {
+        let model_config = ModelConfig {
+            model_name: "gpt-4o-search-preview".to_string(),
+            context_limit: None,
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop_sequences: None,
+            thinking: Default::default(),
+            toolshim: false,
+            toolshim_model: None,
+            native_tools: vec![NativeTool::WebSearch, NativeTool::CodeExecution],
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["web_search_options"], json!({}));
+        assert!(request.get("tools").is_none());
+
+        // Other models can't search, so the option is left out rather than failing the request
+        let model_config = ModelConfig {
+            model_name: "gpt-4o".to_string(),
+            ..model_config
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert!(request.get("web_search_options").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_to_message_url_citations() -> anyhow::Result<()> {
+        let response = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "It's sunny today.",
+                    "annotations": [{
+                        "type": "url_citation",
+                        "url_citation": {
+                            "start_index": 0,
+                            "end_index": 17,
+                            "url": "https://weather.example.com",
+                            "title": "Weather"
+                        }
+                    }]
+                }
+            }]
+        });
+
+        let message = response_to_message(&response)?;
+        assert_eq!(message.content.len(), 2);
+        assert_eq!(message.content[0].as_text(), Some("It's sunny today."));
+        let MessageContent::Citations(citations) = &message.content[1] else {
+            panic!("Expected Citations content");
+        };
+        assert_eq!(
+            citations.citations,
+            vec![Citation {
+                url: "https://weather.example.com".to_string(),
+                title: Some("Weather".to_string()),
+                cited_text: None,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_streamed_url_citations() -> anyhow::Result<()> {
+        let transcript = r#"
+data: {"id":"chatcmpl-2","model":"gpt-4o-search-preview","choices":[{"index":0,"delta":{"role":"assistant","content":"It's sunny."},"finish_reason":null}],"usage":null}
+data: {"id":"chatcmpl-2","model":"gpt-4o-search-preview","choices":[{"index":0,"delta":{"annotations":[{"type":"url_citation","url_citation":{"start_index":0,"end_index":11,"url":"https://weather.example.com","title":"Weather"}}]},"finish_reason":"stop"}],"usage":null}
+data: [DONE]
+"#;
+        let items = collect_stream(transcript).await?;
+
+        assert_eq!(items.len(), 2);
+        let citations = items[1].0.as_ref().unwrap();
+        let MessageContent::Citations(citations) = &citations.content[0] else {
+            panic!("Expected Citations content");
+        };
+        assert_eq!(citations.citations[0].url, "https://weather.example.com");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_sampling_settings() -> anyhow::Result<()> {
+        let model_config = ModelConfig {
+            model_name: "gpt-4o".to_string(),
+            context_limit: None,
+            temperature: Some(3.5),
+            max_tokens: Some(512),
+            top_p: Some(0.9),
+            stop_sequences: Some(["a", "b", "c", "d", "e"].map(String::from).to_vec()),
+            thinking: Default::default(),
+            toolshim: false,
+            toolshim_model: None,
+            native_tools: Vec::new(),
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["temperature"], json!(2.0));
+        assert_eq!(request["top_p"], json!(0.9f32));
+        assert_eq!(request["max_tokens"], json!(512));
+        assert_eq!(request["stop"], json!(["a", "b", "c", "d"]));
+
+        // Reasoning models reject sampling settings, so only the token limit is sent
+        let model_config = ModelConfig {
+            model_name: "o3".to_string(),
+            ..model_config
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        let obj = request.as_object().unwrap();
+        assert!(!obj.contains_key("temperature"));
+        assert!(!obj.contains_key("top_p"));
+        assert!(!obj.contains_key("stop"));
+        assert_eq!(request["max_completion_tokens"], json!(512));
+
+        // Nothing is added when the settings are unset
+        let model_config = ModelConfig {
+            model_name: "gpt-4o".to_string(),
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop_sequences: None,
+            ..model_config
+        };
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        let mut keys: Vec<&String> = request.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["messages", "model"]);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_streamed_multi_tool_response_to_messages() -> anyhow::Result<()> {
         let response_lines = r#"
@@ -1176,4 +1468,85 @@ data: [DONE]
 
         panic!("Expected tool call message with two calls, but did not see it");
     }
+
+    async fn collect_stream(
+        transcript: &str,
+    ) -> anyhow::Result<Vec<(Option<Message>, Option<ProviderUsage>)>> {
+        let lines: Vec<anyhow::Result<String>> = transcript
+            .lines()
+            .map(|line| Ok(line.to_string()))
+            .collect();
+        let stream = response_to_streaming_message(tokio_stream::iter(lines));
+        pin!(stream);
+        let mut items = Vec::new();
+        while let Some(item) = stream.next().await {
+            items.push(item?);
+        }
+        Ok(items)
+    }
+
+    #[tokio::test]
+    async fn test_streamed_usage_attached_to_last_message() -> anyhow::Result<()> {
+        let transcript = r#"
+data: {"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":"Hello"},"finish_reason":null}],"usage":null}
+data: {"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{"content":" there"},"finish_reason":null}],"usage":null}
+data: {"id":"chatcmpl-1","model":"gpt-4o","choices":[{"index":0,"delta":{},"finish_reason":"stop"}],"usage":null}
+data: {"id":"chatcmpl-1","model":"gpt-4o","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":2,"total_tokens":14}}
+data: [DONE]
+"#;
+        let items = collect_stream(transcript).await?;
+
+        assert_eq!(items.len(), 2);
+        assert!(items[0].1.is_none());
+        let (message, usage) = &items[1];
+        assert_eq!(message.as_ref().unwrap().as_concat_text(), " there");
+        let usage = usage.as_ref().unwrap();
+        assert!(!usage.estimated);
+        assert_eq!(usage.usage.input_tokens, Some(12));
+        assert_eq!(usage.usage.output_tokens, Some(2));
+        assert_eq!(usage.usage.total_tokens, Some(14));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_streamed_tool_call_with_usage_chunk() -> anyhow::Result<()> {
+        let transcript = r#"
+data: {"id":"chatcmpl-2","model":"llama-3.3-70b","choices":[{"index":0,"delta":{"role":"assistant","tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"developer__shell","arguments":""}}]},"finish_reason":null}]}
+data: {"id":"chatcmpl-2","model":"llama-3.3-70b","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"command\": \"ls\"}"}}]},"finish_reason":null}]}
+data: {"id":"chatcmpl-2","model":"llama-3.3-70b","choices":[],"x_groq":{"id":"req_1","usage":{"prompt_tokens":30,"completion_tokens":8,"total_tokens":38}}}
+data: [DONE]
+"#;
+        let items = collect_stream(transcript).await?;
+
+        assert_eq!(items.len(), 1);
+        let (message, usage) = &items[0];
+        let message = message.as_ref().unwrap();
+        let MessageContent::ToolRequest(request) = &message.content[0] else {
+            panic!("Expected ToolRequest content");
+        };
+        assert_eq!(
+            request.tool_call.as_ref().unwrap().arguments,
+            json!({"command": "ls"})
+        );
+        assert_eq!(usage.as_ref().unwrap().usage.total_tokens, Some(38));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_streamed_response_without_usage() -> anyhow::Result<()> {
+        let transcript = r#"
+data: {"id":"chatcmpl-3","model":"gpt-4o","choices":[{"index":0,"delta":{"role":"assistant","content":"Hi"},"finish_reason":null}]}
+data: {"id":"chatcmpl-3","model":"gpt-4o","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}
+data: [DONE]
+"#;
+        let items = collect_stream(transcript).await?;
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].0.as_ref().unwrap().as_concat_text(), "Hi");
+        assert!(items[0].1.is_none());
+
+        Ok(())
+    }
 }
@@ -59,12 +59,18 @@ pub fn format_messages(messages: &[Message]) -> Vec<Value> {
                 MessageContent::SummarizationRequested(_) => {
                     // Skip
                 }
+                MessageContent::Attachment(_) => {
+                    // Attachments are resolved into their contents before reaching the provider
+                }
                 MessageContent::Thinking(_thinking) => {
                     // Skip thinking for now
                 }
                 MessageContent::RedactedThinking(_redacted) => {
                     // Skip redacted thinking for now
                 }
+                MessageContent::ServerToolUse(_) | MessageContent::Citations(_) => {
+                    // Skip tools other providers ran and their citations
+                }
                 MessageContent::Image(_) => continue, // Snowflake doesn't support image content yet
                 MessageContent::FrontendToolRequest(_tool_request) => {
                     // Skip frontend tool requests
@@ -10,7 +10,9 @@ use url::Url;
 
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use crate::providers::base::{
+    sanitized_provider_config, ConfigKey, Provider, ProviderMetadata, ProviderUsage,
+};
 
 use crate::providers::errors::ProviderError;
 use crate::providers::formats::gcpvertexai::{
@@ -625,6 +627,10 @@ impl Provider for GcpVertexAIProvider {
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
+
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
 }
 
 #[cfg(test)]
@@ -2,7 +2,9 @@ use super::errors::ProviderError;
 use crate::impl_provider_default;
 use crate::message::Message;
 use crate::model::ModelConfig;
-use crate::providers::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage};
+use crate::providers::base::{
+    sanitized_provider_config, ConfigKey, Provider, ProviderMetadata, ProviderUsage,
+};
 use crate::providers::formats::google::{create_request, get_usage, response_to_message};
 use crate::providers::utils::{
     emit_debug_trace, handle_response_google_compat, unescape_json_values,
@@ -161,6 +163,10 @@ impl Provider for GoogleProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
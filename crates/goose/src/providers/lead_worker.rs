@@ -326,6 +326,15 @@ impl Provider for LeadWorkerProvider {
         self.lead_provider.get_model_config()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        serde_json::json!({
+            "provider": "lead_worker",
+            "lead_turns": self.lead_turns,
+            "lead": self.lead_provider.sanitized_config(),
+            "worker": self.worker_provider.sanitized_config(),
+        })
+    }
+
     async fn complete(
         &self,
         system: &str,
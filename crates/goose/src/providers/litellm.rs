@@ -6,7 +6,10 @@ use std::collections::HashMap;
 use std::time::Duration;
 use url::Url;
 
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    sanitized_provider_config, ConfigKey, DeviceCodeSettings, ModelInfo, Provider,
+    ProviderMetadata, ProviderUsage, SignupMethod,
+};
 use super::embedding::EmbeddingCapable;
 use super::errors::ProviderError;
 use super::utils::{emit_debug_trace, get_model, handle_response_openai_compat, ImageFormat};
@@ -167,12 +170,23 @@ impl Provider for LiteLLMProvider {
                 ConfigKey::new("LITELLM_TIMEOUT", false, false, Some("600")),
             ],
         )
+        // Proxies behind an OAuth server can issue keys through a device code sign in
+        .with_signup(SignupMethod::DeviceCode(DeviceCodeSettings {
+            host_key: "LITELLM_HOST".to_string(),
+            api_key_key: "LITELLM_API_KEY".to_string(),
+            client_id: "goose".to_string(),
+            scope: None,
+        }))
     }
 
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(skip_all, name = "provider_complete")]
     async fn complete(
         &self,
@@ -3,6 +3,7 @@ pub mod azure;
 pub mod azureauth;
 pub mod base;
 pub mod bedrock;
+pub mod capabilities;
 pub mod claude_code;
 pub mod databricks;
 pub mod embedding;
@@ -26,9 +27,10 @@ pub mod sagemaker_tgi;
 pub mod snowflake;
 pub mod testprovider;
 pub mod toolshim;
+pub mod transcript;
 pub mod utils;
 pub mod utils_universal_openai_stream;
 pub mod venice;
 pub mod xai;
 
-pub use factory::{create, providers};
+pub use factory::{create, create_with_model, providers};
@@ -288,7 +288,36 @@ impl OAuthFlow {
         self.extract_token_data(&token_response, Some(refresh_token))
     }
 
+    /// Sign in without a callback server: the user opens the URL anywhere and pastes back the
+    /// URL they were redirected to, or the code from it
+    async fn execute_headless(&self) -> Result<TokenData> {
+        println!("Open this URL in a browser on any machine to sign in:");
+        println!("{}", self.get_authorization_url());
+        println!("Then paste the URL you were redirected to, or the code from it:");
+
+        let pasted = tokio::time::timeout(
+            std::time::Duration::from_secs(300),
+            tokio::task::spawn_blocking(|| {
+                let mut line = String::new();
+                std::io::stdin().read_line(&mut line).map(|_| line)
+            }),
+        )
+        .await
+        .map_err(|_| anyhow::anyhow!("Authentication timed out"))???;
+
+        let (code, state) = crate::config::parse_pasted_code(&pasted)?;
+        if state.is_some_and(|state| state != self.state) {
+            return Err(anyhow::anyhow!("State mismatch"));
+        }
+
+        self.exchange_code_for_token(&code).await
+    }
+
     async fn execute(&self) -> Result<TokenData> {
+        if crate::config::headless_auth() {
+            return self.execute_headless().await;
+        }
+
         // Create a channel that will send the auth code from the app process
         let (tx, rx) = oneshot::channel();
         let state = self.state.clone();
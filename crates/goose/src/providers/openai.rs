@@ -12,7 +12,10 @@ use tokio_stream::StreamExt;
 use tokio_util::codec::{FramedRead, LinesCodec};
 use tokio_util::io::StreamReader;
 
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    estimate_missing_usage, sanitized_provider_config, ConfigKey, ModelInfo, Provider,
+    ProviderMetadata, ProviderUsage, Usage,
+};
 use super::embedding::{EmbeddingCapable, EmbeddingRequest, EmbeddingResponse};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
@@ -161,6 +164,10 @@ impl Provider for OpenAiProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
@@ -260,7 +267,7 @@ impl Provider for OpenAiProvider {
 
         let model_config = self.model.clone();
         // Wrap in a line decoder and yield lines inside the stream
-        Ok(Box::pin(try_stream! {
+        let reply: MessageStream = Box::pin(try_stream! {
             let stream_reader = StreamReader::new(stream);
             let framed = FramedRead::new(stream_reader, LinesCodec::new()).map_err(anyhow::Error::from);
 
@@ -271,7 +278,14 @@ impl Provider for OpenAiProvider {
                 super::utils::emit_debug_trace(&model_config, &payload, &message, &usage.as_ref().map(|f| f.usage).unwrap_or_default());
                 yield (message, usage);
             }
-        }))
+        });
+        Ok(estimate_missing_usage(
+            reply,
+            self.model.model_name.clone(),
+            system,
+            messages,
+            tools,
+        ))
     }
 }
 
@@ -4,7 +4,10 @@ use reqwest::Client;
 use serde_json::{json, Value};
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    sanitized_provider_config, ConfigKey, Provider, ProviderMetadata, ProviderUsage, SignupMethod,
+    Usage,
+};
 use super::errors::ProviderError;
 use super::utils::{
     emit_debug_trace, get_model, handle_response_google_compat, handle_response_openai_compat,
@@ -244,12 +247,17 @@ impl Provider for OpenRouterProvider {
                 ),
             ],
         )
+        .with_signup(SignupMethod::Pkce)
     }
 
     fn get_model_config(&self) -> ModelConfig {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
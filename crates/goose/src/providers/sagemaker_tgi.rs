@@ -10,7 +10,9 @@ use rmcp::model::Tool;
 use serde_json::{json, Value};
 use tokio::time::sleep;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    sanitized_provider_config, ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
 use super::errors::ProviderError;
 use super::utils::emit_debug_trace;
 use crate::impl_provider_default;
@@ -51,7 +53,11 @@ impl SageMakerTgiProvider {
             }
         };
 
-        set_aws_env_vars(config.load_values());
+        set_aws_env_vars(
+            config
+                .load_effective_values()
+                .map(|effective| effective.values),
+        );
         set_aws_env_vars(config.load_secrets());
 
         let aws_config = futures::executor::block_on(aws_config::load_from_env());
@@ -279,6 +285,10 @@ impl Provider for SageMakerTgiProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     #[tracing::instrument(
         skip(self, system, messages, tools),
         fields(model_config, input, output, input_tokens, output_tokens, total_tokens)
@@ -315,6 +325,7 @@ impl Provider for SageMakerTgiProvider {
                         input_tokens: Some(0),  // Would need to tokenize input to get accurate count
                         output_tokens: Some(0), // Would need to tokenize output to get accurate count
                         total_tokens: Some(0),
+                        web_search_requests: None,
                     };
 
                     // Add debug trace
@@ -0,0 +1,503 @@
+//! Opt-in transcripts of provider calls, for debugging what exactly was sent to a provider and
+//! what came back. With `GOOSE_PROVIDER_TRANSCRIPT_DIR` set, each call a session makes is written
+//! to `{dir}/{session_id}/{index}.json`: the request payload with secrets redacted, the response
+//! or each streamed chunk, timings and the HTTP attempts made. The calls are listed by turn in
+//! `{dir}/{session_id}/calls.jsonl`, which is only ever appended to, so recording a call never
+//! touches the session file the agent is saving.
+//!
+//! Providers don't take part directly. The agent runs each call in a [`scope`], and the helpers
+//! every provider already goes through ([`emit_debug_trace`](super::utils::emit_debug_trace) and
+//! the HTTP status handlers in [`utils`](super::utils)) add to the call in scope.
+
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::Instant;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::providers::base::MessageStream;
+use crate::session::{self, Redactor};
+
+const TRANSCRIPT_DIR_KEY: &str = "GOOSE_PROVIDER_TRANSCRIPT_DIR";
+const TRANSCRIPT_MAX_BYTES_KEY: &str = "GOOSE_PROVIDER_TRANSCRIPT_MAX_BYTES";
+/// Most bytes of request and response kept for one call, past it chunks are left out
+const DEFAULT_MAX_BYTES: usize = 10 * 1024 * 1024;
+/// The calls of a session, one [`ProviderCallRef`] per line
+const CALL_INDEX_FILE: &str = "calls.jsonl";
+
+tokio::task_local! {
+    static CURRENT_CALL: Arc<ProviderTranscript>;
+}
+
+/// A recorded provider call, as listed in the session's call index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ProviderCallRef {
+    /// Position of the call in the session, starting at 1
+    pub index: usize,
+    /// The user turn the call was made for, starting at 0
+    pub turn: usize,
+    pub model: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Number of HTTP requests made, more than one when the call was retried
+    pub attempts: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The transcript file
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+}
+
+/// One HTTP request made for a call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpAttempt {
+    /// Time since the call started
+    pub after_ms: u64,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Everything recorded about one provider call, as written to its transcript file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderCall {
+    pub index: usize,
+    pub session_id: String,
+    pub turn: usize,
+    pub provider: Option<String>,
+    pub model: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Time until the first response or chunk arrived
+    pub first_response_ms: Option<u64>,
+    pub attempts: Vec<HttpAttempt>,
+    /// The request payload, with secrets redacted
+    pub request: Option<Value>,
+    /// The response, or each chunk of a streamed response
+    pub responses: Vec<Value>,
+    pub error: Option<String>,
+    /// Whether parts were left out to stay within the size limit
+    pub truncated: bool,
+    #[serde(skip)]
+    bytes: usize,
+}
+
+/// The recording of a call in progress. The transcript is written, and added to the session's
+/// call index, when the last handle to it is dropped.
+pub struct ProviderTranscript {
+    path: PathBuf,
+    max_bytes: usize,
+    started: Instant,
+    redactor: Redactor,
+    call: Mutex<ProviderCall>,
+}
+
+/// Where transcripts are written, if they are turned on
+fn transcript_dir() -> Option<PathBuf> {
+    Config::global()
+        .get_param::<String>(TRANSCRIPT_DIR_KEY)
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+fn session_transcript_dir(dir: &Path, session_id: &str) -> Result<PathBuf> {
+    session::artifacts::validate_id(session_id)?;
+    Ok(dir.join(session_id))
+}
+
+/// Fields that hold credentials in some providers' payloads
+const SECRET_FIELDS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "api-key",
+    "x-api-key",
+    "authorization",
+    "access_token",
+    "refresh_token",
+    "client_secret",
+    "password",
+];
+
+fn strip_secret_fields(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    if SECRET_FIELDS.contains(&key.to_lowercase().as_str()) {
+                        (key, Value::String("[REDACTED]".to_string()))
+                    } else {
+                        (key, strip_secret_fields(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(strip_secret_fields).collect()),
+        value => value,
+    }
+}
+
+/// Index the next call of a session gets, one past the highest already written
+fn next_index(dir: &Path) -> usize {
+    let highest = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?.strip_suffix(".json")?.parse::<usize>().ok()
+        })
+        .max();
+    highest.unwrap_or(0) + 1
+}
+
+impl ProviderTranscript {
+    /// Start recording a call of `session_id`, or `None` when transcripts are off
+    pub fn begin(
+        session_id: &str,
+        turn: usize,
+        provider: Option<&str>,
+        model: &str,
+    ) -> Option<Arc<Self>> {
+        let dir = transcript_dir()?;
+        let max_bytes = Config::global()
+            .get_param::<usize>(TRANSCRIPT_MAX_BYTES_KEY)
+            .unwrap_or(DEFAULT_MAX_BYTES);
+        Self::begin_in(&dir, max_bytes, session_id, turn, provider, model)
+    }
+
+    fn begin_in(
+        dir: &Path,
+        max_bytes: usize,
+        session_id: &str,
+        turn: usize,
+        provider: Option<&str>,
+        model: &str,
+    ) -> Option<Arc<Self>> {
+        let dir = match session_transcript_dir(dir, session_id) {
+            Ok(dir) => dir,
+            Err(e) => {
+                tracing::warn!("Not recording a provider call of {}: {}", session_id, e);
+                return None;
+            }
+        };
+        let index = next_index(&dir);
+        Some(Arc::new(Self {
+            path: dir.join(format!("{:04}.json", index)),
+            max_bytes,
+            started: Instant::now(),
+            redactor: Redactor::from_config(),
+            call: Mutex::new(ProviderCall {
+                index,
+                session_id: session_id.to_string(),
+                turn,
+                provider: provider.map(str::to_string),
+                model: model.to_string(),
+                started_at: Utc::now(),
+                ..ProviderCall::default()
+            }),
+        }))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProviderCall> {
+        self.call
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Keep `value` if it fits in what's left of the size limit
+    fn fit(&self, call: &mut ProviderCall, value: Value) -> Option<Value> {
+        let size = serde_json::to_string(&value).map_or(0, |text| text.len());
+        if call.bytes + size > self.max_bytes {
+            call.truncated = true;
+            return None;
+        }
+        call.bytes += size;
+        Some(self.redactor.redact_value(&strip_secret_fields(value)))
+    }
+
+    fn record_exchange<T1, T2>(&self, payload: &T1, response: &T2)
+    where
+        T1: ?Sized + Serialize,
+        T2: ?Sized + Serialize,
+    {
+        let elapsed = self.elapsed_ms();
+        let mut call = self.lock();
+        // Streamed calls report the same payload with every chunk
+        if call.request.is_none() && !call.truncated {
+            if let Ok(payload) = serde_json::to_value(payload) {
+                call.request = self.fit(&mut call, payload);
+            }
+        }
+        call.first_response_ms.get_or_insert(elapsed);
+        match serde_json::to_value(response) {
+            Ok(Value::Null) | Err(_) => {}
+            Ok(response) => {
+                if let Some(response) = self.fit(&mut call, response) {
+                    call.responses.push(response);
+                }
+            }
+        }
+    }
+
+    fn record_attempt(&self, status: StatusCode, error: Option<String>) {
+        let after_ms = self.elapsed_ms();
+        self.lock().attempts.push(HttpAttempt {
+            after_ms,
+            status: status.as_u16(),
+            error: error.map(|error| self.redactor.redact_text(&error).into_owned()),
+        });
+    }
+
+    /// Record the error the call ended with
+    pub fn record_error(&self, error: &str) {
+        self.lock().error = Some(self.redactor.redact_text(error).into_owned());
+    }
+
+    fn write(&self) -> Result<ProviderCallRef> {
+        let mut call = self.lock();
+        call.duration_ms = self.elapsed_ms();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&*call)?)?;
+        let call_ref = ProviderCallRef {
+            index: call.index,
+            turn: call.turn,
+            model: call.model.clone(),
+            started_at: call.started_at,
+            duration_ms: call.duration_ms,
+            attempts: call.attempts.len(),
+            error: call.error.clone(),
+            path: self.path.clone(),
+        };
+        if let Some(dir) = self.path.parent() {
+            append_to_index(dir, &call_ref)?;
+        }
+        Ok(call_ref)
+    }
+}
+
+/// Add `call` to the index in a session's transcript directory, as a line of its own
+fn append_to_index(dir: &Path, call: &ProviderCallRef) -> Result<()> {
+    let mut line = serde_json::to_vec(call)?;
+    line.push(b'\n');
+    let mut index = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(CALL_INDEX_FILE))?;
+    index.write_all(&line)?;
+    Ok(())
+}
+
+fn read_index(dir: &Path) -> Result<Vec<ProviderCallRef>> {
+    let path = dir.join(CALL_INDEX_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut calls = Vec::new();
+    for line in BufReader::new(std::fs::File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // A line cut short by a crash loses that call, not the ones after it
+        match serde_json::from_str(&line) {
+            Ok(call) => calls.push(call),
+            Err(e) => tracing::warn!("Skipping a provider call in {}: {}", dir.display(), e),
+        }
+    }
+    Ok(calls)
+}
+
+/// The provider calls recorded for a session, oldest first. Empty when transcripts are off.
+pub fn list_calls(session_id: &str) -> Result<Vec<ProviderCallRef>> {
+    let Some(dir) = transcript_dir() else {
+        return Ok(Vec::new());
+    };
+    read_index(&session_transcript_dir(&dir, session_id)?)
+}
+
+impl Drop for ProviderTranscript {
+    fn drop(&mut self) {
+        if let Err(e) = self.write() {
+            let session_id = self.lock().session_id.clone();
+            tracing::warn!(
+                "Failed to write the provider transcript of {}: {}",
+                session_id,
+                e
+            );
+        }
+    }
+}
+
+/// Run `future`, a provider call, with `transcript` recording it
+pub async fn scope<F: Future>(transcript: Option<Arc<ProviderTranscript>>, future: F) -> F::Output {
+    match transcript {
+        Some(transcript) => CURRENT_CALL.scope(transcript, future).await,
+        None => future.await,
+    }
+}
+
+/// Poll `stream`, the response of a provider call, with `transcript` recording it
+pub fn scope_stream(
+    transcript: Option<Arc<ProviderTranscript>>,
+    mut stream: MessageStream,
+) -> MessageStream {
+    let Some(transcript) = transcript else {
+        return stream;
+    };
+    Box::pin(futures::stream::poll_fn(move |cx| {
+        let polled =
+            CURRENT_CALL.sync_scope(Arc::clone(&transcript), || stream.poll_next_unpin(cx));
+        if let Poll::Ready(Some(Err(e))) = &polled {
+            transcript.record_error(&e.to_string());
+        }
+        polled
+    }))
+}
+
+fn with_current(f: impl FnOnce(&ProviderTranscript)) {
+    let _ = CURRENT_CALL.try_with(|transcript| f(transcript));
+}
+
+/// Add a request and its response, or one chunk of it, to the call in scope
+pub(crate) fn record_exchange<T1, T2>(payload: &T1, response: &T2)
+where
+    T1: ?Sized + Serialize,
+    T2: ?Sized + Serialize,
+{
+    with_current(|transcript| transcript.record_exchange(payload, response));
+}
+
+/// Add an HTTP request to the call in scope, with the error it failed with if any
+pub(crate) fn record_attempt(status: StatusCode, error: Option<String>) {
+    with_current(|transcript| transcript.record_attempt(status, error));
+}
+
+/// Remove the transcripts of a session
+pub fn remove_transcripts(session_id: &str) -> Result<()> {
+    let Some(dir) = transcript_dir() else {
+        return Ok(());
+    };
+    let dir = session_transcript_dir(&dir, session_id)?;
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_records_the_call_in_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcript = ProviderTranscript::begin_in(
+            dir.path(),
+            DEFAULT_MAX_BYTES,
+            "transcript-test",
+            2,
+            Some("openai"),
+            "gpt-4o",
+        )
+        .unwrap();
+
+        // Outside a scope nothing is recorded
+        record_exchange(&json!({"model": "other"}), &json!({}));
+
+        let payload = json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "my api_key=sk-abcdef123456"}],
+            "metadata": {"Authorization": "Bearer sk-abcdef123456"}
+        });
+        scope(Some(Arc::clone(&transcript)), async {
+            record_attempt(StatusCode::TOO_MANY_REQUESTS, Some("slow down".into()));
+            record_attempt(StatusCode::OK, None);
+            record_exchange(&payload, &json!({"choices": []}));
+        })
+        .await;
+
+        let path = transcript.path.clone();
+        let call = transcript.write().unwrap();
+        assert_eq!(call.index, 1);
+        assert_eq!(call.turn, 2);
+        assert_eq!(call.attempts, 2);
+
+        let written: ProviderCall =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let request = written.request.unwrap().to_string();
+        assert!(request.contains("REDACTED"));
+        assert!(!request.contains("sk-abcdef123456"));
+        assert_eq!(written.responses, vec![json!({"choices": []})]);
+        assert_eq!(written.attempts[0].status, 429);
+        assert_eq!(next_index(path.parent().unwrap()), 2);
+        assert_eq!(read_index(path.parent().unwrap()).unwrap(), vec![call]);
+    }
+
+    #[test]
+    fn test_dropped_calls_are_appended_to_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        for turn in 0..2 {
+            let transcript = ProviderTranscript::begin_in(
+                dir.path(),
+                DEFAULT_MAX_BYTES,
+                "transcript-index",
+                turn,
+                None,
+                "gpt-4o",
+            )
+            .unwrap();
+            transcript.record_error("boom");
+        }
+
+        let session_dir = dir.path().join("transcript-index");
+        // A line cut short by a crash is skipped
+        let mut index = std::fs::OpenOptions::new()
+            .append(true)
+            .open(session_dir.join(CALL_INDEX_FILE))
+            .unwrap();
+        index.write_all(b"{\"index\": 3\n").unwrap();
+
+        let calls = read_index(&session_dir).unwrap();
+        assert_eq!(
+            calls
+                .iter()
+                .map(|call| (call.index, call.turn))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (2, 1)]
+        );
+        assert_eq!(calls[1].error.as_deref(), Some("boom"));
+        assert!(read_index(&dir.path().join("no-calls")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_size_limit_leaves_out_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcript =
+            ProviderTranscript::begin_in(dir.path(), 64, "transcript-limit", 0, None, "gpt-4o")
+                .unwrap();
+
+        transcript.record_exchange(&json!({"model": "gpt-4o"}), &json!("first chunk"));
+        transcript.record_exchange(&json!({"model": "gpt-4o"}), &json!("x".repeat(100)));
+
+        let call = transcript.lock().clone();
+        assert_eq!(call.responses, vec![json!("first chunk")]);
+        assert!(call.truncated);
+    }
+}
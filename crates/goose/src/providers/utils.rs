@@ -1,5 +1,6 @@
 use super::base::Usage;
 use super::errors::GoogleErrorCode;
+use super::transcript;
 use crate::model::ModelConfig;
 use anyhow::Result;
 use base64::Engine;
@@ -51,9 +52,19 @@ pub async fn handle_status_openai_compat(response: Response) -> Result<Response,
     let status = response.status();
 
     match status {
-        StatusCode::OK => Ok(response),
+        StatusCode::OK => {
+            transcript::record_attempt(status, None);
+            Ok(response)
+        }
         _ => {
             let body = response.json::<Value>().await;
+            transcript::record_attempt(
+                status,
+                Some(match &body {
+                    Ok(body) => body.to_string(),
+                    Err(e) => e.to_string(),
+                }),
+            );
             match (body, status) {
                 (Err(e), _) => Err(ProviderError::RequestFailed(e.to_string())),
                 (Ok(body), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
@@ -90,6 +101,50 @@ pub async fn handle_status_openai_compat(response: Response) -> Result<Response,
     }
 }
 
+/// Map a failed response from `provider` to an error by its status. Rejected credentials become
+/// [`ProviderError::AuthenticationFailed`] naming the provider, so the user can be sent to
+/// sign in again rather than being told the provider is down.
+pub fn map_http_error_to_provider_error(
+    provider: &str,
+    status: StatusCode,
+    payload: Option<Value>,
+) -> ProviderError {
+    let message = payload
+        .as_ref()
+        .and_then(|payload| {
+            let error = payload.get("error").unwrap_or(payload);
+            error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .or_else(|| error.as_str())
+                .map(String::from)
+        })
+        .unwrap_or_else(|| format!("{:?}", payload));
+    transcript::record_attempt(status, Some(message.clone()));
+
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ProviderError::AuthenticationFailed {
+            provider: provider.to_string(),
+            message: format!("{} (status {})", message, status.as_u16()),
+        },
+        StatusCode::TOO_MANY_REQUESTS => ProviderError::RateLimitExceeded(message),
+        StatusCode::INTERNAL_SERVER_ERROR | StatusCode::SERVICE_UNAVAILABLE => {
+            ProviderError::ServerError(message)
+        }
+        _ => {
+            tracing::debug!(
+                "Provider request failed with status: {}. Payload: {:?}",
+                status,
+                payload
+            );
+            ProviderError::RequestFailed(format!(
+                "Request failed with status: {}. Message: {}",
+                status, message
+            ))
+        }
+    }
+}
+
 pub async fn handle_response_openai_compat(response: Response) -> Result<Value, ProviderError> {
     let response = handle_status_openai_compat(response).await?;
 
@@ -337,6 +392,7 @@ pub fn emit_debug_trace<T1, T2>(
     T1: ?Sized + Serialize,
     T2: ?Sized + Serialize,
 {
+    transcript::record_exchange(payload, response);
     tracing::debug!(
         model_config = %serde_json::to_string_pretty(model_config).unwrap_or_default(),
         input = %serde_json::to_string_pretty(payload).unwrap_or_default(),
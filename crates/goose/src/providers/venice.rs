@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::time::Duration;
 
-use super::base::{ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    sanitized_provider_config, ConfigKey, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
 use super::errors::ProviderError;
 use crate::impl_provider_default;
 use crate::message::{Message, MessageContent};
@@ -247,6 +249,10 @@ impl Provider for VeniceProvider {
         self.model.clone()
     }
 
+    fn sanitized_config(&self) -> serde_json::Value {
+        sanitized_provider_config(self)
+    }
+
     async fn fetch_supported_models_async(&self) -> Result<Option<Vec<String>>, ProviderError> {
         // Fetch supported models via Venice API
         let base_url = url::Url::parse(&self.host)
@@ -551,6 +557,7 @@ impl Provider for VeniceProvider {
             input_tokens: usage_data["prompt_tokens"].as_i64().map(|v| v as i32),
             output_tokens: usage_data["completion_tokens"].as_i64().map(|v| v as i32),
             total_tokens: usage_data["total_tokens"].as_i64().map(|v| v as i32),
+            web_search_requests: None,
         };
 
         Ok((
@@ -1,16 +1,23 @@
 use crate::recipe::read_recipe_file_content::RecipeFile;
 use crate::recipe::template_recipe::{parse_recipe_content, render_recipe_content_with_params};
 use crate::recipe::{
-    Recipe, RecipeParameter, RecipeParameterRequirement, BUILT_IN_RECIPE_DIR_PARAM,
+    Recipe, RecipeParameter, RecipeParameterInputType, RecipeParameterRequirement,
+    BUILT_IN_RECIPE_DIR_PARAM,
 };
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::Path;
+use utoipa::ToSchema;
 
 #[derive(Debug, thiserror::Error)]
 pub enum RecipeError {
     #[error("Missing required parameters: {parameters:?}")]
     MissingParams { parameters: Vec<String> },
+    #[error("Invalid parameters: {}", join_parameter_errors(errors))]
+    InvalidParams { errors: Vec<ParameterError> },
     #[error("Template rendering failed: {source}")]
     TemplateRendering { source: anyhow::Error },
     #[error("Recipe parsing failed: {source}")]
@@ -93,6 +100,159 @@ where
     Ok(recipe)
 }
 
+/// A parameter value that is missing or doesn't fit the parameter's declaration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ParameterError {
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+fn join_parameter_errors(errors: &[ParameterError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Build a recipe for a run that isn't started from the CLI, such as from the desktop app or
+/// the scheduler. The values are checked and coerced against the declared parameters, and
+/// defaults fill in the values that aren't given.
+pub fn build_recipe_with_values(
+    recipe_file: RecipeFile,
+    values: &HashMap<String, String>,
+) -> Result<Recipe, RecipeError> {
+    let recipe_dir_str = recipe_file
+        .parent_dir
+        .to_str()
+        .ok_or_else(|| RecipeError::RecipeParsing {
+            source: anyhow::anyhow!("Error getting recipe directory"),
+        })?
+        .to_string();
+    let recipe_parameters = validate_recipe_parameters(&recipe_file.content, &recipe_dir_str)
+        .map_err(|source| RecipeError::RecipeParsing { source })?;
+    let mut params_for_template =
+        coerce_parameter_values(recipe_parameters.as_deref().unwrap_or_default(), values)
+            .map_err(|errors| RecipeError::InvalidParams { errors })?;
+    params_for_template.insert(BUILT_IN_RECIPE_DIR_PARAM.to_string(), recipe_dir_str);
+
+    let rendered_content =
+        render_recipe_content_with_params(&recipe_file.content, &params_for_template)
+            .map_err(|source| RecipeError::TemplateRendering { source })?;
+    let mut recipe = Recipe::from_content(&rendered_content)
+        .map_err(|source| RecipeError::RecipeParsing { source })?;
+
+    if let Some(ref mut sub_recipes) = recipe.sub_recipes {
+        for sub_recipe in sub_recipes {
+            if let Ok(resolved_path) =
+                resolve_sub_recipe_path(&sub_recipe.path, &recipe_file.parent_dir)
+            {
+                sub_recipe.path = resolved_path;
+            }
+        }
+    }
+
+    Ok(recipe)
+}
+
+/// Check `values` against the declared parameters and coerce them to their input types.
+/// Blank values count as missing, and missing values take the parameter's default. Every
+/// offending parameter is reported, not just the first.
+pub fn coerce_parameter_values(
+    parameters: &[RecipeParameter],
+    values: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, Vec<ParameterError>> {
+    let mut coerced = HashMap::new();
+    let mut errors = Vec::new();
+
+    for parameter in parameters {
+        let given = values
+            .get(&parameter.key)
+            .filter(|value| !value.trim().is_empty());
+        let value = match (given, &parameter.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => {
+                coerced.insert(parameter.key.clone(), default.clone());
+                continue;
+            }
+            (None, None) => {
+                if !matches!(parameter.requirement, RecipeParameterRequirement::Optional) {
+                    errors.push(ParameterError {
+                        key: parameter.key.clone(),
+                        message: "A value is required".to_string(),
+                    });
+                }
+                continue;
+            }
+        };
+        match coerce_value(parameter, value) {
+            Ok(value) => {
+                coerced.insert(parameter.key.clone(), value);
+            }
+            Err(message) => errors.push(ParameterError {
+                key: parameter.key.clone(),
+                message,
+            }),
+        }
+    }
+
+    let mut unknown: Vec<&String> = values
+        .keys()
+        .filter(|key| key.as_str() != BUILT_IN_RECIPE_DIR_PARAM)
+        .filter(|key| !parameters.iter().any(|parameter| &parameter.key == *key))
+        .collect();
+    unknown.sort();
+    errors.extend(unknown.into_iter().map(|key| ParameterError {
+        key: key.clone(),
+        message: "Not a parameter of this recipe".to_string(),
+    }));
+
+    if errors.is_empty() {
+        Ok(coerced)
+    } else {
+        Err(errors)
+    }
+}
+
+fn coerce_value(parameter: &RecipeParameter, value: &str) -> Result<String, String> {
+    let trimmed = value.trim();
+    let value = match parameter.input_type {
+        RecipeParameterInputType::String | RecipeParameterInputType::File => value.to_string(),
+        RecipeParameterInputType::Select => trimmed.to_string(),
+        RecipeParameterInputType::Number => match trimmed.parse::<f64>() {
+            Ok(number) if number.is_finite() => trimmed.to_string(),
+            _ => return Err(format!("'{}' is not a number", value)),
+        },
+        RecipeParameterInputType::Boolean => match trimmed.to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => "true".to_string(),
+            "false" | "no" | "off" | "0" => "false".to_string(),
+            _ => return Err(format!("'{}' is not true or false", value)),
+        },
+        RecipeParameterInputType::Date => NaiveDate::parse_from_str(trimmed, "%Y-%m-%d")
+            .or_else(|_| DateTime::parse_from_rfc3339(trimmed).map(|time| time.date_naive()))
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .map_err(|_| format!("'{}' is not a date like 2025-01-31", value))?,
+    };
+
+    match &parameter.options {
+        Some(options) if !options.contains(&value) => Err(format!(
+            "'{}' is not one of the options: {}",
+            value,
+            options.join(", ")
+        )),
+        None if matches!(parameter.input_type, RecipeParameterInputType::Select) => {
+            Err("The parameter has no options to select from".to_string())
+        }
+        _ => Ok(value),
+    }
+}
+
 fn validate_parameters_in_template(
     recipe_parameters: &Option<Vec<RecipeParameter>>,
     template_variables: &HashSet<String>,
@@ -1,10 +1,11 @@
 #[cfg(test)]
 mod tests {
     use crate::recipe::build_recipe::{
-        build_recipe_from_template, resolve_sub_recipe_path, RecipeError,
+        build_recipe_from_template, build_recipe_with_values, resolve_sub_recipe_path, RecipeError,
     };
     use crate::recipe::read_recipe_file_content::RecipeFile;
     use crate::recipe::{RecipeParameterInputType, RecipeParameterRequirement};
+    use std::collections::HashMap;
     use tempfile::TempDir;
 
     const NO_USER_PROMPT: Option<fn(&str, &str) -> Result<String, anyhow::Error>> = None;
@@ -351,6 +352,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_recipe_with_values_coerces_and_applies_defaults() {
+        let instructions_and_parameters = r#"
+                "instructions": "Run {{ count }} times, verbose {{ verbose }}, from {{ since }} in {{ mode }}",
+                "parameters": [
+                    {"key": "count", "input_type": "number", "requirement": "required", "description": "c"},
+                    {"key": "verbose", "input_type": "boolean", "requirement": "required", "description": "v"},
+                    {"key": "since", "input_type": "date", "requirement": "required", "description": "s"},
+                    {"key": "mode", "input_type": "select", "requirement": "optional", "description": "m",
+                     "default": "fast", "enum": ["fast", "thorough"]}
+                ]"#;
+        let (_temp_dir, recipe_file) = setup_recipe_file(instructions_and_parameters);
+
+        let values = HashMap::from([
+            ("count".to_string(), " 3 ".to_string()),
+            ("verbose".to_string(), "Yes".to_string()),
+            ("since".to_string(), "2025-06-01T09:00:00Z".to_string()),
+            ("mode".to_string(), "".to_string()),
+        ]);
+        let recipe = build_recipe_with_values(recipe_file, &values).unwrap();
+        assert_eq!(
+            recipe.instructions.unwrap(),
+            "Run 3 times, verbose true, from 2025-06-01 in fast"
+        );
+    }
+
+    #[test]
+    fn test_build_recipe_with_values_names_each_invalid_parameter() {
+        let instructions_and_parameters = r#"
+                "instructions": "{{ count }} {{ verbose }} {{ since }} {{ mode }} {{ name }}",
+                "parameters": [
+                    {"key": "count", "input_type": "number", "requirement": "required", "description": "c"},
+                    {"key": "verbose", "input_type": "boolean", "requirement": "required", "description": "v"},
+                    {"key": "since", "input_type": "date", "requirement": "required", "description": "s"},
+                    {"key": "mode", "input_type": "select", "requirement": "required", "description": "m",
+                     "options": ["fast", "thorough"]},
+                    {"key": "name", "input_type": "string", "requirement": "user_prompt", "description": "n"}
+                ]"#;
+        let (_temp_dir, recipe_file) = setup_recipe_file(instructions_and_parameters);
+
+        let values = HashMap::from([
+            ("count".to_string(), "abc".to_string()),
+            ("verbose".to_string(), "maybe".to_string()),
+            ("since".to_string(), "June".to_string()),
+            ("mode".to_string(), "slow".to_string()),
+            ("extra".to_string(), "x".to_string()),
+        ]);
+        let err = build_recipe_with_values(recipe_file, &values).unwrap_err();
+        let RecipeError::InvalidParams { errors } = err else {
+            panic!("Expected invalid parameters, got {}", err);
+        };
+        let keys: Vec<&str> = errors.iter().map(|error| error.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec!["count", "verbose", "since", "mode", "name", "extra"]
+        );
+        assert_eq!(errors[0].message, "'abc' is not a number");
+        assert_eq!(errors[4].message, "A value is required");
+    }
+
     mod sub_recipe_path_resolution {
         use super::*;
 
@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use etcetera::{choose_app_strategy, AppStrategy};
+use thiserror::Error;
+use url::Url;
+
+use crate::config::APP_STRATEGY;
+use crate::recipe::template_recipe::render_recipe_for_preview;
+use crate::recipe::validate_recipe::{error_summary, validate_recipe_content, RecipeIssue};
+use crate::recipe::Recipe;
+use crate::recipe_deeplink;
+
+/// Largest recipe that will be downloaded, in bytes
+pub const MAX_RECIPE_SIZE: usize = 512 * 1024;
+
+const DEEPLINK_PREFIX: &str = "goose://recipe?config=";
+const GITHUB_RAW_HOST: &str = "raw.githubusercontent.com";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Unrecognized recipe source '{0}': expected an https URL, owner/repo/path[@ref] or a goose://recipe deeplink")]
+    InvalidSource(String),
+    #[error("Only https URLs can be imported: {0}")]
+    InsecureUrl(String),
+    #[error("Recipe is larger than the {limit} byte limit")]
+    TooLarge { limit: usize },
+    #[error("Failed to fetch recipe: {0}")]
+    Fetch(String),
+    #[error("Recipe is invalid: {}", error_summary(.0).unwrap_or_default())]
+    Invalid(Vec<RecipeIssue>),
+    #[error("A recipe named '{}' already exists", .0.display())]
+    AlreadyExists(PathBuf),
+    #[error("Failed to store recipe: {0}")]
+    Storage(#[from] std::io::Error),
+}
+
+/// Where a shared recipe comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecipeSource {
+    /// An https URL serving the raw YAML or JSON
+    Url(Url),
+    /// A file in a GitHub repository, written `owner/repo/path@ref`
+    GitHub {
+        owner: String,
+        repo: String,
+        path: String,
+        reference: String,
+    },
+    /// The encoded config of a `goose://recipe?config=...` deeplink
+    Deeplink(String),
+}
+
+impl RecipeSource {
+    pub fn parse(source: &str) -> Result<Self, ImportError> {
+        let source = source.trim();
+        if let Some(config) = source.strip_prefix(DEEPLINK_PREFIX) {
+            if config.is_empty() {
+                return Err(ImportError::InvalidSource(source.to_string()));
+            }
+            return Ok(RecipeSource::Deeplink(config.to_string()));
+        }
+
+        if source.contains("://") {
+            let url =
+                Url::parse(source).map_err(|_| ImportError::InvalidSource(source.to_string()))?;
+            if url.scheme() != "https" {
+                return Err(ImportError::InsecureUrl(source.to_string()));
+            }
+            return Ok(RecipeSource::Url(url));
+        }
+
+        let (location, reference) = match source.rsplit_once('@') {
+            Some((location, reference)) if !reference.is_empty() => (location, reference),
+            Some(_) => return Err(ImportError::InvalidSource(source.to_string())),
+            None => (source, "HEAD"),
+        };
+        let mut parts = location.splitn(3, '/');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(owner), Some(repo), Some(path))
+                if !owner.is_empty()
+                    && !repo.is_empty()
+                    && !path.is_empty()
+                    && !path
+                        .split('/')
+                        .any(|segment| segment.is_empty() || segment == "..") =>
+            {
+                Ok(RecipeSource::GitHub {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    path: path.to_string(),
+                    reference: reference.to_string(),
+                })
+            }
+            _ => Err(ImportError::InvalidSource(source.to_string())),
+        }
+    }
+
+    /// The https URL the recipe is downloaded from, if it is downloaded at all
+    pub fn url(&self) -> Option<Url> {
+        match self {
+            RecipeSource::Url(url) => Some(url.clone()),
+            RecipeSource::GitHub {
+                owner,
+                repo,
+                path,
+                reference,
+            } => Url::parse(&format!(
+                "https://{}/{}/{}/{}/{}",
+                GITHUB_RAW_HOST, owner, repo, reference, path
+            ))
+            .ok(),
+            RecipeSource::Deeplink(_) => None,
+        }
+    }
+
+    /// The file name the recipe was shared under, if any
+    fn file_name(&self) -> Option<String> {
+        let url = self.url()?;
+        let name = url.path_segments()?.next_back()?;
+        let name = urlencoding::decode(name).ok()?.into_owned();
+        (!name.is_empty()).then_some(name)
+    }
+}
+
+/// A recipe as downloaded, before it is validated
+#[derive(Debug, Clone)]
+pub struct FetchedRecipe {
+    pub source: RecipeSource,
+    pub content: String,
+}
+
+/// Download the recipe at `source` without checking its contents
+pub async fn fetch_recipe(source: &str) -> Result<FetchedRecipe, ImportError> {
+    let source = RecipeSource::parse(source)?;
+    let content = match &source {
+        RecipeSource::Deeplink(config) => {
+            let recipe = recipe_deeplink::decode(config)
+                .map_err(|e| ImportError::Fetch(format!("Invalid deeplink: {}", e)))?;
+            serde_yaml::to_string(&recipe).map_err(|e| ImportError::Fetch(e.to_string()))?
+        }
+        _ => {
+            let url = source
+                .url()
+                .ok_or_else(|| ImportError::InvalidSource(format!("{:?}", source)))?;
+            download(url).await?
+        }
+    };
+    Ok(FetchedRecipe { source, content })
+}
+
+async fn download(url: Url) -> Result<String, ImportError> {
+    // Refuse to be redirected away from https
+    let redirect_policy = reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.url().scheme() != "https" {
+            let url = attempt.url().to_string();
+            attempt.error(ImportError::InsecureUrl(url))
+        } else if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    });
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .redirect(redirect_policy)
+        .build()
+        .map_err(|e| ImportError::Fetch(e.to_string()))?;
+
+    let mut response = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| ImportError::Fetch(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(ImportError::Fetch(format!(
+            "{} returned {}",
+            url,
+            response.status()
+        )));
+    }
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_RECIPE_SIZE as u64)
+    {
+        return Err(ImportError::TooLarge {
+            limit: MAX_RECIPE_SIZE,
+        });
+    }
+
+    // The declared length can't be trusted, so stop reading once the limit is passed
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| ImportError::Fetch(e.to_string()))?
+    {
+        if body.len() + chunk.len() > MAX_RECIPE_SIZE {
+            return Err(ImportError::TooLarge {
+                limit: MAX_RECIPE_SIZE,
+            });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    String::from_utf8(body).map_err(|_| ImportError::Fetch("Recipe is not valid UTF-8".into()))
+}
+
+/// A recipe that was imported into the local recipe directory
+#[derive(Debug, Clone)]
+pub struct ImportedRecipe {
+    pub recipe: Recipe,
+    pub path: PathBuf,
+    /// Warnings the validator found; imports with errors are refused
+    pub issues: Vec<RecipeIssue>,
+}
+
+/// The directory imported recipes are saved in
+pub fn get_default_recipes_dir() -> Result<PathBuf, ImportError> {
+    let strategy = choose_app_strategy(APP_STRATEGY.clone())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e.to_string()))?;
+    Ok(strategy.config_dir().join("recipes"))
+}
+
+/// Fetch the recipe at `source`, validate it and save it in `recipes_dir`.
+///
+/// The file is named after the shared file, or after the recipe title for deeplinks. An
+/// existing recipe with the same name is only replaced when `overwrite` is set.
+pub async fn import_recipe(
+    source: &str,
+    recipes_dir: &Path,
+    overwrite: bool,
+) -> Result<ImportedRecipe, ImportError> {
+    let fetched = fetch_recipe(source).await?;
+
+    // Relative sub-recipe paths can't be resolved until the recipe is saved, so they are only
+    // reported as warnings here
+    let issues = validate_recipe_content(&fetched.content, None);
+    if error_summary(&issues).is_some() {
+        return Err(ImportError::Invalid(issues));
+    }
+    let recipe = render_recipe_for_preview(
+        &fetched.content,
+        recipes_dir.to_string_lossy().to_string(),
+        &HashMap::new(),
+    )
+    .map_err(|e| ImportError::Fetch(e.to_string()))?;
+
+    let path = recipes_dir.join(recipe_file_name(&fetched.source, &recipe));
+    if path.exists() && !overwrite {
+        return Err(ImportError::AlreadyExists(path));
+    }
+    fs::create_dir_all(recipes_dir)?;
+    fs::write(&path, &fetched.content)?;
+
+    Ok(ImportedRecipe {
+        recipe,
+        path,
+        issues,
+    })
+}
+
+fn recipe_file_name(source: &RecipeSource, recipe: &Recipe) -> String {
+    let (stem, extension) = match source.file_name() {
+        Some(name) => {
+            let name = Path::new(&name);
+            let stem = name
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let extension = match name.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => "json",
+                _ => "yaml",
+            };
+            (stem, extension)
+        }
+        None => (recipe.title.clone(), "yaml"),
+    };
+
+    let stem = sanitize_file_stem(&stem);
+    let stem = if stem.is_empty() {
+        "recipe".to_string()
+    } else {
+        stem
+    };
+    format!("{}.{}", stem, extension)
+}
+
+fn sanitize_file_stem(stem: &str) -> String {
+    let sanitized: String = stem
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    sanitized
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_recipe() -> Recipe {
+        Recipe::builder()
+            .title("Daily Report: Team A")
+            .description("Summarize the day")
+            .instructions("Write a report")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_https_url() {
+        let source = RecipeSource::parse("https://example.com/recipes/report.yaml").unwrap();
+        assert_eq!(
+            source.url().unwrap().as_str(),
+            "https://example.com/recipes/report.yaml"
+        );
+        assert_eq!(source.file_name().as_deref(), Some("report.yaml"));
+    }
+
+    #[test]
+    fn test_parse_rejects_insecure_urls() {
+        assert!(matches!(
+            RecipeSource::parse("http://example.com/report.yaml"),
+            Err(ImportError::InsecureUrl(_))
+        ));
+        assert!(matches!(
+            RecipeSource::parse("file:///etc/passwd"),
+            Err(ImportError::InsecureUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_github_shorthand() {
+        let source = RecipeSource::parse("block/goose/recipes/report.yaml@v1.2").unwrap();
+        assert_eq!(
+            source,
+            RecipeSource::GitHub {
+                owner: "block".to_string(),
+                repo: "goose".to_string(),
+                path: "recipes/report.yaml".to_string(),
+                reference: "v1.2".to_string(),
+            }
+        );
+        assert_eq!(
+            source.url().unwrap().as_str(),
+            "https://raw.githubusercontent.com/block/goose/v1.2/recipes/report.yaml"
+        );
+
+        let source = RecipeSource::parse("block/goose/report.yaml").unwrap();
+        assert_eq!(
+            source.url().unwrap().as_str(),
+            "https://raw.githubusercontent.com/block/goose/HEAD/report.yaml"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_sources() {
+        for source in [
+            "report.yaml",
+            "block/goose",
+            "block/goose/report.yaml@",
+            "block/goose/../secrets.yaml",
+            "block//report.yaml",
+            "goose://recipe?config=",
+        ] {
+            assert!(
+                matches!(
+                    RecipeSource::parse(source),
+                    Err(ImportError::InvalidSource(_))
+                ),
+                "{} should be rejected",
+                source
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_deeplink() {
+        let dir = TempDir::new().unwrap();
+        let encoded = recipe_deeplink::encode(&test_recipe()).unwrap();
+        let source = format!("{}{}", DEEPLINK_PREFIX, encoded);
+
+        let imported = import_recipe(&source, dir.path(), false).await.unwrap();
+        assert_eq!(imported.recipe.title, "Daily Report: Team A");
+        assert_eq!(imported.path, dir.path().join("daily-report-team-a.yaml"));
+        let saved = fs::read_to_string(&imported.path).unwrap();
+        assert_eq!(
+            Recipe::from_content(&saved).unwrap().title,
+            imported.recipe.title
+        );
+
+        assert!(matches!(
+            import_recipe(&source, dir.path(), false).await,
+            Err(ImportError::AlreadyExists(_))
+        ));
+        assert!(import_recipe(&source, dir.path(), true).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_invalid_recipe() {
+        let dir = TempDir::new().unwrap();
+        let mut recipe = test_recipe();
+        recipe.instructions = None;
+        let source = format!(
+            "{}{}",
+            DEEPLINK_PREFIX,
+            recipe_deeplink::encode(&recipe).unwrap()
+        );
+
+        assert!(matches!(
+            import_recipe(&source, dir.path(), false).await,
+            Err(ImportError::Invalid(_))
+        ));
+        assert!(fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_recipe_file_name() {
+        let recipe = test_recipe();
+        let source = RecipeSource::parse("https://example.com/My%20Recipe.json").unwrap();
+        assert_eq!(recipe_file_name(&source, &recipe), "my-recipe.json");
+
+        let source = RecipeSource::Deeplink("abc".to_string());
+        assert_eq!(
+            recipe_file_name(&source, &recipe),
+            "daily-report-team-a.yaml"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::recipe::import_recipe::get_default_recipes_dir;
+use crate::recipe::template_recipe::render_recipe_for_preview;
+use crate::recipe::validate_recipe::{
+    error_summary, validate_recipe_content, IssueSeverity, RecipeIssue,
+};
+use crate::recipe::{Recipe, RecipeParameterInputType, RecipeParameterRequirement};
+
+/// Extra directories to find recipes in, separated like `PATH`
+pub const GOOSE_RECIPE_PATH_ENV_VAR: &str = "GOOSE_RECIPE_PATH";
+
+/// Extensions of recipe files, in the order they are looked up
+pub const RECIPE_FILE_EXTENSIONS: &[&str] = &["yaml", "yml", "json"];
+
+#[derive(Error, Debug)]
+pub enum LocalRecipeError {
+    #[error("Invalid recipe name '{0}'")]
+    InvalidName(String),
+    #[error("Recipe '{0}' not found")]
+    NotFound(String),
+    #[error("Recipe is invalid: {}", error_summary(.0).unwrap_or_default())]
+    Invalid(Vec<RecipeIssue>),
+    #[error("No recipe directory is configured")]
+    NoRecipeDir,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ParameterSummary {
+    pub key: String,
+    pub input_type: RecipeParameterInputType,
+    pub requirement: RecipeParameterRequirement,
+}
+
+/// A recipe file in one of the recipe directories
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct LocalRecipe {
+    /// The file name without extension, prefixed with its folder for nested recipes
+    pub name: String,
+    pub title: String,
+    pub version: String,
+    pub description: String,
+    pub parameters: Vec<ParameterSummary>,
+    pub path: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// The directories recipes are looked up in: the directory imported and saved recipes go to,
+/// followed by the directories in `GOOSE_RECIPE_PATH`
+pub fn configured_recipe_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(dir) = get_default_recipes_dir() {
+        dirs.push(dir);
+    }
+    if let Some(recipe_path) = env::var_os(GOOSE_RECIPE_PATH_ENV_VAR) {
+        dirs.extend(env::split_paths(&recipe_path).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    dirs
+}
+
+/// List the recipes in `dirs` and their direct subdirectories. When two directories have a
+/// recipe with the same name, the one in the earlier directory is listed.
+pub fn list_local_recipes(dirs: &[PathBuf]) -> Vec<LocalRecipe> {
+    let mut seen = HashSet::new();
+    let mut recipes = Vec::new();
+    for dir in dirs {
+        for (name, path) in recipe_files(dir) {
+            if seen.contains(&name) {
+                continue;
+            }
+            match load_local_recipe(&name, &path) {
+                Ok((recipe, _)) => {
+                    seen.insert(name);
+                    recipes.push(recipe);
+                }
+                Err(e) => tracing::debug!("Skipping {}: {}", path.display(), e),
+            }
+        }
+    }
+    recipes.sort_by(|a, b| a.name.cmp(&b.name));
+    recipes
+}
+
+/// Find the recipe called `name` and return it with the raw file content
+pub fn get_local_recipe(
+    dirs: &[PathBuf],
+    name: &str,
+) -> Result<(LocalRecipe, String), LocalRecipeError> {
+    let path = find_recipe_file(dirs, name)?
+        .ok_or_else(|| LocalRecipeError::NotFound(name.to_string()))?;
+    load_local_recipe(name, &path)
+}
+
+/// Validate `content` and save it as the recipe called `name`, replacing the existing file if
+/// there is one and otherwise creating it in the first directory
+pub fn save_local_recipe(
+    dirs: &[PathBuf],
+    name: &str,
+    content: &str,
+) -> Result<(LocalRecipe, Vec<RecipeIssue>), LocalRecipeError> {
+    let path = match find_recipe_file(dirs, name)? {
+        Some(path) => path,
+        None => {
+            let dir = dirs.first().ok_or(LocalRecipeError::NoRecipeDir)?;
+            let extension = if content.trim_start().starts_with('{') {
+                "json"
+            } else {
+                "yaml"
+            };
+            dir.join(format!("{}.{}", name, extension))
+        }
+    };
+
+    let recipe_dir = path.parent().unwrap_or(Path::new("."));
+    let issues = validate_recipe_content(content, Some(recipe_dir));
+    if error_summary(&issues).is_some() {
+        return Err(LocalRecipeError::Invalid(issues));
+    }
+
+    fs::create_dir_all(recipe_dir)?;
+    fs::write(&path, content)?;
+    let (recipe, _) = load_local_recipe(name, &path)?;
+    Ok((recipe, issues))
+}
+
+/// Delete the recipe called `name` and return the path of the removed file
+pub fn delete_local_recipe(dirs: &[PathBuf], name: &str) -> Result<PathBuf, LocalRecipeError> {
+    let path = find_recipe_file(dirs, name)?
+        .ok_or_else(|| LocalRecipeError::NotFound(name.to_string()))?;
+    fs::remove_file(&path)?;
+    Ok(path)
+}
+
+/// Recipe names are a file stem, optionally inside one folder, so they can't escape the
+/// recipe directories
+fn check_name(name: &str) -> Result<(), LocalRecipeError> {
+    let segments: Vec<&str> = name.split('/').collect();
+    let valid = segments.len() <= 2
+        && segments.iter().all(|segment| {
+            !segment.is_empty()
+                && !segment.starts_with('.')
+                && !segment.contains(['\\', ':'])
+                && segment.trim() == *segment
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(LocalRecipeError::InvalidName(name.to_string()))
+    }
+}
+
+fn find_recipe_file(dirs: &[PathBuf], name: &str) -> Result<Option<PathBuf>, LocalRecipeError> {
+    check_name(name)?;
+    Ok(dirs.iter().find_map(|dir| {
+        RECIPE_FILE_EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name, ext)))
+            .find(|path| path.is_file())
+    }))
+}
+
+fn is_recipe_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| RECIPE_FILE_EXTENSIONS.contains(&ext))
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_none_or(|name| name.starts_with('.'))
+}
+
+/// The recipe files in `dir` and its direct subdirectories, keyed by recipe name
+fn recipe_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files: HashMap<String, PathBuf> = HashMap::new();
+    let mut add = |name: String, path: PathBuf| {
+        // A yaml file wins over a json file with the same name, as in lookups
+        let rank = |path: &Path| {
+            RECIPE_FILE_EXTENSIONS
+                .iter()
+                .position(|ext| path.extension().is_some_and(|e| e == *ext))
+        };
+        match files.get(&name) {
+            Some(existing) if rank(existing) <= rank(&path) => {}
+            _ => {
+                files.insert(name, path);
+            }
+        }
+    };
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if is_hidden(&path) {
+            continue;
+        }
+        if is_recipe_file(&path) {
+            if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                add(stem.to_string(), path.clone());
+            }
+        } else if path.is_dir() {
+            let Some(folder) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Ok(nested) = fs::read_dir(&path) else {
+                continue;
+            };
+            for nested_path in nested.flatten().map(|entry| entry.path()) {
+                if is_hidden(&nested_path) || !is_recipe_file(&nested_path) {
+                    continue;
+                }
+                if let Some(stem) = nested_path.file_stem().and_then(|stem| stem.to_str()) {
+                    add(format!("{}/{}", folder, stem), nested_path.clone());
+                }
+            }
+        }
+    }
+    files.into_iter().collect()
+}
+
+fn load_local_recipe(name: &str, path: &Path) -> Result<(LocalRecipe, String), LocalRecipeError> {
+    let content = fs::read_to_string(path)?;
+    let recipe_dir = path
+        .parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let recipe: Recipe =
+        render_recipe_for_preview(&content, recipe_dir, &HashMap::new()).map_err(|e| {
+            LocalRecipeError::Invalid(vec![RecipeIssue {
+                severity: IssueSeverity::Error,
+                location: String::new(),
+                message: e.to_string(),
+            }])
+        })?;
+    let last_modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(DateTime::<Utc>::from);
+
+    let parameters = recipe
+        .parameters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|parameter| ParameterSummary {
+            key: parameter.key,
+            input_type: parameter.input_type,
+            requirement: parameter.requirement,
+        })
+        .collect();
+    let local_recipe = LocalRecipe {
+        name: name.to_string(),
+        title: recipe.title,
+        version: recipe.version,
+        description: recipe.description,
+        parameters,
+        path: path.to_string_lossy().to_string(),
+        last_modified,
+    };
+    Ok((local_recipe, content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn recipe_content(title: &str) -> String {
+        format!(
+            r#"title: {}
+description: A test recipe
+instructions: Say hello to {{{{ name }}}}
+parameters:
+  - key: name
+    input_type: string
+    requirement: required
+    description: Who to greet
+"#,
+            title
+        )
+    }
+
+    #[test]
+    fn test_list_local_recipes() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        fs::write(first.path().join("hello.yaml"), recipe_content("Hello")).unwrap();
+        fs::create_dir(first.path().join("team")).unwrap();
+        fs::write(
+            first.path().join("team").join("standup.yaml"),
+            recipe_content("Standup"),
+        )
+        .unwrap();
+        fs::create_dir_all(first.path().join("team").join("deeper")).unwrap();
+        fs::write(
+            first.path().join("team").join("deeper").join("hidden.yaml"),
+            recipe_content("Too deep"),
+        )
+        .unwrap();
+        fs::write(first.path().join("notes.yaml"), "just: notes").unwrap();
+        let json = serde_json::json!({
+            "title": "Json",
+            "description": "A json recipe",
+            "prompt": "hi",
+        });
+        fs::write(second.path().join("json.json"), json.to_string()).unwrap();
+        fs::write(second.path().join("hello.yaml"), recipe_content("Shadowed")).unwrap();
+
+        let dirs = vec![first.path().to_path_buf(), second.path().to_path_buf()];
+        let recipes = list_local_recipes(&dirs);
+        let names: Vec<_> = recipes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["hello", "json", "team/standup"]);
+
+        let hello = &recipes[0];
+        assert_eq!(hello.title, "Hello");
+        assert_eq!(hello.parameters.len(), 1);
+        assert_eq!(hello.parameters[0].key, "name");
+        assert!(hello.last_modified.is_some());
+    }
+
+    #[test]
+    fn test_get_save_and_delete_local_recipe() {
+        let dir = TempDir::new().unwrap();
+        let dirs = vec![dir.path().to_path_buf()];
+
+        assert!(matches!(
+            get_local_recipe(&dirs, "hello"),
+            Err(LocalRecipeError::NotFound(_))
+        ));
+
+        let (saved, issues) = save_local_recipe(&dirs, "hello", &recipe_content("Hello")).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(saved.path, dir.path().join("hello.yaml").to_string_lossy());
+
+        let (recipe, content) = get_local_recipe(&dirs, "hello").unwrap();
+        assert_eq!(recipe.title, "Hello");
+        assert_eq!(content, recipe_content("Hello"));
+
+        save_local_recipe(&dirs, "hello", &recipe_content("Edited")).unwrap();
+        assert_eq!(get_local_recipe(&dirs, "hello").unwrap().0.title, "Edited");
+
+        let removed = delete_local_recipe(&dirs, "hello").unwrap();
+        assert!(!removed.exists());
+        assert!(matches!(
+            delete_local_recipe(&dirs, "hello"),
+            Err(LocalRecipeError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_rejects_invalid_recipe() {
+        let dir = TempDir::new().unwrap();
+        let dirs = vec![dir.path().to_path_buf()];
+
+        let result = save_local_recipe(&dirs, "broken", "title: Broken\n");
+        assert!(matches!(result, Err(LocalRecipeError::Invalid(_))));
+        assert!(!dir.path().join("broken.yaml").exists());
+    }
+
+    #[test]
+    fn test_rejects_names_outside_recipe_dirs() {
+        let dir = TempDir::new().unwrap();
+        let dirs = vec![dir.path().to_path_buf()];
+        for name in ["../secrets", "a/b/c", "", ".hidden", "/etc/passwd"] {
+            assert!(
+                matches!(
+                    get_local_recipe(&dirs, name),
+                    Err(LocalRecipeError::InvalidName(_))
+                ),
+                "{} should be rejected",
+                name
+            );
+        }
+    }
+}
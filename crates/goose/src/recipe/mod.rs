@@ -10,8 +10,14 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 pub mod build_recipe;
+pub mod import_recipe;
+pub mod local_recipes;
 pub mod read_recipe_file_content;
+pub mod response_schema;
+pub mod risk_summary;
+pub mod run_recipe;
 pub mod template_recipe;
+pub mod validate_recipe;
 
 pub const BUILT_IN_RECIPE_DIR_PARAM: &str = "recipe_dir";
 
@@ -141,6 +147,9 @@ pub struct Settings {
 pub struct Response {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub json_schema: Option<serde_json::Value>,
+    /// Turns spent asking the model to fix output that doesn't match `json_schema`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_validation_retries: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -227,7 +236,8 @@ pub struct RecipeParameter {
     pub description: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The values the parameter accepts; required for `select` parameters
+    #[serde(alias = "enum", skip_serializing_if = "Option::is_none")]
     pub options: Option<Vec<String>>,
 }
 
@@ -316,6 +326,14 @@ impl Recipe {
 
         Ok(recipe)
     }
+
+    /// Names of the extensions the recipe declares, or `None` when it declares none and so
+    /// leaves the session's extensions as they are
+    pub fn extension_names(&self) -> Option<Vec<String>> {
+        self.extensions
+            .as_ref()
+            .map(|extensions| extensions.iter().map(ExtensionConfig::name).collect())
+    }
 }
 
 impl RecipeBuilder {
@@ -685,6 +703,7 @@ sub_recipes:
                 description,
                 timeout,
                 dependencies,
+                ..
             } => {
                 assert_eq!(name, "test_python");
                 assert_eq!(code, "print('hello world')");
@@ -0,0 +1,174 @@
+//! Checking a finished recipe run's output against the recipe's `response.json_schema`, so
+//! callers that consume the output can rely on its shape.
+
+use rmcp::model::Role;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::message::Message;
+use crate::recipe::Response;
+
+/// Corrective turns a run gets when its output doesn't match the schema, unless the recipe says
+const DEFAULT_MAX_VALIDATION_RETRIES: u32 = 1;
+
+/// Whether a run's final output matched its recipe's response schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SchemaValidation {
+    pub schema_valid: bool,
+    /// Why the output didn't match, empty when it did
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl Response {
+    /// Corrective turns to ask for before accepting output that doesn't match the schema
+    pub fn max_validation_retries(&self) -> u32 {
+        self.max_validation_retries
+            .unwrap_or(DEFAULT_MAX_VALIDATION_RETRIES)
+    }
+
+    /// Check the final output in `messages` against the schema, if the response has one. The
+    /// output is the last assistant message with text, which is where the final output tool
+    /// puts what it collected.
+    pub fn validate_output(&self, messages: &[Message]) -> Option<SchemaValidation> {
+        let schema = self.json_schema.as_ref()?;
+        let invalid = |error: String| SchemaValidation {
+            schema_valid: false,
+            errors: vec![error],
+        };
+
+        let validator = match jsonschema::validator_for(schema) {
+            Ok(validator) => validator,
+            Err(e) => return Some(invalid(format!("The response schema is invalid: {}", e))),
+        };
+        let Some(output) = final_output(messages) else {
+            return Some(invalid("The run produced no final output".to_string()));
+        };
+        let Some(output) = parse_output(&output) else {
+            return Some(invalid("The final output is not JSON".to_string()));
+        };
+
+        let errors: Vec<String> = validator
+            .iter_errors(&output)
+            .map(|error| format!("{}: {}", error.instance_path, error))
+            .collect();
+        Some(SchemaValidation {
+            schema_valid: errors.is_empty(),
+            errors,
+        })
+    }
+
+    /// The message asking the model to fix output that failed `validation`
+    pub fn correction_message(&self, validation: &SchemaValidation) -> Message {
+        let schema = self
+            .json_schema
+            .as_ref()
+            .and_then(|schema| serde_json::to_string_pretty(schema).ok())
+            .unwrap_or_default();
+        Message::user().with_text(format!(
+            "Your final output does not match the required JSON schema:\n{}\n\n\
+             Reply again with only the corrected JSON, matching this schema:\n{}",
+            validation
+                .errors
+                .iter()
+                .map(|error| format!("- {}", error))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            schema
+        ))
+    }
+}
+
+fn final_output(messages: &[Message]) -> Option<String> {
+    messages
+        .iter()
+        .rev()
+        .filter(|message| message.role == Role::Assistant)
+        .map(Message::as_concat_text)
+        .find(|text| !text.trim().is_empty())
+}
+
+/// Parse the output as JSON, on its own or in a fenced code block
+fn parse_output(output: &str) -> Option<Value> {
+    let output = output.trim();
+    if let Ok(value) = serde_json::from_str(output) {
+        return Some(value);
+    }
+    let (_, fenced) = output.split_once("```")?;
+    let fenced = fenced.strip_prefix("json").unwrap_or(fenced);
+    let (block, _) = fenced.split_once("```")?;
+    serde_json::from_str(block.trim()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response() -> Response {
+        Response {
+            json_schema: Some(json!({
+                "type": "object",
+                "properties": {"status": {"type": "string"}, "count": {"type": "integer"}},
+                "required": ["status", "count"]
+            })),
+            max_validation_retries: None,
+        }
+    }
+
+    fn run(output: &str) -> Vec<Message> {
+        vec![
+            Message::user().with_text("Summarize"),
+            Message::assistant().with_text(output),
+        ]
+    }
+
+    #[test]
+    fn test_matching_output_is_valid() {
+        let validation = response()
+            .validate_output(&run(r#"{"status": "ok", "count": 3}"#))
+            .unwrap();
+        assert!(validation.schema_valid);
+        assert!(validation.errors.is_empty());
+
+        let fenced = "Here it is:\n```json\n{\"status\": \"ok\", \"count\": 3}\n```";
+        assert!(
+            response()
+                .validate_output(&run(fenced))
+                .unwrap()
+                .schema_valid
+        );
+    }
+
+    #[test]
+    fn test_mismatched_output_lists_errors() {
+        let validation = response()
+            .validate_output(&run(r#"{"status": "ok", "count": "three"}"#))
+            .unwrap();
+        assert!(!validation.schema_valid);
+        assert_eq!(validation.errors.len(), 1);
+        assert!(validation.errors[0].starts_with("/count"));
+
+        let validation = response().validate_output(&run("All done!")).unwrap();
+        assert_eq!(validation.errors, vec!["The final output is not JSON"]);
+
+        let message = response().correction_message(&validation);
+        assert!(message
+            .as_concat_text()
+            .contains("- The final output is not JSON"));
+    }
+
+    #[test]
+    fn test_no_schema_is_not_validated() {
+        let response = Response {
+            json_schema: None,
+            max_validation_retries: None,
+        };
+        assert!(response.validate_output(&run("anything")).is_none());
+        assert_eq!(
+            response.max_validation_retries(),
+            DEFAULT_MAX_VALIDATION_RETRIES
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::agents::{Agent, RetryConfig};
+use crate::config::Config;
+use crate::message::Message;
+use crate::recipe::build_recipe::{build_recipe_with_values, RecipeError};
+use crate::recipe::read_recipe_file_content::RecipeFile;
+use crate::recipe::validate_recipe::{validate_recipe_content, RecipeIssue};
+use crate::recipe::{Recipe, SubRecipe};
+use crate::token_counter::AsyncTokenCounter;
+
+/// Set the agent up to run `recipe`: its instructions, sub-recipe tools and final output tool.
+/// Runners call this after adding their extensions, and dry runs call it on an agent without
+/// a provider, so a preview shows what a run sends.
+pub async fn prepare_agent_for_recipe(agent: &Agent, recipe: &Recipe) {
+    if let Some(sub_recipes) = &recipe.sub_recipes {
+        agent.add_sub_recipes(sub_recipes.clone()).await;
+    }
+    if let Some(response) = &recipe.response {
+        agent.add_final_output_tool(response.clone()).await;
+    }
+    if let Some(instructions) = recipe
+        .instructions
+        .as_ref()
+        .filter(|instructions| !instructions.trim().is_empty())
+    {
+        agent.extend_system_prompt(instructions.clone()).await;
+    }
+}
+
+/// The message a run of `recipe` starts with, if any
+pub fn recipe_initial_message(recipe: &Recipe) -> Option<String> {
+    recipe
+        .prompt
+        .clone()
+        .filter(|prompt| !prompt.trim().is_empty())
+}
+
+/// The model settings a run of a recipe would use
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct RunSettings {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_turns: u32,
+}
+
+/// Everything a run of a recipe would send and enable, worked out without a provider
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct RecipeDryRun {
+    /// The recipe with the parameter values applied
+    pub recipe: Recipe,
+    pub system_prompt: String,
+    pub initial_message: Option<String>,
+    /// Names of the extensions the recipe enables; they aren't started for a dry run
+    pub extensions: Vec<String>,
+    /// Names of the tools available before any extension starts
+    pub tools: Vec<String>,
+    /// Sub-recipes with their paths resolved
+    pub sub_recipes: Vec<SubRecipe>,
+    pub settings: RunSettings,
+    pub retry: Option<RetryConfig>,
+    /// Input tokens of the first request, counted with the local tokenizer. Tools from
+    /// extensions aren't included.
+    pub estimated_input_tokens: Option<usize>,
+    /// Warnings found while validating the recipe
+    pub issues: Vec<RecipeIssue>,
+}
+
+/// Render `recipe_file` with `values` and work out what running it would do, without creating
+/// a session or calling a provider
+pub async fn dry_run_recipe(
+    recipe_file: RecipeFile,
+    values: &HashMap<String, String>,
+) -> Result<RecipeDryRun, RecipeError> {
+    let issues = validate_recipe_content(&recipe_file.content, Some(&recipe_file.parent_dir));
+    let recipe = build_recipe_with_values(recipe_file, values)?;
+
+    let config = Config::global();
+    let recipe_settings = recipe.settings.as_ref();
+    let settings = RunSettings {
+        provider: recipe_settings
+            .and_then(|settings| settings.goose_provider.clone())
+            .or_else(|| config.get_param("GOOSE_PROVIDER").ok()),
+        model: recipe_settings
+            .and_then(|settings| settings.goose_model.clone())
+            .or_else(|| config.get_param("GOOSE_MODEL").ok()),
+        temperature: recipe_settings.and_then(|settings| settings.temperature),
+        max_turns: Agent::default_max_turns(),
+    };
+
+    let agent = Agent::new();
+    prepare_agent_for_recipe(&agent, &recipe).await;
+    let system_prompt = agent.build_system_prompt(settings.model.as_deref()).await;
+    let tools = agent.list_tools_for_request().await;
+    let initial_message = recipe_initial_message(&recipe);
+
+    let estimated_input_tokens = match AsyncTokenCounter::new().await {
+        Ok(counter) => {
+            let messages: Vec<Message> = initial_message
+                .iter()
+                .map(|text| Message::user().with_text(text))
+                .collect();
+            Some(counter.count_chat_tokens(&system_prompt, &messages, &tools))
+        }
+        Err(e) => {
+            tracing::warn!("Failed to load the tokenizer for a dry run: {}", e);
+            None
+        }
+    };
+
+    Ok(RecipeDryRun {
+        system_prompt,
+        initial_message,
+        extensions: recipe
+            .extensions
+            .iter()
+            .flatten()
+            .map(|extension| extension.name())
+            .collect(),
+        tools: tools.iter().map(|tool| tool.name.to_string()).collect(),
+        sub_recipes: recipe.sub_recipes.clone().unwrap_or_default(),
+        settings,
+        retry: recipe.retry.clone(),
+        estimated_input_tokens,
+        issues,
+        recipe,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::final_output_tool::FINAL_OUTPUT_TOOL_NAME;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_dry_run_recipe() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("summarize.yaml"),
+            "title: Summarize\ndescription: Summarize a file\nprompt: Summarize it\n",
+        )
+        .unwrap();
+        let content = r#"
+title: Report
+description: Write a report
+instructions: Write in the style of {{ style }}
+prompt: Report on {{ topic }}
+parameters:
+  - key: style
+    input_type: string
+    requirement: optional
+    default: a newspaper
+    description: Writing style
+  - key: topic
+    input_type: string
+    requirement: required
+    description: What to report on
+sub_recipes:
+  - name: summarize
+    path: summarize.yaml
+response:
+  json_schema:
+    type: object
+    properties:
+      summary:
+        type: string
+"#;
+        let recipe_file = RecipeFile {
+            content: content.to_string(),
+            parent_dir: dir.path().to_path_buf(),
+            file_path: dir.path().join("report.yaml"),
+        };
+        let values = HashMap::from([("topic".to_string(), "the weather".to_string())]);
+
+        let dry_run = dry_run_recipe(recipe_file, &values).await.unwrap();
+        assert_eq!(
+            dry_run.initial_message.as_deref(),
+            Some("Report on the weather")
+        );
+        assert!(dry_run
+            .system_prompt
+            .contains("Write in the style of a newspaper"));
+        assert!(dry_run.tools.iter().any(|tool| tool.contains("summarize")));
+        assert!(dry_run
+            .tools
+            .iter()
+            .any(|tool| tool == FINAL_OUTPUT_TOOL_NAME));
+        assert_eq!(
+            dry_run.sub_recipes[0].path,
+            dir.path().join("summarize.yaml").to_string_lossy()
+        );
+        assert!(dry_run.estimated_input_tokens.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_rejects_missing_values() {
+        let content = "title: Report\ndescription: d\nprompt: Report on {{ topic }}\nparameters:\n  - key: topic\n    input_type: string\n    requirement: required\n    description: topic\n";
+        let recipe_file = RecipeFile {
+            content: content.to_string(),
+            parent_dir: std::env::temp_dir(),
+            file_path: std::env::temp_dir().join("report.yaml"),
+        };
+
+        let result = dry_run_recipe(recipe_file, &HashMap::new()).await;
+        assert!(matches!(result, Err(RecipeError::InvalidParams { .. })));
+    }
+}
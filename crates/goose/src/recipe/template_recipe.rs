@@ -174,6 +174,17 @@ pub fn render_recipe_for_preview(
     recipe_dir: String,
     params: &HashMap<String, String>,
 ) -> Result<Recipe> {
+    let (rendered_content, _) = render_recipe_content_for_preview(content, recipe_dir, params)?;
+    Recipe::from_content(&rendered_content)
+}
+
+// render the recipe content keeping the variables without a value as they are, and return the
+// variables used in the recipe
+pub fn render_recipe_content_for_preview(
+    content: &str,
+    recipe_dir: String,
+    params: &HashMap<String, String>,
+) -> Result<(String, HashSet<String>)> {
     // Pre-process template variables to handle invalid variable names
     let preprocessed_content = preprocess_template_variables(content)?;
 
@@ -189,7 +200,7 @@ pub fn render_recipe_for_preview(
     let rendered_content = template
         .render(ctx)
         .map_err(|e| anyhow::anyhow!("Failed to parse the recipe {}", e))?;
-    Recipe::from_content(&rendered_content)
+    Ok((rendered_content, template_variables))
 }
 
 fn preserve_vars(variables: &HashSet<String>) -> HashMap<String, String> {
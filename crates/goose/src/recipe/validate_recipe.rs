@@ -0,0 +1,689 @@
+//! Structural validation of recipes, shared by every entry point that accepts one.
+//!
+//! Each issue points at the offending part of the recipe with a JSON pointer such as
+//! `/parameters/1/input_type`; an empty location is the recipe as a whole. Errors are problems
+//! that make the recipe fail to load or run, warnings are likely mistakes that don't.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+use crate::recipe::template_recipe::render_recipe_content_for_preview;
+use crate::recipe::{
+    Recipe, RecipeParameterInputType, RecipeParameterRequirement, BUILT_IN_RECIPE_DIR_PARAM,
+};
+
+const RECIPE_FIELDS: &[&str] = &[
+    "version",
+    "title",
+    "description",
+    "instructions",
+    "prompt",
+    "extensions",
+    "context",
+    "settings",
+    "activities",
+    "author",
+    "parameters",
+    "response",
+    "sub_recipes",
+    "retry",
+];
+const AUTHOR_FIELDS: &[&str] = &["contact", "metadata"];
+const SETTINGS_FIELDS: &[&str] = &["goose_provider", "goose_model", "temperature"];
+const RESPONSE_FIELDS: &[&str] = &["json_schema"];
+const PARAMETER_FIELDS: &[&str] = &[
+    "key",
+    "input_type",
+    "requirement",
+    "description",
+    "default",
+    "options",
+    "enum",
+];
+const SUB_RECIPE_FIELDS: &[&str] = &[
+    "name",
+    "path",
+    "values",
+    "sequential_when_repeated",
+    "description",
+];
+const INPUT_TYPES: &[&str] = &["string", "number", "boolean", "date", "file", "select"];
+const REQUIREMENTS: &[&str] = &["required", "optional", "user_prompt"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct RecipeIssue {
+    pub severity: IssueSeverity,
+    /// JSON pointer to the part of the recipe the issue is about, empty for the whole recipe
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for RecipeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.location.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.location, self.message)
+        }
+    }
+}
+
+/// The errors among `issues`, joined into one message; `None` when there are none
+pub fn error_summary(issues: &[RecipeIssue]) -> Option<String> {
+    let errors: Vec<String> = issues
+        .iter()
+        .filter(|issue| issue.severity == IssueSeverity::Error)
+        .map(ToString::to_string)
+        .collect();
+    (!errors.is_empty()).then(|| errors.join("; "))
+}
+
+/// Validate recipe YAML or JSON as written in a recipe file. `recipe_dir` is the directory of
+/// the file, used for included templates and sub-recipe paths; it is `None` for content that
+/// doesn't come from a file.
+pub fn validate_recipe_content(content: &str, recipe_dir: Option<&Path>) -> Vec<RecipeIssue> {
+    let dir = recipe_dir
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (rendered_content, mut variables) =
+        match render_recipe_content_for_preview(content, dir, &HashMap::new()) {
+            Ok(rendered) => rendered,
+            Err(e) => return vec![error(String::new(), format!("Invalid template: {}", e))],
+        };
+    variables.remove(BUILT_IN_RECIPE_DIR_PARAM);
+
+    let value = match serde_json::from_str::<Value>(&rendered_content) {
+        Ok(value) => value,
+        Err(_) => match serde_yaml::from_str::<Value>(&rendered_content) {
+            Ok(value) => value,
+            Err(e) => {
+                return vec![error(
+                    String::new(),
+                    format!("Not valid JSON or YAML: {}", e),
+                )]
+            }
+        },
+    };
+    match value.get("recipe") {
+        Some(nested) => validate_value(nested, "/recipe", &variables, recipe_dir),
+        None => validate_value(&value, "", &variables, recipe_dir),
+    }
+}
+
+/// Validate a recipe that was already parsed or built, such as one created from a session
+pub fn validate_recipe(recipe: &Recipe, recipe_dir: Option<&Path>) -> Vec<RecipeIssue> {
+    let value = match serde_json::to_value(recipe) {
+        Ok(value) => value,
+        Err(e) => return vec![error(String::new(), e.to_string())],
+    };
+    let variable_re = Regex::new(r"\{\{\s*([a-zA-Z_][a-zA-Z0-9_]*)\s*\}\}").unwrap();
+    let mut variables = HashSet::new();
+    visit_strings(&value, "", &mut |_, text| {
+        variables.extend(
+            variable_re
+                .captures_iter(text)
+                .map(|capture| capture[1].to_string()),
+        );
+    });
+    variables.remove(BUILT_IN_RECIPE_DIR_PARAM);
+    validate_value(&value, "", &variables, recipe_dir)
+}
+
+fn error(location: String, message: String) -> RecipeIssue {
+    RecipeIssue {
+        severity: IssueSeverity::Error,
+        location,
+        message,
+    }
+}
+
+fn warning(location: String, message: String) -> RecipeIssue {
+    RecipeIssue {
+        severity: IssueSeverity::Warning,
+        location,
+        message,
+    }
+}
+
+// Append `key` to a JSON pointer, escaping it as RFC 6901 requires
+fn pointer(base: &str, key: impl fmt::Display) -> String {
+    format!(
+        "{}/{}",
+        base,
+        key.to_string().replace('~', "~0").replace('/', "~1")
+    )
+}
+
+fn validate_value(
+    value: &Value,
+    location: &str,
+    variables: &HashSet<String>,
+    recipe_dir: Option<&Path>,
+) -> Vec<RecipeIssue> {
+    let mut issues = Vec::new();
+    let Some(fields) = value.as_object() else {
+        issues.push(error(
+            location.to_string(),
+            "A recipe must be a mapping of fields".to_string(),
+        ));
+        return issues;
+    };
+
+    check_unknown_fields(fields, RECIPE_FIELDS, location, &mut issues);
+    for required in ["title", "description"] {
+        if !fields.get(required).is_some_and(Value::is_string) {
+            issues.push(error(
+                pointer(location, required),
+                format!("'{}' is required and must be a string", required),
+            ));
+        }
+    }
+    let has_text = |field: &str| fields.get(field).is_some_and(|value| !value.is_null());
+    if !has_text("instructions") && !has_text("prompt") {
+        issues.push(error(
+            location.to_string(),
+            "At least one of 'instructions' and 'prompt' must be set".to_string(),
+        ));
+    }
+    for (field, known) in [
+        ("author", AUTHOR_FIELDS),
+        ("settings", SETTINGS_FIELDS),
+        ("response", RESPONSE_FIELDS),
+    ] {
+        if let Some(Value::Object(nested)) = fields.get(field) {
+            check_unknown_fields(nested, known, &pointer(location, field), &mut issues);
+        }
+    }
+
+    check_parameters(value, location, variables, &mut issues);
+    check_sub_recipes(fields, location, recipe_dir, &mut issues);
+    check_response_schema(fields, location, &mut issues);
+
+    let has_errors = issues
+        .iter()
+        .any(|issue| issue.severity == IssueSeverity::Error);
+    match serde_json::from_value::<Recipe>(value.clone()) {
+        Ok(recipe) => {
+            if let Some(Err(e)) = recipe.retry.as_ref().map(|retry| retry.validate()) {
+                issues.push(error(pointer(location, "retry"), e));
+            }
+        }
+        // Only report what the more specific checks above didn't already explain
+        Err(e) if !has_errors => issues.push(error(location.to_string(), e.to_string())),
+        Err(_) => {}
+    }
+    issues
+}
+
+fn check_unknown_fields(
+    fields: &Map<String, Value>,
+    known: &[&str],
+    location: &str,
+    issues: &mut Vec<RecipeIssue>,
+) {
+    for key in fields.keys().filter(|key| !known.contains(&key.as_str())) {
+        let suggestion = known
+            .iter()
+            .find(|candidate| edit_distance(key, candidate) <= 2)
+            .map(|candidate| format!(", did you mean '{}'?", candidate))
+            .unwrap_or_default();
+        issues.push(warning(
+            pointer(location, key),
+            format!("Unknown field '{}' is ignored{}", key, suggestion),
+        ));
+    }
+}
+
+fn check_parameters(
+    recipe: &Value,
+    location: &str,
+    variables: &HashSet<String>,
+    issues: &mut Vec<RecipeIssue>,
+) {
+    let parameters_location = pointer(location, "parameters");
+    let parameters: &[Value] = match recipe.get("parameters") {
+        None | Some(Value::Null) => &[],
+        Some(Value::Array(parameters)) => parameters,
+        Some(_) => {
+            issues.push(error(
+                parameters_location,
+                "'parameters' must be a list".to_string(),
+            ));
+            return;
+        }
+    };
+
+    let mut declared = HashSet::new();
+    for (index, parameter) in parameters.iter().enumerate() {
+        let here = pointer(&parameters_location, index);
+        let Some(fields) = parameter.as_object() else {
+            issues.push(error(here, "A parameter must be a mapping".to_string()));
+            continue;
+        };
+        check_unknown_fields(fields, PARAMETER_FIELDS, &here, issues);
+
+        let key = fields.get("key").and_then(Value::as_str);
+        match key {
+            None => issues.push(error(
+                pointer(&here, "key"),
+                "'key' is required and must be a string".to_string(),
+            )),
+            Some(key) if !declared.insert(key.to_string()) => issues.push(error(
+                pointer(&here, "key"),
+                format!("Parameter '{}' is declared more than once", key),
+            )),
+            Some(key) if !variables.contains(key) => issues.push(error(
+                pointer(&here, "key"),
+                format!("Parameter '{}' is declared but never used", key),
+            )),
+            Some(_) => {}
+        }
+        if !fields.get("description").is_some_and(Value::is_string) {
+            issues.push(error(
+                pointer(&here, "description"),
+                "'description' is required and must be a string".to_string(),
+            ));
+        }
+
+        let input_type = fields.get("input_type");
+        if input_type
+            .and_then(|value| {
+                serde_json::from_value::<RecipeParameterInputType>(value.clone()).ok()
+            })
+            .is_none()
+        {
+            issues.push(error(
+                pointer(&here, "input_type"),
+                format!(
+                    "Invalid input type {}, expected one of {}",
+                    input_type.map_or("(missing)".to_string(), ToString::to_string),
+                    INPUT_TYPES.join(", ")
+                ),
+            ));
+        }
+        let requirement = fields.get("requirement").and_then(|value| {
+            serde_json::from_value::<RecipeParameterRequirement>(value.clone()).ok()
+        });
+        match requirement {
+            None => issues.push(error(
+                pointer(&here, "requirement"),
+                format!(
+                    "Invalid requirement {}, expected one of {}",
+                    fields
+                        .get("requirement")
+                        .map_or("(missing)".to_string(), ToString::to_string),
+                    REQUIREMENTS.join(", ")
+                ),
+            )),
+            Some(RecipeParameterRequirement::Optional) if !fields.contains_key("default") => issues
+                .push(error(
+                    pointer(&here, "default"),
+                    "An optional parameter needs a default value".to_string(),
+                )),
+            Some(_) => {}
+        }
+        let options = fields.get("options").or_else(|| fields.get("enum"));
+        if input_type.and_then(Value::as_str) == Some("select")
+            && !options
+                .and_then(Value::as_array)
+                .is_some_and(|options| !options.is_empty())
+        {
+            issues.push(error(
+                pointer(&here, "options"),
+                "A select parameter needs options to choose from".to_string(),
+            ));
+        }
+    }
+
+    let mut undeclared: Vec<&String> = variables.difference(&declared).collect();
+    undeclared.sort();
+    for variable in undeclared {
+        let used_at =
+            find_reference(recipe, location, variable).unwrap_or(parameters_location.clone());
+        issues.push(error(
+            used_at,
+            format!(
+                "'{{{{ {} }}}}' is used but '{}' is not declared in parameters",
+                variable, variable
+            ),
+        ));
+    }
+}
+
+fn check_sub_recipes(
+    fields: &Map<String, Value>,
+    location: &str,
+    recipe_dir: Option<&Path>,
+    issues: &mut Vec<RecipeIssue>,
+) {
+    let sub_recipes_location = pointer(location, "sub_recipes");
+    let sub_recipes = match fields.get("sub_recipes") {
+        None | Some(Value::Null) => return,
+        Some(Value::Array(sub_recipes)) => sub_recipes,
+        Some(_) => {
+            issues.push(error(
+                sub_recipes_location,
+                "'sub_recipes' must be a list".to_string(),
+            ));
+            return;
+        }
+    };
+
+    let mut names = HashSet::new();
+    for (index, sub_recipe) in sub_recipes.iter().enumerate() {
+        let here = pointer(&sub_recipes_location, index);
+        let Some(sub_fields) = sub_recipe.as_object() else {
+            issues.push(error(here, "A sub-recipe must be a mapping".to_string()));
+            continue;
+        };
+        check_unknown_fields(sub_fields, SUB_RECIPE_FIELDS, &here, issues);
+
+        match sub_fields.get("name").and_then(Value::as_str) {
+            None => issues.push(error(
+                pointer(&here, "name"),
+                "'name' is required and must be a string".to_string(),
+            )),
+            Some(name) if !names.insert(name) => issues.push(error(
+                pointer(&here, "name"),
+                format!("Sub-recipe '{}' is declared more than once", name),
+            )),
+            Some(_) => {}
+        }
+
+        let path_location = pointer(&here, "path");
+        let Some(path) = sub_fields.get("path").and_then(Value::as_str) else {
+            issues.push(error(
+                path_location,
+                "'path' is required and must be a string".to_string(),
+            ));
+            continue;
+        };
+        if path.contains("{{") {
+            continue;
+        }
+        let resolved = if Path::new(path).is_absolute() {
+            Path::new(path).to_path_buf()
+        } else if let Some(dir) = recipe_dir {
+            dir.join(path)
+        } else {
+            issues.push(warning(
+                path_location,
+                format!(
+                    "Relative path '{}' can't be checked without the recipe's location",
+                    path
+                ),
+            ));
+            continue;
+        };
+        if !resolved.is_file() {
+            issues.push(error(
+                path_location,
+                format!("Sub-recipe file '{}' does not exist", resolved.display()),
+            ));
+        }
+    }
+}
+
+fn check_response_schema(
+    fields: &Map<String, Value>,
+    location: &str,
+    issues: &mut Vec<RecipeIssue>,
+) {
+    let Some(schema) = fields
+        .get("response")
+        .and_then(|response| response.get("json_schema"))
+    else {
+        return;
+    };
+    let schema_location = pointer(&pointer(location, "response"), "json_schema");
+    if schema.as_object().is_some_and(Map::is_empty) {
+        issues.push(error(
+            schema_location,
+            "The JSON schema must not be empty".to_string(),
+        ));
+    } else if let Err(e) = jsonschema::meta::validate(schema) {
+        issues.push(error(
+            schema_location,
+            format!("Not a valid JSON Schema: {}", e),
+        ));
+    } else if let Err(e) = jsonschema::validator_for(schema) {
+        issues.push(error(
+            schema_location,
+            format!("Not a usable JSON Schema: {}", e),
+        ));
+    }
+}
+
+// Call `visit` with the location and text of every string in `value`
+fn visit_strings(value: &Value, location: &str, visit: &mut dyn FnMut(&str, &str)) {
+    match value {
+        Value::String(text) => visit(location, text),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                visit_strings(item, &pointer(location, index), visit);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, item) in fields {
+                visit_strings(item, &pointer(location, key), visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+// The first string in `recipe` that uses the template variable `variable`
+fn find_reference(recipe: &Value, location: &str, variable: &str) -> Option<String> {
+    let reference = Regex::new(&format!(r"\{{\{{[^}}]*\b{}\b", regex::escape(variable))).ok()?;
+    let mut found = None;
+    visit_strings(recipe, location, &mut |here, text| {
+        if found.is_none() && reference.is_match(text) {
+            found = Some(here.to_string());
+        }
+    });
+    found
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues_at(content: &str, recipe_dir: Option<&Path>) -> Vec<(IssueSeverity, String)> {
+        validate_recipe_content(content, recipe_dir)
+            .into_iter()
+            .map(|issue| (issue.severity, issue.location))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_recipe_has_no_issues() {
+        let content = r#"
+version: 1.0.0
+title: Greeter
+description: Greets someone
+prompt: "Say hello to {{ name }} in {{ language }}"
+parameters:
+  - key: name
+    input_type: string
+    requirement: required
+    description: Who to greet
+  - key: language
+    input_type: select
+    requirement: optional
+    description: Language of the greeting
+    default: English
+    options: [English, Dutch]
+response:
+  json_schema:
+    type: object
+    properties:
+      greeting:
+        type: string
+"#;
+        assert_eq!(validate_recipe_content(content, None), Vec::new());
+    }
+
+    #[test]
+    fn test_bad_recipes() {
+        use IssueSeverity::{Error, Warning};
+
+        let corpus: &[(&str, &str, &[(IssueSeverity, &str)])] = &[
+            (
+                "unknown fields",
+                r#"{"title": "t", "description": "d", "promt": "hi", "instructions": "x",
+                    "author": {"contakt": "me"}}"#,
+                &[(Warning, "/promt"), (Warning, "/author/contakt")],
+            ),
+            (
+                "no instructions or prompt",
+                r#"{"title": "t", "description": "d"}"#,
+                &[(Error, "")],
+            ),
+            (
+                "missing title",
+                r#"{"description": "d", "prompt": "p"}"#,
+                &[(Error, "/title")],
+            ),
+            (
+                "undeclared parameter",
+                r#"{"title": "t", "description": "d", "instructions": "x",
+                    "prompt": "Summarize {{ topic }}"}"#,
+                &[(Error, "/prompt")],
+            ),
+            (
+                "unused parameter",
+                r#"{"title": "t", "description": "d", "prompt": "p",
+                    "parameters": [{"key": "topic", "input_type": "string",
+                                    "requirement": "required", "description": "x"}]}"#,
+                &[(Error, "/parameters/0/key")],
+            ),
+            (
+                "invalid input type",
+                r#"{"title": "t", "description": "d", "prompt": "{{ topic }}",
+                    "parameters": [{"key": "topic", "input_type": "text",
+                                    "requirement": "required", "description": "x"}]}"#,
+                &[(Error, "/parameters/0/input_type")],
+            ),
+            (
+                "optional parameter without default",
+                r#"{"title": "t", "description": "d", "prompt": "{{ topic }}",
+                    "parameters": [{"key": "topic", "input_type": "string",
+                                    "requirement": "optional", "description": "x"}]}"#,
+                &[(Error, "/parameters/0/default")],
+            ),
+            (
+                "missing sub-recipe",
+                r#"{"title": "t", "description": "d", "prompt": "p",
+                    "sub_recipes": [{"name": "child", "path": "/does/not/exist.yaml"}]}"#,
+                &[(Error, "/sub_recipes/0/path")],
+            ),
+            (
+                "relative sub-recipe without location",
+                r#"{"title": "t", "description": "d", "prompt": "p",
+                    "sub_recipes": [{"name": "child", "path": "child.yaml"}]}"#,
+                &[(Warning, "/sub_recipes/0/path")],
+            ),
+            (
+                "invalid json schema",
+                r#"{"title": "t", "description": "d", "prompt": "p",
+                    "response": {"json_schema": {"type": "objekt"}}}"#,
+                &[(Error, "/response/json_schema")],
+            ),
+            (
+                "empty json schema",
+                r#"{"title": "t", "description": "d", "prompt": "p",
+                    "response": {"json_schema": {}}}"#,
+                &[(Error, "/response/json_schema")],
+            ),
+            (
+                "nested recipe",
+                r#"{"recipe": {"title": "t", "description": "d", "instructions": "x",
+                    "prompt": "{{ topic }}"}}"#,
+                &[(Error, "/recipe/prompt")],
+            ),
+            (
+                "invalid retry",
+                r#"{"title": "t", "description": "d", "prompt": "p",
+                    "retry": {"max_retries": 0, "checks": []}}"#,
+                &[(Error, "/retry")],
+            ),
+            ("not a mapping", "- just\n- a list\n", &[(Error, "")]),
+        ];
+
+        for (name, content, expected) in corpus {
+            let expected: Vec<(IssueSeverity, String)> = expected
+                .iter()
+                .map(|(severity, location)| (*severity, location.to_string()))
+                .collect();
+            assert_eq!(issues_at(content, None), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_sub_recipe_resolved_against_recipe_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("child.yaml"), "title: child").unwrap();
+        let content = r#"{"title": "t", "description": "d", "prompt": "p",
+            "sub_recipes": [{"name": "child", "path": "child.yaml"},
+                            {"name": "other", "path": "other.yaml"}]}"#;
+        assert_eq!(
+            issues_at(content, Some(temp_dir.path())),
+            vec![(IssueSeverity::Error, "/sub_recipes/1/path".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_suggests_close_field_name() {
+        let issues = validate_recipe_content(
+            r#"{"title": "t", "description": "d", "prompt": "p", "instructons": "x"}"#,
+            None,
+        );
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("did you mean 'instructions'?"));
+    }
+
+    #[test]
+    fn test_validate_built_recipe() {
+        let recipe = Recipe::builder()
+            .title("Built")
+            .description("A built recipe")
+            .instructions("Write about {{ topic }}")
+            .build()
+            .unwrap();
+        let issues = validate_recipe(&recipe, None);
+        assert_eq!(
+            error_summary(&issues),
+            Some(
+                "/instructions: '{{ topic }}' is used but 'topic' is not declared in parameters"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -1,13 +1,25 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use thiserror::Error;
 
+use crate::config::RecipeSigningKey;
+use crate::recipe::risk_summary::{summarize_recipe_risks, RecipeRiskSummary};
 use crate::recipe::Recipe;
 
+/// Separates the encoded recipe from the signing key id and the signature. It is not in any
+/// base64 alphabet, so unsigned links never contain it.
+const SIGNATURE_SEPARATOR: char = '.';
+
 #[derive(Error, Debug)]
 pub enum DecodeError {
     #[error("All decoding methods failed")]
     AllMethodsFailed,
+    #[error("The recipe signature doesn't match its contents")]
+    InvalidSignature,
 }
 
 pub fn encode(recipe: &Recipe) -> Result<String, serde_json::Error> {
@@ -16,7 +28,79 @@ pub fn encode(recipe: &Recipe) -> Result<String, serde_json::Error> {
     Ok(encoded)
 }
 
+/// Encode `recipe` with an HMAC-SHA256 signature by `key`, as `<recipe>.<key id>.<signature>`
+pub fn encode_signed(recipe: &Recipe, key: &RecipeSigningKey) -> Result<String, serde_json::Error> {
+    let encoded = encode(recipe)?;
+    let signature = URL_SAFE_NO_PAD.encode(mac(&encoded, key).finalize().into_bytes());
+    Ok(format!(
+        "{}{}{}{}{}",
+        encoded, SIGNATURE_SEPARATOR, key.id, SIGNATURE_SEPARATOR, signature
+    ))
+}
+
+fn mac(encoded: &str, key: &RecipeSigningKey) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&key.secret_bytes()).expect("HMAC accepts any key length");
+    mac.update(encoded.as_bytes());
+    mac
+}
+
+/// Split a link into the encoded recipe and, for signed links, the key id and signature
+fn split_signature(link: &str) -> (&str, Option<(&str, &str)>) {
+    let mut parts = link.splitn(3, SIGNATURE_SEPARATOR);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(encoded), Some(key_id), Some(signature)) => (encoded, Some((key_id, signature))),
+        _ => (link, None),
+    }
+}
+
+/// A decoded recipe with what is known about where it came from
+#[derive(Debug, Clone)]
+pub struct VerifiedRecipe {
+    pub recipe: Recipe,
+    /// Whether the link was signed with one of the trusted keys
+    pub trusted: bool,
+    /// Id of the key the link was signed with, trusted or not
+    pub signer: Option<String>,
+    pub risks: RecipeRiskSummary,
+}
+
+/// Decode a link and check its signature against `trusted_keys`, by key id. Unsigned links and
+/// links signed with an unknown key decode as untrusted; a link whose signature doesn't match
+/// a trusted key has been tampered with and is rejected.
+pub fn decode_verified(
+    link: &str,
+    trusted_keys: &HashMap<String, RecipeSigningKey>,
+) -> Result<VerifiedRecipe, DecodeError> {
+    let (encoded, signature) = split_signature(link);
+    let recipe = decode(encoded)?;
+
+    let trusted = match signature
+        .and_then(|(key_id, signature)| trusted_keys.get(key_id).map(|key| (key, signature)))
+    {
+        Some((key, signature)) => {
+            let signature = URL_SAFE_NO_PAD
+                .decode(signature)
+                .map_err(|_| DecodeError::InvalidSignature)?;
+            mac(encoded, key)
+                .verify_slice(&signature)
+                .map_err(|_| DecodeError::InvalidSignature)?;
+            true
+        }
+        None => false,
+    };
+
+    Ok(VerifiedRecipe {
+        risks: summarize_recipe_risks(&recipe),
+        recipe,
+        trusted,
+        signer: signature.map(|(key_id, _)| key_id.to_string()),
+    })
+}
+
+/// Decode a link, ignoring any signature
 pub fn decode(link: &str) -> Result<Recipe, DecodeError> {
+    let (link, _) = split_signature(link);
     // Handle the current format: URL-safe Base64 without padding.
     if let Ok(decoded_bytes) = URL_SAFE_NO_PAD.decode(link) {
         if let Ok(recipe_json) = String::from_utf8(decoded_bytes) {
@@ -99,6 +183,61 @@ mod tests {
         assert_eq!(recipe.instructions, decoded_recipe.instructions);
     }
 
+    #[test]
+    fn test_signed_link_is_trusted_only_with_its_key() {
+        let recipe = create_test_recipe();
+        let key = RecipeSigningKey::generate();
+        let signed = encode_signed(&recipe, &key).unwrap();
+
+        let trusted_keys = HashMap::from([(key.id.clone(), key.clone())]);
+        let verified = decode_verified(&signed, &trusted_keys).unwrap();
+        assert!(verified.trusted);
+        assert_eq!(verified.signer.as_deref(), Some(key.id.as_str()));
+        assert_eq!(verified.recipe.title, recipe.title);
+
+        let other_key = RecipeSigningKey::generate();
+        let other_keys = HashMap::from([(other_key.id.clone(), other_key)]);
+        let verified = decode_verified(&signed, &other_keys).unwrap();
+        assert!(!verified.trusted);
+        assert_eq!(verified.signer.as_deref(), Some(key.id.as_str()));
+
+        // Signed links still decode where signatures aren't checked
+        assert_eq!(decode(&signed).unwrap().title, recipe.title);
+    }
+
+    #[test]
+    fn test_unsigned_link_is_untrusted() {
+        let key = RecipeSigningKey::generate();
+        let trusted_keys = HashMap::from([(key.id.clone(), key)]);
+        let encoded = encode(&create_test_recipe()).unwrap();
+
+        let verified = decode_verified(&encoded, &trusted_keys).unwrap();
+        assert!(!verified.trusted);
+        assert!(verified.signer.is_none());
+    }
+
+    #[test]
+    fn test_tampered_link_is_rejected() {
+        let key = RecipeSigningKey::generate();
+        let trusted_keys = HashMap::from([(key.id.clone(), key.clone())]);
+        let signed = encode_signed(&create_test_recipe(), &key).unwrap();
+        let (_, signature) = split_signature(&signed);
+        let (key_id, signature) = signature.unwrap();
+
+        let mut tampered_recipe = create_test_recipe();
+        tampered_recipe.instructions = Some("Run rm -rf ~".to_string());
+        let tampered = format!(
+            "{}.{}.{}",
+            encode(&tampered_recipe).unwrap(),
+            key_id,
+            signature
+        );
+        assert!(matches!(
+            decode_verified(&tampered, &trusted_keys),
+            Err(DecodeError::InvalidSignature)
+        ));
+    }
+
     #[test]
     fn test_decode_invalid_input() {
         let result = decode("invalid_base64!");
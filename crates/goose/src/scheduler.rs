@@ -3,6 +3,7 @@ use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
@@ -14,18 +15,30 @@ use tokio_cron_scheduler::{job::JobId, Job, JobScheduler as TokioJobScheduler};
 
 use crate::agents::AgentEvent;
 use crate::agents::{Agent, SessionConfig};
-use crate::config::{self, Config};
+use crate::config::{self, Config, ExtensionConfigManager};
 use crate::message::Message;
 use crate::providers::base::Provider as GooseProvider; // Alias to avoid conflict in test section
 use crate::providers::create;
+use crate::recipe::build_recipe::build_recipe_with_values;
+use crate::recipe::read_recipe_file_content::read_recipe_file;
+use crate::recipe::run_recipe::{prepare_agent_for_recipe, recipe_initial_message};
+use crate::recipe::validate_recipe::{error_summary, validate_recipe_content};
 use crate::recipe::Recipe;
+use crate::scheduler_cron::resolve_timezone;
+use crate::scheduler_gate::{ExecutionConditions, MachineState, SystemMachineState};
+use crate::scheduler_spec::{parse_interval, ScheduleKind, ScheduleSpec};
 use crate::scheduler_trait::SchedulerTrait;
+use crate::scheduler_webhook::{send_notification, RunEvent, RunNotification, WebhookNotification};
 use crate::session;
 use crate::session::storage::SessionMetadata;
+use crate::session_callback::{
+    self, RecipeInfo, SessionCallback, SessionResult, SessionResultPayload,
+};
 
-// Track running tasks with their abort handles
-type RunningTasksMap = HashMap<String, tokio::task::AbortHandle>;
-type JobsMap = HashMap<String, (JobId, ScheduledJob)>;
+// Track running tasks with their abort handles; a job allowing overlap can have several
+type RunningTasksMap = HashMap<String, Vec<tokio::task::AbortHandle>>;
+// One-shot jobs that already ran have no tokio-cron-scheduler job
+type JobsMap = HashMap<String, (Option<JobId>, ScheduledJob)>;
 
 /// Normalize a cron string so that:
 /// 1. It is always in **quartz 7-field format** expected by Temporal
@@ -93,6 +106,7 @@ pub enum SchedulerError {
     AgentSetupError(String),
     PersistError(String),
     CronParseError(String),
+    InvalidTimezone(String),
     SchedulerInternalError(String),
     AnyhowError(anyhow::Error),
 }
@@ -107,6 +121,7 @@ impl std::fmt::Display for SchedulerError {
             SchedulerError::AgentSetupError(e) => write!(f, "Agent setup error: {}", e),
             SchedulerError::PersistError(e) => write!(f, "Failed to persist schedules: {}", e),
             SchedulerError::CronParseError(e) => write!(f, "Invalid cron string: {}", e),
+            SchedulerError::InvalidTimezone(tz) => write!(f, "Unknown timezone '{}'", tz),
             SchedulerError::SchedulerInternalError(e) => {
                 write!(f, "Scheduler internal error: {}", e)
             }
@@ -143,11 +158,180 @@ impl From<anyhow::Error> for SchedulerError {
     }
 }
 
+/// Number of skipped runs kept in a job's history
+const MAX_SKIPPED_RUNS: usize = 20;
+
+/// What happens when a job fires while its previous run is still going
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcurrencyPolicy {
+    /// Drop the new run and record it as skipped
+    #[default]
+    Skip,
+    /// Start the new run as soon as the current one finishes; at most one run waits
+    Queue,
+    /// Start the new run alongside the current one
+    Allow,
+}
+
+/// A cron firing that did not run
+#[derive(Clone, Serialize, Deserialize, Debug, utoipa::ToSchema)]
+pub struct SkippedRun {
+    pub scheduled_at: DateTime<Utc>,
+    pub reason: String,
+}
+
+/// Config key for the number of attempts kept in each job's run history
+pub const RUN_HISTORY_LIMIT_KEY: &str = "GOOSE_SCHEDULER_RUN_HISTORY_LIMIT";
+const DEFAULT_RUN_HISTORY_LIMIT: usize = 50;
+
+/// Number of recent runs the stats in [`RunStats`] cover
+const RUN_STATS_WINDOW: usize = 30;
+
+/// Longest wait between two attempts of a run
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How often a failed run is retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first run
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubles for each retry after that
+    pub backoff_seconds: u64,
+}
+
+impl RetryPolicy {
+    /// The wait before attempt `attempt + 1`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_secs(self.backoff_seconds.saturating_mul(factor)).min(MAX_RETRY_BACKOFF)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Success,
+    Error,
+    /// Stopped through `kill_running_job`
+    Killed,
+    /// Dropped by the concurrency policy, see [`SkippedRun`]
+    Skipped,
+}
+
+/// Tokens used by a run's session
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RunUsage {
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub total_tokens: Option<i32>,
+}
+
+/// Outcome of a job's recent runs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct RunStats {
+    /// Number of runs the stats cover: the most recent executed runs, skipped runs excluded
+    pub runs: usize,
+    /// Share of those runs that succeeded, from 0 to 1
+    pub success_rate: Option<f64>,
+    pub average_duration_seconds: Option<f64>,
+}
+
+/// One attempt at running a job
+#[derive(Clone, Serialize, Deserialize, Debug, utoipa::ToSchema)]
+pub struct RunAttempt {
+    pub session_id: Option<String>,
+    /// 1 for the first try of a run, 2 for its first retry, and so on
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: RunStatus,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub usage: Option<RunUsage>,
+}
+
+impl RunAttempt {
+    fn duration_seconds(&self) -> f64 {
+        (self.finished_at - self.started_at).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+impl From<&SkippedRun> for RunAttempt {
+    fn from(skipped: &SkippedRun) -> Self {
+        RunAttempt {
+            session_id: None,
+            attempt: 1,
+            started_at: skipped.scheduled_at,
+            finished_at: skipped.scheduled_at,
+            status: RunStatus::Skipped,
+            error: Some(skipped.reason.clone()),
+            usage: None,
+        }
+    }
+}
+
+/// How an attempt ended, see [`run_attempt`]
+#[derive(Debug)]
+enum AttemptOutcome {
+    Succeeded(String),
+    Failed {
+        session_id: Option<String>,
+        error: String,
+    },
+    Cancelled,
+}
+
+impl AttemptOutcome {
+    fn session_id(&self) -> Option<&str> {
+        match self {
+            AttemptOutcome::Succeeded(session_id) => Some(session_id),
+            AttemptOutcome::Failed { session_id, .. } => session_id.as_deref(),
+            AttemptOutcome::Cancelled => None,
+        }
+    }
+}
+
+/// What to do with a cron firing, see [`ScheduledJob::claim_run`]
+#[derive(Debug, PartialEq, Eq)]
+enum FireDecision {
+    Start,
+    Queued,
+    Skipped,
+    Paused,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, utoipa::ToSchema)]
 pub struct ScheduledJob {
     pub id: String,
+    /// The recipe file the job runs, empty for a prompt job
+    #[serde(default)]
     pub source: String,
+    /// The prompt a prompt job runs instead of a recipe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Directory a prompt job runs in; the scheduler's working directory when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    /// Names of configured extensions enabled for a prompt job
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// Values for the recipe's parameters; declared defaults fill in the rest
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub params: HashMap<String, String>,
+    #[serde(default)]
+    pub kind: ScheduleKind,
+    /// The cron expression of a `cron` job, empty for other kinds
     pub cron: String,
+    /// When a `run_at` job fires
+    #[serde(default)]
+    pub run_at: Option<DateTime<Utc>>,
+    /// The interval of an `every` job, such as `4h`
+    #[serde(default)]
+    pub every: Option<String>,
+    /// When the interval of an `every` job started counting
+    #[serde(default)]
+    pub interval_anchor: Option<DateTime<Utc>>,
     pub last_run: Option<DateTime<Utc>>,
     #[serde(default)]
     pub currently_running: bool,
@@ -159,6 +343,215 @@ pub struct ScheduledJob {
     pub process_start_time: Option<DateTime<Utc>>,
     #[serde(default)]
     pub execution_mode: Option<String>, // "foreground" or "background"
+    /// IANA timezone the cron expression is evaluated in, e.g. "Europe/Berlin"; UTC when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub concurrency_policy: ConcurrencyPolicy,
+    /// A run is waiting for the current one to finish
+    #[serde(default)]
+    pub queued_run: bool,
+    /// The most recent runs dropped by the concurrency policy, oldest first
+    #[serde(default)]
+    pub skipped_runs: Vec<SkippedRun>,
+    /// Retry failed runs; a failed run is not retried when unset
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Notified when a run fails after its last attempt
+    #[serde(default)]
+    pub on_failure: Option<WebhookNotification>,
+    /// Notified when a run succeeds
+    #[serde(default)]
+    pub on_success: Option<WebhookNotification>,
+    /// Sent the result of every finished run, see [`crate::session_callback`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+    /// Key the callback body is signed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_secret: Option<String>,
+    /// The most recent attempts, oldest first
+    #[serde(default)]
+    pub run_history: Vec<RunAttempt>,
+    /// Conditions that hold runs back, over the global ones
+    #[serde(default)]
+    pub conditions: ExecutionConditions,
+}
+
+impl ScheduledJob {
+    /// When the job fires, from its `kind` and the matching field
+    pub fn spec(&self) -> Result<ScheduleSpec, SchedulerError> {
+        let missing = |field: &str| {
+            SchedulerError::CronParseError(format!("Job '{}' has no {}", self.id, field))
+        };
+        match self.kind {
+            ScheduleKind::Cron => Ok(ScheduleSpec::Cron(self.cron.clone())),
+            ScheduleKind::RunAt => self
+                .run_at
+                .map(ScheduleSpec::RunAt)
+                .ok_or_else(|| missing("run_at time")),
+            ScheduleKind::Every => self
+                .every
+                .clone()
+                .map(ScheduleSpec::Every)
+                .ok_or_else(|| missing("interval")),
+        }
+    }
+
+    pub fn set_spec(&mut self, spec: ScheduleSpec) {
+        self.kind = spec.kind();
+        self.cron = String::new();
+        self.run_at = None;
+        self.every = None;
+        self.interval_anchor = None;
+        match spec {
+            ScheduleSpec::Cron(expression) => self.cron = expression,
+            ScheduleSpec::RunAt(run_at) => self.run_at = Some(run_at),
+            ScheduleSpec::Every(interval) => self.every = Some(interval),
+        }
+    }
+
+    /// How runs of the job are tagged in traces
+    pub fn session_mode(&self) -> &'static str {
+        if self.prompt.is_some() {
+            "scheduled_prompt"
+        } else {
+            "scheduled_recipe"
+        }
+    }
+
+    // What the job runs, for log and error messages
+    fn task_label(&self) -> String {
+        match &self.prompt {
+            Some(_) => "prompt".to_string(),
+            None => format!("recipe '{}'", self.source),
+        }
+    }
+
+    /// A one-shot job that has run at or after its `run_at` time
+    pub fn completed(&self) -> bool {
+        match (self.kind, self.run_at, self.last_run) {
+            (ScheduleKind::RunAt, Some(run_at), Some(last_run)) => {
+                last_run >= run_at && !self.currently_running
+            }
+            _ => false,
+        }
+    }
+
+    /// The next time the job fires after `after`, `None` when paused or completed
+    pub fn next_fire(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.paused || self.completed() {
+            return None;
+        }
+        let timezone = resolve_timezone(self.timezone.as_deref()).ok()?;
+        self.spec()
+            .ok()?
+            .next_fire(&timezone, self.interval_anchor, after)
+    }
+
+    fn record_skipped_run(&mut self, scheduled_at: DateTime<Utc>, reason: &str) {
+        tracing::info!("Skipping run of job '{}': {}", self.id, reason);
+        self.skipped_runs.push(SkippedRun {
+            scheduled_at,
+            reason: reason.to_string(),
+        });
+        let excess = self.skipped_runs.len().saturating_sub(MAX_SKIPPED_RUNS);
+        self.skipped_runs.drain(..excess);
+    }
+
+    fn record_attempt(&mut self, attempt: RunAttempt, limit: usize) {
+        self.run_history.push(attempt);
+        let excess = self.run_history.len().saturating_sub(limit);
+        self.run_history.drain(..excess);
+    }
+
+    /// Recorded attempts and skipped runs, newest first
+    pub fn runs(&self) -> Vec<RunAttempt> {
+        let mut runs: Vec<RunAttempt> = self
+            .run_history
+            .iter()
+            .cloned()
+            .chain(self.skipped_runs.iter().map(RunAttempt::from))
+            .collect();
+        runs.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        runs
+    }
+
+    /// Success rate and average duration of the most recent executed runs
+    pub fn run_stats(&self) -> RunStats {
+        let recent: Vec<&RunAttempt> = self
+            .run_history
+            .iter()
+            .rev()
+            .filter(|run| run.status != RunStatus::Skipped)
+            .take(RUN_STATS_WINDOW)
+            .collect();
+        if recent.is_empty() {
+            return RunStats::default();
+        }
+        let runs = recent.len();
+        let successes = recent
+            .iter()
+            .filter(|run| run.status == RunStatus::Success)
+            .count();
+        let total_duration: f64 = recent.iter().map(|run| run.duration_seconds()).sum();
+        RunStats {
+            runs,
+            success_rate: Some(successes as f64 / runs as f64),
+            average_duration_seconds: Some(total_duration / runs as f64),
+        }
+    }
+
+    fn max_attempts(&self) -> u32 {
+        self.retry.map_or(1, |retry| retry.max_attempts.max(1))
+    }
+
+    fn start_run(&mut self, now: DateTime<Utc>) {
+        self.last_run = Some(now);
+        self.currently_running = true;
+        self.process_start_time = Some(now);
+    }
+
+    /// Apply the concurrency policy to a cron firing at `now`
+    fn claim_run(&mut self, now: DateTime<Utc>) -> FireDecision {
+        if self.paused {
+            return FireDecision::Paused;
+        }
+        if self.currently_running {
+            match self.concurrency_policy {
+                ConcurrencyPolicy::Allow => {}
+                ConcurrencyPolicy::Queue if !self.queued_run => {
+                    self.queued_run = true;
+                    return FireDecision::Queued;
+                }
+                ConcurrencyPolicy::Queue => {
+                    self.record_skipped_run(now, "another run is already queued");
+                    return FireDecision::Skipped;
+                }
+                ConcurrencyPolicy::Skip => {
+                    self.record_skipped_run(now, "the previous run was still in progress");
+                    return FireDecision::Skipped;
+                }
+            }
+        }
+        self.start_run(now);
+        FireDecision::Start
+    }
+
+    /// Record that a run finished, returning true when a queued run takes its place
+    fn finish_run(&mut self, others_running: bool, now: DateTime<Utc>) -> bool {
+        if self.queued_run {
+            self.queued_run = false;
+            self.current_session_id = None;
+            self.start_run(now);
+            return true;
+        }
+        if !others_running {
+            self.currently_running = false;
+            self.current_session_id = None;
+            self.process_start_time = None;
+        }
+        false
+    }
 }
 
 async fn persist_jobs_from_arc(
@@ -180,10 +573,19 @@ pub struct Scheduler {
     jobs: Arc<Mutex<JobsMap>>,
     storage_path: PathBuf,
     running_tasks: Arc<Mutex<RunningTasksMap>>,
+    machine_state: Arc<dyn MachineState>,
 }
 
 impl Scheduler {
     pub async fn new(storage_path: PathBuf) -> Result<Arc<Self>, SchedulerError> {
+        Self::with_machine_state(storage_path, Arc::new(SystemMachineState)).await
+    }
+
+    /// A scheduler that checks execution conditions against `machine_state`
+    pub async fn with_machine_state(
+        storage_path: PathBuf,
+        machine_state: Arc<dyn MachineState>,
+    ) -> Result<Arc<Self>, SchedulerError> {
         let internal_scheduler = TokioJobScheduler::new()
             .await
             .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))?;
@@ -196,6 +598,7 @@ impl Scheduler {
             jobs,
             storage_path,
             running_tasks,
+            machine_state,
         });
 
         arc_self.load_jobs_from_storage().await?;
@@ -208,6 +611,91 @@ impl Scheduler {
         Ok(arc_self)
     }
 
+    /// A tokio-cron-scheduler job that fires `job` on its schedule, or `None` for a completed
+    /// one-shot job. Starts the interval of an `every` job.
+    fn schedule_task(&self, job: &mut ScheduledJob) -> Result<Option<Job>, SchedulerError> {
+        let spec = job.spec()?;
+        if job.completed() {
+            return Ok(None);
+        }
+        if let ScheduleSpec::Every(_) = spec {
+            job.interval_anchor = Some(Utc::now());
+        }
+
+        let job_for_task = job.clone();
+        let jobs_arc = self.jobs.clone();
+        let storage_path = self.storage_path.clone();
+        let running_tasks = self.running_tasks.clone();
+        let machine_state = self.machine_state.clone();
+        let fire = move || {
+            fire_scheduled_job(
+                job_for_task.clone(),
+                jobs_arc.clone(),
+                storage_path.clone(),
+                running_tasks.clone(),
+                machine_state.clone(),
+            )
+        };
+
+        let task = match spec {
+            ScheduleSpec::Cron(cron) => {
+                let normalized_cron = normalize_cron_expression(&cron);
+                // Convert from 7-field (Temporal format) to 6-field (tokio-cron-scheduler format)
+                let tokio_cron = {
+                    let parts: Vec<&str> = normalized_cron.split_whitespace().collect();
+                    if parts.len() == 7 {
+                        parts[..6].join(" ")
+                    } else {
+                        normalized_cron.clone()
+                    }
+                };
+                if tokio_cron != cron {
+                    tracing::info!(
+                        "Converted cron expression from '{}' to '{}' for tokio-cron-scheduler",
+                        cron,
+                        tokio_cron
+                    );
+                }
+                let tz = resolve_timezone(job.timezone.as_deref())?;
+                Job::new_async_tz(tokio_cron.as_str(), tz, move |_uuid, _l| Box::pin(fire()))
+            }
+            ScheduleSpec::Every(interval) => {
+                let interval = parse_interval(&interval)
+                    .map_err(|e| SchedulerError::CronParseError(e.to_string()))?;
+                Job::new_repeated_async(interval, move |_uuid, _l| Box::pin(fire()))
+            }
+            ScheduleSpec::RunAt(run_at) => {
+                // A time missed while the scheduler was not running fires right away
+                let delay = (run_at - Utc::now()).to_std().unwrap_or_default();
+                Job::new_one_shot_async(delay, move |_uuid, _l| Box::pin(fire()))
+            }
+        };
+        task.map(Some)
+            .map_err(|e| SchedulerError::CronParseError(e.to_string()))
+    }
+
+    /// Schedule `job` with tokio-cron-scheduler, see [`Self::schedule_task`]
+    async fn add_task(&self, job: &mut ScheduledJob) -> Result<Option<JobId>, SchedulerError> {
+        let Some(task) = self.schedule_task(job)? else {
+            return Ok(None);
+        };
+        self.internal_scheduler
+            .add(task)
+            .await
+            .map(Some)
+            .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))
+    }
+
+    async fn remove_task(&self, job_uuid: Option<JobId>) -> Result<(), SchedulerError> {
+        let Some(job_uuid) = job_uuid else {
+            return Ok(());
+        };
+        self.internal_scheduler
+            .remove(&job_uuid)
+            .await
+            .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))
+    }
+
     pub async fn add_scheduled_job(
         &self,
         original_job_spec: ScheduledJob,
@@ -217,6 +705,19 @@ impl Scheduler {
             return Err(SchedulerError::JobIdExists(original_job_spec.id.clone()));
         }
 
+        if original_job_spec.prompt.is_some() {
+            check_prompt_job(&original_job_spec)?;
+            let mut stored_job = original_job_spec;
+            stored_job.current_session_id = None;
+            stored_job.process_start_time = None;
+
+            tracing::info!("Scheduling job '{}' {}", stored_job.id, stored_job.spec()?);
+            let job_uuid = self.add_task(&mut stored_job).await?;
+            jobs_guard.insert(stored_job.id.clone(), (job_uuid, stored_job));
+            self.persist_jobs_to_storage_with_guard(&jobs_guard).await?;
+            return Ok(());
+        }
+
         let original_recipe_path = Path::new(&original_job_spec.source);
         if !original_recipe_path.exists() {
             return Err(SchedulerError::RecipeLoadError(format!(
@@ -230,6 +731,22 @@ impl Scheduler {
                 original_job_spec.source
             )));
         }
+        let recipe_content = fs::read_to_string(original_recipe_path)?;
+        let issues = validate_recipe_content(&recipe_content, original_recipe_path.parent());
+        if let Some(errors) = error_summary(&issues) {
+            return Err(SchedulerError::RecipeLoadError(format!(
+                "Recipe {} is invalid: {}",
+                original_job_spec.source, errors
+            )));
+        }
+        let recipe_file = read_recipe_file(original_recipe_path)
+            .map_err(|e| SchedulerError::RecipeLoadError(e.to_string()))?;
+        build_recipe_with_values(recipe_file, &original_job_spec.params).map_err(|e| {
+            SchedulerError::RecipeLoadError(format!(
+                "Recipe {} can't run with the job's parameters: {}",
+                original_job_spec.source, e
+            ))
+        })?;
 
         let scheduled_recipes_dir = get_default_scheduled_recipes_dir()?;
         let original_extension = original_recipe_path
@@ -263,153 +780,8 @@ impl Scheduler {
         stored_job.process_start_time = None;
         tracing::info!("Updated job source path to: {}", stored_job.source);
 
-        let job_for_task = stored_job.clone();
-        let jobs_arc_for_task = self.jobs.clone();
-        let storage_path_for_task = self.storage_path.clone();
-        let running_tasks_for_task = self.running_tasks.clone();
-
-        tracing::info!("Attempting to parse cron expression: '{}'", stored_job.cron);
-        let normalized_cron = normalize_cron_expression(&stored_job.cron);
-        // Convert from 7-field (Temporal format) to 6-field (tokio-cron-scheduler format)
-        let tokio_cron = {
-            let parts: Vec<&str> = normalized_cron.split_whitespace().collect();
-            if parts.len() == 7 {
-                parts[..6].join(" ")
-            } else {
-                normalized_cron.clone()
-            }
-        };
-        if tokio_cron != stored_job.cron {
-            tracing::info!(
-                "Converted cron expression from '{}' to '{}' for tokio-cron-scheduler",
-                stored_job.cron,
-                tokio_cron
-            );
-        }
-        let cron_task = Job::new_async(&tokio_cron, move |_uuid, _l| {
-            let task_job_id = job_for_task.id.clone();
-            let current_jobs_arc = jobs_arc_for_task.clone();
-            let local_storage_path = storage_path_for_task.clone();
-            let job_to_execute = job_for_task.clone(); // Clone for run_scheduled_job_internal
-            let running_tasks_arc = running_tasks_for_task.clone();
-
-            Box::pin(async move {
-                // Check if the job is paused before executing
-                let should_execute = {
-                    let jobs_map_guard = current_jobs_arc.lock().await;
-                    if let Some((_, current_job_in_map)) = jobs_map_guard.get(&task_job_id) {
-                        !current_job_in_map.paused
-                    } else {
-                        false
-                    }
-                };
-
-                if !should_execute {
-                    tracing::info!("Skipping execution of paused job '{}'", &task_job_id);
-                    return;
-                }
-
-                let current_time = Utc::now();
-                let mut needs_persist = false;
-                {
-                    let mut jobs_map_guard = current_jobs_arc.lock().await;
-                    if let Some((_, current_job_in_map)) = jobs_map_guard.get_mut(&task_job_id) {
-                        current_job_in_map.last_run = Some(current_time);
-                        current_job_in_map.currently_running = true;
-                        current_job_in_map.process_start_time = Some(current_time);
-                        needs_persist = true;
-                    }
-                }
-
-                if needs_persist {
-                    if let Err(e) =
-                        persist_jobs_from_arc(&local_storage_path, &current_jobs_arc).await
-                    {
-                        tracing::error!(
-                            "Failed to persist last_run update for job {}: {}",
-                            &task_job_id,
-                            e
-                        );
-                    }
-                }
-
-                // Spawn the job execution as an abortable task
-                let job_task = tokio::spawn(run_scheduled_job_internal(
-                    job_to_execute.clone(),
-                    None,
-                    Some(current_jobs_arc.clone()),
-                    Some(task_job_id.clone()),
-                ));
-
-                // Store the abort handle at the scheduler level
-                {
-                    let mut running_tasks_guard = running_tasks_arc.lock().await;
-                    running_tasks_guard.insert(task_job_id.clone(), job_task.abort_handle());
-                }
-
-                // Wait for the job to complete or be aborted
-                let result = job_task.await;
-
-                // Remove the abort handle
-                {
-                    let mut running_tasks_guard = running_tasks_arc.lock().await;
-                    running_tasks_guard.remove(&task_job_id);
-                }
-
-                // Update the job status after execution
-                {
-                    let mut jobs_map_guard = current_jobs_arc.lock().await;
-                    if let Some((_, current_job_in_map)) = jobs_map_guard.get_mut(&task_job_id) {
-                        current_job_in_map.currently_running = false;
-                        current_job_in_map.current_session_id = None;
-                        current_job_in_map.process_start_time = None;
-                        needs_persist = true;
-                    }
-                }
-
-                if needs_persist {
-                    if let Err(e) =
-                        persist_jobs_from_arc(&local_storage_path, &current_jobs_arc).await
-                    {
-                        tracing::error!(
-                            "Failed to persist running status update for job {}: {}",
-                            &task_job_id,
-                            e
-                        );
-                    }
-                }
-
-                match result {
-                    Ok(Ok(_session_id)) => {
-                        tracing::info!("Scheduled job '{}' completed successfully", &task_job_id);
-                    }
-                    Ok(Err(e)) => {
-                        tracing::error!(
-                            "Scheduled job '{}' execution failed: {}",
-                            &e.job_id,
-                            e.error
-                        );
-                    }
-                    Err(join_error) if join_error.is_cancelled() => {
-                        tracing::info!("Scheduled job '{}' was cancelled/killed", &task_job_id);
-                    }
-                    Err(join_error) => {
-                        tracing::error!(
-                            "Scheduled job '{}' task failed: {}",
-                            &task_job_id,
-                            join_error
-                        );
-                    }
-                }
-            })
-        })
-        .map_err(|e| SchedulerError::CronParseError(e.to_string()))?;
-
-        let job_uuid = self
-            .internal_scheduler
-            .add(cron_task)
-            .await
-            .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))?;
+        tracing::info!("Scheduling job '{}' {}", stored_job.id, stored_job.spec()?);
+        let job_uuid = self.add_task(&mut stored_job).await?;
 
         jobs_guard.insert(stored_job.id.clone(), (job_uuid, stored_job));
         // Pass the jobs_guard by reference for the initial persist after adding a job
@@ -432,165 +804,20 @@ impl Scheduler {
 
         let mut jobs_guard = self.jobs.lock().await;
         for job_to_load in list {
-            if !Path::new(&job_to_load.source).exists() {
+            if job_to_load.prompt.is_none() && !Path::new(&job_to_load.source).exists() {
                 tracing::warn!("Recipe file {} for scheduled job {} not found in shared store. Skipping job load.", job_to_load.source, job_to_load.id);
                 continue;
             }
 
-            let job_for_task = job_to_load.clone();
-            let jobs_arc_for_task = self.jobs.clone();
-            let storage_path_for_task = self.storage_path.clone();
-            let running_tasks_for_task = self.running_tasks.clone();
-
-            tracing::info!(
-                "Loading job '{}' with cron expression: '{}'",
-                job_to_load.id,
-                job_to_load.cron
-            );
-            let normalized_cron = normalize_cron_expression(&job_to_load.cron);
-            // Convert from 7-field (Temporal format) to 6-field (tokio-cron-scheduler format)
-            let tokio_cron = {
-                let parts: Vec<&str> = normalized_cron.split_whitespace().collect();
-                if parts.len() == 7 {
-                    parts[..6].join(" ")
-                } else {
-                    normalized_cron.clone()
-                }
-            };
-            if tokio_cron != job_to_load.cron {
-                tracing::info!(
-                    "Converted cron expression from '{}' to '{}' for tokio-cron-scheduler",
-                    job_to_load.cron,
-                    tokio_cron
-                );
-            }
-            let cron_task = Job::new_async(&tokio_cron, move |_uuid, _l| {
-                let task_job_id = job_for_task.id.clone();
-                let current_jobs_arc = jobs_arc_for_task.clone();
-                let local_storage_path = storage_path_for_task.clone();
-                let job_to_execute = job_for_task.clone(); // Clone for run_scheduled_job_internal
-                let running_tasks_arc = running_tasks_for_task.clone();
-
-                Box::pin(async move {
-                    // Check if the job is paused before executing
-                    let should_execute = {
-                        let jobs_map_guard = current_jobs_arc.lock().await;
-                        if let Some((_, stored_job)) = jobs_map_guard.get(&task_job_id) {
-                            !stored_job.paused
-                        } else {
-                            false
-                        }
-                    };
-
-                    if !should_execute {
-                        tracing::info!("Skipping execution of paused job '{}'", &task_job_id);
-                        return;
-                    }
-
-                    let current_time = Utc::now();
-                    let mut needs_persist = false;
-                    {
-                        let mut jobs_map_guard = current_jobs_arc.lock().await;
-                        if let Some((_, stored_job)) = jobs_map_guard.get_mut(&task_job_id) {
-                            stored_job.last_run = Some(current_time);
-                            stored_job.currently_running = true;
-                            stored_job.process_start_time = Some(current_time);
-                            needs_persist = true;
-                        }
-                    }
-
-                    if needs_persist {
-                        if let Err(e) =
-                            persist_jobs_from_arc(&local_storage_path, &current_jobs_arc).await
-                        {
-                            tracing::error!(
-                                "Failed to persist last_run update for loaded job {}: {}",
-                                &task_job_id,
-                                e
-                            );
-                        }
-                    }
-
-                    // Spawn the job execution as an abortable task
-                    let job_task = tokio::spawn(run_scheduled_job_internal(
-                        job_to_execute,
-                        None,
-                        Some(current_jobs_arc.clone()),
-                        Some(task_job_id.clone()),
-                    ));
-
-                    // Store the abort handle at the scheduler level
-                    {
-                        let mut running_tasks_guard = running_tasks_arc.lock().await;
-                        running_tasks_guard.insert(task_job_id.clone(), job_task.abort_handle());
-                    }
-
-                    // Wait for the job to complete or be aborted
-                    let result = job_task.await;
-
-                    // Remove the abort handle
-                    {
-                        let mut running_tasks_guard = running_tasks_arc.lock().await;
-                        running_tasks_guard.remove(&task_job_id);
-                    }
-
-                    // Update the job status after execution
-                    {
-                        let mut jobs_map_guard = current_jobs_arc.lock().await;
-                        if let Some((_, stored_job)) = jobs_map_guard.get_mut(&task_job_id) {
-                            stored_job.currently_running = false;
-                            stored_job.current_session_id = None;
-                            stored_job.process_start_time = None;
-                            needs_persist = true;
-                        }
-                    }
-
-                    if needs_persist {
-                        if let Err(e) =
-                            persist_jobs_from_arc(&local_storage_path, &current_jobs_arc).await
-                        {
-                            tracing::error!(
-                                "Failed to persist running status update for job {}: {}",
-                                &task_job_id,
-                                e
-                            );
-                        }
-                    }
+            // Nothing from a previous process is still running or waiting to run
+            let mut job_to_load = job_to_load;
+            job_to_load.currently_running = false;
+            job_to_load.queued_run = false;
+            job_to_load.current_session_id = None;
+            job_to_load.process_start_time = None;
 
-                    match result {
-                        Ok(Ok(_session_id)) => {
-                            tracing::info!(
-                                "Scheduled job '{}' completed successfully",
-                                &task_job_id
-                            );
-                        }
-                        Ok(Err(e)) => {
-                            tracing::error!(
-                                "Scheduled job '{}' execution failed: {}",
-                                &e.job_id,
-                                e.error
-                            );
-                        }
-                        Err(join_error) if join_error.is_cancelled() => {
-                            tracing::info!("Scheduled job '{}' was cancelled/killed", &task_job_id);
-                        }
-                        Err(join_error) => {
-                            tracing::error!(
-                                "Scheduled job '{}' task failed: {}",
-                                &task_job_id,
-                                join_error
-                            );
-                        }
-                    }
-                })
-            })
-            .map_err(|e| SchedulerError::CronParseError(e.to_string()))?;
-
-            let job_uuid = self
-                .internal_scheduler
-                .add(cron_task)
-                .await
-                .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))?;
+            tracing::info!("Loading job '{}' {}", job_to_load.id, job_to_load.spec()?);
+            let job_uuid = self.add_task(&mut job_to_load).await?;
             jobs_guard.insert(job_to_load.id.clone(), (job_uuid, job_to_load));
         }
         Ok(())
@@ -627,13 +854,10 @@ impl Scheduler {
     pub async fn remove_scheduled_job(&self, id: &str) -> Result<(), SchedulerError> {
         let mut jobs_guard = self.jobs.lock().await;
         if let Some((job_uuid, scheduled_job)) = jobs_guard.remove(id) {
-            self.internal_scheduler
-                .remove(&job_uuid)
-                .await
-                .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))?;
+            self.remove_task(job_uuid).await?;
 
             let recipe_path = Path::new(&scheduled_job.source);
-            if recipe_path.exists() {
+            if scheduled_job.prompt.is_none() && recipe_path.exists() {
                 fs::remove_file(recipe_path).map_err(SchedulerError::StorageError)?;
             }
 
@@ -701,62 +925,52 @@ impl Scheduler {
             }
         };
 
-        // Spawn the job execution as an abortable task for run_now
-        let job_task = tokio::spawn(run_scheduled_job_internal(
-            job_to_run.clone(),
-            None,
-            Some(self.jobs.clone()),
-            Some(sched_id.to_string()),
-        ));
-
-        // Store the abort handle for run_now jobs
-        {
-            let mut running_tasks_guard = self.running_tasks.lock().await;
-            running_tasks_guard.insert(sched_id.to_string(), job_task.abort_handle());
-        }
-
-        // Wait for the job to complete or be aborted
-        let run_result = job_task.await;
-
-        // Remove the abort handle
-        {
-            let mut running_tasks_guard = self.running_tasks.lock().await;
-            running_tasks_guard.remove(sched_id);
-        }
+        // Run a single attempt; the caller is waiting for the result, so failures aren't retried
+        let run_started = Utc::now();
+        let outcome = run_attempt(&job_to_run, 1, &self.jobs, &self.running_tasks).await;
+        let others_running = prune_running_tasks(&self.running_tasks, sched_id).await;
 
-        // Clear the currently_running flag after execution
-        {
+        // Clear the currently_running flag after execution, unless a cron run was queued
+        let run_queued = {
             let mut jobs_guard = self.jobs.lock().await;
-            if let Some((_tokio_job_id, job_in_map)) = jobs_guard.get_mut(sched_id) {
-                job_in_map.currently_running = false;
-                job_in_map.current_session_id = None;
-                job_in_map.process_start_time = None;
-                job_in_map.last_run = Some(Utc::now());
+            match jobs_guard.get_mut(sched_id) {
+                Some((_tokio_job_id, job_in_map)) => {
+                    let now = Utc::now();
+                    job_in_map.last_run = Some(now);
+                    job_in_map.finish_run(others_running, now)
+                }
+                None => false,
             } // MutexGuard is dropped here
-        }
+        };
 
         // Persist after the lock is released and update is made.
         self.persist_jobs().await?;
 
-        match run_result {
-            Ok(Ok(session_id)) => Ok(session_id),
-            Ok(Err(e)) => Err(SchedulerError::AnyhowError(anyhow!(
+        if run_queued {
+            tokio::spawn(run_claimed_job(
+                job_to_run.clone(),
+                self.jobs.clone(),
+                self.storage_path.clone(),
+                self.running_tasks.clone(),
+            ));
+        }
+
+        notify_run_finished(&job_to_run, &outcome, 1, run_started).await;
+
+        match outcome {
+            AttemptOutcome::Succeeded(session_id) => Ok(session_id),
+            AttemptOutcome::Failed { error, .. } => Err(SchedulerError::AnyhowError(anyhow!(
                 "Failed to execute job '{}' immediately: {}",
                 sched_id,
-                e.error
+                error
             ))),
-            Err(join_error) if join_error.is_cancelled() => {
+            AttemptOutcome::Cancelled => {
                 tracing::info!("Run now job '{}' was cancelled/killed", sched_id);
                 Err(SchedulerError::AnyhowError(anyhow!(
                     "Job '{}' was successfully cancelled",
                     sched_id
                 )))
             }
-            Err(join_error) => Err(SchedulerError::AnyhowError(anyhow!(
-                "Failed to execute job '{}' immediately: {}",
-                sched_id,
-                join_error
-            ))),
         }
     }
 
@@ -793,7 +1007,8 @@ impl Scheduler {
     pub async fn update_schedule(
         &self,
         sched_id: &str,
-        new_cron: String,
+        spec: ScheduleSpec,
+        timezone: Option<String>,
     ) -> Result<(), SchedulerError> {
         let mut jobs_guard = self.jobs.lock().await;
         match jobs_guard.get_mut(sched_id) {
@@ -805,186 +1020,59 @@ impl Scheduler {
                     )));
                 }
 
-                if new_cron == job_def.cron {
+                if job_def.spec().ok().as_ref() == Some(&spec) && timezone == job_def.timezone {
                     // No change needed
                     return Ok(());
                 }
+                resolve_timezone(timezone.as_deref())?;
 
-                // Remove the old job from the scheduler
-                self.internal_scheduler
-                    .remove(job_uuid)
-                    .await
-                    .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))?;
-
-                // Create new job with updated cron
-                let job_for_task = job_def.clone();
-                let jobs_arc_for_task = self.jobs.clone();
-                let storage_path_for_task = self.storage_path.clone();
-                let running_tasks_for_task = self.running_tasks.clone();
-
-                tracing::info!(
-                    "Updating job '{}' with new cron expression: '{}'",
-                    sched_id,
-                    new_cron
-                );
-                let normalized_cron = normalize_cron_expression(&new_cron);
-                // Convert from 7-field (Temporal format) to 6-field (tokio-cron-scheduler format)
-                let tokio_cron = {
-                    let parts: Vec<&str> = normalized_cron.split_whitespace().collect();
-                    if parts.len() == 7 {
-                        parts[..6].join(" ")
-                    } else {
-                        normalized_cron.clone()
-                    }
-                };
-                if tokio_cron != new_cron {
-                    tracing::info!(
-                        "Converted cron expression from '{}' to '{}' for tokio-cron-scheduler",
-                        new_cron,
-                        tokio_cron
-                    );
-                }
-                let cron_task = Job::new_async(&tokio_cron, move |_uuid, _l| {
-                    let task_job_id = job_for_task.id.clone();
-                    let current_jobs_arc = jobs_arc_for_task.clone();
-                    let local_storage_path = storage_path_for_task.clone();
-                    let job_to_execute = job_for_task.clone();
-                    let running_tasks_arc = running_tasks_for_task.clone();
-
-                    Box::pin(async move {
-                        // Check if the job is paused before executing
-                        let should_execute = {
-                            let jobs_map_guard = current_jobs_arc.lock().await;
-                            if let Some((_, current_job_in_map)) = jobs_map_guard.get(&task_job_id)
-                            {
-                                !current_job_in_map.paused
-                            } else {
-                                false
-                            }
-                        };
-
-                        if !should_execute {
-                            tracing::info!("Skipping execution of paused job '{}'", &task_job_id);
-                            return;
-                        }
-
-                        let current_time = Utc::now();
-                        let mut needs_persist = false;
-                        {
-                            let mut jobs_map_guard = current_jobs_arc.lock().await;
-                            if let Some((_, current_job_in_map)) =
-                                jobs_map_guard.get_mut(&task_job_id)
-                            {
-                                current_job_in_map.last_run = Some(current_time);
-                                current_job_in_map.currently_running = true;
-                                current_job_in_map.process_start_time = Some(current_time);
-                                needs_persist = true;
-                            }
-                        }
-
-                        if needs_persist {
-                            if let Err(e) =
-                                persist_jobs_from_arc(&local_storage_path, &current_jobs_arc).await
-                            {
-                                tracing::error!(
-                                    "Failed to persist last_run update for job {}: {}",
-                                    &task_job_id,
-                                    e
-                                );
-                            }
-                        }
-
-                        // Spawn the job execution as an abortable task
-                        let job_task = tokio::spawn(run_scheduled_job_internal(
-                            job_to_execute,
-                            None,
-                            Some(current_jobs_arc.clone()),
-                            Some(task_job_id.clone()),
-                        ));
-
-                        // Store the abort handle at the scheduler level
-                        {
-                            let mut running_tasks_guard = running_tasks_arc.lock().await;
-                            running_tasks_guard
-                                .insert(task_job_id.clone(), job_task.abort_handle());
-                        }
-
-                        // Wait for the job to complete or be aborted
-                        let result = job_task.await;
+                tracing::info!("Updating job '{}' to run {}", sched_id, spec);
+                let mut updated_job = job_def.clone();
+                updated_job.set_spec(spec);
+                updated_job.timezone = timezone;
 
-                        // Remove the abort handle
-                        {
-                            let mut running_tasks_guard = running_tasks_arc.lock().await;
-                            running_tasks_guard.remove(&task_job_id);
-                        }
-
-                        // Update the job status after execution
-                        {
-                            let mut jobs_map_guard = current_jobs_arc.lock().await;
-                            if let Some((_, current_job_in_map)) =
-                                jobs_map_guard.get_mut(&task_job_id)
-                            {
-                                current_job_in_map.currently_running = false;
-                                current_job_in_map.current_session_id = None;
-                                current_job_in_map.process_start_time = None;
-                                needs_persist = true;
-                            }
-                        }
-
-                        if needs_persist {
-                            if let Err(e) =
-                                persist_jobs_from_arc(&local_storage_path, &current_jobs_arc).await
-                            {
-                                tracing::error!(
-                                    "Failed to persist running status update for job {}: {}",
-                                    &task_job_id,
-                                    e
-                                );
-                            }
-                        }
+                // Schedule the new timing before removing the old one, so a bad spec changes nothing
+                let new_job_uuid = self.add_task(&mut updated_job).await?;
+                self.remove_task(job_uuid.take()).await?;
 
-                        match result {
-                            Ok(Ok(_session_id)) => {
-                                tracing::info!(
-                                    "Scheduled job '{}' completed successfully",
-                                    &task_job_id
-                                );
-                            }
-                            Ok(Err(e)) => {
-                                tracing::error!(
-                                    "Scheduled job '{}' execution failed: {}",
-                                    &e.job_id,
-                                    e.error
-                                );
-                            }
-                            Err(join_error) if join_error.is_cancelled() => {
-                                tracing::info!(
-                                    "Scheduled job '{}' was cancelled/killed",
-                                    &task_job_id
-                                );
-                            }
-                            Err(join_error) => {
-                                tracing::error!(
-                                    "Scheduled job '{}' task failed: {}",
-                                    &task_job_id,
-                                    join_error
-                                );
-                            }
-                        }
-                    })
-                })
-                .map_err(|e| SchedulerError::CronParseError(e.to_string()))?;
+                *job_uuid = new_job_uuid;
+                *job_def = updated_job;
 
-                let new_job_uuid = self
-                    .internal_scheduler
-                    .add(cron_task)
-                    .await
-                    .map_err(|e| SchedulerError::SchedulerInternalError(e.to_string()))?;
+                self.persist_jobs_to_storage_with_guard(&jobs_guard).await?;
+                Ok(())
+            }
+            None => Err(SchedulerError::JobNotFound(sched_id.to_string())),
+        }
+    }
 
-                // Update the job UUID and cron expression
-                *job_uuid = new_job_uuid;
-                job_def.cron = new_cron;
+    pub async fn set_concurrency_policy(
+        &self,
+        sched_id: &str,
+        policy: ConcurrencyPolicy,
+    ) -> Result<(), SchedulerError> {
+        let mut jobs_guard = self.jobs.lock().await;
+        match jobs_guard.get_mut(sched_id) {
+            Some((_, job_def)) => {
+                job_def.concurrency_policy = policy;
+                if policy != ConcurrencyPolicy::Queue {
+                    job_def.queued_run = false;
+                }
+                self.persist_jobs_to_storage_with_guard(&jobs_guard).await?;
+                Ok(())
+            }
+            None => Err(SchedulerError::JobNotFound(sched_id.to_string())),
+        }
+    }
 
+    pub async fn set_execution_conditions(
+        &self,
+        sched_id: &str,
+        conditions: ExecutionConditions,
+    ) -> Result<(), SchedulerError> {
+        let mut jobs_guard = self.jobs.lock().await;
+        match jobs_guard.get_mut(sched_id) {
+            Some((_, job_def)) => {
+                job_def.conditions = conditions;
                 self.persist_jobs_to_storage_with_guard(&jobs_guard).await?;
                 Ok(())
             }
@@ -1005,11 +1093,16 @@ impl Scheduler {
 
                 tracing::info!("Killing running job '{}'", sched_id);
 
-                // Abort the running task if it exists
+                // Drop any queued run so it doesn't start once the running one is aborted
+                job_def.queued_run = false;
+
+                // Abort the running tasks if they exist
                 {
                     let mut running_tasks_guard = self.running_tasks.lock().await;
-                    if let Some(abort_handle) = running_tasks_guard.remove(sched_id) {
-                        abort_handle.abort();
+                    if let Some(abort_handles) = running_tasks_guard.remove(sched_id) {
+                        for abort_handle in abort_handles {
+                            abort_handle.abort();
+                        }
                         tracing::info!("Aborted running task for job '{}'", sched_id);
                     } else {
                         tracing::warn!(
@@ -1057,61 +1150,465 @@ impl Scheduler {
     }
 }
 
+/// Drop the abort handles of finished runs, returning whether any run of the job is still going
+async fn prune_running_tasks(running_tasks: &Mutex<RunningTasksMap>, job_id: &str) -> bool {
+    let mut running_tasks_guard = running_tasks.lock().await;
+    let Some(handles) = running_tasks_guard.get_mut(job_id) else {
+        return false;
+    };
+    handles.retain(|handle| !handle.is_finished());
+    if handles.is_empty() {
+        running_tasks_guard.remove(job_id);
+        return false;
+    }
+    true
+}
+
+/// Handle a cron firing: check the execution conditions, apply the job's concurrency policy,
+/// then run it
+async fn fire_scheduled_job(
+    job: ScheduledJob,
+    jobs_arc: Arc<Mutex<JobsMap>>,
+    storage_path: PathBuf,
+    running_tasks: Arc<Mutex<RunningTasksMap>>,
+    machine_state: Arc<dyn MachineState>,
+) {
+    let global_conditions = ExecutionConditions::global();
+    let (conditions, timezone) = match jobs_arc.lock().await.get(&job.id) {
+        Some((_, current_job_in_map)) => (
+            current_job_in_map.conditions.or(&global_conditions),
+            current_job_in_map.timezone.clone(),
+        ),
+        None => return,
+    };
+    let local_time = match resolve_timezone(timezone.as_deref()) {
+        Ok(tz) if timezone.is_some() => Utc::now().with_timezone(&tz).time(),
+        _ => chrono::Local::now().time(),
+    };
+    // Detecting the power source and connection can run commands, so keep it off the runtime
+    let machine_conditions = conditions.clone();
+    let blocked_by_machine = tokio::task::spawn_blocking(move || {
+        machine_conditions.blocked_by_machine(machine_state.as_ref(), local_time)
+    })
+    .await
+    .unwrap_or_default();
+
+    let decision = {
+        let mut jobs_map_guard = jobs_arc.lock().await;
+        let running_jobs = jobs_map_guard
+            .values()
+            .filter(|(_, scheduled)| scheduled.currently_running)
+            .count();
+        match jobs_map_guard.get_mut(&job.id) {
+            Some((job_uuid, current_job_in_map)) => {
+                let mut now = Utc::now();
+                if let Some(run_at) = current_job_in_map.run_at {
+                    // A one-shot job fires once; count the run as on time so the job completes
+                    *job_uuid = None;
+                    now = now.max(run_at);
+                }
+                let blocked = blocked_by_machine
+                    .or_else(|| conditions.blocked_by_running_jobs(running_jobs))
+                    .filter(|_| !current_job_in_map.paused);
+                match blocked {
+                    Some(reason) => {
+                        current_job_in_map.record_skipped_run(now, &reason);
+                        FireDecision::Skipped
+                    }
+                    None => current_job_in_map.claim_run(now),
+                }
+            }
+            None => return,
+        }
+    };
+    if decision == FireDecision::Paused {
+        tracing::info!("Skipping execution of paused job '{}'", &job.id);
+        return;
+    }
+
+    if let Err(e) = persist_jobs_from_arc(&storage_path, &jobs_arc).await {
+        tracing::error!(
+            "Failed to persist last_run update for job {}: {}",
+            &job.id,
+            e
+        );
+    }
+    match decision {
+        FireDecision::Start => run_claimed_job(job, jobs_arc, storage_path, running_tasks).await,
+        FireDecision::Queued => {
+            tracing::info!("Queued a run of job '{}' behind the current one", &job.id)
+        }
+        FireDecision::Skipped | FireDecision::Paused => {}
+    }
+}
+
+/// Run a job already marked as running, retrying failed attempts, then any run queued behind it
+async fn run_claimed_job(
+    job: ScheduledJob,
+    jobs_arc: Arc<Mutex<JobsMap>>,
+    storage_path: PathBuf,
+    running_tasks: Arc<Mutex<RunningTasksMap>>,
+) {
+    let task_job_id = job.id.clone();
+    loop {
+        // Pick up retry and notification settings changed since the job was scheduled
+        let job = match jobs_arc.lock().await.get(&task_job_id) {
+            Some((_, current_job_in_map)) => current_job_in_map.clone(),
+            None => job.clone(),
+        };
+        let run_started = Utc::now();
+        let max_attempts = job.max_attempts();
+        let mut attempt = 1;
+        let outcome = loop {
+            let outcome = run_attempt(&job, attempt, &jobs_arc, &running_tasks).await;
+            let AttemptOutcome::Failed { error, .. } = &outcome else {
+                break outcome;
+            };
+            if attempt >= max_attempts {
+                break outcome;
+            }
+
+            let backoff = job
+                .retry
+                .map(|retry| retry.backoff(attempt))
+                .unwrap_or_default();
+            tracing::warn!(
+                "Attempt {} of {} of job '{}' failed: {}. Retrying in {}s",
+                attempt,
+                max_attempts,
+                &task_job_id,
+                error,
+                backoff.as_secs()
+            );
+            if let Err(e) = persist_jobs_from_arc(&storage_path, &jobs_arc).await {
+                tracing::error!(
+                    "Failed to persist run history for job {}: {}",
+                    &task_job_id,
+                    e
+                );
+            }
+            tokio::time::sleep(backoff).await;
+
+            // The job may have been killed while waiting
+            let still_running = matches!(
+                jobs_arc.lock().await.get(&task_job_id),
+                Some((_, current_job_in_map)) if current_job_in_map.currently_running
+            );
+            if !still_running {
+                break AttemptOutcome::Cancelled;
+            }
+            attempt += 1;
+        };
+        let others_running = prune_running_tasks(&running_tasks, &task_job_id).await;
+
+        // Update the job status after execution
+        let run_queued = {
+            let mut jobs_map_guard = jobs_arc.lock().await;
+            match jobs_map_guard.get_mut(&task_job_id) {
+                Some((_, current_job_in_map)) => {
+                    current_job_in_map.finish_run(others_running, Utc::now())
+                }
+                None => false,
+            }
+        };
+
+        if let Err(e) = persist_jobs_from_arc(&storage_path, &jobs_arc).await {
+            tracing::error!(
+                "Failed to persist running status update for job {}: {}",
+                &task_job_id,
+                e
+            );
+        }
+
+        match &outcome {
+            AttemptOutcome::Succeeded(_) => {
+                tracing::info!("Scheduled job '{}' completed successfully", &task_job_id);
+            }
+            AttemptOutcome::Failed { error, .. } => {
+                tracing::error!(
+                    "Scheduled job '{}' execution failed after {} attempt(s): {}",
+                    &task_job_id,
+                    attempt,
+                    error
+                );
+            }
+            AttemptOutcome::Cancelled => {
+                tracing::info!("Scheduled job '{}' was cancelled/killed", &task_job_id);
+            }
+        }
+        notify_run_finished(&job, &outcome, attempt, run_started).await;
+        deliver_run_result(&job, &outcome, run_started);
+
+        if !run_queued {
+            break;
+        }
+        tracing::info!("Starting queued run of job '{}'", &task_job_id);
+    }
+}
+
+/// Run one attempt of `job` and record it in the job's run history
+async fn run_attempt(
+    job: &ScheduledJob,
+    attempt: u32,
+    jobs_arc: &Arc<Mutex<JobsMap>>,
+    running_tasks: &Mutex<RunningTasksMap>,
+) -> AttemptOutcome {
+    let started_at = Utc::now();
+
+    // Spawn the job execution as an abortable task
+    let job_task = tokio::spawn(run_scheduled_job_internal(
+        job.clone(),
+        None,
+        Some(jobs_arc.clone()),
+        Some(job.id.clone()),
+    ));
+
+    // Store the abort handle at the scheduler level
+    running_tasks
+        .lock()
+        .await
+        .entry(job.id.clone())
+        .or_default()
+        .push(job_task.abort_handle());
+
+    // Wait for the job to complete or be aborted
+    let outcome = match job_task.await {
+        Ok(Ok(session_id)) => AttemptOutcome::Succeeded(session_id),
+        Ok(Err(e)) => AttemptOutcome::Failed {
+            session_id: e.session_id,
+            error: e.error,
+        },
+        Err(join_error) if join_error.is_cancelled() => AttemptOutcome::Cancelled,
+        Err(join_error) => AttemptOutcome::Failed {
+            session_id: None,
+            error: join_error.to_string(),
+        },
+    };
+
+    let finished_at = Utc::now();
+    let session_id = match outcome.session_id() {
+        Some(session_id) => Some(session_id.to_string()),
+        None => jobs_arc
+            .lock()
+            .await
+            .get(&job.id)
+            .and_then(|(_, job_def)| job_def.current_session_id.clone()),
+    };
+    let usage = session_id.as_deref().and_then(session_usage);
+    let (status, error) = match &outcome {
+        AttemptOutcome::Succeeded(_) => (RunStatus::Success, None),
+        AttemptOutcome::Failed { error, .. } => (RunStatus::Error, Some(error.clone())),
+        AttemptOutcome::Cancelled => (RunStatus::Killed, None),
+    };
+    let limit = run_history_limit();
+    if let Some((_, job_def)) = jobs_arc.lock().await.get_mut(&job.id) {
+        job_def.record_attempt(
+            RunAttempt {
+                session_id,
+                attempt,
+                started_at,
+                finished_at,
+                status,
+                error,
+                usage,
+            },
+            limit,
+        );
+    }
+    outcome
+}
+
+fn run_history_limit() -> usize {
+    Config::global()
+        .get_param::<usize>(RUN_HISTORY_LIMIT_KEY)
+        .unwrap_or(DEFAULT_RUN_HISTORY_LIMIT)
+}
+
+/// Token usage recorded in a run's session metadata
+pub(crate) fn session_usage(session_id: &str) -> Option<RunUsage> {
+    let path =
+        session::storage::get_path(session::storage::Identifier::Name(session_id.to_string()))
+            .ok()?;
+    let metadata = session::storage::read_metadata(&path).ok()?;
+    Some(RunUsage {
+        input_tokens: metadata.accumulated_input_tokens.or(metadata.input_tokens),
+        output_tokens: metadata
+            .accumulated_output_tokens
+            .or(metadata.output_tokens),
+        total_tokens: metadata.accumulated_total_tokens.or(metadata.total_tokens),
+    })
+}
+
+/// POST the outcome of a run to the job's `on_success` or `on_failure` webhook
+async fn notify_run_finished(
+    job: &ScheduledJob,
+    outcome: &AttemptOutcome,
+    attempts: u32,
+    run_started: DateTime<Utc>,
+) {
+    let (event, target, error) = match outcome {
+        AttemptOutcome::Succeeded(_) => (RunEvent::RunSucceeded, job.on_success.as_ref(), None),
+        AttemptOutcome::Failed { error, .. } => (
+            RunEvent::RunFailed,
+            job.on_failure.as_ref(),
+            Some(error.clone()),
+        ),
+        AttemptOutcome::Cancelled => return,
+    };
+    let Some(target) = target else {
+        return;
+    };
+
+    let notification = RunNotification {
+        event,
+        job_id: job.id.clone(),
+        session_id: outcome.session_id().map(str::to_string),
+        attempts,
+        error,
+        duration_seconds: (Utc::now() - run_started).num_seconds(),
+        last_assistant_message: None,
+        transcript: None,
+    }
+    .with_session_messages(target.include_transcript);
+
+    if let Err(e) = send_notification(target, &notification).await {
+        tracing::error!("Failed to notify webhook for job '{}': {}", job.id, e);
+    }
+}
+
+/// Send the run's result to the job's callback URL in the background
+fn deliver_run_result(job: &ScheduledJob, outcome: &AttemptOutcome, run_started: DateTime<Utc>) {
+    let Some(url) = job.callback_url.as_deref() else {
+        return;
+    };
+    let callback = match SessionCallback::new(url, job.callback_secret.clone()) {
+        Ok(callback) => callback,
+        Err(e) => {
+            tracing::error!("Not delivering the result of job '{}': {}", job.id, e);
+            return;
+        }
+    };
+
+    let (result, error) = match outcome {
+        AttemptOutcome::Succeeded(_) => (SessionResult::Success, None),
+        AttemptOutcome::Failed { error, .. } => (SessionResult::Error, Some(error.clone())),
+        AttemptOutcome::Cancelled => (SessionResult::Cancelled, None),
+    };
+    let session_id = outcome.session_id().map(str::to_string);
+    let messages = session_id
+        .as_ref()
+        .and_then(|id| {
+            session::storage::get_path(session::storage::Identifier::Name(id.clone())).ok()
+        })
+        .and_then(|path| session::storage::read_messages(&path).ok())
+        .unwrap_or_default();
+    let recipe = (!job.source.is_empty()).then(|| RecipeInfo {
+        source: job.source.clone(),
+        title: read_recipe_file(&job.source)
+            .ok()
+            .and_then(|file| Recipe::from_content(&file.content).ok())
+            .map(|recipe| recipe.title),
+    });
+    let duration = (Utc::now() - run_started).to_std().unwrap_or_default();
+    let payload = SessionResultPayload::new(session_id, result, error, duration, &messages, recipe);
+
+    tokio::spawn(async move {
+        session_callback::deliver(&callback, &payload).await;
+    });
+}
+
 #[derive(Debug)]
 struct JobExecutionError {
     job_id: String,
+    /// The session of the failed run, when it got far enough to have one
+    session_id: Option<String>,
     error: String,
 }
 
+/// Check a prompt job before it is stored: it names no recipe, and its working directory and
+/// extensions exist
+fn check_prompt_job(job: &ScheduledJob) -> Result<(), SchedulerError> {
+    if !job.source.is_empty() {
+        return Err(SchedulerError::RecipeLoadError(format!(
+            "Job '{}' has both a recipe and a prompt",
+            job.id
+        )));
+    }
+    if job
+        .prompt
+        .as_deref()
+        .is_some_and(|prompt| prompt.trim().is_empty())
+    {
+        return Err(SchedulerError::AgentSetupError(format!(
+            "Job '{}' has an empty prompt",
+            job.id
+        )));
+    }
+    if let Some(dir) = &job.working_dir {
+        if !Path::new(dir).is_dir() {
+            return Err(SchedulerError::AgentSetupError(format!(
+                "Working directory '{}' of job '{}' is not a directory",
+                dir, job.id
+            )));
+        }
+    }
+    for name in &job.extensions {
+        match ExtensionConfigManager::get_config_by_name(name) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Err(SchedulerError::AgentSetupError(format!(
+                    "Unknown extension '{}'",
+                    name
+                )))
+            }
+            Err(e) => {
+                return Err(SchedulerError::AgentSetupError(format!(
+                    "Failed to read extension '{}': {}",
+                    name, e
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn load_job_recipe(job: &ScheduledJob) -> std::result::Result<Recipe, JobExecutionError> {
+    let job_error = |error: String| JobExecutionError {
+        job_id: job.id.clone(),
+        session_id: None,
+        error,
+    };
+    let recipe_file = read_recipe_file(&job.source).map_err(|e| {
+        job_error(format!(
+            "Failed to load recipe file '{}': {}",
+            job.source, e
+        ))
+    })?;
+    build_recipe_with_values(recipe_file, &job.params)
+        .map_err(|e| job_error(format!("Failed to build recipe '{}': {}", job.source, e)))
+}
+
+#[tracing::instrument(
+    skip_all,
+    name = "scheduled_job",
+    fields(job_id = %job.id, session_mode = job.session_mode())
+)]
 async fn run_scheduled_job_internal(
     job: ScheduledJob,
     provider_override: Option<Arc<dyn GooseProvider>>, // New optional parameter
     jobs_arc: Option<Arc<Mutex<JobsMap>>>,
     job_id: Option<String>,
 ) -> std::result::Result<String, JobExecutionError> {
-    tracing::info!("Executing job: {} (Source: {})", job.id, job.source);
-
-    let recipe_path = Path::new(&job.source);
+    tracing::info!("Executing job: {} ({})", job.id, job.task_label());
 
-    let recipe_content = match fs::read_to_string(recipe_path) {
-        Ok(content) => content,
-        Err(e) => {
-            return Err(JobExecutionError {
-                job_id: job.id.clone(),
-                error: format!("Failed to load recipe file '{}': {}", job.source, e),
-            });
-        }
+    let recipe = match &job.prompt {
+        Some(_) => None,
+        None => Some(load_job_recipe(&job)?),
+    };
+    let prompt = match &recipe {
+        Some(recipe) => recipe_initial_message(recipe),
+        None => job.prompt.clone(),
     };
-
-    let recipe: Recipe = {
-        let extension = recipe_path
-            .extension()
-            .and_then(|os_str| os_str.to_str())
-            .unwrap_or("yaml")
-            .to_lowercase();
-
-        match extension.as_str() {
-            "json" | "jsonl" => {
-                serde_json::from_str::<Recipe>(&recipe_content).map_err(|e| JobExecutionError {
-                    job_id: job.id.clone(),
-                    error: format!("Failed to parse JSON recipe '{}': {}", job.source, e),
-                })
-            }
-            "yaml" | "yml" => {
-                serde_yaml::from_str::<Recipe>(&recipe_content).map_err(|e| JobExecutionError {
-                    job_id: job.id.clone(),
-                    error: format!("Failed to parse YAML recipe '{}': {}", job.source, e),
-                })
-            }
-            _ => Err(JobExecutionError {
-                job_id: job.id.clone(),
-                error: format!(
-                    "Unsupported recipe file extension '{}' for: {}",
-                    extension, job.source
-                ),
-            }),
-        }
-    }?;
 
     let agent: Agent = Agent::new();
 
@@ -1125,6 +1622,7 @@ async fn run_scheduled_job_internal(
             Ok(name) => name,
             Err(_) => return Err(JobExecutionError {
                 job_id: job.id.clone(),
+                session_id: None,
                 error:
                     "GOOSE_PROVIDER not configured globally. Run 'goose configure' or set env var."
                         .to_string(),
@@ -1135,6 +1633,7 @@ async fn run_scheduled_job_internal(
                 Ok(name) => name,
                 Err(_) => return Err(JobExecutionError {
                     job_id: job.id.clone(),
+                    session_id: None,
                     error:
                         "GOOSE_MODEL not configured globally. Run 'goose configure' or set env var."
                             .to_string(),
@@ -1143,11 +1642,13 @@ async fn run_scheduled_job_internal(
         let model_config =
             crate::model::ModelConfig::new(model_name.as_str()).map_err(|e| JobExecutionError {
                 job_id: job.id.clone(),
+                session_id: None,
                 error: format!("Model config error: {}", e),
             })?;
 
         agent_provider = create(&provider_name, model_config).map_err(|e| JobExecutionError {
             job_id: job.id.clone(),
+            session_id: None,
             error: format!(
                 "Failed to create provider instance '{}': {}",
                 provider_name, e
@@ -1158,11 +1659,43 @@ async fn run_scheduled_job_internal(
     if let Err(e) = agent.update_provider(agent_provider).await {
         return Err(JobExecutionError {
             job_id: job.id.clone(),
+            session_id: None,
             error: format!("Failed to set provider on agent: {}", e),
         });
     }
     tracing::info!("Agent configured with provider for job '{}'", job.id);
 
+    for name in &job.extensions {
+        let extension = match ExtensionConfigManager::get_config_by_name(name) {
+            Ok(Some(extension)) => extension,
+            Ok(None) => {
+                return Err(JobExecutionError {
+                    job_id: job.id.clone(),
+                    session_id: None,
+                    error: format!("Extension '{}' is not configured", name),
+                })
+            }
+            Err(e) => {
+                return Err(JobExecutionError {
+                    job_id: job.id.clone(),
+                    session_id: None,
+                    error: format!("Failed to read extension '{}': {}", name, e),
+                })
+            }
+        };
+        if let Err(e) = agent.add_extension(extension).await {
+            return Err(JobExecutionError {
+                job_id: job.id.clone(),
+                session_id: None,
+                error: format!("Failed to add extension '{}': {}", name, e),
+            });
+        }
+    }
+
+    if let Some(recipe) = &recipe {
+        prepare_agent_for_recipe(&agent, recipe).await;
+    }
+
     // Log the execution mode
     let execution_mode = job.execution_mode.as_deref().unwrap_or("background");
     tracing::info!("Job '{}' running in {} mode", job.id, execution_mode);
@@ -1184,23 +1717,28 @@ async fn run_scheduled_job_internal(
         Err(e) => {
             return Err(JobExecutionError {
                 job_id: job.id.clone(),
+                session_id: None,
                 error: format!("Failed to get session file path: {}", e),
             });
         }
     };
 
-    if let Some(prompt_text) = recipe.prompt {
+    if let Some(prompt_text) = prompt {
         let mut all_session_messages: Vec<Message> =
             vec![Message::user().with_text(prompt_text.clone())];
 
-        let current_dir = match std::env::current_dir() {
-            Ok(cd) => cd,
-            Err(e) => {
-                return Err(JobExecutionError {
-                    job_id: job.id.clone(),
-                    error: format!("Failed to get current directory for job execution: {}", e),
-                });
-            }
+        let current_dir = match job.working_dir.as_ref().map(PathBuf::from) {
+            Some(dir) => dir,
+            None => match std::env::current_dir() {
+                Ok(cd) => cd,
+                Err(e) => {
+                    return Err(JobExecutionError {
+                        job_id: job.id.clone(),
+                        session_id: None,
+                        error: format!("Failed to get current directory for job execution: {}", e),
+                    });
+                }
+            },
         };
 
         let session_config = SessionConfig {
@@ -1209,98 +1747,149 @@ async fn run_scheduled_job_internal(
             schedule_id: Some(job.id.clone()),
             execution_mode: job.execution_mode.clone(),
             max_turns: None,
-            retry_config: None,
+            retry_config: recipe.as_ref().and_then(|recipe| recipe.retry.clone()),
+            allowed_extensions: recipe.as_ref().and_then(|recipe| recipe.extension_names()),
+            persona: None,
+            goose_mode: None,
+            permissions_path: None,
+            recorded_tool_results: None,
         };
 
-        match agent
-            .reply(&all_session_messages, Some(session_config.clone()), None)
-            .await
-        {
-            Ok(mut stream) => {
-                use futures::StreamExt;
-
-                while let Some(message_result) = stream.next().await {
-                    // Check if the task has been cancelled
-                    tokio::task::yield_now().await;
-
-                    match message_result {
-                        Ok(AgentEvent::Message(msg)) => {
-                            if msg.role == rmcp::model::Role::Assistant {
-                                tracing::info!("[Job {}] Assistant: {:?}", job.id, msg.content);
-                            }
-                            all_session_messages.push(msg);
-                        }
-                        Ok(AgentEvent::McpNotification(_)) => {
-                            // Handle notifications if needed
-                        }
-                        Ok(AgentEvent::ModelChange { .. }) => {
-                            // Model change events are informational, just continue
-                        }
-                        Ok(AgentEvent::HistoryReplaced(_)) => {
-                            // Handle history replacement events if needed
-                        }
-                        Err(e) => {
-                            tracing::error!(
-                                "[Job {}] Error receiving message from agent: {}",
-                                job.id,
-                                e
-                            );
-                            break;
-                        }
-                    }
+        let response = recipe.as_ref().and_then(|recipe| recipe.response.as_ref());
+        let mut corrections_left = response.map_or(0, |response| response.max_validation_retries());
+        let mut schema_validation = None;
+        let mut stream_error = None;
+        loop {
+            let mut stream = match agent
+                .reply(&all_session_messages, Some(session_config.clone()), None)
+                .await
+            {
+                Ok(stream) => stream,
+                Err(e) => {
+                    return Err(JobExecutionError {
+                        job_id: job.id.clone(),
+                        session_id: None,
+                        error: format!("Agent failed to reply for {}: {}", job.task_label(), e),
+                    });
                 }
+            };
 
-                match crate::session::storage::read_metadata(&session_file_path) {
-                    Ok(mut updated_metadata) => {
-                        updated_metadata.message_count = all_session_messages.len();
-                        if let Err(e) = crate::session::storage::save_messages_with_metadata(
-                            &session_file_path,
-                            &updated_metadata,
-                            &all_session_messages,
-                        ) {
-                            tracing::error!(
-                                "[Job {}] Failed to persist final messages: {}",
-                                job.id,
-                                e
-                            );
+            use futures::StreamExt;
+            while let Some(message_result) = stream.next().await {
+                // Check if the task has been cancelled
+                tokio::task::yield_now().await;
+
+                match message_result {
+                    Ok(AgentEvent::Message(msg)) => {
+                        if msg.role == rmcp::model::Role::Assistant {
+                            tracing::info!("[Job {}] Assistant: {:?}", job.id, msg.content);
                         }
+                        all_session_messages.push(msg);
+                    }
+                    Ok(AgentEvent::McpNotification(_)) => {
+                        // Handle notifications if needed
                     }
+                    Ok(AgentEvent::ModelChange { .. }) => {
+                        // Model change events are informational, just continue
+                    }
+                    Ok(AgentEvent::HistoryReplaced(_)) => {
+                        // Handle history replacement events if needed
+                    }
+                    Ok(AgentEvent::TurnTiming(_)) => {}
                     Err(e) => {
                         tracing::error!(
-                            "[Job {}] Failed to read updated metadata before final save: {}",
+                            "[Job {}] Error receiving message from agent: {}",
                             job.id,
                             e
                         );
-                        let fallback_metadata = crate::session::storage::SessionMetadata {
-                            working_dir: current_dir.clone(),
-                            description: String::new(),
-                            schedule_id: Some(job.id.clone()),
-                            project_id: None,
-                            message_count: all_session_messages.len(),
-                            total_tokens: None,
-                            input_tokens: None,
-                            output_tokens: None,
-                            accumulated_total_tokens: None,
-                            accumulated_input_tokens: None,
-                            accumulated_output_tokens: None,
-                        };
-                        if let Err(e_fb) = crate::session::storage::save_messages_with_metadata(
-                            &session_file_path,
-                            &fallback_metadata,
-                            &all_session_messages,
-                        ) {
-                            tracing::error!("[Job {}] Failed to persist final messages with fallback metadata: {}", job.id, e_fb);
-                        }
+                        stream_error = Some(e.to_string());
+                        break;
                     }
                 }
             }
+
+            // Downstream automation reads the output, so hold it to the recipe's schema
+            let Some(response) = response.filter(|_| stream_error.is_none()) else {
+                break;
+            };
+            schema_validation = response.validate_output(&all_session_messages);
+            match &schema_validation {
+                Some(validation) if !validation.schema_valid && corrections_left > 0 => {
+                    corrections_left -= 1;
+                    tracing::warn!(
+                        "[Job {}] Output doesn't match the response schema, asking for a correction: {:?}",
+                        job.id,
+                        validation.errors
+                    );
+                    all_session_messages.push(response.correction_message(validation));
+                }
+                _ => break,
+            }
+        }
+
+        match crate::session::storage::read_metadata(&session_file_path) {
+            Ok(mut updated_metadata) => {
+                updated_metadata.message_count = all_session_messages.len();
+                updated_metadata.schema_validation = schema_validation;
+                if let Err(e) = crate::session::storage::save_messages_with_metadata(
+                    &session_file_path,
+                    &updated_metadata,
+                    &all_session_messages,
+                ) {
+                    tracing::error!("[Job {}] Failed to persist final messages: {}", job.id, e);
+                }
+            }
             Err(e) => {
-                return Err(JobExecutionError {
-                    job_id: job.id.clone(),
-                    error: format!("Agent failed to reply for recipe '{}': {}", job.source, e),
-                });
+                tracing::error!(
+                    "[Job {}] Failed to read updated metadata before final save: {}",
+                    job.id,
+                    e
+                );
+                let fallback_metadata = crate::session::storage::SessionMetadata {
+                    format_version: crate::session::storage::SESSION_FORMAT_VERSION,
+                    working_dir: current_dir.clone(),
+                    description: String::new(),
+                    schedule_id: Some(job.id.clone()),
+                    project_id: None,
+                    message_count: all_session_messages.len(),
+                    total_tokens: None,
+                    input_tokens: None,
+                    output_tokens: None,
+                    accumulated_total_tokens: None,
+                    accumulated_input_tokens: None,
+                    accumulated_output_tokens: None,
+                    accumulated_web_search_requests: None,
+                    provider_config: None,
+                    model_changes: Vec::new(),
+                    parent_session_id: None,
+                    origin: crate::session::storage::SessionOrigin::Schedule,
+                    persona: None,
+                    schema_validation,
+                    replay: None,
+                    batch_id: None,
+                    tool_usage: Default::default(),
+                };
+                if let Err(e_fb) = crate::session::storage::save_messages_with_metadata(
+                    &session_file_path,
+                    &fallback_metadata,
+                    &all_session_messages,
+                ) {
+                    tracing::error!(
+                        "[Job {}] Failed to persist final messages with fallback metadata: {}",
+                        job.id,
+                        e_fb
+                    );
+                }
             }
         }
+
+        if let Some(error) = stream_error {
+            return Err(JobExecutionError {
+                job_id: job.id.clone(),
+                session_id: Some(session_id_for_return),
+                error: format!("Agent failed while running {}: {}", job.task_label(), error),
+            });
+        }
     } else {
         tracing::warn!(
             "[Job {}] Recipe '{}' has no prompt to execute.",
@@ -1312,6 +1901,7 @@ async fn run_scheduled_job_internal(
             description: "Empty job - no prompt".to_string(),
             schedule_id: Some(job.id.clone()),
             message_count: 0,
+            origin: crate::session::storage::SessionOrigin::Schedule,
             ..Default::default()
         };
         if let Err(e) =
@@ -1446,13 +2036,32 @@ mod tests {
         let dummy_job = ScheduledJob {
             id: schedule_id_str.clone(),
             source: recipe_filename.to_string_lossy().into_owned(),
+            prompt: None,
+            working_dir: None,
+            extensions: Vec::new(),
+            params: HashMap::new(),
+            kind: ScheduleKind::Cron,
             cron: "* * * * * * ".to_string(), // Runs every second for quick testing
+            run_at: None,
+            every: None,
+            interval_anchor: None,
             last_run: None,
             currently_running: false,
             paused: false,
             current_session_id: None,
             process_start_time: None,
             execution_mode: Some("background".to_string()), // Default for test
+            timezone: None,
+            concurrency_policy: ConcurrencyPolicy::Skip,
+            queued_run: false,
+            skipped_runs: Vec::new(),
+            retry: None,
+            on_failure: None,
+            on_success: None,
+            callback_url: None,
+            callback_secret: None,
+            run_history: Vec::new(),
+            conditions: ExecutionConditions::default(),
         };
 
         let mock_model_config = ModelConfig::new_or_fail("test_model");
@@ -1505,6 +2114,116 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_prompt_job_runs_without_recipe() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let job: ScheduledJob = serde_json::from_value(serde_json::json!({
+            "id": "test_prompt_job",
+            "prompt": "Summarize unread items in inbox.md",
+            "working_dir": temp_dir.path(),
+            "cron": "0 9 * * *",
+            "last_run": null,
+        }))?;
+        assert_eq!(job.session_mode(), "scheduled_prompt");
+        check_prompt_job(&job)?;
+
+        let mock_provider_instance =
+            create_scheduler_test_mock_provider(ModelConfig::new_or_fail("test_model"));
+        let created_session_id =
+            run_scheduled_job_internal(job.clone(), Some(mock_provider_instance), None, None)
+                .await
+                .expect("run_scheduled_job_internal failed");
+
+        let session_path =
+            session::storage::ensure_session_dir()?.join(format!("{}.jsonl", created_session_id));
+        let metadata = read_metadata(&session_path)?;
+        assert_eq!(metadata.schedule_id.as_deref(), Some("test_prompt_job"));
+        assert_eq!(metadata.working_dir, temp_dir.path());
+        let messages = crate::session::storage::read_messages(&session_path)?;
+        assert!(messages.len() >= 2);
+
+        let with_recipe = ScheduledJob {
+            source: "/tmp/recipe.yaml".to_string(),
+            ..job.clone()
+        };
+        assert!(check_prompt_job(&with_recipe).is_err());
+        let missing_dir = ScheduledJob {
+            working_dir: Some(
+                temp_dir
+                    .path()
+                    .join("missing")
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            ..job
+        };
+        assert!(check_prompt_job(&missing_dir).is_err());
+        Ok(())
+    }
+
+    fn running_job(policy: ConcurrencyPolicy) -> ScheduledJob {
+        let mut job: ScheduledJob = serde_json::from_value(serde_json::json!({
+            "id": "overlap",
+            "source": "recipe.yaml",
+            "cron": "0 */30 * * * *",
+            "last_run": null,
+        }))
+        .unwrap();
+        job.concurrency_policy = policy;
+        assert_eq!(job.claim_run(Utc::now()), FireDecision::Start);
+        job
+    }
+
+    #[test]
+    fn test_skip_policy_records_overlapping_runs() {
+        let mut job = running_job(ConcurrencyPolicy::Skip);
+        assert_eq!(job.claim_run(Utc::now()), FireDecision::Skipped);
+        assert_eq!(job.skipped_runs.len(), 1);
+
+        assert!(!job.finish_run(false, Utc::now()));
+        assert!(!job.currently_running);
+        assert_eq!(job.claim_run(Utc::now()), FireDecision::Start);
+
+        for _ in 0..MAX_SKIPPED_RUNS + 5 {
+            job.claim_run(Utc::now());
+        }
+        assert_eq!(job.skipped_runs.len(), MAX_SKIPPED_RUNS);
+    }
+
+    #[test]
+    fn test_queue_policy_runs_once_more() {
+        let mut job = running_job(ConcurrencyPolicy::Queue);
+        assert_eq!(job.claim_run(Utc::now()), FireDecision::Queued);
+        assert_eq!(job.claim_run(Utc::now()), FireDecision::Skipped);
+
+        // The queued run takes over when the first one finishes
+        assert!(job.finish_run(false, Utc::now()));
+        assert!(job.currently_running && !job.queued_run);
+        assert!(!job.finish_run(false, Utc::now()));
+        assert!(!job.currently_running);
+    }
+
+    #[test]
+    fn test_allow_policy_overlaps() {
+        let mut job = running_job(ConcurrencyPolicy::Allow);
+        assert_eq!(job.claim_run(Utc::now()), FireDecision::Start);
+        assert!(job.skipped_runs.is_empty());
+
+        // Still running until the last overlapping run finishes
+        assert!(!job.finish_run(true, Utc::now()));
+        assert!(job.currently_running);
+        assert!(!job.finish_run(false, Utc::now()));
+        assert!(!job.currently_running);
+    }
+
+    #[test]
+    fn test_paused_jobs_do_not_run() {
+        let mut job = running_job(ConcurrencyPolicy::Allow);
+        job.paused = true;
+        assert_eq!(job.claim_run(Utc::now()), FireDecision::Paused);
+        assert!(job.skipped_runs.is_empty());
+    }
 }
 
 #[async_trait]
@@ -1544,9 +2263,26 @@ impl SchedulerTrait for Scheduler {
     async fn update_schedule(
         &self,
         sched_id: &str,
-        new_cron: String,
+        spec: ScheduleSpec,
+        timezone: Option<String>,
+    ) -> Result<(), SchedulerError> {
+        self.update_schedule(sched_id, spec, timezone).await
+    }
+
+    async fn set_concurrency_policy(
+        &self,
+        sched_id: &str,
+        policy: ConcurrencyPolicy,
     ) -> Result<(), SchedulerError> {
-        self.update_schedule(sched_id, new_cron).await
+        self.set_concurrency_policy(sched_id, policy).await
+    }
+
+    async fn set_execution_conditions(
+        &self,
+        sched_id: &str,
+        conditions: ExecutionConditions,
+    ) -> Result<(), SchedulerError> {
+        self.set_execution_conditions(sched_id, conditions).await
     }
 
     async fn kill_running_job(&self, sched_id: &str) -> Result<(), SchedulerError> {
@@ -1559,4 +2295,111 @@ impl SchedulerTrait for Scheduler {
     ) -> Result<Option<(String, DateTime<Utc>)>, SchedulerError> {
         self.get_running_job_info(sched_id).await
     }
+
+    #[test]
+    fn test_retry_backoff_doubles() {
+        let retry = RetryPolicy {
+            max_attempts: 4,
+            backoff_seconds: 30,
+        };
+        assert_eq!(retry.backoff(1), Duration::from_secs(30));
+        assert_eq!(retry.backoff(2), Duration::from_secs(60));
+        assert_eq!(retry.backoff(3), Duration::from_secs(120));
+        assert_eq!(retry.backoff(40), MAX_RETRY_BACKOFF);
+
+        let mut job = running_job(ConcurrencyPolicy::Skip);
+        assert_eq!(job.max_attempts(), 1);
+        job.retry = Some(RetryPolicy {
+            max_attempts: 0,
+            backoff_seconds: 30,
+        });
+        assert_eq!(job.max_attempts(), 1);
+    }
+
+    fn finished_run(status: RunStatus, started_at: DateTime<Utc>, seconds: i64) -> RunAttempt {
+        RunAttempt {
+            session_id: Some(started_at.format("%Y%m%d_%H%M%S").to_string()),
+            attempt: 1,
+            started_at,
+            finished_at: started_at + chrono::Duration::seconds(seconds),
+            status,
+            error: None,
+            usage: None,
+        }
+    }
+
+    #[test]
+    fn test_run_history_is_capped() {
+        let mut job = running_job(ConcurrencyPolicy::Skip);
+        let start = Utc::now();
+        for i in 0..15 {
+            let started_at = start + chrono::Duration::minutes(i);
+            job.record_attempt(finished_run(RunStatus::Error, started_at, 10), 10);
+        }
+        assert_eq!(job.run_history.len(), 10);
+        assert_eq!(
+            job.run_history[0].started_at,
+            start + chrono::Duration::minutes(5)
+        );
+    }
+
+    #[test]
+    fn test_runs_include_skipped_newest_first() {
+        let mut job = running_job(ConcurrencyPolicy::Skip);
+        let start = Utc::now();
+        job.record_attempt(finished_run(RunStatus::Success, start, 60), 50);
+        job.claim_run(start + chrono::Duration::seconds(30));
+        job.record_attempt(
+            finished_run(RunStatus::Killed, start + chrono::Duration::minutes(5), 5),
+            50,
+        );
+
+        let statuses: Vec<RunStatus> = job.runs().iter().map(|run| run.status).collect();
+        assert_eq!(
+            statuses,
+            vec![RunStatus::Killed, RunStatus::Skipped, RunStatus::Success]
+        );
+    }
+
+    #[test]
+    fn test_run_stats() {
+        let mut job = running_job(ConcurrencyPolicy::Skip);
+        assert_eq!(job.run_stats(), RunStats::default());
+
+        let start = Utc::now();
+        for i in 0..40 {
+            // The 10 oldest runs fall outside the window
+            let status = if i < 10 || i % 3 == 0 {
+                RunStatus::Error
+            } else {
+                RunStatus::Success
+            };
+            let started_at = start + chrono::Duration::minutes(i);
+            job.record_attempt(finished_run(status, started_at, 30), 50);
+        }
+        job.claim_run(start + chrono::Duration::hours(1));
+
+        let stats = job.run_stats();
+        assert_eq!(stats.runs, RUN_STATS_WINDOW);
+        assert_eq!(stats.success_rate, Some(20.0 / 30.0));
+        assert_eq!(stats.average_duration_seconds, Some(30.0));
+    }
+
+    #[test]
+    fn test_one_shot_job_completes() {
+        let run_at = Utc::now() + chrono::Duration::hours(2);
+        let mut job = running_job(ConcurrencyPolicy::Skip);
+        job.finish_run(false, Utc::now());
+        job.set_spec(ScheduleSpec::RunAt(run_at));
+        assert_eq!(job.kind, ScheduleKind::RunAt);
+        assert!(job.cron.is_empty());
+        assert!(!job.completed());
+        assert_eq!(job.next_fire(Utc::now()), Some(run_at));
+
+        assert_eq!(job.claim_run(run_at), FireDecision::Start);
+        assert!(!job.completed());
+        job.finish_run(false, run_at);
+        assert!(job.completed());
+        assert_eq!(job.next_fire(Utc::now()), None);
+    }
 }
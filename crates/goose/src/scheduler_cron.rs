@@ -0,0 +1,688 @@
+//! Validation, descriptions and next-run previews for schedule cron expressions.
+//!
+//! Accepts the same syntax as the scheduler: 5 fields (minute precision), 6 fields (with
+//! seconds) or 7 fields (quartz, with a trailing year), plus the `@hourly`, `@daily`,
+//! `@weekly`, `@monthly` and `@yearly` shorthands. Jobs fire on the wall clock of their
+//! timezone, UTC when none is set; previews are converted to the server's timezone.
+//!
+//! Around DST transitions a run whose wall-clock time is skipped (02:30 on a spring-forward
+//! night) happens as long after the transition as it would have before it (03:30), and a run
+//! whose wall-clock time repeats happens once, at the first occurrence.
+
+use chrono::{DateTime, Duration, Local, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use serde::Serialize;
+use tokio_cron_scheduler::Job;
+use utoipa::ToSchema;
+
+use crate::scheduler::{normalize_cron_expression, SchedulerError};
+
+/// Number of upcoming runs returned by [`preview_cron`] when callers have no preference
+pub const DEFAULT_PREVIEW_RUNS: usize = 5;
+
+/// Candidates checked before giving up on a next run; a per-second schedule can need up to an
+/// hour of candidates to get past a DST gap
+const MAX_CANDIDATES: usize = 10_000;
+
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+struct FieldSpec {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+    /// Day fields accept quartz extensions (`?`, `L`, `W`, `#`), left to the parser
+    day_field: bool,
+}
+
+const SECOND: FieldSpec = FieldSpec {
+    name: "second",
+    min: 0,
+    max: 59,
+    names: &[],
+    day_field: false,
+};
+const MINUTE: FieldSpec = FieldSpec {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+    day_field: false,
+};
+const HOUR: FieldSpec = FieldSpec {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+    day_field: false,
+};
+const DAY_OF_MONTH: FieldSpec = FieldSpec {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+    day_field: true,
+};
+const MONTH: FieldSpec = FieldSpec {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &MONTHS,
+    day_field: false,
+};
+const DAY_OF_WEEK: FieldSpec = FieldSpec {
+    name: "day of week",
+    min: 0,
+    max: 7,
+    names: &WEEKDAYS,
+    day_field: true,
+};
+const YEAR: FieldSpec = FieldSpec {
+    name: "year",
+    min: 1970,
+    max: 2099,
+    names: &[],
+    day_field: false,
+};
+
+/// A cron expression that failed to parse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct CronParseError {
+    pub message: String,
+    /// Offset into the expression where the problem starts, when it can be pinned down
+    pub position: Option<usize>,
+}
+
+impl CronParseError {
+    pub(crate) fn at(position: usize, message: String) -> Self {
+        Self {
+            message,
+            position: Some(position),
+        }
+    }
+
+    pub(crate) fn whole(message: String) -> Self {
+        Self {
+            message,
+            position: None,
+        }
+    }
+}
+
+impl std::fmt::Display for CronParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{} (at position {})", self.message, position),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for CronParseError {}
+
+/// A validated cron expression and when it fires next
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CronPreview {
+    pub expression: String,
+    /// The six-field form the scheduler runs
+    pub normalized: String,
+    pub description: String,
+    /// Upcoming runs in the server's timezone
+    #[schema(value_type = Vec<String>)]
+    pub next_runs: Vec<DateTime<Local>>,
+}
+
+fn expand_shorthand(expression: &str) -> Option<&'static str> {
+    match expression {
+        "@yearly" | "@annually" => Some("0 0 0 1 1 *"),
+        "@monthly" => Some("0 0 0 1 * *"),
+        "@weekly" => Some("0 0 0 * * 0"),
+        "@daily" | "@midnight" => Some("0 0 0 * * *"),
+        "@hourly" => Some("0 0 * * * *"),
+        _ => None,
+    }
+}
+
+/// Each whitespace separated field with its offset in `expression`
+fn fields_with_offsets(expression: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (index, c) in expression.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                fields.push((s, &expression[s..index]));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        fields.push((s, &expression[s..]));
+    }
+    fields
+}
+
+fn parse_value(spec: &FieldSpec, value: &str) -> Result<u32, String> {
+    if let Ok(number) = value.parse::<u32>() {
+        if number < spec.min || number > spec.max {
+            return Err(format!(
+                "{} is out of range for the {} field ({}-{})",
+                number, spec.name, spec.min, spec.max
+            ));
+        }
+        return Ok(number);
+    }
+    let upper = value.to_ascii_uppercase();
+    match spec.names.iter().position(|name| *name == upper) {
+        Some(index) => Ok(index as u32 + spec.min),
+        None => Err(format!(
+            "'{}' is not a valid value for the {} field",
+            value, spec.name
+        )),
+    }
+}
+
+/// Check one comma separated item of a field, returning an error offset relative to the item
+fn validate_item(spec: &FieldSpec, item: &str) -> Result<(), (usize, String)> {
+    if item.is_empty() {
+        return Err((0, format!("empty list item in the {} field", spec.name)));
+    }
+    if spec.day_field && item.contains(['?', 'L', 'W', '#']) {
+        // Quartz day extensions are checked by the parser itself
+        return Ok(());
+    }
+
+    let (range, step) = match item.split_once('/') {
+        Some((range, step)) => (range, Some(step)),
+        None => (item, None),
+    };
+    if let Some(step) = step {
+        let step_offset = range.len() + 1;
+        match step.parse::<u32>() {
+            Ok(0) | Err(_) => {
+                return Err((
+                    step_offset,
+                    format!("'{}' is not a valid step for the {} field", step, spec.name),
+                ))
+            }
+            Ok(_) => {}
+        }
+    }
+    if range == "*" {
+        return Ok(());
+    }
+    match range.split_once('-') {
+        Some((from, to)) => {
+            let from_value = parse_value(spec, from).map_err(|e| (0, e))?;
+            let to_value = parse_value(spec, to).map_err(|e| (from.len() + 1, e))?;
+            if from_value > to_value {
+                return Err((
+                    0,
+                    format!(
+                        "range {} in the {} field ends before it starts",
+                        range, spec.name
+                    ),
+                ));
+            }
+            Ok(())
+        }
+        None => parse_value(spec, range).map(|_| ()).map_err(|e| (0, e)),
+    }
+}
+
+fn validate_field(spec: &FieldSpec, offset: usize, field: &str) -> Result<(), CronParseError> {
+    let mut item_offset = offset;
+    for item in field.split(',') {
+        validate_item(spec, item)
+            .map_err(|(position, message)| CronParseError::at(item_offset + position, message))?;
+        item_offset += item.len() + 1;
+    }
+    Ok(())
+}
+
+/// Check `expression` and return the six-field form used by the scheduler
+pub fn validate_cron(expression: &str) -> Result<String, CronParseError> {
+    let trimmed = expression.trim();
+    if trimmed.is_empty() {
+        return Err(CronParseError::whole(
+            "cron expression is empty".to_string(),
+        ));
+    }
+    if trimmed.starts_with('@') {
+        return expand_shorthand(trimmed)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                let position = expression.len() - expression.trim_start().len();
+                CronParseError::at(position, format!("unknown shorthand '{}'", trimmed))
+            });
+    }
+
+    let fields = fields_with_offsets(expression);
+    let specs: &[&FieldSpec] = match fields.len() {
+        5 => &[&MINUTE, &HOUR, &DAY_OF_MONTH, &MONTH, &DAY_OF_WEEK],
+        6 => &[&SECOND, &MINUTE, &HOUR, &DAY_OF_MONTH, &MONTH, &DAY_OF_WEEK],
+        7 => &[
+            &SECOND,
+            &MINUTE,
+            &HOUR,
+            &DAY_OF_MONTH,
+            &MONTH,
+            &DAY_OF_WEEK,
+            &YEAR,
+        ],
+        count => {
+            let position = fields.get(7).map(|(offset, _)| *offset);
+            return Err(CronParseError {
+                message: format!("expected 5, 6 or 7 fields, found {}", count),
+                position,
+            });
+        }
+    };
+    for ((offset, field), spec) in fields.iter().zip(specs) {
+        validate_field(spec, *offset, field)?;
+    }
+
+    // The scheduler drops the quartz year field before handing the expression over
+    let normalized = normalize_cron_expression(expression);
+    let six_fields = normalized
+        .split_whitespace()
+        .take(6)
+        .collect::<Vec<_>>()
+        .join(" ");
+    Job::new_async(&six_fields, |_id, _scheduler| Box::pin(async {}))
+        .map_err(|e| CronParseError::whole(format!("invalid cron expression: {}", e)))?;
+    Ok(six_fields)
+}
+
+/// Parse an IANA timezone name such as `Europe/Berlin`; UTC when unset, like the scheduler
+pub fn resolve_timezone(name: Option<&str>) -> Result<Tz, SchedulerError> {
+    match name.map(str::trim).filter(|name| !name.is_empty()) {
+        None => Ok(Tz::UTC),
+        Some(name) => name
+            .parse::<Tz>()
+            .map_err(|_| SchedulerError::InvalidTimezone(name.to_string())),
+    }
+}
+
+fn parse_six_fields(six_fields: &str) -> Result<Cron, CronParseError> {
+    Cron::new(six_fields)
+        .with_seconds_required()
+        .with_dom_and_dow()
+        .parse()
+        .map_err(|e| CronParseError::whole(format!("invalid cron expression: {}", e)))
+}
+
+/// The instant a wall-clock time inside a DST gap maps to: read with the offset in force before
+/// the transition, so it lands as far past the transition as it was past the gap's start
+fn resolve_gap(timezone: &Tz, wall: NaiveDateTime) -> Option<DateTime<Tz>> {
+    let before = (1..=24).find_map(|hours| {
+        timezone
+            .from_local_datetime(&(wall - Duration::hours(hours)))
+            .earliest()
+    })?;
+    let offset = Duration::seconds(before.offset().fix().local_minus_utc() as i64);
+    Some(timezone.from_utc_datetime(&(wall - offset)))
+}
+
+fn next_occurrence(cron: &Cron, timezone: &Tz, after: DateTime<Utc>) -> Option<DateTime<Tz>> {
+    // Match the cron fields against the zone's wall clock, then place the match in the zone
+    let mut wall = after.with_timezone(timezone).naive_local();
+    for _ in 0..MAX_CANDIDATES {
+        let candidate = cron
+            .find_next_occurrence(&wall.and_utc(), false)
+            .ok()?
+            .naive_utc();
+        let resolved = match timezone.from_local_datetime(&candidate) {
+            LocalResult::Single(time) => Some(time),
+            LocalResult::Ambiguous(earliest, _) => Some(earliest),
+            LocalResult::None => resolve_gap(timezone, candidate),
+        };
+        if let Some(time) = resolved.filter(|time| time.with_timezone(&Utc) > after) {
+            return Some(time);
+        }
+        wall = candidate;
+    }
+    None
+}
+
+/// The first run of `expression` in `timezone` strictly after `after`, or `None` if it never fires
+pub fn next_run(
+    expression: &str,
+    timezone: &Tz,
+    after: DateTime<Utc>,
+) -> Result<Option<DateTime<Tz>>, CronParseError> {
+    let cron = parse_six_fields(&validate_cron(expression)?)?;
+    Ok(next_occurrence(&cron, timezone, after))
+}
+
+/// Validate `expression`, describe it and list its next `count` runs in `timezone`
+pub fn preview_cron(
+    expression: &str,
+    timezone: &Tz,
+    count: usize,
+) -> Result<CronPreview, CronParseError> {
+    let normalized = validate_cron(expression)?;
+    let cron = parse_six_fields(&normalized)?;
+
+    let mut next_runs = Vec::with_capacity(count);
+    let mut after = Utc::now();
+    while next_runs.len() < count {
+        // Expressions such as February 30th never fire
+        let Some(next) = next_occurrence(&cron, timezone, after) else {
+            break;
+        };
+        next_runs.push(next.with_timezone(&Local));
+        after = next.with_timezone(&Utc);
+    }
+
+    Ok(CronPreview {
+        expression: expression.trim().to_string(),
+        description: describe(&normalized, timezone),
+        normalized,
+        next_runs,
+    })
+}
+
+fn is_number(field: &str) -> bool {
+    !field.is_empty() && field.chars().all(|c| c.is_ascii_digit())
+}
+
+fn every(field: &str) -> Option<&str> {
+    field.strip_prefix("*/").filter(|step| is_number(step))
+}
+
+fn is_any(field: &str) -> bool {
+    field == "*" || field == "?"
+}
+
+fn name_of(field: &str, names: &[&str], full: fn(usize) -> &'static str, base: u32) -> String {
+    let describe_value = |value: &str| match value.parse::<u32>() {
+        Ok(number) => full(number.saturating_sub(base) as usize % names.len()).to_string(),
+        Err(_) => names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+            .map(|index| full(index).to_string())
+            .unwrap_or_else(|| value.to_string()),
+    };
+    let items: Vec<String> = field
+        .split(',')
+        .map(|item| match item.split_once('-') {
+            Some((from, to)) if !item.contains('/') => {
+                format!("{} through {}", describe_value(from), describe_value(to))
+            }
+            _ if item.contains(['/', 'L', '#', 'W', '*']) => item.to_string(),
+            _ => describe_value(item),
+        })
+        .collect();
+    join_list(&items)
+}
+
+fn join_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+fn weekday_name(index: usize) -> &'static str {
+    [
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+    ][index]
+}
+
+fn month_name(index: usize) -> &'static str {
+    [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ][index]
+}
+
+fn describe_time(second: &str, minute: &str, hour: &str) -> String {
+    if second == "*" {
+        return "every second".to_string();
+    }
+    if let Some(step) = every(second) {
+        if minute == "*" && hour == "*" {
+            return format!("every {} seconds", step);
+        }
+    }
+    if second == "0" || (is_number(second) && minute == "*" && hour == "*") {
+        let at_second = if second == "0" {
+            String::new()
+        } else {
+            format!(" at second {}", second)
+        };
+        if minute == "*" && hour == "*" {
+            return format!("every minute{}", at_second);
+        }
+        if let Some(step) = every(minute) {
+            if hour == "*" {
+                return format!("every {} minutes{}", step, at_second);
+            }
+        }
+        if is_number(minute) {
+            if hour == "*" {
+                return format!("at minute {} of every hour", minute);
+            }
+            if let Some(step) = every(hour) {
+                return format!("at minute {} of every {} hours", minute, step);
+            }
+            let hours: Vec<&str> = hour.split(',').collect();
+            if hours.iter().all(|h| is_number(h)) {
+                let times: Vec<String> = hours
+                    .iter()
+                    .map(|h| format!("{:0>2}:{:0>2}", h, minute))
+                    .collect();
+                return format!("at {}", join_list(&times));
+            }
+        }
+    }
+    if is_number(second) && is_number(minute) && is_number(hour) {
+        return format!("at {:0>2}:{:0>2}:{:0>2}", hour, minute, second);
+    }
+    format!("at second {}, minute {}, hour {}", second, minute, hour)
+}
+
+/// A short English description of a six-field cron expression evaluated in `timezone`
+pub fn describe(six_fields: &str, timezone: &Tz) -> String {
+    let fields: Vec<&str> = six_fields.split_whitespace().collect();
+    let [second, minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+        return six_fields.to_string();
+    };
+
+    let mut description = describe_time(second, minute, hour);
+    match (is_any(day_of_month), is_any(day_of_week)) {
+        (true, true) => {
+            if hour.split(',').all(is_number) {
+                description.push_str(" every day");
+            }
+        }
+        (false, true) => {
+            description.push_str(&format!(" on day {} of the month", day_of_month));
+        }
+        (true, false) => {
+            description.push_str(&format!(
+                " on {}",
+                name_of(day_of_week, &WEEKDAYS, weekday_name, 0)
+            ));
+        }
+        (false, false) => {
+            description.push_str(&format!(
+                " on day {} of the month if it is a {}",
+                day_of_month,
+                name_of(day_of_week, &WEEKDAYS, weekday_name, 0)
+            ));
+        }
+    }
+    if !is_any(month) {
+        description.push_str(&format!(" in {}", name_of(month, &MONTHS, month_name, 1)));
+    }
+    description.push_str(&format!(" ({})", timezone.name()));
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validate_accepts_scheduler_formats() {
+        assert_eq!(validate_cron("0 9 * * 1-5").unwrap(), "0 0 9 * * 1-5");
+        assert_eq!(validate_cron("*/30 * * * * *").unwrap(), "*/30 * * * * *");
+        assert_eq!(validate_cron("0 0 12 * * * *").unwrap(), "0 0 12 * * *");
+        assert_eq!(
+            validate_cron("0 0 1 JAN-MAR MON").unwrap(),
+            "0 0 0 1 JAN-MAR MON"
+        );
+        assert_eq!(validate_cron("@daily").unwrap(), "0 0 0 * * *");
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_position() {
+        let error = validate_cron("0 25 * * *").unwrap_err();
+        assert_eq!(error.position, Some(2));
+        assert!(error.message.contains("hour"), "{}", error.message);
+
+        let error = validate_cron("0 9 * * 1-5,9").unwrap_err();
+        assert_eq!(error.position, Some(12));
+
+        let error = validate_cron("*/0 * * * *").unwrap_err();
+        assert_eq!(error.position, Some(2));
+
+        let error = validate_cron("0 9 * * * * * *").unwrap_err();
+        assert_eq!(error.position, Some(14));
+        assert!(validate_cron("  ").is_err());
+        assert!(validate_cron("@sometimes").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preview_lists_upcoming_runs() {
+        let preview = preview_cron("30 9 * * *", &Tz::UTC, DEFAULT_PREVIEW_RUNS).unwrap();
+        assert_eq!(preview.next_runs.len(), DEFAULT_PREVIEW_RUNS);
+        assert!(preview.next_runs[0] > Local::now());
+        for pair in preview.next_runs.windows(2) {
+            assert_eq!((pair[1] - pair[0]).num_hours(), 24);
+        }
+        for run in &preview.next_runs {
+            let utc = run.with_timezone(&Utc);
+            assert_eq!(utc.format("%H:%M:%S").to_string(), "09:30:00");
+        }
+    }
+
+    fn describe_utc(six_fields: &str) -> String {
+        describe(six_fields, &Tz::UTC)
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe_utc("0 30 9 * * *"), "at 09:30 every day (UTC)");
+        assert_eq!(describe_utc("0 */15 * * * *"), "every 15 minutes (UTC)");
+        assert_eq!(
+            describe_utc("0 0 9 * * 1-5"),
+            "at 09:00 on Monday through Friday (UTC)"
+        );
+        assert_eq!(
+            describe_utc("0 0 8,17 * * *"),
+            "at 08:00 and 17:00 every day (UTC)"
+        );
+        assert_eq!(
+            describe_utc("0 0 0 1 JAN *"),
+            "at 00:00 on day 1 of the month in January (UTC)"
+        );
+        assert_eq!(
+            describe_utc("0 5 * * * *"),
+            "at minute 5 of every hour (UTC)"
+        );
+    }
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_next_run_in_timezone() {
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        // 09:00 in Berlin is 08:00 UTC in winter and 07:00 UTC in summer
+        let winter = next_run("0 9 * * *", &berlin, utc("2026-01-15T12:00:00Z"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(winter.with_timezone(&Utc), utc("2026-01-16T08:00:00Z"));
+        let summer = next_run("0 9 * * *", &berlin, utc("2026-07-15T12:00:00Z"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(summer.with_timezone(&Utc), utc("2026-07-16T07:00:00Z"));
+
+        assert!(resolve_timezone(Some("Mars/Olympus_Mons")).is_err());
+        assert_eq!(resolve_timezone(None).unwrap(), Tz::UTC);
+    }
+
+    #[tokio::test]
+    async fn test_next_run_across_spring_forward_gap() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        // Clocks jump from 02:00 EST to 03:00 EDT on 2026-03-08, so 02:30 does not exist that night
+        let midnight = utc("2026-03-08T05:00:00Z");
+        let first = next_run("30 2 * * *", &new_york, midnight)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.to_rfc3339(), "2026-03-08T03:30:00-04:00");
+        assert_eq!(first.with_timezone(&Utc), utc("2026-03-08T07:30:00Z"));
+
+        let second = next_run("30 2 * * *", &new_york, first.with_timezone(&Utc))
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.to_rfc3339(), "2026-03-09T02:30:00-04:00");
+
+        // Runs inside the gap do not repeat once the clock reaches the same wall time again
+        let mut after = utc("2026-03-08T06:58:00Z");
+        let mut runs = Vec::new();
+        for _ in 0..3 {
+            let run = next_run("*/30 * * * *", &new_york, after).unwrap().unwrap();
+            runs.push(run.to_rfc3339());
+            after = run.with_timezone(&Utc);
+        }
+        assert_eq!(
+            runs,
+            [
+                "2026-03-08T03:00:00-04:00",
+                "2026-03-08T03:30:00-04:00",
+                "2026-03-08T04:00:00-04:00"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_next_run_across_fall_back_overlap() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        // 01:30 happens twice on 2026-11-01; the job runs at the first one only
+        let first = next_run("30 1 * * *", &new_york, utc("2026-11-01T04:00:00Z"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.to_rfc3339(), "2026-11-01T01:30:00-04:00");
+        let second = next_run("30 1 * * *", &new_york, first.with_timezone(&Utc))
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.to_rfc3339(), "2026-11-02T01:30:00-05:00");
+    }
+}
@@ -0,0 +1,310 @@
+//! Conditions checked before a scheduled job fires.
+//!
+//! Conditions come from the job and from the `GOOSE_SCHEDULER_CONDITIONS` config entry; each
+//! condition set on the job wins over the global one. A run blocked by a condition is recorded
+//! as skipped with the reason, and running a job manually ignores the conditions. When the power
+//! source or connection type can't be detected, the conditions that depend on them don't block.
+
+use std::process::Command;
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+pub const SCHEDULER_CONDITIONS_KEY: &str = "GOOSE_SCHEDULER_CONDITIONS";
+
+/// When scheduled runs are held back; unset conditions don't apply
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ExecutionConditions {
+    /// Skip runs while the machine is running on battery
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_on_battery: Option<bool>,
+    /// Skip runs while the network connection is metered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_on_metered: Option<bool>,
+    /// Skip runs during this daily window, e.g. `22:00-07:00`, in the job's timezone or the
+    /// machine's local time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_hours: Option<String>,
+    /// Skip runs while this many scheduled jobs are already running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_jobs: Option<usize>,
+}
+
+impl ExecutionConditions {
+    /// The conditions configured for all jobs
+    pub fn global() -> Self {
+        Config::global()
+            .get_param(SCHEDULER_CONDITIONS_KEY)
+            .unwrap_or_default()
+    }
+
+    /// These conditions, with the unset ones taken from `fallback`
+    pub fn or(&self, fallback: &Self) -> Self {
+        ExecutionConditions {
+            pause_on_battery: self.pause_on_battery.or(fallback.pause_on_battery),
+            pause_on_metered: self.pause_on_metered.or(fallback.pause_on_metered),
+            pause_hours: self
+                .pause_hours
+                .clone()
+                .or_else(|| fallback.pause_hours.clone()),
+            max_concurrent_jobs: self.max_concurrent_jobs.or(fallback.max_concurrent_jobs),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(hours) = &self.pause_hours {
+            PauseWindow::parse(hours)?;
+        }
+        if self.max_concurrent_jobs == Some(0) {
+            return Err("max_concurrent_jobs must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Why the machine's state or the time of day holds a run back at `local_time`
+    pub fn blocked_by_machine(
+        &self,
+        machine: &dyn MachineState,
+        local_time: NaiveTime,
+    ) -> Option<String> {
+        if let Some(hours) = &self.pause_hours {
+            match PauseWindow::parse(hours) {
+                Ok(window) if window.contains(local_time) => {
+                    return Some(format!("within the paused hours {}", hours));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Ignoring pause_hours '{}': {}", hours, e),
+            }
+        }
+        if self.pause_on_battery == Some(true) && machine.on_battery() == Some(true) {
+            return Some("the machine is running on battery".to_string());
+        }
+        if self.pause_on_metered == Some(true) && machine.on_metered_connection() == Some(true) {
+            return Some("the network connection is metered".to_string());
+        }
+        None
+    }
+
+    /// Why `running_jobs` already running jobs hold a run back
+    pub fn blocked_by_running_jobs(&self, running_jobs: usize) -> Option<String> {
+        let max = self.max_concurrent_jobs?;
+        (running_jobs >= max).then(|| {
+            format!(
+                "{} scheduled jobs are already running (maximum {})",
+                running_jobs, max
+            )
+        })
+    }
+}
+
+/// A daily window such as `22:00-07:00`, which may wrap past midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PauseWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl PauseWindow {
+    pub fn parse(window: &str) -> Result<Self, String> {
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| format!("Expected a window like '22:00-07:00', got '{}'", window))?;
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map_err(|_| format!("Invalid time '{}', expected HH:MM", value.trim()))
+        };
+        Ok(PauseWindow {
+            start: time(start)?,
+            end: time(end)?,
+        })
+    }
+
+    /// Whether `time` falls in the window; the start is included and the end is not
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// The parts of the machine's state that conditions depend on; `None` when unknown
+pub trait MachineState: Send + Sync {
+    fn on_battery(&self) -> Option<bool>;
+    fn on_metered_connection(&self) -> Option<bool>;
+}
+
+/// Reads the power source and connection type from the operating system
+pub struct SystemMachineState;
+
+impl MachineState for SystemMachineState {
+    fn on_battery(&self) -> Option<bool> {
+        if cfg!(target_os = "linux") {
+            linux_on_battery()
+        } else if cfg!(target_os = "macos") {
+            let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+            let output = String::from_utf8_lossy(&output.stdout);
+            Some(output.contains("'Battery Power'"))
+        } else {
+            None
+        }
+    }
+
+    fn on_metered_connection(&self) -> Option<bool> {
+        if !cfg!(target_os = "linux") {
+            return None;
+        }
+        // NetworkManager reports "yes" or "yes (guessed)" for metered devices
+        let output = Command::new("nmcli")
+            .args(["-t", "-f", "GENERAL.METERED", "dev", "show"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        Some(
+            output
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .any(|(_, metered)| metered.starts_with("yes")),
+        )
+    }
+}
+
+fn linux_on_battery() -> Option<bool> {
+    let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let read = |path: &std::path::Path, name: &str| {
+        std::fs::read_to_string(path.join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    Some(supplies.flatten().any(|supply| {
+        let path = supply.path();
+        read(&path, "type") == "Battery" && read(&path, "status") == "Discharging"
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeMachine {
+        on_battery: Option<bool>,
+        metered: Option<bool>,
+    }
+
+    impl MachineState for FakeMachine {
+        fn on_battery(&self) -> Option<bool> {
+            self.on_battery
+        }
+
+        fn on_metered_connection(&self) -> Option<bool> {
+            self.metered
+        }
+    }
+
+    fn time(value: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(value, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_pause_window() {
+        let overnight = PauseWindow::parse("22:00-07:00").unwrap();
+        assert!(overnight.contains(time("23:30")));
+        assert!(overnight.contains(time("06:59")));
+        assert!(!overnight.contains(time("07:00")));
+        assert!(!overnight.contains(time("12:00")));
+
+        let lunch = PauseWindow::parse(" 12:00 - 13:00 ").unwrap();
+        assert!(lunch.contains(time("12:30")));
+        assert!(!lunch.contains(time("13:30")));
+
+        assert!(PauseWindow::parse("22:00").is_err());
+        assert!(PauseWindow::parse("25:00-07:00").is_err());
+    }
+
+    #[test]
+    fn test_job_conditions_override_global() {
+        let global = ExecutionConditions {
+            pause_on_battery: Some(true),
+            max_concurrent_jobs: Some(2),
+            ..Default::default()
+        };
+        let job = ExecutionConditions {
+            pause_on_battery: Some(false),
+            pause_hours: Some("22:00-07:00".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            job.or(&global),
+            ExecutionConditions {
+                pause_on_battery: Some(false),
+                pause_on_metered: None,
+                pause_hours: Some("22:00-07:00".to_string()),
+                max_concurrent_jobs: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn test_blocked_by_machine() {
+        let conditions = ExecutionConditions {
+            pause_on_battery: Some(true),
+            pause_on_metered: Some(true),
+            pause_hours: Some("22:00-07:00".to_string()),
+            ..Default::default()
+        };
+        let plugged_in = FakeMachine {
+            on_battery: Some(false),
+            metered: Some(false),
+        };
+        let on_battery = FakeMachine {
+            on_battery: Some(true),
+            metered: None,
+        };
+        let unknown = FakeMachine {
+            on_battery: None,
+            metered: None,
+        };
+
+        assert_eq!(
+            conditions.blocked_by_machine(&plugged_in, time("12:00")),
+            None
+        );
+        assert_eq!(conditions.blocked_by_machine(&unknown, time("12:00")), None);
+        assert_eq!(
+            conditions.blocked_by_machine(&on_battery, time("12:00")),
+            Some("the machine is running on battery".to_string())
+        );
+        assert_eq!(
+            conditions.blocked_by_machine(&plugged_in, time("23:00")),
+            Some("within the paused hours 22:00-07:00".to_string())
+        );
+        assert_eq!(
+            ExecutionConditions::default().blocked_by_machine(&on_battery, time("23:00")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_blocked_by_running_jobs() {
+        let conditions = ExecutionConditions {
+            max_concurrent_jobs: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(conditions.blocked_by_running_jobs(1), None);
+        assert!(conditions.blocked_by_running_jobs(2).is_some());
+        assert_eq!(
+            ExecutionConditions::default().blocked_by_running_jobs(10),
+            None
+        );
+        assert!(ExecutionConditions {
+            max_concurrent_jobs: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
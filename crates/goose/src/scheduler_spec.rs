@@ -0,0 +1,244 @@
+//! When a scheduled job fires: on a cron expression, once at a `run_at` time, or on an `every`
+//! interval.
+//!
+//! Intervals are one or more `<number><unit>` parts with units `d`, `h`, `m` and `s`, e.g. `4h`,
+//! `30m` or `1h30m`. An interval job first fires one interval after it is scheduled, which
+//! happens when it is created, updated, or loaded when the scheduler starts. A one-shot job whose
+//! time passed while the scheduler was not running fires as soon as it is loaded.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::SchedulerError;
+use crate::scheduler_cron::{next_run, validate_cron, CronParseError};
+
+/// How a job's fire times are given, see [`ScheduleSpec`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleKind {
+    #[default]
+    Cron,
+    RunAt,
+    Every,
+}
+
+/// When a job fires
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleSpec {
+    Cron(String),
+    RunAt(DateTime<Utc>),
+    /// An interval such as `4h` or `1h30m`
+    Every(String),
+}
+
+impl ScheduleSpec {
+    /// The timing given by exactly one of `cron`, `run_at` and `every`
+    pub fn from_parts(
+        cron: Option<String>,
+        run_at: Option<DateTime<Utc>>,
+        every: Option<String>,
+    ) -> Result<Self, CronParseError> {
+        match (cron, run_at, every) {
+            (Some(cron), None, None) => Ok(ScheduleSpec::Cron(cron)),
+            (None, Some(run_at), None) => Ok(ScheduleSpec::RunAt(run_at)),
+            (None, None, Some(every)) => Ok(ScheduleSpec::Every(every)),
+            _ => Err(CronParseError::whole(
+                "Exactly one of 'cron', 'run_at' and 'every' must be set".to_string(),
+            )),
+        }
+    }
+
+    pub fn kind(&self) -> ScheduleKind {
+        match self {
+            ScheduleSpec::Cron(_) => ScheduleKind::Cron,
+            ScheduleSpec::RunAt(_) => ScheduleKind::RunAt,
+            ScheduleSpec::Every(_) => ScheduleKind::Every,
+        }
+    }
+
+    /// Check the expression or interval, and that a one-shot time is still ahead of `now`
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), CronParseError> {
+        match self {
+            ScheduleSpec::Cron(expression) => validate_cron(expression).map(|_| ()),
+            ScheduleSpec::RunAt(run_at) if *run_at <= now => Err(CronParseError::whole(format!(
+                "run_at {} is in the past",
+                run_at.to_rfc3339()
+            ))),
+            ScheduleSpec::RunAt(_) => Ok(()),
+            ScheduleSpec::Every(interval) => parse_interval(interval).map(|_| ()),
+        }
+    }
+
+    /// The first fire time after `after`; `anchor` is when an interval schedule started
+    pub fn next_fire(
+        &self,
+        timezone: &Tz,
+        anchor: Option<DateTime<Utc>>,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            ScheduleSpec::Cron(expression) => next_run(expression, timezone, after)
+                .ok()
+                .flatten()
+                .map(|time| time.with_timezone(&Utc)),
+            ScheduleSpec::RunAt(run_at) => (*run_at > after).then_some(*run_at),
+            ScheduleSpec::Every(interval) => {
+                let interval = chrono::Duration::from_std(parse_interval(interval).ok()?).ok()?;
+                let anchor = anchor?;
+                if after < anchor {
+                    return Some(anchor + interval);
+                }
+                let elapsed = (after - anchor).num_milliseconds() / interval.num_milliseconds();
+                Some(anchor + interval * (elapsed as i32 + 1))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ScheduleSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleSpec::Cron(expression) => write!(f, "cron '{}'", expression),
+            ScheduleSpec::RunAt(run_at) => write!(f, "once at {}", run_at.to_rfc3339()),
+            ScheduleSpec::Every(interval) => write!(f, "every {}", interval),
+        }
+    }
+}
+
+/// Parse an interval such as `4h`, `30m` or `1h30m`
+pub fn parse_interval(interval: &str) -> Result<Duration, CronParseError> {
+    let trimmed = interval.trim_start();
+    let offset = interval.len() - trimmed.len();
+    let trimmed = trimmed.trim_end();
+    if trimmed.is_empty() {
+        return Err(CronParseError::whole("Interval is empty".to_string()));
+    }
+
+    let mut seconds: u64 = 0;
+    let mut number_start = 0;
+    for (index, c) in trimmed.char_indices() {
+        if c.is_ascii_digit() {
+            continue;
+        }
+        let unit = match c {
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => {
+                return Err(CronParseError::at(
+                    offset + index,
+                    format!("Unknown interval unit '{}', expected d, h, m or s", c),
+                ))
+            }
+        };
+        let value: u64 = trimmed[number_start..index].parse().map_err(|_| {
+            CronParseError::at(offset + index, format!("Missing number before '{}'", c))
+        })?;
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|part| seconds.checked_add(part))
+            .ok_or_else(|| {
+                CronParseError::at(offset + number_start, "Interval is too long".to_string())
+            })?;
+        number_start = index + c.len_utf8();
+    }
+    if number_start < trimmed.len() {
+        return Err(CronParseError::at(
+            offset + trimmed.len(),
+            "Missing unit after the last number, e.g. '30m'".to_string(),
+        ));
+    }
+    if seconds == 0 {
+        return Err(CronParseError::at(
+            offset,
+            "Interval must be longer than zero".to_string(),
+        ));
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Require a cron schedule, for schedulers that support nothing else
+pub fn require_cron(spec: ScheduleSpec) -> Result<String, SchedulerError> {
+    match spec {
+        ScheduleSpec::Cron(expression) => Ok(expression),
+        other => Err(SchedulerError::CronParseError(format!(
+            "Only cron schedules are supported, got {}",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(
+            parse_interval(" 4h ").unwrap(),
+            Duration::from_secs(4 * 3600)
+        );
+        assert_eq!(parse_interval("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86400));
+
+        assert_eq!(parse_interval("4x").unwrap_err().position, Some(1));
+        assert_eq!(parse_interval("h").unwrap_err().position, Some(0));
+        assert_eq!(parse_interval("1h30").unwrap_err().position, Some(4));
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("").is_err());
+    }
+
+    #[test]
+    fn test_exactly_one_timing() {
+        assert_eq!(
+            ScheduleSpec::from_parts(None, None, Some("4h".to_string())).unwrap(),
+            ScheduleSpec::Every("4h".to_string())
+        );
+        assert!(ScheduleSpec::from_parts(None, None, None).is_err());
+        assert!(ScheduleSpec::from_parts(
+            Some("0 0 * * *".to_string()),
+            None,
+            Some("4h".to_string())
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_next_fire() {
+        let anchor = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let every = ScheduleSpec::Every("4h".to_string());
+        assert_eq!(
+            every.next_fire(&Tz::UTC, Some(anchor), anchor),
+            Some(Utc.with_ymd_and_hms(2025, 6, 1, 16, 0, 0).unwrap())
+        );
+        assert_eq!(
+            every.next_fire(
+                &Tz::UTC,
+                Some(anchor),
+                Utc.with_ymd_and_hms(2025, 6, 1, 21, 0, 0).unwrap()
+            ),
+            Some(Utc.with_ymd_and_hms(2025, 6, 2, 0, 0, 0).unwrap())
+        );
+        assert_eq!(every.next_fire(&Tz::UTC, None, anchor), None);
+
+        let run_at = ScheduleSpec::RunAt(anchor);
+        assert_eq!(
+            run_at.next_fire(&Tz::UTC, None, anchor - chrono::Duration::hours(1)),
+            Some(anchor)
+        );
+        assert_eq!(run_at.next_fire(&Tz::UTC, None, anchor), None);
+        assert!(run_at.validate(anchor).is_err());
+
+        let cron = ScheduleSpec::Cron("0 0 * * *".to_string());
+        assert_eq!(
+            cron.next_fire(&Tz::UTC, None, anchor),
+            Some(Utc.with_ymd_and_hms(2025, 6, 2, 0, 0, 0).unwrap())
+        );
+    }
+}
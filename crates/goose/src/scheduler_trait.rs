@@ -1,7 +1,9 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 
-use crate::scheduler::{ScheduledJob, SchedulerError};
+use crate::scheduler::{ConcurrencyPolicy, ScheduledJob, SchedulerError};
+use crate::scheduler_gate::ExecutionConditions;
+use crate::scheduler_spec::ScheduleSpec;
 use crate::session::storage::SessionMetadata;
 
 /// Common trait for all scheduler implementations
@@ -32,9 +34,32 @@ pub trait SchedulerTrait: Send + Sync {
         limit: usize,
     ) -> Result<Vec<(String, SessionMetadata)>, SchedulerError>;
 
-    /// Update a schedule's cron expression
-    async fn update_schedule(&self, sched_id: &str, new_cron: String)
-        -> Result<(), SchedulerError>;
+    /// Update when a job fires and its timezone; the new timing may be of a different kind
+    async fn update_schedule(
+        &self,
+        sched_id: &str,
+        spec: ScheduleSpec,
+        timezone: Option<String>,
+    ) -> Result<(), SchedulerError>;
+
+    /// Whether jobs can be held to `policy`, to check before creating or changing one
+    fn supports_concurrency_policy(&self, _policy: ConcurrencyPolicy) -> bool {
+        true
+    }
+
+    /// Change what happens when a job fires while its previous run is still going
+    async fn set_concurrency_policy(
+        &self,
+        sched_id: &str,
+        policy: ConcurrencyPolicy,
+    ) -> Result<(), SchedulerError>;
+
+    /// Replace the conditions that hold a job's runs back
+    async fn set_execution_conditions(
+        &self,
+        sched_id: &str,
+        conditions: ExecutionConditions,
+    ) -> Result<(), SchedulerError>;
 
     /// Kill a running job
     async fn kill_running_job(&self, sched_id: &str) -> Result<(), SchedulerError>;
@@ -0,0 +1,165 @@
+//! Webhook notifications for finished scheduled runs.
+//!
+//! The payload is POSTed as JSON. When `GOOSE_SCHEDULER_WEBHOOK_SECRET` is configured the body is
+//! signed with HMAC-SHA256 and the signature is sent as `X-Goose-Signature: sha256=<hex>`, so
+//! receivers can check that the notification came from this goose.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use rmcp::model::Role;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::config::Config;
+use crate::message::Message;
+use crate::session::storage::{self, Identifier};
+
+pub const SIGNATURE_HEADER: &str = "X-Goose-Signature";
+pub const WEBHOOK_SECRET_KEY: &str = "GOOSE_SCHEDULER_WEBHOOK_SECRET";
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where to report a run of a scheduled job
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct WebhookNotification {
+    pub webhook_url: String,
+    /// Send the messages of the run's session along with the summary
+    #[serde(default)]
+    pub include_transcript: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunEvent {
+    RunSucceeded,
+    RunFailed,
+}
+
+/// The JSON body POSTed to a job's webhook
+#[derive(Debug, Serialize)]
+pub struct RunNotification {
+    pub event: RunEvent,
+    pub job_id: String,
+    pub session_id: Option<String>,
+    /// Number of attempts made, including the first
+    pub attempts: u32,
+    pub error: Option<String>,
+    /// Time from the start of the first attempt to the end of the last one
+    pub duration_seconds: i64,
+    pub last_assistant_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<Vec<Message>>,
+}
+
+impl RunNotification {
+    /// Fill in the last assistant message, and the transcript if requested, from the session
+    pub fn with_session_messages(mut self, include_transcript: bool) -> Self {
+        let Some(session_id) = self.session_id.as_deref() else {
+            return self;
+        };
+        let messages = match storage::get_path(Identifier::Name(session_id.to_string()))
+            .and_then(|path| storage::read_messages(&path))
+        {
+            Ok(messages) => messages,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to read session '{}' for job '{}' notification: {}",
+                    session_id,
+                    self.job_id,
+                    e
+                );
+                return self;
+            }
+        };
+        self.last_assistant_message = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == Role::Assistant)
+            .map(Message::as_concat_text);
+        if include_transcript {
+            self.transcript = Some(messages);
+        }
+        self
+    }
+}
+
+/// The `X-Goose-Signature` value for `body`
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", digest)
+}
+
+/// POST `notification` to the webhook
+pub async fn send_notification(
+    target: &WebhookNotification,
+    notification: &RunNotification,
+) -> Result<()> {
+    let body = serde_json::to_vec(notification)?;
+    let mut request = reqwest::Client::new()
+        .post(&target.webhook_url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+
+    match Config::global().get_secret::<String>(WEBHOOK_SECRET_KEY) {
+        Ok(secret) => request = request.header(SIGNATURE_HEADER, sign_payload(&secret, &body)),
+        Err(_) => tracing::warn!(
+            "{} is not set; sending unsigned notification for job '{}'",
+            WEBHOOK_SECRET_KEY,
+            notification.job_id
+        ),
+    }
+
+    let response = request.body(body).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Webhook {} responded with {}",
+            target.webhook_url,
+            response.status()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_transcript_omitted_unless_requested() {
+        let notification = RunNotification {
+            event: RunEvent::RunFailed,
+            job_id: "nightly".to_string(),
+            session_id: None,
+            attempts: 3,
+            error: Some("provider unavailable".to_string()),
+            duration_seconds: 95,
+            last_assistant_message: None,
+            transcript: None,
+        }
+        .with_session_messages(true);
+
+        let body = serde_json::to_value(&notification).unwrap();
+        assert_eq!(body["event"], "run_failed");
+        assert_eq!(body["attempts"], 3);
+        assert!(body.get("transcript").is_none());
+    }
+}
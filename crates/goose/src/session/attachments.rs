@@ -0,0 +1,498 @@
+//! Files uploaded to a session and referenced from its messages by id.
+//!
+//! Uploads are stored under `artifacts/{session_id}/attachments` in the session directory, next
+//! to the tool result artifacts, so they are removed along with the session. Each attachment is
+//! kept as `{id}` with its details in `{id}.json`, and the text extracted from a PDF in
+//! `{id}.txt`. Messages carry a [`MessageContent::Attachment`] reference, which is swapped for
+//! the image or text just before the provider is called so the session file stays small.
+//!
+//! Uploads are capped at `GOOSE_MAX_ATTACHMENT_BYTES` (20 MiB by default) and limited to the
+//! mime types in `GOOSE_ATTACHMENT_TYPES`, where an entry like `text/*` allows a whole family.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::message::{AttachmentReference, Message, MessageContent};
+use crate::session::artifacts::artifact_dir;
+use crate::session::storage::ensure_session_dir;
+
+const MAX_ATTACHMENT_BYTES_KEY: &str = "GOOSE_MAX_ATTACHMENT_BYTES";
+const ATTACHMENT_TYPES_KEY: &str = "GOOSE_ATTACHMENT_TYPES";
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+const DEFAULT_ATTACHMENT_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "application/json",
+    "text/*",
+];
+const ATTACHMENTS_DIR: &str = "attachments";
+/// Characters of extracted text kept in [`AttachmentInfo::text_preview`]
+const TEXT_PREVIEW_CHARS: usize = 200;
+
+#[derive(Error, Debug)]
+pub enum AttachmentError {
+    #[error("Attachment is {size} bytes, over the limit of {limit} bytes")]
+    TooLarge { size: usize, limit: usize },
+    #[error("Attachments of type {0} are not allowed")]
+    UnsupportedType(String),
+    #[error("Invalid id {0}")]
+    InvalidId(String),
+    #[error("No attachment with id {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
+
+/// Details of a stored attachment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentInfo {
+    /// Id to reference the attachment by in a message
+    pub id: String,
+    /// Name of the uploaded file
+    pub name: String,
+    /// Mime type, detected from the contents where possible
+    pub mime_type: String,
+    /// Size in bytes
+    pub size: usize,
+    /// Width in pixels, for images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Height in pixels, for images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Characters of text extracted from the file, for PDFs and text files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_length: Option<usize>,
+    /// The start of the extracted text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_preview: Option<String>,
+}
+
+impl AttachmentInfo {
+    fn is_image(&self) -> bool {
+        self.width.is_some()
+    }
+}
+
+/// Size and type limits for uploads
+#[derive(Debug, Clone)]
+pub struct AttachmentLimits {
+    pub max_bytes: usize,
+    pub allowed_types: Vec<String>,
+}
+
+impl Default for AttachmentLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
+            allowed_types: DEFAULT_ATTACHMENT_TYPES
+                .iter()
+                .map(|mime| mime.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl AttachmentLimits {
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        let defaults = Self::default();
+        Self {
+            max_bytes: config
+                .get_param(MAX_ATTACHMENT_BYTES_KEY)
+                .unwrap_or(defaults.max_bytes),
+            allowed_types: config
+                .get_param(ATTACHMENT_TYPES_KEY)
+                .unwrap_or(defaults.allowed_types),
+        }
+    }
+
+    pub fn allows(&self, mime_type: &str) -> bool {
+        self.allowed_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(family) => mime_type
+                    .split_once('/')
+                    .is_some_and(|(prefix, _)| prefix == family),
+                None => allowed == mime_type,
+            })
+    }
+
+    fn check(&self, size: usize, mime_type: &str) -> Result<(), AttachmentError> {
+        if size > self.max_bytes {
+            return Err(AttachmentError::TooLarge {
+                size,
+                limit: self.max_bytes,
+            });
+        }
+        if !self.allows(mime_type) {
+            return Err(AttachmentError::UnsupportedType(mime_type.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Directory holding the attachments of a session
+pub fn attachment_dir(session_dir: &Path, session_id: &str) -> PathBuf {
+    artifact_dir(session_dir, session_id).join(ATTACHMENTS_DIR)
+}
+
+fn validate_id(id: &str) -> Result<(), AttachmentError> {
+    if id.is_empty() || id.len() > 255 || id.contains("..") || id.contains(['/', '\\']) {
+        return Err(AttachmentError::InvalidId(id.to_string()));
+    }
+    Ok(())
+}
+
+fn session_attachment_dir(session_id: &str) -> Result<PathBuf, AttachmentError> {
+    validate_id(session_id)?;
+    Ok(attachment_dir(&ensure_session_dir()?, session_id))
+}
+
+/// Store an upload for `session_id` under the configured limits
+pub fn save_attachment(
+    session_id: &str,
+    file_name: Option<&str>,
+    declared_mime_type: Option<&str>,
+    bytes: &[u8],
+) -> Result<AttachmentInfo, AttachmentError> {
+    save_attachment_in(
+        &session_attachment_dir(session_id)?,
+        &AttachmentLimits::from_config(),
+        file_name,
+        declared_mime_type,
+        bytes,
+    )
+}
+
+/// Store an upload in `dir`, detecting its type and pulling out its dimensions or text
+pub fn save_attachment_in(
+    dir: &Path,
+    limits: &AttachmentLimits,
+    file_name: Option<&str>,
+    declared_mime_type: Option<&str>,
+    bytes: &[u8],
+) -> Result<AttachmentInfo, AttachmentError> {
+    let mime_type = detect_mime_type(bytes, file_name, declared_mime_type);
+    limits.check(bytes.len(), &mime_type)?;
+
+    let id = Uuid::new_v4().to_string();
+    let mut info = AttachmentInfo {
+        id: id.clone(),
+        name: file_name
+            .and_then(|name| Path::new(name).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| id.clone()),
+        mime_type,
+        size: bytes.len(),
+        width: None,
+        height: None,
+        text_length: None,
+        text_preview: None,
+    };
+
+    // Images that can't be decoded, like SVGs, are passed on as text
+    let dimensions = image::io::Reader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+    let text = if let Some((width, height)) = dimensions {
+        info.width = Some(width);
+        info.height = Some(height);
+        None
+    } else if info.mime_type == "application/pdf" {
+        Some(extract_pdf_text(bytes)?)
+    } else {
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    };
+
+    fs::create_dir_all(dir).map_err(anyhow::Error::from)?;
+    fs::write(dir.join(&id), bytes).map_err(anyhow::Error::from)?;
+    if let Some(text) = text {
+        if info.mime_type == "application/pdf" {
+            fs::write(dir.join(format!("{}.txt", id)), &text).map_err(anyhow::Error::from)?;
+        }
+        info.text_length = Some(text.chars().count());
+        info.text_preview = Some(text.chars().take(TEXT_PREVIEW_CHARS).collect());
+    }
+    let details = serde_json::to_vec_pretty(&info).map_err(anyhow::Error::from)?;
+    fs::write(dir.join(format!("{}.json", id)), details).map_err(anyhow::Error::from)?;
+
+    Ok(info)
+}
+
+/// Details of an attachment stored in `dir`
+pub fn read_attachment_info(dir: &Path, id: &str) -> Result<AttachmentInfo, AttachmentError> {
+    validate_id(id)?;
+    let details = match fs::read(dir.join(format!("{}.json", id))) {
+        Ok(details) => details,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(AttachmentError::NotFound(id.to_string()))
+        }
+        Err(e) => return Err(anyhow::Error::from(e).into()),
+    };
+    Ok(serde_json::from_slice(&details).map_err(anyhow::Error::from)?)
+}
+
+/// Work out the mime type of an upload, trusting its contents over what the client declared
+fn detect_mime_type(bytes: &[u8], file_name: Option<&str>, declared: Option<&str>) -> String {
+    if let Ok(format) = image::guess_format(bytes) {
+        let sniffed = match format {
+            image::ImageFormat::Png => Some("image/png"),
+            image::ImageFormat::Jpeg => Some("image/jpeg"),
+            image::ImageFormat::Gif => Some("image/gif"),
+            image::ImageFormat::WebP => Some("image/webp"),
+            _ => None,
+        };
+        if let Some(mime_type) = sniffed {
+            return mime_type.to_string();
+        }
+    }
+    if bytes.starts_with(b"%PDF-") {
+        return "application/pdf".to_string();
+    }
+
+    let declared = declared
+        .map(|mime| {
+            mime.split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase()
+        })
+        .filter(|mime| !mime.is_empty() && mime != "application/octet-stream");
+    if let Some(declared) = declared {
+        return declared;
+    }
+
+    let extension = file_name
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("json") => "application/json".to_string(),
+        Some("md") | Some("markdown") => "text/markdown".to_string(),
+        Some("csv") => "text/csv".to_string(),
+        Some("html") | Some("htm") => "text/html".to_string(),
+        _ if std::str::from_utf8(bytes).is_ok() => "text/plain".to_string(),
+        _ => "application/octet-stream".to_string(),
+    }
+}
+
+fn extract_pdf_text(bytes: &[u8]) -> Result<String, AttachmentError> {
+    let document = lopdf::Document::load_mem(bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to read PDF: {}", e))?;
+    let pages: Vec<u32> = document.get_pages().keys().copied().collect();
+    Ok(document.extract_text(&pages).unwrap_or_default())
+}
+
+/// Replace the attachment references in `messages` with the contents of the attachments
+/// uploaded to `session_id`, ready to send to a provider
+pub fn resolve_attachments(session_id: Option<&str>, messages: &[Message]) -> Vec<Message> {
+    let has_attachments = messages.iter().any(|message| {
+        message
+            .content
+            .iter()
+            .any(|content| matches!(content, MessageContent::Attachment(_)))
+    });
+    if !has_attachments {
+        return messages.to_vec();
+    }
+    let dir = session_id.and_then(|id| session_attachment_dir(id).ok());
+    resolve_attachments_in(dir.as_deref(), messages)
+}
+
+/// Replace the attachment references in `messages` with the attachments stored in `dir`
+pub fn resolve_attachments_in(dir: Option<&Path>, messages: &[Message]) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut message = message.clone();
+            for content in message.content.iter_mut() {
+                if let MessageContent::Attachment(reference) = content {
+                    let resolved = match dir {
+                        Some(dir) => resolve(dir, reference).unwrap_or_else(|e| {
+                            tracing::warn!("Failed to resolve attachment {}: {}", reference.id, e);
+                            missing_note(reference)
+                        }),
+                        None => missing_note(reference),
+                    };
+                    *content = resolved;
+                }
+            }
+            message
+        })
+        .collect()
+}
+
+fn resolve(dir: &Path, reference: &AttachmentReference) -> Result<MessageContent, AttachmentError> {
+    let info = read_attachment_info(dir, &reference.id)?;
+    if info.is_image() {
+        let bytes = fs::read(dir.join(&info.id)).map_err(anyhow::Error::from)?;
+        return Ok(MessageContent::image(BASE64.encode(bytes), info.mime_type));
+    }
+
+    let text_file = if info.mime_type == "application/pdf" {
+        dir.join(format!("{}.txt", info.id))
+    } else {
+        dir.join(&info.id)
+    };
+    let bytes = fs::read(text_file).map_err(anyhow::Error::from)?;
+    Ok(MessageContent::text(format!(
+        "[Attachment: {} ({})]\n{}",
+        info.name,
+        info.mime_type,
+        String::from_utf8_lossy(&bytes)
+    )))
+}
+
+fn missing_note(reference: &AttachmentReference) -> MessageContent {
+    MessageContent::text(format!(
+        "[Attachment {} is no longer available]",
+        reference.name.as_deref().unwrap_or(&reference.id)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::DynamicImage::new_rgb8(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_image_attachment_resolves_to_image_content() {
+        let dir = tempdir().unwrap();
+        let bytes = png(3, 2);
+
+        let info = save_attachment_in(
+            dir.path(),
+            &AttachmentLimits::default(),
+            Some("screenshot.png"),
+            Some("application/octet-stream"),
+            &bytes,
+        )
+        .unwrap();
+        assert_eq!(info.name, "screenshot.png");
+        assert_eq!(info.mime_type, "image/png");
+        assert_eq!((info.width, info.height), (Some(3), Some(2)));
+        assert_eq!(read_attachment_info(dir.path(), &info.id).unwrap(), info);
+
+        let messages = vec![Message::user()
+            .with_text("What is in this?")
+            .with_attachment(info.id, None)];
+        let resolved = resolve_attachments_in(Some(dir.path()), &messages);
+
+        let MessageContent::Image(image) = &resolved[0].content[1] else {
+            panic!("expected an image, got {:?}", resolved[0].content[1]);
+        };
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!(BASE64.decode(&image.data).unwrap(), bytes);
+        assert!(matches!(
+            messages[0].content[1],
+            MessageContent::Attachment(_)
+        ));
+    }
+
+    #[test]
+    fn test_text_attachment_resolves_to_text() {
+        let dir = tempdir().unwrap();
+        let info = save_attachment_in(
+            dir.path(),
+            &AttachmentLimits::default(),
+            Some("notes.md"),
+            None,
+            b"# Notes\nShip it",
+        )
+        .unwrap();
+        assert_eq!(info.mime_type, "text/markdown");
+        assert_eq!(info.text_length, Some(15));
+
+        let messages = vec![Message::user().with_attachment(info.id, Some("notes.md".into()))];
+        let resolved = resolve_attachments_in(Some(dir.path()), &messages);
+        assert_eq!(
+            resolved[0].as_concat_text(),
+            "[Attachment: notes.md (text/markdown)]\n# Notes\nShip it"
+        );
+    }
+
+    #[test]
+    fn test_resolved_text_attachment_is_counted_by_its_contents() {
+        let dir = tempdir().unwrap();
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(500);
+        let info = save_attachment_in(
+            dir.path(),
+            &AttachmentLimits::default(),
+            Some("notes.txt"),
+            None,
+            text.as_bytes(),
+        )
+        .unwrap();
+
+        let messages = vec![Message::user().with_attachment(info.id, None)];
+        let unresolved = crate::tokenizer::count_messages(&messages, "gpt-4o");
+        let resolved = crate::tokenizer::count_messages(
+            &resolve_attachments_in(Some(dir.path()), &messages),
+            "gpt-4o",
+        );
+        assert!(resolved > unresolved + 4000, "{} tokens", resolved);
+    }
+
+    #[test]
+    fn test_limits_are_enforced() {
+        let dir = tempdir().unwrap();
+        let limits = AttachmentLimits {
+            max_bytes: 10,
+            allowed_types: vec!["text/*".to_string()],
+        };
+
+        assert!(matches!(
+            save_attachment_in(dir.path(), &limits, Some("big.txt"), None, &[b'a'; 11]),
+            Err(AttachmentError::TooLarge {
+                size: 11,
+                limit: 10
+            })
+        ));
+        assert!(matches!(
+            save_attachment_in(dir.path(), &limits, Some("a.png"), None, &png(1, 1)),
+            Err(AttachmentError::UnsupportedType(mime)) if mime == "image/png"
+        ));
+        assert!(save_attachment_in(dir.path(), &limits, Some("ok.txt"), None, b"ok").is_ok());
+        assert!(!AttachmentLimits::default().allows("application/zip"));
+    }
+
+    #[test]
+    fn test_missing_attachment_becomes_note() {
+        let dir = tempdir().unwrap();
+        let messages = vec![Message::user().with_attachment("gone", Some("report.pdf".into()))];
+
+        let resolved = resolve_attachments_in(Some(dir.path()), &messages);
+        assert_eq!(
+            resolved[0].as_concat_text(),
+            "[Attachment report.pdf is no longer available]"
+        );
+        assert!(matches!(
+            read_attachment_info(dir.path(), "../escape"),
+            Err(AttachmentError::InvalidId(_))
+        ));
+    }
+}
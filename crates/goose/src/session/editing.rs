@@ -0,0 +1,208 @@
+//! Editing and deleting individual messages in a saved session, keeping the history valid to
+//! send back to a provider.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use rmcp::model::Role;
+use thiserror::Error;
+
+use crate::message::{Message, MessageContent};
+use crate::session::storage::{
+    get_path, read_messages, read_metadata, save_messages_with_metadata, Identifier,
+};
+
+#[derive(Error, Debug)]
+pub enum MessageEditError {
+    #[error("Session has no message at index {0}")]
+    NoSuchMessage(usize),
+    #[error("Only user messages with text can be edited")]
+    NotEditable,
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
+
+/// Replace the text of the user message at `index`, keeping any images or tool results it
+/// carries. With `truncate_after`, every later message is dropped so the conversation can be
+/// re-run from the edit. Returns the updated history.
+pub fn edit_message_text(
+    session_file: &Path,
+    index: usize,
+    text: &str,
+    truncate_after: bool,
+) -> Result<Vec<Message>, MessageEditError> {
+    rewrite(session_file, |messages| {
+        let message = messages
+            .get_mut(index)
+            .ok_or(MessageEditError::NoSuchMessage(index))?;
+        if message.role != Role::User {
+            return Err(MessageEditError::NotEditable);
+        }
+        let first_text = message
+            .content
+            .iter()
+            .position(|content| matches!(content, MessageContent::Text(_)))
+            .ok_or(MessageEditError::NotEditable)?;
+
+        message.content[first_text] = MessageContent::text(text);
+        let mut position = 0;
+        message.content.retain(|content| {
+            let keep = position <= first_text || !matches!(content, MessageContent::Text(_));
+            position += 1;
+            keep
+        });
+
+        if truncate_after {
+            messages.truncate(index + 1);
+        }
+        Ok(())
+    })
+}
+
+/// Delete the message at `index`, along with the other half of any tool calls in it: the
+/// results of the tool requests it made, or the requests its tool results answered. With
+/// `truncate_after`, every later message is dropped too. Returns the updated history.
+pub fn delete_message(
+    session_file: &Path,
+    index: usize,
+    truncate_after: bool,
+) -> Result<Vec<Message>, MessageEditError> {
+    rewrite(session_file, |messages| {
+        if index >= messages.len() {
+            return Err(MessageEditError::NoSuchMessage(index));
+        }
+        let removed = messages.remove(index);
+        if truncate_after {
+            messages.truncate(index);
+        }
+
+        let tool_ids: HashSet<&str> = removed.content.iter().filter_map(tool_call_id).collect();
+        if !tool_ids.is_empty() {
+            for message in messages.iter_mut() {
+                message.content.retain(|content| {
+                    tool_call_id(content).is_none_or(|id| !tool_ids.contains(id))
+                });
+            }
+            messages.retain(|message| !message.content.is_empty());
+        }
+        Ok(())
+    })
+}
+
+/// The tool call a piece of content belongs to, if any
+fn tool_call_id(content: &MessageContent) -> Option<&str> {
+    match content {
+        MessageContent::ToolRequest(request) => Some(&request.id),
+        MessageContent::ToolResponse(response) => Some(&response.id),
+        MessageContent::FrontendToolRequest(request) => Some(&request.id),
+        MessageContent::ToolConfirmationRequest(request) => Some(&request.id),
+        _ => None,
+    }
+}
+
+/// Apply `change` to the session's messages and save them back, replacing the file atomically
+fn rewrite(
+    session_file: &Path,
+    change: impl FnOnce(&mut Vec<Message>) -> Result<(), MessageEditError>,
+) -> Result<Vec<Message>, MessageEditError> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    let mut metadata = read_metadata(&secure_path)?;
+    let mut messages = read_messages(&secure_path)?;
+
+    change(&mut messages)?;
+
+    metadata.message_count = messages.len();
+    save_messages_with_metadata(&secure_path, &metadata, &messages)?;
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::storage::SessionMetadata;
+    use mcp_core::tool::ToolCall;
+    use rmcp::model::Content;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn save(path: &Path, messages: &[Message]) {
+        save_messages_with_metadata(path, &SessionMetadata::default(), messages).unwrap();
+    }
+
+    fn tool_exchange() -> Vec<Message> {
+        vec![
+            Message::user().with_text("List the files"),
+            Message::assistant()
+                .with_text("Listing them")
+                .with_tool_request("call_1", Ok(ToolCall::new("shell", json!({"cmd": "ls"})))),
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("a.txt")])),
+            Message::assistant().with_text("There is one file"),
+        ]
+    }
+
+    #[test]
+    fn test_edit_message_text() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("edit.jsonl");
+        save(&path, &tool_exchange());
+
+        let messages = edit_message_text(&path, 0, "List the hidden files", false).unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].as_concat_text(), "List the hidden files");
+        assert_eq!(read_messages(&path).unwrap(), messages);
+        assert_eq!(read_metadata(&path).unwrap().message_count, 4);
+
+        let messages = edit_message_text(&path, 0, "List nothing", true).unwrap();
+        assert_eq!(messages.len(), 1);
+
+        assert!(matches!(
+            edit_message_text(&path, 3, "Out of range", false),
+            Err(MessageEditError::NoSuchMessage(3))
+        ));
+    }
+
+    #[test]
+    fn test_only_user_text_is_editable() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("not_editable.jsonl");
+        let original = tool_exchange();
+        save(&path, &original);
+
+        assert!(matches!(
+            edit_message_text(&path, 1, "Assistant text", false),
+            Err(MessageEditError::NotEditable)
+        ));
+        assert!(matches!(
+            edit_message_text(&path, 2, "Tool result", false),
+            Err(MessageEditError::NotEditable)
+        ));
+        assert_eq!(read_messages(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_delete_tool_request_removes_its_result() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("delete.jsonl");
+        save(&path, &tool_exchange());
+
+        let messages = delete_message(&path, 1, false).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].as_concat_text(), "List the files");
+        assert_eq!(messages[1].as_concat_text(), "There is one file");
+
+        let messages = delete_message(&path, 0, true).unwrap();
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn test_delete_tool_result_removes_its_request() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("delete_result.jsonl");
+        save(&path, &tool_exchange());
+
+        let messages = delete_message(&path, 2, false).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(!messages[1].is_tool_call());
+        assert_eq!(messages[1].as_concat_text(), "Listing them");
+    }
+}
@@ -0,0 +1,368 @@
+//! Optional encryption-at-rest for session files.
+//!
+//! When `GOOSE_SESSION_ENCRYPTION` is enabled, the messages of a session are written as a single
+//! AES-256-GCM encrypted line that follows the metadata line. The metadata line itself stays in
+//! plaintext so sessions can still be listed without touching the key. Files without the
+//! encrypted marker are read as regular JSONL, so encrypted and plaintext sessions can live side
+//! by side in the same directory.
+//!
+//! The key is stored alongside provider secrets (system keyring, or the secrets file when the
+//! keyring is disabled) and is generated on first use.
+
+use crate::config::{Config, ConfigError};
+use crate::session::storage::list_sessions;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+const ENCRYPTION_ENABLED_KEY: &str = "GOOSE_SESSION_ENCRYPTION";
+const SESSION_KEY_SECRET: &str = "GOOSE_SESSION_ENCRYPTION_KEY";
+const PREVIOUS_SESSION_KEY_SECRET: &str = "GOOSE_SESSION_ENCRYPTION_KEY_PREVIOUS";
+
+/// Marker at the start of the line holding the encrypted messages
+pub const ENCRYPTED_LINE_PREFIX: &str = "GOOSE_ENCRYPTED_V1:";
+
+const NONCE_LEN: usize = 12;
+
+#[derive(Error, Debug)]
+pub enum SessionEncryptionError {
+    #[error("Session file is encrypted but no session encryption key was found in the goose secret storage (GOOSE_SESSION_ENCRYPTION_KEY)")]
+    MissingKey,
+    #[error("Session file could not be decrypted with the available session encryption key")]
+    DecryptionFailed,
+    #[error("Encrypted session data is malformed: {0}")]
+    Malformed(String),
+    #[error("Failed to encrypt session data")]
+    EncryptionFailed,
+    #[error("Failed to access session encryption key: {0}")]
+    KeyStorage(#[from] ConfigError),
+    #[error("Failed to rewrite session file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A 256-bit session encryption key
+#[derive(Clone)]
+pub struct SessionKey(Key<Aes256Gcm>);
+
+impl SessionKey {
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng))
+    }
+
+    fn from_base64(encoded: &str) -> Result<Self, SessionEncryptionError> {
+        let bytes = BASE64.decode(encoded.trim()).map_err(|e| {
+            SessionEncryptionError::Malformed(format!("invalid key encoding: {}", e))
+        })?;
+        if bytes.len() != 32 {
+            return Err(SessionEncryptionError::Malformed(format!(
+                "expected a 32 byte key, found {} bytes",
+                bytes.len()
+            )));
+        }
+        Ok(Self(Key::<Aes256Gcm>::clone_from_slice(&bytes)))
+    }
+
+    fn to_base64(&self) -> String {
+        BASE64.encode(self.0.as_slice())
+    }
+}
+
+/// Whether new session writes should be encrypted
+pub fn is_enabled() -> bool {
+    Config::global()
+        .get_param::<bool>(ENCRYPTION_ENABLED_KEY)
+        .unwrap_or(false)
+}
+
+fn load_key(secret_name: &str) -> Result<Option<SessionKey>, SessionEncryptionError> {
+    match Config::global().get_secret::<String>(secret_name) {
+        Ok(encoded) => SessionKey::from_base64(&encoded).map(Some),
+        Err(ConfigError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn store_key(secret_name: &str, key: &SessionKey) -> Result<(), SessionEncryptionError> {
+    Config::global().set_secret(secret_name, serde_json::Value::String(key.to_base64()))?;
+    Ok(())
+}
+
+/// Load the session key, creating and storing a new one if none exists yet
+pub fn get_or_create_key() -> Result<SessionKey, SessionEncryptionError> {
+    if let Some(key) = load_key(SESSION_KEY_SECRET)? {
+        return Ok(key);
+    }
+    let key = SessionKey::generate();
+    store_key(SESSION_KEY_SECRET, &key)?;
+    Ok(key)
+}
+
+/// Encrypt the serialized messages of a session into a single marker-prefixed line
+pub fn encrypt_body(key: &SessionKey, plaintext: &[u8]) -> Result<String, SessionEncryptionError> {
+    let cipher = Aes256Gcm::new(&key.0);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| SessionEncryptionError::EncryptionFailed)?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(format!(
+        "{}{}",
+        ENCRYPTED_LINE_PREFIX,
+        BASE64.encode(payload)
+    ))
+}
+
+/// Decrypt a marker-prefixed line produced by [`encrypt_body`]
+pub fn decrypt_body(key: &SessionKey, line: &str) -> Result<Vec<u8>, SessionEncryptionError> {
+    let encoded = line
+        .trim_end()
+        .strip_prefix(ENCRYPTED_LINE_PREFIX)
+        .ok_or_else(|| SessionEncryptionError::Malformed("missing encryption marker".into()))?;
+    let payload = BASE64
+        .decode(encoded)
+        .map_err(|e| SessionEncryptionError::Malformed(e.to_string()))?;
+    if payload.len() < NONCE_LEN {
+        return Err(SessionEncryptionError::Malformed(
+            "payload too short".into(),
+        ));
+    }
+
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    Aes256Gcm::new(&key.0)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| SessionEncryptionError::DecryptionFailed)
+}
+
+/// Split raw session file contents into the metadata line and the encrypted line, if encrypted
+fn split_encrypted(contents: &[u8]) -> Option<(&[u8], &str)> {
+    let newline = contents.iter().position(|b| *b == b'\n')?;
+    let (metadata, rest) = contents.split_at(newline + 1);
+    let rest = std::str::from_utf8(rest).ok()?;
+    rest.starts_with(ENCRYPTED_LINE_PREFIX)
+        .then_some((metadata, rest))
+}
+
+/// Whether raw session file contents carry an encrypted message body
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    split_encrypted(contents).is_some()
+}
+
+/// Turn raw session file contents into plaintext JSONL using the given keys
+///
+/// Plaintext contents are returned unchanged. Each key is tried in order.
+fn decrypt_contents_with(
+    contents: Vec<u8>,
+    keys: &[SessionKey],
+) -> Result<Vec<u8>, SessionEncryptionError> {
+    let Some((metadata, encrypted)) = split_encrypted(&contents) else {
+        return Ok(contents);
+    };
+    if keys.is_empty() {
+        return Err(SessionEncryptionError::MissingKey);
+    }
+
+    for key in keys {
+        match decrypt_body(key, encrypted) {
+            Ok(body) => {
+                let mut plaintext = metadata.to_vec();
+                plaintext.extend_from_slice(&body);
+                return Ok(plaintext);
+            }
+            Err(SessionEncryptionError::DecryptionFailed) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(SessionEncryptionError::DecryptionFailed)
+}
+
+/// Turn raw session file contents into plaintext JSONL, decrypting if necessary
+pub fn decrypt_contents(contents: Vec<u8>) -> Result<Vec<u8>, SessionEncryptionError> {
+    if !is_encrypted(&contents) {
+        return Ok(contents);
+    }
+    // The previous key is only present while a rotation is in progress
+    let keys: Vec<SessionKey> = [
+        load_key(SESSION_KEY_SECRET)?,
+        load_key(PREVIOUS_SESSION_KEY_SECRET)?,
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    decrypt_contents_with(contents, &keys)
+}
+
+fn reencrypt_file(
+    path: &Path,
+    old_keys: &[SessionKey],
+    new_key: &SessionKey,
+) -> Result<bool, SessionEncryptionError> {
+    let contents = fs::read(path)?;
+    let Some((metadata, _)) = split_encrypted(&contents) else {
+        return Ok(false);
+    };
+    let metadata = metadata.to_vec();
+    let plaintext = decrypt_contents_with(contents, old_keys)?;
+    let body = &plaintext[metadata.len()..];
+
+    let mut rewritten = metadata;
+    rewritten.extend_from_slice(encrypt_body(new_key, body)?.as_bytes());
+    rewritten.push(b'\n');
+
+    let temp_file = path.with_extension("tmp");
+    fs::write(&temp_file, rewritten)?;
+    fs::rename(&temp_file, path)?;
+    Ok(true)
+}
+
+/// The key an interrupted rotation generated, when there is one to finish: a previous key is
+/// only stored while a rotation is in progress
+fn interrupted_rotation_key(
+    previous: Option<&SessionKey>,
+    current: Option<&SessionKey>,
+) -> Option<SessionKey> {
+    previous.and(current).cloned()
+}
+
+/// Generate a new session key and re-encrypt every encrypted session with it
+///
+/// The old key is kept as a fallback until all sessions have been rewritten, so an interrupted
+/// rotation can simply be run again: it then finishes with the key it generated rather than
+/// generating another, which would drop a key sessions may still need. Returns the number of
+/// sessions that were re-encrypted.
+pub fn rotate_session_key() -> anyhow::Result<usize> {
+    let previous = load_key(PREVIOUS_SESSION_KEY_SECRET)?;
+    let current = load_key(SESSION_KEY_SECRET)?;
+
+    let new_key = match interrupted_rotation_key(previous.as_ref(), current.as_ref()) {
+        Some(key) => key,
+        None => {
+            let key = SessionKey::generate();
+            if let Some(current) = &current {
+                store_key(PREVIOUS_SESSION_KEY_SECRET, current)?;
+            }
+            store_key(SESSION_KEY_SECRET, &key)?;
+            key
+        }
+    };
+
+    let keys: Vec<SessionKey> = [previous, current, Some(new_key.clone())]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut rotated = 0;
+    for (name, path) in list_sessions()? {
+        match reencrypt_file(&path, &keys, &new_key) {
+            Ok(true) => rotated += 1,
+            Ok(false) => {}
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "Failed to re-encrypt session {}: {}. The previous key has been kept; run the rotation again to finish.",
+                    name,
+                    e
+                ))
+            }
+        }
+    }
+
+    Config::global().delete_secret(PREVIOUS_SESSION_KEY_SECRET)?;
+    Ok(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENTS: &str =
+        "{\"description\":\"test\"}\n{\"role\":\"user\"}\n{\"role\":\"assistant\"}\n";
+
+    fn encrypt_contents(key: &SessionKey, contents: &str) -> Vec<u8> {
+        let (metadata, body) = contents.split_at(contents.find('\n').unwrap() + 1);
+        format!(
+            "{}{}\n",
+            metadata,
+            encrypt_body(key, body.as_bytes()).unwrap()
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let key = SessionKey::generate();
+        let encrypted = encrypt_contents(&key, CONTENTS);
+
+        assert!(is_encrypted(&encrypted));
+        assert!(encrypted.starts_with(b"{\"description\":\"test\"}\n"));
+
+        let decrypted = decrypt_contents_with(encrypted, &[key]).unwrap();
+        assert_eq!(String::from_utf8(decrypted).unwrap(), CONTENTS);
+    }
+
+    #[test]
+    fn test_plaintext_passes_through() {
+        let contents = CONTENTS.as_bytes().to_vec();
+        assert!(!is_encrypted(&contents));
+        assert_eq!(
+            decrypt_contents_with(contents.clone(), &[]).unwrap(),
+            contents
+        );
+    }
+
+    #[test]
+    fn test_missing_key_is_a_clear_error() {
+        let encrypted = encrypt_contents(&SessionKey::generate(), CONTENTS);
+        let err = decrypt_contents_with(encrypted, &[]).unwrap_err();
+        assert!(matches!(err, SessionEncryptionError::MissingKey));
+    }
+
+    #[test]
+    fn test_wrong_key_fails_cleanly() {
+        let encrypted = encrypt_contents(&SessionKey::generate(), CONTENTS);
+        let err = decrypt_contents_with(encrypted, &[SessionKey::generate()]).unwrap_err();
+        assert!(matches!(err, SessionEncryptionError::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_fallback_key_and_reencrypt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let old_key = SessionKey::generate();
+        let new_key = SessionKey::generate();
+        fs::write(&path, encrypt_contents(&old_key, CONTENTS)).unwrap();
+
+        let keys = [new_key.clone(), old_key];
+        assert!(reencrypt_file(&path, &keys, &new_key).unwrap());
+
+        let rewritten = fs::read(&path).unwrap();
+        let decrypted = decrypt_contents_with(rewritten, &[new_key]).unwrap();
+        assert_eq!(String::from_utf8(decrypted).unwrap(), CONTENTS);
+    }
+
+    #[test]
+    fn test_rerun_rotation_keeps_the_older_key() {
+        let first = SessionKey::generate();
+        let second = SessionKey::generate();
+
+        // A rotation from a settled key generates a new one
+        assert!(interrupted_rotation_key(None, Some(&first)).is_none());
+        assert!(interrupted_rotation_key(None, None).is_none());
+        // Rerunning one that was interrupted finishes it with the key it generated, so the
+        // first key stays stored as the previous one until every session is rewritten
+        let resumed = interrupted_rotation_key(Some(&first), Some(&second)).unwrap();
+        assert_eq!(resumed.0, second.0);
+    }
+
+    #[test]
+    fn test_key_encoding_round_trip() {
+        let key = SessionKey::generate();
+        let decoded = SessionKey::from_base64(&key.to_base64()).unwrap();
+        assert_eq!(decoded.0, key.0);
+        assert!(SessionKey::from_base64("c2hvcnQ=").is_err());
+    }
+}
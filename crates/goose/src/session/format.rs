@@ -0,0 +1,266 @@
+//! The session file format shared by the CLI and the server.
+//!
+//! A session file is JSONL: a [`SessionMetadata`] header followed by one message per line, or
+//! by a single line of encrypted messages. The header records the `format_version` the file was
+//! written in, and files from before it was recorded are version 0. The oldest files have no
+//! header at all and start straight with a message.
+//!
+//! The CLI and the server both go through [`load_session`] to continue a session, so either can
+//! pick up one the other started. It refuses files written by a newer goose, since rewriting them
+//! would drop what this build doesn't understand, and brings older files up to date with
+//! [`normalize`]. Nothing is written back until the session is next saved.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+use mcp_core::ToolError;
+use rmcp::model::Role;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::message::{Message, MessageContent};
+use crate::session::storage::{
+    get_home_dir, get_path, read_messages, read_metadata, Identifier, SessionMetadata,
+    SESSION_FORMAT_VERSION,
+};
+
+/// Result given to tool calls that were cut off before they finished
+const INTERRUPTED_TOOL_CALL: &str = "The tool call was interrupted before it finished";
+
+#[derive(Error, Debug)]
+pub enum SessionFormatError {
+    #[error(
+        "Session was written in format version {found} by a newer goose, and this one only \
+         supports up to version {supported}"
+    )]
+    NewerVersion { found: u32, supported: u32 },
+    #[error("Session metadata could not be read: {0}")]
+    UnreadableMetadata(String),
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
+
+/// Fail if `metadata` was written in a format newer than this build can rewrite
+pub fn check_version(metadata: &SessionMetadata) -> Result<(), SessionFormatError> {
+    check_version_number(metadata.format_version)
+}
+
+fn check_version_number(found: u32) -> Result<(), SessionFormatError> {
+    if found > SESSION_FORMAT_VERSION {
+        return Err(SessionFormatError::NewerVersion {
+            found,
+            supported: SESSION_FORMAT_VERSION,
+        });
+    }
+    Ok(())
+}
+
+/// The first line of a session file, or `None` for a new or empty session
+fn read_header(session_file: &Path) -> Result<Option<Value>, SessionFormatError> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    let file = match fs::File::open(&secure_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow::Error::from(e).into()),
+    };
+    let mut line = String::new();
+    io::BufReader::new(file)
+        .read_line(&mut line)
+        .map_err(anyhow::Error::from)?;
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(|e| SessionFormatError::UnreadableMetadata(e.to_string()))
+}
+
+/// Check that a session file can be continued and rewritten by this build. A file that doesn't
+/// exist yet is a new session and always can.
+pub fn check_resumable(session_file: &Path) -> Result<(), SessionFormatError> {
+    let Some(header) = read_header(session_file)? else {
+        return Ok(());
+    };
+    let version = header
+        .get("format_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    check_version_number(u32::try_from(version).unwrap_or(u32::MAX))?;
+
+    match serde_json::from_value::<SessionMetadata>(header.clone()) {
+        Ok(_) => Ok(()),
+        Err(_) if serde_json::from_value::<Message>(header).is_ok() => Ok(()),
+        Err(e) => Err(SessionFormatError::UnreadableMetadata(e.to_string())),
+    }
+}
+
+/// Read a session to continue it, normalized to the current format
+pub fn load_session(
+    session_file: &Path,
+) -> Result<(SessionMetadata, Vec<Message>), SessionFormatError> {
+    check_resumable(session_file)?;
+    let has_working_dir = read_header(session_file)?
+        .and_then(|header| header.get("working_dir").cloned())
+        .is_some_and(|dir| dir.is_string());
+
+    let mut metadata = read_metadata(session_file)?;
+    let mut messages = read_messages(session_file)?;
+    // Without a recorded working directory the metadata falls back to wherever this process
+    // happens to run, which differs between the CLI and the server
+    if !has_working_dir && session_file.exists() {
+        metadata.working_dir = get_home_dir();
+    }
+    normalize(&mut metadata, &mut messages);
+    Ok((metadata, messages))
+}
+
+/// Bring a session up to the current format:
+/// - messages left without content, as an interrupted reply can leave them, are dropped
+/// - tool requests that never got a result, because the CLI or server stopped mid-call, are
+///   answered with an error so providers accept the history
+/// - the message count is recounted
+pub fn normalize(metadata: &mut SessionMetadata, messages: &mut Vec<Message>) {
+    messages.retain(|message| !message.content.is_empty());
+    answer_interrupted_tool_calls(messages);
+    metadata.message_count = messages.len();
+    metadata.format_version = SESSION_FORMAT_VERSION;
+}
+
+/// Give every unanswered tool request an error result in the user message right after it
+fn answer_interrupted_tool_calls(messages: &mut Vec<Message>) {
+    let answered: HashSet<String> = messages
+        .iter()
+        .flat_map(|message| message.content.iter())
+        .filter_map(|content| content.as_tool_response())
+        .map(|response| response.id.clone())
+        .collect();
+
+    let mut index = 0;
+    while index < messages.len() {
+        let unanswered: Vec<MessageContent> = if messages[index].role == Role::Assistant {
+            messages[index]
+                .content
+                .iter()
+                .filter_map(|content| content.as_tool_request())
+                .filter(|request| !answered.contains(&request.id))
+                .map(|request| {
+                    MessageContent::tool_response(
+                        request.id.clone(),
+                        Err(ToolError::ExecutionError(INTERRUPTED_TOOL_CALL.to_string())),
+                    )
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        if !unanswered.is_empty() {
+            match messages.get_mut(index + 1) {
+                Some(next) if next.role == Role::User => {
+                    next.content.splice(0..0, unanswered);
+                }
+                _ => {
+                    let mut results = Message::user();
+                    results.content = unanswered;
+                    messages.insert(index + 1, results);
+                }
+            }
+        }
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::storage::save_messages_with_metadata;
+    use mcp_core::tool::ToolCall;
+    use rmcp::model::Content;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn tool_request(id: &str) -> Message {
+        Message::assistant()
+            .with_text("Running it")
+            .with_tool_request(id, Ok(ToolCall::new("shell", json!({"command": "ls"}))))
+    }
+
+    #[test]
+    fn test_legacy_session_is_normalized() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("legacy.jsonl");
+        let lines = [
+            json!({"description": "Old session", "message_count": 7}).to_string(),
+            serde_json::to_string(&Message::user().with_text("List the files")).unwrap(),
+            serde_json::to_string(&tool_request("call_1")).unwrap(),
+            serde_json::to_string(&Message::assistant()).unwrap(),
+            serde_json::to_string(&Message::user().with_text("Never mind")).unwrap(),
+        ];
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let (metadata, messages) = load_session(&path).unwrap();
+        assert_eq!(metadata.format_version, SESSION_FORMAT_VERSION);
+        assert_eq!(metadata.description, "Old session");
+        assert_eq!(metadata.working_dir, get_home_dir());
+        assert_eq!(metadata.message_count, 3);
+
+        // The interrupted call is answered at the start of the next user message
+        assert_eq!(messages.len(), 3);
+        let response = messages[2].content[0].as_tool_response().unwrap();
+        assert_eq!(response.id, "call_1");
+        assert!(response.tool_result.is_err());
+        assert_eq!(messages[2].content[1].as_text(), Some("Never mind"));
+    }
+
+    #[test]
+    fn test_trailing_tool_request_gets_a_result_message() {
+        let mut messages = vec![
+            Message::user().with_text("List the files"),
+            tool_request("call_1"),
+            Message::user().with_tool_response("call_1", Ok(vec![Content::text("a.txt")])),
+            tool_request("call_2"),
+        ];
+        let mut metadata = SessionMetadata::default();
+
+        normalize(&mut metadata, &mut messages);
+
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[4].role, Role::User);
+        assert_eq!(
+            messages[4].content[0].as_tool_response().unwrap().id,
+            "call_2"
+        );
+        assert_eq!(metadata.message_count, 5);
+
+        // Normalizing again changes nothing
+        let normalized = messages.clone();
+        normalize(&mut metadata, &mut messages);
+        assert_eq!(messages, normalized);
+    }
+
+    #[test]
+    fn test_newer_and_unreadable_sessions_are_refused() {
+        let dir = tempdir().unwrap();
+        let newer = dir.path().join("newer.jsonl");
+        let metadata = SessionMetadata {
+            format_version: SESSION_FORMAT_VERSION + 1,
+            ..Default::default()
+        };
+        save_messages_with_metadata(&newer, &metadata, &[Message::user().with_text("hi")]).unwrap();
+        assert!(matches!(
+            check_resumable(&newer),
+            Err(SessionFormatError::NewerVersion { found, .. }) if found == SESSION_FORMAT_VERSION + 1
+        ));
+
+        let unreadable = dir.path().join("unreadable.jsonl");
+        fs::write(&unreadable, "{\"description\": 3}\n").unwrap();
+        assert!(matches!(
+            load_session(&unreadable),
+            Err(SessionFormatError::UnreadableMetadata(_))
+        ));
+
+        assert!(check_resumable(&dir.path().join("new.jsonl")).is_ok());
+    }
+}
@@ -0,0 +1,292 @@
+//! Images returned by tools, kept out of the session file and the reply stream.
+//!
+//! Screenshots and charts come back from tools as base64 inside the tool result. An image with
+//! more than `GOOSE_IMAGE_ARTIFACT_BYTES` of encoded data (32 KiB by default) is written to the
+//! session's artifact directory and replaced in the result by an [`ImageReference`], embedded
+//! as a resource of type [`IMAGE_REFERENCE_MIME_TYPE`]. Clients fetch the image itself from
+//! `GET /sessions/{session_id}/artifacts/{name}`, and [`resolve_image_references`] puts it back
+//! inline just before the conversation is sent to the provider.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rmcp::model::{Content, RawContent, ResourceContents};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::message::{Message, MessageContent};
+use crate::session::artifacts::{artifact_dir, extension_for_mime, validate_id};
+use crate::session::storage::{ensure_session_dir, Identifier};
+
+const IMAGE_ARTIFACT_BYTES_KEY: &str = "GOOSE_IMAGE_ARTIFACT_BYTES";
+const DEFAULT_IMAGE_ARTIFACT_BYTES: usize = 32 * 1024;
+/// Mime type of the resource that stands in for an image saved as an artifact
+pub const IMAGE_REFERENCE_MIME_TYPE: &str = "application/vnd.goose.image-reference+json";
+
+/// An image from a tool result that was saved as a session artifact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageReference {
+    /// Server path the image is served from
+    pub uri: String,
+    /// Artifact file name
+    pub name: String,
+    pub mime_type: String,
+    /// Size in bytes
+    pub size: usize,
+    /// Width in pixels, when the image could be decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Height in pixels, when the image could be decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl ImageReference {
+    fn into_content(self) -> Content {
+        Content::resource(ResourceContents::TextResourceContents {
+            uri: self.uri.clone(),
+            mime_type: Some(IMAGE_REFERENCE_MIME_TYPE.to_string()),
+            text: serde_json::to_string(&self).unwrap_or_default(),
+        })
+    }
+
+    /// The reference carried by `content`, if it is one
+    pub fn from_content(content: &Content) -> Option<Self> {
+        let RawContent::Resource(resource) = &**content else {
+            return None;
+        };
+        match &resource.resource {
+            ResourceContents::TextResourceContents {
+                mime_type: Some(mime_type),
+                text,
+                ..
+            } if mime_type == IMAGE_REFERENCE_MIME_TYPE => serde_json::from_str(text).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Moves large images out of tool results and into a session's artifact directory
+#[derive(Debug, Clone)]
+pub struct ImageArtifacts {
+    max_inline_bytes: usize,
+    session_id: String,
+    dir: PathBuf,
+}
+
+impl ImageArtifacts {
+    /// Save images with more than `max_inline_bytes` of encoded data to `dir`
+    pub fn new(max_inline_bytes: usize, session_id: String, dir: PathBuf) -> Self {
+        Self {
+            max_inline_bytes,
+            session_id,
+            dir,
+        }
+    }
+
+    /// Build from config, or `None` when there is no session to save images to
+    pub fn for_session(session: Option<&Identifier>) -> Option<Self> {
+        let session_id = session.and_then(Identifier::session_id)?;
+        validate_id(&session_id).ok()?;
+        let dir = artifact_dir(&ensure_session_dir().ok()?, &session_id);
+        let max_inline_bytes = Config::global()
+            .get_param::<usize>(IMAGE_ARTIFACT_BYTES_KEY)
+            .unwrap_or(DEFAULT_IMAGE_ARTIFACT_BYTES);
+        Some(Self::new(max_inline_bytes, session_id, dir))
+    }
+
+    /// Replace the large images in a tool result with references to saved artifacts. An image
+    /// that can't be saved stays inline.
+    pub fn persist(&self, contents: Vec<Content>) -> Vec<Content> {
+        contents
+            .into_iter()
+            .map(|content| {
+                let RawContent::Image(image) = &*content else {
+                    return content;
+                };
+                if image.data.len() <= self.max_inline_bytes {
+                    return content;
+                }
+                match self.save(&image.data, &image.mime_type) {
+                    Ok(reference) => reference.into_content(),
+                    Err(e) => {
+                        tracing::warn!("Failed to save tool image as an artifact: {}", e);
+                        content
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn save(&self, data: &str, mime_type: &str) -> Result<ImageReference> {
+        let bytes = BASE64.decode(data)?;
+        let dimensions = image::io::Reader::new(Cursor::new(&bytes))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok());
+
+        let name = format!("{}.{}", Uuid::new_v4(), extension_for_mime(mime_type));
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(&name), &bytes)?;
+
+        Ok(ImageReference {
+            uri: format!("/sessions/{}/artifacts/{}", self.session_id, name),
+            name,
+            mime_type: mime_type.to_string(),
+            size: bytes.len(),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+        })
+    }
+}
+
+/// Replace the image references in the tool results of `messages` with the images saved for
+/// `session_id`, ready to send to a provider
+pub fn resolve_image_references(session_id: Option<&str>, messages: &[Message]) -> Vec<Message> {
+    let has_references = messages
+        .iter()
+        .flat_map(|message| message.content.iter())
+        .filter_map(|content| content.as_tool_response())
+        .filter_map(|response| response.tool_result.as_ref().ok())
+        .flatten()
+        .any(|content| ImageReference::from_content(content).is_some());
+    if !has_references {
+        return messages.to_vec();
+    }
+    let dir = session_id
+        .filter(|id| validate_id(id).is_ok())
+        .and_then(|id| Some(artifact_dir(&ensure_session_dir().ok()?, id)));
+    resolve_image_references_in(dir.as_deref(), messages)
+}
+
+/// Replace the image references in `messages` with the images stored in `dir`
+pub fn resolve_image_references_in(dir: Option<&Path>, messages: &[Message]) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut message = message.clone();
+            for content in message.content.iter_mut() {
+                let MessageContent::ToolResponse(response) = content else {
+                    continue;
+                };
+                let Ok(contents) = response.tool_result.as_mut() else {
+                    continue;
+                };
+                for item in contents.iter_mut() {
+                    if let Some(reference) = ImageReference::from_content(item) {
+                        *item = match dir {
+                            Some(dir) => resolve(dir, &reference).unwrap_or_else(|e| {
+                                tracing::warn!("Failed to resolve image {}: {}", reference.name, e);
+                                missing_note(&reference)
+                            }),
+                            None => missing_note(&reference),
+                        };
+                    }
+                }
+            }
+            message
+        })
+        .collect()
+}
+
+fn resolve(dir: &Path, reference: &ImageReference) -> Result<Content> {
+    validate_id(&reference.name)?;
+    let bytes = fs::read(dir.join(&reference.name))?;
+    Ok(Content::image(BASE64.encode(bytes), &reference.mime_type))
+}
+
+fn missing_note(reference: &ImageReference) -> Content {
+    Content::text(format!("[Image {} is no longer available]", reference.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::DynamicImage::new_rgb8(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn tool_result(contents: Vec<Content>) -> Message {
+        Message::user().with_tool_response("call_1", Ok(contents))
+    }
+
+    fn result_contents(message: &Message) -> &Vec<Content> {
+        message.content[0]
+            .as_tool_response()
+            .unwrap()
+            .tool_result
+            .as_ref()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_only_images_over_the_threshold_are_saved() {
+        let dir = tempdir().unwrap();
+        let data = BASE64.encode(png(4, 3));
+        let artifacts = ImageArtifacts::new(data.len(), "session".to_string(), dir.path().into());
+        let image = Content::image(data.clone(), "image/png");
+
+        // At the threshold the image stays inline
+        let kept = artifacts.persist(vec![image.clone(), Content::text("done")]);
+        assert_eq!(kept, vec![image.clone(), Content::text("done")]);
+        assert!(fs::read_dir(dir.path()).unwrap().next().is_none());
+
+        // One byte over, it is replaced by a reference
+        let artifacts =
+            ImageArtifacts::new(data.len() - 1, "session".to_string(), dir.path().into());
+        let persisted = artifacts.persist(vec![image]);
+        let reference = ImageReference::from_content(&persisted[0]).unwrap();
+        assert_eq!(reference.mime_type, "image/png");
+        assert_eq!((reference.width, reference.height), (Some(4), Some(3)));
+        assert_eq!(
+            reference.uri,
+            format!("/sessions/session/artifacts/{}", reference.name)
+        );
+        assert!(reference.name.ends_with(".png"));
+        assert_eq!(
+            fs::read(dir.path().join(&reference.name)).unwrap(),
+            BASE64.decode(&data).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_references_resolve_back_to_the_image() {
+        let dir = tempdir().unwrap();
+        let data = BASE64.encode(png(8, 8));
+        let artifacts = ImageArtifacts::new(0, "session".to_string(), dir.path().into());
+        let original = tool_result(vec![
+            Content::text("Screenshot taken"),
+            Content::image(data.clone(), "image/png"),
+        ]);
+        let mut stored = original.clone();
+        stored.content[0] = MessageContent::tool_response(
+            "call_1",
+            Ok(artifacts.persist(result_contents(&original).clone())),
+        );
+        assert!(!serde_json::to_string(&stored).unwrap().contains(&data));
+
+        let resolved = resolve_image_references_in(Some(dir.path()), &[stored.clone()]);
+        assert_eq!(resolved, vec![original]);
+
+        // Once the artifact is gone the provider is told instead
+        let missing = resolve_image_references_in(None, &[stored]);
+        assert!(result_contents(&missing[0])[1]
+            .as_text()
+            .unwrap()
+            .text
+            .contains("is no longer available"));
+    }
+}
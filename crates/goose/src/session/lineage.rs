@@ -0,0 +1,172 @@
+//! Relationships between sessions. A session forked from another, or started by one of its
+//! sub-recipes, records that session as its parent. Sessions without a parent are roots, and so
+//! are sessions whose parent has since been deleted.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::session::info::SessionInfo;
+use crate::session::storage::SessionOrigin;
+
+/// A session in a tree of related sessions
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedSession {
+    pub id: String,
+    pub description: String,
+    pub origin: SessionOrigin,
+    /// Sessions started from this one. Always empty for ancestors.
+    pub children: Vec<RelatedSession>,
+}
+
+/// The sessions related to one session
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedSessions {
+    /// Ancestors of the session, from its root down to its parent
+    pub ancestors: Vec<RelatedSession>,
+    /// The session, with its descendants as children
+    pub session: RelatedSession,
+}
+
+/// Keep only the sessions that aren't the child of another listed session
+pub fn root_sessions(sessions: Vec<SessionInfo>) -> Vec<SessionInfo> {
+    let ids: HashSet<String> = sessions.iter().map(|s| s.id.clone()).collect();
+    sessions
+        .into_iter()
+        .filter(|s| {
+            s.metadata
+                .parent_session_id
+                .as_ref()
+                .is_none_or(|parent| !ids.contains(parent))
+        })
+        .collect()
+}
+
+/// The ancestors and descendants of session `id` among `sessions`, or `None` if it isn't listed.
+/// Children keep the order of `sessions`.
+pub fn related_sessions(sessions: &[SessionInfo], id: &str) -> Option<RelatedSessions> {
+    let by_id: HashMap<&str, &SessionInfo> = sessions.iter().map(|s| (s.id.as_str(), s)).collect();
+    let session = by_id.get(id)?;
+
+    let mut children: HashMap<&str, Vec<&SessionInfo>> = HashMap::new();
+    for s in sessions {
+        if let Some(parent) = s.metadata.parent_session_id.as_deref() {
+            children.entry(parent).or_default().push(s);
+        }
+    }
+
+    // A hand-edited file could make a cycle, so every session is visited at most once
+    let mut seen = HashSet::from([id]);
+    let mut ancestors = Vec::new();
+    let mut parent = session.metadata.parent_session_id.as_deref();
+    while let Some(parent_session) = parent.and_then(|p| by_id.get(p)) {
+        if !seen.insert(&parent_session.id) {
+            break;
+        }
+        ancestors.push(node(parent_session, Vec::new()));
+        parent = parent_session.metadata.parent_session_id.as_deref();
+    }
+    ancestors.reverse();
+
+    let session = descendants(session, &children, &mut seen);
+    Some(RelatedSessions { ancestors, session })
+}
+
+fn descendants<'a>(
+    session: &'a SessionInfo,
+    children: &HashMap<&str, Vec<&'a SessionInfo>>,
+    seen: &mut HashSet<&'a str>,
+) -> RelatedSession {
+    let mut nodes = Vec::new();
+    for child in children.get(session.id.as_str()).into_iter().flatten() {
+        if seen.insert(&child.id) {
+            nodes.push(descendants(child, children, seen));
+        }
+    }
+    node(session, nodes)
+}
+
+fn node(session: &SessionInfo, children: Vec<RelatedSession>) -> RelatedSession {
+    RelatedSession {
+        id: session.id.clone(),
+        description: session.metadata.description.clone(),
+        origin: session.metadata.origin,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionMetadata;
+
+    fn info(id: &str, parent: Option<&str>, origin: SessionOrigin) -> SessionInfo {
+        SessionInfo {
+            id: id.to_string(),
+            path: format!("{}.jsonl", id),
+            modified: "2025-01-01 00:00:00 UTC".to_string(),
+            metadata: SessionMetadata {
+                description: format!("Session {}", id),
+                parent_session_id: parent.map(String::from),
+                origin,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn ids(nodes: &[RelatedSession]) -> Vec<&str> {
+        nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_related_sessions_tree() {
+        let sessions = vec![
+            info("root", None, SessionOrigin::Manual),
+            info("fork", Some("root"), SessionOrigin::Fork),
+            info("sub_a", Some("fork"), SessionOrigin::SubRecipe),
+            info("sub_b", Some("fork"), SessionOrigin::SubRecipe),
+            info("nested", Some("sub_a"), SessionOrigin::SubRecipe),
+            info("other", None, SessionOrigin::Schedule),
+        ];
+
+        let related = related_sessions(&sessions, "fork").unwrap();
+        assert_eq!(ids(&related.ancestors), vec!["root"]);
+        assert_eq!(related.session.origin, SessionOrigin::Fork);
+        assert_eq!(ids(&related.session.children), vec!["sub_a", "sub_b"]);
+        assert_eq!(ids(&related.session.children[0].children), vec!["nested"]);
+
+        let related = related_sessions(&sessions, "nested").unwrap();
+        assert_eq!(ids(&related.ancestors), vec!["root", "fork", "sub_a"]);
+        assert!(related.session.children.is_empty());
+
+        assert!(related_sessions(&sessions, "missing").is_none());
+    }
+
+    #[test]
+    fn test_cycles_and_orphans() {
+        let sessions = vec![
+            info("a", Some("b"), SessionOrigin::Fork),
+            info("b", Some("a"), SessionOrigin::Fork),
+            info("orphan", Some("deleted"), SessionOrigin::Fork),
+            info("legacy", None, SessionOrigin::Manual),
+        ];
+
+        let related = related_sessions(&sessions, "a").unwrap();
+        assert_eq!(ids(&related.ancestors), vec!["b"]);
+        assert!(related.session.children.is_empty());
+
+        let roots: Vec<String> = root_sessions(sessions).into_iter().map(|s| s.id).collect();
+        assert_eq!(roots, vec!["orphan", "legacy"]);
+    }
+
+    #[test]
+    fn test_legacy_metadata_is_a_manual_root() {
+        let metadata: SessionMetadata =
+            serde_json::from_str(r#"{"description": "Old", "message_count": 2}"#).unwrap();
+        assert_eq!(metadata.parent_session_id, None);
+        assert_eq!(metadata.origin, SessionOrigin::Manual);
+    }
+}
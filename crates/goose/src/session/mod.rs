@@ -1,12 +1,35 @@
+pub mod artifacts;
+pub mod attachments;
+pub mod editing;
+pub mod encryption;
+pub mod format;
+pub mod images;
 pub mod info;
+pub mod lineage;
+pub mod redaction;
+pub mod replay;
+pub mod retention;
 pub mod storage;
+pub mod tool_usage;
 
 // Re-export common session types and functions
 pub use storage::{
     ensure_session_dir, generate_description, generate_description_with_schedule_id,
     generate_session_id, get_most_recent_session, get_path, list_sessions, persist_messages,
-    persist_messages_with_schedule_id, read_messages, read_metadata, update_metadata, Identifier,
-    SessionMetadata,
+    persist_messages_with_schedule_id, read_messages, read_metadata, record_model_change,
+    record_schema_validation, set_session_dir, update_metadata, Identifier, ModelChangeRecord,
+    SessionMetadata, SessionOrigin,
 };
 
+pub use artifacts::ToolResultLimiter;
+pub use attachments::{resolve_attachments, save_attachment, AttachmentError, AttachmentInfo};
+pub use editing::{delete_message, edit_message_text, MessageEditError};
+pub use encryption::rotate_session_key;
+pub use format::{check_resumable, load_session, SessionFormatError};
+pub use images::{resolve_image_references, ImageArtifacts, ImageReference};
 pub use info::{get_valid_sorted_sessions, SessionInfo};
+pub use lineage::{related_sessions, root_sessions, RelatedSession, RelatedSessions};
+pub use redaction::Redactor;
+pub use replay::{RecordedToolResults, ReplayRecord, TurnComparison, TurnStats};
+pub use retention::{cleanup, CleanupOptions, CleanupReport, RemovedSession, RetentionPolicy};
+pub use tool_usage::{ToolUsage, ToolUsageSummary};
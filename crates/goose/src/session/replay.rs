@@ -0,0 +1,233 @@
+//! Replaying a session's user turns against another model. The replay is a new session with the
+//! original as its parent, and its metadata compares each turn of the two.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use mcp_core::handler::{ToolError, ToolResult};
+use mcp_core::tool::ToolCall;
+use rmcp::model::{Content, Role};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::message::{Message, MessageContent};
+use crate::tokenizer;
+
+/// A replay of another session, and how its turns compare to the original's
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReplayRecord {
+    pub source_session_id: String,
+    pub provider: String,
+    pub model: String,
+    /// Whether tool calls were answered with the original session's results instead of run
+    pub skip_tools: bool,
+    /// Number of user turns in the original session
+    pub total_turns: usize,
+    /// The turns replayed so far
+    #[serde(default)]
+    pub turns: Vec<TurnComparison>,
+}
+
+/// One user turn of the original session and of its replay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TurnComparison {
+    /// Position of the turn, starting at 0
+    pub turn: usize,
+    pub original: TurnStats,
+    pub replay: TurnStats,
+}
+
+/// What a turn produced and how long it took
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TurnStats {
+    /// Tokens of the assistant's messages, counted locally so both sides are counted alike
+    pub output_tokens: usize,
+    pub tool_calls: usize,
+    /// From the user's message to the end of the turn. The original's is read from message
+    /// timestamps, which only have second precision, and is unknown when they're missing.
+    pub duration_ms: Option<u64>,
+}
+
+impl TurnStats {
+    /// Stats of `turn`, a user message and the messages that answered it
+    pub fn of(turn: &[Message], model: &str) -> Self {
+        let assistant_messages = turn.iter().filter(|m| m.role == Role::Assistant);
+        Self {
+            output_tokens: assistant_messages
+                .clone()
+                .map(|message| tokenizer::count_message(message, model))
+                .sum(),
+            tool_calls: assistant_messages
+                .flat_map(|message| &message.content)
+                .filter(|content| matches!(content, MessageContent::ToolRequest(_)))
+                .count(),
+            duration_ms: match (turn.first(), turn.last()) {
+                (Some(first), Some(last)) if first.created > 0 && last.created >= first.created => {
+                    Some((last.created - first.created) as u64 * 1000)
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// The user turns of a conversation: each starts with a message the user wrote, and holds
+/// everything up to the next one. Messages before the first are left out.
+pub fn user_turns(messages: &[Message]) -> Vec<&[Message]> {
+    let starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.role == Role::User && !message.is_tool_response())
+        .map(|(index, _)| index)
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(messages.len());
+            &messages[start..end]
+        })
+        .collect()
+}
+
+/// Digest of a tool call's arguments that doesn't depend on the order of their keys
+pub fn argument_digest(arguments: &Value) -> String {
+    let canonical = serde_json::to_string(&canonical(arguments)).unwrap_or_default();
+    format!("{:x}", Sha256::digest(canonical.as_bytes()))
+}
+
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.iter().map(canonical).collect()),
+        value => value.clone(),
+    }
+}
+
+/// The tool results of a session, to answer a replay's tool calls without running them. A
+/// call gets the result of an original call to the same tool with the same arguments; calls
+/// made more than once get their results in the original order.
+#[derive(Debug, Default)]
+pub struct RecordedToolResults {
+    results: Mutex<HashMap<(String, String), VecDeque<ToolResult<Vec<Content>>>>>,
+}
+
+impl RecordedToolResults {
+    pub fn from_messages(messages: &[Message]) -> Self {
+        let calls: HashMap<&str, (String, String)> = messages
+            .iter()
+            .flat_map(|message| &message.content)
+            .filter_map(|content| {
+                let request = content.as_tool_request()?;
+                let call = request.tool_call.as_ref().ok()?;
+                Some((
+                    request.id.as_str(),
+                    (call.name.clone(), argument_digest(&call.arguments)),
+                ))
+            })
+            .collect();
+
+        let mut results: HashMap<(String, String), VecDeque<ToolResult<Vec<Content>>>> =
+            HashMap::new();
+        for response in messages
+            .iter()
+            .flat_map(|message| &message.content)
+            .filter_map(|content| content.as_tool_response())
+        {
+            if let Some(call) = calls.get(response.id.as_str()) {
+                results
+                    .entry(call.clone())
+                    .or_default()
+                    .push_back(response.tool_result.clone());
+            }
+        }
+        Self {
+            results: Mutex::new(results),
+        }
+    }
+
+    /// The recorded result for `call`, or an error telling the model there is none
+    pub fn take(&self, call: &ToolCall) -> ToolResult<Vec<Content>> {
+        let key = (call.name.clone(), argument_digest(&call.arguments));
+        self.results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_mut(&key)
+            .and_then(VecDeque::pop_front)
+            .unwrap_or_else(|| {
+                Err(ToolError::ExecutionError(format!(
+                    "The original session has no result for this call to '{}', tools are not run during a replay",
+                    call.name
+                )))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_user_turns() {
+        let messages = vec![
+            Message::user().with_text("list the files"),
+            Message::assistant().with_tool_request("1", Ok(ToolCall::new("shell", json!({})))),
+            Message::user().with_tool_response("1", Ok(vec![Content::text("a.txt")])),
+            Message::assistant().with_text("There's a.txt"),
+            Message::user().with_text("thanks"),
+            Message::assistant().with_text("You're welcome"),
+        ];
+
+        let turns = user_turns(&messages);
+
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].len(), 4);
+        assert_eq!(turns[1][0].as_concat_text(), "thanks");
+        assert_eq!(TurnStats::of(turns[0], "gpt-4o").tool_calls, 1);
+    }
+
+    #[test]
+    fn test_recorded_results_match_name_and_arguments() {
+        let messages = vec![
+            Message::user().with_text("read both"),
+            Message::assistant()
+                .with_tool_request(
+                    "1",
+                    Ok(ToolCall::new("read", json!({"path": "a", "lines": 10}))),
+                )
+                .with_tool_request("2", Ok(ToolCall::new("read", json!({"path": "b"})))),
+            Message::user()
+                .with_tool_response("1", Ok(vec![Content::text("contents of a")]))
+                .with_tool_response("2", Ok(vec![Content::text("contents of b")])),
+        ];
+        let recorded = RecordedToolResults::from_messages(&messages);
+
+        // Key order doesn't matter
+        let result = recorded
+            .take(&ToolCall::new("read", json!({"lines": 10, "path": "a"})))
+            .unwrap();
+        assert_eq!(result[0].as_text().unwrap().text, "contents of a");
+        // Each recorded result answers one call
+        assert!(recorded
+            .take(&ToolCall::new("read", json!({"path": "a", "lines": 10})))
+            .is_err());
+        assert!(recorded
+            .take(&ToolCall::new("write", json!({"path": "b"})))
+            .is_err());
+        assert!(recorded
+            .take(&ToolCall::new("read", json!({"path": "b"})))
+            .is_ok());
+    }
+}
@@ -0,0 +1,403 @@
+//! Retention policy for session files.
+//!
+//! Sessions accumulate forever unless something removes them. The policy here is driven by
+//! `GOOSE_SESSION_RETENTION_DAYS` (maximum age) and `GOOSE_SESSION_MAX_COUNT` (maximum number
+//! of sessions to keep). Sessions that belong to a known schedule are never removed.
+
+use crate::config::Config;
+use crate::providers::transcript::remove_transcripts;
+use crate::session::artifacts::artifact_dir;
+use crate::session::storage::{ensure_session_dir, read_metadata};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use utoipa::ToSchema;
+
+const RETENTION_DAYS_KEY: &str = "GOOSE_SESSION_RETENTION_DAYS";
+const MAX_COUNT_KEY: &str = "GOOSE_SESSION_MAX_COUNT";
+const ARCHIVE_DIR: &str = "archive";
+
+/// Limits applied when cleaning up the session directory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Sessions last modified more than this many days ago are removed
+    pub max_age_days: Option<u64>,
+    /// Only the most recently modified sessions up to this count are kept
+    pub max_count: Option<usize>,
+}
+
+impl RetentionPolicy {
+    /// Build the policy from `GOOSE_SESSION_RETENTION_DAYS` and `GOOSE_SESSION_MAX_COUNT`
+    pub fn from_config() -> Self {
+        let config = Config::global();
+        Self {
+            max_age_days: config.get_param(RETENTION_DAYS_KEY).ok(),
+            max_count: config.get_param(MAX_COUNT_KEY).ok(),
+        }
+    }
+
+    /// A policy with no limits never removes anything
+    pub fn is_unbounded(&self) -> bool {
+        self.max_age_days.is_none() && self.max_count.is_none()
+    }
+}
+
+/// Options controlling how a cleanup run behaves
+#[derive(Debug, Clone, Default)]
+pub struct CleanupOptions {
+    /// Only report the candidates, leave files untouched
+    pub dry_run: bool,
+    /// Move removed sessions into a compressed tarball instead of deleting them outright
+    pub archive: bool,
+    /// Schedule IDs whose sessions must be kept regardless of the policy
+    pub protected_schedule_ids: HashSet<String>,
+}
+
+/// A session selected for removal by the retention policy
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovedSession {
+    /// Session identifier (file stem)
+    pub id: String,
+    /// Size of the session file in bytes
+    pub size_bytes: u64,
+    /// Last modification time of the session file
+    pub modified: DateTime<Utc>,
+}
+
+/// Result of a cleanup run
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReport {
+    /// Whether this was a dry run (nothing removed)
+    pub dry_run: bool,
+    /// Sessions that were (or, for a dry run, would be) removed
+    pub removed: Vec<RemovedSession>,
+    /// Total bytes freed from the session directory
+    pub bytes_reclaimed: u64,
+    /// Path of the tarball the sessions were archived to, if archiving was requested
+    #[schema(value_type = Option<String>)]
+    pub archive_path: Option<PathBuf>,
+}
+
+struct SessionFile {
+    id: String,
+    path: PathBuf,
+    size_bytes: u64,
+    modified: SystemTime,
+}
+
+/// Apply the retention policy to the session directory
+pub fn cleanup(policy: &RetentionPolicy, options: &CleanupOptions) -> Result<CleanupReport> {
+    let session_dir = ensure_session_dir()?;
+    cleanup_in_dir(&session_dir, policy, options, SystemTime::now())
+}
+
+fn cleanup_in_dir(
+    session_dir: &Path,
+    policy: &RetentionPolicy,
+    options: &CleanupOptions,
+    now: SystemTime,
+) -> Result<CleanupReport> {
+    let candidates = select_candidates(session_dir, policy, &options.protected_schedule_ids, now)?;
+
+    let mut report = CleanupReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    if candidates.is_empty() || options.dry_run {
+        report.bytes_reclaimed = candidates.iter().map(|c| c.size_bytes).sum();
+        report.removed = candidates.iter().map(to_removed).collect();
+        return Ok(report);
+    }
+
+    if options.archive {
+        report.archive_path = Some(archive_sessions(session_dir, &candidates)?);
+    }
+
+    for candidate in &candidates {
+        if let Err(e) = fs::remove_file(&candidate.path) {
+            tracing::warn!("Failed to remove session {}: {}", candidate.id, e);
+            continue;
+        }
+        let backup = candidate.path.with_extension("backup");
+        if backup.exists() {
+            let _ = fs::remove_file(backup);
+        }
+        let artifacts = artifact_dir(session_dir, &candidate.id);
+        if artifacts.exists() {
+            let _ = fs::remove_dir_all(artifacts);
+        }
+        let _ = remove_transcripts(&candidate.id);
+        report.bytes_reclaimed += candidate.size_bytes;
+        report.removed.push(to_removed(candidate));
+    }
+
+    tracing::info!(
+        "Session cleanup removed {} sessions ({} bytes)",
+        report.removed.len(),
+        report.bytes_reclaimed
+    );
+
+    Ok(report)
+}
+
+fn to_removed(file: &SessionFile) -> RemovedSession {
+    RemovedSession {
+        id: file.id.clone(),
+        size_bytes: file.size_bytes,
+        modified: DateTime::<Utc>::from(file.modified),
+    }
+}
+
+fn select_candidates(
+    session_dir: &Path,
+    policy: &RetentionPolicy,
+    protected_schedule_ids: &HashSet<String>,
+    now: SystemTime,
+) -> Result<Vec<SessionFile>> {
+    if policy.is_unbounded() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(session_dir)?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.extension().is_some_and(|ext| ext == "jsonl") {
+            continue;
+        }
+        let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            continue;
+        };
+
+        let is_protected = read_metadata(&path)
+            .ok()
+            .and_then(|m| m.schedule_id)
+            .is_some_and(|sid| protected_schedule_ids.contains(&sid));
+        if is_protected {
+            continue;
+        }
+
+        let Ok(file_meta) = entry.metadata() else {
+            continue;
+        };
+        sessions.push(SessionFile {
+            id,
+            path,
+            size_bytes: file_meta.len(),
+            modified: file_meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+
+    // Most recent first so that the count limit keeps the newest sessions
+    sessions.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+    // An age too large to subtract from now can't be reached, so there is no cutoff
+    let cutoff = policy.max_age_days.and_then(|days| {
+        days.checked_mul(24 * 60 * 60)
+            .and_then(|secs| now.checked_sub(Duration::from_secs(secs)))
+    });
+
+    Ok(sessions
+        .into_iter()
+        .enumerate()
+        .filter(|(index, session)| {
+            let too_old = cutoff.is_some_and(|cutoff| session.modified < cutoff);
+            let over_count = policy.max_count.is_some_and(|max| *index >= max);
+            too_old || over_count
+        })
+        .map(|(_, session)| session)
+        .collect())
+}
+
+fn archive_sessions(session_dir: &Path, sessions: &[SessionFile]) -> Result<PathBuf> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let archive_dir = session_dir.join(ARCHIVE_DIR);
+    fs::create_dir_all(&archive_dir)?;
+    let archive_path = archive_dir.join(format!(
+        "sessions-{}.tar.gz",
+        Utc::now().format("%Y%m%d_%H%M%S")
+    ));
+
+    let file = fs::File::create(&archive_path)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    for session in sessions {
+        builder.append_path_with_name(&session.path, format!("{}.jsonl", session.id))?;
+    }
+    builder.into_inner()?.finish()?;
+
+    Ok(archive_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::storage::SessionMetadata;
+    use tempfile::tempdir;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn write_session(dir: &Path, id: &str, age_days: u32, schedule_id: Option<&str>) {
+        let metadata = SessionMetadata {
+            schedule_id: schedule_id.map(String::from),
+            ..Default::default()
+        };
+        let path = dir.join(format!("{}.jsonl", id));
+        fs::write(
+            &path,
+            format!("{}\n", serde_json::to_string(&metadata).unwrap()),
+        )
+        .unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - DAY * age_days)
+            .unwrap();
+    }
+
+    fn ids(report: &CleanupReport) -> Vec<String> {
+        let mut ids: Vec<String> = report.removed.iter().map(|r| r.id.clone()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_unbounded_policy_removes_nothing() {
+        let dir = tempdir().unwrap();
+        write_session(dir.path(), "old", 400, None);
+
+        let report = cleanup_in_dir(
+            dir.path(),
+            &RetentionPolicy::default(),
+            &CleanupOptions::default(),
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        assert!(report.removed.is_empty());
+        assert!(dir.path().join("old.jsonl").exists());
+    }
+
+    #[test]
+    fn test_age_and_count_limits() {
+        let dir = tempdir().unwrap();
+        write_session(dir.path(), "a", 1, None);
+        write_session(dir.path(), "b", 2, None);
+        write_session(dir.path(), "c", 3, None);
+        write_session(dir.path(), "d", 40, None);
+        fs::create_dir_all(artifact_dir(dir.path(), "d")).unwrap();
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_count: Some(2),
+        };
+        let report = cleanup_in_dir(
+            dir.path(),
+            &policy,
+            &CleanupOptions::default(),
+            SystemTime::now(),
+        )
+        .unwrap();
+
+        assert_eq!(ids(&report), vec!["c", "d"]);
+        assert!(report.bytes_reclaimed > 0);
+        assert!(dir.path().join("a.jsonl").exists());
+        assert!(!dir.path().join("d.jsonl").exists());
+        assert!(!artifact_dir(dir.path(), "d").exists());
+    }
+
+    #[test]
+    fn test_huge_max_age_removes_nothing() {
+        let dir = tempdir().unwrap();
+        write_session(dir.path(), "old", 400, None);
+
+        for days in [u64::MAX, u64::MAX / (24 * 60 * 60)] {
+            let policy = RetentionPolicy {
+                max_age_days: Some(days),
+                max_count: None,
+            };
+            let report = cleanup_in_dir(
+                dir.path(),
+                &policy,
+                &CleanupOptions::default(),
+                SystemTime::now(),
+            )
+            .unwrap();
+            assert!(report.removed.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_dry_run_keeps_files() {
+        let dir = tempdir().unwrap();
+        write_session(dir.path(), "old", 90, None);
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_count: None,
+        };
+        let options = CleanupOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = cleanup_in_dir(dir.path(), &policy, &options, SystemTime::now()).unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(ids(&report), vec!["old"]);
+        assert!(dir.path().join("old.jsonl").exists());
+    }
+
+    #[test]
+    fn test_scheduled_sessions_are_exempt() {
+        let dir = tempdir().unwrap();
+        write_session(dir.path(), "scheduled", 90, Some("nightly"));
+        write_session(dir.path(), "orphaned", 90, Some("deleted-schedule"));
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_count: None,
+        };
+        let options = CleanupOptions {
+            protected_schedule_ids: HashSet::from(["nightly".to_string()]),
+            ..Default::default()
+        };
+        let report = cleanup_in_dir(dir.path(), &policy, &options, SystemTime::now()).unwrap();
+
+        assert_eq!(ids(&report), vec!["orphaned"]);
+        assert!(dir.path().join("scheduled.jsonl").exists());
+    }
+
+    #[test]
+    fn test_archive_writes_tarball() {
+        let dir = tempdir().unwrap();
+        write_session(dir.path(), "old", 90, None);
+
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_count: None,
+        };
+        let options = CleanupOptions {
+            archive: true,
+            ..Default::default()
+        };
+        let report = cleanup_in_dir(dir.path(), &policy, &options, SystemTime::now()).unwrap();
+
+        let archive_path = report.archive_path.expect("archive path");
+        assert!(archive_path.exists());
+        assert!(!dir.path().join("old.jsonl").exists());
+
+        let file = fs::File::open(archive_path).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        let names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["old.jsonl"]);
+    }
+}
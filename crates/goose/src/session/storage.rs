@@ -5,16 +5,24 @@
 // - Backup creation
 // Additional debug logging can be added if needed for troubleshooting.
 
+use crate::context_mgmt::summarizer::summarizer_provider;
 use crate::message::Message;
 use crate::providers::base::Provider;
+use crate::recipe::response_schema::SchemaValidation;
+use crate::session::encryption;
+use crate::session::format;
+use crate::session::redaction::Redactor;
+use crate::session::replay::ReplayRecord;
+use crate::session::tool_usage::ToolUsageSummary;
 use crate::utils::safe_truncate;
 use anyhow::Result;
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 use etcetera::{choose_app_strategy, AppStrategy, AppStrategyArgs};
+use once_cell::sync::OnceCell;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, Read, Write};
 use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -25,7 +33,7 @@ const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10MB
 const MAX_MESSAGE_COUNT: usize = 5000;
 const MAX_LINE_LENGTH: usize = 1024 * 1024; // 1MB per line
 
-fn get_home_dir() -> PathBuf {
+pub(crate) fn get_home_dir() -> PathBuf {
     choose_app_strategy(crate::config::APP_STRATEGY.clone())
         .expect("goose requires a home dir")
         .home_dir()
@@ -38,9 +46,14 @@ fn get_current_working_dir() -> PathBuf {
         .expect("could not determine the current working directory")
 }
 
+/// Version of the session file format written by this build, see [`crate::session::format`]
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
 /// Metadata for a session, stored as the first line in the session file
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct SessionMetadata {
+    /// Version of the session file format. Files written before it was recorded are version 0.
+    pub format_version: u32,
     /// Working directory for the session
     #[schema(value_type = String, example = "/home/user/sessions/session1")]
     pub working_dir: PathBuf,
@@ -64,6 +77,64 @@ pub struct SessionMetadata {
     pub accumulated_input_tokens: Option<i32>,
     /// The number of output tokens used in the session. Accumulated across all messages.
     pub accumulated_output_tokens: Option<i32>,
+    /// Web searches the provider ran itself, which are billed apart from the tokens. Accumulated across all messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accumulated_web_search_requests: Option<i32>,
+    /// The provider and model settings the session was created with, without credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Object)]
+    pub provider_config: Option<serde_json::Value>,
+    /// Models the session switched to after it was created, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_changes: Vec<ModelChangeRecord>,
+    /// Session this one was forked from or started by, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+    /// How the session was started
+    pub origin: SessionOrigin,
+    /// Persona of the latest reply that selected one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+    /// Whether the latest recipe run's output matched the recipe's response schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_validation: Option<SchemaValidation>,
+    /// The session this one replays and how their turns compare, for replays
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<ReplayRecord>,
+    /// The batch this session ran an item of, for sessions started by `POST /ask/batch`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    /// Tool calls in the session by extension and tool, counted whenever the session is saved
+    #[serde(default)]
+    pub tool_usage: ToolUsageSummary,
+}
+
+/// How a session was started. Sessions from before this was recorded read as manual.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionOrigin {
+    /// Started by a user in the CLI or desktop app
+    #[default]
+    Manual,
+    /// Forked from another session's history
+    Fork,
+    /// Started by a sub-recipe of another session
+    SubRecipe,
+    /// Started by a scheduled job
+    Schedule,
+    /// Replays another session's user turns with a different model
+    Replay,
+    /// Runs one item of a batch
+    Batch,
+}
+
+/// A switch to a different model partway through a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ModelChangeRecord {
+    pub model: String,
+    /// The role the model took, such as "lead" or "worker"
+    pub mode: String,
+    pub changed_at: DateTime<Utc>,
 }
 
 // Custom deserializer to handle old sessions without working_dir
@@ -74,6 +145,8 @@ impl<'de> Deserialize<'de> for SessionMetadata {
     {
         #[derive(Deserialize)]
         struct Helper {
+            #[serde(default)]
+            format_version: u32,
             description: String,
             message_count: usize,
             schedule_id: Option<String>, // For backward compatibility
@@ -84,7 +157,27 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: Option<i32>,
             accumulated_input_tokens: Option<i32>,
             accumulated_output_tokens: Option<i32>,
+            #[serde(default)]
+            accumulated_web_search_requests: Option<i32>,
             working_dir: Option<PathBuf>,
+            #[serde(default)]
+            provider_config: Option<serde_json::Value>,
+            #[serde(default)]
+            model_changes: Vec<ModelChangeRecord>,
+            #[serde(default)]
+            parent_session_id: Option<String>,
+            #[serde(default)]
+            origin: SessionOrigin,
+            #[serde(default)]
+            persona: Option<String>,
+            #[serde(default)]
+            schema_validation: Option<SchemaValidation>,
+            #[serde(default)]
+            replay: Option<ReplayRecord>,
+            #[serde(default)]
+            batch_id: Option<String>,
+            #[serde(default)]
+            tool_usage: ToolUsageSummary,
         }
 
         let helper = Helper::deserialize(deserializer)?;
@@ -96,6 +189,7 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             .unwrap_or_else(get_current_working_dir);
 
         Ok(SessionMetadata {
+            format_version: helper.format_version,
             description: helper.description,
             message_count: helper.message_count,
             schedule_id: helper.schedule_id,
@@ -106,7 +200,17 @@ impl<'de> Deserialize<'de> for SessionMetadata {
             accumulated_total_tokens: helper.accumulated_total_tokens,
             accumulated_input_tokens: helper.accumulated_input_tokens,
             accumulated_output_tokens: helper.accumulated_output_tokens,
+            accumulated_web_search_requests: helper.accumulated_web_search_requests,
             working_dir,
+            provider_config: helper.provider_config,
+            model_changes: helper.model_changes,
+            parent_session_id: helper.parent_session_id,
+            origin: helper.origin,
+            persona: helper.persona,
+            schema_validation: helper.schema_validation,
+            replay: helper.replay,
+            batch_id: helper.batch_id,
+            tool_usage: helper.tool_usage,
         })
     }
 }
@@ -121,6 +225,7 @@ impl SessionMetadata {
         };
 
         Self {
+            format_version: SESSION_FORMAT_VERSION,
             working_dir,
             description: String::new(),
             schedule_id: None,
@@ -132,8 +237,58 @@ impl SessionMetadata {
             accumulated_total_tokens: None,
             accumulated_input_tokens: None,
             accumulated_output_tokens: None,
+            accumulated_web_search_requests: None,
+            provider_config: None,
+            model_changes: Vec::new(),
+            parent_session_id: None,
+            origin: SessionOrigin::Manual,
+            persona: None,
+            schema_validation: None,
+            replay: None,
+            batch_id: None,
+            tool_usage: ToolUsageSummary::default(),
         }
     }
+
+    /// Start the metadata for a new session, recording the provider it runs with if known and
+    /// whether a schedule started it
+    fn for_new_session(
+        working_dir: PathBuf,
+        provider: Option<&Arc<dyn Provider>>,
+        schedule_id: Option<&String>,
+    ) -> Self {
+        let mut metadata = Self::new(working_dir);
+        metadata.provider_config = provider.map(|provider| provider.sanitized_config());
+        if schedule_id.is_some() {
+            metadata.origin = SessionOrigin::Schedule;
+        }
+        metadata
+    }
+
+    /// The model the session is currently using, as far as the metadata records
+    pub fn current_model(&self) -> Option<&str> {
+        match self.model_changes.last() {
+            Some(change) => Some(&change.model),
+            None => self
+                .provider_config
+                .as_ref()
+                .and_then(|config| config["model"]["model_name"].as_str()),
+        }
+    }
+
+    /// Record a switch to `model`. Returns false without recording anything if the session is
+    /// already using it.
+    pub fn record_model_change(&mut self, model: &str, mode: &str) -> bool {
+        if self.current_model() == Some(model) {
+            return false;
+        }
+        self.model_changes.push(ModelChangeRecord {
+            model: model.to_string(),
+            mode: mode.to_string(),
+            changed_at: Utc::now(),
+        });
+        true
+    }
 }
 
 impl Default for SessionMetadata {
@@ -151,6 +306,18 @@ pub enum Identifier {
     Path(PathBuf),
 }
 
+impl Identifier {
+    /// The session id: the name, or the file stem of a session path
+    pub fn session_id(&self) -> Option<String> {
+        match self {
+            Identifier::Name(name) => Some(name.clone()),
+            Identifier::Path(path) => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string()),
+        }
+    }
+}
+
 pub fn get_path(id: Identifier) -> Result<PathBuf> {
     let path = match id {
         Identifier::Name(name) => {
@@ -317,19 +484,32 @@ fn normalize_path_for_comparison(path: &Path) -> PathBuf {
     }
 }
 
+/// Directory the sessions are kept in instead of the data dir, once set
+static SESSION_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Keep the sessions of this process in `dir` instead of the data dir, such as a temporary
+/// directory for tests. Only the first call has an effect.
+pub fn set_session_dir(dir: PathBuf) {
+    let _ = SESSION_DIR.set(dir);
+}
+
 /// Ensure the session directory exists and return its path
 pub fn ensure_session_dir() -> Result<PathBuf> {
-    let app_strategy = AppStrategyArgs {
-        top_level_domain: "Block".to_string(),
-        author: "Block".to_string(),
-        app_name: APP_NAME.to_string(),
+    let data_dir = match SESSION_DIR.get() {
+        Some(dir) => dir.clone(),
+        None => {
+            let app_strategy = AppStrategyArgs {
+                top_level_domain: "Block".to_string(),
+                author: "Block".to_string(),
+                app_name: APP_NAME.to_string(),
+            };
+            choose_app_strategy(app_strategy)
+                .expect("goose requires a home dir")
+                .data_dir()
+                .join("sessions")
+        }
     };
 
-    let data_dir = choose_app_strategy(app_strategy)
-        .expect("goose requires a home dir")
-        .data_dir()
-        .join("sessions");
-
     if !data_dir.exists() {
         fs::create_dir_all(&data_dir)?;
     }
@@ -456,14 +636,19 @@ pub fn read_messages_with_truncation(
     }
 
     // Open the file with appropriate options
-    let file = fs::OpenOptions::new()
+    let mut file = fs::OpenOptions::new()
         .read(true)
         .write(true)
         .create(true)
         .truncate(false)
         .open(session_file)?;
 
-    let reader = io::BufReader::new(file);
+    // Encrypted sessions are decrypted up front, plaintext sessions pass through unchanged
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    let contents = encryption::decrypt_contents(contents)?;
+
+    let reader = io::Cursor::new(contents);
     let mut lines = reader.lines();
     let mut messages = Vec::new();
     let mut corrupted_lines = Vec::new();
@@ -1042,6 +1227,15 @@ pub fn read_metadata(session_file: &Path) -> Result<SessionMetadata> {
     }
 }
 
+/// Read the metadata of an existing session to save it again. Sessions written by a newer goose
+/// are refused, and older ones are saved in the current format.
+fn read_metadata_for_rewrite(session_file: &Path) -> Result<SessionMetadata> {
+    let mut metadata = read_metadata(session_file)?;
+    format::check_version(&metadata)?;
+    metadata.format_version = SESSION_FORMAT_VERSION;
+    Ok(metadata)
+}
+
 /// Write messages to a session file with metadata
 ///
 /// Overwrites the file with metadata as the first line, followed by all messages in JSONL format.
@@ -1105,11 +1299,11 @@ pub async fn persist_messages_with_schedule_id(
         _ => {
             // Read existing metadata or create new with proper working_dir
             let mut metadata = if secure_path.exists() {
-                read_metadata(&secure_path)?
+                read_metadata_for_rewrite(&secure_path)?
             } else {
                 // Create new metadata with the provided working_dir or fall back to home
                 let work_dir = working_dir.clone().unwrap_or_else(get_home_dir);
-                SessionMetadata::new(work_dir)
+                SessionMetadata::for_new_session(work_dir, provider.as_ref(), schedule_id.as_ref())
             };
 
             // Update the working_dir if provided (even for existing files)
@@ -1160,6 +1354,12 @@ pub fn save_messages_with_metadata(
         return Err(anyhow::anyhow!("Too many messages to save"));
     }
 
+    let mut metadata = metadata.clone();
+    metadata.tool_usage = ToolUsageSummary::from_messages(messages);
+
+    // Strip secrets before anything reaches the disk
+    let messages = Redactor::from_config().redact_messages(messages);
+
     // Create a temporary file in the same directory to ensure atomic move
     let temp_file = secure_path.with_extension("tmp");
 
@@ -1212,12 +1412,26 @@ pub fn save_messages_with_metadata(
         writeln!(writer)?;
 
         // Write all messages with progress tracking
-        for (i, message) in messages.iter().enumerate() {
-            serde_json::to_writer(&mut writer, &message).map_err(|e| {
-                tracing::error!("Failed to serialize message {}: {}", i, e);
-                anyhow::anyhow!("Failed to write session message")
-            })?;
-            writeln!(writer)?;
+        if encryption::is_enabled() {
+            // Messages are encrypted as a single line; the metadata line stays readable
+            let mut body = Vec::new();
+            for (i, message) in messages.iter().enumerate() {
+                serde_json::to_writer(&mut body, &message).map_err(|e| {
+                    tracing::error!("Failed to serialize message {}: {}", i, e);
+                    anyhow::anyhow!("Failed to write session message")
+                })?;
+                writeln!(body)?;
+            }
+            let key = encryption::get_or_create_key()?;
+            writeln!(writer, "{}", encryption::encrypt_body(&key, &body)?)?;
+        } else {
+            for (i, message) in messages.iter().enumerate() {
+                serde_json::to_writer(&mut writer, &message).map_err(|e| {
+                    tracing::error!("Failed to serialize message {}: {}", i, e);
+                    anyhow::anyhow!("Failed to write session message")
+                })?;
+                writeln!(writer)?;
+            }
         }
 
         // Ensure all data is written to disk
@@ -1294,22 +1508,38 @@ pub async fn generate_description_with_schedule_id(
         ));
     }
 
-    // Use the provider's session naming capability
-    let sanitized_description = provider
-        .generate_session_name(messages)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to generate session description: {}", e);
-            anyhow::anyhow!("Failed to generate session description")
-        })?;
+    // Use the provider's session naming capability, preferring the cheaper summarizer model
+    let summarized = match summarizer_provider() {
+        Some(summarizer) => summarizer
+            .generate_session_name(messages)
+            .await
+            .map_err(|e| {
+                tracing::warn!(
+                    "Summarizer failed to name session, using primary model: {}",
+                    e
+                );
+            })
+            .ok(),
+        None => None,
+    };
+    let sanitized_description = match summarized {
+        Some(description) => description,
+        None => provider
+            .generate_session_name(messages)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to generate session description: {}", e);
+                anyhow::anyhow!("Failed to generate session description")
+            })?,
+    };
 
     // Create metadata with proper working_dir or read existing and update
     let mut metadata = if secure_path.exists() {
-        read_metadata(&secure_path)?
+        read_metadata_for_rewrite(&secure_path)?
     } else {
         // Create new metadata with the provided working_dir or fall back to home
         let work_dir = working_dir.clone().unwrap_or_else(get_home_dir);
-        SessionMetadata::new(work_dir)
+        SessionMetadata::for_new_session(work_dir, Some(&provider), schedule_id.as_ref())
     };
 
     // Update description and schedule_id
@@ -1327,6 +1557,32 @@ pub async fn generate_description_with_schedule_id(
     save_messages_with_metadata(&secure_path, &metadata, messages)
 }
 
+/// Record that a session switched to `model`, if it wasn't already using it. Sessions that
+/// haven't been saved yet are skipped, since their provider snapshot is taken when they are.
+pub async fn record_model_change(session_file: &Path, model: &str, mode: &str) -> Result<()> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    if !secure_path.exists() {
+        return Ok(());
+    }
+
+    let mut metadata = read_metadata_for_rewrite(&secure_path)?;
+    if metadata.record_model_change(model, mode) {
+        update_metadata(&secure_path, &metadata).await?;
+    }
+    Ok(())
+}
+
+/// Record whether a recipe run's output matched the recipe's response schema
+pub async fn record_schema_validation(
+    session_file: &Path,
+    validation: SchemaValidation,
+) -> Result<()> {
+    let secure_path = get_path(Identifier::Path(session_file.to_path_buf()))?;
+    let mut metadata = read_metadata_for_rewrite(&secure_path)?;
+    metadata.schema_validation = Some(validation);
+    update_metadata(&secure_path, &metadata).await
+}
+
 /// Update only the metadata in a session file, preserving all messages
 ///
 /// Security features:
@@ -1664,6 +1920,38 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_model_changes_recorded_once() -> Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("model_changes.jsonl");
+
+        let metadata = SessionMetadata {
+            provider_config: Some(serde_json::json!({
+                "provider": "openai",
+                "host": "https://api.openai.com",
+                "model": {"model_name": "gpt-4o", "temperature": 0.2}
+            })),
+            ..Default::default()
+        };
+        let messages = vec![Message::user().with_text("test")];
+        save_messages_with_metadata(&file_path, &metadata, &messages)?;
+
+        // Staying on the snapshot's model isn't a change
+        record_model_change(&file_path, "gpt-4o", "lead").await?;
+        record_model_change(&file_path, "gpt-4o-mini", "worker").await?;
+        record_model_change(&file_path, "gpt-4o-mini", "worker").await?;
+
+        let read_metadata = read_metadata(&file_path)?;
+        assert_eq!(read_metadata.provider_config, metadata.provider_config);
+        assert_eq!(read_metadata.model_changes.len(), 1);
+        assert_eq!(read_metadata.model_changes[0].model, "gpt-4o-mini");
+        assert_eq!(read_metadata.model_changes[0].mode, "worker");
+        assert_eq!(read_metadata.current_model(), Some("gpt-4o-mini"));
+        assert_eq!(read_messages(&file_path)?.len(), 1);
+
+        Ok(())
+    }
+
     #[test]
     fn test_invalid_working_dir() -> Result<()> {
         let dir = tempdir()?;
@@ -0,0 +1,164 @@
+//! How often each tool was called in a session and how often it failed. Tool names carry their
+//! extension as a prefix, as in `developer__shell`, so the counts are kept by extension and tool.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::message::{Message, MessageContent};
+
+/// Calls of one tool in a session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ToolUsage {
+    /// The extension the tool belongs to, empty for tools without an extension prefix
+    pub extension: String,
+    pub tool: String,
+    pub calls: usize,
+    /// Calls that returned an error
+    pub failures: usize,
+    /// Total time the tool ran, over the calls that were timed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Tool calls in a session, by extension and tool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ToolUsageSummary {
+    pub calls: usize,
+    pub failures: usize,
+    /// Sorted by extension, then tool
+    pub tools: Vec<ToolUsage>,
+}
+
+impl ToolUsageSummary {
+    /// Count the tool requests in `messages`, with the failures and durations of their responses.
+    /// Requests whose tool call couldn't be parsed have no name and are left out.
+    pub fn from_messages(messages: &[Message]) -> Self {
+        let mut usage: BTreeMap<(String, String), ToolUsage> = BTreeMap::new();
+        let mut names: HashMap<&str, (String, String)> = HashMap::new();
+
+        for content in messages.iter().flat_map(|message| &message.content) {
+            match content {
+                MessageContent::ToolRequest(request) => {
+                    let Ok(call) = &request.tool_call else {
+                        continue;
+                    };
+                    let (extension, tool) = call
+                        .name
+                        .split_once("__")
+                        .unwrap_or(("", call.name.as_str()));
+                    let key = (extension.to_string(), tool.to_string());
+                    usage
+                        .entry(key.clone())
+                        .or_insert_with(|| ToolUsage {
+                            extension: key.0.clone(),
+                            tool: key.1.clone(),
+                            ..Default::default()
+                        })
+                        .calls += 1;
+                    names.insert(&request.id, key);
+                }
+                MessageContent::ToolResponse(response) => {
+                    let Some(tool) = names
+                        .get(response.id.as_str())
+                        .and_then(|key| usage.get_mut(key))
+                    else {
+                        continue;
+                    };
+                    if response.tool_result.is_err() {
+                        tool.failures += 1;
+                    }
+                    if let Some(duration_ms) = response.duration_ms {
+                        *tool.duration_ms.get_or_insert(0) += duration_ms;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let tools: Vec<ToolUsage> = usage.into_values().collect();
+        Self {
+            calls: tools.iter().map(|tool| tool.calls).sum(),
+            failures: tools.iter().map(|tool| tool.failures).sum(),
+            tools,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use mcp_core::ToolError;
+    use rmcp::model::Content;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn request(id: &str, name: &str) -> Message {
+        Message::assistant().with_tool_request(id, Ok(ToolCall::new(name, json!({}))))
+    }
+
+    #[test]
+    fn test_counts_calls_failures_and_durations_by_tool() {
+        let messages = vec![
+            request("1", "developer__shell"),
+            Message::user().with_timed_tool_response(
+                "1",
+                Ok(vec![Content::text("ok")]),
+                None,
+                Duration::from_millis(120),
+            ),
+            request("2", "developer__shell"),
+            Message::user().with_timed_tool_response(
+                "2",
+                Err(ToolError::ExecutionError("exit status 1".to_string())),
+                None,
+                Duration::from_millis(30),
+            ),
+            request("3", "developer__text_editor"),
+            Message::user().with_tool_response("3", Ok(vec![])),
+            // Cancelled before it answered
+            request("4", "read_only_tool"),
+            // Unparseable calls have no name to count them under
+            Message::assistant().with_tool_request(
+                "5",
+                Err(ToolError::InvalidParameters("bad json".to_string())),
+            ),
+            Message::user().with_tool_response(
+                "5",
+                Err(ToolError::InvalidParameters("bad json".to_string())),
+            ),
+        ];
+
+        let summary = ToolUsageSummary::from_messages(&messages);
+        assert_eq!(summary.calls, 4);
+        assert_eq!(summary.failures, 1);
+        assert_eq!(
+            summary.tools,
+            vec![
+                ToolUsage {
+                    extension: String::new(),
+                    tool: "read_only_tool".to_string(),
+                    calls: 1,
+                    failures: 0,
+                    duration_ms: None,
+                },
+                ToolUsage {
+                    extension: "developer".to_string(),
+                    tool: "shell".to_string(),
+                    calls: 2,
+                    failures: 1,
+                    duration_ms: Some(150),
+                },
+                ToolUsage {
+                    extension: "developer".to_string(),
+                    tool: "text_editor".to_string(),
+                    calls: 1,
+                    failures: 0,
+                    duration_ms: None,
+                },
+            ]
+        );
+    }
+}
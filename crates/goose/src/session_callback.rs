@@ -0,0 +1,398 @@
+//! Delivery of a finished session's result to a callback URL.
+//!
+//! Clients that start a run, from CI say, can name a `callback_url` instead of polling for the
+//! session to finish. The result is POSTed as JSON, signed like scheduler webhooks when a
+//! `callback_secret` is given, and retried with backoff. Every attempt is recorded in
+//! `deliveries/{session_id}.jsonl` under the session directory.
+//!
+//! Only http(s) URLs are accepted, and hosts resolving to link-local or cloud metadata
+//! addresses are refused unless `GOOSE_CALLBACK_ALLOW_LINK_LOCAL` is set.
+
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rmcp::model::Role;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::message::Message;
+use crate::scheduler::{session_usage, RunUsage};
+use crate::scheduler_webhook::{sign_payload, SIGNATURE_HEADER};
+use crate::session::artifacts::validate_id;
+use crate::session::storage::ensure_session_dir;
+
+const ALLOW_LINK_LOCAL_KEY: &str = "GOOSE_CALLBACK_ALLOW_LINK_LOCAL";
+const DELIVERY_ATTEMPTS: u32 = 3;
+/// Wait before the second attempt, doubled before each one after
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const DELIVERIES_DIR: &str = "deliveries";
+
+/// Where to send a session's result once it finishes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionCallback {
+    pub url: Url,
+    /// Key the body is signed with, sent as `X-Goose-Signature`
+    pub secret: Option<String>,
+}
+
+impl SessionCallback {
+    /// A callback to `url`, refusing URLs it would not be safe for the server to call
+    pub fn new(url: &str, secret: Option<String>) -> Result<Self> {
+        let url = Url::parse(url).map_err(|e| anyhow!("Invalid callback URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("Callback URLs must use http or https"));
+        }
+        match url.host() {
+            None => return Err(anyhow!("Callback URL has no host")),
+            Some(url::Host::Ipv4(ip)) => check_address(IpAddr::V4(ip))?,
+            Some(url::Host::Ipv6(ip)) => check_address(IpAddr::V6(ip))?,
+            Some(url::Host::Domain(_)) => {}
+        }
+        Ok(Self { url, secret })
+    }
+}
+
+/// How a session's reply ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionResult {
+    Success,
+    Error,
+    Cancelled,
+}
+
+/// The recipe a session ran
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RecipeInfo {
+    /// Local recipe name or recipe file the run was started with
+    pub source: String,
+    pub title: Option<String>,
+}
+
+/// The JSON body POSTed to a callback URL
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionResultPayload {
+    pub session_id: Option<String>,
+    pub result: SessionResult,
+    pub error: Option<String>,
+    pub duration_seconds: f64,
+    pub usage: RunUsage,
+    pub last_assistant_message: Option<String>,
+    pub recipe: Option<RecipeInfo>,
+}
+
+impl SessionResultPayload {
+    /// The payload for a finished session, with its usage read from the saved session
+    pub fn new(
+        session_id: Option<String>,
+        result: SessionResult,
+        error: Option<String>,
+        duration: Duration,
+        messages: &[Message],
+        recipe: Option<RecipeInfo>,
+    ) -> Self {
+        let usage = session_id
+            .as_deref()
+            .and_then(session_usage)
+            .unwrap_or_default();
+        let last_assistant_message = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == Role::Assistant)
+            .map(Message::as_concat_text);
+        Self {
+            session_id,
+            result,
+            error,
+            duration_seconds: duration.as_secs_f64(),
+            usage,
+            last_assistant_message,
+            recipe,
+        }
+    }
+}
+
+/// One attempt at delivering a session's result
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeliveryAttempt {
+    pub url: String,
+    /// 1 for the first attempt
+    pub attempt: u32,
+    pub attempted_at: DateTime<Utc>,
+    /// Status the callback responded with, if it responded
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub delivered: bool,
+}
+
+/// POST `payload` to the callback, retrying failed attempts with backoff. Returns whether it
+/// was delivered; failures are recorded and logged rather than returned.
+pub async fn deliver(callback: &SessionCallback, payload: &SessionResultPayload) -> bool {
+    deliver_with_delay(callback, payload, RETRY_DELAY).await
+}
+
+async fn deliver_with_delay(
+    callback: &SessionCallback,
+    payload: &SessionResultPayload,
+    retry_delay: Duration,
+) -> bool {
+    let body = match serde_json::to_vec(payload) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to encode session result: {}", e);
+            return false;
+        }
+    };
+
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        if attempt > 1 {
+            tokio::time::sleep(retry_delay * 2u32.pow(attempt - 2)).await;
+        }
+        let (status, error) = match post(callback, &body).await {
+            Ok(status) if (200..300).contains(&status) => (Some(status), None),
+            Ok(status) => (
+                Some(status),
+                Some(format!("Callback responded with {}", status)),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let delivered = error.is_none();
+        let record = DeliveryAttempt {
+            url: callback.url.to_string(),
+            attempt,
+            attempted_at: Utc::now(),
+            status,
+            error,
+            delivered,
+        };
+        if let Some(session_id) = payload.session_id.as_deref() {
+            if let Err(e) = record_attempt(session_id, &record) {
+                tracing::warn!("Failed to record delivery for '{}': {}", session_id, e);
+            }
+        }
+        if delivered {
+            return true;
+        }
+        tracing::warn!(
+            "Delivery {} of {} to {} failed: {}",
+            attempt,
+            DELIVERY_ATTEMPTS,
+            callback.url,
+            record.error.unwrap_or_default()
+        );
+    }
+    false
+}
+
+/// Send the body to the callback, connecting only to an address checked to be allowed
+async fn post(callback: &SessionCallback, body: &[u8]) -> Result<u16> {
+    let host = callback
+        .url
+        .host_str()
+        .ok_or_else(|| anyhow!("Callback URL has no host"))?;
+    let port = callback
+        .url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("Callback URL has no port"))?;
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    for address in &addresses {
+        check_address(address.ip())?;
+    }
+    let address = addresses
+        .first()
+        .ok_or_else(|| anyhow!("{} did not resolve", host))?;
+
+    // Pin the checked address so a second lookup can't point the request elsewhere
+    let client = reqwest::Client::builder()
+        .resolve(host, *address)
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(DELIVERY_TIMEOUT)
+        .build()?;
+    let mut request = client
+        .post(callback.url.clone())
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(secret) = &callback.secret {
+        request = request.header(SIGNATURE_HEADER, sign_payload(secret, body));
+    }
+    let response = request.body(body.to_vec()).send().await?;
+    Ok(response.status().as_u16())
+}
+
+/// Refuse link-local addresses, which include the cloud metadata endpoints, and other
+/// addresses that are never a real callback receiver
+fn check_address(ip: IpAddr) -> Result<()> {
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    };
+    if ip.is_unspecified() || ip.is_multicast() {
+        return Err(anyhow!("Callbacks to {} are not allowed", ip));
+    }
+    let link_local = match ip {
+        IpAddr::V4(v4) => v4.is_link_local() || v4 == Ipv4Addr::new(100, 100, 100, 200),
+        IpAddr::V6(v6) => {
+            (v6.segments()[0] & 0xffc0) == 0xfe80
+                || v6 == Ipv6Addr::new(0xfd00, 0xec2, 0, 0, 0, 0, 0, 0x254)
+        }
+    };
+    let allowed = Config::global()
+        .get_param(ALLOW_LINK_LOCAL_KEY)
+        .unwrap_or(false);
+    if link_local && !allowed {
+        return Err(anyhow!(
+            "Callbacks to link-local or metadata address {} are not allowed; set {} to allow them",
+            ip,
+            ALLOW_LINK_LOCAL_KEY
+        ));
+    }
+    Ok(())
+}
+
+fn deliveries_path(session_id: &str) -> Result<PathBuf> {
+    validate_id(session_id)?;
+    Ok(ensure_session_dir()?
+        .join(DELIVERIES_DIR)
+        .join(format!("{}.jsonl", session_id)))
+}
+
+fn record_attempt(session_id: &str, attempt: &DeliveryAttempt) -> Result<()> {
+    let path = deliveries_path(session_id)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(attempt)?)?;
+    Ok(())
+}
+
+/// The delivery attempts made for a session's result, oldest first
+pub fn list_deliveries(session_id: &str) -> Result<Vec<DeliveryAttempt>> {
+    let path = deliveries_path(session_id)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut attempts = Vec::new();
+    for line in BufReader::new(fs::File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        attempts.push(serde_json::from_str(&line)?);
+    }
+    Ok(attempts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn payload(session_id: &str) -> SessionResultPayload {
+        SessionResultPayload::new(
+            Some(session_id.to_string()),
+            SessionResult::Success,
+            None,
+            Duration::from_secs(12),
+            &[
+                Message::user().with_text("Run the checks"),
+                Message::assistant().with_text("All checks passed"),
+            ],
+            None,
+        )
+    }
+
+    #[test]
+    fn test_unsafe_callback_urls_are_refused() {
+        for url in [
+            "ftp://example.com/hook",
+            "file:///etc/passwd",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[fe80::1]/hook",
+            "http://[::ffff:169.254.169.254]/hook",
+            "http://0.0.0.0/hook",
+            "not a url",
+        ] {
+            assert!(SessionCallback::new(url, None).is_err(), "{}", url);
+        }
+        assert!(SessionCallback::new("https://ci.example.com/hook", None).is_ok());
+        assert!(SessionCallback::new("http://10.0.0.5:8080/hook", None).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_retried_and_recorded() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(header_exists(SIGNATURE_HEADER))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session_id = format!("test-delivery-{}", uuid::Uuid::new_v4());
+        let callback = SessionCallback::new(
+            &format!("{}/hook", server.uri()),
+            Some("ci-secret".to_string()),
+        )
+        .unwrap();
+        let delivered =
+            deliver_with_delay(&callback, &payload(&session_id), Duration::from_millis(10)).await;
+        assert!(delivered);
+
+        let attempts = list_deliveries(&session_id).unwrap();
+        let _ = fs::remove_file(deliveries_path(&session_id).unwrap());
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].status, Some(503));
+        assert!(!attempts[0].delivered);
+        assert_eq!(attempts[1].attempt, 2);
+        assert!(attempts[1].delivered);
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(body["result"], "success");
+        assert_eq!(body["last_assistant_message"], "All checks passed");
+        assert_eq!(
+            requests[1].headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign_payload("ci-secret", &requests[1].body)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_gives_up_after_three_attempts() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let session_id = format!("test-delivery-{}", uuid::Uuid::new_v4());
+        let callback = SessionCallback::new(&server.uri(), None).unwrap();
+        let delivered =
+            deliver_with_delay(&callback, &payload(&session_id), Duration::from_millis(10)).await;
+        assert!(!delivered);
+
+        let attempts = list_deliveries(&session_id).unwrap();
+        let _ = fs::remove_file(deliveries_path(&session_id).unwrap());
+        assert_eq!(attempts.len(), 3);
+        assert!(attempts.iter().all(|attempt| !attempt.delivered));
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::process::Command;
 use std::sync::Arc;
 use std::time::Duration;
@@ -9,7 +10,12 @@ use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 use tracing::{info, warn};
 
-use crate::scheduler::{normalize_cron_expression, ScheduledJob, SchedulerError};
+use crate::recipe::validate_recipe::{error_summary, validate_recipe_content};
+use crate::scheduler::{
+    normalize_cron_expression, ConcurrencyPolicy, ScheduledJob, SchedulerError,
+};
+use crate::scheduler_gate::ExecutionConditions;
+use crate::scheduler_spec::{require_cron, ScheduleKind, ScheduleSpec};
 use crate::scheduler_trait::SchedulerTrait;
 use crate::session::storage::SessionMetadata;
 
@@ -20,6 +26,12 @@ const TEMPORAL_SERVICE_HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(5
 // to avoid conflicts with common services
 const DEFAULT_HTTP_PORTS: &[u16] = &[58080, 58081, 58082, 58083, 58084, 58085];
 
+fn concurrency_policy_unsupported() -> SchedulerError {
+    SchedulerError::SchedulerInternalError(
+        "Concurrency policies are not supported by the Temporal scheduler".to_string(),
+    )
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct JobRequest {
     action: String,
@@ -27,6 +39,8 @@ struct JobRequest {
     cron: Option<String>,
     recipe_path: Option<String>,
     execution_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -48,6 +62,8 @@ struct TemporalJobStatus {
     paused: bool,
     created_at: String,
     execution_mode: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -546,6 +562,49 @@ impl TemporalScheduler {
             job.id
         );
 
+        if !self.supports_concurrency_policy(job.concurrency_policy) {
+            return Err(concurrency_policy_unsupported());
+        }
+        require_cron(job.spec()?)?;
+        if job.prompt.is_some() {
+            return Err(SchedulerError::RecipeLoadError(
+                "TemporalScheduler only runs recipes, not prompts".to_string(),
+            ));
+        }
+        // The recipe may only exist where the Temporal service runs, so check it when it's here
+        if let Ok(content) = std::fs::read_to_string(&job.source) {
+            let issues =
+                validate_recipe_content(&content, std::path::Path::new(&job.source).parent());
+            if let Some(errors) = error_summary(&issues) {
+                return Err(SchedulerError::RecipeLoadError(format!(
+                    "Recipe {} is invalid: {}",
+                    job.source, errors
+                )));
+            }
+        }
+        if job.retry.is_some()
+            || job.on_failure.is_some()
+            || job.on_success.is_some()
+            || job.callback_url.is_some()
+        {
+            tracing::warn!(
+                "TemporalScheduler: retries and webhooks for job '{}' are not supported and will be ignored",
+                job.id
+            );
+        }
+        if !job.params.is_empty() {
+            tracing::warn!(
+                "TemporalScheduler: parameter values for job '{}' are not supported and will be ignored",
+                job.id
+            );
+        }
+        if job.conditions != ExecutionConditions::default() {
+            tracing::warn!(
+                "TemporalScheduler: execution conditions for job '{}' are not supported and will be ignored",
+                job.id
+            );
+        }
+
         // Normalize the cron expression to ensure it's 6-field format
         let normalized_cron = normalize_cron_expression(&job.cron);
         if normalized_cron != job.cron {
@@ -562,6 +621,7 @@ impl TemporalScheduler {
             cron: Some(normalized_cron.clone()),
             recipe_path: Some(job.source.clone()),
             execution_mode: job.execution_mode.clone(),
+            timezone: job.timezone.clone(),
         };
 
         let response = self.make_request(request).await?;
@@ -582,6 +642,7 @@ impl TemporalScheduler {
             cron: None,
             recipe_path: None,
             execution_mode: None,
+            timezone: None,
         };
 
         let response = self.make_request(request).await?;
@@ -594,13 +655,32 @@ impl TemporalScheduler {
                     ScheduledJob {
                         id: tj.id,
                         source: tj.recipe_path,
+                        prompt: None,
+                        working_dir: None,
+                        extensions: Vec::new(),
+                        params: HashMap::new(),
+                        kind: ScheduleKind::Cron,
                         cron: tj.cron,
+                        run_at: None,
+                        every: None,
+                        interval_anchor: None,
                         last_run: tj.last_run.and_then(|s| s.parse::<DateTime<Utc>>().ok()),
                         currently_running: tj.currently_running,
                         paused: tj.paused,
                         current_session_id: None, // Not provided by Temporal service
                         process_start_time: None, // Not provided by Temporal service
                         execution_mode: tj.execution_mode,
+                        timezone: tj.timezone,
+                        concurrency_policy: ConcurrencyPolicy::Skip,
+                        queued_run: false,
+                        skipped_runs: Vec::new(),
+                        retry: None,
+                        on_failure: None,
+                        on_success: None,
+                        callback_url: None,
+                        callback_secret: None,
+                        run_history: Vec::new(),
+                        conditions: ExecutionConditions::default(),
                     }
                 })
                 .collect();
@@ -617,6 +697,7 @@ impl TemporalScheduler {
             cron: None,
             recipe_path: None,
             execution_mode: None,
+            timezone: None,
         };
 
         let response = self.make_request(request).await?;
@@ -636,6 +717,7 @@ impl TemporalScheduler {
             cron: None,
             recipe_path: None,
             execution_mode: None,
+            timezone: None,
         };
 
         let response = self.make_request(request).await?;
@@ -655,6 +737,7 @@ impl TemporalScheduler {
             cron: None,
             recipe_path: None,
             execution_mode: None,
+            timezone: None,
         };
 
         let response = self.make_request(request).await?;
@@ -675,6 +758,7 @@ impl TemporalScheduler {
             cron: None,
             recipe_path: None,
             execution_mode: None,
+            timezone: None,
         };
 
         let response = self.make_request(request).await?;
@@ -752,6 +836,7 @@ impl TemporalScheduler {
         &self,
         sched_id: &str,
         new_cron: String,
+        timezone: Option<String>,
     ) -> Result<(), SchedulerError> {
         tracing::info!(
             "TemporalScheduler: update_schedule() called for job '{}' with cron '{}'",
@@ -775,6 +860,7 @@ impl TemporalScheduler {
             cron: Some(normalized_cron),
             recipe_path: None,
             execution_mode: None,
+            timezone: timezone.clone(),
         };
 
         let response = self.make_request(request).await?;
@@ -799,6 +885,7 @@ impl TemporalScheduler {
             cron: None,
             recipe_path: None,
             execution_mode: None,
+            timezone: None,
         };
 
         let response = self.make_request(request).await?;
@@ -825,6 +912,7 @@ impl TemporalScheduler {
                     cron: None,
                     recipe_path: None,
                     execution_mode: None,
+                    timezone: None,
                 };
 
                 match self.make_request(request).await {
@@ -907,6 +995,7 @@ impl TemporalScheduler {
                         cron: None,
                         recipe_path: None,
                         execution_mode: None,
+                        timezone: None,
                     };
 
                     if let Err(e) = self.make_request(request).await {
@@ -954,6 +1043,7 @@ impl TemporalScheduler {
             cron: None,
             recipe_path: None,
             execution_mode: None,
+            timezone: None,
         };
 
         let response = self.make_request(request).await?;
@@ -1219,9 +1309,40 @@ impl SchedulerTrait for TemporalScheduler {
     async fn update_schedule(
         &self,
         sched_id: &str,
-        new_cron: String,
+        spec: ScheduleSpec,
+        timezone: Option<String>,
+    ) -> Result<(), SchedulerError> {
+        self.update_schedule(sched_id, require_cron(spec)?, timezone)
+            .await
+    }
+
+    // The Temporal service skips runs that would overlap
+    fn supports_concurrency_policy(&self, policy: ConcurrencyPolicy) -> bool {
+        policy == ConcurrencyPolicy::Skip
+    }
+
+    async fn set_concurrency_policy(
+        &self,
+        _sched_id: &str,
+        policy: ConcurrencyPolicy,
+    ) -> Result<(), SchedulerError> {
+        if self.supports_concurrency_policy(policy) {
+            return Ok(());
+        }
+        Err(concurrency_policy_unsupported())
+    }
+
+    async fn set_execution_conditions(
+        &self,
+        _sched_id: &str,
+        conditions: ExecutionConditions,
     ) -> Result<(), SchedulerError> {
-        self.update_schedule(sched_id, new_cron).await
+        if conditions == ExecutionConditions::default() {
+            return Ok(());
+        }
+        Err(SchedulerError::SchedulerInternalError(
+            "Execution conditions are not supported by the Temporal scheduler".to_string(),
+        ))
     }
 
     async fn kill_running_job(&self, sched_id: &str) -> Result<(), SchedulerError> {
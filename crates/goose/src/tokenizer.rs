@@ -0,0 +1,322 @@
+//! Local token counting for when a count is needed before, or without, asking the provider.
+//!
+//! OpenAI models are counted exactly with their tiktoken encoding. Other providers don't publish
+//! their tokenizers, so their models are estimated with a heuristic that tracks BPE tokenizers
+//! closely on prose and code alike.
+
+use std::sync::{Arc, OnceLock};
+
+use rmcp::model::Tool;
+use tiktoken_rs::CoreBPE;
+
+use crate::message::{Message, MessageContent};
+
+/// Tokens added around every message for its role and separators
+const TOKENS_PER_MESSAGE: usize = 4;
+/// Tokens that prime every reply
+const REPLY_PRIMING_TOKENS: usize = 3;
+/// Tokens added around every tool definition
+const TOKENS_PER_TOOL: usize = 7;
+/// Tokens that close the tool definitions
+const TOOLS_END_TOKENS: usize = 12;
+/// Rough cost of an image, which is billed by size but not sized here
+const IMAGE_TOKENS: usize = 85;
+
+static CL100K: OnceLock<Option<Arc<CoreBPE>>> = OnceLock::new();
+static O200K: OnceLock<Option<Arc<CoreBPE>>> = OnceLock::new();
+
+/// How text is split into tokens for a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// GPT-4, GPT-3.5 and OpenAI embedding models
+    Cl100k,
+    /// GPT-4o, GPT-4.1, GPT-5 and the o-series reasoning models
+    O200k,
+    /// Any other model
+    Heuristic,
+}
+
+impl Encoding {
+    pub fn for_model(model: &str) -> Self {
+        // Providers prefix or namespace model names, as in "openai/gpt-4o" or "goose-gpt-4o"
+        let name = model.rsplit('/').next().unwrap_or(model);
+        let name = name.strip_prefix("goose-").unwrap_or(name);
+        let is_o_series = ["o1", "o3", "o4"]
+            .iter()
+            .any(|series| name == *series || name.starts_with(&format!("{}-", series)));
+        if is_o_series
+            || name.starts_with("gpt-4o")
+            || name.starts_with("gpt-4.1")
+            || name.starts_with("gpt-5")
+            || name.starts_with("chatgpt-4o")
+        {
+            Encoding::O200k
+        } else if name.starts_with("gpt-4")
+            || name.starts_with("gpt-3.5")
+            || name.starts_with("text-embedding-")
+        {
+            Encoding::Cl100k
+        } else {
+            Encoding::Heuristic
+        }
+    }
+
+    fn bpe(self) -> Option<Arc<CoreBPE>> {
+        match self {
+            Encoding::Cl100k => CL100K
+                .get_or_init(|| tiktoken_rs::cl100k_base().ok().map(Arc::new))
+                .clone(),
+            Encoding::O200k => O200K
+                .get_or_init(|| tiktoken_rs::o200k_base().ok().map(Arc::new))
+                .clone(),
+            Encoding::Heuristic => None,
+        }
+    }
+
+    /// Count the tokens in `text`
+    pub fn count(self, text: &str) -> usize {
+        match self.bpe() {
+            Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+            None => heuristic_count(text),
+        }
+    }
+}
+
+/// Estimate tokens without a vocabulary. Runs of ASCII letters and digits are split into pieces
+/// of about four characters, while punctuation and non-ASCII characters mostly get a token each,
+/// which keeps code and non-English text from being undercounted.
+fn heuristic_count(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word = 0;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word += 1;
+            continue;
+        }
+        tokens += word.div_ceil(4);
+        word = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word.div_ceil(4)
+}
+
+/// Count the tokens in `text` for `model`
+pub fn count_text(text: &str, model: &str) -> usize {
+    Encoding::for_model(model).count(text)
+}
+
+/// Count the tokens `messages` take up in a request to `model`, including the overhead each
+/// message adds and the tool calls and results they carry
+pub fn count_messages(messages: &[Message], model: &str) -> usize {
+    let encoding = Encoding::for_model(model);
+    let content_tokens: usize = messages
+        .iter()
+        .map(|message| message_tokens(encoding, message))
+        .sum();
+    content_tokens + REPLY_PRIMING_TOKENS
+}
+
+/// Count the tokens one message takes up in a request to `model`, including its overhead
+pub fn count_message(message: &Message, model: &str) -> usize {
+    message_tokens(Encoding::for_model(model), message)
+}
+
+fn message_tokens(encoding: Encoding, message: &Message) -> usize {
+    TOKENS_PER_MESSAGE
+        + message
+            .content
+            .iter()
+            .map(|content| count_content(encoding, content))
+            .sum::<usize>()
+}
+
+fn count_content(encoding: Encoding, content: &MessageContent) -> usize {
+    match content {
+        MessageContent::Text(text) => encoding.count(&text.text),
+        // Callers that know the session resolve attachments before counting, so one left here
+        // can't be read and is counted like an image
+        MessageContent::Image(_) | MessageContent::Attachment(_) => IMAGE_TOKENS,
+        MessageContent::ToolRequest(request) => match &request.tool_call {
+            Ok(call) => {
+                encoding.count(&request.id)
+                    + encoding.count(&call.name)
+                    + encoding.count(&call.arguments.to_string())
+            }
+            Err(_) => encoding.count(&request.id),
+        },
+        MessageContent::FrontendToolRequest(request) => match &request.tool_call {
+            Ok(call) => {
+                encoding.count(&request.id)
+                    + encoding.count(&call.name)
+                    + encoding.count(&call.arguments.to_string())
+            }
+            Err(_) => encoding.count(&request.id),
+        },
+        MessageContent::ToolResponse(response) => match &response.tool_result {
+            Ok(contents) => {
+                encoding.count(&response.id)
+                    + contents
+                        .iter()
+                        .map(|content| match content.as_text() {
+                            Some(text) => encoding.count(&text.text),
+                            None => IMAGE_TOKENS,
+                        })
+                        .sum::<usize>()
+            }
+            Err(e) => encoding.count(&response.id) + encoding.count(&e.to_string()),
+        },
+        MessageContent::Thinking(thinking) => encoding.count(&thinking.thinking),
+        MessageContent::ServerToolUse(tool_use) => {
+            encoding.count(&tool_use.name)
+                + encoding.count(&tool_use.input.to_string())
+                + tool_use
+                    .result
+                    .as_ref()
+                    .map_or(0, |result| encoding.count(&result.to_string()))
+        }
+        MessageContent::Citations(_) => 0,
+        MessageContent::RedactedThinking(_)
+        | MessageContent::ToolConfirmationRequest(_)
+        | MessageContent::ContextLengthExceeded(_)
+        | MessageContent::SummarizationRequested(_) => 0,
+    }
+}
+
+/// Count the tokens the definitions of `tools` take up in a request to `model`
+pub fn count_tools(tools: &[Tool], model: &str) -> usize {
+    if tools.is_empty() {
+        return 0;
+    }
+    let encoding = Encoding::for_model(model);
+    let definitions: usize = tools
+        .iter()
+        .map(|tool| {
+            let description = tool.description.as_deref().unwrap_or_default();
+            let schema = serde_json::to_string(&tool.input_schema).unwrap_or_default();
+            TOKENS_PER_TOOL
+                + encoding.count(&tool.name)
+                + encoding.count(description)
+                + encoding.count(&schema)
+        })
+        .sum();
+    definitions + TOOLS_END_TOKENS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_core::tool::ToolCall;
+    use rmcp::object;
+    use serde_json::json;
+
+    const RUST_SNIPPET: &str = r#"
+pub fn fibonacci(n: u64) -> u64 {
+    let (mut a, mut b) = (0u64, 1u64);
+    for _ in 0..n {
+        (a, b) = (b, a.wrapping_add(b));
+    }
+    a
+}
+
+#[test]
+fn test_fibonacci() {
+    assert_eq!(fibonacci(10), 55);
+}
+"#;
+
+    #[test]
+    fn test_encoding_for_model() {
+        assert_eq!(Encoding::for_model("gpt-4o-mini"), Encoding::O200k);
+        assert_eq!(Encoding::for_model("openai/gpt-4.1"), Encoding::O200k);
+        assert_eq!(Encoding::for_model("o3-mini-high"), Encoding::O200k);
+        assert_eq!(Encoding::for_model("goose-o1"), Encoding::O200k);
+        assert_eq!(Encoding::for_model("gpt-4-turbo"), Encoding::Cl100k);
+        assert_eq!(Encoding::for_model("gpt-3.5-turbo"), Encoding::Cl100k);
+        assert_eq!(
+            Encoding::for_model("claude-sonnet-4-20250514"),
+            Encoding::Heuristic
+        );
+        assert_eq!(Encoding::for_model("ollama-3"), Encoding::Heuristic);
+    }
+
+    #[test]
+    fn test_heuristic_tracks_tokenizer_on_long_code() {
+        let text = format!(
+            "Here is the implementation you asked for:\n```rust{}```\n",
+            RUST_SNIPPET
+        )
+        .repeat(200);
+
+        let exact = Encoding::O200k.count(&text);
+        let estimate = Encoding::Heuristic.count(&text);
+        let ratio = estimate as f64 / exact as f64;
+        assert!(
+            (0.7..=1.4).contains(&ratio),
+            "heuristic {} too far from tokenizer {}",
+            estimate,
+            exact
+        );
+    }
+
+    #[test]
+    fn test_heuristic_counts_punctuation_and_words() {
+        assert_eq!(heuristic_count(""), 0);
+        assert_eq!(heuristic_count("hello"), 2);
+        assert_eq!(heuristic_count("a.b"), 3);
+        assert_eq!(heuristic_count("   "), 0);
+        assert_eq!(heuristic_count("日本"), 2);
+    }
+
+    #[test]
+    fn test_count_messages_includes_overhead_and_tool_calls() {
+        let model = "gpt-4o";
+        assert_eq!(count_messages(&[], model), REPLY_PRIMING_TOKENS);
+
+        let text = Message::user().with_text("Fix the failing test");
+        let with_text = count_messages(std::slice::from_ref(&text), model);
+        assert_eq!(
+            with_text,
+            TOKENS_PER_MESSAGE + count_text("Fix the failing test", model) + REPLY_PRIMING_TOKENS
+        );
+
+        let call = Message::assistant().with_tool_request(
+            "call_1",
+            Ok(ToolCall::new(
+                "developer__text_editor",
+                json!({"command": "write", "path": "src/lib.rs", "file_text": RUST_SNIPPET}),
+            )),
+        );
+        let with_call = count_messages(&[text, call], model);
+        assert!(with_call > with_text + count_text(RUST_SNIPPET, model));
+    }
+
+    #[test]
+    fn test_count_tools_grows_with_schema() {
+        let small = Tool::new(
+            "ping",
+            "Check the server is up",
+            object!({"type": "object", "properties": {}}),
+        );
+        let large = Tool::new(
+            "edit",
+            "Edit a file",
+            object!({
+                "type": "object",
+                "properties": {
+                    "path": {"type": "string", "description": "Absolute path to the file"},
+                    "old_str": {"type": "string", "description": "Text to replace"},
+                    "new_str": {"type": "string", "description": "Replacement text"}
+                },
+                "required": ["path", "old_str", "new_str"]
+            }),
+        );
+
+        assert_eq!(count_tools(&[], "gpt-4o"), 0);
+        let small_count = count_tools(std::slice::from_ref(&small), "gpt-4o");
+        let large_count = count_tools(std::slice::from_ref(&large), "gpt-4o");
+        assert!(large_count > small_count);
+        assert!(count_tools(&[small, large], "claude-sonnet-4") > 0);
+    }
+}
@@ -146,6 +146,7 @@ async fn run_truncate_test(
             Ok(AgentEvent::HistoryReplaced(_)) => {
                 // Handle history replacement events if needed
             }
+            Ok(AgentEvent::TurnTiming(_)) => {}
             Err(e) => {
                 println!("Error: {:?}", e);
                 return Err(e);
@@ -354,7 +355,7 @@ mod schedule_tool_tests {
     use async_trait::async_trait;
     use chrono::{DateTime, Utc};
     use goose::agents::platform_tools::PLATFORM_MANAGE_SCHEDULE_TOOL_NAME;
-    use goose::scheduler::{ScheduledJob, SchedulerError};
+    use goose::scheduler::{ConcurrencyPolicy, ScheduledJob, SchedulerError};
     use goose::scheduler_trait::SchedulerTrait;
     use goose::session::storage::SessionMetadata;
     use std::sync::Arc;
@@ -418,7 +419,24 @@ mod schedule_tool_tests {
         async fn update_schedule(
             &self,
             _sched_id: &str,
-            _new_cron: String,
+            _spec: goose::scheduler_spec::ScheduleSpec,
+            _timezone: Option<String>,
+        ) -> Result<(), SchedulerError> {
+            Ok(())
+        }
+
+        async fn set_concurrency_policy(
+            &self,
+            _sched_id: &str,
+            _policy: ConcurrencyPolicy,
+        ) -> Result<(), SchedulerError> {
+            Ok(())
+        }
+
+        async fn set_execution_conditions(
+            &self,
+            _sched_id: &str,
+            _conditions: goose::scheduler_gate::ExecutionConditions,
         ) -> Result<(), SchedulerError> {
             Ok(())
         }
@@ -599,6 +617,7 @@ mod final_output_tool_tests {
                 },
                 "required": ["result"]
             })),
+            max_validation_retries: None,
         };
         agent.add_final_output_tool(response).await;
 
@@ -719,6 +738,7 @@ mod final_output_tool_tests {
                 },
                 "required": ["result"]
             })),
+            max_validation_retries: None,
         };
         agent.add_final_output_tool(response).await;
 
@@ -853,6 +873,11 @@ mod retry_tests {
             execution_mode: None,
             max_turns: None,
             retry_config: Some(retry_config),
+            allowed_extensions: None,
+            persona: None,
+            goose_mode: None,
+            permissions_path: None,
+            recorded_tool_results: None,
         };
 
         let initial_messages = vec![Message::user().with_text("Complete this task")];
@@ -1020,6 +1045,11 @@ mod max_turns_tests {
             execution_mode: None,
             max_turns: Some(1),
             retry_config: None,
+            allowed_extensions: None,
+            persona: None,
+            goose_mode: None,
+            permissions_path: None,
+            recorded_tool_results: None,
         };
         let messages = vec![Message::user().with_text("Hello")];
 
@@ -1038,6 +1068,7 @@ mod max_turns_tests {
                             goose::permission::PermissionConfirmation {
                                 principal_type: goose::permission::permission_confirmation::PrincipalType::Tool,
                                 permission: goose::permission::Permission::AllowOnce,
+                                persist: true,
                             }
                         ).await;
                     }
@@ -1046,6 +1077,7 @@ mod max_turns_tests {
                 Ok(AgentEvent::McpNotification(_)) => {}
                 Ok(AgentEvent::ModelChange { .. }) => {}
                 Ok(AgentEvent::HistoryReplaced(_)) => {}
+                Ok(AgentEvent::TurnTiming(_)) => {}
                 Err(e) => {
                     return Err(e);
                 }
@@ -10,7 +10,9 @@ use tempfile::TempDir;
 use tokio::sync::Mutex;
 
 use goose::agents::Agent;
-use goose::scheduler::{ScheduledJob, SchedulerError};
+use goose::scheduler::{ConcurrencyPolicy, ScheduledJob, SchedulerError};
+use goose::scheduler_gate::ExecutionConditions;
+use goose::scheduler_spec::{ScheduleKind, ScheduleSpec};
 use goose::scheduler_trait::SchedulerTrait;
 use goose::session::storage::SessionMetadata;
 
@@ -225,7 +227,8 @@ impl SchedulerTrait for ConfigurableMockScheduler {
     async fn update_schedule(
         &self,
         sched_id: &str,
-        _new_cron: String,
+        _spec: ScheduleSpec,
+        _timezone: Option<String>,
     ) -> Result<(), SchedulerError> {
         self.log_call("update_schedule").await;
 
@@ -244,6 +247,50 @@ impl SchedulerTrait for ConfigurableMockScheduler {
         }
     }
 
+    async fn set_concurrency_policy(
+        &self,
+        sched_id: &str,
+        _policy: ConcurrencyPolicy,
+    ) -> Result<(), SchedulerError> {
+        self.log_call("set_concurrency_policy").await;
+
+        match self.get_behavior("set_concurrency_policy").await {
+            MockBehavior::Success => {
+                let jobs = self.jobs.lock().await;
+                if jobs.contains_key(sched_id) {
+                    Ok(())
+                } else {
+                    Err(SchedulerError::JobNotFound(sched_id.to_string()))
+                }
+            }
+            MockBehavior::NotFound(job_id) => Err(SchedulerError::JobNotFound(job_id)),
+            MockBehavior::InternalError(msg) => Err(SchedulerError::SchedulerInternalError(msg)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_execution_conditions(
+        &self,
+        sched_id: &str,
+        _conditions: ExecutionConditions,
+    ) -> Result<(), SchedulerError> {
+        self.log_call("set_execution_conditions").await;
+
+        match self.get_behavior("set_execution_conditions").await {
+            MockBehavior::Success => {
+                let jobs = self.jobs.lock().await;
+                if jobs.contains_key(sched_id) {
+                    Ok(())
+                } else {
+                    Err(SchedulerError::JobNotFound(sched_id.to_string()))
+                }
+            }
+            MockBehavior::NotFound(job_id) => Err(SchedulerError::JobNotFound(job_id)),
+            MockBehavior::InternalError(msg) => Err(SchedulerError::SchedulerInternalError(msg)),
+            _ => Ok(()),
+        }
+    }
+
     async fn kill_running_job(&self, sched_id: &str) -> Result<(), SchedulerError> {
         self.log_call("kill_running_job").await;
 
@@ -356,13 +403,32 @@ impl ScheduleToolTestBuilder {
         let job = ScheduledJob {
             id: job_id.to_string(),
             source: "/tmp/test.json".to_string(),
+            prompt: None,
+            working_dir: None,
+            extensions: Vec::new(),
+            params: HashMap::new(),
+            kind: ScheduleKind::Cron,
             cron: cron.to_string(),
+            run_at: None,
+            every: None,
+            interval_anchor: None,
             last_run: None,
             currently_running: false,
             paused: false,
             current_session_id: None,
             process_start_time: None,
             execution_mode: Some("background".to_string()),
+            timezone: None,
+            concurrency_policy: ConcurrencyPolicy::Skip,
+            queued_run: false,
+            skipped_runs: Vec::new(),
+            retry: None,
+            on_failure: None,
+            on_success: None,
+            callback_url: None,
+            callback_secret: None,
+            run_history: Vec::new(),
+            conditions: ExecutionConditions::default(),
         };
         {
             let mut jobs = self.scheduler.jobs.lock().await;
@@ -401,6 +467,7 @@ impl ScheduleToolTestBuilder {
 // Helper function to create test session metadata
 pub fn create_test_session_metadata(message_count: usize, working_dir: &str) -> SessionMetadata {
     SessionMetadata {
+        format_version: goose::session::storage::SESSION_FORMAT_VERSION,
         message_count,
         working_dir: PathBuf::from(working_dir),
         description: "Test session".to_string(),
@@ -412,5 +479,15 @@ pub fn create_test_session_metadata(message_count: usize, working_dir: &str) ->
         accumulated_total_tokens: Some(100),
         accumulated_input_tokens: Some(50),
         accumulated_output_tokens: Some(50),
+        accumulated_web_search_requests: None,
+        provider_config: None,
+        model_changes: Vec::new(),
+        parent_session_id: None,
+        origin: goose::session::SessionOrigin::Schedule,
+        persona: None,
+        schema_validation: None,
+        replay: None,
+        batch_id: None,
+        tool_usage: Default::default(),
     }
 }
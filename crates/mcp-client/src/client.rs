@@ -3,24 +3,29 @@ use mcp_core::protocol::{
     ListToolsResult, ReadResourceResult, ServerCapabilities, METHOD_NOT_FOUND,
 };
 use rmcp::model::{
-    GetPromptResult, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
-    JsonRpcResponse, JsonRpcVersion2_0, Notification, NumberOrString, Request, RequestId,
-    ServerNotification,
+    ErrorCode, ErrorData, GetPromptResult, JsonRpcError, JsonRpcMessage, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, JsonRpcVersion2_0, LoggingLevel, Notification, NumberOrString,
+    Prompt, Request, RequestId, Resource, ServerNotification, Tool,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashSet;
 use std::sync::{
     atomic::{AtomicU64, Ordering},
     Arc,
 };
+use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::{mpsc, Mutex};
-use tower::{timeout::TimeoutLayer, Layer, Service, ServiceExt};
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tower::{Service, ServiceExt};
 
 use crate::{McpService, TransportHandle};
 
 pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
 
+/// Most pages the `list_all_*` helpers fetch before giving up on the rest of a listing
+pub const MAX_LIST_PAGES: usize = 50;
+
 /// Error type for MCP client operations.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -45,9 +50,17 @@ pub enum Error {
     #[error("Request timed out")]
     Timeout(#[from] tower::timeout::error::Elapsed),
 
+    #[error(
+        "Request '{method}' timed out after {timeout:?}; the server may still be working on it"
+    )]
+    RequestTimeout { method: String, timeout: Duration },
+
     #[error("Error from mcp-server: {0}")]
     ServerBoxError(BoxError),
 
+    #[error("Server does not support '{0}'")]
+    UnsupportedCapability(String),
+
     #[error("Call to '{server}' failed for '{method}'. {source}")]
     McpServerError {
         method: String,
@@ -72,7 +85,24 @@ pub struct ClientInfo {
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct ClientCapabilities {
-    // Add fields as needed. For now, empty capabilities are fine.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<RootsCapability>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RootsCapability {
+    /// Whether the client sends `notifications/roots/list_changed`
+    pub list_changed: bool,
+}
+
+/// A location the server may operate on, returned from `roots/list`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Root {
+    /// A `file://` URI
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -103,11 +133,137 @@ pub trait McpClientTrait: Send + Sync {
 
     async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult, Error>;
 
+    /// Call a tool, asking the server to tag its `notifications/progress` with `progress_token`
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        _progress_token: &str,
+    ) -> Result<CallToolResult, Error> {
+        self.call_tool(name, arguments).await
+    }
+
     async fn list_prompts(&self, next_cursor: Option<String>) -> Result<ListPromptsResult, Error>;
 
     async fn get_prompt(&self, name: &str, arguments: Value) -> Result<GetPromptResult, Error>;
 
     async fn subscribe(&self) -> mpsc::Receiver<ServerNotification>;
+
+    /// Ask the server to send `notifications/resources/updated` when the resource changes
+    async fn subscribe_resource(&self, _uri: &str) -> Result<(), Error> {
+        Err(Error::UnsupportedCapability(
+            "resources/subscribe".to_string(),
+        ))
+    }
+
+    async fn unsubscribe_resource(&self, _uri: &str) -> Result<(), Error> {
+        Err(Error::UnsupportedCapability(
+            "resources/subscribe".to_string(),
+        ))
+    }
+
+    /// Replace the roots offered to the server, notifying it if they changed
+    async fn set_roots(&self, _roots: Vec<Root>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Check that the server is still answering requests
+    async fn ping(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Ask the server to only send log messages at `level` or above
+    async fn set_logging_level(&self, _level: LoggingLevel) -> Result<(), Error> {
+        Err(Error::UnsupportedCapability("logging".to_string()))
+    }
+
+    /// Every tool the server offers, following `next_cursor` across pages
+    async fn list_all_tools(&self) -> Result<Vec<Tool>, Error> {
+        let mut tools = Vec::new();
+        let mut pages = PageCursor::new("tools/list");
+        let mut cursor = None;
+        loop {
+            let page = self.list_tools(cursor).await?;
+            tools.extend(page.tools);
+            cursor = pages.advance(page.next_cursor);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Every resource the server offers, following `next_cursor` across pages
+    async fn list_all_resources(&self) -> Result<Vec<Resource>, Error> {
+        let mut resources = Vec::new();
+        let mut pages = PageCursor::new("resources/list");
+        let mut cursor = None;
+        loop {
+            let page = self.list_resources(cursor).await?;
+            resources.extend(page.resources);
+            cursor = pages.advance(page.next_cursor);
+            if cursor.is_none() {
+                return Ok(resources);
+            }
+        }
+    }
+
+    /// Every prompt the server offers, following `next_cursor` across pages
+    async fn list_all_prompts(&self) -> Result<Vec<Prompt>, Error> {
+        let mut prompts = Vec::new();
+        let mut pages = PageCursor::new("prompts/list");
+        let mut cursor = None;
+        loop {
+            let page = self.list_prompts(cursor).await?;
+            prompts.extend(page.prompts);
+            cursor = pages.advance(page.next_cursor);
+            if cursor.is_none() {
+                return Ok(prompts);
+            }
+        }
+    }
+}
+
+/// Cursor bookkeeping for the `list_all_*` helpers
+///
+/// Stops the listing when a server hands back a cursor it already gave, or after
+/// `MAX_LIST_PAGES` pages, so a misbehaving server cannot keep the client looping forever.
+struct PageCursor {
+    method: &'static str,
+    pages: usize,
+    seen: HashSet<String>,
+}
+
+impl PageCursor {
+    fn new(method: &'static str) -> Self {
+        Self {
+            method,
+            pages: 0,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// The cursor to request next, or `None` when the listing is finished
+    fn advance(&mut self, next_cursor: Option<String>) -> Option<String> {
+        self.pages += 1;
+        let cursor = next_cursor?;
+        if self.pages >= MAX_LIST_PAGES {
+            tracing::warn!(
+                "Stopping {} after {} pages, the server keeps returning a cursor",
+                self.method,
+                MAX_LIST_PAGES
+            );
+            return None;
+        }
+        if !self.seen.insert(cursor.clone()) {
+            tracing::warn!(
+                "Stopping {}, the server returned cursor '{}' twice",
+                self.method,
+                cursor
+            );
+            return None;
+        }
+        Some(cursor)
+    }
 }
 
 /// The MCP client is the interface for MCP operations.
@@ -115,23 +271,33 @@ pub struct McpClient<T>
 where
     T: TransportHandle + Send + Sync + 'static,
 {
-    service: Mutex<tower::timeout::Timeout<McpService<T>>>,
+    /// Cloned for each request so that requests to the same server can be in flight together
+    service: McpService<T>,
+    /// Deadline for each request, after which it is cancelled
+    timeout: Duration,
     next_id_counter: AtomicU64, // Added for atomic ID generation
     server_capabilities: Option<ServerCapabilities>,
     server_info: Option<Implementation>,
     notification_subscribers: Arc<Mutex<Vec<mpsc::Sender<ServerNotification>>>>,
+    /// Notifications dropped because a subscriber was not keeping up
+    dropped_notifications: Arc<AtomicU64>,
+    roots: Arc<RwLock<Vec<Root>>>,
 }
 
 impl<T> McpClient<T>
 where
     T: TransportHandle + Send + Sync + 'static,
 {
-    pub async fn connect(transport: T, timeout: std::time::Duration) -> Result<Self, Error> {
+    pub async fn connect(transport: T, timeout: Duration) -> Result<Self, Error> {
         let service = McpService::new(transport.clone());
         let service_ptr = service.clone();
         let notification_subscribers =
             Arc::new(Mutex::new(Vec::<mpsc::Sender<ServerNotification>>::new()));
         let subscribers_ptr = notification_subscribers.clone();
+        let dropped_notifications = Arc::new(AtomicU64::new(0));
+        let dropped_ptr = dropped_notifications.clone();
+        let roots = Arc::new(RwLock::new(Vec::new()));
+        let roots_ptr = roots.clone();
 
         tokio::spawn(async move {
             loop {
@@ -149,14 +315,39 @@ where
                             }) => {
                                 service_ptr.respond(&id.to_string(), Ok(message)).await;
                             }
+                            JsonRpcMessage::Request(JsonRpcRequest { id, request, .. }) => {
+                                let response =
+                                    respond_to_server_request(&roots_ptr, id, &request.method)
+                                        .await;
+                                if let Err(e) = transport.send(response).await {
+                                    tracing::warn!("Failed to respond to server request: {}", e);
+                                }
+                            }
                             JsonRpcMessage::Notification(JsonRpcNotification {
                                 notification,
                                 ..
                             }) => {
                                 let mut subs = subscribers_ptr.lock().await;
                                 if let Some(server_notification) = notification.into() {
+                                    // A subscriber that falls behind loses notifications rather
+                                    // than holding up the responses read by this loop
                                     subs.retain(|sub| {
-                                        sub.try_send(server_notification.clone()).is_ok()
+                                        match sub.try_send(server_notification.clone()) {
+                                            Ok(()) => true,
+                                            Err(mpsc::error::TrySendError::Full(_)) => {
+                                                let dropped = dropped_ptr
+                                                    .fetch_add(1, Ordering::Relaxed)
+                                                    + 1;
+                                                if dropped == 1 || dropped % 100 == 0 {
+                                                    tracing::warn!(
+                                                        dropped,
+                                                        "Notification subscriber is full, dropping notifications"
+                                                    );
+                                                }
+                                                true
+                                            }
+                                            Err(mpsc::error::TrySendError::Closed(_)) => false,
+                                        }
                                     });
                                 }
                             }
@@ -177,24 +368,28 @@ where
             }
         });
 
-        let middleware = TimeoutLayer::new(timeout);
-
         Ok(Self {
-            service: Mutex::new(middleware.layer(service)),
+            service,
+            timeout,
             next_id_counter: AtomicU64::new(1),
             server_capabilities: None,
             server_info: None,
             notification_subscribers,
+            dropped_notifications,
+            roots,
         })
     }
 
+    /// Number of notifications dropped so far because a subscriber was not keeping up
+    pub fn dropped_notifications(&self) -> u64 {
+        self.dropped_notifications.load(Ordering::Relaxed)
+    }
+
     /// Send a JSON-RPC request and check we don't get an error response.
     async fn send_request<R>(&self, method: &str, params: Value) -> Result<R, Error>
     where
         R: for<'de> Deserialize<'de>,
     {
-        let mut service = self.service.lock().await;
-        service.ready().await.map_err(|_| Error::NotReady)?;
         let id_num = self.next_id_counter.fetch_add(1, Ordering::SeqCst);
         let id = RequestId::Number(id_num as u32);
 
@@ -205,7 +400,7 @@ where
 
         let request = JsonRpcMessage::Request(JsonRpcRequest {
             jsonrpc: JsonRpcVersion2_0,
-            id,
+            id: id.clone(),
             request: Request {
                 method: method.to_string(),
                 params: params.as_object().unwrap().clone(),
@@ -213,19 +408,33 @@ where
             },
         });
 
-        let response_msg = service
-            .call(request)
-            .await
-            .map_err(|e| Error::McpServerError {
-                server: self
-                    .server_info
-                    .as_ref()
-                    .map(|s| s.name.clone())
-                    .unwrap_or("".to_string()),
-                method: method.to_string(),
-                // we don't need include params because it can be really large
-                source: Box::<Error>::new(e.into()),
-            })?;
+        let mut service = self.service.clone();
+        service.ready().await.map_err(|_| Error::NotReady)?;
+        let response = service.call(request);
+        let mut in_flight = InFlightRequest::new(service, id);
+
+        let response_msg = match tokio::time::timeout(self.timeout, response).await {
+            Ok(response) => {
+                in_flight.complete();
+                response.map_err(|e| Error::McpServerError {
+                    server: self
+                        .server_info
+                        .as_ref()
+                        .map(|s| s.name.clone())
+                        .unwrap_or("".to_string()),
+                    method: method.to_string(),
+                    // we don't need include params because it can be really large
+                    source: Box::<Error>::new(e.into()),
+                })?
+            }
+            Err(_) => {
+                in_flight.reason = "timed out";
+                return Err(Error::RequestTimeout {
+                    method: method.to_string(),
+                    timeout: self.timeout,
+                });
+            }
+        };
 
         match response_msg {
             JsonRpcMessage::Response(JsonRpcResponse { id, result, .. }) => {
@@ -261,7 +470,7 @@ where
 
     /// Send a JSON-RPC notification.
     async fn send_notification(&self, method: &str, params: Value) -> Result<(), Error> {
-        let mut service = self.service.lock().await;
+        let mut service = self.service.clone();
         service.ready().await.map_err(|_| Error::NotReady)?;
 
         let notification = JsonRpcMessage::Notification(JsonRpcNotification {
@@ -294,6 +503,109 @@ where
     fn completed_initialization(&self) -> bool {
         self.server_capabilities.is_some()
     }
+
+    // Check that the server advertised `resources.subscribe` during initialization
+    fn check_resource_subscriptions(&self) -> Result<(), Error> {
+        let capabilities = self
+            .server_capabilities
+            .as_ref()
+            .ok_or(Error::NotInitialized)?;
+        let supported = capabilities
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.subscribe)
+            .unwrap_or(false);
+        if !supported {
+            return Err(Error::UnsupportedCapability(
+                "resources/subscribe".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A request awaiting its response; the server is told to cancel it if it is abandoned
+///
+/// Dropping the guard before `complete` — on timeout, or when the caller stops waiting because
+/// the reply was cancelled — sends `notifications/cancelled` for the request.
+struct InFlightRequest<T>
+where
+    T: TransportHandle + Send + Sync + 'static,
+{
+    service: McpService<T>,
+    id: RequestId,
+    reason: &'static str,
+    completed: bool,
+}
+
+impl<T> InFlightRequest<T>
+where
+    T: TransportHandle + Send + Sync + 'static,
+{
+    fn new(service: McpService<T>, id: RequestId) -> Self {
+        Self {
+            service,
+            id,
+            reason: "cancelled by the client",
+            completed: false,
+        }
+    }
+
+    fn complete(&mut self) {
+        self.completed = true;
+    }
+}
+
+impl<T> Drop for InFlightRequest<T>
+where
+    T: TransportHandle + Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let service = self.service.clone();
+        let id = self.id.clone();
+        let reason = self.reason;
+        runtime.spawn(async move {
+            if let Err(e) = service.cancel_request(id, reason).await {
+                tracing::debug!("Failed to cancel request: {}", e);
+            }
+        });
+    }
+}
+
+/// Answer a request the server sent to the client
+async fn respond_to_server_request(
+    roots: &RwLock<Vec<Root>>,
+    id: RequestId,
+    method: &str,
+) -> JsonRpcMessage {
+    let result = match method {
+        "roots/list" => json!({ "roots": *roots.read().await }),
+        "ping" => json!({}),
+        _ => {
+            return JsonRpcMessage::Error(JsonRpcError {
+                jsonrpc: JsonRpcVersion2_0,
+                id,
+                error: ErrorData {
+                    code: ErrorCode(METHOD_NOT_FOUND),
+                    message: format!("Method not found: {}", method).into(),
+                    data: None,
+                },
+            })
+        }
+    };
+
+    JsonRpcMessage::Response(JsonRpcResponse {
+        jsonrpc: JsonRpcVersion2_0,
+        id,
+        result: result.as_object().cloned().unwrap_or_default(),
+    })
 }
 
 #[async_trait::async_trait]
@@ -413,6 +725,30 @@ where
         self.send_request("tools/call", params).await
     }
 
+    async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress_token: &str,
+    ) -> Result<CallToolResult, Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        if self.server_capabilities.as_ref().unwrap().tools.is_none() {
+            return Err(Error::RpcError {
+                code: METHOD_NOT_FOUND,
+                message: "Server does not support 'tools' capability".to_string(),
+            });
+        }
+
+        let params = serde_json::json!({
+            "name": name,
+            "arguments": arguments,
+            "_meta": { "progressToken": progress_token },
+        });
+        self.send_request("tools/call", params).await
+    }
+
     async fn list_prompts(&self, next_cursor: Option<String>) -> Result<ListPromptsResult, Error> {
         if !self.completed_initialization() {
             return Err(Error::NotInitialized);
@@ -456,4 +792,490 @@ where
         self.notification_subscribers.lock().await.push(tx);
         rx
     }
+
+    async fn subscribe_resource(&self, uri: &str) -> Result<(), Error> {
+        self.check_resource_subscriptions()?;
+
+        // Updates arrive as `notifications/resources/updated` through `subscribe`
+        let params = serde_json::json!({ "uri": uri });
+        let _: Value = self.send_request("resources/subscribe", params).await?;
+        Ok(())
+    }
+
+    async fn unsubscribe_resource(&self, uri: &str) -> Result<(), Error> {
+        self.check_resource_subscriptions()?;
+
+        let params = serde_json::json!({ "uri": uri });
+        let _: Value = self.send_request("resources/unsubscribe", params).await?;
+        Ok(())
+    }
+
+    async fn set_roots(&self, roots: Vec<Root>) -> Result<(), Error> {
+        {
+            let mut current = self.roots.write().await;
+            if *current == roots {
+                return Ok(());
+            }
+            *current = roots;
+        }
+
+        // Before initialization the server has not asked for roots yet
+        if self.completed_initialization() {
+            self.send_notification("notifications/roots/list_changed", serde_json::json!({}))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        if !self.completed_initialization() {
+            return Err(Error::NotInitialized);
+        }
+        let _: Value = self.send_request("ping", serde_json::json!({})).await?;
+        Ok(())
+    }
+
+    async fn set_logging_level(&self, level: LoggingLevel) -> Result<(), Error> {
+        let capabilities = self
+            .server_capabilities
+            .as_ref()
+            .ok_or(Error::NotInitialized)?;
+        if capabilities.logging.is_none() {
+            return Err(Error::UnsupportedCapability("logging".to_string()));
+        }
+
+        let params = serde_json::json!({ "level": level });
+        let _: Value = self.send_request("logging/setLevel", params).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{Error as TransportError, TransportMessageRecv};
+    use rmcp::model::JsonObject;
+
+    /// In-process server: answers `initialize`, never answers anything else, and lets the test
+    /// inject server messages
+    #[derive(Clone)]
+    struct MockTransport {
+        incoming_tx: mpsc::Sender<TransportMessageRecv>,
+        incoming_rx: Arc<Mutex<mpsc::Receiver<TransportMessageRecv>>>,
+        sent_tx: mpsc::UnboundedSender<JsonRpcMessage>,
+    }
+
+    impl MockTransport {
+        fn new() -> (Self, mpsc::UnboundedReceiver<JsonRpcMessage>) {
+            let (incoming_tx, incoming_rx) = mpsc::channel(16);
+            let (sent_tx, sent_rx) = mpsc::unbounded_channel();
+            let transport = Self {
+                incoming_tx,
+                incoming_rx: Arc::new(Mutex::new(incoming_rx)),
+                sent_tx,
+            };
+            (transport, sent_rx)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TransportHandle for MockTransport {
+        async fn send(&self, message: JsonRpcMessage) -> Result<(), TransportError> {
+            if let JsonRpcMessage::Request(JsonRpcRequest { id, request, .. }) = &message {
+                if request.method == "initialize" {
+                    let result = json!({
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": { "tools": {}, "logging": {} },
+                        "serverInfo": { "name": "mock", "version": "1.0.0" }
+                    });
+                    let response = JsonRpcMessage::Response(JsonRpcResponse {
+                        jsonrpc: JsonRpcVersion2_0,
+                        id: id.clone(),
+                        result: result.as_object().cloned().unwrap(),
+                    });
+                    let _ = self.incoming_tx.send(response).await;
+                }
+            }
+            self.sent_tx
+                .send(message)
+                .map_err(|_| TransportError::ChannelClosed)
+        }
+
+        async fn receive(&self) -> Result<TransportMessageRecv, TransportError> {
+            self.incoming_rx
+                .lock()
+                .await
+                .recv()
+                .await
+                .ok_or(TransportError::ChannelClosed)
+        }
+    }
+
+    async fn next_sent(sent: &mut mpsc::UnboundedReceiver<JsonRpcMessage>) -> JsonRpcMessage {
+        tokio::time::timeout(Duration::from_secs(5), sent.recv())
+            .await
+            .expect("client did not send a message")
+            .expect("transport closed")
+    }
+
+    async fn initialized_client(
+        timeout: Duration,
+    ) -> (
+        McpClient<MockTransport>,
+        mpsc::Sender<TransportMessageRecv>,
+        mpsc::UnboundedReceiver<JsonRpcMessage>,
+    ) {
+        let (transport, mut sent) = MockTransport::new();
+        let incoming = transport.incoming_tx.clone();
+        let mut client = McpClient::connect(transport, timeout).await.unwrap();
+        client
+            .initialize(
+                ClientInfo {
+                    name: "test".to_string(),
+                    version: "1.0.0".to_string(),
+                },
+                ClientCapabilities::default(),
+            )
+            .await
+            .unwrap();
+        // initialize request and initialized notification
+        next_sent(&mut sent).await;
+        next_sent(&mut sent).await;
+        (client, incoming, sent)
+    }
+
+    fn cancelled_request_id(message: JsonRpcMessage) -> Value {
+        let JsonRpcMessage::Notification(notification) = message else {
+            panic!("expected a notification");
+        };
+        assert_eq!(notification.notification.method, "notifications/cancelled");
+        notification.notification.params["requestId"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_times_out_and_cancels() {
+        let (client, _incoming, mut sent) = initialized_client(Duration::from_millis(100)).await;
+
+        let result = client.call_tool("hang", json!({})).await;
+        assert!(
+            matches!(result, Err(Error::RequestTimeout { ref method, .. }) if method == "tools/call")
+        );
+
+        let JsonRpcMessage::Request(request) = next_sent(&mut sent).await else {
+            panic!("expected the tool call request");
+        };
+        let request_id = serde_json::to_value(&request.id).unwrap();
+        assert_eq!(cancelled_request_id(next_sent(&mut sent).await), request_id);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_call_is_cancelled() {
+        let (client, _incoming, mut sent) = initialized_client(Duration::from_secs(30)).await;
+
+        // Abandon the call the way a cancelled reply stream drops its tool futures
+        let call = client.call_tool("hang", json!({}));
+        let _ = tokio::time::timeout(Duration::from_millis(50), call).await;
+
+        let JsonRpcMessage::Request(request) = next_sent(&mut sent).await else {
+            panic!("expected the tool call request");
+        };
+        let request_id = serde_json::to_value(&request.id).unwrap();
+        assert_eq!(cancelled_request_id(next_sent(&mut sent).await), request_id);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_calls_overlap() {
+        let (client, incoming, mut sent) = initialized_client(Duration::from_secs(5)).await;
+
+        // The server only answers once both calls are outstanding
+        let server = tokio::spawn(async move {
+            let mut pending = Vec::new();
+            while pending.len() < 2 {
+                if let JsonRpcMessage::Request(request) = next_sent(&mut sent).await {
+                    pending.push(request.id);
+                }
+            }
+            for id in pending {
+                let response = JsonRpcMessage::Response(JsonRpcResponse {
+                    jsonrpc: JsonRpcVersion2_0,
+                    id,
+                    result: json!({ "content": [] }).as_object().cloned().unwrap(),
+                });
+                incoming.send(response).await.unwrap();
+            }
+        });
+
+        let (first, second) = tokio::join!(
+            client.call_tool("slow", json!({})),
+            client.call_tool("slow", json!({}))
+        );
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_with_progress_sends_token() {
+        let (client, _incoming, mut sent) = initialized_client(Duration::from_millis(100)).await;
+
+        let _ = client
+            .call_tool_with_progress("slow", json!({}), "toolu_1")
+            .await;
+
+        let JsonRpcMessage::Request(request) = next_sent(&mut sent).await else {
+            panic!("expected the tool call request");
+        };
+        let params = serde_json::to_value(&request.request.params).unwrap();
+        assert_eq!(params["_meta"]["progressToken"], "toolu_1");
+    }
+
+    #[tokio::test]
+    async fn test_full_subscriber_drops_notifications_and_stays_subscribed() {
+        let (client, incoming, _sent) = initialized_client(Duration::from_secs(5)).await;
+        let mut notifications = client.subscribe().await;
+
+        let progress = |progress: u32| -> TransportMessageRecv {
+            serde_json::from_value(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/progress",
+                "params": { "progressToken": "toolu_1", "progress": progress }
+            }))
+            .unwrap()
+        };
+        // One more than the subscriber channel holds
+        for i in 0..17 {
+            incoming.send(progress(i)).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(client.dropped_notifications(), 1);
+
+        for _ in 0..16 {
+            notifications.recv().await.unwrap();
+        }
+        // The subscriber was kept, so later notifications still arrive
+        incoming.send(progress(17)).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ping() {
+        let (client, incoming, mut sent) = initialized_client(Duration::from_secs(5)).await;
+
+        let server = tokio::spawn(async move {
+            let JsonRpcMessage::Request(request) = next_sent(&mut sent).await else {
+                panic!("expected a request");
+            };
+            assert_eq!(request.request.method, "ping");
+            let response = JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: JsonRpcVersion2_0,
+                id: request.id,
+                result: JsonObject::new(),
+            });
+            incoming.send(response).await.unwrap();
+        });
+
+        client.ping().await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_logging_level() {
+        let (client, incoming, mut sent) = initialized_client(Duration::from_secs(5)).await;
+
+        let server = tokio::spawn(async move {
+            let JsonRpcMessage::Request(request) = next_sent(&mut sent).await else {
+                panic!("expected a request");
+            };
+            assert_eq!(request.request.method, "logging/setLevel");
+            let params = serde_json::to_value(&request.request.params).unwrap();
+            assert_eq!(params["level"], "warning");
+            let response = JsonRpcMessage::Response(JsonRpcResponse {
+                jsonrpc: JsonRpcVersion2_0,
+                id: request.id,
+                result: JsonObject::new(),
+            });
+            incoming.send(response).await.unwrap();
+        });
+
+        client
+            .set_logging_level(LoggingLevel::Warning)
+            .await
+            .unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_roots_round_trip() {
+        let (transport, mut sent) = MockTransport::new();
+        let incoming = transport.incoming_tx.clone();
+        let mut client = McpClient::connect(transport, Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let root = Root {
+            uri: "file:///workspace".to_string(),
+            name: Some("workspace".to_string()),
+        };
+        client.set_roots(vec![root.clone()]).await.unwrap();
+        client
+            .initialize(
+                ClientInfo {
+                    name: "test".to_string(),
+                    version: "1.0.0".to_string(),
+                },
+                ClientCapabilities {
+                    roots: Some(RootsCapability { list_changed: true }),
+                },
+            )
+            .await
+            .unwrap();
+        // initialize request and initialized notification
+        next_sent(&mut sent).await;
+        next_sent(&mut sent).await;
+
+        incoming
+            .send(JsonRpcMessage::Request(JsonRpcRequest {
+                jsonrpc: JsonRpcVersion2_0,
+                id: NumberOrString::Number(100),
+                request: Request {
+                    method: "roots/list".to_string(),
+                    params: JsonObject::new(),
+                    extensions: Default::default(),
+                },
+            }))
+            .await
+            .unwrap();
+        let JsonRpcMessage::Response(response) = next_sent(&mut sent).await else {
+            panic!("expected a response to roots/list");
+        };
+        assert_eq!(response.id, NumberOrString::Number(100));
+        let roots: Vec<Root> = serde_json::from_value(response.result["roots"].clone()).unwrap();
+        assert_eq!(roots, vec![root]);
+
+        // Changing the roots after initialization notifies the server
+        client
+            .set_roots(vec![Root {
+                uri: "file:///other".to_string(),
+                name: None,
+            }])
+            .await
+            .unwrap();
+        let JsonRpcMessage::Notification(notification) = next_sent(&mut sent).await else {
+            panic!("expected a notification");
+        };
+        assert_eq!(
+            notification.notification.method,
+            "notifications/roots/list_changed"
+        );
+    }
+
+    /// Serves one tool per page, `tool-{index}`, with the next cursor given by `next`
+    struct PagedClient {
+        next: fn(usize) -> Option<String>,
+        requested: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    impl PagedClient {
+        fn new(next: fn(usize) -> Option<String>) -> Self {
+            Self {
+                next,
+                requested: std::sync::Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for PagedClient {
+        async fn initialize(
+            &mut self,
+            _info: ClientInfo,
+            _capabilities: ClientCapabilities,
+        ) -> Result<InitializeResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn read_resource(&self, _uri: &str) -> Result<ReadResourceResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_tools(&self, next_cursor: Option<String>) -> Result<ListToolsResult, Error> {
+            let index = next_cursor
+                .as_deref()
+                .map(|cursor| cursor.trim_start_matches("page-").parse().unwrap())
+                .unwrap_or(0);
+            self.requested.lock().unwrap().push(next_cursor);
+            Ok(ListToolsResult {
+                tools: vec![Tool::new(format!("tool-{}", index), "", JsonObject::new())],
+                next_cursor: (self.next)(index),
+            })
+        }
+
+        async fn call_tool(&self, _name: &str, _arguments: Value) -> Result<CallToolResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::NotInitialized)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            mpsc::channel(1).1
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_all_tools_follows_cursors() {
+        let client = PagedClient::new(|index| (index < 2).then(|| format!("page-{}", index + 1)));
+
+        let tools = client.list_all_tools().await.unwrap();
+
+        let names: Vec<String> = tools.iter().map(|tool| tool.name.to_string()).collect();
+        assert_eq!(names, vec!["tool-0", "tool-1", "tool-2"]);
+        assert_eq!(
+            *client.requested.lock().unwrap(),
+            vec![None, Some("page-1".to_string()), Some("page-2".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_all_tools_stops_on_repeated_cursor() {
+        // The third page points back at itself
+        let client = PagedClient::new(|index| Some(format!("page-{}", index.min(1) + 1)));
+
+        let tools = client.list_all_tools().await.unwrap();
+
+        assert_eq!(tools.len(), 3);
+        assert_eq!(client.requested.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_list_all_tools_stops_at_page_cap() {
+        // Every page points at a fresh cursor, so only the cap ends the listing
+        let client = PagedClient::new(|index| Some(format!("page-{}", index + 1)));
+
+        let tools = client.list_all_tools().await.unwrap();
+
+        assert_eq!(tools.len(), MAX_LIST_PAGES);
+    }
 }
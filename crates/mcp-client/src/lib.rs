@@ -7,7 +7,10 @@ pub mod transport;
 mod oauth_tests;
 
 pub use client::{ClientCapabilities, ClientInfo, Error, McpClient, McpClientTrait};
-pub use oauth::{authenticate_service, ServiceConfig};
+pub use oauth::{
+    authenticate_service, authorize, authorize_server, refresh_credentials, CredentialStore,
+    OAuthCredentials, ServiceConfig,
+};
 pub use service::McpService;
 pub use transport::{
     SseTransport, StdioTransport, StreamableHttpTransport, Transport, TransportHandle,
@@ -21,6 +21,23 @@ struct TokenData {
     refresh_token: Option<String>,
 }
 
+/// Tokens issued for an MCP server, along with what is needed to refresh them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuthCredentials {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub token_endpoint: String,
+    pub client_id: String,
+    /// Canonical resource URI the tokens were issued for (RFC 8707)
+    pub resource: String,
+}
+
+/// Persists credentials for a single MCP server between runs
+pub trait CredentialStore: Send + Sync {
+    fn load(&self) -> Option<OAuthCredentials>;
+    fn save(&self, credentials: &OAuthCredentials);
+}
+
 #[derive(Serialize, Deserialize)]
 struct ClientRegistrationRequest {
     redirect_uris: Vec<String>,
@@ -76,6 +93,27 @@ impl ServiceConfig {
         })
     }
 
+    /// Create a configuration from a 401 response of the MCP server
+    ///
+    /// When the `WWW-Authenticate` header points at protected resource metadata (RFC 9728),
+    /// the authorization server listed there is used; otherwise discovery falls back to the
+    /// MCP server's own host.
+    pub async fn from_unauthorized(mcp_url: &str, www_authenticate: Option<&str>) -> Result<Self> {
+        let mut config = Self::from_mcp_endpoint(mcp_url)?;
+        let Some(metadata_url) = www_authenticate.and_then(resource_metadata_url) else {
+            return Ok(config);
+        };
+        match discover_authorization_server(&metadata_url).await {
+            Ok(authorization_server) => config.oauth_host = authorization_server,
+            Err(e) => tracing::debug!(
+                "Could not read protected resource metadata at {}: {}",
+                metadata_url,
+                e
+            ),
+        }
+        Ok(config)
+    }
+
     /// Create configuration with custom discovery path for non-standard services
     pub fn with_custom_discovery(mut self, discovery_path: String) -> Self {
         self.discovery_path = Some(discovery_path);
@@ -229,23 +267,7 @@ impl OAuthFlow {
             ));
         }
 
-        let token_response: Value = resp.json().await?;
-
-        let access_token = token_response
-            .get("access_token")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("access_token not found in token response"))?
-            .to_string();
-
-        let refresh_token = token_response
-            .get("refresh_token")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
-
-        Ok(TokenData {
-            access_token,
-            refresh_token,
-        })
+        parse_token_response(resp.json().await?)
     }
 
     async fn execute(&self, resource: &str) -> Result<TokenData> {
@@ -406,6 +428,50 @@ async fn get_oauth_endpoints(
     }))
 }
 
+fn parse_token_response(token_response: Value) -> Result<TokenData> {
+    let access_token = token_response
+        .get("access_token")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("access_token not found in token response"))?
+        .to_string();
+
+    let refresh_token = token_response
+        .get("refresh_token")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    Ok(TokenData {
+        access_token,
+        refresh_token,
+    })
+}
+
+/// Extract the protected resource metadata URL from a `WWW-Authenticate` header
+pub fn resource_metadata_url(www_authenticate: &str) -> Option<String> {
+    let (_, rest) = www_authenticate.split_once("resource_metadata=")?;
+    let value = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split([',', ' ']).next()?,
+    };
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Read protected resource metadata and return the first authorization server it lists
+async fn discover_authorization_server(metadata_url: &str) -> Result<String> {
+    let metadata: Value = reqwest::get(metadata_url)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    metadata
+        .get("authorization_servers")
+        .and_then(|v| v.as_array())
+        .and_then(|servers| servers.first())
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim_end_matches('/').to_string())
+        .ok_or_else(|| anyhow::anyhow!("authorization_servers not found in resource metadata"))
+}
+
 fn parse_oauth_config(oidc_config: Value) -> Result<OidcEndpoints> {
     let authorization_endpoint = oidc_config
         .get("authorization_endpoint")
@@ -433,6 +499,11 @@ fn parse_oauth_config(oidc_config: Value) -> Result<OidcEndpoints> {
 
 /// Perform OAuth flow for a service
 pub async fn authenticate_service(config: ServiceConfig, mcp_url: &str) -> Result<String> {
+    Ok(authorize(config, mcp_url).await?.access_token)
+}
+
+/// Perform OAuth flow for a service, returning credentials that can later be refreshed
+pub async fn authorize(config: ServiceConfig, mcp_url: &str) -> Result<OAuthCredentials> {
     tracing::info!("Starting OAuth authentication for service...");
 
     // Get the canonical resource URI for the MCP server
@@ -447,10 +518,78 @@ pub async fn authenticate_service(config: ServiceConfig, mcp_url: &str) -> Resul
     let client_id = OAuthFlow::register_client(&endpoints, &config).await?;
 
     // Create and execute OAuth flow with the dynamic client_id
-    let flow = OAuthFlow::new(endpoints, client_id, config.redirect_uri);
+    let token_endpoint = endpoints.token_endpoint.clone();
+    let flow = OAuthFlow::new(endpoints, client_id.clone(), config.redirect_uri);
 
     let token_data = flow.execute(&resource_uri).await?;
 
     tracing::info!("OAuth authentication successful!");
-    Ok(token_data.access_token)
+    Ok(OAuthCredentials {
+        access_token: token_data.access_token,
+        refresh_token: token_data.refresh_token,
+        token_endpoint,
+        client_id,
+        resource: resource_uri,
+    })
+}
+
+/// Perform OAuth flow for an MCP server, discovering its authorization server from the 401
+/// it answers unauthenticated requests with
+pub async fn authorize_server(mcp_url: &str) -> Result<OAuthCredentials> {
+    let www_authenticate = reqwest::Client::new()
+        .get(mcp_url)
+        .header("Accept", "text/event-stream")
+        .send()
+        .await
+        .ok()
+        .filter(|resp| resp.status().as_u16() == 401)
+        .and_then(|resp| {
+            resp.headers()
+                .get("WWW-Authenticate")
+                .and_then(|h| h.to_str().ok())
+                .map(String::from)
+        });
+
+    let config = ServiceConfig::from_unauthorized(mcp_url, www_authenticate.as_deref()).await?;
+    authorize(config, mcp_url).await
+}
+
+/// Exchange the refresh token for a new access token
+///
+/// Servers that don't rotate refresh tokens omit it from the response, in which case the
+/// existing one is kept.
+pub async fn refresh_credentials(credentials: &OAuthCredentials) -> Result<OAuthCredentials> {
+    let refresh_token = credentials
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("No refresh token available"))?;
+
+    let params = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", &credentials.client_id),
+        ("resource", &credentials.resource),
+    ];
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(&credentials.token_endpoint)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .form(&params)
+        .send()
+        .await?;
+
+    if !resp.status().is_success() {
+        let err_text = resp.text().await?;
+        return Err(anyhow::anyhow!("Failed to refresh token: {}", err_text));
+    }
+
+    let token_data = parse_token_response(resp.json().await?)?;
+    Ok(OAuthCredentials {
+        access_token: token_data.access_token,
+        refresh_token: token_data
+            .refresh_token
+            .or_else(|| credentials.refresh_token.clone()),
+        ..credentials.clone()
+    })
 }
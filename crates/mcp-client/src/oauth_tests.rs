@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::oauth::ServiceConfig;
+    use crate::oauth::{
+        refresh_credentials, resource_metadata_url, OAuthCredentials, ServiceConfig,
+    };
+    use mockito::Server;
 
     #[test]
     fn test_canonical_resource_uri_generation() {
@@ -78,4 +81,70 @@ mod tests {
             Some("/custom/oauth/discovery".to_string())
         );
     }
+
+    #[test]
+    fn test_resource_metadata_url() {
+        assert_eq!(
+            resource_metadata_url(
+                r#"Bearer error="invalid_token", resource_metadata="https://mcp.example.com/.well-known/oauth-protected-resource""#
+            ),
+            Some("https://mcp.example.com/.well-known/oauth-protected-resource".to_string())
+        );
+        assert_eq!(
+            resource_metadata_url("Bearer resource_metadata=https://a.example/meta, scope=x"),
+            Some("https://a.example/meta".to_string())
+        );
+        assert_eq!(resource_metadata_url(r#"Bearer realm="mcp""#), None);
+    }
+
+    #[tokio::test]
+    async fn test_from_unauthorized_uses_resource_metadata() {
+        let mut server = Server::new_async().await;
+        let metadata = server
+            .mock("GET", "/.well-known/oauth-protected-resource")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"authorization_servers": ["https://auth.example.com/"]}"#)
+            .create_async()
+            .await;
+
+        let header = format!(
+            r#"Bearer resource_metadata="{}/.well-known/oauth-protected-resource""#,
+            server.url()
+        );
+        let config = ServiceConfig::from_unauthorized("https://mcp.example.com/mcp", Some(&header))
+            .await
+            .unwrap();
+
+        metadata.assert_async().await;
+        assert_eq!(config.oauth_host, "https://auth.example.com");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_credentials_keeps_refresh_token() {
+        let mut server = Server::new_async().await;
+        let token = server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+                mockito::Matcher::UrlEncoded("refresh_token".into(), "refresh-1".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token": "access-2"}"#)
+            .create_async()
+            .await;
+
+        let credentials = OAuthCredentials {
+            access_token: "access-1".to_string(),
+            refresh_token: Some("refresh-1".to_string()),
+            token_endpoint: format!("{}/token", server.url()),
+            client_id: "client".to_string(),
+            resource: "https://mcp.example.com/mcp".to_string(),
+        };
+        let refreshed = refresh_credentials(&credentials).await.unwrap();
+
+        token.assert_async().await;
+        assert_eq!(refreshed.access_token, "access-2");
+        assert_eq!(refreshed.refresh_token.as_deref(), Some("refresh-1"));
+        assert_eq!(refreshed.client_id, "client");
+    }
 }
@@ -1,5 +1,8 @@
 use futures::future::BoxFuture;
-use rmcp::model::{JsonRpcMessage, JsonRpcRequest};
+use rmcp::model::{
+    JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcVersion2_0, Notification, RequestId,
+};
+use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::task::{Context, Poll};
@@ -30,6 +33,22 @@ impl<T: TransportHandle> McpService<T> {
     pub async fn hangup(&self, error: Error) {
         self.pending_requests.broadcast_close(error).await
     }
+
+    /// Forget a pending request and send `notifications/cancelled` so the server can stop it
+    pub async fn cancel_request(&self, id: RequestId, reason: &str) -> Result<(), Error> {
+        self.pending_requests.remove(&id.to_string()).await;
+
+        let params = json!({ "requestId": id, "reason": reason });
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JsonRpcVersion2_0,
+            notification: Notification {
+                method: "notifications/cancelled".to_string(),
+                params: params.as_object().cloned().unwrap_or_default(),
+                extensions: Default::default(),
+            },
+        });
+        self.inner.send(notification).await
+    }
 }
 
 impl<T> Service<JsonRpcMessage> for McpService<T>
@@ -131,6 +150,10 @@ impl PendingRequests {
         }
     }
 
+    pub async fn remove(&self, id: &str) {
+        self.requests.write().await.remove(id);
+    }
+
     pub async fn clear(&self) {
         self.requests.write().await.clear();
     }
@@ -1,3 +1,4 @@
+use crate::oauth::{refresh_credentials, CredentialStore};
 use crate::transport::{Error, TransportMessageRecv};
 use async_trait::async_trait;
 use eventsource_client::{Client, SSE};
@@ -30,6 +31,8 @@ pub struct SseActor {
     http_client: HttpClient,
     /// The discovered endpoint for POST requests (once "endpoint" SSE event arrives)
     post_endpoint: Arc<RwLock<Option<String>>>,
+    /// Where OAuth credentials for this server are kept
+    credential_store: Option<Arc<dyn CredentialStore>>,
 }
 
 impl SseActor {
@@ -45,23 +48,38 @@ impl SseActor {
             sse_url,
             post_endpoint,
             http_client: HttpClient::new(),
+            credential_store: None,
         }
     }
 
+    /// Send the access token from `store` with every request and refresh it on a 401
+    pub fn with_credential_store(mut self, store: Option<Arc<dyn CredentialStore>>) -> Self {
+        self.credential_store = store;
+        self
+    }
+
     /// The main entry point for the actor. Spawns two concurrent loops:
     /// 1) handle_incoming_messages (SSE events)
     /// 2) handle_outgoing_messages (sending messages via POST)
     pub async fn run(self) {
+        let access_token = self
+            .credential_store
+            .as_ref()
+            .and_then(|store| store.load())
+            .map(|credentials| credentials.access_token);
         tokio::join!(
             Self::handle_incoming_messages(
                 self.sender,
                 self.sse_url.clone(),
-                Arc::clone(&self.post_endpoint)
+                Arc::clone(&self.post_endpoint),
+                access_token.clone(),
             ),
             Self::handle_outgoing_messages(
                 self.receiver,
                 self.http_client.clone(),
                 Arc::clone(&self.post_endpoint),
+                self.credential_store.clone(),
+                access_token,
             )
         );
     }
@@ -74,8 +92,15 @@ impl SseActor {
         sender: mpsc::Sender<TransportMessageRecv>,
         sse_url: String,
         post_endpoint: Arc<RwLock<Option<String>>>,
+        access_token: Option<String>,
     ) {
-        let client = match eventsource_client::ClientBuilder::for_url(&sse_url) {
+        let builder = eventsource_client::ClientBuilder::for_url(&sse_url).and_then(|builder| {
+            match &access_token {
+                Some(token) => builder.header("Authorization", &format!("Bearer {}", token)),
+                None => Ok(builder),
+            }
+        });
+        let client = match builder {
             Ok(builder) => builder.build(),
             Err(e) => {
                 warn!("Failed to connect SSE client: {}", e);
@@ -140,6 +165,8 @@ impl SseActor {
         mut receiver: mpsc::Receiver<String>,
         http_client: HttpClient,
         post_endpoint: Arc<RwLock<Option<String>>>,
+        credential_store: Option<Arc<dyn CredentialStore>>,
+        mut access_token: Option<String>,
     ) {
         while let Some(message_str) = receiver.recv().await {
             let post_url = match post_endpoint.read().await.as_ref() {
@@ -151,14 +178,25 @@ impl SseActor {
                 }
             };
 
-            // Perform the HTTP POST
-            match http_client
-                .post(&post_url)
-                .header("Content-Type", "application/json")
-                .body(message_str)
-                .send()
-                .await
-            {
+            // Perform the HTTP POST, refreshing the access token once if it was rejected
+            let mut result =
+                Self::post_message(&http_client, &post_url, &message_str, &access_token).await;
+            if let (Ok(resp), Some(store)) = (&result, &credential_store) {
+                if resp.status().as_u16() == 401 {
+                    if let Some(token) = Self::refresh_access_token(store.as_ref()).await {
+                        access_token = Some(token);
+                        result = Self::post_message(
+                            &http_client,
+                            &post_url,
+                            &message_str,
+                            &access_token,
+                        )
+                        .await;
+                    }
+                }
+            }
+
+            match result {
                 Ok(resp) => {
                     if !resp.status().is_success() {
                         let err = Error::HttpError {
@@ -179,6 +217,36 @@ impl SseActor {
 
         tracing::info!("SseActor shut down.");
     }
+
+    async fn post_message(
+        http_client: &HttpClient,
+        post_url: &str,
+        message_str: &str,
+        access_token: &Option<String>,
+    ) -> reqwest::Result<reqwest::Response> {
+        let mut request = http_client
+            .post(post_url)
+            .header("Content-Type", "application/json")
+            .body(message_str.to_string());
+        if let Some(token) = access_token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await
+    }
+
+    async fn refresh_access_token(store: &dyn CredentialStore) -> Option<String> {
+        let credentials = store.load()?;
+        match refresh_credentials(&credentials).await {
+            Ok(refreshed) => {
+                store.save(&refreshed);
+                Some(refreshed.access_token)
+            }
+            Err(e) => {
+                warn!("Failed to refresh OAuth access token: {e}");
+                None
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -203,6 +271,7 @@ impl TransportHandle for SseTransportHandle {
 pub struct SseTransport {
     sse_url: String,
     env: HashMap<String, String>,
+    credential_store: Option<Arc<dyn CredentialStore>>,
 }
 
 /// The SSE transport spawns an `SseActor` on `start()`.
@@ -211,9 +280,16 @@ impl SseTransport {
         Self {
             sse_url: sse_url.into(),
             env,
+            credential_store: None,
         }
     }
 
+    /// Authenticate with OAuth credentials from `store`, saving refreshed ones back to it
+    pub fn with_credential_store(mut self, store: Arc<dyn CredentialStore>) -> Self {
+        self.credential_store = Some(store);
+        self
+    }
+
     /// Waits for the endpoint to be set, up to 10 attempts.
     async fn wait_for_endpoint(
         post_endpoint: Arc<RwLock<Option<String>>>,
@@ -252,7 +328,8 @@ impl Transport for SseTransport {
         let post_endpoint_clone = Arc::clone(&post_endpoint);
 
         // Build the actor
-        let actor = SseActor::new(rx, otx, self.sse_url.clone(), post_endpoint);
+        let actor = SseActor::new(rx, otx, self.sse_url.clone(), post_endpoint)
+            .with_credential_store(self.credential_store.clone());
 
         // Spawn the actor task
         tokio::spawn(actor.run());
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
@@ -194,6 +195,7 @@ pub struct StdioTransport {
     command: String,
     args: Vec<String>,
     env: HashMap<String, String>,
+    cwd: Option<PathBuf>,
 }
 
 impl StdioTransport {
@@ -206,9 +208,16 @@ impl StdioTransport {
             command: command.into(),
             args,
             env,
+            cwd: None,
         }
     }
 
+    /// Run the process in `cwd` instead of the current working directory
+    pub fn with_cwd(mut self, cwd: Option<PathBuf>) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
     async fn spawn_process(&self) -> Result<(Child, ChildStdin, ChildStdout, ChildStderr), Error> {
         let mut command = Command::new(&self.command);
         command
@@ -219,6 +228,10 @@ impl StdioTransport {
             .stderr(std::process::Stdio::piped())
             .kill_on_drop(true);
 
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
         // Set process group and ensure signal handling on Unix systems
         #[cfg(unix)]
         command.process_group(0);
@@ -317,3 +330,30 @@ impl Transport for StdioTransport {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_gets_cwd_and_env() {
+        let cwd = std::env::temp_dir().canonicalize().unwrap();
+        // Echo the working directory and one env var back as a JSON-RPC response
+        let script = r#"printf '{"jsonrpc":"2.0","id":1,"result":{"cwd":"%s","token":"%s"}}\n' "$(pwd -P)" "$EXT_TOKEN""#;
+        let transport = StdioTransport::new(
+            "sh",
+            vec!["-c".to_string(), script.to_string()],
+            HashMap::from([("EXT_TOKEN".to_string(), "s3cret".to_string())]),
+        )
+        .with_cwd(Some(cwd.clone()));
+
+        let handle = transport.start().await.unwrap();
+        let JsonRpcMessage::Response(response) = handle.receive().await.unwrap() else {
+            panic!("expected a response from the script");
+        };
+
+        assert_eq!(response.result["cwd"], cwd.to_string_lossy().as_ref());
+        assert_eq!(response.result["token"], "s3cret");
+    }
+}
@@ -1,10 +1,13 @@
-use crate::oauth::{authenticate_service, ServiceConfig};
+use crate::oauth::{authorize, refresh_credentials, CredentialStore, ServiceConfig};
 use crate::transport::{Error, TransportMessageRecv};
 use async_trait::async_trait;
 use eventsource_client::{Client, SSE};
 use futures::TryStreamExt;
 use reqwest::Client as HttpClient;
-use rmcp::model::{JsonRpcMessage, JsonRpcRequest, NumberOrString::Number};
+use rmcp::model::{
+    ErrorCode, ErrorData, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcVersion2_0, NumberOrString::Number,
+};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex, RwLock};
@@ -16,6 +19,17 @@ use super::{serialize_and_send, Transport, TransportHandle};
 
 // Default timeout for HTTP requests
 const HTTP_TIMEOUT_SECS: u64 = 30;
+// Attempts to resume a response stream that ended before the response arrived
+const MAX_RESUME_ATTEMPTS: u32 = 3;
+const RESUME_BACKOFF: Duration = Duration::from_millis(250);
+
+/// What was seen on a streamed response before it ended
+struct StreamOutcome {
+    /// Whether a response or error for the request arrived
+    responded: bool,
+    /// ID of the last event, used to resume the stream
+    last_event_id: Option<String>,
+}
 
 /// The Streamable HTTP transport actor that handles:
 /// - HTTP POST requests to send messages to the server
@@ -36,6 +50,12 @@ pub struct StreamableHttpActor {
     env: HashMap<String, String>,
     /// Custom headers to include in requests
     headers: HashMap<String, String>,
+    /// The initialize request and initialized notification, replayed when the session expires
+    handshake: Vec<String>,
+    /// Where OAuth credentials for this server are kept
+    credential_store: Option<Arc<dyn CredentialStore>>,
+    /// `WWW-Authenticate` header of the last 401 response, used for OAuth discovery
+    www_authenticate: Option<String>,
 }
 
 impl StreamableHttpActor {
@@ -58,9 +78,18 @@ impl StreamableHttpActor {
             session_id,
             env,
             headers,
+            handshake: Vec::new(),
+            credential_store: None,
+            www_authenticate: None,
         }
     }
 
+    /// Persist OAuth credentials to `store` and refresh them from it on a 401
+    pub fn with_credential_store(mut self, store: Option<Arc<dyn CredentialStore>>) -> Self {
+        self.credential_store = store;
+        self
+    }
+
     /// Main entry point for the actor
     pub async fn run(mut self) {
         // Set environment variables
@@ -68,11 +97,12 @@ impl StreamableHttpActor {
             std::env::set_var(key, value);
         }
 
-        // Handle outgoing messages
+        // Handle outgoing messages; a failed request fails on its own instead of closing the
+        // transport, so the next request reconnects
         while let Some(message_str) = self.receiver.recv().await {
-            if let Err(e) = self.handle_outgoing_message(message_str).await {
+            if let Err(e) = self.handle_outgoing_message(message_str.clone()).await {
                 error!("Error handling outgoing message: {}", e);
-                break;
+                self.fail_request(&message_str, &e).await;
             }
         }
 
@@ -92,8 +122,76 @@ impl StreamableHttpActor {
             JsonRpcMessage::Request(JsonRpcRequest { id: Number(_), .. })
         );
 
-        // Try to send the request
-        match self.send_request(&message_str, expects_response).await {
+        // Remember the handshake so that an expired session can be re-established
+        let is_initialize = match &parsed_message {
+            JsonRpcMessage::Request(JsonRpcRequest { request, .. }) => {
+                request.method == "initialize"
+            }
+            _ => false,
+        };
+        if is_initialize {
+            self.handshake = vec![message_str.clone()];
+        } else if let JsonRpcMessage::Notification(JsonRpcNotification { notification, .. }) =
+            &parsed_message
+        {
+            if notification.method == "notifications/initialized" {
+                self.handshake.push(message_str.clone());
+            }
+        }
+
+        match self
+            .send_authenticated(&message_str, expects_response)
+            .await
+        {
+            Err(Error::SessionError(_)) if !is_initialize && !self.handshake.is_empty() => {
+                info!("Session expired, starting a new session");
+                self.reinitialize().await?;
+                self.send_authenticated(&message_str, expects_response)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    /// Replay the initialize handshake to obtain a new session id
+    ///
+    /// The response to the replayed initialize request reaches the client, which ignores it
+    /// because it is no longer waiting for that id.
+    async fn reinitialize(&mut self) -> Result<(), Error> {
+        let handshake = self.handshake.clone();
+        for (index, message) in handshake.iter().enumerate() {
+            self.send_authenticated(message, index == 0).await?;
+        }
+        Ok(())
+    }
+
+    /// Answer a request that could not be delivered with an error, so the caller isn't left
+    /// waiting
+    async fn fail_request(&self, message_str: &str, error: &Error) {
+        let Ok(JsonRpcMessage::Request(JsonRpcRequest { id, .. })) =
+            serde_json::from_str::<JsonRpcMessage>(message_str)
+        else {
+            return;
+        };
+        let response = JsonRpcMessage::Error(JsonRpcError {
+            jsonrpc: JsonRpcVersion2_0,
+            id,
+            error: ErrorData {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: error.to_string().into(),
+                data: None,
+            },
+        });
+        let _ = self.sender.send(response).await;
+    }
+
+    /// Send a message, running the OAuth flow and retrying once if the server asks for it
+    async fn send_authenticated(
+        &mut self,
+        message_str: &str,
+        expects_response: bool,
+    ) -> Result<(), Error> {
+        match self.send_request(message_str, expects_response).await {
             Ok(()) => Ok(()),
             Err(Error::HttpError { status, .. }) if status == 401 || status == 403 => {
                 // Authentication challenge - try to authenticate and retry
@@ -106,7 +204,7 @@ impl StreamableHttpActor {
                     info!("Authentication successful, retrying request...");
                     self.headers
                         .insert("Authorization".to_string(), format!("Bearer {}", token));
-                    self.send_request(&message_str, expects_response).await
+                    self.send_request(message_str, expects_response).await
                 } else {
                     Err(Error::StreamableHttpError(
                         "Authentication failed - service not supported or OAuth flow failed"
@@ -152,6 +250,13 @@ impl StreamableHttpActor {
         // Handle HTTP error status codes
         if !response.status().is_success() {
             let status = response.status();
+            if status.as_u16() == 401 {
+                self.www_authenticate = response
+                    .headers()
+                    .get("WWW-Authenticate")
+                    .and_then(|h| h.to_str().ok())
+                    .map(String::from);
+            }
             if status.as_u16() == 404 {
                 // Session not found - clear our session ID
                 *self.session_id.write().await = None;
@@ -187,7 +292,8 @@ impl StreamableHttpActor {
         if content_type.starts_with("text/event-stream") {
             // Handle streaming HTTP response (server chose to stream multiple messages back)
             if expects_response {
-                self.handle_streaming_response(response).await?;
+                let outcome = self.handle_streaming_response(response).await?;
+                self.resume_until_responded(outcome).await?;
             }
         } else if content_type.starts_with("application/json") || expects_response {
             // Handle single JSON response
@@ -208,18 +314,43 @@ impl StreamableHttpActor {
     }
 
     /// Attempt to authenticate with the service
+    ///
+    /// Stored credentials are refreshed first; the interactive flow only runs when there is
+    /// nothing to refresh or the refresh is rejected.
     async fn attempt_authentication(&self) -> Result<Option<String>, Error> {
+        let stored = self
+            .credential_store
+            .as_ref()
+            .and_then(|store| store.load());
+        if let Some(credentials) = stored.filter(|c| c.refresh_token.is_some()) {
+            match refresh_credentials(&credentials).await {
+                Ok(refreshed) => {
+                    info!("Refreshed OAuth access token");
+                    if let Some(store) = &self.credential_store {
+                        store.save(&refreshed);
+                    }
+                    return Ok(Some(refreshed.access_token));
+                }
+                Err(e) => warn!("Failed to refresh OAuth access token: {}", e),
+            }
+        }
+
         info!("Attempting to authenticate with service...");
 
         // Create a generic OAuth configuration from the MCP endpoint
-        match ServiceConfig::from_mcp_endpoint(&self.mcp_endpoint) {
+        match ServiceConfig::from_unauthorized(&self.mcp_endpoint, self.www_authenticate.as_deref())
+            .await
+        {
             Ok(config) => {
                 info!("Created OAuth config for endpoint: {}", self.mcp_endpoint);
 
-                match authenticate_service(config, &self.mcp_endpoint).await {
-                    Ok(token) => {
+                match authorize(config, &self.mcp_endpoint).await {
+                    Ok(credentials) => {
                         info!("OAuth authentication successful!");
-                        Ok(Some(token))
+                        if let Some(store) = &self.credential_store {
+                            store.save(&credentials);
+                        }
+                        Ok(Some(credentials.access_token))
                     }
                     Err(e) => {
                         warn!("OAuth authentication failed: {}", e);
@@ -237,6 +368,69 @@ impl StreamableHttpActor {
         }
     }
 
+    /// Resume a response stream that ended before the response arrived
+    ///
+    /// Resumption needs the server to have sent event ids; the stream is reopened with a GET
+    /// carrying `Last-Event-ID` so the server can replay what was missed.
+    async fn resume_until_responded(&mut self, mut outcome: StreamOutcome) -> Result<(), Error> {
+        let mut attempts = 0;
+        while !outcome.responded {
+            let Some(last_event_id) = outcome.last_event_id.clone() else {
+                break;
+            };
+            if attempts == MAX_RESUME_ATTEMPTS {
+                warn!(
+                    "Giving up resuming response stream after {} attempts",
+                    attempts
+                );
+                break;
+            }
+            attempts += 1;
+            tokio::time::sleep(RESUME_BACKOFF * attempts).await;
+
+            match self.resume_stream(&last_event_id).await {
+                Ok(response) => {
+                    let resumed = self.handle_streaming_response(response).await?;
+                    outcome.responded = resumed.responded;
+                    if resumed.last_event_id.is_some() {
+                        outcome.last_event_id = resumed.last_event_id;
+                    }
+                }
+                Err(e) => warn!("Failed to resume response stream: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reopen the event stream after `last_event_id`
+    async fn resume_stream(&self, last_event_id: &str) -> Result<reqwest::Response, Error> {
+        let mut request = self
+            .http_client
+            .get(&self.mcp_endpoint)
+            .header("Accept", "text/event-stream")
+            .header("MCP-Protocol-Version", "2025-06-18")
+            .header("Last-Event-ID", last_event_id);
+
+        if let Some(session_id) = self.session_id.read().await.as_ref() {
+            request = request.header("Mcp-Session-Id", session_id);
+        }
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Error::StreamableHttpError(format!("HTTP request failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(Error::HttpError {
+                status: response.status().as_u16(),
+                message: "Failed to resume response stream".to_string(),
+            });
+        }
+        Ok(response)
+    }
+
     /// Handle streaming HTTP response that uses Server-Sent Events format
     ///
     /// This is called when the server responds to an HTTP POST with `text/event-stream`
@@ -246,7 +440,7 @@ impl StreamableHttpActor {
     async fn handle_streaming_response(
         &mut self,
         response: reqwest::Response,
-    ) -> Result<(), Error> {
+    ) -> Result<StreamOutcome, Error> {
         use futures::StreamExt;
         use tokio::io::AsyncBufReadExt;
         use tokio_util::io::StreamReader;
@@ -261,15 +455,28 @@ impl StreamableHttpActor {
         let mut event_type = String::new();
         let mut event_data = String::new();
         let mut event_id = String::new();
+        let mut outcome = StreamOutcome {
+            responded: false,
+            last_event_id: None,
+        };
 
         while let Ok(Some(line)) = lines.next_line().await {
             if line.is_empty() {
                 // Empty line indicates end of event
+                if !event_id.is_empty() {
+                    outcome.last_event_id = Some(event_id.clone());
+                }
                 if !event_data.is_empty() {
                     // Parse the streamed data as JSON-RPC message
                     match serde_json::from_str::<TransportMessageRecv>(&event_data) {
                         Ok(message) => {
                             debug!("Received streaming HTTP response message: {:?}", message);
+                            if matches!(
+                                message,
+                                JsonRpcMessage::Response(_) | JsonRpcMessage::Error(_)
+                            ) {
+                                outcome.responded = true;
+                            }
                             let _ = self.sender.send(message).await;
                         }
                         Err(err) => {
@@ -294,7 +501,7 @@ impl StreamableHttpActor {
             // Ignore other fields (retry, etc.) - we only care about data
         }
 
-        Ok(())
+        Ok(outcome)
     }
 }
 
@@ -431,6 +638,7 @@ pub struct StreamableHttpTransport {
     mcp_endpoint: String,
     env: HashMap<String, String>,
     headers: HashMap<String, String>,
+    credential_store: Option<Arc<dyn CredentialStore>>,
 }
 
 impl StreamableHttpTransport {
@@ -439,6 +647,7 @@ impl StreamableHttpTransport {
             mcp_endpoint: mcp_endpoint.into(),
             env,
             headers: HashMap::new(),
+            credential_store: None,
         }
     }
 
@@ -451,9 +660,16 @@ impl StreamableHttpTransport {
             mcp_endpoint: mcp_endpoint.into(),
             env,
             headers,
+            credential_store: None,
         }
     }
 
+    /// Authenticate with OAuth credentials from `store`, saving new ones back to it
+    pub fn with_credential_store(mut self, store: Arc<dyn CredentialStore>) -> Self {
+        self.credential_store = Some(store);
+        self
+    }
+
     /// Validate that the URL is a valid MCP endpoint
     pub fn validate_endpoint(endpoint: &str) -> Result<(), Error> {
         Url::parse(endpoint)
@@ -477,6 +693,17 @@ impl Transport for StreamableHttpTransport {
         let session_id: Arc<RwLock<Option<String>>> = Arc::new(RwLock::new(None));
         let session_id_clone = Arc::clone(&session_id);
 
+        // Stored credentials are used unless an Authorization header was configured explicitly
+        let mut headers = self.headers.clone();
+        if !headers.contains_key("Authorization") {
+            if let Some(credentials) = self.credential_store.as_ref().and_then(|s| s.load()) {
+                headers.insert(
+                    "Authorization".to_string(),
+                    format!("Bearer {}", credentials.access_token),
+                );
+            }
+        }
+
         // Create and spawn the actor
         let actor = StreamableHttpActor::new(
             rx,
@@ -484,8 +711,9 @@ impl Transport for StreamableHttpTransport {
             self.mcp_endpoint.clone(),
             session_id,
             self.env.clone(),
-            self.headers.clone(),
-        );
+            headers.clone(),
+        )
+        .with_credential_store(self.credential_store.clone());
 
         tokio::spawn(actor.run());
 
@@ -499,7 +727,7 @@ impl Transport for StreamableHttpTransport {
                 .timeout(Duration::from_secs(HTTP_TIMEOUT_SECS))
                 .build()
                 .unwrap(),
-            headers: self.headers.clone(),
+            headers,
         };
 
         Ok(handle)
@@ -998,4 +1226,253 @@ mod tests {
         // Verify the mock was called
         mock.assert_async().await;
     }
+
+    #[derive(Default)]
+    struct MemoryCredentialStore(std::sync::Mutex<Option<crate::oauth::OAuthCredentials>>);
+
+    impl CredentialStore for MemoryCredentialStore {
+        fn load(&self) -> Option<crate::oauth::OAuthCredentials> {
+            self.0.lock().unwrap().clone()
+        }
+
+        fn save(&self, credentials: &crate::oauth::OAuthCredentials) {
+            *self.0.lock().unwrap() = Some(credentials.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_outgoing_message_refreshes_token_on_401() {
+        let mut server = Server::new_async().await;
+        let rejected = server
+            .mock("POST", "/")
+            .match_header("Authorization", "Bearer expired")
+            .with_status(401)
+            .create_async()
+            .await;
+        let token = server
+            .mock("POST", "/token")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token": "fresh", "refresh_token": "refresh-2"}"#)
+            .create_async()
+            .await;
+        let accepted = server
+            .mock("POST", "/")
+            .match_header("Authorization", "Bearer fresh")
+            .with_status(202)
+            .create_async()
+            .await;
+
+        let store = Arc::new(MemoryCredentialStore::default());
+        store.save(&crate::oauth::OAuthCredentials {
+            access_token: "expired".to_string(),
+            refresh_token: Some("refresh-1".to_string()),
+            token_endpoint: format!("{}/token", server.url()),
+            client_id: "client".to_string(),
+            resource: server.url(),
+        });
+
+        let (_tx, rx) = mpsc::channel(32);
+        let (otx, _orx) = mpsc::channel(32);
+        let mut actor = StreamableHttpActor::new(
+            rx,
+            otx,
+            server.url(),
+            Arc::new(RwLock::new(None)),
+            HashMap::new(),
+            HashMap::from([("Authorization".to_string(), "Bearer expired".to_string())]),
+        )
+        .with_credential_store(Some(store.clone() as Arc<dyn CredentialStore>));
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        actor
+            .handle_outgoing_message(notification.to_string())
+            .await
+            .unwrap();
+
+        rejected.assert_async().await;
+        token.assert_async().await;
+        accepted.assert_async().await;
+        let saved = store.load().unwrap();
+        assert_eq!(saved.access_token, "fresh");
+        assert_eq!(saved.refresh_token.as_deref(), Some("refresh-2"));
+    }
+
+    /// Minimal streamable HTTP server used to exercise the handshake, session expiry and
+    /// stream resumption end to end
+    #[derive(Default)]
+    struct HandshakeServer {
+        sessions_started: usize,
+        session_id: Option<String>,
+        expire_session: bool,
+        drop_stream: bool,
+        pending_response: Option<serde_json::Value>,
+    }
+
+    type SharedServer = Arc<Mutex<HandshakeServer>>;
+
+    async fn handle_post(
+        axum::extract::State(server): axum::extract::State<SharedServer>,
+        headers: axum::http::HeaderMap,
+        body: String,
+    ) -> axum::response::Response {
+        use axum::http::StatusCode;
+        use axum::response::IntoResponse;
+
+        let message: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let mut server = server.lock().await;
+
+        if message["method"] == "initialize" {
+            server.sessions_started += 1;
+            let session_id = format!("session-{}", server.sessions_started);
+            server.session_id = Some(session_id.clone());
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": message["id"],
+                "result": {
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "handshake", "version": "1.0.0" }
+                }
+            });
+            return (
+                [
+                    ("Mcp-Session-Id", session_id),
+                    ("content-type", "application/json".to_string()),
+                ],
+                response.to_string(),
+            )
+                .into_response();
+        }
+
+        let session_id = headers.get("Mcp-Session-Id").and_then(|h| h.to_str().ok());
+        if server.expire_session {
+            server.expire_session = false;
+            server.session_id = None;
+        }
+        if session_id.is_none() || session_id != server.session_id.as_deref() {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        if message.get("id").is_none() {
+            return StatusCode::ACCEPTED.into_response();
+        }
+
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": message["id"],
+            "result": { "tools": [] }
+        });
+        if server.drop_stream {
+            // Only the priming event makes it out before the stream closes
+            server.drop_stream = false;
+            server.pending_response = Some(response);
+            return (
+                [("content-type", "text/event-stream")],
+                "id: evt-1\n\n".to_string(),
+            )
+                .into_response();
+        }
+        (
+            [("content-type", "text/event-stream")],
+            format!("id: evt-1\ndata: {}\n\n", response),
+        )
+            .into_response()
+    }
+
+    async fn handle_get(
+        axum::extract::State(server): axum::extract::State<SharedServer>,
+        headers: axum::http::HeaderMap,
+    ) -> axum::response::Response {
+        use axum::http::StatusCode;
+        use axum::response::IntoResponse;
+
+        let last_event_id = headers.get("Last-Event-ID").and_then(|h| h.to_str().ok());
+        let mut server = server.lock().await;
+        match (last_event_id, server.pending_response.take()) {
+            (Some("evt-1"), Some(response)) => (
+                [("content-type", "text/event-stream")],
+                format!("id: evt-2\ndata: {}\n\n", response),
+            )
+                .into_response(),
+            _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+        }
+    }
+
+    async fn start_handshake_server(server: SharedServer) -> String {
+        let app = axum::Router::new()
+            .route("/mcp", axum::routing::post(handle_post).get(handle_get))
+            .with_state(server);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/mcp", addr)
+    }
+
+    async fn connect_client(
+        endpoint: &str,
+    ) -> crate::client::McpClient<StreamableHttpTransportHandle> {
+        use crate::client::{ClientCapabilities, ClientInfo, McpClient, McpClientTrait};
+
+        let handle = StreamableHttpTransport::new(endpoint, HashMap::new())
+            .start()
+            .await
+            .unwrap();
+        let mut client = McpClient::connect(handle, Duration::from_secs(10))
+            .await
+            .unwrap();
+        client
+            .initialize(
+                ClientInfo {
+                    name: "test".to_string(),
+                    version: "1.0.0".to_string(),
+                },
+                ClientCapabilities::default(),
+            )
+            .await
+            .unwrap();
+        client
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_handshake() {
+        use crate::client::McpClientTrait;
+
+        let server = SharedServer::default();
+        let endpoint = start_handshake_server(Arc::clone(&server)).await;
+        let client = connect_client(&endpoint).await;
+
+        let tools = client.list_tools(None).await.unwrap();
+        assert!(tools.tools.is_empty());
+        assert_eq!(server.lock().await.sessions_started, 1);
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_reinitializes_expired_session() {
+        use crate::client::McpClientTrait;
+
+        let server = SharedServer::default();
+        let endpoint = start_handshake_server(Arc::clone(&server)).await;
+        let client = connect_client(&endpoint).await;
+
+        server.lock().await.expire_session = true;
+        client.list_tools(None).await.unwrap();
+
+        let server = server.lock().await;
+        assert_eq!(server.sessions_started, 2);
+        assert_eq!(server.session_id.as_deref(), Some("session-2"));
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_resumes_dropped_stream() {
+        use crate::client::McpClientTrait;
+
+        let server = SharedServer::default();
+        let endpoint = start_handshake_server(Arc::clone(&server)).await;
+        let client = connect_client(&endpoint).await;
+
+        server.lock().await.drop_stream = true;
+        let tools = client.list_tools(None).await.unwrap();
+
+        assert!(tools.tools.is_empty());
+        assert!(server.lock().await.pending_response.is_none());
+    }
 }
@@ -77,6 +77,7 @@ impl CapabilitiesBuilder {
             tools: self.tools,
             prompts: self.prompts,
             resources: self.resources,
+            logging: None,
         }
     }
 }
@@ -185,10 +186,12 @@ pub trait Router: Send + Sync + 'static {
                 Ok(result) => CallToolResult {
                     content: result,
                     is_error: None,
+                    structured_content: None,
                 },
                 Err(err) => CallToolResult {
                     content: vec![Content::text(err.to_string())],
                     is_error: Some(true),
+                    structured_content: None,
                 },
             };
 
@@ -250,7 +253,10 @@ pub trait Router: Send + Sync + 'static {
         async move {
             let prompts = self.list_prompts();
 
-            let result = ListPromptsResult { prompts };
+            let result = ListPromptsResult {
+                prompts,
+                next_cursor: None,
+            };
 
             let mut response = self.create_response(req.id);
             self.set_result(&mut response, result)?;